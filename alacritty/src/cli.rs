@@ -66,6 +66,11 @@ pub struct Options {
     #[clap(long)]
     pub daemon: bool,
 
+    /// Print the charts of a YAML config in the current charts config format and exit.
+    #[cfg(feature = "charts")]
+    #[clap(long, value_hint = ValueHint::FilePath, value_name = "PATH")]
+    pub print_migrated_charts_config: Option<PathBuf>,
+
     /// CLI options for config overrides.
     #[clap(skip)]
     pub config_options: ParsedOptions,
@@ -14,6 +14,8 @@ use winit::keyboard::{
 use winit::platform::scancode::PhysicalKeyExtScancode;
 
 use alacritty_config_derive::{ConfigDeserialize, SerdeReplace};
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::ChartRef;
 
 use alacritty_terminal::term::TermMode;
 use alacritty_terminal::vi_mode::ViMotion;
@@ -115,6 +117,11 @@ pub enum Action {
     #[config(skip)]
     Mouse(MouseAction),
 
+    /// Cycles the time window of the chart referenced by the `chart` of the binding.
+    #[cfg(feature = "charts")]
+    #[config(skip)]
+    CycleChartWindowOf(ChartRef),
+
     /// Paste contents of system clipboard.
     Paste,
 
@@ -252,6 +259,15 @@ pub enum Action {
 
     /// Toggles viewing or not Alacritty Charts
     ToggleChartShow,
+
+    /// Toggles the debug overlay describing the state of the charts
+    ToggleChartDebugOverlay,
+
+    /// Toggles the decorations of the WithDecorToggle visibility group
+    ToggleDecorations,
+
+    /// Cycles the time window of the charts through the window presets
+    CycleChartWindow,
 }
 
 impl From<&'static str> for Action {
@@ -284,6 +300,17 @@ impl From<MouseAction> for Action {
     }
 }
 
+#[cfg(feature = "charts")]
+impl Action {
+    /// The chart referenced by the action, it may not exist in the charts config.
+    pub fn chart_ref(&self) -> Option<&ChartRef> {
+        match self {
+            Action::CycleChartWindowOf(chart) => Some(chart),
+            _ => None,
+        }
+    }
+}
+
 /// Display trait used for error logging.
 impl Display for Action {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
@@ -941,7 +968,8 @@ impl<'a> Deserialize<'a> for RawBinding {
     where
         D: Deserializer<'a>,
     {
-        const FIELDS: &[&str] = &["key", "mods", "mode", "action", "chars", "mouse", "command"];
+        const FIELDS: &[&str] =
+            &["key", "mods", "mode", "action", "chars", "mouse", "command", "chart"];
 
         enum Field {
             Key,
@@ -951,6 +979,7 @@ impl<'a> Deserialize<'a> for RawBinding {
             Chars,
             Mouse,
             Command,
+            Chart,
         }
 
         impl<'a> Deserialize<'a> for Field {
@@ -979,6 +1008,7 @@ impl<'a> Deserialize<'a> for RawBinding {
                             "chars" => Ok(Field::Chars),
                             "mouse" => Ok(Field::Mouse),
                             "command" => Ok(Field::Command),
+                            "chart" => Ok(Field::Chart),
                             _ => Err(E::unknown_field(value, FIELDS)),
                         }
                     }
@@ -1008,6 +1038,11 @@ impl<'a> Deserialize<'a> for RawBinding {
                 let mut not_mode: Option<BindingMode> = None;
                 let mut mouse: Option<MouseButton> = None;
                 let mut command: Option<Program> = None;
+                #[cfg(feature = "charts")]
+                let mut chart: Option<ChartRef> = None;
+                // Without the charts the binding cycles all of them, which does nothing.
+                #[cfg(not(feature = "charts"))]
+                let mut chart: Option<de::IgnoredAny> = None;
 
                 use de::Error;
 
@@ -1110,6 +1145,13 @@ impl<'a> Deserialize<'a> for RawBinding {
 
                             command = Some(map.next_value::<Program>()?);
                         },
+                        Field::Chart => {
+                            if chart.is_some() {
+                                return Err(<V::Error as Error>::duplicate_field("chart"));
+                            }
+
+                            chart = Some(map.next_value()?);
+                        },
                     }
                 }
 
@@ -1139,6 +1181,19 @@ impl<'a> Deserialize<'a> for RawBinding {
                     },
                 };
 
+                let action = match (action, chart) {
+                    #[cfg(feature = "charts")]
+                    (Action::CycleChartWindow, Some(chart)) => Action::CycleChartWindowOf(chart),
+                    #[cfg(not(feature = "charts"))]
+                    (action @ Action::CycleChartWindow, Some(_)) => action,
+                    (action, Some(_)) => {
+                        return Err(V::Error::custom(format!(
+                            "action `{action}` does not take a chart",
+                        )));
+                    },
+                    (action, None) => action,
+                };
+
                 if mouse.is_none() && key.is_none() {
                     return Err(V::Error::custom("bindings require mouse button or key"));
                 }
@@ -1444,4 +1499,20 @@ mod tests {
         assert!(!binding.is_triggered_by(BindingMode::ALT_SCREEN, mods, &t));
         assert!(!binding.is_triggered_by(BindingMode::ALT_SCREEN | BindingMode::VI, mods, &t));
     }
+
+    #[cfg(feature = "charts")]
+    #[test]
+    fn binding_chart_ref() {
+        let binding: KeyBinding =
+            toml::from_str("key = \"W\"\naction = \"CycleChartWindow\"\nchart = \"loadavg\"")
+                .unwrap();
+        assert_eq!(binding.action, Action::CycleChartWindowOf(ChartRef::Name("loadavg".into())));
+
+        let binding: KeyBinding =
+            toml::from_str("key = \"W\"\naction = \"CycleChartWindow\"\nchart = 1").unwrap();
+        assert_eq!(binding.action.chart_ref(), Some(&ChartRef::Index(1)));
+
+        let binding = toml::from_str::<KeyBinding>("key = \"W\"\naction = \"Copy\"\nchart = 1");
+        assert!(binding.is_err());
+    }
 }
@@ -1,14 +1,53 @@
-use serde::{self, Deserialize, Serialize};
+#[cfg(not(feature = "charts"))]
+use log::warn;
+use serde::{self, Deserialize, Deserializer, Serialize};
 
-use alacritty_terminal::charts::ChartsConfig;
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::{deserialize_migrated, ChartsConfig};
 
-#[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
+#[cfg(not(feature = "charts"))]
+use crate::logging::LOG_TARGET_CONFIG;
+
+#[cfg(feature = "charts")]
+#[derive(Serialize, Default, Clone, Debug, PartialEq)]
 pub struct Charts {
     /// Chart configuration
     #[serde(flatten)]
     pub config: ChartsConfig,
 }
 
+#[cfg(feature = "charts")]
+impl<'de> Deserialize<'de> for Charts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // The charts of older config versions are migrated to the current format.
+        let config = deserialize_migrated(deserializer)?;
+        Ok(Self { config })
+    }
+}
+
+/// Without the charts feature the `charts` section is accepted but ignored.
+#[cfg(not(feature = "charts"))]
+#[derive(Serialize, Default, Clone, Debug, PartialEq)]
+pub struct Charts;
+
+#[cfg(not(feature = "charts"))]
+impl<'de> Deserialize<'de> for Charts {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        warn!(
+            target: LOG_TARGET_CONFIG,
+            "Config `charts` ignored: built without the charts feature"
+        );
+        Ok(Self)
+    }
+}
+
 impl alacritty_config::SerdeReplace for Charts {
     fn replace(&mut self, value: toml::Value) -> Result<(), Box<dyn std::error::Error>> {
         *self = serde::Deserialize::deserialize(value)?;
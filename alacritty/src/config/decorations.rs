@@ -1,7 +1,14 @@
+#[cfg(not(feature = "charts"))]
+use log::warn;
 use serde::{self, Deserialize, Serialize};
 
-use alacritty_terminal::decorations::DecorationsConfig;
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::DecorationsConfig;
 
+#[cfg(not(feature = "charts"))]
+use crate::logging::LOG_TARGET_CONFIG;
+
+#[cfg(feature = "charts")]
 #[derive(Serialize, Deserialize, Default, Clone, Debug, PartialEq)]
 pub struct Decorations {
     /// Decorations configuration
@@ -9,6 +16,26 @@ pub struct Decorations {
     pub config: DecorationsConfig,
 }
 
+/// Without the charts feature the `decorations` section is accepted but ignored.
+#[cfg(not(feature = "charts"))]
+#[derive(Serialize, Default, Clone, Debug, PartialEq)]
+pub struct Decorations;
+
+#[cfg(not(feature = "charts"))]
+impl<'de> Deserialize<'de> for Decorations {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: serde::Deserializer<'de>,
+    {
+        serde::de::IgnoredAny::deserialize(deserializer)?;
+        warn!(
+            target: LOG_TARGET_CONFIG,
+            "Config `decorations` ignored: built without the charts feature"
+        );
+        Ok(Self)
+    }
+}
+
 impl alacritty_config::SerdeReplace for Decorations {
     fn replace(&mut self, value: toml::Value) -> Result<(), Box<dyn std::error::Error>> {
         *self = serde::Deserialize::deserialize(value)?;
@@ -168,6 +168,19 @@ fn after_loading(config: &mut UiConfig, options: &mut Options) {
 
     // Create key bindings for regex hints.
     config.generate_hint_bindings();
+
+    // The bindings may reference charts that were renamed or removed from the config.
+    #[cfg(feature = "charts")]
+    {
+        let unknown_charts = config.unknown_chart_refs();
+        if !unknown_charts.is_empty() {
+            warn!(
+                target: LOG_TARGET_CONFIG,
+                "Key bindings reference unknown charts: {}",
+                unknown_charts.join(", ")
+            );
+        }
+    }
 }
 
 /// Load configuration file and log errors.
@@ -155,6 +155,24 @@ impl UiConfig {
         }
     }
 
+    /// The charts referenced by the key and mouse bindings that do not exist in the charts config.
+    #[cfg(feature = "charts")]
+    pub fn unknown_chart_refs(&self) -> Vec<String> {
+        let key_actions = self.key_bindings().iter().map(|binding| &binding.action);
+        let mouse_actions = self.mouse_bindings().iter().map(|binding| &binding.action);
+        let mut unknown: Vec<String> = key_actions
+            .chain(mouse_actions)
+            .filter_map(Action::chart_ref)
+            .filter(|chart| {
+                self.charts.as_ref().and_then(|charts| charts.config.resolve_chart(chart)).is_none()
+            })
+            .map(ToString::to_string)
+            .collect();
+        unknown.sort();
+        unknown.dedup();
+        unknown
+    }
+
     #[inline]
     pub fn window_opacity(&self) -> f32 {
         self.window.opacity.as_f32()
@@ -640,6 +658,8 @@ impl serde::de::Visitor<'_> for StringVisitor {
 mod tests {
     use super::*;
 
+    #[cfg(feature = "charts")]
+    use alacritty_terminal::charts::prelude::{ChartsConfig, TimeSeriesChart};
     use alacritty_terminal::term::test::mock_term;
 
     use crate::display::hint::visible_regex_match_iter;
@@ -692,4 +712,48 @@ mod tests {
             )
         }
     }
+
+    #[cfg(feature = "charts")]
+    #[test]
+    fn unknown_chart_bindings() {
+        let mut config: UiConfig = toml::from_str(
+            r#"
+            [keyboard]
+            bindings = [
+                { key = "W", action = "CycleChartWindow", chart = "loadavg" },
+                { key = "E", action = "CycleChartWindow", chart = "input" },
+                { key = "R", action = "CycleChartWindow", chart = 1 },
+                { key = "T", action = "CycleChartWindow" },
+            ]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.unknown_chart_refs(), vec!["#1", "'input'", "'loadavg'"]);
+
+        let chart = TimeSeriesChart { name: String::from("input"), ..Default::default() };
+        let charts = ChartsConfig { charts: vec![chart], ..Default::default() };
+        config.charts = Some(Charts { config: charts });
+        assert_eq!(config.unknown_chart_refs(), vec!["#1", "'loadavg'"]);
+    }
+
+    #[cfg(not(feature = "charts"))]
+    #[test]
+    fn charts_ignored_without_charts_feature() {
+        let config: UiConfig = toml::from_str(
+            r#"
+            [keyboard]
+            bindings = [{ key = "W", action = "CycleChartWindow", chart = "loadavg" }]
+
+            [charts]
+            window_presets = [60, 300]
+
+            [decorations]
+            "#,
+        )
+        .unwrap();
+        assert_eq!(config.charts, Some(Charts));
+        assert_eq!(config.decorations, Some(Decorations));
+        let action = &config.key_bindings()[0].action;
+        assert_eq!(action, &Action::CycleChartWindow);
+    }
 }
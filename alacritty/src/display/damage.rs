@@ -90,7 +90,7 @@ impl DamageTracker {
     }
 
     /// Get shaped frame damage for the active frame.
-    pub fn shape_frame_damage(&self, size_info: SizeInfo<u32>) -> Vec<Rect> {
+    pub fn shape_frame_damage(&self, size_info: SizeInfo) -> Vec<Rect> {
         if self.frames[0].full {
             vec![Rect::new(0, 0, size_info.width() as i32, size_info.height() as i32)]
         } else {
@@ -215,27 +215,29 @@ pub fn damage_y_to_viewport_y(size_info: &SizeInfo, rect: &Rect) -> i32 {
 /// Iterator which converts `alacritty_terminal` damage information into renderer damaged rects.
 struct RenderDamageIterator<'a> {
     damaged_lines: Peekable<TermDamageIterator<'a>>,
-    size_info: &'a SizeInfo<u32>,
+    size_info: &'a SizeInfo,
 }
 
 impl<'a> RenderDamageIterator<'a> {
-    pub fn new(damaged_lines: TermDamageIterator<'a>, size_info: &'a SizeInfo<u32>) -> Self {
+    pub fn new(damaged_lines: TermDamageIterator<'a>, size_info: &'a SizeInfo) -> Self {
         Self { damaged_lines: damaged_lines.peekable(), size_info }
     }
 
     #[inline]
     fn rect_for_line(&self, line_damage: LineDamageBounds) -> Rect {
         let size_info = &self.size_info;
-        let y_top = size_info.height() - size_info.padding_y();
-        let x = size_info.padding_x() + line_damage.left as u32 * size_info.cell_width();
-        let y = y_top - (line_damage.line + 1) as u32 * size_info.cell_height();
-        let width = (line_damage.right - line_damage.left + 1) as u32 * size_info.cell_width();
-        Rect::new(x as i32, y as i32, width as i32, size_info.cell_height() as i32)
+        let cell_width = size_info.cell_width() as u32;
+        let cell_height = size_info.cell_height() as u32;
+        let y_top = size_info.height() as u32 - size_info.padding_y() as u32;
+        let x = size_info.padding_x() as u32 + line_damage.left as u32 * cell_width;
+        let y = y_top - (line_damage.line + 1) as u32 * cell_height;
+        let width = (line_damage.right - line_damage.left + 1) as u32 * cell_width;
+        Rect::new(x as i32, y as i32, width as i32, cell_height as i32)
     }
 
     // Make sure to damage near cells to include wide chars.
     #[inline]
-    fn overdamage(size_info: &SizeInfo<u32>, mut rect: Rect) -> Rect {
+    fn overdamage(size_info: &SizeInfo, mut rect: Rect) -> Rect {
         rect.x = (rect.x - size_info.cell_width() as i32).max(0);
         rect.width = cmp::min(
             size_info.width() as i32 - rect.x,
@@ -308,7 +310,7 @@ mod tests {
         let cell_size = 4;
         let bound = 100;
 
-        let size_info: SizeInfo<u32> = SizeInfo::new(
+        let size_info = SizeInfo::new(
             bound as f32,
             bound as f32,
             cell_size as f32,
@@ -316,8 +318,7 @@ mod tests {
             2.,
             2.,
             true,
-        )
-        .into();
+        );
 
         // Test min clamping.
         let rect = Rect::new(0, 0, rect_side, rect_side);
@@ -1,5 +1,6 @@
 //! The display subsystem including window management, font rasterization, and
 //! GPU drawing.
+#[cfg(feature = "charts")]
 use tokio::sync::{mpsc as futures_mpsc, oneshot};
 
 use std::cmp;
@@ -13,9 +14,10 @@ use glutin::context::{NotCurrentContext, PossiblyCurrentContext};
 use glutin::prelude::*;
 use glutin::surface::{Surface, SwapInterval, WindowSurface};
 
-use log::{debug, error, info};
+#[cfg(feature = "charts")]
+use log::error;
+use log::{debug, info};
 use parking_lot::MutexGuard;
-use serde::{Deserialize, Serialize};
 use winit::dpi::PhysicalSize;
 use winit::keyboard::ModifiersState;
 use winit::raw_window_handle::RawWindowHandle;
@@ -24,7 +26,7 @@ use winit::window::CursorIcon;
 use crossfont::{Rasterize, Rasterizer, Size as FontSize};
 use unicode_width::UnicodeWidthChar;
 
-use alacritty_terminal::event::{EventListener, OnResize, WindowSize};
+use alacritty_terminal::event::{EventListener, OnResize};
 use alacritty_terminal::grid::Dimensions as TermDimensions;
 use alacritty_terminal::index::{Column, Direction, Line, Point};
 use alacritty_terminal::selection::Selection;
@@ -55,10 +57,13 @@ use crate::scheduler::{Scheduler, TimerId, Topic};
 use crate::string::{ShortenDirection, StrShortener};
 
 // Chartacritty:
-use alacritty_terminal::decorations::{
-    DecorationLines, DecorationPoints, DecorationTriangles, DecorationTypes, DecorationsConfig,
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::{
+    block_on_reply, get_debug_overlay_data, push_chart_draw_items, set_charts_palette, AsyncTask,
+    ChartDrawBatch, ChartIndex, ChartsConfig, ChartsVertexCache, DecorationLines, DecorationPoints,
+    DecorationTriangles, DecorationTypes, DecorationsConfig, NumericReadout, TermChartsHandle,
+    TimelineReadout, WindowId as ChartsWindowId, TERMINAL_COLOR_NAMES,
 };
-use alacritty_terminal::term::TermChartsHandle;
 
 pub mod color;
 pub mod content;
@@ -70,6 +75,9 @@ mod bell;
 mod damage;
 mod meter;
 
+/// Terminal size info, shared with the charts and decorations of the terminal crate.
+pub use alacritty_terminal::term::SizeInfo;
+
 /// Label for the forward terminal search bar.
 const FORWARD_SEARCH_LABEL: &str = "Search: ";
 
@@ -143,181 +151,6 @@ impl From<glutin::error::Error> for Error {
     }
 }
 
-/// Terminal size info.
-#[derive(Serialize, Deserialize, Debug, Copy, Clone, PartialEq, Eq)]
-pub struct SizeInfo<T = f32> {
-    /// Terminal window width.
-    width: T,
-
-    /// Terminal window height.
-    height: T,
-
-    /// Width of individual cell.
-    cell_width: T,
-
-    /// Height of individual cell.
-    cell_height: T,
-
-    /// Horizontal window padding.
-    padding_x: T,
-
-    /// Vertical window padding.
-    padding_y: T,
-
-    /// Number of lines in the viewport.
-    screen_lines: usize,
-
-    /// Number of columns in the viewport.
-    columns: usize,
-}
-
-impl From<SizeInfo<f32>> for SizeInfo<u32> {
-    fn from(size_info: SizeInfo<f32>) -> Self {
-        Self {
-            width: size_info.width as u32,
-            height: size_info.height as u32,
-            cell_width: size_info.cell_width as u32,
-            cell_height: size_info.cell_height as u32,
-            padding_x: size_info.padding_x as u32,
-            padding_y: size_info.padding_y as u32,
-            screen_lines: size_info.screen_lines,
-            columns: size_info.screen_lines,
-        }
-    }
-}
-
-impl From<SizeInfo<f32>> for WindowSize {
-    fn from(size_info: SizeInfo<f32>) -> Self {
-        Self {
-            num_cols: size_info.columns() as u16,
-            num_lines: size_info.screen_lines() as u16,
-            cell_width: size_info.cell_width() as u16,
-            cell_height: size_info.cell_height() as u16,
-        }
-    }
-}
-
-impl<T: Clone + Copy> SizeInfo<T> {
-    #[inline]
-    pub fn width(&self) -> T {
-        self.width
-    }
-
-    #[inline]
-    pub fn height(&self) -> T {
-        self.height
-    }
-
-    #[inline]
-    pub fn cell_width(&self) -> T {
-        self.cell_width
-    }
-
-    #[inline]
-    pub fn cell_height(&self) -> T {
-        self.cell_height
-    }
-
-    #[inline]
-    pub fn padding_x(&self) -> T {
-        self.padding_x
-    }
-
-    #[inline]
-    pub fn padding_y(&self) -> T {
-        self.padding_y
-    }
-}
-
-impl SizeInfo<f32> {
-    #[allow(clippy::too_many_arguments)]
-    pub fn new(
-        width: f32,
-        height: f32,
-        cell_width: f32,
-        cell_height: f32,
-        mut padding_x: f32,
-        mut padding_y: f32,
-        dynamic_padding: bool,
-    ) -> SizeInfo {
-        if dynamic_padding {
-            padding_x = Self::dynamic_padding(padding_x.floor(), width, cell_width);
-            padding_y = Self::dynamic_padding(padding_y.floor(), height, cell_height);
-        }
-
-        let lines = (height - 2. * padding_y) / cell_height;
-        let screen_lines = cmp::max(lines as usize, MIN_SCREEN_LINES);
-
-        let columns = (width - 2. * padding_x) / cell_width;
-        let columns = cmp::max(columns as usize, MIN_COLUMNS);
-
-        SizeInfo {
-            width,
-            height,
-            cell_width,
-            cell_height,
-            padding_x: padding_x.floor(),
-            padding_y: padding_y.floor(),
-            screen_lines,
-            columns,
-        }
-    }
-
-    #[inline]
-    pub fn reserve_lines(&mut self, count: usize) {
-        self.screen_lines = cmp::max(self.screen_lines.saturating_sub(count), MIN_SCREEN_LINES);
-    }
-
-    /// Check if coordinates are inside the terminal grid.
-    ///
-    /// The padding, message bar or search are not counted as part of the grid.
-    #[inline]
-    pub fn contains_point(&self, x: usize, y: usize) -> bool {
-        x <= (self.padding_x + self.columns as f32 * self.cell_width) as usize
-            && x > self.padding_x as usize
-            && y <= (self.padding_y + self.screen_lines as f32 * self.cell_height) as usize
-            && y > self.padding_y as usize
-    }
-
-    /// Calculate padding to spread it evenly around the terminal content.
-    #[inline]
-    fn dynamic_padding(padding: f32, dimension: f32, cell_dimension: f32) -> f32 {
-        padding + ((dimension - 2. * padding) % cell_dimension) / 2.
-    }
-}
-
-impl TermDimensions for SizeInfo {
-    #[inline]
-    fn columns(&self) -> usize {
-        self.columns
-    }
-
-    #[inline]
-    fn screen_lines(&self) -> usize {
-        self.screen_lines
-    }
-
-    #[inline]
-    fn total_lines(&self) -> usize {
-        self.screen_lines()
-    }
-}
-
-impl From<SizeInfo<f32>> for alacritty_terminal::term::SizeInfo {
-    fn from(src: SizeInfo<f32>) -> Self {
-        Self {
-            width: src.width,
-            height: src.height,
-            cell_width: src.cell_width,
-            cell_height: src.cell_height,
-            padding_x: src.padding_x,
-            padding_y: src.padding_y,
-            screen_lines: src.screen_lines,
-            columns: src.columns,
-        }
-    }
-}
-
 #[derive(Default, Clone, Debug, PartialEq, Eq)]
 pub struct DisplayUpdate {
     pub dirty: bool,
@@ -414,8 +247,26 @@ pub struct Display {
 
     glyph_cache: GlyphCache,
     meter: Meter,
+    #[cfg(feature = "charts")]
     decorations: DecorationsConfig,
+    #[cfg(feature = "charts")]
     tokio_setup: Option<TermChartsHandle>,
+
+    /// The chart vertices last received from the charts coordinator.
+    #[cfg(feature = "charts")]
+    charts_cache: ChartsVertexCache,
+
+    /// The vertices of all the charts, rebuilt when any of them changes.
+    #[cfg(feature = "charts")]
+    chart_batch: ChartDrawBatch,
+
+    /// Mouse position in window pixels while the charts are enabled.
+    #[cfg(feature = "charts")]
+    chart_mouse: Option<(f32, f32)>,
+
+    /// State of the timeline band under the mouse.
+    #[cfg(feature = "charts")]
+    timeline_readout: Option<TimelineReadout>,
 }
 
 impl Display {
@@ -528,11 +379,19 @@ impl Display {
         let mut damage_tracker = DamageTracker::new(size_info.screen_lines(), size_info.columns());
         damage_tracker.debug = config.debug.highlight_damage;
 
-        let decorations_config =
-            config.decorations.as_ref().map(|decorations| decorations.config.clone());
-        let mut decorations =
-            DecorationsConfig::optional_decor_to_sized(decorations_config, size_info.into());
-        decorations.init_timers();
+        #[cfg(feature = "charts")]
+        let decorations = {
+            let decorations_config =
+                config.decorations.as_ref().map(|decorations| decorations.config.clone());
+            let mut decorations =
+                DecorationsConfig::optional_decor_to_sized(decorations_config, size_info);
+            if let Some(charts) = &config.charts {
+                decorations.set_timezone(charts.config.timezone);
+            }
+            decorations.init_timers();
+            decorations
+        };
+
         // Disable vsync.
         if let Err(err) = surface.set_swap_interval(&context, SwapInterval::DontWait) {
             info!("Failed to disable vsync: {}", err);
@@ -562,13 +421,85 @@ impl Display {
             cursor_hidden: Default::default(),
             meter: Default::default(),
             ime: Default::default(),
+            #[cfg(feature = "charts")]
             decorations,
+            #[cfg(feature = "charts")]
             tokio_setup: None,
+            #[cfg(feature = "charts")]
+            charts_cache: Default::default(),
+            #[cfg(feature = "charts")]
+            chart_batch: Default::default(),
+            #[cfg(feature = "charts")]
+            chart_mouse: Default::default(),
+            #[cfg(feature = "charts")]
+            timeline_readout: Default::default(),
         })
     }
 
+    #[cfg(feature = "charts")]
     pub fn set_tokio_setup(&mut self, tokio_setup: TermChartsHandle) {
         self.tokio_setup = Some(tokio_setup);
+        self.update_charts_palette();
+    }
+
+    /// Send the terminal colors to the charts, their named colors are resolved against them.
+    #[cfg(feature = "charts")]
+    pub fn update_charts_palette(&self) {
+        let tokio_setup = match self.tokio_setup {
+            Some(ref tokio_setup) => tokio_setup.clone(),
+            None => return,
+        };
+        let colors = TERMINAL_COLOR_NAMES
+            .iter()
+            .map(|&(name, color)| (name.to_string(), self.colors[color].0))
+            .collect();
+        let unknown = set_charts_palette(tokio_setup.charts_tx, colors, tokio_setup.tokio_handle);
+        debug!("Sent the palette to the charts, unknown color names: {:?}", unknown);
+    }
+
+    /// Whether the renderer is able to draw the charts.
+    #[cfg(feature = "charts")]
+    pub fn charts_available(&self) -> bool {
+        self.renderer.charts_available()
+    }
+
+    /// The error that disabled the chart renderers, if any.
+    #[cfg(feature = "charts")]
+    pub fn charts_error(&self) -> Option<&renderer::Error> {
+        self.renderer.charts_error()
+    }
+
+    /// Terminal size info shared with the charts, including the window scale factor.
+    #[cfg(feature = "charts")]
+    pub fn charts_size_info(&self, size_info: SizeInfo) -> SizeInfo {
+        SizeInfo { dpr: self.window.scale_factor, ..size_info }
+    }
+
+    /// Notify the charts about a new window scale factor, the charts are laid out again for it.
+    #[cfg(feature = "charts")]
+    pub fn update_charts_scale_factor(&self, scale_factor: f64) {
+        let tokio_setup = match self.tokio_setup {
+            Some(ref tokio_setup) => tokio_setup.clone(),
+            None => return,
+        };
+        let (scale_factor_tx, scale_factor_rx) = oneshot::channel();
+        let charts_tx = tokio_setup.charts_tx.clone();
+        let tokio_handle = tokio_setup.tokio_handle;
+        tokio_handle.spawn(async move {
+            let send_scale_factor = charts_tx.send(AsyncTask::ChangeScaleFactor(
+                ChartsWindowId::default(),
+                scale_factor,
+                scale_factor_tx,
+            ));
+            match send_scale_factor.await {
+                Err(e) => error!("Sending ChangeScaleFactor Task: err={:?}", e),
+                Ok(_) => debug!("Sent ChangeScaleFactor Task scale_factor: {}", scale_factor),
+            }
+        });
+        match block_on_reply(&tokio_handle, scale_factor_rx) {
+            Ok(_) => debug!("Got response from ChangeScaleFactor Task."),
+            Err(err) => error!("Error response from ChangeScaleFactor Task: {:?}", err),
+        }
     }
 
     #[inline]
@@ -601,7 +532,7 @@ impl Display {
                 if matches!(self.raw_window_handle, RawWindowHandle::Wayland(_))
                     && !self.damage_tracker.debug =>
             {
-                let damage = self.damage_tracker.shape_frame_damage(self.size_info.into());
+                let damage = self.damage_tracker.shape_frame_damage(self.size_info);
                 surface.swap_buffers_with_damage(context, &damage)
             },
             (surface, context) => surface.swap_buffers(context),
@@ -695,6 +626,17 @@ impl Display {
         let charts_lines = usize::from(terminal.charts_enabled());
         new_size.reserve_lines(message_bar_lines + search_lines + charts_lines);
 
+        // Keep track of the lines drawn under the charts.
+        #[cfg(feature = "charts")]
+        let charts_covered_lines = match &config.charts {
+            Some(charts) if terminal.charts_enabled() => {
+                charts.config.covered_rows(&self.charts_size_info(new_size))
+            },
+            _ => Vec::new(),
+        };
+        #[cfg(feature = "charts")]
+        terminal.set_charts_covered_lines(charts_covered_lines);
+
         // Update resize increments.
         if config.window.resize_increments {
             self.window.set_resize_increments(PhysicalSize::new(cell_width, cell_height));
@@ -741,43 +683,34 @@ impl Display {
         if renderer_update.resize {
             let width = NonZeroU32::new(self.size_info.width() as u32).unwrap();
             let height = NonZeroU32::new(self.size_info.height() as u32).unwrap();
-            let chart_width = self.size_info.width();
-            let chart_height = self.size_info.height();
-            let (chart_resize_tx, chart_resize_rx) = oneshot::channel();
-            let (padding_x, padding_y) = (self.size_info.padding_x(), self.size_info.padding_y());
+            #[cfg(feature = "charts")]
             if let Some(ref tokio_setup) = self.tokio_setup {
-                let tokio_setup = tokio_setup.clone();
-                let charts_tx = tokio_setup.charts_tx.clone();
-                let tokio_handle = tokio_setup.tokio_handle;
-                tokio_handle.spawn(async move {
-                    let send_display_size = charts_tx.send(
-                        alacritty_terminal::async_utils::AsyncTask::ChangeDisplaySize(
-                            chart_width,
-                            chart_height,
-                            padding_x,
-                            padding_y,
-                            chart_resize_tx,
-                        ),
-                    );
-                    match send_display_size.await {
-                        Err(e) => error!("Sending ChangeDisplaySize Task: err={:?}", e),
-                        Ok(_) => debug!(
-                            "Sent ChangeDisplaySize Task height: {}, width: {}, padding_y: {}, \
+                let chart_width = self.size_info.width();
+                let chart_height = self.size_info.height();
+                let padding_x = self.size_info.padding_x();
+                let padding_y = self.size_info.padding_y();
+                // The charts coordinator debounces the sizes sent during interactive resizes,
+                // until then the charts are not drawn so that they are not stretched.
+                let display_size = AsyncTask::UpdateDisplaySize(
+                    ChartsWindowId::default(),
+                    chart_width,
+                    chart_height,
+                    padding_x,
+                    padding_y,
+                    self.size_info.cell_width(),
+                    self.size_info.cell_height(),
+                );
+                match tokio_setup.charts_tx.try_send(display_size) {
+                    Err(e) => error!("Sending UpdateDisplaySize Task: err={:?}", e),
+                    Ok(_) => {
+                        debug!(
+                            "Sent UpdateDisplaySize Task height: {}, width: {}, padding_y: {}, \
                              padding_x: {}",
                             height, width, padding_x, padding_y
-                        ),
-                    }
-                });
-                tokio_handle.block_on(async {
-                    match chart_resize_rx.await {
-                        Ok(_) => {
-                            debug!("Got response from ChangeDisplaySize Task.");
-                        },
-                        Err(err) => {
-                            error!("Error response from ChangeDisplaySize Task: {:?}", err);
-                        },
-                    }
-                });
+                        );
+                        self.charts_cache.resize();
+                    },
+                }
             }
             self.surface.resize(&self.context, width, height);
         }
@@ -791,7 +724,8 @@ impl Display {
 
         self.renderer.resize(&self.size_info);
         // SEB: TODO: do not call when decorations are not enabled
-        self.decorations.set_size_info(self.size_info.into());
+        #[cfg(feature = "charts")]
+        self.decorations.set_size_info(self.size_info);
 
         info!("Padding: {} x {}", self.size_info.padding_x(), self.size_info.padding_y());
         info!("Width: {}, Height: {}", self.size_info.width(), self.size_info.height());
@@ -826,16 +760,21 @@ impl Display {
         let total_lines = terminal.grid().total_lines();
         let metrics = self.glyph_cache.font_metrics();
         let size_info = self.size_info;
+        #[cfg(feature = "charts")]
         let charts_enabled = terminal.charts_enabled();
+        #[cfg(feature = "charts")]
+        let chart_debug_overlay = terminal.chart_debug_overlay_enabled();
+        #[cfg(feature = "charts")]
         let decorations_enabled = terminal.decorations_enabled;
 
         let vi_mode = terminal.mode().contains(TermMode::VI);
         let vi_cursor_point = if vi_mode { Some(terminal.vi_mode_cursor.point) } else { None };
 
+        #[cfg(feature = "charts")]
         let mut tokio_handle: Option<tokio::runtime::Handle> = None;
-        let mut charts_tx: Option<
-            futures_mpsc::Sender<alacritty_terminal::async_utils::AsyncTask>,
-        > = None;
+        #[cfg(feature = "charts")]
+        let mut charts_tx: Option<futures_mpsc::Sender<AsyncTask>> = None;
+        #[cfg(feature = "charts")]
         if let Some(ref tokio_setup) = terminal.tokio_setup {
             tokio_handle = Some(tokio_setup.tokio_handle.clone());
             charts_tx = Some(tokio_setup.charts_tx.clone());
@@ -1049,15 +988,15 @@ impl Display {
             self.renderer.draw_rects(&size_info, &metrics, rects);
         }
 
-        if decorations_enabled {
-            self.draw_decorations(&size_info);
-        } else {
-            debug!("Decorations are not enabled");
-        }
+        // The decorations are shown by their visibility group, the hidden ones are suspended.
+        #[cfg(feature = "charts")]
+        self.draw_decorations(&size_info, charts_enabled, decorations_enabled);
 
         // Draw the charts
+        #[cfg(feature = "charts")]
         if charts_enabled {
-            if let (Some(tokio_handle), Some(charts_tx)) = (tokio_handle, charts_tx) {
+            if let (Some(tokio_handle), Some(charts_tx)) = (tokio_handle.clone(), charts_tx.clone())
+            {
                 self.draw_charts(config, &size_info, charts_tx, tokio_handle);
             }
         } else {
@@ -1072,12 +1011,20 @@ impl Display {
             self.draw_hyperlink_preview(config, cursor_point, display_offset);
         }
 
+        // Draw the debug overlay of the charts over everything else.
+        #[cfg(feature = "charts")]
+        if chart_debug_overlay {
+            if let (Some(tokio_handle), Some(charts_tx)) = (tokio_handle, charts_tx) {
+                self.draw_chart_debug_overlay(config, charts_tx, tokio_handle);
+            }
+        }
+
         // Notify winit that we're about to present.
         self.window.pre_present_notify();
 
         // Highlight damage for debugging.
         if self.damage_tracker.debug {
-            let damage = self.damage_tracker.shape_frame_damage(self.size_info.into());
+            let damage = self.damage_tracker.shape_frame_damage(self.size_info);
             let mut rects = Vec::with_capacity(damage.len());
             self.highlight_damage(&mut rects);
             self.renderer.draw_rects(&self.size_info, &metrics, rects);
@@ -1103,64 +1050,201 @@ impl Display {
     }
 
     /// Iterates over the configured  charts and draws them
+    #[cfg(feature = "charts")]
     pub fn draw_charts(
         &mut self,
         config: &UiConfig,
         size_info: &SizeInfo,
-        charts_tx: futures_mpsc::Sender<alacritty_terminal::async_utils::AsyncTask>,
+        charts_tx: futures_mpsc::Sender<AsyncTask>,
         tokio_handle: tokio::runtime::Handle,
     ) {
         if let Some(chart_config) = &config.charts {
             let chart_config = &chart_config.config;
+            // Only the charts whose vertices changed since the last frame are requested again.
+            let changed = self.charts_cache.refresh(charts_tx.clone(), tokio_handle.clone());
+            if changed > 0 {
+                self.chart_batch.clear();
+            }
+            let window_id = ChartsWindowId::default();
             for chart_idx in 0..chart_config.charts.len() {
                 debug!("draw: Drawing chart: {}", chart_config.charts[chart_idx].name);
-                for decoration_idx in 0..chart_config.charts[chart_idx].decorations.len() {
-                    // TODO: Change this to return a ChartOpenglData that contains:
-                    let opengl_data = alacritty_terminal::async_utils::get_metric_opengl_data(
-                        charts_tx.clone(),
-                        chart_idx,
-                        decoration_idx,
-                        "decoration",
-                        tokio_handle.clone(),
-                    );
-                    self.renderer.draw_array(
+                let chart_index = ChartIndex(chart_idx);
+                // Charts still awaiting their first data pulse, so they do not look broken.
+                if let Some(placeholder) = self.charts_cache.placeholder(
+                    charts_tx.clone(),
+                    window_id,
+                    chart_index,
+                    tokio_handle.clone(),
+                ) {
+                    let rect = placeholder.rect;
+                    let color =
+                        Rgb::new(placeholder.color.r, placeholder.color.g, placeholder.color.b);
+                    let (x, y, alpha) = (rect.x, rect.y, placeholder.alpha);
+                    let pulse = RenderRect::new(x, y, rect.width, rect.height, color, alpha);
+                    let metrics = self.glyph_cache.font_metrics();
+                    self.renderer.draw_rects(size_info, &metrics, vec![pulse]);
+                    self.damage_tracker.frame().add_viewport_rect(
                         size_info,
-                        &opengl_data.0,
-                        Rgb::new(
-                            chart_config.charts[chart_idx].decorations[decoration_idx].color().r,
-                            chart_config.charts[chart_idx].decorations[decoration_idx].color().g,
-                            chart_config.charts[chart_idx].decorations[decoration_idx].color().b,
-                        ),
-                        opengl_data.1,
-                        renderer::DrawArrayMode::LineStrip,
+                        x as i32,
+                        y as i32,
+                        rect.width.ceil() as i32,
+                        rect.height.ceil() as i32,
                     );
                 }
-                for series_idx in 0..chart_config.charts[chart_idx].sources.len() {
-                    let opengl_data = alacritty_terminal::async_utils::get_metric_opengl_data(
+                if changed > 0 {
+                    self.push_chart_draw_items(
+                        chart_config,
                         charts_tx.clone(),
-                        chart_idx,
-                        series_idx,
-                        "metric_data",
+                        window_id,
+                        chart_index,
                         tokio_handle.clone(),
                     );
-                    self.renderer.draw_array(
-                        size_info,
-                        &opengl_data.0,
-                        Rgb::new(
-                            chart_config.charts[chart_idx].sources[series_idx].color().r,
-                            chart_config.charts[chart_idx].sources[series_idx].color().g,
-                            chart_config.charts[chart_idx].sources[series_idx].color().b,
-                        ),
-                        opengl_data.1,
-                        renderer::DrawArrayMode::LineStrip,
-                    );
                 }
             }
+            // The vertices of all the charts are uploaded once and only when they changed.
+            self.renderer.draw_chart_batch(size_info, &self.chart_batch);
+            for chart_idx in 0..chart_config.charts.len() {
+                let chart_index = ChartIndex(chart_idx);
+                let readout = self
+                    .charts_cache
+                    .numeric_readout(
+                        charts_tx.clone(),
+                        window_id,
+                        chart_index,
+                        tokio_handle.clone(),
+                    )
+                    .cloned();
+                if let Some(readout) = readout {
+                    self.draw_numeric_readout(config, size_info, &readout);
+                }
+                self.charts_cache.timeline_bands(
+                    charts_tx.clone(),
+                    window_id,
+                    chart_index,
+                    tokio_handle.clone(),
+                );
+            }
+            // The bands may have moved since the mouse did.
+            self.timeline_readout =
+                self.chart_mouse.and_then(|(x, y)| self.charts_cache.timeline_readout(x, y));
+            if let Some(readout) = self.timeline_readout.clone() {
+                self.draw_timeline_readout(config, size_info, &readout);
+            }
+        }
+    }
+
+    /// Draws the state of the timeline band under the mouse on the line above the band, starting
+    /// at the mouse column.
+    #[cfg(feature = "charts")]
+    fn draw_timeline_readout(
+        &mut self,
+        config: &UiConfig,
+        size_info: &SizeInfo,
+        readout: &TimelineReadout,
+    ) {
+        let (mouse_x, _) = match self.chart_mouse {
+            Some(mouse) => mouse,
+            None => return,
+        };
+        let (columns, screen_lines) = (size_info.columns(), size_info.screen_lines());
+        let band_line =
+            ((readout.rect.y - size_info.padding_y()) / size_info.cell_height()).floor();
+        let line = (band_line.max(1.) as usize - 1).min(screen_lines.saturating_sub(1));
+        let width = readout.text.chars().count().min(columns);
+        let mouse_column = ((mouse_x - size_info.padding_x()) / size_info.cell_width()).max(0.);
+        let column = (mouse_column as usize).min(columns - width);
+        let point = Point::new(line, Column(column));
+
+        // Damage the readout for current and next frame, it moves with the mouse.
+        let damage = LineDamageBounds::new(point.line, point.column.0, column + width);
+        self.damage_tracker.frame().damage_line(damage);
+        self.damage_tracker.next_frame().damage_line(damage);
+
+        let fg = Rgb::new(readout.color.r, readout.color.g, readout.color.b);
+        let bg = config.colors.primary.background;
+        let glyph_cache = &mut self.glyph_cache;
+        let chars = readout.text.chars().take(width);
+        self.renderer.draw_string(point, fg, bg, chars, size_info, glyph_cache);
+    }
+
+    /// Adds the decorations and series of a chart to the draw batch, they are kept inside of the
+    /// chart area, including their anti-aliased edges, unless the chart may overflow.
+    #[cfg(feature = "charts")]
+    fn push_chart_draw_items(
+        &mut self,
+        chart_config: &ChartsConfig,
+        charts_tx: futures_mpsc::Sender<AsyncTask>,
+        window_id: ChartsWindowId,
+        chart_index: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) {
+        push_chart_draw_items(
+            &mut self.chart_batch,
+            &mut self.charts_cache,
+            chart_config,
+            charts_tx,
+            window_id,
+            chart_index,
+            tokio_handle,
+        );
+    }
+
+    /// Draws the latest value of a Numeric chart centered in the chart area, the sparkline of
+    /// the recent samples is drawn underneath when the chart is at least two lines tall.
+    #[cfg(feature = "charts")]
+    fn draw_numeric_readout(
+        &mut self,
+        config: &UiConfig,
+        size_info: &SizeInfo,
+        readout: &NumericReadout,
+    ) {
+        let fg = Rgb::new(readout.color.r, readout.color.g, readout.color.b);
+        let bg = config.colors.primary.background;
+        for (point, row) in numeric_readout_rows(readout, size_info) {
+            let right = point.column.0 + row.chars().count();
+            let damage = LineDamageBounds::new(point.line, point.column.0, right);
+            self.damage_tracker.frame().damage_line(damage);
+            let glyph_cache = &mut self.glyph_cache;
+            self.renderer.draw_string(point, fg, bg, row.chars(), size_info, glyph_cache);
         }
     }
 
-    /// Iterates over the decorations
-    pub fn draw_decorations(&mut self, size_info: &SizeInfo) {
+    /// Draws the lines of the charts debug overlay, they are laid out by the coordinator.
+    #[cfg(feature = "charts")]
+    fn draw_chart_debug_overlay(
+        &mut self,
+        config: &UiConfig,
+        charts_tx: futures_mpsc::Sender<AsyncTask>,
+        tokio_handle: tokio::runtime::Handle,
+    ) {
+        let lines = get_debug_overlay_data(charts_tx, tokio_handle);
+        let bg = config.colors.primary.background;
+        let (screen_lines, columns) = (self.size_info.screen_lines(), self.size_info.columns());
+        // The lines are laid out for the size known to the coordinator, it may be stale.
+        for line in lines.iter().filter(|line| line.point.line < screen_lines) {
+            let fg = Rgb::new(line.fg.r, line.fg.g, line.fg.b);
+            let point = line.point;
+            let right = (point.column.0 + line.text.chars().count()).min(columns);
+
+            // Damage the overlay for current and next frame, the lines may change or be hidden.
+            let damage = LineDamageBounds::new(point.line, point.column.0, right);
+            self.damage_tracker.frame().damage_line(damage);
+            self.damage_tracker.next_frame().damage_line(damage);
+
+            let glyph_cache = &mut self.glyph_cache;
+            let chars = line.text.chars();
+            self.renderer.draw_string(point, fg, bg, chars, &self.size_info, glyph_cache);
+        }
+    }
+
+    /// Iterates over the decorations visible with the charts and decorations toggles
+    #[cfg(feature = "charts")]
+    pub fn draw_decorations(
+        &mut self,
+        size_info: &SizeInfo,
+        charts_enabled: bool,
+        decorations_enabled: bool,
+    ) {
         // Create a "wind" effect of a moving curtain by making it very transparent as it
         // reaches 1000
         //
@@ -1168,23 +1252,20 @@ impl Display {
         // - Move to the decorations module and implement with tick()
         // - Use lyon and perlin noise
         let seconds_cycle = 15f32;
-        let epoch =
-            std::time::SystemTime::now().duration_since(std::time::SystemTime::UNIX_EPOCH).unwrap();
-
-        let curr_second_cycle = (epoch.as_secs() % (seconds_cycle as u64)) as f32;
         // |-------------------------------|---------------------------------|
         // 0.0 u                         0.25 u                             0.5
         // 0.0 seconds                    7.5 seconds                       15 seconds
         // Every 15 seconds the opacity should go back to 100% of out top
         let max_hexagon_opacity = 0.5f32;
         let wind_screen_size = 0.5f32;
-        let x_move_in_time = (curr_second_cycle * wind_screen_size) / seconds_cycle;
-        self.decorations.tick();
-        for decoration in &self.decorations.decorators {
-            let elapsed_secs_with_millis = self.decorations.get_elapsed_secs_with_millis();
+        self.decorations.tick(charts_enabled, decorations_enabled);
+        for (decoration, elapsed_secs_with_millis) in self.decorations.visible_decorators() {
             match decoration {
                 DecorationTypes::Lines(line_decor) => match line_decor {
                     DecorationLines::Hexagon(hex_lines) => {
+                        // The wind follows the animation time, it stops while suspended.
+                        let curr_second_cycle = (elapsed_secs_with_millis % seconds_cycle).floor();
+                        let x_move_in_time = (curr_second_cycle * wind_screen_size) / seconds_cycle;
                         // Draw chunks of 12, since it's 2 points (x,y) per coordinate
                         for opengl_data in hex_lines.vecs.chunks(12) {
                             // Mid-left is the 6th in the array
@@ -1236,7 +1317,8 @@ impl Display {
                         );
                     },
                 },
-                DecorationTypes::None => unreachable!("Attempting to draw decoration of type None"),
+                // Decorations added after this renderer are skipped until it learns to draw them.
+                _ => (),
             }
         }
     }
@@ -1246,6 +1328,8 @@ impl Display {
         self.damage_tracker.debug = config.debug.highlight_damage;
         self.visual_bell.update_config(&config.bell);
         self.colors = List::from(&config.colors);
+        #[cfg(feature = "charts")]
+        self.update_charts_palette();
     }
 
     /// Update the mouse/vi mode cursor hint highlighting.
@@ -1275,6 +1359,19 @@ impl Display {
             self.damage_tracker.frame().mark_fully_damaged();
         }
 
+        // Update the state of the timeline band under the mouse.
+        #[cfg(feature = "charts")]
+        {
+            self.chart_mouse = term.charts_enabled().then_some((mouse.x as f32, mouse.y as f32));
+            let timeline_readout =
+                self.chart_mouse.and_then(|(x, y)| self.charts_cache.timeline_readout(x, y));
+            if timeline_readout != self.timeline_readout {
+                self.timeline_readout = timeline_readout;
+                self.damage_tracker.frame().mark_fully_damaged();
+                dirty = true;
+            }
+        }
+
         // Abort if mouse highlighting conditions are not met.
         if !mouse.inside_text_area || !term.selection.as_ref().map_or(true, Selection::is_empty) {
             if self.highlighted_hint.take().is_some() {
@@ -1572,7 +1669,7 @@ impl Display {
     ///
     /// This function is for debug purposes only.
     fn highlight_damage(&self, render_rects: &mut Vec<RenderRect>) {
-        for damage_rect in &self.damage_tracker.shape_frame_damage(self.size_info.into()) {
+        for damage_rect in &self.damage_tracker.shape_frame_damage(self.size_info) {
             let x = damage_rect.x as f32;
             let height = damage_rect.height as f32;
             let width = damage_rect.width as f32;
@@ -1830,6 +1927,41 @@ impl FrameTimer {
     }
 }
 
+/// `numeric_readout_rows` lays out the rows of a numeric readout centered in the cells of its
+/// chart, truncated to its columns. The lines past the screen lines, i.e. in the lines reserved
+/// for the charts at the bottom, are skipped.
+#[cfg(feature = "charts")]
+fn numeric_readout_rows(
+    readout: &NumericReadout,
+    size_info: &SizeInfo,
+) -> Vec<(Point<usize>, String)> {
+    let rect = readout.rect;
+    let cell_width = size_info.cell_width();
+    let cell_height = size_info.cell_height();
+    let first_column = ((rect.x - size_info.padding_x()) / cell_width).ceil().max(0.) as usize;
+    let columns = (rect.width / cell_width).floor() as usize;
+    let first_line = ((rect.y - size_info.padding_y()) / cell_height).ceil().max(0.) as usize;
+    let last_line = ((rect.y + rect.height - size_info.padding_y()) / cell_height).floor();
+    let lines = (last_line.max(0.) as usize).saturating_sub(first_line);
+    if columns == 0 || lines == 0 {
+        return vec![];
+    }
+    let mut rows = vec![format!("{}{}", readout.value, readout.trend.symbol())];
+    if lines > 1 {
+        rows.push(readout.sparkline.clone());
+    }
+    let start_line = first_line + (lines - rows.len()) / 2;
+    rows.into_iter()
+        .enumerate()
+        .filter(|(i, _)| start_line + i < size_info.screen_lines())
+        .map(|(i, row)| {
+            let width = row.chars().count().min(columns);
+            let point = Point::new(start_line + i, Column(first_column + (columns - width) / 2));
+            (point, row.chars().take(width).collect())
+        })
+        .collect()
+}
+
 /// Calculate the cell dimensions based on font metrics.
 ///
 /// This will return a tuple of the cell width and height.
@@ -1861,3 +1993,40 @@ fn window_size(
 
     PhysicalSize::new(width as u32, height as u32)
 }
+
+#[cfg(all(test, feature = "charts"))]
+mod tests {
+    use super::*;
+
+    use alacritty_terminal::charts::{PixelRect, Trend};
+
+    /// `readout` returns the readout of a chart 10 cells wide starting at column 5
+    fn readout(y: f32, height: f32) -> NumericReadout {
+        NumericReadout {
+            rect: PixelRect { x: 50., y, width: 100., height },
+            value: String::from("42"),
+            trend: Trend::Up,
+            color: alacritty_terminal::vte::ansi::Rgb { r: 255, g: 255, b: 255 },
+            sparkline: String::from("▁▂▃"),
+        }
+    }
+
+    #[test]
+    fn it_skips_the_numeric_readout_lines_past_the_screen() {
+        // 10 lines of 20 pixels, the last one is reserved for the charts.
+        let mut size_info = SizeInfo::new(200., 200., 10., 20., 0., 0., false);
+        size_info.reserve_lines(1);
+        assert_eq!(size_info.screen_lines(), 9);
+        // A chart one line tall at the bottom is in the reserved line.
+        assert!(numeric_readout_rows(&readout(180., 20.), &size_info).is_empty());
+        // The sparkline of a chart two lines tall is in the reserved line, the value is kept.
+        let rows = numeric_readout_rows(&readout(160., 40.), &size_info);
+        assert_eq!(rows, vec![(Point::new(8, Column(8)), String::from("42↑"))]);
+        // Both rows are drawn above the reserved line.
+        let rows = numeric_readout_rows(&readout(140., 40.), &size_info);
+        assert_eq!(rows, vec![
+            (Point::new(7, Column(8)), String::from("42↑")),
+            (Point::new(8, Column(8)), String::from("▁▂▃")),
+        ]);
+    }
+}
@@ -53,7 +53,7 @@ use crate::display::window::Window;
 use crate::display::{Display, Preedit, SizeInfo};
 use crate::input::{self, ActionContext as _, FONT_SIZE_STEP};
 use crate::logging::{LOG_TARGET_CONFIG, LOG_TARGET_WINIT};
-use crate::message_bar::{Message, MessageBuffer};
+use crate::message_bar::{Message, MessageBuffer, MessageType};
 use crate::scheduler::{Scheduler, TimerId, Topic};
 use crate::window_context::WindowContext;
 
@@ -665,6 +665,9 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         self.terminal.scroll_display(scroll);
 
         let lines_changed = old_offset - self.terminal.grid().display_offset() as i32;
+        if lines_changed != 0 {
+            self.terminal.increment_chart_scroll_counter(f64::from(lines_changed.abs()));
+        }
 
         // Keep track of manual display offset changes during search.
         if self.search_active() {
@@ -705,6 +708,15 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
             self.clipboard.store(ClipboardType::Clipboard, text.clone());
         }
         self.clipboard.store(ty, text);
+
+        // A completed selection is copied to the selection clipboard.
+        if ty == ClipboardType::Selection {
+            let columns = self.terminal.columns();
+            let range = self.terminal.selection.as_ref().and_then(|s| s.to_range(self.terminal));
+            if let Some(range) = range {
+                self.terminal.increment_chart_selection_counter(range.cell_count(columns) as f64);
+            }
+        }
     }
 
     fn selection_is_empty(&self) -> bool {
@@ -727,6 +739,11 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         // Treat motion over message bar like motion over the last line.
         point.line = min(point.line, self.terminal.bottommost_line());
 
+        // Do not extend the selection below the charts.
+        if self.terminal.charts_enabled() {
+            point = self.terminal.clamp_to_charts_uncovered(point);
+        }
+
         // Update selection.
         selection.update(point, side);
 
@@ -889,6 +906,12 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
             .set_font(self.config.font.clone().with_size(self.display.font_size));
     }
 
+    #[inline]
+    fn push_message(&mut self, message: Message) {
+        self.display.pending_update.dirty = true;
+        self.message_buffer.push(message);
+    }
+
     #[inline]
     fn pop_message(&mut self) {
         if !self.message_buffer.is_empty() {
@@ -1180,6 +1203,23 @@ impl<'a, N: Notify + 'a, T: EventListener> input::ActionContext<T> for ActionCon
         }
     }
 
+    /// Open a chart link with the program of the first hint that launches a command, the same
+    /// way URLs in the text are opened.
+    fn launch_chart_link(&mut self, link: &str) {
+        let command = self.config.hints.enabled.iter().find_map(|hint| match &hint.action {
+            HintAction::Command(command) => Some(command.clone()),
+            HintAction::Action(_) => None,
+        });
+        match command {
+            Some(command) => {
+                let mut args = command.args().to_vec();
+                args.push(link.into());
+                self.spawn_daemon(command.program(), &args);
+            },
+            None => warn!("No hint command configured to open chart link {}", link),
+        }
+    }
+
     /// Expand the selection to the current mouse cursor position.
     #[inline]
     fn expand_selection(&mut self) {
@@ -1789,6 +1829,14 @@ impl input::Processor<EventProxy, ActionContext<'_, Notifier, EventProxy>> {
                     TerminalEvent::Exit | TerminalEvent::ChildExit(_) | TerminalEvent::Wakeup => (),
                     TerminalEvent::DecorEvent => self.ctx.display.pending_update.dirty = true,
                     TerminalEvent::ChartEvent => self.ctx.display.pending_update.dirty = true,
+                    // The alerts are shown in the message bar, without ringing the bell.
+                    TerminalEvent::ChartAlert(text) => {
+                        let message = Message::new(text, MessageType::Warning);
+                        if !self.ctx.message_buffer.is_queued(&message) {
+                            self.ctx.message_buffer.push(message);
+                            self.ctx.display.pending_update.dirty = true;
+                        }
+                    },
                 },
                 #[cfg(unix)]
                 EventType::IpcConfig(_) => (),
@@ -1812,6 +1860,10 @@ impl input::Processor<EventProxy, ActionContext<'_, Notifier, EventProxy>> {
 
                         let font = self.ctx.config.font.clone();
                         display_update_pending.set_font(font.with_size(self.ctx.display.font_size));
+
+                        // Charts are configured in logical pixels.
+                        #[cfg(feature = "charts")]
+                        self.ctx.display.update_charts_scale_factor(scale_factor);
                     },
                     WindowEvent::Resized(size) => {
                         // Ignore resize events to zero in any dimension, to avoid issues with Winit
@@ -1855,6 +1907,14 @@ impl input::Processor<EventProxy, ActionContext<'_, Notifier, EventProxy>> {
 
                         self.ctx.update_cursor_blinking();
                         self.on_focus_change(is_focused);
+
+                        // Summarize the charts since the focus was lost after a long absence.
+                        let summaries = self.ctx.terminal.charts_focus_changed(is_focused);
+                        if !summaries.is_empty() {
+                            let text = summaries.join("\n");
+                            self.ctx.message_buffer.push(Message::new(text, MessageType::Warning));
+                            self.ctx.display.pending_update.dirty = true;
+                        }
                     },
                     WindowEvent::Occluded(occluded) => {
                         *self.ctx.occluded = occluded;
@@ -103,6 +103,7 @@ pub trait ActionContext<T: EventListener> {
     fn create_new_window(&mut self) {}
     fn change_font_size(&mut self, _delta: f32) {}
     fn reset_font_size(&mut self) {}
+    fn push_message(&mut self, _message: Message) {}
     fn pop_message(&mut self) {}
     fn message(&self) -> Option<&Message>;
     fn config(&self) -> &UiConfig;
@@ -131,6 +132,7 @@ pub trait ActionContext<T: EventListener> {
     fn inline_search_previous(&mut self) {}
     fn hint_input(&mut self, _character: char) {}
     fn trigger_hint(&mut self, _hint: &HintMatch) {}
+    fn launch_chart_link(&mut self, _link: &str) {}
     fn expand_selection(&mut self) {}
     fn on_terminal_input_start(&mut self) {}
     fn paste(&mut self, _text: &str, _bracketed: bool) {}
@@ -382,6 +384,38 @@ impl<T: EventListener> Execute<T> for Action {
             Action::ClearHistory => ctx.terminal_mut().clear_screen(ClearMode::Saved),
             Action::ClearLogNotice => ctx.pop_message(),
             Action::ToggleChartShow => ctx.terminal_mut().toggle_chart_show(),
+            Action::ToggleChartDebugOverlay => {
+                ctx.terminal_mut().toggle_chart_debug_overlay();
+                ctx.mark_dirty();
+            },
+            Action::ToggleDecorations => {
+                ctx.terminal_mut().toggle_decorations();
+                ctx.mark_dirty();
+            },
+            #[cfg(feature = "charts")]
+            Action::CycleChartWindow => {
+                let _ = ctx.terminal_mut().cycle_chart_window(None);
+                ctx.mark_dirty();
+            },
+            #[cfg(not(feature = "charts"))]
+            Action::CycleChartWindow => (),
+            #[cfg(feature = "charts")]
+            Action::CycleChartWindowOf(chart) => {
+                let chart_index = ctx
+                    .config()
+                    .charts
+                    .as_ref()
+                    .and_then(|charts| charts.config.resolve_chart(chart));
+                // The chart may be missing from the config, or from the coordinator.
+                let found = chart_index.map_or(false, |chart_index| {
+                    ctx.terminal_mut().cycle_chart_window(Some(chart_index)).is_ok()
+                });
+                if !found {
+                    let text = format!("chart {chart} not found");
+                    ctx.push_message(Message::new(text, message_bar::MessageType::Warning));
+                }
+                ctx.mark_dirty();
+            },
             #[cfg(not(target_os = "macos"))]
             Action::CreateNewWindow => ctx.create_new_window(),
             Action::SpawnNewInstance => ctx.spawn_new_instance(),
@@ -526,6 +560,12 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
             return;
         }
 
+        // Clicks on the charts are not reported to the application.
+        let terminal = self.ctx.terminal();
+        if terminal.charts_enabled() && terminal.is_point_covered_by_charts(point) {
+            return;
+        }
+
         // Calculate modifiers value.
         let mut mods = 0;
         let modifiers = self.ctx.modifiers().state();
@@ -684,6 +724,17 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
             return;
         }
 
+        // Open the link of the chart under the mouse on Ctrl+click, instead of the hint under it.
+        if button == MouseButton::Left
+            && self.ctx.modifiers().state().control_key()
+            && !self.ctx.mouse().block_hint_launcher
+        {
+            if let Some(link) = self.chart_link_at_mouse() {
+                self.ctx.launch_chart_link(&link);
+                return;
+            }
+        }
+
         // Trigger hints highlighted by the mouse.
         let hint = self.ctx.display().highlighted_hint.take();
         if let Some(hint) = hint.as_ref().filter(|_| button == MouseButton::Left) {
@@ -1052,6 +1103,23 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
         }
     }
 
+    /// Link of the chart under the mouse, if any.
+    #[cfg(feature = "charts")]
+    fn chart_link_at_mouse(&mut self) -> Option<String> {
+        if !self.ctx.terminal().charts_enabled() {
+            return None;
+        }
+        let size_info = self.ctx.size_info();
+        let size_info = self.ctx.display().charts_size_info(size_info);
+        let (x, y) = (self.ctx.mouse().x as f32, self.ctx.mouse().y as f32);
+        self.ctx.config().charts.as_ref()?.config.link_at_pixel(x, y, size_info)
+    }
+
+    #[cfg(not(feature = "charts"))]
+    fn chart_link_at_mouse(&mut self) -> Option<String> {
+        None
+    }
+
     /// Icon state of the cursor.
     fn cursor_state(&mut self) -> CursorIcon {
         let display_offset = self.ctx.terminal().grid().display_offset();
@@ -1063,7 +1131,9 @@ impl<T: EventListener, A: ActionContext<T>> Processor<T, A> {
 
         if let Some(mouse_state) = self.message_bar_cursor_state() {
             mouse_state
-        } else if self.ctx.display().highlighted_hint.as_ref().map_or(false, hint_highlighted) {
+        } else if self.ctx.display().highlighted_hint.as_ref().map_or(false, hint_highlighted)
+            || self.chart_link_at_mouse().is_some()
+        {
             CursorIcon::Pointer
         } else if !self.ctx.modifiers().state().shift_key() && self.ctx.mouse_mode() {
             CursorIcon::Default
@@ -1116,6 +1186,8 @@ mod tests {
     use winit::keyboard::Key;
     use winit::window::WindowId;
 
+    #[cfg(feature = "charts")]
+    use alacritty_terminal::charts::prelude::ChartRef;
     use alacritty_terminal::event::Event as TerminalEvent;
 
     use crate::config::Binding;
@@ -1210,6 +1282,10 @@ mod tests {
             unimplemented!();
         }
 
+        fn push_message(&mut self, message: Message) {
+            self.message_buffer.push(message);
+        }
+
         fn pop_message(&mut self) {
             self.message_buffer.pop();
         }
@@ -1512,4 +1588,34 @@ mod tests {
         mode: BindingMode::empty(),
         mods: ModifiersState::ALT | ModifiersState::SUPER,
     }
+
+    #[cfg(feature = "charts")]
+    #[test]
+    fn missing_chart_binding_warns() {
+        let mut clipboard = Clipboard::new_nop();
+        let cfg = UiConfig::default();
+        let size = SizeInfo::new(21.0, 51.0, 3.0, 3.0, 0., 0., false);
+        let mut terminal = Term::new(cfg.term_options(), &size, MockEventProxy);
+        let mut mouse = Mouse::default();
+        let mut inline_search_state = InlineSearchState::default();
+        let mut message_buffer = MessageBuffer::default();
+
+        let mut context = ActionContext {
+            terminal: &mut terminal,
+            mouse: &mut mouse,
+            size_info: &size,
+            clipboard: &mut clipboard,
+            modifiers: Default::default(),
+            message_buffer: &mut message_buffer,
+            inline_search_state: &mut inline_search_state,
+            config: &cfg,
+        };
+
+        let action = Action::CycleChartWindowOf(ChartRef::Name(String::from("loadavg")));
+        action.execute(&mut context);
+
+        let text = String::from("chart 'loadavg' not found");
+        let message = Message::new(text, message_bar::MessageType::Warning);
+        assert!(context.message_buffer.is_queued(&message));
+    }
 }
@@ -77,6 +77,12 @@ fn main() -> Result<(), Box<dyn Error>> {
     // Load command line options.
     let options = Options::new();
 
+    #[cfg(feature = "charts")]
+    if let Some(path) = &options.print_migrated_charts_config {
+        print!("{}", alacritty_terminal::charts::prelude::migrated_charts_yaml(path)?);
+        return Ok(());
+    }
+
     match options.subcommands {
         #[cfg(unix)]
         Some(Subcommands::Msg(options)) => msg(options)?,
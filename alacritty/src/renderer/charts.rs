@@ -1,4 +1,6 @@
-use std::mem;
+use std::{mem, ptr};
+
+use alacritty_terminal::charts::prelude::{ChartDrawBatch, DrawItem, FLOATS_PER_VERTEX};
 
 use crate::gl;
 use crate::gl::types::*;
@@ -15,6 +17,9 @@ pub struct ChartRenderer {
     pub vbo: GLuint,
 
     program: ChartsShaderProgram,
+
+    /// The generation of the batch whose vertices are in the buffer.
+    uploaded_generation: Option<u64>,
 }
 
 impl ChartRenderer {
@@ -41,7 +46,7 @@ impl ChartRenderer {
                 gl::FLOAT,
                 gl::FALSE,
                 // [2(x,y) + 4(r,g,b,a) ] -> 6
-                (mem::size_of::<f32>() * 6) as i32,
+                (mem::size_of::<f32>() * FLOATS_PER_VERTEX) as i32,
                 attribute_offset as *const _,
             );
             gl::EnableVertexAttribArray(0);
@@ -54,7 +59,7 @@ impl ChartRenderer {
                 gl::FLOAT,
                 gl::FALSE,
                 // [2(x,y) + 4(r,g,b,a) ] -> 6
-                (mem::size_of::<f32>() * 6) as i32,
+                (mem::size_of::<f32>() * FLOATS_PER_VERTEX) as i32,
                 // The colors are offset by 2 (x,y) points
                 attribute_offset as *const _,
             );
@@ -64,10 +69,12 @@ impl ChartRenderer {
             gl::BindVertexArray(0);
             gl::BindBuffer(gl::ARRAY_BUFFER, 0);
         }
-        Ok(Self { vao, vbo, program })
+        Ok(Self { vao, vbo, program, uploaded_generation: None })
     }
 
     pub fn draw(&mut self, opengl_data: &[f32], gl_mode: u32) {
+        // The buffer no longer holds the vertices of a batch.
+        self.uploaded_generation = None;
         unsafe {
             // Bind VAO to enable vertex attribute slots.
             gl::BindVertexArray(self.vao);
@@ -88,7 +95,7 @@ impl ChartRenderer {
 
             // Draw the incoming array, opengl_data contains:
             // [2(x,y) + 4(r,g,b,a) ] -> 6
-            gl::DrawArrays(gl_mode, 0, (opengl_data.len() / 6usize) as i32);
+            gl::DrawArrays(gl_mode, 0, (opengl_data.len() / FLOATS_PER_VERTEX) as i32);
 
             // Disable program.
             gl::UseProgram(0);
@@ -100,6 +107,43 @@ impl ChartRenderer {
     }
 }
 
+impl ChartRenderer {
+    /// Load the vertices of a frame into the buffer, a batch is only uploaded once per
+    /// generation so unchanged charts reuse the buffer of the previous frames.
+    pub fn upload(&mut self, batch: &ChartDrawBatch) {
+        if self.uploaded_generation == Some(batch.generation()) {
+            return;
+        }
+        let vertices = batch.vertices();
+        let size = mem::size_of_val(vertices) as GLsizeiptr;
+        unsafe {
+            gl::BindBuffer(gl::ARRAY_BUFFER, self.vbo);
+
+            // Orphan the previous storage so the upload does not wait for pending draws.
+            gl::BufferData(gl::ARRAY_BUFFER, size, ptr::null(), gl::DYNAMIC_DRAW);
+            gl::BufferSubData(gl::ARRAY_BUFFER, 0, size, vertices.as_ptr() as *const _);
+
+            gl::BindBuffer(gl::ARRAY_BUFFER, 0);
+        }
+        self.uploaded_generation = Some(batch.generation());
+    }
+
+    /// Draw the sub-ranges of the uploaded buffer of each item, they must share the mode.
+    pub fn draw_items(&mut self, items: &[DrawItem], gl_mode: u32) {
+        unsafe {
+            gl::BindVertexArray(self.vao);
+            gl::UseProgram(self.program.id());
+
+            for item in items {
+                gl::DrawArrays(gl_mode, item.first as i32, item.count as i32);
+            }
+
+            gl::UseProgram(0);
+            gl::BindVertexArray(0);
+        }
+    }
+}
+
 /// Charts drawing program.
 #[derive(Debug)]
 pub struct ChartsShaderProgram {
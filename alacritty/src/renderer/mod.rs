@@ -1,4 +1,6 @@
 use std::borrow::Cow;
+#[cfg(feature = "charts")]
+use std::cell::Cell;
 use std::collections::HashSet;
 use std::ffi::{CStr, CString};
 use std::sync::atomic::{AtomicBool, Ordering};
@@ -12,6 +14,8 @@ use glutin::display::{GetGlDisplay, GlDisplay};
 use log::{debug, error, info, warn, LevelFilter};
 use unicode_width::UnicodeWidthChar;
 
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::{ChartDrawBatch, DrawMode, RgbExt};
 use alacritty_terminal::index::Point;
 use alacritty_terminal::term::cell::Flags;
 
@@ -20,12 +24,16 @@ use crate::display::color::Rgb;
 use crate::display::content::RenderableCell;
 use crate::display::SizeInfo;
 use crate::gl;
+#[cfg(feature = "charts")]
 use crate::renderer::charts::ChartRenderer;
+#[cfg(feature = "charts")]
 use crate::renderer::hex_bg::HexBgRenderer;
 use crate::renderer::rects::{RectRenderer, RenderRect};
 use crate::renderer::shader::ShaderError;
 
+#[cfg(feature = "charts")]
 pub mod charts;
+#[cfg(feature = "charts")]
 pub mod hex_bg;
 pub mod platform;
 pub mod rects;
@@ -57,20 +65,36 @@ pub enum Error {
     Other(String),
 }
 
+#[cfg(feature = "charts")]
 #[derive(Debug, Clone)]
 pub enum DrawArrayMode {
     Points,
     LineStrip,
     LineLoop,
     // GlTriangleFan,
-    // GlLines,
-    // GlTriangleStrip,
+    GlLines,
+    GlTriangleStrip,
     GlTriangles,
     // GlQuadStrip, // Unsupported
     // GlQuads,
     // GlPolygon,
 }
 
+#[cfg(feature = "charts")]
+impl From<DrawMode> for DrawArrayMode {
+    fn from(mode: DrawMode) -> Self {
+        match mode {
+            DrawMode::Points => DrawArrayMode::Points,
+            DrawMode::LineStrip => DrawArrayMode::LineStrip,
+            DrawMode::LineLoop => DrawArrayMode::LineLoop,
+            DrawMode::Lines => DrawArrayMode::GlLines,
+            DrawMode::Triangles => DrawArrayMode::GlTriangles,
+            DrawMode::TriangleStrip => DrawArrayMode::GlTriangleStrip,
+        }
+    }
+}
+
+#[cfg(feature = "charts")]
 impl From<DrawArrayMode> for u32 {
     fn from(src: DrawArrayMode) -> Self {
         // Translate our enum to opengl enum, maybe this can be ommitted?
@@ -81,8 +105,8 @@ impl From<DrawArrayMode> for u32 {
             DrawArrayMode::LineStrip => gl::LINE_STRIP,
             DrawArrayMode::LineLoop => gl::LINE_LOOP,
             // DrawArrayMode::GlTriangleFan => gl::TRIANGLE_FAN,
-            // DrawArrayMode::GlLines => gl::LINES,
-            // DrawArrayMode::GlTriangleStrip => gl::TRIANGLE_STRIP,
+            DrawArrayMode::GlLines => gl::LINES,
+            DrawArrayMode::GlTriangleStrip => gl::TRIANGLE_STRIP,
             DrawArrayMode::GlTriangles => gl::TRIANGLES,
             // DrawArrayMode::GlQuadStrip => gl::QUAD_STRIP, // Unsupported?
             // DrawArrayMode::GlQuads => gl::QUADS,
@@ -134,8 +158,36 @@ enum TextRendererProvider {
 pub struct Renderer {
     text_renderer: TextRendererProvider,
     rect_renderer: RectRenderer,
-    chart_renderer: ChartRenderer,
-    hex_bg_renderer: HexBgRenderer,
+    #[cfg(feature = "charts")]
+    chart_renderer: Option<ChartRenderer>,
+    #[cfg(feature = "charts")]
+    hex_bg_renderer: Option<HexBgRenderer>,
+
+    /// The first error building the optional chart renderers, charts are disabled without them.
+    #[cfg(feature = "charts")]
+    charts_error: Option<Error>,
+
+    /// The scissor test state and box before the chart scissor was enabled.
+    #[cfg(feature = "charts")]
+    prev_scissor: Cell<Option<(bool, [i32; 4])>>,
+}
+
+/// Keep an optional renderer only if it was built, the first error is stored in `error` and
+/// logged as a warning so it shows once in the message bar.
+#[cfg(feature = "charts")]
+fn optional_renderer<R>(
+    name: &str,
+    renderer: Result<R, Error>,
+    error: &mut Option<Error>,
+) -> Option<R> {
+    match renderer {
+        Ok(renderer) => Some(renderer),
+        Err(err) => {
+            warn!("Disabling the {name} renderer: {err}");
+            error.get_or_insert(err);
+            None
+        },
+    }
 }
 
 /// Wrapper around gl::GetString with error checking and reporting.
@@ -193,19 +245,28 @@ impl Renderer {
             None => (shader_version.as_ref() >= "3.3" && !is_gles_context, true),
         };
 
-        let (text_renderer, rect_renderer, chart_renderer, hex_bg_renderer) = if use_glsl3 {
+        let (text_renderer, rect_renderer) = if use_glsl3 {
             let text_renderer = TextRendererProvider::Glsl3(Glsl3Renderer::new()?);
             let rect_renderer = RectRenderer::new(ShaderVersion::Glsl3)?;
-            let chart_renderer = ChartRenderer::new(ShaderVersion::Glsl3)?;
-            let hex_bg_renderer = HexBgRenderer::new(ShaderVersion::Glsl3)?;
-            (text_renderer, rect_renderer, chart_renderer, hex_bg_renderer)
+            (text_renderer, rect_renderer)
         } else {
             let text_renderer =
                 TextRendererProvider::Gles2(Gles2Renderer::new(allow_dsb, is_gles_context)?);
             let rect_renderer = RectRenderer::new(ShaderVersion::Gles2)?;
-            let chart_renderer = ChartRenderer::new(ShaderVersion::Gles2)?;
-            let hex_bg_renderer = HexBgRenderer::new(ShaderVersion::Gles2)?;
-            (text_renderer, rect_renderer, chart_renderer, hex_bg_renderer)
+            (text_renderer, rect_renderer)
+        };
+
+        // Charts are optional, failing to build their renderers must not stop the terminal.
+        #[cfg(feature = "charts")]
+        let (chart_renderer, hex_bg_renderer, charts_error) = {
+            let shader_version =
+                if use_glsl3 { ShaderVersion::Glsl3 } else { ShaderVersion::Gles2 };
+            let mut charts_error = None;
+            let chart_renderer =
+                optional_renderer("chart", ChartRenderer::new(shader_version), &mut charts_error);
+            let hex_bg_renderer =
+                optional_renderer("hex_bg", HexBgRenderer::new(shader_version), &mut charts_error);
+            (chart_renderer, hex_bg_renderer, charts_error)
         };
 
         // Enable debug logging for OpenGL as well.
@@ -218,7 +279,18 @@ impl Renderer {
             }
         }
 
-        Ok(Self { text_renderer, rect_renderer, chart_renderer, hex_bg_renderer })
+        Ok(Self {
+            text_renderer,
+            rect_renderer,
+            #[cfg(feature = "charts")]
+            chart_renderer,
+            #[cfg(feature = "charts")]
+            hex_bg_renderer,
+            #[cfg(feature = "charts")]
+            charts_error,
+            #[cfg(feature = "charts")]
+            prev_scissor: Cell::new(None),
+        })
     }
 
     pub fn draw_cells<I: Iterator<Item = RenderableCell>>(
@@ -333,11 +405,61 @@ impl Renderer {
             // Reset blending strategy.
             gl::BlendFunc(gl::SRC1_COLOR, gl::ONE_MINUS_SRC1_COLOR);
 
+            // Restore the scissor state from before the chart scissor was enabled.
+            #[cfg(feature = "charts")]
+            if let Some((was_enabled, [x, y, width, height])) = self.prev_scissor.take() {
+                if was_enabled {
+                    gl::Scissor(x, y, width, height);
+                } else {
+                    gl::Disable(gl::SCISSOR_TEST);
+                }
+            }
+
             // Restore viewport with padding.
             self.set_viewport(size_info);
         }
     }
 
+    /// Whether the chart renderer is available, charts are disabled when its shader failed.
+    #[cfg(feature = "charts")]
+    pub fn charts_available(&self) -> bool {
+        self.chart_renderer.is_some()
+    }
+
+    /// The error that disabled the chart renderers, if any.
+    #[cfg(feature = "charts")]
+    pub fn charts_error(&self) -> Option<&Error> {
+        self.charts_error.as_ref()
+    }
+
+    /// Scissor the following chart draw calls to `rect`, in GL window coordinates with the origin
+    /// at the bottom left of the window, so it is not affected by the padding-less viewport. The
+    /// scissor state from before the first chart scissor is saved in `prev_scissor` for
+    /// `activate_regular_state` to restore it, None restores it right away.
+    #[cfg(feature = "charts")]
+    fn apply_chart_scissor(
+        prev_scissor: &Cell<Option<(bool, [i32; 4])>>,
+        rect: Option<(i32, i32, i32, i32)>,
+    ) {
+        unsafe {
+            match (rect, prev_scissor.get()) {
+                (Some((x, y, width, height)), saved) => {
+                    if saved.is_none() {
+                        let was_enabled = gl::IsEnabled(gl::SCISSOR_TEST) == gl::TRUE;
+                        let mut scissor_box = [0i32; 4];
+                        gl::GetIntegerv(gl::SCISSOR_BOX, scissor_box.as_mut_ptr());
+                        prev_scissor.set(Some((was_enabled, scissor_box)));
+                    }
+                    gl::Enable(gl::SCISSOR_TEST);
+                    gl::Scissor(x, y, width, height);
+                },
+                (None, Some((true, [x, y, width, height]))) => gl::Scissor(x, y, width, height),
+                (None, Some((false, _))) => gl::Disable(gl::SCISSOR_TEST),
+                (None, None) => (),
+            }
+        }
+    }
+
     /// Draw all rectangles simultaneously to prevent excessive program swaps.
     pub fn draw_rects(&mut self, size_info: &SizeInfo, metrics: &Metrics, rects: Vec<RenderRect>) {
         if rects.is_empty() {
@@ -352,6 +474,7 @@ impl Renderer {
     }
 
     /// `draw_xyzrgba_array` draws an array of triangles with properties (x,y,z,r,g,b,a)
+    #[cfg(feature = "charts")]
     pub fn draw_xyzrgba_vertices(
         &mut self,
         size_info: &SizeInfo,
@@ -369,14 +492,42 @@ impl Renderer {
         // 0.7f32, 0.3f32, 0.0f32 // x, y, z
         // 0.0f32, 0.0f32, 1.0f32, 1.0f32, // RGBA
         // ];
+        if self.hex_bg_renderer.is_none() {
+            return;
+        }
         Self::prepare_rect_rendering_state(size_info);
 
-        self.hex_bg_renderer.draw(opengl_data, mode.into(), size_info, time_secs_with_ms);
+        if let Some(hex_bg_renderer) = self.hex_bg_renderer.as_mut() {
+            hex_bg_renderer.draw(opengl_data, mode.into(), size_info, time_secs_with_ms);
+        }
+
+        self.activate_regular_state(size_info);
+    }
+
+    /// Draw the items of a frame of charts, the vertices are uploaded once per batch generation
+    /// and the state is changed once per group of consecutive items sharing mode and scissor.
+    #[cfg(feature = "charts")]
+    pub fn draw_chart_batch(&mut self, size_info: &SizeInfo, batch: &ChartDrawBatch) {
+        let chart_renderer = match self.chart_renderer.as_mut() {
+            Some(chart_renderer) if !batch.is_empty() => chart_renderer,
+            _ => return,
+        };
+
+        Self::prepare_rect_rendering_state(size_info);
+
+        chart_renderer.upload(batch);
+        let items = batch.items();
+        for group in batch.groups() {
+            let scissor = group.scissor.map(|rect| rect.to_gl_window(size_info.height()));
+            Self::apply_chart_scissor(&self.prev_scissor, scissor);
+            chart_renderer.draw_items(&items[group.items], DrawArrayMode::from(group.mode).into());
+        }
 
         self.activate_regular_state(size_info);
     }
 
     /// `draw_array` draws a vec made of 2D values in a specific mode
+    #[cfg(feature = "charts")]
     pub fn draw_array(
         &mut self,
         size_info: &SizeInfo,
@@ -385,6 +536,9 @@ impl Renderer {
         alpha: f32,
         mode: DrawArrayMode,
     ) {
+        if self.chart_renderer.is_none() {
+            return;
+        }
         match mode {
             DrawArrayMode::Points => (),
             _ =>
@@ -399,15 +553,14 @@ impl Renderer {
         for position in opengl_vecs.chunks(2) {
             opengl_data_with_color.push(position[0]);
             opengl_data_with_color.push(position[1]);
-            opengl_data_with_color.push(f32::from(color.r) / 255.);
-            opengl_data_with_color.push(f32::from(color.g) / 255.);
-            opengl_data_with_color.push(f32::from(color.b) / 255.);
-            opengl_data_with_color.push(alpha);
+            opengl_data_with_color.extend_from_slice(&color.with_alpha(alpha));
         }
 
         Self::prepare_rect_rendering_state(size_info);
 
-        self.chart_renderer.draw(&opengl_data_with_color, mode.into());
+        if let Some(chart_renderer) = self.chart_renderer.as_mut() {
+            chart_renderer.draw(&opengl_data_with_color, mode.into());
+        }
 
         self.activate_regular_state(size_info);
     }
@@ -509,3 +662,24 @@ extern "system" fn gl_debug_log(
         _ => debug!("[gl_render] {}", msg),
     }
 }
+
+#[cfg(all(test, feature = "charts"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn optional_renderers_keep_the_first_error() {
+        let mut error = None;
+        let chart: Option<u8> =
+            optional_renderer("chart", Err(Error::Other("bad chart shader".into())), &mut error);
+        assert!(chart.is_none());
+        let hex_bg: Option<u8> =
+            optional_renderer("hex_bg", Err(Error::Other("bad hex_bg shader".into())), &mut error);
+        assert!(hex_bg.is_none());
+        assert_eq!(error.map(|err| err.to_string()), Some(String::from("bad chart shader")));
+
+        let mut error = None;
+        assert_eq!(optional_renderer("chart", Ok(1u8), &mut error), Some(1));
+        assert!(error.is_none());
+    }
+}
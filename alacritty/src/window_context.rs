@@ -20,13 +20,15 @@ use winit::event_loop::{ActiveEventLoop, EventLoopProxy};
 use winit::raw_window_handle::HasDisplayHandle;
 use winit::window::WindowId;
 
+#[cfg(feature = "charts")]
+use alacritty_terminal::charts::prelude::TermChartsHandle;
 use alacritty_terminal::event::Event as TerminalEvent;
 use alacritty_terminal::event_loop::{EventLoop as PtyEventLoop, Msg, Notifier};
 use alacritty_terminal::grid::{Dimensions, Scroll};
 use alacritty_terminal::index::Direction;
 use alacritty_terminal::sync::FairMutex;
 use alacritty_terminal::term::test::TermSize;
-use alacritty_terminal::term::{Term, TermChartsHandle, TermMode};
+use alacritty_terminal::term::{Term, TermMode};
 use alacritty_terminal::tty;
 
 use crate::cli::{ParsedOptions, WindowOptions};
@@ -40,6 +42,8 @@ use crate::event::{
 #[cfg(unix)]
 use crate::logging::LOG_TARGET_IPC_CONFIG;
 use crate::message_bar::MessageBuffer;
+#[cfg(feature = "charts")]
+use crate::message_bar::{Message, MessageType};
 use crate::scheduler::Scheduler;
 use crate::{input, renderer};
 
@@ -166,7 +170,7 @@ impl WindowContext {
 
     /// Create a new terminal window context.
     fn new(
-        mut display: Display,
+        #[cfg_attr(not(feature = "charts"), allow(unused_mut))] mut display: Display,
         config: Rc<UiConfig>,
         options: WindowOptions,
         proxy: EventLoopProxy<Event>,
@@ -189,18 +193,37 @@ impl WindowContext {
         // This object contains all of the state about what's being displayed. It's
         // wrapped in a clonable mutex since both the I/O loop and display need to
         // access it.
+        #[cfg_attr(not(feature = "charts"), allow(unused_mut))]
         let mut terminal =
             Term::new(config.term_options(), &display.size_info, event_proxy.clone());
         // If tokio is enabled (i.e. charts are enabled) then capture the thread join handle
+        #[cfg(feature = "charts")]
         let mut _tokio_thread: Option<std::thread::JoinHandle<()>> = None;
+        #[cfg(feature = "charts")]
         let mut tokio_setup: Option<TermChartsHandle> = None;
+        #[cfg_attr(not(feature = "charts"), allow(unused_mut))]
+        let mut message_buffer = MessageBuffer::default();
+        #[cfg(feature = "charts")]
         if let Some(charts_config) = &config.charts {
-            let charts_config = &charts_config.config;
-            let (tokio_setup_tmp, tokio_thread_tmp) = TermChartsHandle::new_with_thread(
-                charts_config,
-                display.size_info.into(),
+            let mut charts_config = charts_config.config.clone();
+            // The decoration ticks of the coordinator follow the fastest decoration.
+            if let Some(decorations) = &config.decorations {
+                charts_config.decoration_rates = decorations.config.update_rates();
+            }
+            let (mut tokio_setup_tmp, tokio_thread_tmp) = TermChartsHandle::new_with_thread(
+                &charts_config,
+                display.charts_size_info(display.size_info),
                 event_proxy.clone(),
             );
+            // Keep the terminal usable without charts when their shaders failed to build.
+            if !display.charts_available() {
+                tokio_setup_tmp.disable_rendering();
+                if let Some(err) = display.charts_error() {
+                    let text = format!("Charts are disabled: {err}");
+                    message_buffer.push(Message::new(text, MessageType::Warning));
+                    display.pending_update.dirty = true;
+                }
+            }
             terminal.set_tokio_setup(tokio_setup_tmp.clone());
             display.set_tokio_setup(tokio_setup_tmp.clone());
             _tokio_thread = Some(tokio_thread_tmp);
@@ -226,6 +249,7 @@ impl WindowContext {
         // renderer and input processing. Note that access to the terminal state is
         // synchronized since the I/O loop updates the state, and the display
         // consumes it periodically.
+        #[cfg_attr(not(feature = "charts"), allow(unused_mut))]
         let mut event_loop = PtyEventLoop::new(
             Arc::clone(&terminal),
             event_proxy.clone(),
@@ -233,6 +257,7 @@ impl WindowContext {
             pty_config.hold,
             config.debug.ref_test,
         )?;
+        #[cfg(feature = "charts")]
         if let Some(tokio_setup) = &tokio_setup {
             event_loop.set_tokio_setup(tokio_setup.clone());
         }
@@ -262,7 +287,7 @@ impl WindowContext {
             notifier: Notifier(loop_tx),
             cursor_blink_timed_out: Default::default(),
             inline_search_state: Default::default(),
-            message_buffer: Default::default(),
+            message_buffer,
             window_config: Default::default(),
             search_state: Default::default(),
             event_queue: Default::default(),
@@ -0,0 +1,411 @@
+//! Draws the charts of a charts YAML file in a plain OpenGL window, without a terminal.
+//!
+//! Usage:
+//!   cargo run -p alacritty_terminal --example standalone_dashboard \
+//!     --features standalone-dashboard -- [path/to/charts.yml]
+//!
+//! The charts are laid out by alacritty_terminal::async_utils::dashboard, this example only
+//! contains a minimal copy of the chart renderer of alacritty.
+
+use std::error::Error;
+use std::ffi::{c_char, c_void, CString};
+use std::num::NonZeroU32;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+use std::{mem, ptr};
+
+use alacritty_terminal::charts::prelude::{
+    load_charts_config, ChannelEventSink, ChartDrawBatch, Dashboard, DrawMode, FLOATS_PER_VERTEX,
+};
+use alacritty_terminal::event::Event;
+use alacritty_terminal::term::SizeInfo;
+use glutin::config::{Config, ConfigTemplateBuilder};
+use glutin::context::{ContextApi, ContextAttributesBuilder, PossiblyCurrentContext, Version};
+use glutin::display::{Display, DisplayApiPreference};
+use glutin::prelude::*;
+use glutin::surface::{Surface, SurfaceAttributesBuilder, WindowSurface};
+use log::{error, info};
+use winit::application::ApplicationHandler;
+use winit::dpi::PhysicalSize;
+use winit::event::{StartCause, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::raw_window_handle::{HasDisplayHandle, HasWindowHandle, RawWindowHandle};
+use winit::window::{Window, WindowId};
+
+/// The time between frames, the charts are drawn at ~10 fps
+const FRAME_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The size of the window when it is opened
+const INITIAL_SIZE: PhysicalSize<u32> = PhysicalSize::new(1280, 200);
+
+// The GL constants used by the renderer.
+const COLOR_BUFFER_BIT: u32 = 0x4000;
+const BLEND: u32 = 0x0BE2;
+const SCISSOR_TEST: u32 = 0x0C11;
+const SRC_ALPHA: u32 = 0x0302;
+const ONE_MINUS_SRC_ALPHA: u32 = 0x0303;
+const VERTEX_SHADER: u32 = 0x8B31;
+const FRAGMENT_SHADER: u32 = 0x8B30;
+const COMPILE_STATUS: u32 = 0x8B81;
+const LINK_STATUS: u32 = 0x8B82;
+const ARRAY_BUFFER: u32 = 0x8892;
+const DYNAMIC_DRAW: u32 = 0x88E8;
+const FLOAT: u32 = 0x1406;
+const POINTS: u32 = 0x0000;
+const LINES: u32 = 0x0001;
+const LINE_LOOP: u32 = 0x0002;
+const LINE_STRIP: u32 = 0x0003;
+const TRIANGLES: u32 = 0x0004;
+const TRIANGLE_STRIP: u32 = 0x0005;
+
+static CHART_SHADER_V: &str = r#"#version 330 core
+layout (location = 0) in vec2 aPos;
+layout (location = 1) in vec4 aColor;
+
+flat out vec4 color;
+
+void main() {
+    color = aColor;
+    gl_Position = vec4(aPos.x, aPos.y, 0.0, 1.0);
+}
+"#;
+
+static CHART_SHADER_F: &str = r#"#version 330 core
+flat in vec4 color;
+
+out vec4 FragColor;
+
+void main() {
+    FragColor = color;
+}
+"#;
+
+/// `gl_functions` declares the GL functions used by the renderer, they are loaded from the
+/// display as the gl bindings of alacritty are generated by its build script.
+macro_rules! gl_functions {
+    ($($name:ident: fn($($arg:ty),*) $(-> $ret:ty)?;)*) => {
+        #[allow(non_snake_case)]
+        struct Gl {
+            $($name: unsafe extern "system" fn($($arg),*) $(-> $ret)?,)*
+        }
+
+        impl Gl {
+            fn load(display: &Display) -> Result<Gl, String> {
+                Ok(Gl {
+                    $($name: {
+                        let symbol = CString::new(concat!("gl", stringify!($name))).unwrap();
+                        let address = display.get_proc_address(&symbol);
+                        if address.is_null() {
+                            return Err(format!("Unable to load {:?}", symbol));
+                        }
+                        unsafe {
+                            mem::transmute::<
+                                *const c_void,
+                                unsafe extern "system" fn($($arg),*) $(-> $ret)?,
+                            >(address)
+                        }
+                    },)*
+                })
+            }
+        }
+    };
+}
+
+gl_functions! {
+    ClearColor: fn(f32, f32, f32, f32);
+    Clear: fn(u32);
+    Viewport: fn(i32, i32, i32, i32);
+    Enable: fn(u32);
+    Disable: fn(u32);
+    BlendFunc: fn(u32, u32);
+    Scissor: fn(i32, i32, i32, i32);
+    CreateShader: fn(u32) -> u32;
+    ShaderSource: fn(u32, i32, *const *const c_char, *const i32);
+    CompileShader: fn(u32);
+    GetShaderiv: fn(u32, u32, *mut i32);
+    DeleteShader: fn(u32);
+    CreateProgram: fn() -> u32;
+    AttachShader: fn(u32, u32);
+    LinkProgram: fn(u32);
+    GetProgramiv: fn(u32, u32, *mut i32);
+    UseProgram: fn(u32);
+    GenVertexArrays: fn(i32, *mut u32);
+    BindVertexArray: fn(u32);
+    GenBuffers: fn(i32, *mut u32);
+    BindBuffer: fn(u32, u32);
+    BufferData: fn(u32, isize, *const c_void, u32);
+    VertexAttribPointer: fn(u32, i32, u32, u8, i32, *const c_void);
+    EnableVertexAttribArray: fn(u32);
+    DrawArrays: fn(u32, i32, i32);
+}
+
+/// `gl_mode` returns the GL primitive of a draw mode
+fn gl_mode(mode: DrawMode) -> u32 {
+    match mode {
+        DrawMode::Points => POINTS,
+        DrawMode::LineStrip => LINE_STRIP,
+        DrawMode::LineLoop => LINE_LOOP,
+        DrawMode::Lines => LINES,
+        DrawMode::Triangles => TRIANGLES,
+        DrawMode::TriangleStrip => TRIANGLE_STRIP,
+    }
+}
+
+/// `ChartRenderer` is a minimal copy of the chart renderer of alacritty, the vertices of a
+/// batch are uploaded once per generation and each item is drawn as a sub-range of them.
+struct ChartRenderer {
+    gl: Gl,
+    program: u32,
+    vao: u32,
+    vbo: u32,
+    uploaded_generation: Option<u64>,
+}
+
+impl ChartRenderer {
+    fn new(gl: Gl) -> Result<Self, String> {
+        let program = unsafe { link_program(&gl)? };
+        let (mut vao, mut vbo) = (0u32, 0u32);
+        unsafe {
+            (gl.GenVertexArrays)(1, &mut vao);
+            (gl.GenBuffers)(1, &mut vbo);
+            (gl.BindVertexArray)(vao);
+            (gl.BindBuffer)(ARRAY_BUFFER, vbo);
+            let stride = (mem::size_of::<f32>() * FLOATS_PER_VERTEX) as i32;
+            // Position: x, y.
+            (gl.VertexAttribPointer)(0, 2, FLOAT, 0, stride, ptr::null());
+            (gl.EnableVertexAttribArray)(0);
+            // Color: r, g, b, a, after the position.
+            let color_offset = mem::size_of::<f32>() * 2;
+            (gl.VertexAttribPointer)(1, 4, FLOAT, 0, stride, color_offset as *const c_void);
+            (gl.EnableVertexAttribArray)(1);
+            (gl.BindVertexArray)(0);
+            (gl.BindBuffer)(ARRAY_BUFFER, 0);
+        }
+        Ok(Self { gl, program, vao, vbo, uploaded_generation: None })
+    }
+
+    fn draw(&mut self, size_info: &SizeInfo, batch: &ChartDrawBatch) {
+        let gl = &self.gl;
+        unsafe {
+            (gl.Viewport)(0, 0, size_info.width as i32, size_info.height as i32);
+            (gl.ClearColor)(0., 0., 0., 1.);
+            (gl.Clear)(COLOR_BUFFER_BIT);
+            if batch.is_empty() {
+                return;
+            }
+            (gl.Enable)(BLEND);
+            (gl.BlendFunc)(SRC_ALPHA, ONE_MINUS_SRC_ALPHA);
+            (gl.BindVertexArray)(self.vao);
+            (gl.BindBuffer)(ARRAY_BUFFER, self.vbo);
+            if self.uploaded_generation != Some(batch.generation()) {
+                let vertices = batch.vertices();
+                let size = mem::size_of_val(vertices) as isize;
+                (gl.BufferData)(ARRAY_BUFFER, size, vertices.as_ptr() as *const _, DYNAMIC_DRAW);
+                self.uploaded_generation = Some(batch.generation());
+            }
+            (gl.UseProgram)(self.program);
+            let items = batch.items();
+            for group in batch.groups() {
+                match group.scissor {
+                    Some(rect) => {
+                        let (x, y, width, height) = rect.to_gl_window(size_info.height);
+                        (gl.Enable)(SCISSOR_TEST);
+                        (gl.Scissor)(x, y, width, height);
+                    },
+                    None => (gl.Disable)(SCISSOR_TEST),
+                }
+                for item in &items[group.items] {
+                    (gl.DrawArrays)(gl_mode(group.mode), item.first as i32, item.count as i32);
+                }
+            }
+            (gl.Disable)(SCISSOR_TEST);
+            (gl.UseProgram)(0);
+            (gl.BindBuffer)(ARRAY_BUFFER, 0);
+            (gl.BindVertexArray)(0);
+        }
+    }
+}
+
+/// `compile_shader` compiles the source of a shader of type `kind`
+unsafe fn compile_shader(gl: &Gl, kind: u32, source: &str) -> Result<u32, String> {
+    let shader = (gl.CreateShader)(kind);
+    let source = CString::new(source).map_err(|err| err.to_string())?;
+    (gl.ShaderSource)(shader, 1, &source.as_ptr(), ptr::null());
+    (gl.CompileShader)(shader);
+    let mut success = 0;
+    (gl.GetShaderiv)(shader, COMPILE_STATUS, &mut success);
+    if success == 0 {
+        (gl.DeleteShader)(shader);
+        return Err(format!("Unable to compile shader of type {:#x}", kind));
+    }
+    Ok(shader)
+}
+
+/// `link_program` links the chart shaders into a program
+unsafe fn link_program(gl: &Gl) -> Result<u32, String> {
+    let vertex = compile_shader(gl, VERTEX_SHADER, CHART_SHADER_V)?;
+    let fragment = compile_shader(gl, FRAGMENT_SHADER, CHART_SHADER_F)?;
+    let program = (gl.CreateProgram)();
+    (gl.AttachShader)(program, vertex);
+    (gl.AttachShader)(program, fragment);
+    (gl.LinkProgram)(program);
+    (gl.DeleteShader)(vertex);
+    (gl.DeleteShader)(fragment);
+    let mut success = 0;
+    (gl.GetProgramiv)(program, LINK_STATUS, &mut success);
+    if success == 0 {
+        return Err(String::from("Unable to link the chart shaders"));
+    }
+    Ok(program)
+}
+
+/// `size_info` returns the size of a window of `size` pixels, the charts only use the window
+/// size and the device pixel ratio.
+fn size_info(size: PhysicalSize<u32>, scale_factor: f64) -> SizeInfo {
+    SizeInfo {
+        width: size.width as f32,
+        height: size.height as f32,
+        dpr: scale_factor,
+        ..SizeInfo::default()
+    }
+}
+
+/// `GlWindow` is the window along with the GL objects drawing into it
+struct GlWindow {
+    window: Window,
+    surface: Surface<WindowSurface>,
+    context: PossiblyCurrentContext,
+    renderer: ChartRenderer,
+}
+
+impl GlWindow {
+    fn new(event_loop: &ActiveEventLoop) -> Result<Self, Box<dyn Error>> {
+        let attributes = Window::default_attributes()
+            .with_title("Chartacritty dashboard")
+            .with_inner_size(INITIAL_SIZE);
+        let window = event_loop.create_window(attributes)?;
+        let raw_display_handle = window.display_handle()?.as_raw();
+        let raw_window_handle = window.window_handle()?.as_raw();
+        let display = create_gl_display(raw_display_handle, raw_window_handle)?;
+        let template =
+            ConfigTemplateBuilder::new().compatible_with_native_window(raw_window_handle).build();
+        let config: Config = unsafe { display.find_configs(template)? }
+            .next()
+            .ok_or("Unable to find a GL config")?;
+        let context_attributes = ContextAttributesBuilder::new()
+            .with_context_api(ContextApi::OpenGl(Some(Version::new(3, 3))))
+            .build(Some(raw_window_handle));
+        let context = unsafe { display.create_context(&config, &context_attributes)? };
+        let size = window.inner_size();
+        let surface_attributes = SurfaceAttributesBuilder::<WindowSurface>::new().build(
+            raw_window_handle,
+            NonZeroU32::new(size.width.max(1)).unwrap(),
+            NonZeroU32::new(size.height.max(1)).unwrap(),
+        );
+        let surface = unsafe { display.create_window_surface(&config, &surface_attributes)? };
+        let context = context.make_current(&surface)?;
+        let renderer = ChartRenderer::new(Gl::load(&display)?)?;
+        Ok(Self { window, surface, context, renderer })
+    }
+}
+
+/// `create_gl_display` creates the GL display with the preferred API of the platform
+fn create_gl_display(
+    raw_display_handle: winit::raw_window_handle::RawDisplayHandle,
+    _raw_window_handle: RawWindowHandle,
+) -> glutin::error::Result<Display> {
+    #[cfg(target_os = "macos")]
+    let preference = DisplayApiPreference::Cgl;
+
+    #[cfg(windows)]
+    let preference = DisplayApiPreference::WglThenEgl(Some(_raw_window_handle));
+
+    #[cfg(not(any(target_os = "macos", windows)))]
+    let preference = DisplayApiPreference::Egl;
+
+    unsafe { Display::new(raw_display_handle, preference) }
+}
+
+/// `App` draws the dashboard on every frame interval
+struct App {
+    dashboard: Dashboard,
+    events: std::sync::mpsc::Receiver<Event>,
+    gl_window: Option<GlWindow>,
+}
+
+impl ApplicationHandler for App {
+    fn new_events(&mut self, event_loop: &ActiveEventLoop, cause: StartCause) {
+        if let StartCause::ResumeTimeReached { .. } = cause {
+            // The coordinator events only tell that the charts changed, each frame asks for the
+            // generations anyway.
+            for _ in self.events.try_iter() {}
+            if let Some(gl_window) = &self.gl_window {
+                gl_window.window.request_redraw();
+            }
+        }
+        event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + FRAME_INTERVAL));
+    }
+
+    fn resumed(&mut self, event_loop: &ActiveEventLoop) {
+        if self.gl_window.is_some() {
+            return;
+        }
+        match GlWindow::new(event_loop) {
+            Ok(gl_window) => {
+                let size = gl_window.window.inner_size();
+                self.dashboard.resize(size_info(size, gl_window.window.scale_factor()));
+                self.gl_window = Some(gl_window);
+            },
+            Err(err) => {
+                error!("Unable to create the window: {}", err);
+                event_loop.exit();
+            },
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, _id: WindowId, event: WindowEvent) {
+        let gl_window = match self.gl_window.as_mut() {
+            Some(gl_window) => gl_window,
+            None => return,
+        };
+        match event {
+            WindowEvent::CloseRequested => event_loop.exit(),
+            WindowEvent::Resized(size) if size.width > 0 && size.height > 0 => {
+                gl_window.surface.resize(
+                    &gl_window.context,
+                    NonZeroU32::new(size.width).unwrap(),
+                    NonZeroU32::new(size.height).unwrap(),
+                );
+                // The charts are laid out again through the ChangeDisplaySize path.
+                self.dashboard.resize(size_info(size, gl_window.window.scale_factor()));
+                gl_window.window.request_redraw();
+            },
+            WindowEvent::RedrawRequested => {
+                let size_info = self.dashboard.size_info;
+                gl_window.renderer.draw(&size_info, self.dashboard.frame());
+                if let Err(err) = gl_window.surface.swap_buffers(&gl_window.context) {
+                    error!("Unable to swap buffers: {}", err);
+                }
+            },
+            _ => (),
+        }
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    env_logger::init();
+    let path = std::env::args()
+        .nth(1)
+        .map(PathBuf::from)
+        .unwrap_or_else(|| PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/charts.yml")));
+    let charts_config = load_charts_config(&path)?;
+    info!("Loaded {} charts from {}", charts_config.charts.len(), path.display());
+    let (event_tx, events) = std::sync::mpsc::channel();
+    let size_info = size_info(INITIAL_SIZE, 1.);
+    let dashboard = Dashboard::start(charts_config, size_info, ChannelEventSink(event_tx));
+    let event_loop = EventLoop::new()?;
+    event_loop.set_control_flow(ControlFlow::WaitUntil(Instant::now() + FRAME_INTERVAL));
+    let mut app = App { dashboard, events, gl_window: None };
+    event_loop.run_app(&mut app)?;
+    Ok(())
+}
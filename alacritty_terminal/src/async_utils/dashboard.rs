@@ -0,0 +1,325 @@
+//! `dashboard` drives the charts without a terminal: it loads a charts config, starts the
+//! async_coordinator and lays out the vertices of every frame in a ChartDrawBatch that a
+//! renderer uploads at once. It is used by the standalone_dashboard example and the draw
+//! batch of a chart is built the same way by the display of the terminal.
+
+use super::{block_on_reply, AsyncTask, ChartsVertexCache, WindowId};
+use crate::charts::config::Config;
+use crate::charts::draw_batch::{ChartDrawBatch, DrawLayer, DrawMode};
+use crate::charts::{ChartIndex, ChartsConfig, DecorationIndex, SeriesIndex};
+use crate::event::{Event, EventListener};
+use crate::term::{SizeInfo, TermChartsHandle};
+use log::*;
+use std::path::Path;
+use std::thread;
+use tokio::sync::{mpsc, oneshot};
+
+/// `load_charts_config` reads the charts of a YAML file, the file has the format of charts.yml
+pub fn load_charts_config(path: &Path) -> Result<ChartsConfig, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+    let config: Config = serde_yaml::from_str(&contents)
+        .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?;
+    config.charts.ok_or_else(|| format!("No charts found in {}", path.display()))
+}
+
+/// `ChannelEventSink` forwards the events of the async_coordinator to a channel, i.e. so that
+/// a window redraws when the charts change
+#[derive(Debug, Clone)]
+pub struct ChannelEventSink(pub std::sync::mpsc::Sender<Event>);
+
+impl EventListener for ChannelEventSink {
+    fn send_event(&self, event: Event) {
+        // The receiver is gone once the window is closed.
+        let _ = self.0.send(event);
+    }
+}
+
+/// `push_chart_draw_items` adds the decorations and series of a chart to the draw batch, they
+/// are kept inside of the chart area, including their anti-aliased edges, unless the chart may
+/// overflow. The series split into small multiples are kept inside of their cell. The vertices
+/// generated for a previous size of the window are not drawn, they would be stretched.
+#[allow(clippy::too_many_arguments)]
+pub fn push_chart_draw_items(
+    batch: &mut ChartDrawBatch,
+    cache: &mut ChartsVertexCache,
+    chart_config: &ChartsConfig,
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) {
+    let scissor =
+        cache.scissor_rect(charts_tx.clone(), window_id, chart_index, tokio_handle.clone());
+    let size_generation = cache.size_generation();
+    let decorations = &chart_config.charts[chart_index.0].decorations;
+    for (decoration_idx, decoration) in decorations.iter().enumerate() {
+        let (vecs, alpha, vecs_generation) = cache.decoration_data(
+            charts_tx.clone(),
+            window_id,
+            chart_index,
+            DecorationIndex(decoration_idx),
+            tokio_handle.clone(),
+        );
+        if *vecs_generation != size_generation {
+            debug!("push_chart_draw_items:(Chart: {}) Stale decoration vertices", chart_index);
+            continue;
+        }
+        batch.push(
+            chart_index,
+            DrawLayer::Decoration,
+            decoration.draw_mode(),
+            scissor,
+            vecs,
+            decoration.color(),
+            *alpha,
+        );
+    }
+    // The coordinator may have discovered series that are not in the config.
+    let series_styles =
+        cache.series_styles(charts_tx.clone(), chart_index, tokio_handle.clone()).to_vec();
+    let cell_rects =
+        cache.cell_rects(charts_tx.clone(), window_id, chart_index, tokio_handle.clone()).to_vec();
+    for (series_idx, style) in series_styles.into_iter().enumerate() {
+        let (vecs, alpha, vecs_generation) = cache.series_data(
+            charts_tx.clone(),
+            window_id,
+            chart_index,
+            SeriesIndex(series_idx),
+            tokio_handle.clone(),
+        );
+        if *vecs_generation != size_generation {
+            debug!("push_chart_draw_items:(Chart: {}) Stale series vertices", chart_index);
+            continue;
+        }
+        let series_scissor = cell_rects.get(series_idx).copied().or(scissor);
+        // The timeline bands carry the color of each run.
+        if style.timeline {
+            let mode = style.draw_mode();
+            batch.push_colored(chart_index, DrawLayer::Series, mode, series_scissor, vecs, *alpha);
+            continue;
+        }
+        // The smooth series carry the fade of each vertex.
+        let push = if style.smooth { ChartDrawBatch::push_faded } else { ChartDrawBatch::push };
+        push(
+            batch,
+            chart_index,
+            DrawLayer::Series,
+            style.draw_mode(),
+            series_scissor,
+            vecs,
+            style.color,
+            *alpha,
+        );
+        if !style.live_edge {
+            continue;
+        }
+        // The second in progress is a separate dimmed segment after the series.
+        let (vecs, alpha, vecs_generation) = cache.live_edge_data(
+            charts_tx.clone(),
+            window_id,
+            chart_index,
+            SeriesIndex(series_idx),
+            tokio_handle.clone(),
+        );
+        if *vecs_generation == size_generation {
+            batch.push(
+                chart_index,
+                DrawLayer::Series,
+                DrawMode::LineStrip,
+                series_scissor,
+                vecs,
+                style.color,
+                *alpha,
+            );
+        }
+    }
+}
+
+/// `Dashboard` is a set of charts drawn in a plain window, without a terminal
+pub struct Dashboard {
+    /// The charts being drawn
+    pub charts_config: ChartsConfig,
+
+    /// The connection to the async_coordinator
+    pub handle: TermChartsHandle,
+
+    /// The size of the window the charts are drawn in
+    pub size_info: SizeInfo,
+
+    /// The background thread running the tokio runtime, joined on drop
+    tokio_thread: Option<thread::JoinHandle<()>>,
+
+    /// The vertices of the charts, they are requested again when their generation changes
+    cache: ChartsVertexCache,
+
+    /// The vertices of the latest frame
+    batch: ChartDrawBatch,
+}
+
+impl Dashboard {
+    /// `start` spawns the async_coordinator and the polls of the series of the charts
+    pub fn start<T>(charts_config: ChartsConfig, size_info: SizeInfo, event_proxy: T) -> Self
+    where
+        T: EventListener + Send + 'static,
+    {
+        let (handle, tokio_thread) =
+            TermChartsHandle::new_with_thread(&charts_config, size_info, event_proxy);
+        Self {
+            charts_config,
+            handle,
+            size_info,
+            tokio_thread: Some(tokio_thread),
+            cache: ChartsVertexCache::default(),
+            batch: ChartDrawBatch::default(),
+        }
+    }
+
+    /// `resize` lays out the charts for a new window size, the charts are regenerated before
+    /// this returns so the next frame is drawn with the new size.
+    pub fn resize(&mut self, size_info: SizeInfo) {
+        self.size_info = size_info;
+        let (size_tx, size_rx) = oneshot::channel();
+        let request = AsyncTask::ChangeDisplaySize(
+            WindowId::default(),
+            size_info.height,
+            size_info.width,
+            size_info.padding_y,
+            size_info.padding_x,
+            size_info.cell_width,
+            size_info.cell_height,
+            size_tx,
+        );
+        if let Err(err) = self.handle.charts_tx.try_send(request) {
+            error!("Dashboard::resize: Unable to send ChangeDisplaySize: {:?}", err);
+            return;
+        }
+        self.cache.resize();
+        if let Err(err) = block_on_reply(&self.handle.tokio_handle, size_rx) {
+            error!("Dashboard::resize: No response from ChangeDisplaySize: {:?}", err);
+        }
+    }
+
+    /// `frame` returns the vertices of the charts to draw, the batch is only rebuilt when the
+    /// vertices of a chart changed, its generation tells the renderer when to upload it again.
+    pub fn frame(&mut self) -> &ChartDrawBatch {
+        let charts_tx = self.handle.charts_tx.clone();
+        let tokio_handle = self.handle.tokio_handle.clone();
+        if self.cache.refresh(charts_tx.clone(), tokio_handle.clone()) == 0 {
+            return &self.batch;
+        }
+        self.batch.clear();
+        for chart_idx in 0..self.charts_config.charts.len() {
+            push_chart_draw_items(
+                &mut self.batch,
+                &mut self.cache,
+                &self.charts_config,
+                charts_tx.clone(),
+                WindowId::default(),
+                ChartIndex(chart_idx),
+                tokio_handle.clone(),
+            );
+        }
+        &self.batch
+    }
+}
+
+impl Drop for Dashboard {
+    fn drop(&mut self) {
+        if let Err(err) = self.handle.charts_tx.try_send(AsyncTask::Shutdown) {
+            error!("Dashboard: Unable to send shutdown signal to tokio runtime: {:?}", err);
+            return;
+        }
+        if let Some(tokio_thread) = self.tokio_thread.take() {
+            if tokio_thread.join().is_err() {
+                error!("Dashboard: The tokio runtime thread panicked");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{Read, Write};
+    use std::net::TcpListener;
+    use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+    /// `serve_fixture` answers every request with a query_range response of the last seconds
+    fn serve_fixture() -> std::net::SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut request = [0u8; 4096];
+                let _ = stream.read(&mut request);
+                let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+                let values: Vec<String> =
+                    (0..5).rev().map(|ago| format!("[{},\"{}\"]", now - ago, ago + 1)).collect();
+                let body = format!(
+                    r#"{{"status":"success","data":{{"resultType":"matrix","result":[{{"metric":{{"__name__":"up"}},"values":[{}]}}]}}}}"#,
+                    values.join(",")
+                );
+                let _ = write!(
+                    stream,
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: \
+                     {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn it_draws_a_dashboard_without_a_terminal() {
+        let addr = serve_fixture();
+        let yaml = format!(
+            r#"
+charts:
+  position:
+    x: 0
+    y: 0
+  default_dimensions:
+    x: 100
+    y: 25
+  spacing: 10
+  charts:
+  - name: up
+    series:
+    - name: up
+      type: prometheus
+      pull_interval: 1
+      source: 'http://{}/api/v1/query_range?query=up'
+      color: "0x00ff00"
+      alpha: 1.0
+"#,
+            addr
+        );
+        let path =
+            std::env::temp_dir().join(format!("chartacritty-dashboard-{}.yml", std::process::id()));
+        std::fs::write(&path, yaml).unwrap();
+        let charts_config = load_charts_config(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(charts_config.charts.len(), 1);
+        assert!(load_charts_config(&path).is_err());
+
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let size_info = SizeInfo { width: 400., height: 200., ..SizeInfo::default() };
+        let mut dashboard = Dashboard::start(charts_config, size_info, ChannelEventSink(event_tx));
+        dashboard.resize(SizeInfo { width: 800., height: 400., ..size_info });
+        // The first data tick loads the fixture and the series is drawn.
+        let started = Instant::now();
+        loop {
+            let batch = dashboard.frame();
+            if batch.items().iter().any(|item| item.layer == DrawLayer::Series) {
+                break;
+            }
+            assert!(started.elapsed() < Duration::from_secs(10), "No series drawn");
+            thread::sleep(Duration::from_millis(50));
+        }
+        // The window is asked to redraw when the data arrives.
+        assert!(event_rx.try_iter().any(|event| matches!(event, Event::ChartEvent)));
+    }
+}
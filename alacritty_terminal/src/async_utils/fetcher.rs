@@ -0,0 +1,695 @@
+//! `fetcher` polls the data sources of the charts. A source only tells how to fetch its data, the
+//! polling loop owns the interval, the timeout, the quick retries of the first poll, the backoff
+//! after failures, the pause and the counters of the source, and sends each result to the
+//! coordinator. The simple sources fetch samples that are sent as LoadSamples, the Prometheus
+//! series keep their LoadResponse as their responses are filtered by labels when loaded.
+
+use crate::async_utils::{
+    get_series_capacity, startup_jitter, subscribe_window_changes, window_changed, AsyncTask,
+    FetchLimiter, MetricRequest, StartupRetries,
+};
+use crate::charts::{prometheus, ChartIndex, SeriesIndex};
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{mpsc, watch};
+use tokio::time::{self, interval_at};
+use tracing::{event, span, Instrument, Level};
+
+/// A sample of a series, its epoch and its value, None when it is missing
+pub type Sample = (u64, Option<f64>);
+
+/// `FetchFuture` is the future of a fetch, boxed so that the sources can be polled by the same
+/// loop
+pub type FetchFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, FetchError>> + Send + 'a>>;
+
+/// `SourceIdentity` is the series of a chart that a source fills
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceIdentity {
+    pub chart_index: ChartIndex,
+
+    pub series_index: SeriesIndex,
+
+    /// The name of the source for the logs, i.e. its URL
+    pub name: String,
+}
+
+impl fmt::Display for SourceIdentity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} (Chart: {}, Series: {})", self.name, self.chart_index, self.series_index)
+    }
+}
+
+/// `FetchContext` is what the polling loop tells a source about a fetch
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FetchContext {
+    /// The capacity of the series in the coordinator, only for the sources whose fetch depends
+    /// on the window of the chart
+    pub capacity: Option<usize>,
+
+    /// The time the fetch may take, the loop abandons it afterwards
+    pub timeout: Duration,
+
+    /// The quick retries of the first poll before this fetch
+    pub attempt: u32,
+}
+
+/// `FetchError` is the reason a source returned no data
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FetchError {
+    /// The fetch did not finish before the timeout
+    Timeout(Duration),
+    /// The source failed, with the reason
+    Failed(String),
+}
+
+impl FetchError {
+    pub fn is_timeout(&self) -> bool {
+        matches!(self, FetchError::Timeout(_))
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FetchError::Timeout(timeout) => write!(f, "timed out after {:?}", timeout),
+            FetchError::Failed(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+/// `FetchSource` is a data source polled by `poll_source`, a new source type only implements
+/// how its data is fetched and sent to the coordinator
+pub trait FetchSource: Send + Sync {
+    /// The data of a fetch, the samples for the simple sources
+    type Payload: Send;
+
+    /// `fetch` gets the data of the source once
+    fn fetch<'a>(&'a self, ctx: &'a FetchContext) -> FetchFuture<'a, Self::Payload>;
+
+    /// `interval` is the time between the polls
+    fn interval(&self) -> Duration;
+
+    /// `identity` is the series of a chart the data is loaded into
+    fn identity(&self) -> SourceIdentity;
+
+    /// `window_capacity` returns the capacity of the series when the fetch depends on the window
+    /// of the chart, i.e. the time range of a query_range. The loop then refreshes the capacity
+    /// from the coordinator before each fetch and fetches right away when the window grows.
+    fn window_capacity(&self) -> Option<usize> {
+        None
+    }
+
+    /// `load_task` returns the task that sends the result of a fetch to the coordinator, the
+    /// failures are sent too
+    fn load_task(&self, ctx: &FetchContext, result: Result<Self::Payload, FetchError>)
+        -> AsyncTask;
+}
+
+/// `samples_task` returns the task that loads the samples of a simple source
+pub fn samples_task(
+    identity: SourceIdentity,
+    result: Result<Vec<Sample>, FetchError>,
+) -> AsyncTask {
+    AsyncTask::LoadSamples { identity, samples: result }
+}
+
+/// `FetchCounters` count the fetches of a source, they are shared with whoever reports them
+#[derive(Debug, Default)]
+pub struct FetchCounters {
+    fetches: AtomicU64,
+    failures: AtomicU64,
+    timeouts: AtomicU64,
+    retries: AtomicU64,
+}
+
+/// `FetchCounts` are the FetchCounters at a point in time
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct FetchCounts {
+    /// The fetches started
+    pub fetches: u64,
+
+    /// The fetches that failed, the timeouts included
+    pub failures: u64,
+
+    /// The fetches that did not finish before the timeout
+    pub timeouts: u64,
+
+    /// The quick retries of the first poll
+    pub retries: u64,
+}
+
+impl FetchCounters {
+    pub fn counts(&self) -> FetchCounts {
+        FetchCounts {
+            fetches: self.fetches.load(Ordering::Relaxed),
+            failures: self.failures.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            retries: self.retries.load(Ordering::Relaxed),
+        }
+    }
+
+    fn record(&self, result: Result<(), &FetchError>) {
+        self.fetches.fetch_add(1, Ordering::Relaxed);
+        if let Err(err) = result {
+            self.failures.fetch_add(1, Ordering::Relaxed);
+            if err.is_timeout() {
+                self.timeouts.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// `PollOptions` are the settings of `poll_source` shared by the source types
+#[derive(Debug, Clone)]
+pub struct PollOptions {
+    pub startup_retries: StartupRetries,
+
+    /// The permits of the fetches in flight across all the sources
+    pub limiter: FetchLimiter,
+
+    /// The time a fetch may take, defaults to the interval of the source
+    pub timeout: Option<Duration>,
+
+    /// The most intervals skipped after consecutive failed polls, the skipped intervals double
+    /// with each failure up to it. With 0 a failed source is polled on every interval.
+    pub max_backoff_intervals: u32,
+
+    /// The polls are skipped while it is true
+    pub paused: Option<watch::Receiver<bool>>,
+
+    pub counters: Arc<FetchCounters>,
+}
+
+impl PollOptions {
+    /// `new` polls on every interval without a pause
+    pub fn new(startup_retries: StartupRetries, limiter: FetchLimiter) -> PollOptions {
+        PollOptions {
+            startup_retries,
+            limiter,
+            timeout: None,
+            max_backoff_intervals: 0,
+            paused: None,
+            counters: Arc::default(),
+        }
+    }
+}
+
+/// `backoff_intervals` returns the intervals skipped after `failures` consecutive failed polls,
+/// 1, 2, 4... up to `max`
+pub fn backoff_intervals(failures: u32, max: u32) -> u32 {
+    match failures {
+        0 => 0,
+        failures => 2u32.saturating_pow(failures - 1).min(max),
+    }
+}
+
+/// `poll_source` fetches a source on every interval, the first poll is retried after the
+/// `startup_retries` delay until it succeeds or the retries are exhausted, then the polls
+/// continue one interval after the last attempt. The first tick is delayed by the startup_jitter,
+/// every fetch waits for a permit of the limiter and is abandoned after the timeout. The polls
+/// stop when the coordinator is gone, the task of a source is cancelled by aborting it.
+pub async fn poll_source<S: FetchSource>(
+    source: &S,
+    options: PollOptions,
+    tx: mpsc::Sender<AsyncTask>,
+) -> Result<(), ()> {
+    let identity = source.identity();
+    let period = source.interval();
+    let timeout = options.timeout.unwrap_or(period);
+    let start = time::Instant::now() + startup_jitter(period, &mut rand::thread_rng());
+    let mut interval = interval_at(start, period);
+    let mut capacity = source.window_capacity();
+    let mut window_rx = if capacity.is_some() { subscribe_window_changes(&tx).await } else { None };
+    // Only the first poll of the source is retried quickly, later failures back off.
+    let mut retries_left = options.startup_retries.retries;
+    let mut failures = 0u32;
+    let mut skipped_intervals = 0u32;
+    loop {
+        let backfill = tokio::select! {
+            _ = interval.tick() => false,
+            changed = window_changed(&mut window_rx) => changed,
+        };
+        if backfill {
+            let current_capacity =
+                get_series_capacity(&tx, identity.chart_index, identity.series_index).await;
+            match (capacity, current_capacity) {
+                (Some(capacity), Some(current_capacity)) if current_capacity > capacity => {
+                    event!(Level::DEBUG, capacity, current_capacity, "Window grew, backfilling");
+                },
+                _ => continue,
+            }
+        } else if skipped_intervals > 0 {
+            skipped_intervals -= 1;
+            continue;
+        }
+        if options.paused.as_ref().map_or(false, |paused| *paused.borrow()) {
+            continue;
+        }
+        let mut attempt = 0u32;
+        let loaded = loop {
+            if capacity.is_some() {
+                if let Some(current_capacity) =
+                    get_series_capacity(&tx, identity.chart_index, identity.series_index).await
+                {
+                    capacity = Some(current_capacity);
+                }
+            }
+            let ctx = FetchContext { capacity, timeout, attempt };
+            event!(Level::DEBUG, ?capacity, "Interval triggered");
+            let result = {
+                let _permit = options.limiter.acquire().await.ok_or(())?;
+                match time::timeout(timeout, source.fetch(&ctx)).await {
+                    Ok(result) => result,
+                    Err(_elapsed) => Err(FetchError::Timeout(timeout)),
+                }
+            };
+            options.counters.record(result.as_ref().map(|_| ()));
+            let loaded = result.is_ok();
+            if let Err(err) = &result {
+                let counts = options.counters.counts();
+                event!(Level::DEBUG, source = %identity, error = %err, ?counts, "Fetch failed");
+            }
+            if tx.send(source.load_task(&ctx, result)).await.is_err() {
+                event!(Level::DEBUG, source = %identity, "The coordinator is gone, polls stopped");
+                return Ok(());
+            }
+            if loaded || retries_left == 0 {
+                break loaded;
+            }
+            retries_left -= 1;
+            attempt += 1;
+            options.counters.retries.fetch_add(1, Ordering::Relaxed);
+            event!(Level::INFO, retries_left, "First poll failed, retrying");
+            time::sleep(options.startup_retries.delay).await;
+            // The regular polls resume one interval after the last quick retry.
+            interval.reset();
+        };
+        retries_left = 0;
+        failures = if loaded { 0 } else { failures.saturating_add(1) };
+        skipped_intervals = backoff_intervals(failures, options.max_backoff_intervals);
+    }
+}
+
+/// `PrometheusSource` fetches a Prometheus series. Its responses are sent whole as a
+/// LoadResponse, the coordinator filters them by the labels of the series when they are loaded.
+#[derive(Debug, Clone)]
+pub(crate) struct PrometheusSource {
+    pub request: MetricRequest,
+}
+
+impl PrometheusSource {
+    pub fn new(request: MetricRequest) -> PrometheusSource {
+        PrometheusSource { request }
+    }
+
+    /// `fetch_response` gets a response of the series, the fetch duration, body size and result
+    /// are recorded as fields of the events of its span
+    async fn fetch_response(
+        &self,
+        ctx: &FetchContext,
+    ) -> Result<Option<prometheus::HTTPResponse>, FetchError> {
+        let capacity = ctx.capacity.unwrap_or(self.request.capacity);
+        let url = prometheus::PrometheusTimeSeries::prepare_url(
+            &self.request.source_url,
+            capacity as u64,
+        )
+        .map_err(FetchError::Failed)?;
+        let started = std::time::Instant::now();
+        let max_body_bytes = Some(self.request.max_body_bytes);
+        let res = prometheus::get_from_prometheus(url, Some(ctx.timeout), max_body_bytes).await;
+        let duration_ms = started.elapsed().as_millis() as u64;
+        match res {
+            Err((uri, error)) => {
+                let result = if error.is_timeout() { "timeout" } else { "error" };
+                event!(
+                    Level::INFO,
+                    duration_ms,
+                    result,
+                    url = %uri,
+                    error = ?error,
+                    "fetch failed"
+                );
+                // The HTTP client may time out on its own, it is counted as a timeout too.
+                match error {
+                    error if error.is_timeout() => Err(FetchError::Timeout(ctx.timeout)),
+                    error => Err(FetchError::Failed(format!("{:?}", error))),
+                }
+            },
+            Ok(body) => {
+                event!(
+                    Level::DEBUG,
+                    duration_ms,
+                    bytes = body.len(),
+                    result = "ok",
+                    "fetch finished"
+                );
+                event!(Level::TRACE, raw = ?body, "fetch body");
+                // Only the parsed response is sent, the body is consumed by the parse.
+                Ok(prometheus::parse_body(self.request.source_url.clone(), body).await)
+            },
+        }
+    }
+}
+
+impl FetchSource for PrometheusSource {
+    type Payload = Option<prometheus::HTTPResponse>;
+
+    fn fetch<'a>(&'a self, ctx: &'a FetchContext) -> FetchFuture<'a, Self::Payload> {
+        let span = span!(
+            Level::DEBUG,
+            "fetch_prometheus_response",
+            chart_index = self.request.chart_index.0,
+            series_index = self.request.series_index.0,
+            capacity = ctx.capacity.unwrap_or(self.request.capacity),
+        );
+        Box::pin(self.fetch_response(ctx).instrument(span))
+    }
+
+    fn interval(&self) -> Duration {
+        Duration::from_secs(self.request.pull_interval)
+    }
+
+    fn identity(&self) -> SourceIdentity {
+        SourceIdentity {
+            chart_index: self.request.chart_index,
+            series_index: self.request.series_index,
+            name: self.request.source_url.clone(),
+        }
+    }
+
+    fn window_capacity(&self) -> Option<usize> {
+        // The capacity decides the start of the time range, only query_range requests use it.
+        let query_range = self.request.source_url.contains("/api/v1/query_range?");
+        query_range.then_some(self.request.capacity)
+    }
+
+    fn load_task(
+        &self,
+        ctx: &FetchContext,
+        result: Result<Self::Payload, FetchError>,
+    ) -> AsyncTask {
+        // The coordinator keeps the failures for the debug overlay, the series is unchanged.
+        AsyncTask::LoadResponse(MetricRequest {
+            data: result.ok().flatten(),
+            capacity: ctx.capacity.unwrap_or(self.request.capacity),
+            ..self.request.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::async_utils::spawn_datasource_interval_polls;
+    use std::sync::atomic::AtomicUsize;
+    use std::sync::Mutex;
+
+    /// `MockSource` fetches the result of its closure, which is given the fetches made so far
+    struct MockSource<F> {
+        interval: Duration,
+        series_index: usize,
+        fetches: AtomicUsize,
+        fetch: F,
+    }
+
+    impl<F> MockSource<F>
+    where
+        F: Fn(usize) -> FetchFuture<'static, Vec<Sample>> + Send + Sync,
+    {
+        fn new(interval: Duration, fetch: F) -> MockSource<F> {
+            MockSource { interval, series_index: 0, fetches: AtomicUsize::new(0), fetch }
+        }
+    }
+
+    impl<F> FetchSource for MockSource<F>
+    where
+        F: Fn(usize) -> FetchFuture<'static, Vec<Sample>> + Send + Sync,
+    {
+        type Payload = Vec<Sample>;
+
+        fn fetch<'a>(&'a self, _ctx: &'a FetchContext) -> FetchFuture<'a, Self::Payload> {
+            (self.fetch)(self.fetches.fetch_add(1, Ordering::SeqCst))
+        }
+
+        fn interval(&self) -> Duration {
+            self.interval
+        }
+
+        fn identity(&self) -> SourceIdentity {
+            SourceIdentity {
+                chart_index: ChartIndex(0),
+                series_index: SeriesIndex(self.series_index),
+                name: String::from("mock"),
+            }
+        }
+
+        fn load_task(
+            &self,
+            _ctx: &FetchContext,
+            result: Result<Vec<Sample>, FetchError>,
+        ) -> AsyncTask {
+            samples_task(self.identity(), result)
+        }
+    }
+
+    fn no_retries() -> StartupRetries {
+        StartupRetries { retries: 0, delay: Duration::ZERO }
+    }
+
+    fn prometheus_request(source_url: String) -> MetricRequest {
+        MetricRequest {
+            pull_interval: 1,
+            source_url,
+            chart_index: ChartIndex(1),
+            series_index: SeriesIndex(2),
+            data: None,
+            capacity: 30,
+            max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+        }
+    }
+
+    #[tokio::test]
+    async fn it_retries_only_the_first_poll_quickly() {
+        // The first two attempts fail, then the series loads and a later poll fails again.
+        let outcomes = [false, false, true, true, false, true];
+        let attempts = Arc::new(Mutex::new(vec![]));
+        let fetch_attempts = attempts.clone();
+        let source = MockSource::new(Duration::from_millis(300), move |fetches| {
+            fetch_attempts.lock().unwrap().push(time::Instant::now());
+            let loaded = outcomes.get(fetches).copied().unwrap_or(true);
+            let result = if loaded { Ok(vec![]) } else { Err(FetchError::Failed(String::new())) };
+            Box::pin(async move { result }) as FetchFuture<'static, Vec<Sample>>
+        });
+        let (tx, _rx) = mpsc::channel(16);
+        let startup_retries = StartupRetries { retries: 3, delay: Duration::from_millis(20) };
+        let options = PollOptions::new(startup_retries, FetchLimiter::new(1));
+        let counters = options.counters.clone();
+        let poll = poll_source(&source, options, tx);
+        let _ = time::timeout(Duration::from_millis(1200), poll).await;
+        let attempts = attempts.lock().unwrap();
+        assert!(attempts.len() >= outcomes.len());
+        let gaps: Vec<Duration> = attempts.windows(2).map(|pair| pair[1] - pair[0]).collect();
+        // The first poll is retried quickly until it succeeds.
+        assert!(attempts[2] - attempts[0] < Duration::from_millis(150));
+        // Then the series is polled on every period.
+        assert!(gaps[2] >= Duration::from_millis(250));
+        assert!(gaps[3] >= Duration::from_millis(250));
+        // A failure after the first poll waits for the next period.
+        assert!(gaps[4] >= Duration::from_millis(250));
+        let counts = counters.counts();
+        assert_eq!((counts.failures, counts.timeouts, counts.retries), (3, 0, 2));
+        assert_eq!(counts.fetches, attempts.len() as u64);
+    }
+
+    #[tokio::test]
+    async fn it_caps_the_fetches_in_flight() {
+        // The mock fetch counts the requests in flight and keeps the maximum.
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let max_in_flight = Arc::new(AtomicUsize::new(0));
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let limiter = FetchLimiter::new(3);
+        let mut polls = vec![];
+        let mut receivers = vec![];
+        for series_index in 0..20 {
+            let (in_flight, max_in_flight, fetched) =
+                (in_flight.clone(), max_in_flight.clone(), fetched.clone());
+            let mut source = MockSource::new(Duration::from_secs(1), move |_fetches| {
+                let (in_flight, max_in_flight, fetched) =
+                    (in_flight.clone(), max_in_flight.clone(), fetched.clone());
+                Box::pin(async move {
+                    let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                    max_in_flight.fetch_max(current, Ordering::SeqCst);
+                    time::sleep(Duration::from_millis(20)).await;
+                    in_flight.fetch_sub(1, Ordering::SeqCst);
+                    fetched.fetch_add(1, Ordering::SeqCst);
+                    Ok(vec![])
+                }) as FetchFuture<'static, Vec<Sample>>
+            });
+            source.series_index = series_index;
+            let options = PollOptions::new(no_retries(), limiter.clone());
+            let (tx, rx) = mpsc::channel(16);
+            receivers.push(rx);
+            polls.push(tokio::spawn(async move { poll_source(&source, options, tx).await }));
+        }
+        // The first ticks are within the jitter, then the fetches wait for the permits.
+        time::sleep(Duration::from_millis(800)).await;
+        for poll in polls {
+            poll.abort();
+        }
+        assert_eq!(fetched.load(Ordering::SeqCst), 20);
+        assert_eq!(max_in_flight.load(Ordering::SeqCst), 3);
+        assert_eq!(
+            FetchLimiter::from(&crate::charts::ChartsConfig::default()).0.available_permits(),
+            8
+        );
+        assert_eq!(FetchLimiter::new(0).0.available_permits(), 1);
+    }
+
+    #[tokio::test]
+    async fn it_sends_the_samples_and_counts_the_timeouts() {
+        // The first fetch hangs past the timeout, the next ones return a sample.
+        let source = MockSource::new(Duration::from_millis(100), |fetches| {
+            Box::pin(async move {
+                if fetches == 0 {
+                    time::sleep(Duration::from_secs(30)).await;
+                }
+                Ok(vec![(1000 + fetches as u64, Some(fetches as f64))])
+            }) as FetchFuture<'static, Vec<Sample>>
+        });
+        let (tx, mut rx) = mpsc::channel(16);
+        let mut options = PollOptions::new(no_retries(), FetchLimiter::new(1));
+        options.timeout = Some(Duration::from_millis(50));
+        let counters = options.counters.clone();
+        let poll = tokio::spawn(async move { poll_source(&source, options, tx).await });
+        let mut results = vec![];
+        while results.len() < 3 {
+            match rx.recv().await {
+                Some(AsyncTask::LoadSamples { identity, samples }) => {
+                    assert_eq!(identity.to_string(), "mock (Chart: 0, Series: 0)");
+                    results.push(samples);
+                },
+                other => panic!("unexpected task {:?}", other),
+            }
+        }
+        assert_eq!(results[0], Err(FetchError::Timeout(Duration::from_millis(50))));
+        assert_eq!(results[1], Ok(vec![(1001, Some(1.))]));
+        assert_eq!(results[2], Ok(vec![(1002, Some(2.))]));
+        // The polls stop once the coordinator is gone.
+        drop(rx);
+        assert_eq!(time::timeout(Duration::from_secs(1), poll).await.unwrap().unwrap(), Ok(()));
+        let counts = counters.counts();
+        assert_eq!((counts.failures, counts.timeouts, counts.retries), (1, 1, 0));
+    }
+
+    #[tokio::test]
+    async fn it_backs_off_after_failed_polls() {
+        assert_eq!(backoff_intervals(0, 8), 0);
+        assert_eq!(backoff_intervals(1, 8), 1);
+        assert_eq!(backoff_intervals(3, 8), 4);
+        assert_eq!(backoff_intervals(40, 8), 8);
+        assert_eq!(backoff_intervals(3, 0), 0);
+        // Every poll fails, after the first one the polls skip 1, 2 and then 2 intervals.
+        let attempts = Arc::new(Mutex::new(vec![]));
+        let fetch_attempts = attempts.clone();
+        let source = MockSource::new(Duration::from_millis(100), move |_fetches| {
+            fetch_attempts.lock().unwrap().push(time::Instant::now());
+            Box::pin(async { Err(FetchError::Failed(String::from("down"))) })
+                as FetchFuture<'static, Vec<Sample>>
+        });
+        let (tx, _rx) = mpsc::channel(64);
+        let mut options = PollOptions::new(no_retries(), FetchLimiter::new(1));
+        options.max_backoff_intervals = 2;
+        let _ = time::timeout(Duration::from_millis(1150), poll_source(&source, options, tx)).await;
+        let attempts = attempts.lock().unwrap();
+        let gaps: Vec<u128> =
+            attempts.windows(2).map(|pair| (pair[1] - pair[0]).as_millis()).collect();
+        assert!(attempts.len() >= 4 && attempts.len() <= 5, "{} polls", attempts.len());
+        assert!((180..260).contains(&gaps[0]), "{:?}", gaps);
+        assert!((280..360).contains(&gaps[1]), "{:?}", gaps);
+        assert!((280..360).contains(&gaps[2]), "{:?}", gaps);
+    }
+
+    #[tokio::test]
+    async fn it_skips_the_polls_while_paused() {
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let source_fetched = fetched.clone();
+        let source = MockSource::new(Duration::from_millis(50), move |_fetches| {
+            source_fetched.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async { Ok(vec![]) }) as FetchFuture<'static, Vec<Sample>>
+        });
+        let (paused_tx, paused_rx) = watch::channel(true);
+        let (tx, _rx) = mpsc::channel(64);
+        let mut options = PollOptions::new(no_retries(), FetchLimiter::new(1));
+        options.paused = Some(paused_rx);
+        let poll = tokio::spawn(async move { poll_source(&source, options, tx).await });
+        time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(fetched.load(Ordering::SeqCst), 0);
+        paused_tx.send(false).unwrap();
+        time::sleep(Duration::from_millis(300)).await;
+        assert!(fetched.load(Ordering::SeqCst) >= 4);
+        poll.abort();
+    }
+
+    #[tokio::test]
+    async fn it_polls_prometheus_like_before() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        // A fixture server that replies a scalar to the first request and then hangs up.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let mut replied = false;
+            while let Ok((mut socket, _)) = listener.accept().await {
+                if replied {
+                    drop(socket);
+                    continue;
+                }
+                replied = true;
+                let mut buf = vec![0u8; 4096];
+                let _ = socket.read(&mut buf).await;
+                let body =
+                    r#"{"status":"success","data":{"resultType":"scalar","result":[1000,"7"]}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let item = prometheus_request(format!("http://{}/api/v1/query?query=up", addr));
+        let source = PrometheusSource::new(item.clone());
+        assert_eq!(source.interval(), Duration::from_secs(1));
+        assert_eq!(source.window_capacity(), None);
+        let (tx, mut rx) = mpsc::channel(16);
+        let poll = tokio::spawn(async move {
+            spawn_datasource_interval_polls(&item, no_retries(), FetchLimiter::new(1), tx).await
+        });
+        // The responses are sent whole, a failure is sent without data.
+        let mut responses = vec![];
+        while responses.len() < 2 {
+            match rx.recv().await {
+                Some(AsyncTask::LoadResponse(response)) => responses.push(response),
+                other => panic!("unexpected task {:?}", other),
+            }
+        }
+        poll.abort();
+        for response in &responses {
+            assert_eq!(
+                (response.chart_index, response.series_index),
+                (ChartIndex(1), SeriesIndex(2))
+            );
+            assert_eq!((response.pull_interval, response.capacity), (1, 30));
+            assert!(response.source_url.ends_with("/api/v1/query?query=up"));
+        }
+        let data = responses[0].data.as_ref().unwrap();
+        assert_eq!(data.status, "success");
+        assert!(responses[1].data.is_none());
+        // The query_range sources follow the window of the chart.
+        let range = prometheus_request(format!("http://{}/api/v1/query_range?query=up", addr));
+        assert_eq!(PrometheusSource::new(range).window_capacity(), Some(30));
+    }
+}
@@ -0,0 +1,354 @@
+//! `governor` protects the interactivity of the terminal when the coordinator is busy, i.e. a
+//! heavy build makes the output counter increment thousands of times per second. The time the
+//! coordinator takes to handle each message is sampled and when the p95 of the recent samples is
+//! over a threshold the charts are degraded: the internal counters are added up in buckets that
+//! are applied once per DEGRADED_FLUSH_INTERVAL, the redraws of the loaded data wait as long and
+//! the decoration ticks pause. The charts recover when the p95 falls under a lower threshold, or
+//! when the coordinator is idle, after they stayed degraded for the hold time.
+
+use crate::charts::GovernorConfig;
+use std::collections::VecDeque;
+use std::fmt;
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// The time the deferred counters wait while the charts are degraded, it is also the time the
+/// redraws of the loaded data wait
+pub const DEGRADED_FLUSH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// `GovernorMode` is how the coordinator handles the internal counters and the decorations
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum GovernorMode {
+    /// Every message is handled right away
+    #[default]
+    Normal,
+    /// The counters are deferred and the decorations paused
+    Degraded,
+}
+
+/// `GovernorTransition` is a change of the mode of the governor with the p95 that caused it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GovernorTransition {
+    /// The p95 went over the threshold
+    Degraded { p95: Duration, threshold: Duration },
+    /// The p95 fell under the recovery threshold, or the coordinator went idle
+    Recovered { p95: Duration, threshold: Duration },
+}
+
+impl GovernorTransition {
+    /// `mode` returns the mode the governor is in after the transition
+    pub fn mode(&self) -> GovernorMode {
+        match self {
+            GovernorTransition::Degraded { .. } => GovernorMode::Degraded,
+            GovernorTransition::Recovered { .. } => GovernorMode::Normal,
+        }
+    }
+}
+
+impl fmt::Display for GovernorTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GovernorTransition::Degraded { p95, threshold } => write!(
+                f,
+                "Charts degraded: message handling p95 {:.1}ms over {:.1}ms",
+                p95.as_secs_f64() * 1000.,
+                threshold.as_secs_f64() * 1000.
+            ),
+            GovernorTransition::Recovered { p95, threshold } => write!(
+                f,
+                "Charts recovered: message handling p95 {:.1}ms under {:.1}ms",
+                p95.as_secs_f64() * 1000.,
+                threshold.as_secs_f64() * 1000.
+            ),
+        }
+    }
+}
+
+/// `LoadGovernor` keeps the handling time of the last messages of the coordinator. The time is
+/// given by the caller so that the transitions can be driven by synthetic samples.
+#[derive(Debug, Clone)]
+pub struct LoadGovernor {
+    /// The thresholds, window and hold time
+    config: GovernorConfig,
+
+    /// The handling time of the last messages, at most a window of them
+    samples: VecDeque<Duration>,
+
+    /// The current mode
+    mode: GovernorMode,
+
+    /// The time at which the charts were degraded
+    degraded_at: Option<Instant>,
+
+    /// The time of the last sample
+    last_sample: Option<Instant>,
+
+    /// The p95 of the samples when the window was last full
+    p95: Option<Duration>,
+}
+
+impl LoadGovernor {
+    /// `new` starts in the Normal mode without samples
+    pub fn new(config: GovernorConfig) -> Self {
+        Self {
+            config,
+            samples: VecDeque::with_capacity(config.window()),
+            mode: GovernorMode::Normal,
+            degraded_at: None,
+            last_sample: None,
+            p95: None,
+        }
+    }
+
+    /// `mode` returns the current mode
+    pub fn mode(&self) -> GovernorMode {
+        self.mode
+    }
+
+    /// `is_degraded` returns whether the counters are deferred and the decorations paused
+    pub fn is_degraded(&self) -> bool {
+        self.mode == GovernorMode::Degraded
+    }
+
+    /// `p95` returns the p95 of the samples, None until a whole window has been sampled
+    pub fn p95(&self) -> Option<Duration> {
+        self.p95
+    }
+
+    /// `record` adds the handling time of a message at `now` and returns the transition it
+    /// caused, if any. No transition happens before a whole window has been sampled.
+    pub fn record(&mut self, latency: Duration, now: Instant) -> Option<GovernorTransition> {
+        let window = self.config.window();
+        self.last_sample = Some(now);
+        self.samples.push_back(latency);
+        while self.samples.len() > window {
+            self.samples.pop_front();
+        }
+        if self.samples.len() < window {
+            return None;
+        }
+        let p95 = percentile(&self.samples, 0.95);
+        self.p95 = Some(p95);
+        match self.mode {
+            GovernorMode::Normal if p95 > self.config.threshold() => Some(self.degrade(p95, now)),
+            GovernorMode::Degraded
+                if p95 < self.config.recover_threshold() && self.held_long_enough(now) =>
+            {
+                Some(self.recover(p95))
+            },
+            _ => None,
+        }
+    }
+
+    /// `tick` is called regularly while degraded, the charts recover once no message was
+    /// handled for the hold time. The samples are dropped, they are not recent anymore.
+    pub fn tick(&mut self, now: Instant) -> Option<GovernorTransition> {
+        if !self.is_degraded() || !self.held_long_enough(now) {
+            return None;
+        }
+        let idle = self
+            .last_sample
+            .map_or(true, |last| now.saturating_duration_since(last) >= self.config.hold());
+        if !idle {
+            return None;
+        }
+        self.samples.clear();
+        let p95 = self.p95.take().unwrap_or_default();
+        Some(self.recover(p95))
+    }
+
+    /// `force` switches to a mode regardless of the samples
+    #[cfg(test)]
+    pub fn force(&mut self, mode: GovernorMode, now: Instant) -> Option<GovernorTransition> {
+        let p95 = self.p95.unwrap_or_default();
+        match (self.mode, mode) {
+            (GovernorMode::Normal, GovernorMode::Degraded) => Some(self.degrade(p95, now)),
+            (GovernorMode::Degraded, GovernorMode::Normal) => Some(self.recover(p95)),
+            _ => None,
+        }
+    }
+
+    /// `held_long_enough` returns whether the charts have been degraded for the hold time
+    fn held_long_enough(&self, now: Instant) -> bool {
+        self.degraded_at
+            .map_or(true, |since| now.saturating_duration_since(since) >= self.config.hold())
+    }
+
+    fn degrade(&mut self, p95: Duration, now: Instant) -> GovernorTransition {
+        self.mode = GovernorMode::Degraded;
+        self.degraded_at = Some(now);
+        GovernorTransition::Degraded { p95, threshold: self.config.threshold() }
+    }
+
+    fn recover(&mut self, p95: Duration) -> GovernorTransition {
+        self.mode = GovernorMode::Normal;
+        self.degraded_at = None;
+        GovernorTransition::Recovered { p95, threshold: self.config.recover_threshold() }
+    }
+}
+
+/// `percentile` returns the sample at the `rank` fraction of the sorted samples, which must not
+/// be empty
+fn percentile(samples: &VecDeque<Duration>, rank: f64) -> Duration {
+    let mut sorted: Vec<Duration> = samples.iter().copied().collect();
+    let idx = ((sorted.len() as f64 * rank).ceil() as usize).clamp(1, sorted.len()) - 1;
+    *sorted.select_nth_unstable(idx).1
+}
+
+/// `CounterBuckets` adds up the internal counter increments deferred while the charts are
+/// degraded by counter and epoch, applying a bucket once gives the same series as applying each
+/// of its increments. The buckets are kept in the order they were first incremented so that a
+/// clock jump is recovered as it would have been.
+#[derive(Debug, Default, Clone)]
+pub struct CounterBuckets {
+    /// The counter type, epoch and added up value of each bucket
+    buckets: Vec<(&'static str, u64, f64)>,
+
+    /// The increments added to the buckets since they were last taken
+    increments: u64,
+}
+
+impl CounterBuckets {
+    /// `add` defers an increment of a counter at an epoch
+    pub fn add(&mut self, counter_type: &'static str, epoch: u64, value: f64) {
+        self.increments += 1;
+        let bucket = self.buckets.iter_mut().rev().find(|(bucket_type, bucket_epoch, _)| {
+            *bucket_type == counter_type && *bucket_epoch == epoch
+        });
+        match bucket {
+            Some((_, _, total)) => *total += value,
+            None => self.buckets.push((counter_type, epoch, value)),
+        }
+    }
+
+    /// `is_empty` returns whether there is no deferred increment
+    pub fn is_empty(&self) -> bool {
+        self.buckets.is_empty()
+    }
+
+    /// `increments` returns the increments deferred since the buckets were last taken
+    pub fn increments(&self) -> u64 {
+        self.increments
+    }
+
+    /// `take` returns the buckets to apply and empties them
+    pub fn take(&mut self) -> Vec<(&'static str, u64, f64)> {
+        self.increments = 0;
+        std::mem::take(&mut self.buckets)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> GovernorConfig {
+        GovernorConfig {
+            p95_threshold_ms: 10.,
+            recover_ratio: Some(0.5),
+            window: Some(20),
+            hold_ms: Some(1000),
+        }
+    }
+
+    /// `feed` records the same latency `count` times, one millisecond apart from `start`, and
+    /// returns the transitions
+    fn feed(
+        governor: &mut LoadGovernor,
+        latency_ms: u64,
+        count: u64,
+        start: Instant,
+    ) -> Vec<GovernorTransition> {
+        (0..count)
+            .filter_map(|idx| {
+                let now = start + Duration::from_millis(idx);
+                governor.record(Duration::from_millis(latency_ms), now)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn it_degrades_and_recovers_with_hysteresis() {
+        let start = Instant::now();
+        let mut governor = LoadGovernor::new(config());
+        // Nothing happens before a whole window is sampled.
+        assert!(feed(&mut governor, 50, 19, start).is_empty());
+        assert_eq!(governor.p95(), None);
+        // A few slow messages do not move the p95.
+        let mut governor = LoadGovernor::new(config());
+        assert!(feed(&mut governor, 1, 19, start).is_empty());
+        assert!(feed(&mut governor, 50, 1, start).is_empty());
+        assert_eq!(governor.p95(), Some(Duration::from_millis(1)));
+        assert_eq!(governor.mode(), GovernorMode::Normal);
+        // A sustained load degrades the charts once.
+        let transitions = feed(&mut governor, 20, 40, start);
+        assert_eq!(transitions, vec![GovernorTransition::Degraded {
+            p95: Duration::from_millis(20),
+            threshold: Duration::from_millis(10),
+        }]);
+        assert!(governor.is_degraded());
+        // Between both thresholds the charts stay degraded.
+        assert!(feed(&mut governor, 7, 40, start + Duration::from_secs(2)).is_empty());
+        assert!(governor.is_degraded());
+        // Under the recovery threshold the charts recover once.
+        let transitions = feed(&mut governor, 2, 40, start + Duration::from_secs(3));
+        assert_eq!(transitions, vec![GovernorTransition::Recovered {
+            p95: Duration::from_millis(2),
+            threshold: Duration::from_millis(5),
+        }]);
+        assert_eq!(governor.mode(), GovernorMode::Normal);
+        // Between both thresholds the charts stay normal.
+        assert!(feed(&mut governor, 7, 40, start + Duration::from_secs(4)).is_empty());
+        assert_eq!(governor.mode(), GovernorMode::Normal);
+    }
+
+    #[test]
+    fn it_stays_degraded_for_the_hold_time() {
+        let start = Instant::now();
+        let mut governor = LoadGovernor::new(config());
+        assert_eq!(feed(&mut governor, 20, 20, start).len(), 1);
+        let degraded_at = start + Duration::from_millis(19);
+        // The deferred messages are fast but the charts are held degraded.
+        assert!(feed(&mut governor, 1, 500, degraded_at).is_empty());
+        assert!(governor.is_degraded());
+        let transitions = feed(&mut governor, 1, 1, degraded_at + Duration::from_secs(1));
+        assert_eq!(transitions.len(), 1);
+        assert!(!governor.is_degraded());
+    }
+
+    #[test]
+    fn it_recovers_when_the_coordinator_goes_idle() {
+        let start = Instant::now();
+        let mut governor = LoadGovernor::new(config());
+        assert_eq!(feed(&mut governor, 20, 20, start).len(), 1);
+        let last = start + Duration::from_millis(19);
+        assert_eq!(governor.tick(last + Duration::from_millis(500)), None);
+        // The burst ended with the p95 still high, no message will lower it.
+        let transition = governor.tick(last + Duration::from_secs(1));
+        assert!(matches!(transition, Some(GovernorTransition::Recovered { .. })));
+        assert_eq!(governor.p95(), None);
+        assert_eq!(governor.tick(last + Duration::from_secs(5)), None);
+    }
+
+    #[test]
+    fn it_adds_up_the_increments_by_counter_and_epoch() {
+        let mut buckets = CounterBuckets::default();
+        for _ in 0..1000 {
+            buckets.add("output", 11, 1.);
+        }
+        buckets.add("input", 11, 2.);
+        buckets.add("output", 12, 3.);
+        // The clock jumped backwards, the bucket order is kept.
+        buckets.add("output", 5, 1.);
+        buckets.add("output", 11, 1.);
+        assert_eq!(buckets.increments(), 1004);
+        assert_eq!(buckets.take(), vec![
+            ("output", 11, 1001.),
+            ("input", 11, 2.),
+            ("output", 12, 3.),
+            ("output", 5, 1.),
+        ]);
+        assert!(buckets.is_empty());
+        assert_eq!(buckets.increments(), 0);
+    }
+}
@@ -4,101 +4,905 @@
 //! An async_coordinator is defined that receives requests over a futures mpsc
 //! channel that may contain new data, may request OpenGL data or increment
 //! internal counters.
+use crate::charts::animation::{TickSchedule, DEFAULT_UPDATE_HZ};
+use crate::charts::color_ref::TerminalPalette;
 use crate::charts::config::Config;
-use crate::charts::{prometheus, ChartSizeInfo, ChartsConfig, TimeSeriesChart, TimeSeriesSource};
+use crate::charts::debug_overlay::{self, OverlayEntry, OverlayLine, OVERLAY_ERROR_FG, OVERLAY_FG};
+use crate::charts::journal::{
+    AnomalyJournal, JournalEntry, SeriesBaseline, JOURNAL_OVERLAY_ENTRIES,
+};
+use crate::charts::persistence::{self, PersistenceConfig, SnapshotError, SnapshotSchedule};
+use crate::charts::timeline::{TimelineBand, TimelineReadout};
+use crate::charts::{
+    order, prometheus, timefmt, ChartIndex, ChartPlaceholder, ChartSizeInfo, ChartSummary,
+    ChartsConfig, DecorationIndex, NumericReadout, PixelRect, SeriesIndex, SeriesStyle,
+    TimeSeriesChart, TimeSeriesSource, DEFAULT_FETCH_CONCURRENCY, DEFAULT_RESIZE_DEBOUNCE_MS,
+    DEFAULT_STARTUP_RETRIES, DEFAULT_STARTUP_RETRY_DELAY_MS, LIVE_EDGE_ALPHA,
+};
 use crate::event::{Event, EventListener};
 use crate::term::SizeInfo;
+use crate::vte::ansi::Rgb;
+use fetcher::{FetchError, PollOptions, PrometheusSource, Sample, SourceIdentity};
+use governor::{
+    CounterBuckets, GovernorMode, GovernorTransition, LoadGovernor, DEGRADED_FLUSH_INTERVAL,
+};
 use log::*;
+use rand::Rng;
+use std::any::Any;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, UNIX_EPOCH};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, watch, Semaphore, SemaphorePermit};
 use tokio::time::{self, interval_at};
-use tracing::{event, span, Level};
+use tracing::{event, span, Instrument, Level};
+use watchdog::DataWatchdog;
+
+pub mod dashboard;
+pub mod fetcher;
+pub mod governor;
+pub mod watchdog;
 
 /// `MetricRequest` defines remote data sources that should be loaded regularly
 #[derive(Debug, Clone)]
 pub struct MetricRequest {
     pub pull_interval: u64,
     pub source_url: String,
-    pub chart_index: usize,  // For Vec<TimeSeriesChart>
-    pub series_index: usize, // For Vec<TimeSeriesSource>
-    pub data: Option<prometheus::HTTPResponse>,
-    pub capacity: usize, // This maps to the time range in seconds to query.
+    pub chart_index: ChartIndex,
+    pub series_index: SeriesIndex,
+    pub(crate) data: Option<prometheus::HTTPResponse>,
+    pub capacity: usize,       // This maps to the time range in seconds to query.
+    pub max_body_bytes: usize, // Larger response bodies are discarded while they are read.
+}
+
+/// `StartupRetries` are the quick retries of the first poll of a series, they are used once per
+/// series so that a chart does not stay empty for a whole pull_interval when the network was not
+/// ready at startup. Later failures wait for the next interval.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StartupRetries {
+    /// The attempts after the failed first poll
+    pub retries: u32,
+
+    /// The time between the attempts
+    pub delay: Duration,
+}
+
+impl From<&ChartsConfig> for StartupRetries {
+    fn from(chart_config: &ChartsConfig) -> Self {
+        StartupRetries {
+            retries: chart_config.startup_retries.unwrap_or(DEFAULT_STARTUP_RETRIES),
+            delay: Duration::from_millis(
+                chart_config.startup_retry_delay_ms.unwrap_or(DEFAULT_STARTUP_RETRY_DELAY_MS),
+            ),
+        }
+    }
+}
+
+/// `FetchLimiter` bounds the Prometheus requests in flight, it is shared by the polls of all the
+/// series so that slow servers are not flooded when the intervals align.
+#[derive(Debug, Clone)]
+pub struct FetchLimiter(Arc<Semaphore>);
+
+impl FetchLimiter {
+    /// `new` allows `permits` requests at once, at least one
+    pub fn new(permits: usize) -> Self {
+        FetchLimiter(Arc::new(Semaphore::new(permits.max(1))))
+    }
+
+    /// `acquire` waits until a request may be sent, the request is in flight while the permit is
+    /// held. None is returned if the limiter was closed.
+    pub async fn acquire(&self) -> Option<SemaphorePermit<'_>> {
+        self.0.acquire().await.ok()
+    }
 }
 
+impl From<&ChartsConfig> for FetchLimiter {
+    fn from(chart_config: &ChartsConfig) -> Self {
+        FetchLimiter::new(chart_config.fetch_concurrency.unwrap_or(DEFAULT_FETCH_CONCURRENCY))
+    }
+}
+
+/// `ChartTaskError` is the reply of the tasks addressing a chart that does not exist, i.e. a key
+/// binding references a chart that was removed from the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartTaskError {
+    NotFound(ChartIndex),
+}
+
+impl std::fmt::Display for ChartTaskError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ChartTaskError::NotFound(chart_index) => write!(f, "chart #{} not found", chart_index),
+        }
+    }
+}
+
+/// `ChartTaskResult` is the reply of the tasks addressing a chart by its index
+pub type ChartTaskResult<T> = Result<T, ChartTaskError>;
+
 /// `AsyncTask` contains message types that async_coordinator can work on
 #[derive(Debug)]
+#[non_exhaustive]
 pub enum AsyncTask {
     LoadResponse(MetricRequest),
-    SendMetricsOpenGLData(usize, usize, oneshot::Sender<(Vec<f32>, f32)>),
-    SendChartDecorationsOpenGLData(usize, usize, oneshot::Sender<(Vec<f32>, f32)>),
-    ChangeDisplaySize(f32, f32, f32, f32, oneshot::Sender<bool>),
-    IncrementInputCounter(u64, f64),
-    IncrementOutputCounter(u64, f64),
-    DecorUpdate(usize, f32),
+    /// The samples fetched by a source of the `fetcher`, or the reason it failed
+    LoadSamples {
+        identity: SourceIdentity,
+        samples: Result<Vec<Sample>, FetchError>,
+    },
+    /// Replies the vertices and alpha of a series and the size generation they were built for
+    SendMetricsOpenGLData(WindowId, ChartIndex, SeriesIndex, oneshot::Sender<(Vec<f32>, f32, u64)>),
+    /// Replies the vertices and alpha of a decoration and the size generation they were built for
+    SendChartDecorationsOpenGLData(
+        WindowId,
+        ChartIndex,
+        DecorationIndex,
+        oneshot::Sender<(Vec<f32>, f32, u64)>,
+    ),
+    /// Replies the live edge segment and alpha of a series and the size generation it was built
+    /// for
+    SendLiveEdgeOpenGLData(
+        WindowId,
+        ChartIndex,
+        SeriesIndex,
+        oneshot::Sender<(Vec<f32>, f32, u64)>,
+    ),
+    SendSeriesCapacity(ChartIndex, SeriesIndex, oneshot::Sender<usize>),
+    SendChartScissorRect(WindowId, ChartIndex, oneshot::Sender<Option<PixelRect>>),
+    SendChartCellRects(WindowId, ChartIndex, oneshot::Sender<Vec<PixelRect>>),
+    SendChartTimelineBands(WindowId, ChartIndex, oneshot::Sender<Vec<TimelineBand>>),
+    SendChartSeriesStyles(ChartIndex, oneshot::Sender<Vec<SeriesStyle>>),
+    SendChartPlaceholder(WindowId, ChartIndex, oneshot::Sender<Option<ChartPlaceholder>>),
+    SendChartNumericReadout(WindowId, ChartIndex, oneshot::Sender<Option<NumericReadout>>),
+    LoadLabelValues(
+        ChartIndex,
+        SeriesIndex,
+        Vec<String>,
+        oneshot::Sender<(Vec<MetricRequest>, Vec<SeriesIndex>)>,
+    ),
+    /// The height, width, padding_y, padding_x, cell_width and cell_height of a window
+    ChangeDisplaySize(WindowId, f32, f32, f32, f32, f32, f32, oneshot::Sender<bool>),
+    /// The height, width, padding_y, padding_x, cell_width and cell_height of a window, debounced
+    UpdateDisplaySize(WindowId, f32, f32, f32, f32, f32, f32),
+    ChangeScaleFactor(WindowId, f64, oneshot::Sender<bool>),
+    /// The internal counters are incremented at the epoch of the coordinator clock
+    IncrementInputCounter(f64),
+    IncrementOutputCounter(f64),
+    IncrementScrollCounter(f64),
+    IncrementSelectionCounter(f64),
+    /// A decoration tick, with the milliseconds elapsed since the previous one
+    DecorUpdate(ChartIndex, f32),
+    PeekGenerations(oneshot::Sender<Vec<(ChartIndex, u64)>>),
+    /// The terminal shows or hides the charts, hidden charts do not ask for redraws
+    SetChartsVisible(bool),
+    /// The terminal enables or disables the decorations of the WithDecorToggle group
+    SetDecorationsEnabled(bool),
+    SendCoordinatorStats(oneshot::Sender<CoordinatorStats>),
+    /// The lines of the debug overlay describing the state of the charts
+    DebugOverlayData(oneshot::Sender<Vec<OverlayLine>>),
+    /// Resizes the sources of a chart, or of all the charts when None, to a window of seconds.
+    /// Replies whether the window changed, NotFound when the chart does not exist.
+    SetWindowSeconds(Option<ChartIndex>, u64, oneshot::Sender<ChartTaskResult<bool>>),
+    /// Resizes a series to hold a number of samples, the newest samples are kept when shrinking
+    ResizeSeriesCapacity(ChartIndex, SeriesIndex, usize),
+    /// Drops the samples of every source of a chart, i.e. after their queries changed
+    ClearChart(ChartIndex),
+    /// The query_range polls subscribe to the window changes to fetch a grown window right away
+    SubscribeWindowChanges(oneshot::Sender<watch::Receiver<u64>>),
+    /// The colors of the terminal by name, the named chart colors are resolved against them.
+    /// Replies the unknown names.
+    SetPalette(Vec<(String, Rgb)>, oneshot::Sender<Vec<String>>),
+    /// Replies the entries of the anomaly journal, the oldest first
+    GetJournal(oneshot::Sender<Vec<JournalEntry>>),
+    /// Replies the summary of each chart between an epoch and the coordinator clock, i.e. since
+    /// the terminal lost the focus
+    SummarizeSince {
+        epoch: u64,
+        reply: oneshot::Sender<Vec<ChartSummary>>,
+    },
+    DisableRendering,
     Shutdown,
+    /// Makes the coordinator panic while handling it
+    #[cfg(test)]
+    Poison,
+    /// Stops the coordinator clock at an epoch
+    #[cfg(test)]
+    FreezeClock(u64),
+    /// Switches the load governor to a mode regardless of the handling times
+    #[cfg(test)]
+    ForceGovernorMode(GovernorMode),
+    /// Replies the values of a series
+    #[cfg(test)]
+    SendSeriesValues(ChartIndex, SeriesIndex, oneshot::Sender<Vec<(u64, Option<f64>)>>),
     // Maybe add CloudWatch/etc
 }
 
+/// `ChartsStatus` is the state of the async_coordinator as seen by the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChartsStatus {
+    /// The coordinator is handling the requests
+    Running,
+    /// The coordinator is overloaded, the internal counters are applied late and the decorations
+    /// are paused
+    Degraded,
+    /// The coordinator panicked, the requests are dropped and the charts should not be drawn
+    Disabled,
+}
+
+/// The time to wait for a reply of the async_coordinator, a dead or stuck coordinator must not
+/// freeze the rendering.
+pub const COORDINATOR_REPLY_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// The time the loaded responses are gathered before the terminal is asked to redraw once for
+/// all of them
+pub const LOAD_REDRAW_BATCH: Duration = Duration::from_millis(50);
+
+/// The time between the evaluations of the data watchdog when no response is loaded
+pub const WATCHDOG_TICK: Duration = Duration::from_secs(1);
+
+/// The time between the checks of the periodic snapshots of the charts
+pub const SNAPSHOT_TICK: Duration = Duration::from_secs(1);
+
+/// The first tick of the polls is delayed up to this fraction of their interval, so that the
+/// series with the same interval are not requested at the same instant
+pub const MAX_STARTUP_JITTER: f64 = 0.1;
+
+/// `startup_jitter` returns the random delay of the first tick of a poll with `period`, between
+/// zero and MAX_STARTUP_JITTER of the period.
+pub fn startup_jitter<R: Rng>(period: Duration, rng: &mut R) -> Duration {
+    period.mul_f64(rng.gen_range(0f64..MAX_STARTUP_JITTER))
+}
+
+/// `ReplyError` is the reason a request to the async_coordinator got no reply
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReplyError {
+    /// The request was dropped, i.e. the coordinator is disabled or gone
+    Dropped,
+    /// The reply did not arrive within COORDINATOR_REPLY_TIMEOUT
+    Timeout,
+}
+
+/// `block_on_reply` waits for the reply of the async_coordinator to a request, the wait is
+/// bounded by COORDINATOR_REPLY_TIMEOUT so that the callers can fall back to a default.
+pub fn block_on_reply<T>(
+    tokio_handle: &tokio::runtime::Handle,
+    reply_rx: oneshot::Receiver<T>,
+) -> Result<T, ReplyError> {
+    tokio_handle.block_on(async {
+        match time::timeout(COORDINATOR_REPLY_TIMEOUT, reply_rx).await {
+            Ok(Ok(reply)) => Ok(reply),
+            Ok(Err(_)) => Err(ReplyError::Dropped),
+            Err(_) => Err(ReplyError::Timeout),
+        }
+    })
+}
+
+/// `panic_message` returns the message of a panic payload, if it has one
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(message) = payload.downcast_ref::<&str>() {
+        message
+    } else if let Some(message) = payload.downcast_ref::<String>() {
+        message.as_str()
+    } else {
+        "unknown panic payload"
+    }
+}
+
+/// `CoordinatorStats` counts the requests handled by the coordinator and the memory of the charts
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CoordinatorStats {
+    /// The SendMetricsOpenGLData requests
+    pub metrics_requests: u64,
+
+    /// The SendChartDecorationsOpenGLData requests
+    pub decoration_requests: u64,
+
+    /// The SendChartScissorRect requests
+    pub scissor_requests: u64,
+
+    /// The PeekGenerations requests
+    pub generation_peeks: u64,
+
+    /// The bytes held by the series and vertices of the charts when the stats were requested
+    pub memory_bytes: usize,
+}
+
+/// `FetchStatus` is the result of the last response of a Prometheus series
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum FetchStatus {
+    /// The response was loaded with the number of records
+    Loaded(usize),
+    /// The request failed or the response was not successful
+    Failed,
+}
+
+/// `DebugOverlayState` is what the coordinator keeps only to describe itself in the debug
+/// overlay
+#[derive(Debug, Default, Clone)]
+pub(crate) struct DebugOverlayState {
+    /// The result of the last response of each Prometheus series
+    pub fetches: HashMap<(ChartIndex, SeriesIndex), FetchStatus>,
+
+    /// The time of the last decorations tick
+    pub last_decoration_tick: Option<time::Instant>,
+
+    /// Whether the data watchdog found the loaded items below its minimum
+    pub data_starved: bool,
+
+    /// The sources the data watchdog found without loaded items
+    pub starved_sources: Vec<String>,
+
+    /// The p95 of the message handling time when the load governor degraded the charts
+    pub degraded_p95: Option<Duration>,
+
+    /// The internal counter increments deferred by the load governor
+    pub deferred_increments: u64,
+
+    /// The descriptions of the latest entries of the anomaly journal
+    pub recent_anomalies: Vec<String>,
+}
+
+/// `CoordinatorClock` is the clock of the async_coordinator, the internal counters are stamped
+/// with it when their tasks are handled rather than when they are sent.
+#[derive(Debug, Default)]
+pub struct CoordinatorClock {
+    /// The epoch the clock is stopped at
+    frozen: std::cell::Cell<Option<u64>>,
+}
+
+impl CoordinatorClock {
+    /// `now` returns the current epoch in seconds
+    pub fn now(&self) -> u64 {
+        self.frozen.get().unwrap_or_else(|| {
+            std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs()
+        })
+    }
+
+    /// `freeze` stops the clock at an epoch
+    #[cfg(test)]
+    fn freeze(&self, epoch: u64) {
+        self.frozen.set(Some(epoch));
+    }
+}
+
+/// `WindowId` identifies the window requesting chart data, windows may have different sizes and
+/// so different chart vertices. The default id is reserved for the single window case.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct WindowId(pub u64);
+
+/// `VertexKey` identifies the cached vertices of a chart series or decoration
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+enum VertexKey {
+    Series(ChartIndex, SeriesIndex),
+    LiveEdge(ChartIndex, SeriesIndex),
+    Decoration(ChartIndex, DecorationIndex),
+}
+
+/// `OpenGLDataRequest` selects the vertices of a chart requested with get_metric_opengl_data
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OpenGLDataRequest {
+    Series(SeriesIndex),
+    LiveEdge(SeriesIndex),
+    Decoration(DecorationIndex),
+}
+
+/// `DisplaySize` is the size of a window sent with ChangeDisplaySize or UpdateDisplaySize, the
+/// cell size changes with the font size and the charts laid out in cells follow it.
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct DisplaySize {
+    pub height: f32,
+    pub width: f32,
+    pub padding_y: f32,
+    pub padding_x: f32,
+    pub cell_width: f32,
+    pub cell_height: f32,
+}
+
+/// `DisplaySizeDebouncer` collects the display sizes sent during interactive resizes, only the
+/// latest size of each window is kept and it is applied once the sizes have been stable for
+/// `delay`.
+#[derive(Debug)]
+pub(crate) struct DisplaySizeDebouncer {
+    /// The time the sizes must be stable
+    delay: Duration,
+
+    /// The latest size of each window that has not been applied yet
+    pending: HashMap<WindowId, DisplaySize>,
+
+    /// The time at which the pending sizes are applied, reset by every new size
+    deadline: Option<time::Instant>,
+}
+
+impl DisplaySizeDebouncer {
+    /// `new` creates a debouncer that waits `delay` after the last size before applying it
+    pub fn new(delay: Duration) -> Self {
+        DisplaySizeDebouncer { delay, pending: HashMap::new(), deadline: None }
+    }
+
+    /// `push` stores the size of a window, replacing any pending size, and resets the deadline
+    pub fn push(&mut self, window_id: WindowId, size: DisplaySize, now: time::Instant) {
+        self.pending.insert(window_id, size);
+        self.deadline = Some(now + self.delay);
+    }
+
+    /// `discard` drops the pending size of a window, i.e. when a newer size was applied already
+    pub fn discard(&mut self, window_id: WindowId) {
+        self.pending.remove(&window_id);
+        if self.pending.is_empty() {
+            self.deadline = None;
+        }
+    }
+
+    /// `deadline` returns the time at which the pending sizes should be applied, if any
+    pub fn deadline(&self) -> Option<time::Instant> {
+        self.deadline
+    }
+
+    /// `take_ready` returns the pending sizes once the deadline has passed
+    pub fn take_ready(&mut self, now: time::Instant) -> Vec<(WindowId, DisplaySize)> {
+        match self.deadline {
+            Some(deadline) if deadline <= now => {
+                self.deadline = None;
+                self.pending.drain().collect()
+            },
+            _ => vec![],
+        }
+    }
+}
+
+/// `WindowCharts` keeps the size of each window drawing the charts. The charts vertices are
+/// generated for one window size at a time, the replies for each window are cached so that
+/// windows of different sizes do not regenerate the vertices on every request.
+#[derive(Debug, Default)]
+pub(crate) struct WindowCharts {
+    /// The size of each window
+    sizes: HashMap<WindowId, ChartSizeInfo>,
+
+    /// The size the charts vertices were last generated for
+    active_size: ChartSizeInfo,
+
+    /// The vertices and alpha already sent to each window
+    cache: HashMap<WindowId, HashMap<VertexKey, (Vec<f32>, f32)>>,
+
+    /// Incremented every time the charts vertices change, windows keep the vertices they
+    /// received until it changes
+    generation: u64,
+
+    /// Whether no display size with dimensions is known yet, i.e. at startup before the display
+    /// sends its size. The charts vertices are not generated until then.
+    awaiting_size: bool,
+
+    /// Whether the charts changed while awaiting the display size, they are redrawn once it
+    /// arrives
+    pending_regeneration: bool,
+
+    /// The display sizes received from each window, the size generation they expect
+    size_requests: HashMap<WindowId, u64>,
+}
+
+impl WindowCharts {
+    /// `new` registers the size of the default window, the charts vertices are generated for it.
+    pub fn new(size: ChartSizeInfo) -> Self {
+        let mut sizes = HashMap::new();
+        sizes.insert(WindowId::default(), size);
+        WindowCharts {
+            sizes,
+            active_size: size,
+            cache: HashMap::new(),
+            generation: 0,
+            awaiting_size: !size.has_dimensions(),
+            pending_regeneration: false,
+            size_requests: HashMap::new(),
+        }
+    }
+
+    /// `active_size` returns the size the charts vertices are currently generated for
+    pub fn active_size(&self) -> ChartSizeInfo {
+        self.active_size
+    }
+
+    /// `size` returns the size of a window, unknown windows use the default window size
+    pub fn size(&self, window_id: WindowId) -> ChartSizeInfo {
+        match self.sizes.get(&window_id) {
+            Some(size) => *size,
+            None => self.sizes.get(&WindowId::default()).copied().unwrap_or(self.active_size),
+        }
+    }
+
+    /// `set_size` stores the new size of a window, the first size with dimensions ends the wait
+    /// for the display size
+    pub fn set_size(&mut self, window_id: WindowId, size: ChartSizeInfo) {
+        if self.awaiting_size && size.has_dimensions() {
+            event!(Level::DEBUG, "WindowCharts::set_size: Received the display size: {:?}", size);
+            self.awaiting_size = false;
+        }
+        self.sizes.insert(window_id, size);
+    }
+
+    /// `notify_change` returns whether the display should be told that the charts changed, the
+    /// changes made while awaiting the display size are told once it arrives
+    pub fn notify_change(&mut self) -> bool {
+        if self.awaiting_size {
+            self.pending_regeneration = true;
+        }
+        !self.awaiting_size
+    }
+
+    /// `take_pending_regeneration` returns true once, after the display size arrived, if the
+    /// charts changed while awaiting it. The charts are regenerated for the new size already.
+    pub fn take_pending_regeneration(&mut self) -> bool {
+        !self.awaiting_size && std::mem::take(&mut self.pending_regeneration)
+    }
+
+    /// `request_size` counts a display size received from a window, the size may be applied
+    /// later than it was received, i.e. once it is debounced.
+    pub fn request_size(&mut self, window_id: WindowId) {
+        let size_generation = self.size_requests.entry(window_id).or_default();
+        *size_generation = size_generation.wrapping_add(1);
+    }
+
+    /// `requested_generation` returns the size generation of the latest display size received
+    /// from a window
+    pub fn requested_generation(&self, window_id: WindowId) -> u64 {
+        self.size_requests.get(&window_id).copied().unwrap_or_default()
+    }
+
+    /// `invalidate` drops the cached vertices of every window, must be called when the charts
+    /// data changes
+    pub fn invalidate(&mut self) {
+        self.cache.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// `generation` returns the generation of the charts vertices
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// `activate` regenerates the charts vertices for the size of the window if they were
+    /// generated for a different size.
+    pub fn activate(&mut self, charts: &mut [TimeSeriesChart], window_id: WindowId) {
+        if self.size(window_id) != self.active_size {
+            self.regenerate(charts, window_id);
+        }
+    }
+
+    /// `regenerate` generates the charts vertices for the size of the window, it waits for the
+    /// display size.
+    pub fn regenerate(&mut self, charts: &mut [TimeSeriesChart], window_id: WindowId) {
+        let size = self.size(window_id);
+        if self.awaiting_size {
+            event!(Level::DEBUG, "WindowCharts::regenerate: {:?} awaits its size", window_id);
+            self.pending_regeneration = true;
+            return;
+        }
+        event!(Level::DEBUG, "WindowCharts::regenerate: {:?} with size: {:?}", window_id, size);
+        for chart in charts.iter_mut() {
+            chart.update_all_series_opengl_vecs(size);
+        }
+        self.active_size = size;
+    }
+
+    /// `opengl_data` returns the cached vertices for a window, they are generated with
+    /// `generate` after activating the window size when they are not cached. Replies for out of
+    /// bounds indexes are not cached. The size generation of the window is added to the reply.
+    fn opengl_data<F>(
+        &mut self,
+        charts: &mut [TimeSeriesChart],
+        window_id: WindowId,
+        key: VertexKey,
+        generate: F,
+    ) -> (Vec<f32>, f32, u64)
+    where
+        F: FnOnce(&[TimeSeriesChart]) -> Option<(Vec<f32>, f32)>,
+    {
+        let size_generation = self.size(window_id).size_generation;
+        if let Some(opengl_data) = self.cache.get(&window_id).and_then(|cache| cache.get(&key)) {
+            let (vertices, alpha) = opengl_data.clone();
+            return (vertices, alpha, size_generation);
+        }
+        self.activate(charts, window_id);
+        match generate(&*charts) {
+            Some((vertices, alpha)) => {
+                let opengl_data = (vertices.clone(), alpha);
+                self.cache.entry(window_id).or_default().insert(key, opengl_data);
+                (vertices, alpha, size_generation)
+            },
+            None => (vec![], 0f32, size_generation),
+        }
+    }
+}
+
+/// `update_chart_vertices` regenerates the vertices of a chart for the size, the samples loaded
+/// before the display sent a size with dimensions wait for it instead.
+fn update_chart_vertices(chart: &mut TimeSeriesChart, size: ChartSizeInfo) {
+    if size.has_dimensions() {
+        chart.update_all_series_opengl_vecs(size);
+    }
+}
+
 /// `increment_internal_counter` handles a request to increment different
-/// internal counter types.
+/// internal counter types at the `now` epoch of the coordinator clock, or at the epoch of the
+/// Prometheus series of the charts aligned to them. Returns true if any chart contains the
+/// counter.
 pub fn increment_internal_counter(
     charts: &mut Vec<TimeSeriesChart>,
     counter_type: &'static str,
-    epoch: u64,
+    now: u64,
     value: f64,
     size: ChartSizeInfo,
-) {
+) -> bool {
+    let mut any_chart_updated = false;
     for chart in charts {
+        let epoch = chart.counter_epoch(now);
+        let live_edge = chart.live_edge;
         let mut chart_updated = false;
         for series in &mut chart.sources {
-            if counter_type == "input" {
-                if let TimeSeriesSource::AlacrittyInput(ref mut input) = series {
-                    input.series.upsert((epoch, Some(value)));
-                    chart_updated = true;
-                }
-            }
-            if counter_type == "output" {
-                if let TimeSeriesSource::AlacrittyOutput(ref mut output) = series {
-                    output.series.upsert((epoch, Some(value)));
-                    chart_updated = true;
-                }
-            }
-            // Update the loaded item counters
-            if counter_type == "async_loaded_items" {
-                if let TimeSeriesSource::AsyncLoadedItems(ref mut items) = series {
-                    items.series.upsert((epoch, Some(value)));
-                    chart_updated = true;
-                }
+            let counter = match (counter_type, series) {
+                ("input", TimeSeriesSource::AlacrittyInput(counter))
+                | ("output", TimeSeriesSource::AlacrittyOutput(counter))
+                | ("scroll", TimeSeriesSource::AlacrittyScroll(counter))
+                | ("selection", TimeSeriesSource::AlacrittySelection(counter)) => counter,
+                _ => continue,
+            };
+            if live_edge {
+                counter.accumulate(epoch, value);
+            } else {
+                counter.series.upsert((epoch, Some(value)));
             }
+            chart_updated = true;
         }
         if chart_updated {
             chart.synchronize_series_epoch_range();
-            chart.update_all_series_opengl_vecs(size);
+            update_chart_vertices(chart, size);
+            any_chart_updated = true;
+        }
+    }
+    any_chart_updated
+}
+
+/// `LoadedItemsRoutes` maps the series a response is loaded into to the async_loaded_items
+/// counters that count its items: the global counters count the loads of all the series, the
+/// per_source counters only the loads of the series they watch.
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub(crate) struct LoadedItemsRoutes {
+    /// The counters of the loads of all the series
+    global: Vec<(ChartIndex, SeriesIndex)>,
+
+    /// The counters of the loads of each watched series
+    per_source: HashMap<(ChartIndex, SeriesIndex), Vec<(ChartIndex, SeriesIndex)>>,
+}
+
+impl LoadedItemsRoutes {
+    /// `new` resolves the series watched by the per_source counters to their indices, the
+    /// counters watching unknown names are not routed
+    pub fn new(charts: &[TimeSeriesChart]) -> LoadedItemsRoutes {
+        let mut routes = LoadedItemsRoutes::default();
+        for (chart_idx, chart) in charts.iter().enumerate() {
+            for (series_idx, source) in chart.sources.iter().enumerate() {
+                let counter = match source {
+                    TimeSeriesSource::AsyncLoadedItems(counter) => counter,
+                    _ => continue,
+                };
+                let route = (ChartIndex(chart_idx), SeriesIndex(series_idx));
+                if !counter.per_source {
+                    routes.global.push(route);
+                } else if let Some(watched) = counter.watched_series(charts) {
+                    routes.per_source.entry(watched).or_default().push(route);
+                }
+            }
+        }
+        routes
+    }
+
+    /// `counters` returns the counters of the items loaded into a series
+    pub fn counters(&self, origin: (ChartIndex, SeriesIndex)) -> Vec<(ChartIndex, SeriesIndex)> {
+        let watchers = self.per_source.get(&origin).into_iter().flatten();
+        self.global.iter().chain(watchers).copied().collect()
+    }
+}
+
+/// `increment_loaded_items` increments the async_loaded_items counters routed from the series
+/// the items were loaded into, `origin`. The routes are resolved on each load so that the
+/// discovered series are watched too. Returns true if any counter was incremented.
+pub fn increment_loaded_items(
+    charts: &mut [TimeSeriesChart],
+    origin: (ChartIndex, SeriesIndex),
+    now: u64,
+    value: f64,
+    size: ChartSizeInfo,
+) -> bool {
+    let mut updated_charts: Vec<ChartIndex> = vec![];
+    for (chart_index, series_index) in LoadedItemsRoutes::new(charts).counters(origin) {
+        let chart = &mut charts[chart_index.0];
+        let epoch = chart.counter_epoch(now);
+        chart.sources[series_index.0].series_mut().upsert((epoch, Some(value)));
+        if !updated_charts.contains(&chart_index) {
+            updated_charts.push(chart_index);
+        }
+    }
+    for chart_index in &updated_charts {
+        let chart = &mut charts[chart_index.0];
+        chart.synchronize_series_epoch_range();
+        update_chart_vertices(chart, size);
+    }
+    !updated_charts.is_empty()
+}
+
+/// `increment_counter_task` handles the async_coordinator tasks that increment an internal
+/// counter, a clock that moved backwards is recovered first so that the counter is not
+/// discarded as too old.
+fn increment_counter_task(
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    counter_type: &'static str,
+    now: u64,
+    value: f64,
+) {
+    let size = windows.active_size();
+    let recovered = chart_config.recover_clock_jump(now).is_some();
+    if recovered {
+        for chart in &mut chart_config.charts {
+            update_chart_vertices(chart, size);
+        }
+    }
+    let charts = &mut chart_config.charts;
+    if increment_internal_counter(charts, counter_type, now, value, size) || recovered {
+        windows.invalidate();
+    }
+}
+
+/// `increment_or_defer` handles the async_coordinator tasks that increment an internal counter,
+/// the increment is added to the `deferred` buckets instead while the charts are degraded.
+fn increment_or_defer(
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    deferred: Option<&mut CounterBuckets>,
+    counter_type: &'static str,
+    now: u64,
+    value: f64,
+) {
+    match deferred {
+        Some(buckets) => buckets.add(counter_type, now, value),
+        None => increment_counter_task(chart_config, windows, counter_type, now, value),
+    }
+}
+
+/// `flush_counter_buckets` applies the internal counter increments deferred while the charts
+/// were degraded, at the epochs they were received at
+fn flush_counter_buckets(
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    buckets: &mut CounterBuckets,
+) {
+    if buckets.is_empty() {
+        return;
+    }
+    event!(
+        Level::DEBUG,
+        "flush_counter_buckets: Applying {} deferred increments",
+        buckets.increments()
+    );
+    for (counter_type, epoch, value) in buckets.take() {
+        increment_counter_task(chart_config, windows, counter_type, epoch, value);
+    }
+}
+
+/// `report_governor_transition` logs a change of the mode of the load governor once and shows
+/// it in the status of the charts, charts disabled after a panic stay disabled. The deferred
+/// counters are applied on recovery. Returns the time of the next governor tick.
+fn report_governor_transition(
+    transition: GovernorTransition,
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    buckets: &mut CounterBuckets,
+    status_tx: &watch::Sender<ChartsStatus>,
+    debug_state: &mut DebugOverlayState,
+) -> Option<time::Instant> {
+    event!(Level::WARN, "LoadGovernor: {}", transition);
+    let (status, p95, deadline) = match transition {
+        GovernorTransition::Degraded { p95, .. } => (
+            ChartsStatus::Degraded,
+            Some(p95),
+            Some(time::Instant::now() + DEGRADED_FLUSH_INTERVAL),
+        ),
+        GovernorTransition::Recovered { .. } => {
+            flush_counter_buckets(chart_config, windows, buckets);
+            (ChartsStatus::Running, None, None)
+        },
+    };
+    debug_state.degraded_p95 = p95;
+    debug_state.deferred_increments = buckets.increments();
+    status_tx.send_if_modified(|current| {
+        let changed = *current != ChartsStatus::Disabled && *current != status;
+        if changed {
+            *current = status;
         }
+        changed
+    });
+    deadline
+}
+
+/// `set_palette` handles the async_coordinator task of type SetPalette, the named colors of the
+/// charts are resolved against the new palette and their vertices are regenerated. Returns the
+/// unknown names, they are drawn with the foreground.
+pub(crate) fn set_palette(
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    colors: Vec<(String, Rgb)>,
+) -> Vec<String> {
+    let palette = TerminalPalette::new(colors);
+    let unknown = chart_config.resolve_colors(&palette);
+    for name in &unknown {
+        event!(
+            Level::WARN,
+            "set_palette: Unknown terminal color '{}', falling back to the foreground",
+            name
+        );
+    }
+    let size = windows.active_size();
+    for chart in &mut chart_config.charts {
+        update_chart_vertices(chart, size);
+    }
+    windows.invalidate();
+    unknown
+}
+
+/// `load_label_values` handles the async_coordinator task of type LoadLabelValues, returns the
+/// requests of the series that should start being polled and the indexes of the retired series.
+pub fn load_label_values(
+    charts: &mut [TimeSeriesChart],
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    values: &[String],
+    size: ChartSizeInfo,
+) -> (Vec<MetricRequest>, Vec<SeriesIndex>) {
+    let chart = match charts.get_mut(chart_index.0) {
+        Some(chart) => chart,
+        None => {
+            event!(Level::WARN, "load_label_values:(Chart: {}) Index out of bounds", chart_index);
+            return (vec![], vec![]);
+        },
+    };
+    let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    let changes = chart.apply_discovered_values(series_index.0, values, now);
+    if !changes.started.is_empty() {
+        update_chart_vertices(chart, size);
     }
+    let requests = changes
+        .started
+        .iter()
+        .filter_map(|idx| match chart.sources.get(*idx) {
+            Some(TimeSeriesSource::PrometheusTimeSeries(prom)) => {
+                Some(series_metric_request(chart_index, SeriesIndex(*idx), prom))
+            },
+            _ => None,
+        })
+        .collect();
+    (requests, changes.retired.into_iter().map(SeriesIndex).collect())
 }
 
-/// `load_http_response` handles the async_coordinator task of type LoadResponse
-/// Currently only PrometheusTimeSeries are handled.
+/// `load_http_response` handles the async_coordinator task of type LoadResponse, `now` is the
+/// epoch of the coordinator clock. Currently only PrometheusTimeSeries are handled.
 pub fn load_http_response(
     charts: &mut Vec<TimeSeriesChart>,
     response: MetricRequest,
     size: ChartSizeInfo,
+    now: u64,
 ) -> Option<usize> {
     // XXX: Move to prometheus.rs?
-    let span = span!(Level::DEBUG, "load_http_response", idx = response.chart_index);
+    let span = span!(Level::DEBUG, "load_http_response", idx = response.chart_index.0);
     let _enter = span.enter();
     if let Some(data) = response.data {
         if data.status != "success" {
             return None;
         }
         let mut ok_records = 0;
-        if response.chart_index < charts.len()
-            && response.series_index < charts[response.chart_index].sources.len()
-        {
-            if let TimeSeriesSource::PrometheusTimeSeries(ref mut prom) =
-                charts[response.chart_index].sources[response.series_index]
+        if let Some(chart) = charts.get_mut(response.chart_index.0) {
+            if let Some(TimeSeriesSource::PrometheusTimeSeries(ref mut prom)) =
+                chart.sources.get_mut(response.series_index.0)
             {
                 match prom.load_prometheus_response(data) {
                     Ok(num_records) => {
@@ -127,554 +931,4799 @@ pub fn load_http_response(
                 }
                 event!(
                     Level::DEBUG,
-                    "load_http_response:(Chart: {}, Series: {}) After loading. TimeSeries is: {:?}",
+                    "load_http_response:(Chart: {}, Series: {}) After loading. TimeSeries is: {}",
                     response.chart_index,
                     response.series_index,
-                    charts[response.chart_index].sources[response.series_index]
+                    prom.series.summary(&prom.name)
+                );
+                event!(Level::TRACE, "load_http_response: TimeSeries is: {:?}", prom);
+            } else {
+                event!(
+                    Level::WARN,
+                    "load_http_response:(Chart: {}, Series: {}) Series index out of bounds or not \
+                     a Prometheus series",
+                    response.chart_index,
+                    response.series_index
                 );
             }
-            charts[response.chart_index].synchronize_series_epoch_range();
-            charts[response.chart_index].update_all_series_opengl_vecs(size);
+            chart.update_epoch_skew(now);
+            chart.synchronize_series_epoch_range();
+            update_chart_vertices(chart, size);
+        } else {
+            event!(
+                Level::WARN,
+                "load_http_response:(Chart: {}, Series: {}) Chart index out of bounds",
+                response.chart_index,
+                response.series_index
+            );
         }
-        let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        increment_internal_counter(charts, "async_loaded_items", now, ok_records as f64, size);
+        let origin = (response.chart_index, response.series_index);
+        increment_loaded_items(charts, origin, now, ok_records as f64, size);
         Some(ok_records)
     } else {
         None
     }
 }
 
-/// `send_metrics_opengl_vecs` handles the async_coordinator task of type
-/// SendMetricsOpenGLData, it sends the logged metrics as vertices
-/// representation through the channel parameter. The vertices are deduplicated
-/// for troubleshooting purposes mostly.
-pub fn send_metrics_opengl_vecs(
-    charts: &[TimeSeriesChart],
-    chart_index: usize,
-    series_index: usize,
-    channel: oneshot::Sender<(Vec<f32>, f32)>,
-) {
-    event!(
-        Level::DEBUG,
-        "send_metrics_opengl_vecs:(Chart: {}, Series: {}): Request received",
-        chart_index,
-        series_index
-    );
-    match channel.send(
-        if chart_index >= charts.len() || series_index >= charts[chart_index].sources.len() {
-            (vec![], 0.0f32)
-        } else {
-            (
-                charts[chart_index].get_deduped_opengl_vecs(series_index),
-                charts[chart_index].sources[series_index].alpha(),
-            )
-        },
-    ) {
-        Ok(()) => {
-            event!(
-                Level::DEBUG,
-                "send_metrics_opengl_vecs:(Chart: {}, Series: {}) oneshot::message sent",
-                chart_index,
-                series_index
-            );
+/// `load_samples` handles the async_coordinator task of type LoadSamples, `now` is the epoch of
+/// the coordinator clock. Returns the number of samples loaded, None if the fetch failed.
+pub fn load_samples(
+    charts: &mut [TimeSeriesChart],
+    identity: &SourceIdentity,
+    samples: Result<Vec<Sample>, FetchError>,
+    size: ChartSizeInfo,
+    now: u64,
+) -> Option<usize> {
+    let samples = match samples {
+        Ok(samples) => samples,
+        Err(err) => {
+            event!(Level::DEBUG, "load_samples:{} Fetch failed: {}", identity, err);
+            return None;
         },
-        Err(err) => event!(
-            Level::ERROR,
-            "send_metrics_opengl_vecs:(Chart: {}, Series: {}) Error sending: {:?}",
-            chart_index,
-            series_index,
-            err
-        ),
     };
+    let chart = match charts.get_mut(identity.chart_index.0) {
+        Some(chart) if identity.series_index.0 < chart.sources.len() => chart,
+        _ => {
+            event!(Level::WARN, "load_samples:{} Chart or series index out of bounds", identity);
+            return None;
+        },
+    };
+    let series = chart.sources[identity.series_index.0].series_mut();
+    let loaded: usize = samples.into_iter().map(|sample| series.upsert(sample)).sum();
+    if loaded > 0 {
+        series.calculate_stats();
+    }
+    event!(Level::DEBUG, "load_samples:{} {} records loaded into TimeSeries", identity, loaded);
+    chart.update_epoch_skew(now);
+    chart.synchronize_series_epoch_range();
+    update_chart_vertices(chart, size);
+    let origin = (identity.chart_index, identity.series_index);
+    increment_loaded_items(charts, origin, now, loaded as f64, size);
+    Some(loaded)
 }
 
-/// `send_decorations_opengl_data` handles the async_coordinator task of type
-/// SendChartDecorationsOpenGLData, it returns the chart index as opengl vertices
-/// representation and the alpha through the channel parameter
-pub fn send_chart_decorations_opengl_data(
-    charts: &[TimeSeriesChart],
-    chart_index: usize,
-    data_index: usize,
-    channel: oneshot::Sender<(Vec<f32>, f32)>,
-) {
-    event!(Level::DEBUG, "send_decorations_vecs for chart_index: {}", chart_index);
-    match channel.send(
-        if chart_index >= charts.len() || data_index >= charts[chart_index].decorations.len() {
-            (vec![], 0f32)
-        } else {
+/// `load_initial_data_files` loads the initial_data_file of the Prometheus series, this is done
+/// at startup before polling begins. Returns the total number of items loaded.
+pub fn load_initial_data_files(charts: &mut [TimeSeriesChart], size: ChartSizeInfo) -> usize {
+    let mut total_loaded = 0usize;
+    for (chart_idx, chart) in charts.iter_mut().enumerate() {
+        let mut chart_loaded = 0usize;
+        for (series_idx, source) in chart.sources.iter_mut().enumerate() {
+            let prom = match source {
+                TimeSeriesSource::PrometheusTimeSeries(ref mut prom) => prom,
+                _ => continue,
+            };
+            let path = match prom.initial_data_file.clone() {
+                Some(path) => path,
+                None => continue,
+            };
+            match prom.load_from_file(&path) {
+                Ok(num_records) => {
+                    event!(
+                        Level::INFO,
+                        "load_initial_data_files:(Chart: {}, Series: {}) {} records from {:?}",
+                        chart_idx,
+                        series_idx,
+                        num_records,
+                        path
+                    );
+                    chart_loaded += num_records;
+                },
+                Err(err) => {
+                    event!(
+                        Level::ERROR,
+                        "load_initial_data_files:(Chart: {}, Series: {}) Error loading {:?}: {:?}",
+                        chart_idx,
+                        series_idx,
+                        path,
+                        err
+                    );
+                },
+            }
+        }
+        if chart_loaded > 0 {
+            chart.synchronize_series_epoch_range();
+            update_chart_vertices(chart, size);
+        }
+        total_loaded += chart_loaded;
+    }
+    total_loaded
+}
+
+/// `load_persisted_state` loads the samples the previous session kept in the state file, this is
+/// done at startup before polling begins. A snapshot of another format version or chart config
+/// loads nothing. Returns the number of samples loaded.
+pub fn load_persisted_state(
+    persistence: Option<&PersistenceConfig>,
+    charts: &mut [TimeSeriesChart],
+    size: ChartSizeInfo,
+) -> usize {
+    let path = match persistence {
+        Some(persistence) => &persistence.path,
+        None => return 0,
+    };
+    match persistence::load_snapshot(path, charts) {
+        Ok((header, loaded)) => {
             event!(
-                Level::DEBUG,
-                "send_decorations_opengl_data Sending vertices: {:?}",
-                charts[chart_index].decorations[data_index].opengl_vertices()
+                Level::INFO,
+                "load_persisted_state: {} samples from {:?}, written at {}",
+                loaded,
+                path,
+                header.created_epoch
             );
-            (
-                charts[chart_index].decorations[data_index].opengl_vertices(),
-                charts[chart_index].decorations[data_index].alpha(),
-            )
+            for chart in charts.iter_mut() {
+                chart.synchronize_series_epoch_range();
+                update_chart_vertices(chart, size);
+            }
+            loaded
         },
-    ) {
-        Ok(()) => {
-            event!(
-                Level::DEBUG,
-                "send_decorations_opengl_data: oneshot::message sent for index: {}",
-                chart_index
-            );
+        Err(SnapshotError::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+            event!(Level::INFO, "load_persisted_state: No state file at {:?}", path);
+            0
         },
-        Err(err) => event!(Level::ERROR, "send_decorations_opengl_data: Error sending: {:?}", err),
-    };
+        Err(err) => {
+            event!(Level::WARN, "load_persisted_state: Not loading {:?}: {}", path, err);
+            0
+        },
+    }
 }
 
-/// `change_display_size` handles changes to the Display resizes.
-/// It is debatable that we need to handle this message or return
-/// anything, so we'll just return a true ACK, the charts are updated
-/// after the size changes, potentially could be slow and we should delay
-/// until the size is stabilized.
-pub fn change_display_size(
-    charts: &mut Vec<TimeSeriesChart>,
-    size: &mut ChartSizeInfo,
-    height: f32,
-    width: f32,
-    padding_y: f32,
-    padding_x: f32,
-    channel: oneshot::Sender<bool>,
+/// `write_persisted_state` writes a snapshot of the charts to the state file
+fn write_persisted_state(persistence: &PersistenceConfig, charts: &[TimeSeriesChart], now: u64) {
+    match persistence::write_snapshot(&persistence.path, charts, now) {
+        Ok(bytes) => event!(
+            Level::DEBUG,
+            "write_persisted_state: {} bytes written to {:?}",
+            bytes,
+            persistence.path
+        ),
+        Err(err) => event!(
+            Level::ERROR,
+            "write_persisted_state: Unable to write {:?}: {}",
+            persistence.path,
+            err
+        ),
+    }
+}
+
+/// `send_metrics_opengl_vecs` handles the async_coordinator task of type
+/// SendMetricsOpenGLData, it sends the logged metrics as vertices
+/// representation through the channel parameter. The vertices are deduplicated
+/// for troubleshooting purposes mostly.
+pub(crate) fn send_metrics_opengl_vecs(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    channel: oneshot::Sender<(Vec<f32>, f32, u64)>,
 ) {
     event!(
         Level::DEBUG,
-        "change_display_size for height: {}, width: {}, padding_y: {}, padding_x: {}",
-        height,
-        width,
-        padding_y,
-        padding_x
+        "send_metrics_opengl_vecs:(Chart: {}, Series: {}): Request received from {:?}",
+        chart_index,
+        series_index,
+        window_id
     );
-    size.term_size.height = height;
-    size.term_size.width = width;
-    size.term_size.padding_y = padding_y;
-    size.term_size.padding_x = padding_x;
-    for chart in charts {
-        // Update the OpenGL representation when the display changes
-        chart.update_all_series_opengl_vecs(*size);
-    }
-    match channel.send(true) {
-        Ok(()) => event!(
-            Level::DEBUG,
-            "change_display_size: Sent reply back to resize notifier, new size: {:?}",
-            size
+    let key = VertexKey::Series(chart_index, series_index);
+    let opengl_data = windows.opengl_data(charts, window_id, key, |charts| {
+        let opengl_data = charts.get(chart_index.0).and_then(|chart| {
+            chart
+                .series_alpha(series_index.0)
+                .map(|alpha| (chart.get_styled_opengl_vecs(series_index.0), alpha))
+        });
+        if opengl_data.is_none() {
+            event!(
+                Level::WARN,
+                "send_metrics_opengl_vecs:(Chart: {}, Series: {}) Request for index out of bounds",
+                chart_index,
+                series_index
+            );
+        }
+        opengl_data
+    });
+    match channel.send(opengl_data) {
+        Ok(()) => {
+            event!(
+                Level::DEBUG,
+                "send_metrics_opengl_vecs:(Chart: {}, Series: {}) oneshot::message sent",
+                chart_index,
+                series_index
+            );
+        },
+        Err(err) => event!(
+            Level::ERROR,
+            "send_metrics_opengl_vecs:(Chart: {}, Series: {}) Error sending: {:?}",
+            chart_index,
+            series_index,
+            err
         ),
-        Err(err) => event!(Level::ERROR, "change_display_size: Error sending: {:?}", err),
     };
 }
 
-/// `async_coordinator` receives messages from the tasks about data loaded from
-/// the network, it owns the charts array and is the single point by which data can
-/// be loaded or requested. XXX: Config updates are not possible yet.
-pub async fn async_coordinator<U>(
-    mut rx: mpsc::Receiver<AsyncTask>,
-    mut chart_config: crate::charts::ChartsConfig,
-    size_info: SizeInfo,
-    event_proxy: U,
-) where
-    U: EventListener + Send + 'static,
-{
-    event!(Level::DEBUG, "async_coordinator: Starting, terminal size info: {:?}", size_info,);
-    // This Instant is synchronized with the decorations thread, mainly used so that decorations
-    // are ran under specific circumstances
-    chart_config.setup_chart_spacing();
-    for chart in &mut chart_config.charts {
-        // Calculate the spacing between charts
-        event!(Level::DEBUG, "Finishing setup for sources in chart: '{}'", chart.name);
-        for series in &mut chart.sources {
-            series.init();
-        }
-    }
-    let mut size = ChartSizeInfo { term_size: size_info, ..ChartSizeInfo::default() };
-    while let Some(message) = rx.recv().await {
-        event!(Level::DEBUG, "async_coordinator: message: {:?}", message);
-        match message {
-            AsyncTask::LoadResponse(req) => {
-                if let Some(_items) = load_http_response(&mut chart_config.charts, req, size) {
-                    chart_config.sync_latest_epoch(size);
-                    event_proxy.send_event(Event::ChartEvent);
-                }
-            },
-            AsyncTask::SendMetricsOpenGLData(chart_index, data_index, channel) => {
-                send_metrics_opengl_vecs(&chart_config.charts, chart_index, data_index, channel);
-            },
-            AsyncTask::SendChartDecorationsOpenGLData(chart_index, data_index, channel) => {
-                send_chart_decorations_opengl_data(
-                    &chart_config.charts,
-                    chart_index,
-                    data_index,
-                    channel,
-                );
-            },
-            AsyncTask::ChangeDisplaySize(height, width, padding_y, padding_x, channel) => {
-                change_display_size(
-                    &mut chart_config.charts,
-                    &mut size,
-                    height,
-                    width,
-                    padding_y,
-                    padding_x,
-                    channel,
-                );
-            },
-            AsyncTask::IncrementInputCounter(epoch, value) => {
-                increment_internal_counter(&mut chart_config.charts, "input", epoch, value, size);
-            },
-            AsyncTask::IncrementOutputCounter(epoch, value) => {
-                increment_internal_counter(&mut chart_config.charts, "output", epoch, value, size);
-            },
-            AsyncTask::DecorUpdate(_idx, _epoch_ms) => {
-                event_proxy.send_event(Event::DecorEvent);
-            },
-            AsyncTask::Shutdown => {
-                break;
-            },
-        };
+/// `send_live_edge_opengl_vecs` handles the async_coordinator task of type
+/// SendLiveEdgeOpenGLData, the segment is drawn with a fraction of the alpha of the series.
+pub(crate) fn send_live_edge_opengl_vecs(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    channel: oneshot::Sender<(Vec<f32>, f32, u64)>,
+) {
+    let key = VertexKey::LiveEdge(chart_index, series_index);
+    let opengl_data = windows.opengl_data(charts, window_id, key, |charts| {
+        let chart = charts.get(chart_index.0)?;
+        let alpha = chart.series_alpha(series_index.0)? * LIVE_EDGE_ALPHA;
+        Some((chart.get_live_edge_opengl_vecs(series_index.0), alpha))
+    });
+    if let Err(err) = channel.send(opengl_data) {
+        event!(
+            Level::ERROR,
+            "send_live_edge_opengl_vecs:(Chart: {}, Series: {}) Error sending: {:?}",
+            chart_index,
+            series_index,
+            err
+        );
     }
-    event!(Level::INFO, "async_coordinator: Exiting");
 }
-/// `fetch_prometheus_response` gets data from prometheus and once data is ready
-/// it sends the results to the coordinator.
-async fn fetch_prometheus_response(
-    item: MetricRequest,
-    tx: mpsc::Sender<AsyncTask>,
-) -> Result<(), ()> {
+
+/// `send_decorations_opengl_data` handles the async_coordinator task of type
+/// SendChartDecorationsOpenGLData, it returns the chart index as opengl vertices
+/// representation and the alpha through the channel parameter. The decorations hidden by their
+/// visibility group with the `toggles` of the charts and decorations reply without vertices.
+pub(crate) fn send_chart_decorations_opengl_data(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    decoration_index: DecorationIndex,
+    toggles: (bool, bool),
+    channel: oneshot::Sender<(Vec<f32>, f32, u64)>,
+) {
     event!(
         Level::DEBUG,
-        "fetch_prometheus_response:(Chart: {}, Series: {}) Starting",
-        item.chart_index,
-        item.series_index
+        "send_decorations_vecs for chart_index: {} from {:?}",
+        chart_index,
+        window_id
     );
-    let url = prometheus::PrometheusTimeSeries::prepare_url(&item.source_url, item.capacity as u64)
-        .unwrap();
-    let url_copy = item.source_url.clone();
-    let chart_index = item.chart_index;
-    let series_index = item.series_index;
-    let prom_res =
-        prometheus::get_from_prometheus(url.clone(), Some(Duration::from_secs(item.pull_interval)))
-            .await;
-    match prom_res {
-        Err(e) => {
-            // e contains (Uri, Err)
-            let (uri, error) = e;
-            if error.is_timeout() {
+    let key = VertexKey::Decoration(chart_index, decoration_index);
+    let (charts_visible, decorations_enabled) = toggles;
+    let visible = charts
+        .get(chart_index.0)
+        .and_then(|chart| chart.decorations.get(decoration_index.0))
+        .map_or(true, |decoration| {
+            decoration.visibility_group().is_visible(charts_visible, decorations_enabled)
+        });
+    let mut opengl_data = windows.opengl_data(charts, window_id, key, |charts| {
+        let chart = charts.get(chart_index.0);
+        match chart.and_then(|chart| chart.decorations.get(decoration_index.0)) {
+            Some(decoration) => {
                 event!(
-                    Level::INFO,
-                    "fetch_prometheus_response:(Chart: {}, Series: {}) TimeOut accesing: {}",
-                    chart_index,
-                    series_index,
-                    url_copy
+                    Level::DEBUG,
+                    "send_decorations_opengl_data Sending vertices: {:?}",
+                    decoration.opengl_vertices()
                 );
-            } else {
+                // The decorations of a chart that does not fit in the window are not drawn.
+                let hidden = chart.map_or(false, |chart| chart.hidden);
+                let vertices = if hidden { vec![] } else { decoration.opengl_vertices() };
+                Some((vertices, decoration.alpha()))
+            },
+            None => {
                 event!(
-                    Level::INFO,
-                    "fetch_prometheus_response:(Chart: {}, Series: {}) url={:?}, err={:?}",
+                    Level::WARN,
+                    "send_decorations_opengl_data:(Chart: {}, Decoration: {}) Request for index \
+                     out of bounds",
                     chart_index,
-                    series_index,
-                    uri,
-                    error
+                    decoration_index
                 );
-            };
-            // Instead of an error, return this so we can retry later.
-            // XXX: Maybe exponential retries in the future.
-            Ok(())
-        },
-        Ok(value) => {
+                None
+            },
+        }
+    });
+    // The cached vertices are kept for when the decoration is shown again.
+    if !visible {
+        opengl_data.0.clear();
+    }
+    match channel.send(opengl_data) {
+        Ok(()) => {
             event!(
                 Level::DEBUG,
-                "fetch_prometheus_response:(Chart: {}, Series: {}) Prometheus raw value={:?}",
-                chart_index,
-                series_index,
-                value
-            );
-            let res = prometheus::parse_json(&item.source_url, &value);
-            let tx_res = tx
-                .send(AsyncTask::LoadResponse(MetricRequest {
-                    source_url: item.source_url.clone(),
-                    chart_index: item.chart_index,
-                    series_index: item.series_index,
-                    pull_interval: item.pull_interval,
-                    data: res.clone(),
-                    capacity: item.capacity,
-                }))
-                .await;
-            if let Err(err) = tx_res {
-                event!(
-                    Level::ERROR,
-                    "fetch_prometheus_response:(Chart: {}, Series: {}) unable to send data back \
-                     to coordinator; err={:?}",
-                    chart_index,
-                    series_index,
-                    err
-                )
-            }
-            Ok(())
+                "send_decorations_opengl_data: oneshot::message sent for index: {}",
+                chart_index
+            );
         },
-    }
+        Err(err) => event!(Level::ERROR, "send_decorations_opengl_data: Error sending: {:?}", err),
+    };
 }
 
-/// `spawn_decoration_intervals` iterates over the charts and sources
-pub fn spawn_decoration_intervals(
-    charts_tx: mpsc::Sender<AsyncTask>,
-    tokio_handle: tokio::runtime::Handle,
+/// `send_series_capacity` handles the async_coordinator task of type SendSeriesCapacity, it
+/// replies with the current metrics capacity of a series, this may change at runtime when the
+/// series has adaptive_capacity enabled.
+pub fn send_series_capacity(
+    charts: &[TimeSeriesChart],
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    channel: oneshot::Sender<usize>,
 ) {
-    tokio_handle.spawn(async move {
-        // 10 FPS for decorations
-        let mut interval = time::interval(Duration::from_millis(100));
-        loop {
-            interval.tick().await;
-            match charts_tx.send(AsyncTask::DecorUpdate(0usize, 0f32)).await {
-                Ok(()) => {},
-                Err(err) => error!("Unable to send DecorUpdate: {:?}", err),
-            };
-        }
-    });
+    let source = charts.get(chart_index.0).and_then(|chart| chart.sources.get(series_index.0));
+    let capacity = match source {
+        Some(source) => source.series().metrics_capacity(),
+        None => {
+            // Dropping the channel lets the requester keep its current capacity.
+            event!(
+                Level::WARN,
+                "send_series_capacity:(Chart: {}, Series: {}) Request for index out of bounds",
+                chart_index,
+                series_index
+            );
+            return;
+        },
+    };
+    if let Err(err) = channel.send(capacity) {
+        event!(
+            Level::ERROR,
+            "send_series_capacity:(Chart: {}, Series: {}) Error sending: {:?}",
+            chart_index,
+            series_index,
+            err
+        );
+    }
 }
 
-/// `spawn_charts_intervals` iterates over the charts and sources
-/// and, if PrometheusTimeSeries it would call the spawn_datasource_interval_polls on it,
-/// that would be constantly loading data asynchronously.
-pub fn spawn_charts_intervals(
-    charts: Vec<TimeSeriesChart>,
-    charts_tx: mpsc::Sender<AsyncTask>,
-    tokio_handle: tokio::runtime::Handle,
-) {
-    for (chart_index, chart) in charts.into_iter().enumerate() {
-        for (series_index, series) in chart.sources.into_iter().enumerate() {
-            if let TimeSeriesSource::PrometheusTimeSeries(ref prom) = series {
+/// `set_window_seconds` handles the async_coordinator task of type SetWindowSeconds, the sources
+/// of the chart, or of all the charts when `chart_index` is None, are resized to hold `seconds`
+/// of samples. The polls of the series read the new capacity on their next request. Returns
+/// false if the window is empty and NotFound if the chart does not exist.
+pub fn set_window_seconds(
+    charts: &mut [TimeSeriesChart],
+    chart_index: Option<ChartIndex>,
+    seconds: u64,
+    size: ChartSizeInfo,
+) -> ChartTaskResult<bool> {
+    let charts = match chart_index {
+        Some(chart_index) => match charts.get_mut(chart_index.0) {
+            Some(chart) => std::slice::from_mut(chart),
+            None => {
                 event!(
-                    Level::DEBUG,
-                    "spawn_charts_intervals:(Chart: {}, Series: {}) - Adding interval run for '{}'",
-                    chart_index,
-                    series_index,
-                    chart.name
+                    Level::WARN,
+                    "set_window_seconds:(Chart: {}) Request for index out of bounds",
+                    chart_index
                 );
-                let data_request = MetricRequest {
-                    source_url: prom.source.clone(),
-                    pull_interval: prom.pull_interval as u64,
-                    chart_index,
-                    series_index,
-                    capacity: prom.series.metrics_capacity,
-                    data: None,
-                };
-                let charts_tx = charts_tx.clone();
-                tokio_handle.spawn(async move {
-                    spawn_datasource_interval_polls(&data_request, charts_tx).await.unwrap_or_else(
-                        |_| {
-                            panic!(
-                                "spawn_charts_intervals:(Chart: {}, Series: {}) Error spawning \
-                                 datasource internal polls",
-                                chart_index, series_index
-                            )
-                        },
-                    );
-                });
-            }
-        }
+                return Err(ChartTaskError::NotFound(chart_index));
+            },
+        },
+        None => charts,
+    };
+    if seconds == 0 {
+        event!(Level::WARN, "set_window_seconds: Ignoring an empty window");
+        return Ok(false);
     }
+    for chart in charts.iter_mut() {
+        event!(Level::DEBUG, "set_window_seconds:({}) window: {}s", chart.name, seconds);
+        chart.set_window(seconds as usize, size);
+    }
+    Ok(true)
 }
-/// `spawn_datasource_interval_polls` creates intervals for each series requested
-/// Each series will have to reply to a mspc tx with the data
-pub async fn spawn_datasource_interval_polls(
-    item: &MetricRequest,
-    tx: mpsc::Sender<AsyncTask>,
-) -> Result<(), ()> {
+
+/// `resize_series_capacity` handles the async_coordinator task of type ResizeSeriesCapacity, the
+/// series keeps its newest samples in epoch order and its polls read the new capacity on their
+/// next request. Returns false if the capacity did not change or the series does not exist.
+pub fn resize_series_capacity(
+    charts: &mut [TimeSeriesChart],
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    capacity: usize,
+    size: ChartSizeInfo,
+) -> bool {
+    let chart = match charts.get_mut(chart_index.0) {
+        Some(chart) if series_index.0 < chart.sources.len() => chart,
+        _ => {
+            event!(
+                Level::WARN,
+                "resize_series_capacity:(Chart: {}, Series: {}) Request for index out of bounds",
+                chart_index,
+                series_index
+            );
+            return false;
+        },
+    };
+    if capacity == 0 {
+        event!(Level::WARN, "resize_series_capacity: Ignoring an empty capacity");
+        return false;
+    }
     event!(
         Level::DEBUG,
-        "spawn_datasource_interval_polls:(Chart: {}, Series: {}) Starting for item={:?}",
-        item.chart_index,
-        item.series_index,
-        item
+        "resize_series_capacity:({}, Series: {}) capacity: {}",
+        chart.name,
+        series_index,
+        capacity
     );
-    let mut interval =
-        interval_at(tokio::time::Instant::now(), Duration::from_secs(item.pull_interval));
-    loop {
-        interval.tick().await;
-        let async_metric_item = MetricRequest {
-            source_url: item.source_url.clone(),
-            chart_index: item.chart_index,
-            series_index: item.series_index,
-            pull_interval: item.pull_interval,
-            data: None,
-            capacity: item.capacity,
-        };
-        event!(
-            Level::DEBUG,
-            "spawn_datasource_interval_polls:(Chart: {}, Series: {}) Interval triggered for {:?}",
-            async_metric_item.chart_index,
-            async_metric_item.series_index,
-            async_metric_item.source_url
-        );
-        match fetch_prometheus_response(async_metric_item.clone(), tx.clone()).await {
-            Ok(res) => {
-                event!(
-                    Level::DEBUG,
-                    "spawn_datasource_interval_polls:(Chart: {}, Series: {}) Response {:?}",
-                    async_metric_item.chart_index,
-                    async_metric_item.series_index,
-                    res
-                );
-            },
-            Err(()) => return Err(()),
-        }
-    }
-    // How do we return Ok(())?
+    chart.resize_series_capacity(series_index.0, capacity, size)
 }
 
-/// `get_metric_opengl_data` generates a oneshot::channel to communicate
-/// with the async coordinator and request the vectors of the metric_data
-/// or the decorations vertices, along with its alpha
-pub fn get_metric_opengl_data(
-    charts_tx: mpsc::Sender<AsyncTask>,
-    chart_idx: usize,
-    series_idx: usize,
-    request_type: &'static str,
-    tokio_handle: tokio::runtime::Handle,
-) -> (Vec<f32>, f32) {
-    let (opengl_tx, opengl_rx) = oneshot::channel();
-    let chart_idx_bkp = chart_idx;
-    tokio_handle.spawn(async move {
-        let get_metric_request = charts_tx.send(if request_type == "metric_data" {
-            AsyncTask::SendMetricsOpenGLData(chart_idx, series_idx, opengl_tx)
-        } else {
-            AsyncTask::SendChartDecorationsOpenGLData(chart_idx, series_idx, opengl_tx)
-        });
-        match get_metric_request.await {
-            Err(e) => event!(
-                Level::ERROR,
-                "get_metric_opengl_data:(Chart: {}, Series: {}) Sending {} Task. err={:?}",
-                chart_idx,
-                series_idx,
-                request_type,
-                e
+/// `clear_chart` handles the async_coordinator task of type ClearChart, the sources of the chart
+/// drop their samples and the next ones start a fresh window. Returns false if the chart does not
+/// exist.
+pub fn clear_chart(
+    charts: &mut [TimeSeriesChart],
+    chart_index: ChartIndex,
+    size: ChartSizeInfo,
+) -> bool {
+    let chart = match charts.get_mut(chart_index.0) {
+        Some(chart) => chart,
+        None => {
+            event!(
+                Level::WARN,
+                "clear_chart:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            return false;
+        },
+    };
+    event!(Level::DEBUG, "clear_chart:({}) sources: {}", chart.name, chart.sources.len());
+    chart.clear(size);
+    true
+}
+
+/// `send_chart_scissor_rect` handles the async_coordinator task of type SendChartScissorRect, it
+/// replies with the chart area in window pixels, None if the chart should not be scissored.
+pub(crate) fn send_chart_scissor_rect(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    channel: oneshot::Sender<Option<PixelRect>>,
+) {
+    // The chart area depends on the window size.
+    windows.activate(charts, window_id);
+    let rect = match charts.get(chart_index.0) {
+        Some(chart) => chart.scissor_rect(),
+        None => {
+            event!(
+                Level::WARN,
+                "send_chart_scissor_rect:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            None
+        },
+    };
+    if let Err(err) = channel.send(rect) {
+        event!(
+            Level::ERROR,
+            "send_chart_scissor_rect:(Chart: {}) Error sending: {:?}",
+            chart_index,
+            err
+        );
+    }
+}
+
+/// `send_chart_cell_rects` handles the async_coordinator task of type SendChartCellRects, it
+/// replies with the small multiples cell of each series in window pixels, empty if the series
+/// are not split into cells.
+pub(crate) fn send_chart_cell_rects(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    channel: oneshot::Sender<Vec<PixelRect>>,
+) {
+    // The cells depend on the window size.
+    windows.activate(charts, window_id);
+    let rects = match charts.get(chart_index.0) {
+        Some(chart) => chart.cell_scissor_rects(),
+        None => {
+            event!(
+                Level::WARN,
+                "send_chart_cell_rects:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            vec![]
+        },
+    };
+    if let Err(err) = channel.send(rects) {
+        event!(
+            Level::ERROR,
+            "send_chart_cell_rects:(Chart: {}) Error sending: {:?}",
+            chart_index,
+            err
+        );
+    }
+}
+
+/// `send_chart_timeline_bands` handles the async_coordinator task of type
+/// SendChartTimelineBands, it replies with the bands of the timeline sources of a chart in window
+/// pixels.
+pub(crate) fn send_chart_timeline_bands(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    channel: oneshot::Sender<Vec<TimelineBand>>,
+) {
+    // The bands depend on the window size.
+    windows.activate(charts, window_id);
+    let bands = match charts.get(chart_index.0) {
+        Some(chart) if !chart.hidden => chart.timeline_bands.clone(),
+        Some(_) => vec![],
+        None => {
+            event!(
+                Level::WARN,
+                "send_chart_timeline_bands:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            vec![]
+        },
+    };
+    if let Err(err) = channel.send(bands) {
+        event!(
+            Level::ERROR,
+            "send_chart_timeline_bands:(Chart: {}) Error sending: {:?}",
+            chart_index,
+            err
+        );
+    }
+}
+
+/// `send_chart_placeholder` handles the async_coordinator task of type SendChartPlaceholder, it
+/// replies with the placeholder of a chart that is awaiting data at the decoration `tick`, None
+/// once any of its sources loaded data.
+pub(crate) fn send_chart_placeholder(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    tick: u64,
+    channel: oneshot::Sender<Option<ChartPlaceholder>>,
+) {
+    // The chart area depends on the window size.
+    windows.activate(charts, window_id);
+    let placeholder = match charts.get(chart_index.0) {
+        Some(chart) => chart.placeholder(tick),
+        None => {
+            event!(
+                Level::WARN,
+                "send_chart_placeholder:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            None
+        },
+    };
+    if let Err(err) = channel.send(placeholder) {
+        event!(
+            Level::ERROR,
+            "send_chart_placeholder:(Chart: {}) Error sending: {:?}",
+            chart_index,
+            err
+        );
+    }
+}
+
+/// `send_chart_numeric_readout` handles the async_coordinator task of type
+/// SendChartNumericReadout, it replies with the latest value of a chart in Numeric style.
+pub(crate) fn send_chart_numeric_readout(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    chart_index: ChartIndex,
+    channel: oneshot::Sender<Option<NumericReadout>>,
+) {
+    // The readout is drawn inside of the chart area, which depends on the window size.
+    windows.activate(charts, window_id);
+    let readout = match charts.get(chart_index.0) {
+        Some(chart) => chart.numeric_readout(),
+        None => {
+            event!(
+                Level::WARN,
+                "send_chart_numeric_readout:(Chart: {}) Request for index out of bounds",
+                chart_index
+            );
+            None
+        },
+    };
+    if let Err(err) = channel.send(readout) {
+        event!(
+            Level::ERROR,
+            "send_chart_numeric_readout:(Chart: {}) Error sending: {:?}",
+            chart_index,
+            err
+        );
+    }
+}
+
+/// `debug_overlay_lines` describes the coordinator and each chart for the debug overlay: the
+/// name, rect, generation and age of the charts, the samples and last fetch of their sources
+/// and the vertices of their decorations. The lines are laid out for the active size.
+pub(crate) fn debug_overlay_lines(
+    chart_config: &ChartsConfig,
+    windows: &WindowCharts,
+    stats: &CoordinatorStats,
+    debug_state: &DebugOverlayState,
+    now_epoch: u64,
+    now: time::Instant,
+) -> Vec<OverlayLine> {
+    let mut entries = vec![OverlayEntry::new(
+        format!(
+            "charts: {} gen {} mem {}KiB requests {}/{}/{} peeks {}",
+            chart_config.charts.len(),
+            windows.generation(),
+            stats.memory_bytes / 1024,
+            stats.metrics_requests,
+            stats.decoration_requests,
+            stats.scissor_requests,
+            stats.generation_peeks,
+        ),
+        OVERLAY_FG,
+    )];
+    if debug_state.data_starved || !debug_state.starved_sources.is_empty() {
+        let state = if debug_state.data_starved { "data starved" } else { "sources starved" };
+        entries.push(OverlayEntry::new(
+            format!("watchdog: {} {}", state, debug_state.starved_sources.join(", ")),
+            OVERLAY_ERROR_FG,
+        ));
+    }
+    if let Some(p95) = debug_state.degraded_p95 {
+        entries.push(OverlayEntry::new(
+            format!(
+                "governor: degraded p95 {:.1}ms deferred {}",
+                p95.as_secs_f64() * 1000.,
+                debug_state.deferred_increments
             ),
-            Ok(_) => event!(
-                Level::DEBUG,
-                "get_metric_opengl_data:(Chart: {}, Series: {}) Sent Request for {} Task",
-                chart_idx,
-                series_idx,
-                request_type
+            OVERLAY_ERROR_FG,
+        ));
+    }
+    for anomaly in &debug_state.recent_anomalies {
+        entries.push(OverlayEntry::new(format!("journal: {}", anomaly), OVERLAY_ERROR_FG));
+    }
+    let tick = match debug_state.last_decoration_tick {
+        Some(tick) => timefmt::ago_millis(now.saturating_duration_since(tick).as_millis()),
+        None => String::from("never"),
+    };
+    for (chart_idx, chart) in chart_config.charts.iter().enumerate() {
+        let rect = chart.pixel_rect;
+        let updated = if chart.last_updated == 0 {
+            String::from("never")
+        } else {
+            timefmt::ago(now_epoch.saturating_sub(chart.last_updated))
+        };
+        let skew = match chart.epoch_skew_secs {
+            Some(skew) => format!(" skew {}s", skew),
+            None => String::new(),
+        };
+        let fill = match chart.fill_ratio() {
+            Some(ratio) if ratio < 1. => format!(" fill {:.0}%", ratio * 100.),
+            _ => String::new(),
+        };
+        entries.push(OverlayEntry::new(
+            format!(
+                "{} {:.0}x{:.0}+{:.0}+{:.0} gen {} updated {}{}{}",
+                chart.title(),
+                rect.width,
+                rect.height,
+                rect.x,
+                rect.y,
+                windows.generation(),
+                updated,
+                skew,
+                fill
             ),
+            OVERLAY_FG,
+        ));
+        for (series_idx, source) in chart.sources.iter().enumerate() {
+            let series = source.series_ref();
+            let samples = series.active_items;
+            let key = (ChartIndex(chart_idx), SeriesIndex(series_idx));
+            let (fetch, fg) = match (source, debug_state.fetches.get(&key)) {
+                (TimeSeriesSource::PrometheusTimeSeries(_), Some(FetchStatus::Loaded(items))) => {
+                    (format!(", fetch ok ({})", items), OVERLAY_FG)
+                },
+                (TimeSeriesSource::PrometheusTimeSeries(_), Some(FetchStatus::Failed)) => {
+                    (String::from(", fetch failed"), OVERLAY_ERROR_FG)
+                },
+                (TimeSeriesSource::PrometheusTimeSeries(_), None) => {
+                    (String::from(", fetch pending"), OVERLAY_FG)
+                },
+                _ => (String::new(), OVERLAY_FG),
+            };
+            // The gaps of the source are told apart from the padding of the sync of the charts.
+            let gaps = match (series.stats.missing_count, series.sync_padding_count()) {
+                (0, _) => String::new(),
+                (missing, 0) => format!(", {} gaps", missing),
+                (missing, padding) => {
+                    format!(", {} gaps ({} padded)", missing.saturating_sub(padding), padding)
+                },
+            };
+            let text = format!("  {}: {} samples{}{}", source.name(), samples, gaps, fetch);
+            entries.push(OverlayEntry::new(text, fg));
+            // The responses with too many entries are loaded partially.
+            if let TimeSeriesSource::PrometheusTimeSeries(prom) = source {
+                if let Some(hint) = prom.truncation_hint() {
+                    entries.push(OverlayEntry::new(format!("    {}", hint), OVERLAY_ERROR_FG));
+                }
+            }
         }
-    });
-    // .expect(&format!(
-    // "get_metric_opengl_data:(Chart: {}, Series: {}) Unable to spawn get_opengl_task",
-    // chart_idx, series_idx
-    // ));
-    tokio_handle.block_on(async {
-        match opengl_rx.await {
-            Ok(data) => {
-                event!(
-                    Level::DEBUG,
-                    "get_metric_opengl_data:(Chart: {}, Series: {}) Response from {} Task: {:?}",
-                    chart_idx_bkp,
-                    series_idx,
-                    request_type,
-                    data
-                );
-                data
-            },
-            Err(err) => {
-                event!(
-                    Level::ERROR,
-                    "get_metric_opengl_data:(Chart: {}, Series: {}) Error from {} Task: {:?}",
-                    chart_idx_bkp,
-                    series_idx,
-                    request_type,
-                    err
-                );
-                (vec![], 0f32)
-            },
+        for decoration in &chart.decorations {
+            let vertices = decoration.opengl_vertices().len() / 2;
+            let text = format!("  {}: {} vertices, tick {}", decoration.kind(), vertices, tick);
+            entries.push(OverlayEntry::new(text, OVERLAY_FG));
         }
-    })
+    }
+    let term_size = windows.active_size().term_size;
+    debug_overlay::layout(
+        &entries,
+        chart_config.debug_overlay_corner,
+        term_size.columns,
+        term_size.screen_lines,
+    )
 }
 
-/// `spawn_async_tasks` Starts a background thread to be used for tokio for async tasks
-pub fn spawn_async_tasks<U>(
-    chart_config: &ChartsConfig,
-    charts_tx: mpsc::Sender<AsyncTask>,
-    charts_rx: mpsc::Receiver<AsyncTask>,
-    handle_tx: std::sync::mpsc::Sender<tokio::runtime::Handle>,
-    size_info: SizeInfo,
-    event_proxy: U,
-) -> thread::JoinHandle<()>
-where
-    U: EventListener + Send + 'static,
-{
-    // let decor_config = config.decorations.clone();
-    let chart_config = chart_config.clone();
-    ::std::thread::Builder::new()
-        .name("async I/O".to_owned())
-        .spawn(move || {
-            let tokio_runtime =
-                tokio::runtime::Runtime::new().expect("Failed to start new tokio Runtime");
-            info!("Tokio runtime created.");
+/// `change_display_size` handles changes to the Display resizes.
+/// The charts are regenerated right away and a true ACK is returned, interactive resizes
+/// should use UpdateDisplaySize instead, which is debounced until the size is stabilized.
+pub(crate) fn change_display_size(
+    charts: &mut Vec<TimeSeriesChart>,
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    display_size: DisplaySize,
+    channel: oneshot::Sender<bool>,
+) {
+    let size = resize_window_charts(charts, windows, window_id, display_size);
+    match channel.send(true) {
+        Ok(()) => event!(
+            Level::DEBUG,
+            "change_display_size: Sent reply back to resize notifier, new size: {:?}",
+            size
+        ),
+        Err(err) => event!(Level::ERROR, "change_display_size: Error sending: {:?}", err),
+    };
+}
 
-            // Give a handle to the runtime back to the main thread.
-            handle_tx
-                .send(tokio_runtime.handle().clone())
-                .expect("Unable to give runtime handle to the main thread");
-            let chart_array = chart_config.charts.clone();
-            let async_chart_config = chart_config.clone();
-            let tokio_handle = tokio_runtime.handle().clone();
-            let charts_tx_cp = charts_tx.clone();
-            tokio_runtime.spawn(async {
-                spawn_charts_intervals(chart_array, charts_tx_cp, tokio_handle);
-            });
-            let tokio_handle = tokio_runtime.handle().clone();
-            tokio_runtime.spawn(async {
-                spawn_decoration_intervals(charts_tx, tokio_handle);
-            });
-            tokio_runtime.block_on(async {
-                async_coordinator(charts_rx, async_chart_config, size_info, event_proxy).await
-            });
-            info!("Tokio runtime finished.");
-        })
-        .expect("Unable to start async I/O thread")
+/// `apply_display_sizes` handles the display sizes sent with UpdateDisplaySize, the charts are
+/// regenerated once per window after the sizes have been stable for the debounce delay. Until
+/// then the replies carry the previous size generation, which the windows do not draw. Returns
+/// the number of windows resized.
+pub(crate) fn apply_display_sizes(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    debouncer: &mut DisplaySizeDebouncer,
+    now: time::Instant,
+) -> usize {
+    let ready = debouncer.take_ready(now);
+    for (window_id, display_size) in &ready {
+        resize_window_charts(charts, windows, *window_id, *display_size);
+    }
+    ready.len()
 }
 
-/// `run` is an example use of the crate without drawing the data.
-pub fn run<U>(config: Config, event_proxy: U)
-where
-    U: EventListener + Send + 'static,
-{
-    let chart_config = if let Some(chart_config) = config.charts {
-        chart_config
-    } else {
-        return;
-    };
-    let size_info = SizeInfo {
-        width: 100.,
-        height: 100.,
-        cell_width: 0.,
-        cell_height: 0.,
-        padding_x: 0.,
-        padding_y: 0.,
-        ..SizeInfo::default()
+/// `resize_window_charts` stores the new size of a window and regenerates the charts for it,
+/// returns the new size.
+fn resize_window_charts(
+    charts: &mut [TimeSeriesChart],
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    display_size: DisplaySize,
+) -> ChartSizeInfo {
+    event!(Level::DEBUG, "resize_window_charts for {:?} {:?}", window_id, display_size);
+    let mut size = windows.size(window_id);
+    size.term_size.height = display_size.height;
+    size.term_size.width = display_size.width;
+    size.term_size.padding_y = display_size.padding_y;
+    size.term_size.padding_x = display_size.padding_x;
+    // The charts laid out in cells follow the font size.
+    size.term_size.cell_width = display_size.cell_width;
+    size.term_size.cell_height = display_size.cell_height;
+    // The debouncer only keeps the latest size, so it is the one of the latest generation.
+    size.size_generation = windows.requested_generation(window_id);
+    windows.set_size(window_id, size);
+    for chart in charts.iter_mut() {
+        // Series with adaptive_capacity follow the chart width in pixels.
+        chart.adapt_series_capacity(size);
+    }
+    // Update the OpenGL representation when the display changes
+    windows.invalidate();
+    windows.regenerate(charts, window_id);
+    size
+}
+
+/// `change_scale_factor` handles the window moving to a monitor with a different device pixel
+/// ratio. The charts are configured in logical pixels so their layout is recalculated and every
+/// vertex is regenerated for the new ratio.
+pub(crate) fn change_scale_factor(
+    chart_config: &mut ChartsConfig,
+    windows: &mut WindowCharts,
+    window_id: WindowId,
+    dpr: f64,
+    channel: oneshot::Sender<bool>,
+) {
+    event!(Level::DEBUG, "change_scale_factor for {:?} dpr: {}", window_id, dpr);
+    let mut size = windows.size(window_id);
+    size.term_size.dpr = dpr;
+    windows.set_size(window_id, size);
+    chart_config.setup_chart_spacing();
+    for chart in chart_config.charts.iter_mut() {
+        // Series with adaptive_capacity follow the chart width in physical pixels.
+        chart.adapt_series_capacity(size);
+    }
+    windows.invalidate();
+    windows.regenerate(&mut chart_config.charts, window_id);
+    match channel.send(true) {
+        Ok(()) => event!(
+            Level::DEBUG,
+            "change_scale_factor: Sent reply back to scale factor notifier, new size: {:?}",
+            size
+        ),
+        Err(err) => event!(Level::ERROR, "change_scale_factor: Error sending: {:?}", err),
     };
-    // Create the channel that is used to communicate with the
-    // charts background task.
-    let (charts_tx, charts_rx) = mpsc::channel(4_096usize);
-    // Create a channel to receive a handle from Tokio
-    //
-    let (handle_tx, handle_rx) = std::sync::mpsc::channel();
-    // Start the Async I/O runtime, this needs to run in a background thread because in OSX, only
-    // the main thread can write to the graphics card.
-    let tokio_thread = spawn_async_tasks(
-        &chart_config,
-        charts_tx.clone(),
-        charts_rx,
-        handle_tx,
-        size_info,
-        event_proxy,
-    );
-    let tokio_handle =
-        handle_rx.recv().expect("Unable to get the tokio handle in a background thread");
+}
 
-    // Load some data, fetch the data and draw it.
-    tokio_handle.spawn(async move { charts_tx.send(AsyncTask::Shutdown).await });
+/// `check_data_watchdog` evaluates the data watchdog, if any, alerting on its transitions, and
+/// keeps its state for the debug overlay
+fn check_data_watchdog<U>(
+    watchdog: &mut Option<DataWatchdog>,
+    charts: &[TimeSeriesChart],
+    debug_state: &mut DebugOverlayState,
+    now: u64,
+    event_proxy: &U,
+) where
+    U: EventListener,
+{
+    if let Some(watchdog) = watchdog {
+        watchdog.check(charts, now, event_proxy);
+        debug_state.data_starved = watchdog.is_starved();
+        debug_state.starved_sources = watchdog.starved_sources();
+    }
+}
 
-    // Terminate the background therad:
-    tokio_thread.join().expect("Unable to shutdown tokio channel");
+/// `async_coordinator` receives messages from the tasks about data loaded from
+/// the network, it owns the charts array and is the single point by which data can
+/// be loaded or requested. XXX: Config updates are not possible yet.
+pub async fn async_coordinator<U>(
+    mut rx: mpsc::Receiver<AsyncTask>,
+    mut chart_config: crate::charts::ChartsConfig,
+    size_info: SizeInfo,
+    event_proxy: U,
+    status_tx: watch::Sender<ChartsStatus>,
+) where
+    U: EventListener + Send + 'static,
+{
+    event!(Level::DEBUG, "async_coordinator: Starting, terminal size info: {:?}", size_info,);
+    // This Instant is synchronized with the decorations thread, mainly used so that decorations
+    // are ran under specific circumstances
+    chart_config.setup_chart_spacing();
+    chart_config.apply_memory_budget();
+    event!(
+        Level::INFO,
+        "async_coordinator: The series are projected to use {} bytes",
+        chart_config.projected_memory_bytes()
+    );
+    for chart in &mut chart_config.charts {
+        // Calculate the spacing between charts
+        event!(Level::DEBUG, "Finishing setup for sources in chart: '{}'", chart.name);
+        for series in &mut chart.sources {
+            series.init();
+        }
+        chart.tick_schedule = TickSchedule::new(chart.update_hz());
+        if let Err(err) = chart.resolve_derived_series() {
+            event!(Level::ERROR, "async_coordinator: {}", err);
+        }
+        if let Err(err) = chart.validate_capacities() {
+            event!(Level::ERROR, "async_coordinator: {}", err);
+        }
+        if let Err(err) = chart.validate_decorations_space() {
+            event!(Level::WARN, "async_coordinator: {}", err);
+        }
+        if let Err(err) = chart.validate_smoothing() {
+            event!(Level::WARN, "async_coordinator: {}", err);
+        }
+        if let Err(err) = chart.validate_y_bounds() {
+            event!(Level::WARN, "async_coordinator: {}", err);
+        }
+    }
+    if let Err(err) = chart_config.validate_loaded_items_watchers() {
+        event!(Level::ERROR, "async_coordinator: {}", err);
+    }
+    let mut windows =
+        WindowCharts::new(ChartSizeInfo { term_size: size_info, ..ChartSizeInfo::default() });
+    // Charts open pre-populated when their series have an initial data file.
+    let mut loaded = load_initial_data_files(&mut chart_config.charts, windows.active_size());
+    // The samples kept by the previous session are restored too.
+    let persistence = chart_config.persistence.clone();
+    loaded +=
+        load_persisted_state(persistence.as_ref(), &mut chart_config.charts, windows.active_size());
+    if loaded > 0 {
+        chart_config.sync_latest_epoch(windows.active_size());
+    }
+    let resize_debounce = chart_config.resize_debounce_ms.unwrap_or(DEFAULT_RESIZE_DEBOUNCE_MS);
+    let mut debouncer = DisplaySizeDebouncer::new(Duration::from_millis(resize_debounce));
+    // The display may be unable to draw the charts, then there is no point generating vertices.
+    let mut rendering_available = true;
+    let mut stats = CoordinatorStats::default();
+    let mut debug_state = DebugOverlayState::default();
+    // Bumped when the window of the charts changes, the query_range polls fetch a grown window.
+    let (window_tx, _) = watch::channel(0u64);
+    // The decoration ticks drive the pulse of the charts awaiting data.
+    let mut decoration_tick = 0u64;
+    let mut pulse_schedule = TickSchedule::new(DEFAULT_UPDATE_HZ);
+    // The background decorations are drawn by the display, their updates ask it to redraw.
+    let mut decoration_schedules: Vec<TickSchedule> =
+        chart_config.decoration_rates.iter().map(|hz| TickSchedule::new(*hz)).collect();
+    // Whether the terminal shows the charts, the loaded data of hidden charts is not redrawn.
+    let mut charts_visible = true;
+    // Whether the terminal enables the decorations of the WithDecorToggle group.
+    let mut decorations_enabled = true;
+    // The time at which the terminal is asked to redraw the responses loaded since the last one.
+    let mut redraw_deadline: Option<time::Instant> = None;
+    let clock = CoordinatorClock::default();
+    // The loaded items are also watched while no response arrives.
+    let mut watchdog =
+        chart_config.data_watchdog.map(|config| DataWatchdog::new(config, clock.now()));
+    let mut watchdog_deadline = watchdog.as_ref().map(|_| time::Instant::now() + WATCHDOG_TICK);
+    // The handling time of the messages is sampled, an overloaded coordinator degrades the charts.
+    let mut governor = chart_config.load_governor.map(LoadGovernor::new);
+    let mut counter_buckets = CounterBuckets::default();
+    // The time at which the deferred counters are applied while the charts are degraded.
+    let mut governor_deadline: Option<time::Instant> = None;
+    // The anomalies of the loaded data are kept for postmortems.
+    let mut journal = chart_config.journal.clone().map(AnomalyJournal::new);
+    // The state file is written on shutdown and, when configured, periodically.
+    let mut snapshots =
+        SnapshotSchedule::new(persistence.as_ref().and_then(|config| config.snapshot_interval_s));
+    let mut snapshot_deadline =
+        snapshots.is_enabled().then(|| time::Instant::now() + SNAPSHOT_TICK);
+    loop {
+        let deadline = [
+            debouncer.deadline(),
+            redraw_deadline,
+            watchdog_deadline,
+            governor_deadline,
+            snapshot_deadline,
+        ]
+        .into_iter()
+        .flatten()
+        .min();
+        let message = match deadline {
+            Some(deadline) => tokio::select! {
+                message = rx.recv() => message,
+                _ = time::sleep_until(deadline) => {
+                    let now = time::Instant::now();
+                    apply_display_sizes(
+                        &mut chart_config.charts,
+                        &mut windows,
+                        &mut debouncer,
+                        now,
+                    );
+                    if windows.take_pending_regeneration() {
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                    if redraw_deadline.map_or(false, |deadline| deadline <= now) {
+                        redraw_deadline = None;
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                    if watchdog_deadline.map_or(false, |deadline| deadline <= now) {
+                        watchdog_deadline = Some(now + WATCHDOG_TICK);
+                        check_data_watchdog(
+                            &mut watchdog,
+                            &chart_config.charts,
+                            &mut debug_state,
+                            clock.now(),
+                            &event_proxy,
+                        );
+                    }
+                    if snapshot_deadline.map_or(false, |deadline| deadline <= now) {
+                        snapshot_deadline = Some(now + SNAPSHOT_TICK);
+                        if let Some(persistence) = persistence.as_ref() {
+                            let epoch = clock.now();
+                            if snapshots.due(epoch) {
+                                write_persisted_state(persistence, &chart_config.charts, epoch);
+                            }
+                        }
+                    }
+                    if governor_deadline.map_or(false, |deadline| deadline <= now) {
+                        governor_deadline = Some(now + DEGRADED_FLUSH_INTERVAL);
+                        flush_counter_buckets(
+                            &mut chart_config,
+                            &mut windows,
+                            &mut counter_buckets,
+                        );
+                        debug_state.deferred_increments = 0;
+                        if let Some(transition) = governor.as_mut().and_then(|g| g.tick(now)) {
+                            governor_deadline = report_governor_transition(
+                                transition,
+                                &mut chart_config,
+                                &mut windows,
+                                &mut counter_buckets,
+                                &status_tx,
+                                &mut debug_state,
+                            );
+                        }
+                    }
+                    continue;
+                },
+            },
+            None => rx.recv().await,
+        };
+        let message = match message {
+            Some(message) => message,
+            None => break,
+        };
+        match &message {
+            AsyncTask::LoadResponse(response) => {
+                // The responses may hold thousands of samples, only their summary is logged.
+                event!(
+                    Level::DEBUG,
+                    "async_coordinator: message: LoadResponse(Chart: {}, Series: {}) {}",
+                    response.chart_index,
+                    response.series_index,
+                    response.data.as_ref().map_or(String::from("without data"), |data| {
+                        prometheus::ResponseSummary::new(data, None).to_string()
+                    })
+                );
+                event!(Level::TRACE, "async_coordinator: message: {:?}", message);
+            },
+            AsyncTask::LoadSamples { identity, samples } => {
+                event!(
+                    Level::DEBUG,
+                    "async_coordinator: message: LoadSamples({}) {}",
+                    identity,
+                    samples.as_ref().map_or_else(
+                        |err| format!("failed: {}", err),
+                        |samples| format!("{} samples", samples.len())
+                    )
+                );
+                event!(Level::TRACE, "async_coordinator: message: {:?}", message);
+            },
+            _ => event!(Level::DEBUG, "async_coordinator: message: {:?}", message),
+        }
+        if !rendering_available && !matches!(message, AsyncTask::Shutdown) {
+            // The reply channels are dropped, the requesters get an error instead of data.
+            event!(Level::TRACE, "async_coordinator: Rendering unavailable, dropping message");
+            continue;
+        }
+        if matches!(message, AsyncTask::Shutdown) {
+            // The running values of the live edges are kept as samples.
+            for chart in &mut chart_config.charts {
+                chart.commit_live_edges(u64::MAX);
+            }
+            if let Some(persistence) = persistence.as_ref() {
+                write_persisted_state(persistence, &chart_config.charts, clock.now());
+            }
+            break;
+        }
+        #[cfg(test)]
+        if let AsyncTask::ForceGovernorMode(mode) = message {
+            let now = time::Instant::now();
+            if let Some(transition) = governor.as_mut().and_then(|g| g.force(mode, now)) {
+                governor_deadline = report_governor_transition(
+                    transition,
+                    &mut chart_config,
+                    &mut windows,
+                    &mut counter_buckets,
+                    &status_tx,
+                    &mut debug_state,
+                );
+            }
+            continue;
+        }
+        let received = time::Instant::now();
+        let degraded = governor.as_ref().map_or(false, |governor| governor.is_degraded());
+        // A bug in the charts must not take the terminal down, the coordinator stops handling
+        // requests after a panic and the requesters fall back to their defaults.
+        let handled = panic::catch_unwind(AssertUnwindSafe(|| match message {
+            task @ (AsyncTask::LoadResponse(_) | AsyncTask::LoadSamples { .. }) => {
+                let size = windows.active_size();
+                let now = clock.now();
+                let (chart_index, series_index, loaded) = match task {
+                    AsyncTask::LoadResponse(req) => {
+                        let (chart_index, series_index) = (req.chart_index, req.series_index);
+                        // The loaded samples are compared with the series as it was before the
+                        // response.
+                        let baseline = journal.as_ref().and_then(|_| {
+                            let chart = chart_config.charts.get(chart_index.0)?;
+                            let source = chart.sources.get(series_index.0)?;
+                            let summary =
+                                prometheus::ResponseSummary::new(req.data.as_ref()?, None);
+                            Some((SeriesBaseline::new(source.series_ref()), summary.to_string()))
+                        });
+                        let interval = req.pull_interval;
+                        let loaded = load_http_response(&mut chart_config.charts, req, size, now);
+                        if let (Some(journal), Some((baseline, summary)), Some(items)) =
+                            (journal.as_mut(), baseline, loaded)
+                        {
+                            let added = chart_config.charts.get(chart_index.0).map_or(0, |chart| {
+                                journal.check(
+                                    chart,
+                                    series_index,
+                                    &baseline,
+                                    items,
+                                    interval,
+                                    &summary,
+                                    now,
+                                )
+                            });
+                            if added > 0 {
+                                debug_state.recent_anomalies =
+                                    journal.recent(JOURNAL_OVERLAY_ENTRIES, &chart_config.timezone);
+                            }
+                        }
+                        (chart_index, series_index, loaded)
+                    },
+                    AsyncTask::LoadSamples { identity, samples } => {
+                        let charts = &mut chart_config.charts;
+                        let loaded = load_samples(charts, &identity, samples, size, now);
+                        (identity.chart_index, identity.series_index, loaded)
+                    },
+                    _ => unreachable!("only the load tasks reach this arm"),
+                };
+                let status = loaded.map_or(FetchStatus::Failed, FetchStatus::Loaded);
+                debug_state.fetches.insert((chart_index, series_index), status);
+                if let (Some(watchdog), Some(items)) = (watchdog.as_mut(), loaded) {
+                    watchdog.record(chart_index, series_index, items, now);
+                }
+                check_data_watchdog(
+                    &mut watchdog,
+                    &chart_config.charts,
+                    &mut debug_state,
+                    now,
+                    &event_proxy,
+                );
+                if loaded.is_some() {
+                    chart_config.sync_latest_epoch(size);
+                    windows.invalidate();
+                    // Only the charts that can be seen wake up the terminal, the responses that
+                    // arrive in a burst are redrawn at once. The responses loaded before the
+                    // display size are redrawn when it arrives.
+                    let redraw = charts_visible
+                        && chart_config
+                            .charts
+                            .get(chart_index.0)
+                            .map_or(false, |chart| chart.redraws_on_load());
+                    if redraw && windows.notify_change() && redraw_deadline.is_none() {
+                        // The redraws wait for the deferred counters while degraded.
+                        let batch =
+                            if degraded { DEGRADED_FLUSH_INTERVAL } else { LOAD_REDRAW_BATCH };
+                        redraw_deadline = Some(time::Instant::now() + batch);
+                    }
+                }
+            },
+            AsyncTask::SendMetricsOpenGLData(window_id, chart_index, series_index, channel) => {
+                stats.metrics_requests += 1;
+                send_metrics_opengl_vecs(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    series_index,
+                    channel,
+                );
+            },
+            AsyncTask::SendLiveEdgeOpenGLData(window_id, chart_index, series_index, channel) => {
+                send_live_edge_opengl_vecs(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    series_index,
+                    channel,
+                );
+            },
+            AsyncTask::SendChartDecorationsOpenGLData(
+                window_id,
+                chart_index,
+                decoration_index,
+                channel,
+            ) => {
+                stats.decoration_requests += 1;
+                send_chart_decorations_opengl_data(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    decoration_index,
+                    (charts_visible, decorations_enabled),
+                    channel,
+                );
+            },
+            AsyncTask::SendSeriesCapacity(chart_index, series_index, channel) => {
+                send_series_capacity(&chart_config.charts, chart_index, series_index, channel);
+            },
+            AsyncTask::SendChartScissorRect(window_id, chart_index, channel) => {
+                stats.scissor_requests += 1;
+                send_chart_scissor_rect(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    channel,
+                );
+            },
+            AsyncTask::SendChartCellRects(window_id, chart_index, channel) => {
+                send_chart_cell_rects(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    channel,
+                );
+            },
+            AsyncTask::SendChartTimelineBands(window_id, chart_index, channel) => {
+                send_chart_timeline_bands(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    channel,
+                );
+            },
+            AsyncTask::ChangeDisplaySize(
+                window_id,
+                height,
+                width,
+                padding_y,
+                padding_x,
+                cell_width,
+                cell_height,
+                channel,
+            ) => {
+                // A pending debounced size is older than this one.
+                debouncer.discard(window_id);
+                windows.request_size(window_id);
+                let size =
+                    DisplaySize { height, width, padding_y, padding_x, cell_width, cell_height };
+                change_display_size(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    size,
+                    channel,
+                );
+                if windows.take_pending_regeneration() {
+                    event_proxy.send_event(Event::ChartEvent);
+                }
+            },
+            AsyncTask::UpdateDisplaySize(
+                window_id,
+                height,
+                width,
+                padding_y,
+                padding_x,
+                cell_width,
+                cell_height,
+            ) => {
+                // Interactive resizes send many sizes, the charts are regenerated once the size
+                // is stable.
+                let size =
+                    DisplaySize { height, width, padding_y, padding_x, cell_width, cell_height };
+                windows.request_size(window_id);
+                debouncer.push(window_id, size, time::Instant::now());
+            },
+            AsyncTask::ChangeScaleFactor(window_id, dpr, channel) => {
+                change_scale_factor(&mut chart_config, &mut windows, window_id, dpr, channel);
+            },
+            AsyncTask::IncrementInputCounter(value) => {
+                let deferred = degraded.then_some(&mut counter_buckets);
+                let (charts, now) = (&mut chart_config, clock.now());
+                increment_or_defer(charts, &mut windows, deferred, "input", now, value);
+            },
+            AsyncTask::IncrementOutputCounter(value) => {
+                let deferred = degraded.then_some(&mut counter_buckets);
+                let (charts, now) = (&mut chart_config, clock.now());
+                increment_or_defer(charts, &mut windows, deferred, "output", now, value);
+            },
+            AsyncTask::IncrementScrollCounter(value) => {
+                let deferred = degraded.then_some(&mut counter_buckets);
+                let (charts, now) = (&mut chart_config, clock.now());
+                increment_or_defer(charts, &mut windows, deferred, "scroll", now, value);
+            },
+            AsyncTask::IncrementSelectionCounter(value) => {
+                let deferred = degraded.then_some(&mut counter_buckets);
+                let (charts, now) = (&mut chart_config, clock.now());
+                increment_or_defer(charts, &mut windows, deferred, "selection", now, value);
+            },
+            AsyncTask::DecorUpdate(_idx, elapsed_ms) => {
+                // The decorations pause while the charts are degraded.
+                if degraded {
+                    return;
+                }
+                let elapsed = Duration::from_secs_f64(f64::from(elapsed_ms.max(0f32)) / 1000f64);
+                // The display is only asked to redraw when a decoration actually updated.
+                let mut updated = false;
+                for schedule in decoration_schedules.iter_mut() {
+                    updated |= schedule.advance(elapsed);
+                }
+                // The pulse of the hidden charts is suspended rather than kept running.
+                if pulse_schedule.advance(elapsed) && charts_visible {
+                    decoration_tick = decoration_tick.wrapping_add(1);
+                    updated |= chart_config.charts.iter().any(|chart| chart.awaiting_data());
+                }
+                // The transitions of the animated charts advance with their own cadence.
+                let mut animating = false;
+                for chart in chart_config.charts.iter_mut() {
+                    if chart.tick_schedule.advance(elapsed) {
+                        animating |= chart.advance_animations();
+                    }
+                }
+                if animating {
+                    windows.invalidate();
+                    updated = true;
+                }
+                // The running values of the seconds that completed become samples.
+                let (now, size) = (clock.now(), windows.active_size());
+                let mut committed = false;
+                for chart in chart_config.charts.iter_mut() {
+                    if chart.commit_live_edges(now) {
+                        update_chart_vertices(chart, size);
+                        committed = true;
+                    }
+                }
+                if committed {
+                    windows.invalidate();
+                    updated = true;
+                }
+                debug_state.last_decoration_tick = Some(time::Instant::now());
+                if updated {
+                    event_proxy.send_event(Event::DecorEvent);
+                }
+            },
+            AsyncTask::SendChartNumericReadout(window_id, chart_index, channel) => {
+                send_chart_numeric_readout(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    channel,
+                );
+            },
+            AsyncTask::SendChartPlaceholder(window_id, chart_index, channel) => {
+                send_chart_placeholder(
+                    &mut chart_config.charts,
+                    &mut windows,
+                    window_id,
+                    chart_index,
+                    decoration_tick,
+                    channel,
+                );
+            },
+            AsyncTask::SendChartSeriesStyles(chart_index, channel) => {
+                let styles = chart_config
+                    .charts
+                    .get(chart_index.0)
+                    .map(|chart| chart.series_styles())
+                    .unwrap_or_default();
+                if let Err(err) = channel.send(styles) {
+                    event!(Level::ERROR, "async_coordinator: Series styles reply: {:?}", err);
+                }
+            },
+            AsyncTask::LoadLabelValues(chart_index, series_index, values, channel) => {
+                let (requests, retired) = load_label_values(
+                    &mut chart_config.charts,
+                    chart_index,
+                    series_index,
+                    &values,
+                    windows.active_size(),
+                );
+                if !requests.is_empty() || !retired.is_empty() {
+                    windows.invalidate();
+                    if windows.notify_change() {
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                }
+                if let Err(err) = channel.send((requests, retired)) {
+                    event!(Level::ERROR, "async_coordinator: Discovery reply: {:?}", err);
+                }
+            },
+            AsyncTask::PeekGenerations(channel) => {
+                stats.generation_peeks += 1;
+                // All the charts vertices are invalidated together for now.
+                let generation = windows.generation();
+                let generations = (0..chart_config.charts.len())
+                    .map(|chart_index| (ChartIndex(chart_index), generation))
+                    .collect();
+                if let Err(err) = channel.send(generations) {
+                    event!(Level::ERROR, "async_coordinator: Generations reply: {:?}", err);
+                }
+            },
+            AsyncTask::SendCoordinatorStats(channel) => {
+                let stats = CoordinatorStats { memory_bytes: chart_config.memory_bytes(), ..stats };
+                if let Err(err) = channel.send(stats) {
+                    event!(Level::ERROR, "async_coordinator: Stats reply: {:?}", err);
+                }
+            },
+            AsyncTask::DebugOverlayData(channel) => {
+                let stats = CoordinatorStats { memory_bytes: chart_config.memory_bytes(), ..stats };
+                let lines = debug_overlay_lines(
+                    &chart_config,
+                    &windows,
+                    &stats,
+                    &debug_state,
+                    clock.now(),
+                    time::Instant::now(),
+                );
+                if let Err(err) = channel.send(lines) {
+                    event!(Level::ERROR, "async_coordinator: Debug overlay reply: {:?}", err);
+                }
+            },
+            AsyncTask::SetWindowSeconds(chart_index, seconds, channel) => {
+                let size = windows.active_size();
+                let changed =
+                    set_window_seconds(&mut chart_config.charts, chart_index, seconds, size);
+                if changed == Ok(true) {
+                    windows.invalidate();
+                    window_tx.send_modify(|generation| *generation = generation.wrapping_add(1));
+                    if windows.notify_change() {
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                }
+                if let Err(err) = channel.send(changed) {
+                    event!(Level::ERROR, "async_coordinator: Window reply: {:?}", err);
+                }
+            },
+            AsyncTask::ResizeSeriesCapacity(chart_index, series_index, capacity) => {
+                let size = windows.active_size();
+                let charts = &mut chart_config.charts;
+                if resize_series_capacity(charts, chart_index, series_index, capacity, size) {
+                    // A grown window is fetched right away, like a wider time window.
+                    windows.invalidate();
+                    window_tx.send_modify(|generation| *generation = generation.wrapping_add(1));
+                    if windows.notify_change() {
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                }
+            },
+            AsyncTask::ClearChart(chart_index) => {
+                let size = windows.active_size();
+                if clear_chart(&mut chart_config.charts, chart_index, size) {
+                    windows.invalidate();
+                    if windows.notify_change() {
+                        event_proxy.send_event(Event::ChartEvent);
+                    }
+                }
+            },
+            AsyncTask::SubscribeWindowChanges(channel) => {
+                if let Err(err) = channel.send(window_tx.subscribe()) {
+                    event!(Level::ERROR, "async_coordinator: Window subscription reply: {:?}", err);
+                }
+            },
+            AsyncTask::SetPalette(colors, channel) => {
+                let unknown = set_palette(&mut chart_config, &mut windows, colors);
+                if windows.notify_change() {
+                    event_proxy.send_event(Event::ChartEvent);
+                }
+                if let Err(err) = channel.send(unknown) {
+                    event!(Level::ERROR, "async_coordinator: Palette reply: {:?}", err);
+                }
+            },
+            AsyncTask::GetJournal(channel) => {
+                let entries = journal.as_ref().map(AnomalyJournal::entries).unwrap_or_default();
+                if let Err(err) = channel.send(entries) {
+                    event!(Level::ERROR, "async_coordinator: Journal reply: {:?}", err);
+                }
+            },
+            AsyncTask::SummarizeSince { epoch, reply } => {
+                let summaries = chart_config.summarize_since(epoch, clock.now());
+                if let Err(err) = reply.send(summaries) {
+                    event!(Level::ERROR, "async_coordinator: Summaries reply: {:?}", err);
+                }
+            },
+            AsyncTask::SetChartsVisible(visible) => {
+                event!(Level::DEBUG, "async_coordinator: Charts visible: {}", visible);
+                charts_visible = visible;
+            },
+            AsyncTask::SetDecorationsEnabled(enabled) => {
+                event!(Level::DEBUG, "async_coordinator: Decorations enabled: {}", enabled);
+                decorations_enabled = enabled;
+            },
+            AsyncTask::DisableRendering => {
+                event!(Level::WARN, "async_coordinator: Charts rendering is unavailable");
+                rendering_available = false;
+            },
+            AsyncTask::Shutdown => {},
+            #[cfg(test)]
+            AsyncTask::Poison => panic!("async_coordinator: Poisoned task"),
+            #[cfg(test)]
+            AsyncTask::FreezeClock(epoch) => clock.freeze(epoch),
+            #[cfg(test)]
+            AsyncTask::ForceGovernorMode(_) => {},
+            #[cfg(test)]
+            AsyncTask::SendSeriesValues(chart_index, series_index, channel) => {
+                let values = chart_config
+                    .charts
+                    .get(chart_index.0)
+                    .and_then(|chart| chart.sources.get(series_index.0))
+                    .map(|source| source.series_ref().as_vec())
+                    .unwrap_or_default();
+                let _ = channel.send(values);
+            },
+        }));
+        if let Err(payload) = handled {
+            event!(
+                Level::ERROR,
+                payload = panic_message(payload.as_ref()),
+                "async_coordinator: Charts disabled after a panic"
+            );
+            rendering_available = false;
+            status_tx.send_replace(ChartsStatus::Disabled);
+            continue;
+        }
+        if let Some(governor) = governor.as_mut() {
+            let now = time::Instant::now();
+            debug_state.deferred_increments = counter_buckets.increments();
+            if let Some(transition) = governor.record(now.saturating_duration_since(received), now)
+            {
+                governor_deadline = report_governor_transition(
+                    transition,
+                    &mut chart_config,
+                    &mut windows,
+                    &mut counter_buckets,
+                    &status_tx,
+                    &mut debug_state,
+                );
+            }
+        }
+    }
+    event!(Level::INFO, "async_coordinator: Exiting");
+}
+/// `spawn_named` spawns a task on the runtime. When built with the `console` feature and
+/// `--cfg tokio_unstable` the task is named so that tokio-console can tell the tasks apart.
+pub fn spawn_named<F>(
+    tokio_handle: &tokio::runtime::Handle,
+    name: &str,
+    future: F,
+) -> tokio::task::JoinHandle<F::Output>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    #[cfg(all(tokio_unstable, feature = "console"))]
+    {
+        tokio::task::Builder::new()
+            .name(name)
+            .spawn_on(future, tokio_handle)
+            .unwrap_or_else(|err| panic!("Unable to spawn task {}: {:?}", name, err))
+    }
+    #[cfg(not(all(tokio_unstable, feature = "console")))]
+    {
+        event!(Level::TRACE, task = name, "spawn_named: Spawning task");
+        tokio_handle.spawn(future)
+    }
+}
+
+/// `source_host` returns the host of a source URL, used to identify the datasource tasks
+pub fn source_host(source_url: &str) -> String {
+    match reqwest::Url::parse(source_url) {
+        Ok(url) => url.host_str().unwrap_or_default().to_string(),
+        Err(_) => String::new(),
+    }
+}
+
+/// `datasource_span` is the root span of the task polling a series of a chart
+pub fn datasource_span(chart_name: &str, item: &MetricRequest) -> tracing::Span {
+    span!(
+        Level::INFO,
+        "datasource_poll",
+        chart = chart_name,
+        chart_index = item.chart_index.0,
+        series_index = item.series_index.0,
+        host = source_host(&item.source_url).as_str(),
+        pull_interval = item.pull_interval,
+    )
+}
+
+/// `spawn_decoration_intervals` sends a decoration tick every `period`, each with the time
+/// elapsed since the previous one
+pub fn spawn_decoration_intervals(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    period: Duration,
+    tokio_handle: tokio::runtime::Handle,
+) {
+    let span = span!(Level::INFO, "decoration_interval");
+    let decorations = async move {
+        // The ticks follow the fastest decoration, the slower ones skip them.
+        let mut interval = time::interval(period);
+        let mut previous = interval.tick().await;
+        loop {
+            let now = interval.tick().await;
+            let elapsed_ms = now.saturating_duration_since(previous).as_secs_f32() * 1000f32;
+            previous = now;
+            match charts_tx.send(AsyncTask::DecorUpdate(ChartIndex(0), elapsed_ms)).await {
+                Ok(()) => {},
+                Err(err) => event!(Level::ERROR, error = ?err, "Unable to send DecorUpdate"),
+            };
+        }
+    };
+    spawn_named(&tokio_handle, "decorations", decorations.instrument(span));
+}
+
+/// `spawn_charts_intervals` iterates over the charts and sources
+/// and, if PrometheusTimeSeries it would call the spawn_datasource_interval_polls on it,
+/// that would be constantly loading data asynchronously. Sources with discovery also start the
+/// task that polls the label values.
+pub fn spawn_charts_intervals(
+    charts: Vec<TimeSeriesChart>,
+    startup_retries: StartupRetries,
+    limiter: FetchLimiter,
+    charts_tx: mpsc::Sender<AsyncTask>,
+    tokio_handle: tokio::runtime::Handle,
+) {
+    for (chart_index, chart) in charts.into_iter().enumerate() {
+        for (series_index, series) in chart.sources.into_iter().enumerate() {
+            if let TimeSeriesSource::PrometheusTimeSeries(ref prom) = series {
+                event!(
+                    Level::DEBUG,
+                    "spawn_charts_intervals:(Chart: {}, Series: {}) - Adding interval run for '{}'",
+                    chart_index,
+                    series_index,
+                    chart.name
+                );
+                let data_request =
+                    series_metric_request(ChartIndex(chart_index), SeriesIndex(series_index), prom);
+                spawn_series_poll(
+                    &tokio_handle,
+                    &chart.name,
+                    data_request,
+                    startup_retries,
+                    limiter.clone(),
+                    charts_tx.clone(),
+                );
+                if let Some(discover) = &prom.discover {
+                    let span = span!(
+                        Level::INFO,
+                        "discovery_poll",
+                        chart = chart.name.as_str(),
+                        chart_index,
+                        series_index,
+                        label = discover.label.as_str(),
+                    );
+                    let task_name = format!("discovery:{}:{}", chart.name, series_index);
+                    let discovery = spawn_discovery_interval_polls(
+                        chart.name.clone(),
+                        ChartIndex(chart_index),
+                        SeriesIndex(series_index),
+                        discover.clone(),
+                        startup_retries,
+                        limiter.clone(),
+                        charts_tx.clone(),
+                        tokio_handle.clone(),
+                    );
+                    spawn_named(&tokio_handle, &task_name, discovery.instrument(span));
+                }
+            }
+        }
+    }
+}
+
+/// `series_metric_request` returns the request that polls a prometheus series
+pub fn series_metric_request(
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    prom: &prometheus::PrometheusTimeSeries,
+) -> MetricRequest {
+    MetricRequest {
+        source_url: prom.source.clone(),
+        pull_interval: prom.pull_interval as u64,
+        chart_index,
+        series_index,
+        capacity: prom.series.metrics_capacity(),
+        data: None,
+        max_body_bytes: prom.max_response_bytes.unwrap_or(prometheus::MAX_RESPONSE_BODY_BYTES),
+    }
+}
+
+/// `spawn_series_poll` spawns the task that polls a prometheus series of a chart
+fn spawn_series_poll(
+    tokio_handle: &tokio::runtime::Handle,
+    chart_name: &str,
+    data_request: MetricRequest,
+    startup_retries: StartupRetries,
+    limiter: FetchLimiter,
+    charts_tx: mpsc::Sender<AsyncTask>,
+) -> tokio::task::JoinHandle<()> {
+    let span = datasource_span(chart_name, &data_request);
+    let task_name = format!("chart:{}:{}", chart_name, data_request.series_index);
+    let poll = async move {
+        // A failed poll only stops this series, it must not take the runtime down.
+        let polls =
+            spawn_datasource_interval_polls(&data_request, startup_retries, limiter, charts_tx);
+        if polls.await.is_err() {
+            event!(
+                Level::ERROR,
+                "spawn_charts_intervals:(Chart: {}, Series: {}) Error spawning datasource \
+                 internal polls",
+                data_request.chart_index,
+                data_request.series_index
+            );
+        }
+    };
+    spawn_named(tokio_handle, &task_name, poll.instrument(span))
+}
+
+/// `spawn_discovery_interval_polls` requests the label values of a discovery on every interval
+/// and sends them to the coordinator, the series that the coordinator starts are polled by
+/// tasks owned by this one, retired series have their task aborted.
+#[allow(clippy::too_many_arguments)]
+pub async fn spawn_discovery_interval_polls(
+    chart_name: String,
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+    discover: prometheus::DiscoveryConfig,
+    startup_retries: StartupRetries,
+    limiter: FetchLimiter,
+    tx: mpsc::Sender<AsyncTask>,
+    tokio_handle: tokio::runtime::Handle,
+) {
+    let url = match discover.label_values_url() {
+        Ok(url) => url,
+        Err(err) => {
+            event!(Level::ERROR, error = %err, "Unable to build the label values URL");
+            return;
+        },
+    };
+    let period = Duration::from_secs(discover.interval.max(1));
+    let start = tokio::time::Instant::now() + startup_jitter(period, &mut rand::thread_rng());
+    let mut interval = interval_at(start, period);
+    let mut polls: HashMap<SeriesIndex, tokio::task::JoinHandle<()>> = HashMap::new();
+    loop {
+        interval.tick().await;
+        let response = {
+            let _permit = limiter.acquire().await;
+            prometheus::get_from_prometheus(url.clone(), Some(period), None).await
+        };
+        // A failed request does not retire the series, the values are requested again later.
+        let values = match response {
+            Ok(body) => match prometheus::parse_label_values(&body) {
+                Ok(values) => values,
+                Err(err) => {
+                    event!(Level::INFO, error = ?err, "Unable to parse the label values");
+                    continue;
+                },
+            },
+            Err((uri, err)) => {
+                event!(Level::INFO, url = %uri, error = ?err, "label values fetch failed");
+                continue;
+            },
+        };
+        event!(Level::DEBUG, values = values.len(), "Label values loaded");
+        let (changes_tx, changes_rx) = oneshot::channel();
+        let task = AsyncTask::LoadLabelValues(chart_index, series_index, values, changes_tx);
+        if tx.send(task).await.is_err() {
+            break;
+        }
+        let (started, retired) = match changes_rx.await {
+            Ok(changes) => changes,
+            Err(_) => break,
+        };
+        for idx in retired {
+            if let Some(poll) = polls.remove(&idx) {
+                poll.abort();
+            }
+        }
+        for request in started {
+            // A reused slot may still have the task of its previous series.
+            if let Some(poll) = polls.remove(&request.series_index) {
+                poll.abort();
+            }
+            let idx = request.series_index;
+            let poll = spawn_series_poll(
+                &tokio_handle,
+                &chart_name,
+                request,
+                startup_retries,
+                limiter.clone(),
+                tx.clone(),
+            );
+            polls.insert(idx, poll);
+        }
+    }
+    for poll in polls.into_values() {
+        poll.abort();
+    }
+}
+
+/// `spawn_datasource_interval_polls` creates intervals for each series requested
+/// Each series will have to reply to a mspc tx with the data
+pub async fn spawn_datasource_interval_polls(
+    item: &MetricRequest,
+    startup_retries: StartupRetries,
+    limiter: FetchLimiter,
+    tx: mpsc::Sender<AsyncTask>,
+) -> Result<(), ()> {
+    event!(Level::DEBUG, item = ?item, "spawn_datasource_interval_polls: Starting");
+    let source = PrometheusSource::new(item.clone());
+    fetcher::poll_source(&source, PollOptions::new(startup_retries, limiter), tx).await
+}
+
+/// `subscribe_window_changes` requests a receiver of the window changes from the
+/// async_coordinator, None is returned if the coordinator is not available.
+async fn subscribe_window_changes(tx: &mpsc::Sender<AsyncTask>) -> Option<watch::Receiver<u64>> {
+    let (window_tx, window_rx) = oneshot::channel();
+    tx.send(AsyncTask::SubscribeWindowChanges(window_tx)).await.ok()?;
+    window_rx.await.ok()
+}
+
+/// `window_changed` resolves to true when the window of the charts changes, it never resolves
+/// without a subscription. The subscription is dropped once the coordinator is gone.
+async fn window_changed(window_rx: &mut Option<watch::Receiver<u64>>) -> bool {
+    match window_rx {
+        Some(receiver) => {
+            if receiver.changed().await.is_ok() {
+                return true;
+            }
+            *window_rx = None;
+            false
+        },
+        None => std::future::pending().await,
+    }
+}
+
+/// `get_series_capacity` requests the current metrics capacity of a series from the
+/// async_coordinator, None is returned if the coordinator is not available.
+async fn get_series_capacity(
+    tx: &mpsc::Sender<AsyncTask>,
+    chart_index: ChartIndex,
+    series_index: SeriesIndex,
+) -> Option<usize> {
+    let (capacity_tx, capacity_rx) = oneshot::channel();
+    tx.send(AsyncTask::SendSeriesCapacity(chart_index, series_index, capacity_tx)).await.ok()?;
+    capacity_rx.await.ok()
+}
+
+/// `get_metric_opengl_data` generates a oneshot::channel to communicate
+/// with the async coordinator and request the vectors of the metric_data
+/// or the decorations vertices, along with its alpha and the size generation of the window they
+/// were generated for
+pub fn get_metric_opengl_data(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    request: OpenGLDataRequest,
+    tokio_handle: tokio::runtime::Handle,
+) -> (Vec<f32>, f32, u64) {
+    let (opengl_tx, opengl_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let get_metric_request = charts_tx.send(match request {
+            OpenGLDataRequest::Series(series_idx) => {
+                AsyncTask::SendMetricsOpenGLData(window_id, chart_idx, series_idx, opengl_tx)
+            },
+            OpenGLDataRequest::LiveEdge(series_idx) => {
+                AsyncTask::SendLiveEdgeOpenGLData(window_id, chart_idx, series_idx, opengl_tx)
+            },
+            OpenGLDataRequest::Decoration(decoration_idx) => {
+                AsyncTask::SendChartDecorationsOpenGLData(
+                    window_id,
+                    chart_idx,
+                    decoration_idx,
+                    opengl_tx,
+                )
+            },
+        });
+        match get_metric_request.await {
+            Err(e) => event!(
+                Level::ERROR,
+                "get_metric_opengl_data:(Chart: {}, {:?}) Sending Task. err={:?}",
+                chart_idx,
+                request,
+                e
+            ),
+            Ok(_) => event!(
+                Level::DEBUG,
+                "get_metric_opengl_data:(Chart: {}, {:?}) Sent Request",
+                chart_idx,
+                request
+            ),
+        }
+    });
+    match block_on_reply(&tokio_handle, opengl_rx) {
+        Ok(data) => {
+            event!(
+                Level::DEBUG,
+                "get_metric_opengl_data:(Chart: {}, {:?}) Response from Task: {:?}",
+                chart_idx,
+                request,
+                data
+            );
+            data
+        },
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_metric_opengl_data:(Chart: {}, {:?}) Error from Task: {:?}",
+                chart_idx,
+                request,
+                err
+            );
+            (vec![], 0f32, 0)
+        },
+    }
+}
+
+/// `get_chart_scissor_rect` requests the area of a chart in window pixels from the
+/// async_coordinator so that the renderer can restrict the chart draw calls to it.
+pub fn get_chart_scissor_rect(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Option<PixelRect> {
+    let (rect_tx, rect_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let request = AsyncTask::SendChartScissorRect(window_id, chart_idx, rect_tx);
+        if let Err(err) = charts_tx.send(request).await {
+            event!(
+                Level::ERROR,
+                "get_chart_scissor_rect:(Chart: {}) Sending Task. err={:?}",
+                chart_idx,
+                err
+            );
+        }
+    });
+    match block_on_reply(&tokio_handle, rect_rx) {
+        Ok(rect) => rect,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_scissor_rect:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            None
+        },
+    }
+}
+
+/// `get_chart_cell_rects` requests the small multiples cells of a chart in window pixels from
+/// the async_coordinator so that the renderer can restrict each series to its cell.
+pub fn get_chart_cell_rects(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<PixelRect> {
+    let (rects_tx, rects_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let request = AsyncTask::SendChartCellRects(window_id, chart_idx, rects_tx);
+        if let Err(err) = charts_tx.send(request).await {
+            event!(
+                Level::ERROR,
+                "get_chart_cell_rects:(Chart: {}) Sending Task. err={:?}",
+                chart_idx,
+                err
+            );
+        }
+    });
+    match block_on_reply(&tokio_handle, rects_rx) {
+        Ok(rects) => rects,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_cell_rects:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            vec![]
+        },
+    }
+}
+
+/// `get_chart_timeline_bands` requests the bands of the timeline sources of a chart in window
+/// pixels from the async_coordinator
+pub fn get_chart_timeline_bands(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<TimelineBand> {
+    let (bands_tx, bands_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let request = AsyncTask::SendChartTimelineBands(window_id, chart_idx, bands_tx);
+        if let Err(err) = charts_tx.send(request).await {
+            event!(
+                Level::ERROR,
+                "get_chart_timeline_bands:(Chart: {}) Sending Task. err={:?}",
+                chart_idx,
+                err
+            );
+        }
+    });
+    match block_on_reply(&tokio_handle, bands_rx) {
+        Ok(bands) => bands,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_timeline_bands:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            vec![]
+        },
+    }
+}
+
+/// `get_chart_generations` requests the generation of the vertices of each chart, the request is
+/// cheap compared to requesting the vertices themselves.
+pub fn get_chart_generations(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<(ChartIndex, u64)> {
+    let (generations_tx, generations_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        if let Err(err) = charts_tx.send(AsyncTask::PeekGenerations(generations_tx)).await {
+            event!(Level::ERROR, "get_chart_generations: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, generations_rx) {
+        Ok(generations) => generations,
+        Err(err) => {
+            event!(Level::ERROR, "get_chart_generations: Error from Task: {:?}", err);
+            vec![]
+        },
+    }
+}
+
+/// `get_debug_overlay_data` requests the lines of the debug overlay, they describe the state of
+/// the coordinator and the charts.
+pub fn get_debug_overlay_data(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<OverlayLine> {
+    let (overlay_tx, overlay_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        if let Err(err) = charts_tx.send(AsyncTask::DebugOverlayData(overlay_tx)).await {
+            event!(Level::ERROR, "get_debug_overlay_data: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, overlay_rx) {
+        Ok(lines) => lines,
+        Err(err) => {
+            event!(Level::ERROR, "get_debug_overlay_data: Error from Task: {:?}", err);
+            vec![]
+        },
+    }
+}
+
+/// `set_charts_window` asks the coordinator to resize the sources of a chart, or of all the
+/// charts when `chart_idx` is None, to a window of `seconds`. Returns false when the window was
+/// not changed and NotFound when the chart does not exist.
+pub fn set_charts_window(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    chart_idx: Option<ChartIndex>,
+    seconds: u64,
+    tokio_handle: tokio::runtime::Handle,
+) -> ChartTaskResult<bool> {
+    let (changed_tx, changed_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let task = AsyncTask::SetWindowSeconds(chart_idx, seconds, changed_tx);
+        if let Err(err) = charts_tx.send(task).await {
+            event!(Level::ERROR, "set_charts_window: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, changed_rx) {
+        Ok(changed) => changed,
+        Err(err) => {
+            event!(Level::ERROR, "set_charts_window: Error from Task: {:?}", err);
+            Ok(false)
+        },
+    }
+}
+
+/// `summarize_charts_since` asks the coordinator for the summary of each chart since `epoch`,
+/// there are no summaries when the coordinator does not reply.
+pub fn summarize_charts_since(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    epoch: u64,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<ChartSummary> {
+    let (reply, summaries_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        if let Err(err) = charts_tx.send(AsyncTask::SummarizeSince { epoch, reply }).await {
+            event!(Level::ERROR, "summarize_charts_since: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, summaries_rx) {
+        Ok(summaries) => summaries,
+        Err(err) => {
+            event!(Level::ERROR, "summarize_charts_since: Error from Task: {:?}", err);
+            vec![]
+        },
+    }
+}
+
+/// `set_charts_palette` sends the colors of the terminal to the coordinator, at startup and when
+/// the colors are reloaded. Returns the names of the chart colors that are unknown.
+pub fn set_charts_palette(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    colors: Vec<(String, Rgb)>,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<String> {
+    let (unknown_tx, unknown_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        if let Err(err) = charts_tx.send(AsyncTask::SetPalette(colors, unknown_tx)).await {
+            event!(Level::ERROR, "set_charts_palette: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, unknown_rx) {
+        Ok(unknown) => unknown,
+        Err(err) => {
+            event!(Level::ERROR, "set_charts_palette: Error from Task: {:?}", err);
+            vec![]
+        },
+    }
+}
+
+/// `get_chart_series_styles` requests the color and line pattern of each series of a chart, the
+/// discovered series are only known to the coordinator.
+pub fn get_chart_series_styles(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Vec<SeriesStyle> {
+    let (styles_tx, styles_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let task = AsyncTask::SendChartSeriesStyles(chart_idx, styles_tx);
+        if let Err(err) = charts_tx.send(task).await {
+            event!(Level::ERROR, "get_chart_series_styles: Sending Task. err={:?}", err);
+        }
+    });
+    match block_on_reply(&tokio_handle, styles_rx) {
+        Ok(styles) => styles,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_series_styles:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            vec![]
+        },
+    }
+}
+
+/// `get_chart_placeholder` requests the placeholder of a chart that is awaiting data from the
+/// async_coordinator, None is returned once the chart has data.
+pub fn get_chart_placeholder(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Option<ChartPlaceholder> {
+    let (placeholder_tx, placeholder_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let request = AsyncTask::SendChartPlaceholder(window_id, chart_idx, placeholder_tx);
+        if let Err(err) = charts_tx.send(request).await {
+            event!(
+                Level::ERROR,
+                "get_chart_placeholder:(Chart: {}) Sending Task. err={:?}",
+                chart_idx,
+                err
+            );
+        }
+    });
+    match block_on_reply(&tokio_handle, placeholder_rx) {
+        Ok(placeholder) => placeholder,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_placeholder:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            None
+        },
+    }
+}
+
+/// `get_chart_numeric_readout` requests the latest value of a chart in Numeric style from the
+/// async_coordinator, None is returned for charts drawn as a graph.
+pub fn get_chart_numeric_readout(
+    charts_tx: mpsc::Sender<AsyncTask>,
+    window_id: WindowId,
+    chart_idx: ChartIndex,
+    tokio_handle: tokio::runtime::Handle,
+) -> Option<NumericReadout> {
+    let (readout_tx, readout_rx) = oneshot::channel();
+    tokio_handle.spawn(async move {
+        let request = AsyncTask::SendChartNumericReadout(window_id, chart_idx, readout_tx);
+        if let Err(err) = charts_tx.send(request).await {
+            event!(
+                Level::ERROR,
+                "get_chart_numeric_readout:(Chart: {}) Sending Task. err={:?}",
+                chart_idx,
+                err
+            );
+        }
+    });
+    match block_on_reply(&tokio_handle, readout_rx) {
+        Ok(readout) => readout,
+        Err(err) => {
+            event!(
+                Level::ERROR,
+                "get_chart_numeric_readout:(Chart: {}) Error from Task: {:?}",
+                chart_idx,
+                err
+            );
+            None
+        },
+    }
+}
+
+/// `ChartsVertexCache` keeps the vertices drawn by a window, the coordinator is only asked for
+/// them again once the generation of their chart changes, so that redraws of the terminal, i.e.
+/// cursor blinking or typing, do not request the chart vertices. The decorations of the charts
+/// follow the same generation, their animations are ticked by the display decorations.
+#[derive(Debug, Default)]
+pub struct ChartsVertexCache {
+    /// The generation of each chart the cached data belongs to
+    generations: HashMap<ChartIndex, u64>,
+
+    /// The vertices, alpha and size generation of each (chart, series)
+    series: HashMap<(ChartIndex, SeriesIndex), (Vec<f32>, f32, u64)>,
+
+    /// The live edge segment, alpha and size generation of each (chart, series)
+    live_edges: HashMap<(ChartIndex, SeriesIndex), (Vec<f32>, f32, u64)>,
+
+    /// The vertices, alpha and size generation of each (chart, decoration)
+    decorations: HashMap<(ChartIndex, DecorationIndex), (Vec<f32>, f32, u64)>,
+
+    /// The scissor rect of each chart
+    scissor_rects: HashMap<ChartIndex, Option<PixelRect>>,
+
+    /// The small multiples cells of each chart
+    cell_rects: HashMap<ChartIndex, Vec<PixelRect>>,
+
+    /// The bands of the timeline sources of each chart
+    timeline_bands: HashMap<ChartIndex, Vec<TimelineBand>>,
+
+    /// The color and line pattern of each series of a chart, this includes the discovered series
+    series_styles: HashMap<ChartIndex, Vec<SeriesStyle>>,
+
+    /// The charts that received data, they do not await data again for the rest of the session
+    loaded: HashSet<ChartIndex>,
+
+    /// The latest value of each chart in Numeric style
+    numeric_readouts: HashMap<ChartIndex, Option<NumericReadout>>,
+
+    /// The display sizes sent by the window, the vertices of other size generations are stale
+    size_generation: u64,
+}
+
+impl ChartsVertexCache {
+    /// `resize` must be called when the window sends a new display size to the coordinator, the
+    /// cached data of every chart is dropped and the vertices generated for the previous sizes
+    /// are stale until the coordinator applies the new size.
+    pub fn resize(&mut self) {
+        self.size_generation = self.size_generation.wrapping_add(1);
+        self.generations.clear();
+    }
+
+    /// `size_generation` returns the size generation the vertices must be generated for
+    pub fn size_generation(&self) -> u64 {
+        self.size_generation
+    }
+
+    /// `refresh` peeks the generation of the charts and drops the cached data of the charts that
+    /// changed, returns the number of charts that changed.
+    pub fn refresh(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> usize {
+        let mut changed = 0;
+        for (chart_id, generation) in get_chart_generations(charts_tx, tokio_handle) {
+            if self.generations.insert(chart_id, generation) != Some(generation) {
+                self.series.retain(|(cached_chart_id, _), _| *cached_chart_id != chart_id);
+                self.live_edges.retain(|(cached_chart_id, _), _| *cached_chart_id != chart_id);
+                self.decorations.retain(|(cached_chart_id, _), _| *cached_chart_id != chart_id);
+                self.scissor_rects.remove(&chart_id);
+                self.cell_rects.remove(&chart_id);
+                self.timeline_bands.remove(&chart_id);
+                self.series_styles.remove(&chart_id);
+                self.numeric_readouts.remove(&chart_id);
+                changed += 1;
+            }
+        }
+        changed
+    }
+
+    /// `series_data` returns the vertices, alpha and size generation of a series, requesting
+    /// them if not cached
+    pub fn series_data(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        series_idx: SeriesIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &(Vec<f32>, f32, u64) {
+        self.series.entry((chart_id, series_idx)).or_insert_with(|| {
+            let request = OpenGLDataRequest::Series(series_idx);
+            get_metric_opengl_data(charts_tx, window_id, chart_id, request, tokio_handle)
+        })
+    }
+
+    /// `live_edge_data` returns the live edge segment, alpha and size generation of a series,
+    /// requesting them if not cached
+    pub fn live_edge_data(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        series_idx: SeriesIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &(Vec<f32>, f32, u64) {
+        self.live_edges.entry((chart_id, series_idx)).or_insert_with(|| {
+            let request = OpenGLDataRequest::LiveEdge(series_idx);
+            get_metric_opengl_data(charts_tx, window_id, chart_id, request, tokio_handle)
+        })
+    }
+
+    /// `decoration_data` returns the vertices, alpha and size generation of a decoration,
+    /// requesting them if not cached
+    pub fn decoration_data(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        decoration_idx: DecorationIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &(Vec<f32>, f32, u64) {
+        self.decorations.entry((chart_id, decoration_idx)).or_insert_with(|| {
+            let request = OpenGLDataRequest::Decoration(decoration_idx);
+            get_metric_opengl_data(charts_tx, window_id, chart_id, request, tokio_handle)
+        })
+    }
+
+    /// `series_styles` returns the color and line pattern of each series of a chart, requesting
+    /// them if not cached
+    pub fn series_styles(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &[SeriesStyle] {
+        self.series_styles
+            .entry(chart_id)
+            .or_insert_with(|| get_chart_series_styles(charts_tx, chart_id, tokio_handle))
+    }
+
+    /// `placeholder` returns the placeholder of a chart awaiting data, it is requested on every
+    /// draw as it pulses with the decoration ticks, until the chart reports it has data.
+    pub fn placeholder(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> Option<ChartPlaceholder> {
+        if self.loaded.contains(&chart_id) {
+            return None;
+        }
+        let placeholder = get_chart_placeholder(charts_tx, window_id, chart_id, tokio_handle);
+        if placeholder.is_none() {
+            self.loaded.insert(chart_id);
+        }
+        placeholder
+    }
+
+    /// `numeric_readout` returns the latest value of a chart in Numeric style, requesting it if
+    /// not cached
+    pub fn numeric_readout(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> Option<&NumericReadout> {
+        self.numeric_readouts
+            .entry(chart_id)
+            .or_insert_with(|| {
+                get_chart_numeric_readout(charts_tx, window_id, chart_id, tokio_handle)
+            })
+            .as_ref()
+    }
+
+    /// `scissor_rect` returns the scissor rect of a chart, requesting it if not cached
+    pub fn scissor_rect(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> Option<PixelRect> {
+        *self
+            .scissor_rects
+            .entry(chart_id)
+            .or_insert_with(|| get_chart_scissor_rect(charts_tx, window_id, chart_id, tokio_handle))
+    }
+
+    /// `cell_rects` returns the small multiples cells of a chart, requesting them if not cached
+    pub fn cell_rects(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &[PixelRect] {
+        self.cell_rects
+            .entry(chart_id)
+            .or_insert_with(|| get_chart_cell_rects(charts_tx, window_id, chart_id, tokio_handle))
+    }
+
+    /// `timeline_bands` returns the bands of the timeline sources of a chart, requesting them if
+    /// not cached
+    pub fn timeline_bands(
+        &mut self,
+        charts_tx: mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+        chart_id: ChartIndex,
+        tokio_handle: tokio::runtime::Handle,
+    ) -> &[TimelineBand] {
+        self.timeline_bands.entry(chart_id).or_insert_with(|| {
+            get_chart_timeline_bands(charts_tx, window_id, chart_id, tokio_handle)
+        })
+    }
+
+    /// `timeline_readout` returns the state of the cached timeline band under the x,y window
+    /// pixel, the bands are not requested so it can be called on every mouse move.
+    pub fn timeline_readout(&self, x: f32, y: f32) -> Option<TimelineReadout> {
+        let mut charts: Vec<&ChartIndex> = self.timeline_bands.keys().collect();
+        order::stable_order_by_key(&mut charts, |chart_id| **chart_id);
+        charts.into_iter().find_map(|chart_id| {
+            self.timeline_bands[chart_id].iter().find_map(|band| band.readout_at(x, y))
+        })
+    }
+}
+
+/// `spawn_async_tasks` Starts a background thread to be used for tokio for async tasks
+pub fn spawn_async_tasks<U>(
+    chart_config: &ChartsConfig,
+    charts_tx: mpsc::Sender<AsyncTask>,
+    charts_rx: mpsc::Receiver<AsyncTask>,
+    handle_tx: std::sync::mpsc::Sender<tokio::runtime::Handle>,
+    size_info: SizeInfo,
+    event_proxy: U,
+    status_tx: watch::Sender<ChartsStatus>,
+) -> thread::JoinHandle<()>
+where
+    U: EventListener + Send + 'static,
+{
+    // let decor_config = config.decorations.clone();
+    let chart_config = chart_config.clone();
+    ::std::thread::Builder::new()
+        .name("async I/O".to_owned())
+        .spawn(move || {
+            #[cfg(feature = "console")]
+            init_console_subscriber();
+            let tokio_runtime =
+                tokio::runtime::Runtime::new().expect("Failed to start new tokio Runtime");
+            info!("Tokio runtime created.");
+
+            // Give a handle to the runtime back to the main thread.
+            handle_tx
+                .send(tokio_runtime.handle().clone())
+                .expect("Unable to give runtime handle to the main thread");
+            let chart_array = chart_config.charts.clone();
+            let startup_retries = StartupRetries::from(&chart_config);
+            let limiter = FetchLimiter::from(&chart_config);
+            let async_chart_config = chart_config.clone();
+            let tokio_handle = tokio_runtime.handle().clone();
+            let charts_tx_cp = charts_tx.clone();
+            tokio_runtime.spawn(async move {
+                spawn_charts_intervals(
+                    chart_array,
+                    startup_retries,
+                    limiter,
+                    charts_tx_cp,
+                    tokio_handle,
+                );
+            });
+            let tokio_handle = tokio_runtime.handle().clone();
+            let decoration_period = chart_config.decoration_tick_interval();
+            tokio_runtime.spawn(async move {
+                spawn_decoration_intervals(charts_tx, decoration_period, tokio_handle);
+            });
+            let coordinator_span = span!(Level::INFO, "async_coordinator");
+            tokio_runtime.block_on(
+                async_coordinator(charts_rx, async_chart_config, size_info, event_proxy, status_tx)
+                    .instrument(coordinator_span),
+            );
+            info!("Tokio runtime finished.");
+        })
+        .expect("Unable to start async I/O thread")
+}
+
+/// `init_console_subscriber` serves the tasks to tokio-console, only the first call installs
+/// the subscriber as every window starts its own async tasks.
+#[cfg(feature = "console")]
+fn init_console_subscriber() {
+    static INIT: std::sync::Once = std::sync::Once::new();
+    INIT.call_once(console_subscriber::init);
+}
+
+/// `run` is an example use of the crate without drawing the data.
+pub fn run<U>(config: Config, event_proxy: U)
+where
+    U: EventListener + Send + 'static,
+{
+    let chart_config = if let Some(chart_config) = config.charts {
+        chart_config
+    } else {
+        return;
+    };
+    let size_info = SizeInfo {
+        width: 100.,
+        height: 100.,
+        cell_width: 0.,
+        cell_height: 0.,
+        padding_x: 0.,
+        padding_y: 0.,
+        ..SizeInfo::default()
+    };
+    // Create the channel that is used to communicate with the
+    // charts background task.
+    let (charts_tx, charts_rx) = mpsc::channel(4_096usize);
+    // Create a channel to receive a handle from Tokio
+    //
+    let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+    // Start the Async I/O runtime, this needs to run in a background thread because in OSX, only
+    // the main thread can write to the graphics card.
+    let tokio_thread = spawn_async_tasks(
+        &chart_config,
+        charts_tx.clone(),
+        charts_rx,
+        handle_tx,
+        size_info,
+        event_proxy,
+        watch::channel(ChartsStatus::Running).0,
+    );
+    let tokio_handle =
+        handle_rx.recv().expect("Unable to get the tokio handle in a background thread");
+
+    // Load some data, fetch the data and draw it.
+    tokio_handle.spawn(async move { charts_tx.send(AsyncTask::Shutdown).await });
+
+    // Terminate the background therad:
+    tokio_thread.join().expect("Unable to shutdown tokio channel");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charts::animation::DECORATION_TICK_MS;
+    use crate::charts::color_ref::ChartColor;
+    use crate::charts::debug_overlay::OverlayCorner;
+    use crate::charts::decorations::{
+        ActiveAlertUnderLineDecoration, Decoration, ReferencePointDecoration,
+    };
+    use crate::charts::fixtures::{
+        decorated_config, matrix_response, matrix_result, multi_source_config, scalar_response,
+        single_chart_config, MockClock,
+    };
+    use crate::charts::journal::AnomalyKind;
+    use crate::charts::{
+        ChartLength2D, ChartOverflow, DataQualityConfig, JournalConfig, LinePattern,
+        ManualTimeSeries, PLACEHOLDER_PULSE_TICKS,
+    };
+    use crate::decorations::VisibilityGroup;
+    use crate::event::VoidListener;
+    use crate::index::{Column, Point};
+    use fetcher::FetchSource;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use vte::ansi::Rgb;
+
+    /// `status_tx` returns the sender of the status of a coordinator that nobody watches
+    fn status_tx() -> watch::Sender<ChartsStatus> {
+        watch::channel(ChartsStatus::Running).0
+    }
+
+    #[tokio::test]
+    async fn it_keeps_polling_after_a_request_timeout() {
+        // A fixture server that accepts connections but never replies.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let accepted = Arc::new(AtomicUsize::new(0));
+        let server_accepted = accepted.clone();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                server_accepted.fetch_add(1, Ordering::SeqCst);
+                tokio::spawn(async move {
+                    time::sleep(Duration::from_secs(30)).await;
+                    drop(socket);
+                });
+            }
+        });
+        let (tx, _rx) = mpsc::channel(16);
+        let item = MetricRequest {
+            pull_interval: 1,
+            source_url: format!("http://{}/api/v1/query?query=up", addr),
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(0),
+            data: None,
+            capacity: 30,
+            max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+        };
+        // The first request times out after 1 second, the loop must issue another one.
+        let startup_retries = StartupRetries { retries: 0, delay: Duration::ZERO };
+        let poll =
+            spawn_datasource_interval_polls(&item, startup_retries, FetchLimiter::new(1), tx);
+        let _ = time::timeout(Duration::from_millis(2500), poll).await;
+        assert!(accepted.load(Ordering::SeqCst) >= 2);
+    }
+
+    #[tokio::test]
+    async fn it_backfills_the_grown_window_of_the_charts() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        // A fixture server that records the requested time ranges.
+        let requests = Arc::new(std::sync::Mutex::new(vec![]));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let server_requests = requests.clone();
+        tokio::spawn(async move {
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let mut buf = vec![0u8; 4096];
+                let len = socket.read(&mut buf).await.unwrap_or(0);
+                let request = String::from_utf8_lossy(&buf[..len]).to_string();
+                server_requests.lock().unwrap().push(request);
+                let body = r#"{"status":"success","data":{"resultType":"matrix","result":[]}}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = socket.write_all(response.as_bytes()).await;
+            }
+        });
+        let windows = || -> Vec<u64> {
+            let param = |request: &str, name: &str| -> u64 {
+                let value = request.split(['?', '&', ' ']).find_map(|x| x.strip_prefix(name));
+                value.unwrap().parse().unwrap()
+            };
+            let requests = requests.lock().unwrap();
+            requests.iter().map(|x| param(x, "end=") - param(x, "start=")).collect()
+        };
+        let source = format!("http://{}/api/v1/query_range?query=up", addr);
+        let mut prom = prometheus::PrometheusTimeSeries {
+            source: source.clone(),
+            pull_interval: 2,
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        prom.series = prom.series.with_capacity(300);
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("load"),
+                sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let item = MetricRequest {
+            pull_interval: 2,
+            source_url: source,
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(0),
+            data: None,
+            capacity: 300,
+            max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+        };
+        let startup_retries = StartupRetries { retries: 0, delay: Duration::ZERO };
+        let poll_tx = tx.clone();
+        let poll = tokio::spawn(async move {
+            spawn_datasource_interval_polls(&item, startup_retries, FetchLimiter::new(1), poll_tx)
+                .await
+        });
+        let set_window = |chart_index, seconds| {
+            let tx = tx.clone();
+            async move {
+                let (changed_tx, changed_rx) = oneshot::channel();
+                let task = AsyncTask::SetWindowSeconds(chart_index, seconds, changed_tx);
+                tx.send(task).await.unwrap();
+                changed_rx.await.unwrap()
+            }
+        };
+        // The first poll is within the startup jitter.
+        time::sleep(Duration::from_millis(500)).await;
+        assert_eq!(windows(), vec![300]);
+        // The grown window is fetched right away instead of on the next tick.
+        assert_eq!(set_window(None, 900).await, Ok(true));
+        time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(windows(), vec![300, 900]);
+        // Shrinking drops the oldest samples, there is nothing to fetch.
+        assert_eq!(set_window(Some(ChartIndex(0)), 60).await, Ok(true));
+        time::sleep(Duration::from_millis(300)).await;
+        assert_eq!(windows(), vec![300, 900]);
+        let (capacity_tx, capacity_rx) = oneshot::channel();
+        tx.send(AsyncTask::SendSeriesCapacity(ChartIndex(0), SeriesIndex(0), capacity_tx))
+            .await
+            .unwrap();
+        assert_eq!(capacity_rx.await.unwrap(), 60);
+        // The next tick of the same poll requests the new window.
+        time::sleep(Duration::from_millis(1500)).await;
+        assert_eq!(windows(), vec![300, 900, 60]);
+        assert_eq!(
+            set_window(Some(ChartIndex(3)), 60).await,
+            Err(ChartTaskError::NotFound(ChartIndex(3)))
+        );
+        assert_eq!(set_window(None, 0).await, Ok(false));
+        poll.abort();
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[test]
+    fn it_spreads_the_startup_ticks() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let period = Duration::from_secs(30);
+        let jitters: Vec<Duration> = (0..1000).map(|_| startup_jitter(period, &mut rng)).collect();
+        // Up to 10% of the interval.
+        assert!(jitters.iter().all(|jitter| *jitter < Duration::from_secs(3)));
+        // The series with the same interval do not tick together.
+        let min = jitters.iter().min().unwrap();
+        let max = jitters.iter().max().unwrap();
+        assert!(*min < Duration::from_millis(300));
+        assert!(*max > Duration::from_millis(2700));
+        let mut buckets = [0usize; 10];
+        for jitter in &jitters {
+            buckets[(jitter.as_millis() / 300) as usize] += 1;
+        }
+        assert!(buckets.iter().all(|count| *count > 50));
+    }
+
+    /// `CapturingSubscriber` records the name and fields of the spans and events
+    #[derive(Clone, Default)]
+    struct CapturingSubscriber {
+        records: Arc<std::sync::Mutex<Vec<(String, Vec<String>)>>>,
+        next_id: Arc<AtomicUsize>,
+    }
+
+    struct FieldRecorder(Vec<String>);
+
+    impl tracing::field::Visit for FieldRecorder {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push(format!("{}={:?}", field.name(), value));
+        }
+    }
+
+    impl tracing::Subscriber for CapturingSubscriber {
+        fn enabled(&self, _metadata: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, span: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = FieldRecorder(vec![]);
+            span.record(&mut fields);
+            self.records.lock().unwrap().push((span.metadata().name().to_string(), fields.0));
+            tracing::span::Id::from_u64(self.next_id.fetch_add(1, Ordering::SeqCst) as u64 + 1)
+        }
+
+        fn record(&self, _span: &tracing::span::Id, _values: &tracing::span::Record<'_>) {}
+
+        fn record_follows_from(&self, _span: &tracing::span::Id, _follows: &tracing::span::Id) {}
+
+        fn event(&self, event: &tracing::Event<'_>) {
+            let mut fields = FieldRecorder(vec![]);
+            event.record(&mut fields);
+            self.records.lock().unwrap().push((String::from("event"), fields.0));
+        }
+
+        fn enter(&self, _span: &tracing::span::Id) {}
+
+        fn exit(&self, _span: &tracing::span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn it_instruments_the_datasource_tasks() {
+        let subscriber = CapturingSubscriber::default();
+        let _guard = tracing::subscriber::set_default(subscriber.clone());
+        // A fixture server that closes every connection right away.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            while let Ok((socket, _)) = listener.accept().await {
+                drop(socket);
+            }
+        });
+        let item = MetricRequest {
+            source_url: format!("http://{}/api/v1/query?query=up", addr),
+            chart_index: ChartIndex(1),
+            series_index: SeriesIndex(2),
+            pull_interval: 1,
+            data: None,
+            capacity: 30,
+            max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+        };
+        let span = datasource_span("load", &item);
+        let ctx =
+            fetcher::FetchContext { capacity: None, timeout: Duration::from_secs(1), attempt: 0 };
+        let source = PrometheusSource::new(item);
+        assert!(source.fetch(&ctx).instrument(span).await.is_err());
+        let records = subscriber.records.lock().unwrap().clone();
+        let has_field = |fields: &[String], expected: &str| fields.iter().any(|f| f == expected);
+        // The root span of the task identifies the chart source.
+        let (_, root) = records.iter().find(|(name, _)| name == "datasource_poll").unwrap();
+        for field in ["chart=\"load\"", "chart_index=1", "series_index=2", "host=\"127.0.0.1\""] {
+            assert!(has_field(root, field), "missing {} in {:?}", field, root);
+        }
+        assert!(records.iter().any(|(name, fields)| {
+            name == "fetch_prometheus_response" && has_field(fields, "capacity=30")
+        }));
+        // Each iteration records its result as fields of an event.
+        let (_, fetched) = records
+            .iter()
+            .find(|(name, fields)| {
+                name == "event" && fields.iter().any(|f| f.starts_with("result="))
+            })
+            .unwrap();
+        assert!(has_field(fetched, "result=\"error\""));
+        assert!(fetched.iter().any(|f| f.starts_with("duration_ms=")));
+        assert_eq!(source_host("not a url"), "");
+    }
+
+    #[tokio::test]
+    async fn it_describes_the_charts_in_the_debug_overlay() {
+        let prom = prometheus::PrometheusTimeSeries {
+            name: String::from("load"),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let reference =
+            ReferencePointDecoration { opengl_data: vec![0f32; 12], ..Default::default() };
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("system"),
+                sources: vec![
+                    TimeSeriesSource::default(),
+                    TimeSeriesSource::PrometheusTimeSeries(Box::new(prom)),
+                ],
+                decorations: vec![Decoration::Reference(reference)],
+                ..TimeSeriesChart::default()
+            }],
+            debug_overlay_corner: OverlayCorner::BottomLeft,
+            ..ChartsConfig::default()
+        };
+        let size = SizeInfo { columns: 60, screen_lines: 10, ..SizeInfo::default() };
+        let windows = WindowCharts::new(ChartSizeInfo { term_size: size, ..Default::default() });
+        let mut debug_state = DebugOverlayState::default();
+        let stats = CoordinatorStats { memory_bytes: 4096, ..CoordinatorStats::default() };
+        let now = time::Instant::now();
+        debug_state.last_decoration_tick = Some(now);
+        let lines = debug_overlay_lines(
+            &chart_config,
+            &windows,
+            &stats,
+            &debug_state,
+            100,
+            now + Duration::from_millis(250),
+        );
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts[0], "charts: 1 gen 0 mem 4KiB requests 0/0/0 peeks 0");
+        assert_eq!(texts[1], "system 0x0+0+0 gen 0 updated never");
+        assert!(texts[2].ends_with(": 0 samples"));
+        assert_eq!(texts[3], "  load: 0 samples, fetch pending");
+        assert_eq!(texts[4], "  reference: 6 vertices, tick 250ms ago");
+        // The column starts at the configured corner.
+        assert_eq!(lines[0].point, Point::new(5, Column(0)));
+        assert_eq!(lines[4].point, Point::new(9, Column(0)));
+        // The starvation found by the data watchdog follows the coordinator.
+        debug_state.starved_sources = vec![String::from("system/load")];
+        let lines = debug_overlay_lines(&chart_config, &windows, &stats, &debug_state, 100, now);
+        assert_eq!(lines[1].text, "watchdog: sources starved system/load");
+        assert_eq!(lines[1].fg, OVERLAY_ERROR_FG);
+
+        // The coordinator keeps the failed fetches of the series.
+        let (tx, rx) = mpsc::channel(16);
+        let coordinator =
+            tokio::spawn(async_coordinator(rx, chart_config, size, VoidListener, status_tx()));
+        let failure = MetricRequest {
+            pull_interval: 15,
+            source_url: String::from("http://localhost:9090/api/v1/query?query=up"),
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(1),
+            data: None,
+            capacity: 30,
+            max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+        };
+        tx.send(AsyncTask::LoadResponse(failure)).await.unwrap();
+        let (overlay_tx, overlay_rx) = oneshot::channel();
+        tx.send(AsyncTask::DebugOverlayData(overlay_tx)).await.unwrap();
+        let lines = overlay_rx.await.unwrap();
+        assert_eq!(lines[3].text, "  load: 0 samples, fetch failed");
+        assert_eq!(lines[3].fg, OVERLAY_ERROR_FG);
+        assert_eq!(lines[4].text, "  reference: 6 vertices, tick never");
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_handles_out_of_range_indices() {
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("one chart"),
+                sources: vec![TimeSeriesSource::default()],
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(16);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        for (chart_index, series_index) in [(0usize, 7usize), (7usize, 0usize), (7usize, 7usize)] {
+            let (chart_index, series_index) = (ChartIndex(chart_index), SeriesIndex(series_index));
+            let response = MetricRequest {
+                pull_interval: 15,
+                source_url: String::from("http://localhost:9090/api/v1/query?query=up"),
+                chart_index,
+                series_index,
+                data: Some(prometheus::HTTPResponse {
+                    status: String::from("success"),
+                    ..prometheus::HTTPResponse::default()
+                }),
+                capacity: 30,
+                max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+            };
+            tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+
+            let (opengl_tx, opengl_rx) = oneshot::channel();
+            tx.send(AsyncTask::SendMetricsOpenGLData(
+                WindowId::default(),
+                chart_index,
+                series_index,
+                opengl_tx,
+            ))
+            .await
+            .unwrap();
+            assert_eq!(opengl_rx.await.unwrap(), (vec![], 0f32, 0));
+
+            let (opengl_tx, opengl_rx) = oneshot::channel();
+            tx.send(AsyncTask::SendChartDecorationsOpenGLData(
+                WindowId::default(),
+                chart_index,
+                DecorationIndex(series_index.0),
+                opengl_tx,
+            ))
+            .await
+            .unwrap();
+            assert_eq!(opengl_rx.await.unwrap(), (vec![], 0f32, 0));
+
+            let (capacity_tx, capacity_rx) = oneshot::channel();
+            tx.send(AsyncTask::SendSeriesCapacity(chart_index, series_index, capacity_tx))
+                .await
+                .unwrap();
+            assert!(capacity_rx.await.is_err());
+
+            let (rect_tx, rect_rx) = oneshot::channel();
+            tx.send(AsyncTask::SendChartScissorRect(WindowId::default(), chart_index, rect_tx))
+                .await
+                .unwrap();
+            assert_eq!(rect_rx.await.unwrap(), None);
+
+            tx.send(AsyncTask::DecorUpdate(chart_index, DECORATION_TICK_MS as f32)).await.unwrap();
+        }
+        let (size_tx, size_rx) = oneshot::channel();
+        tx.send(AsyncTask::ChangeDisplaySize(
+            WindowId::default(),
+            100.,
+            100.,
+            0.,
+            0.,
+            10.,
+            20.,
+            size_tx,
+        ))
+        .await
+        .unwrap();
+        assert!(size_rx.await.unwrap());
+        tx.send(AsyncTask::FreezeClock(10)).await.unwrap();
+        tx.send(AsyncTask::IncrementInputCounter(1.)).await.unwrap();
+        tx.send(AsyncTask::IncrementOutputCounter(1.)).await.unwrap();
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        // A panic in the coordinator would be returned as a JoinError.
+        coordinator.await.unwrap();
+    }
+
+    /// Requests the first series vertices of the first chart for a window
+    async fn request_series_vertices(
+        tx: &mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+    ) -> Vec<f32> {
+        request_series_reply(tx, window_id).await.0
+    }
+
+    /// Requests the first series vertices of the first chart for a window, with their alpha and
+    /// size generation
+    async fn request_series_reply(
+        tx: &mpsc::Sender<AsyncTask>,
+        window_id: WindowId,
+    ) -> (Vec<f32>, f32, u64) {
+        let (opengl_tx, opengl_rx) = oneshot::channel();
+        let task =
+            AsyncTask::SendMetricsOpenGLData(window_id, ChartIndex(0), SeriesIndex(0), opengl_tx);
+        tx.send(task).await.unwrap();
+        opengl_rx.await.unwrap()
+    }
+
+    #[test]
+    fn it_loads_initial_data_files_at_startup() {
+        let mut prom = crate::charts::prometheus::PrometheusTimeSeries::default();
+        prom.series = prom.series.with_capacity(15usize);
+        prom.initial_data_file = Some(std::path::PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/charts/query_range_matrix.json"
+        )));
+        let mut charts = vec![TimeSeriesChart {
+            name: String::from("load"),
+            sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            ..TimeSeriesChart::default()
+        }];
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        assert_eq!(load_initial_data_files(&mut charts, size), 10usize);
+        assert_eq!(charts[0].sources[0].series().active_items, 10usize);
+        // One vertex per loaded item, before any polling happened.
+        assert_eq!(charts[0].opengl_vecs[0].len(), 20usize);
+        // Series without a file, or that fail to load, are skipped.
+        if let TimeSeriesSource::PrometheusTimeSeries(ref mut prom) = charts[0].sources[0] {
+            prom.initial_data_file = Some(std::path::PathBuf::from("/nonexistent/chart.json"));
+        }
+        charts.push(TimeSeriesChart::default());
+        assert_eq!(load_initial_data_files(&mut charts, size), 0usize);
+    }
+
+    #[test]
+    fn it_follows_the_cell_size_of_the_display() {
+        let mut charts = vec![TimeSeriesChart {
+            name: String::from("input"),
+            sources: vec![TimeSeriesSource::default()],
+            dimensions: Some(ChartLength2D::cells(10., 2.)),
+            ..TimeSeriesChart::default()
+        }];
+        let mut windows = WindowCharts::new(ChartSizeInfo::default());
+        let window_id = WindowId::default();
+        // A font size change resizes the cells, the window may keep its size.
+        for (cell_width, width) in [(8., 80.), (12., 120.)] {
+            let size = DisplaySize {
+                height: 400.,
+                width: 1000.,
+                cell_width,
+                cell_height: 16.,
+                ..Default::default()
+            };
+            let (size_tx, size_rx) = oneshot::channel();
+            change_display_size(&mut charts, &mut windows, window_id, size, size_tx);
+            assert!(size_rx.blocking_recv().unwrap());
+            let size = windows.size(window_id);
+            assert_eq!(size.term_size.cell_width, cell_width);
+            assert_eq!(charts[0].drawable_width(size), width);
+        }
+    }
+
+    #[test]
+    fn it_sends_the_sparse_series_faint() {
+        let mut charts = vec![TimeSeriesChart {
+            name: String::from("load avg"),
+            sources: vec![TimeSeriesSource::default(), TimeSeriesSource::default()],
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            data_quality: Some(DataQualityConfig {
+                faint_alpha_factor: Some(0.25),
+                ..DataQualityConfig::default()
+            }),
+            ..TimeSeriesChart::default()
+        }];
+        // The first series has a value every 3 seconds, the second one every second.
+        for epoch in 10..20 {
+            let sparse = if epoch % 3 == 0 { Some(1.) } else { None };
+            charts[0].sources[0].series_mut().upsert((epoch, sparse));
+            charts[0].sources[1].series_mut().upsert((epoch, Some(1.)));
+        }
+        charts[0].calculate_stats();
+        let mut windows = WindowCharts::new(ChartSizeInfo::default());
+        let alpha = |charts: &mut [TimeSeriesChart], windows: &mut WindowCharts, series| {
+            let (opengl_tx, opengl_rx) = oneshot::channel();
+            let (window_id, chart_index) = (WindowId::default(), ChartIndex(0));
+            send_metrics_opengl_vecs(
+                charts,
+                windows,
+                window_id,
+                chart_index,
+                SeriesIndex(series),
+                opengl_tx,
+            );
+            opengl_rx.blocking_recv().unwrap().1
+        };
+        assert_eq!(alpha(&mut charts, &mut windows, 0), 0.25);
+        assert_eq!(alpha(&mut charts, &mut windows, 1), 1.);
+    }
+
+    #[test]
+    fn it_debounces_display_sizes() {
+        let mut charts = vec![TimeSeriesChart {
+            name: String::from("input"),
+            sources: vec![TimeSeriesSource::default()],
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            ..TimeSeriesChart::default()
+        }];
+        charts[0].sources[0].series_mut().upsert((10, Some(1.)));
+        let mut windows = WindowCharts::new(ChartSizeInfo::default());
+        let mut debouncer = DisplaySizeDebouncer::new(Duration::from_millis(100));
+        let start = time::Instant::now();
+        assert_eq!(debouncer.deadline(), None);
+        // A burst of sizes while dragging the window corner.
+        for step in 0..10u64 {
+            let now = start + Duration::from_millis(step * 10);
+            let size =
+                DisplaySize { height: 400. + step as f32, width: 1000., ..Default::default() };
+            debouncer.push(WindowId(1), size, now);
+            assert_eq!(apply_display_sizes(&mut charts, &mut windows, &mut debouncer, now), 0);
+        }
+        // The deadline is reset by every size.
+        assert_eq!(debouncer.deadline(), Some(start + Duration::from_millis(190)));
+        let now = start + Duration::from_millis(189);
+        assert_eq!(apply_display_sizes(&mut charts, &mut windows, &mut debouncer, now), 0);
+        assert_eq!(windows.active_size(), ChartSizeInfo::default());
+        // Exactly one regeneration, for the last size.
+        let now = start + Duration::from_millis(190);
+        assert_eq!(apply_display_sizes(&mut charts, &mut windows, &mut debouncer, now), 1);
+        assert_eq!(windows.active_size().term_size.height, 409.);
+        assert_eq!(windows.size(WindowId(1)).term_size.height, 409.);
+        assert_eq!(debouncer.deadline(), None);
+        assert_eq!(apply_display_sizes(&mut charts, &mut windows, &mut debouncer, now), 0);
+    }
+
+    #[test]
+    fn it_recovers_the_counters_after_the_clock_jumps_backwards() {
+        // A timezone-like jump keeps the history, any other jump restarts the series.
+        for (jump, expected_items) in [(3600u64, 12usize), (1000, 2)] {
+            let mut chart_config = single_chart_config();
+            let mut windows = WindowCharts::new(ChartSizeInfo::default());
+            let clock = MockClock::new(1_700_000_000);
+            for _ in 0..10 {
+                increment_counter_task(&mut chart_config, &mut windows, "input", clock.now(), 1.);
+                clock.advance(1);
+            }
+            let generation = windows.generation();
+            clock.set(clock.now() - jump);
+            increment_counter_task(&mut chart_config, &mut windows, "input", clock.now(), 1.);
+            assert_ne!(windows.generation(), generation);
+            // The updates resume immediately instead of being dropped as too old.
+            let now = clock.advance(1);
+            increment_counter_task(&mut chart_config, &mut windows, "input", now, 2.);
+            let series = chart_config.charts[0].sources[0].series_ref();
+            assert_eq!(series.newest_epoch(), Some(now));
+            let items = series.as_vec().iter().filter(|(_, value)| value.is_some()).count();
+            assert_eq!(items, expected_items);
+            assert_eq!(chart_config.recover_clock_jump(now), None);
+        }
+    }
+
+    #[test]
+    fn it_aligns_the_counters_to_a_skewed_prometheus_clock() {
+        let start = 1_700_000_000u64;
+        let skew = 5u64;
+        for align_to_remote in [false, true] {
+            let prom = prometheus::PrometheusTimeSeries {
+                name: String::from("up"),
+                ..prometheus::PrometheusTimeSeries::default()
+            };
+            let mut charts = vec![TimeSeriesChart {
+                name: String::from("mixed"),
+                sources: vec![
+                    TimeSeriesSource::PrometheusTimeSeries(Box::new(prom)),
+                    TimeSeriesSource::default(),
+                ],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                align_to_remote,
+                ..TimeSeriesChart::default()
+            }];
+            for series in &mut charts[0].sources {
+                series.init();
+            }
+            let size = ChartSizeInfo {
+                term_size: SizeInfo { width: 400., height: 200., ..SizeInfo::default() },
+                ..ChartSizeInfo::default()
+            };
+            // The Prometheus server clock is ahead of the coordinator clock.
+            for now in start..start + 20 {
+                let body = format!(
+                    r#"{{"status":"success","data":{{"resultType":"matrix","result":[{{"metric":{{"__name__":"up"}},"values":[[{},"1"]]}}]}}}}"#,
+                    now + skew
+                );
+                let source_url = String::from("http://localhost:9090/api/v1/query_range?query=up");
+                let data = prometheus::parse_json(&source_url, &bytes::Bytes::from(body));
+                let response = MetricRequest {
+                    pull_interval: 1,
+                    source_url,
+                    chart_index: ChartIndex(0),
+                    series_index: SeriesIndex(0),
+                    data,
+                    capacity: 300,
+                    max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+                };
+                assert_eq!(load_http_response(&mut charts, response, size, now), Some(1));
+                increment_internal_counter(&mut charts, "input", now, 1., size);
+            }
+            assert_eq!(charts[0].epoch_skew_secs, Some(skew as i64));
+            let counter = charts[0].sources[1].series_ref();
+            assert_eq!(counter.newest_epoch(), Some(start + 19 + skew));
+            let padded = counter.as_vec().iter().filter(|(_, value)| value.is_none()).count();
+            // Unless aligned, the counter lags the server and is padded to its epoch on every
+            // load.
+            assert_eq!(padded, if align_to_remote { 0 } else { skew as usize });
+        }
+    }
+
+    #[tokio::test]
+    async fn it_applies_the_last_display_size() {
+        let chart_config = ChartsConfig { resize_debounce_ms: Some(500), ..single_chart_config() };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        for (epoch, value) in [(10, 1.), (11, 2.), (12, 3.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementInputCounter(value)).await.unwrap();
+        }
+        // The reference window is resized right away to the final size.
+        let (reference, resized) = (WindowId(1), WindowId(2));
+        let (size_tx, size_rx) = oneshot::channel();
+        tx.send(AsyncTask::ChangeDisplaySize(reference, 800., 2000., 0., 0., 10., 20., size_tx))
+            .await
+            .unwrap();
+        assert!(size_rx.await.unwrap());
+        for step in 0..=20 {
+            let height = 400. + step as f32 * 20.;
+            tx.send(AsyncTask::UpdateDisplaySize(resized, height, 2000., 0., 0., 10., 20.))
+                .await
+                .unwrap();
+        }
+        // Until the size is stable the previous vertices are used.
+        let stale_vertices = request_series_vertices(&tx, resized).await;
+        assert_eq!(stale_vertices, request_series_vertices(&tx, WindowId(3)).await);
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        let reference_vertices = request_series_vertices(&tx, reference).await;
+        assert!(!reference_vertices.is_empty());
+        assert_ne!(reference_vertices, stale_vertices);
+        assert_eq!(request_series_vertices(&tx, resized).await, reference_vertices);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_tags_the_vertices_with_the_size_generation() {
+        let chart_config = ChartsConfig { resize_debounce_ms: Some(500), ..single_chart_config() };
+        let (tx, rx) = mpsc::channel(16);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo { width: 1000., height: 400., ..SizeInfo::default() },
+            VoidListener,
+            status_tx(),
+        ));
+        tx.send(AsyncTask::FreezeClock(10)).await.unwrap();
+        tx.send(AsyncTask::IncrementInputCounter(1.)).await.unwrap();
+        let window_id = WindowId::default();
+        let (initial_vertices, _, size_generation) = request_series_reply(&tx, window_id).await;
+        assert_eq!(size_generation, 0);
+        // The window is resized and draws before the coordinator applies the size.
+        let mut cache = ChartsVertexCache::default();
+        tx.send(AsyncTask::UpdateDisplaySize(window_id, 800., 2000., 0., 0., 10., 20.))
+            .await
+            .unwrap();
+        cache.resize();
+        let (stale_vertices, _, stale_generation) = request_series_reply(&tx, window_id).await;
+        assert_eq!(stale_vertices, initial_vertices);
+        assert_ne!(stale_generation, cache.size_generation());
+        // A response loaded meanwhile is still generated for the previous size.
+        tx.send(AsyncTask::FreezeClock(11)).await.unwrap();
+        tx.send(AsyncTask::IncrementInputCounter(2.)).await.unwrap();
+        assert_ne!(request_series_reply(&tx, window_id).await.2, cache.size_generation());
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        let (vertices, _, size_generation) = request_series_reply(&tx, window_id).await;
+        assert_eq!(size_generation, cache.size_generation());
+        assert_ne!(vertices, stale_vertices);
+        // The sizes applied right away are tagged right away, a pending size is not applied later.
+        tx.send(AsyncTask::UpdateDisplaySize(window_id, 400., 1000., 0., 0., 10., 20.))
+            .await
+            .unwrap();
+        cache.resize();
+        let (size_tx, size_rx) = oneshot::channel();
+        tx.send(AsyncTask::ChangeDisplaySize(window_id, 800., 2000., 0., 0., 10., 20., size_tx))
+            .await
+            .unwrap();
+        assert!(size_rx.await.unwrap());
+        cache.resize();
+        tokio::time::sleep(Duration::from_millis(700)).await;
+        let (latest_vertices, _, size_generation) = request_series_reply(&tx, window_id).await;
+        assert_eq!(size_generation, cache.size_generation());
+        assert_eq!(latest_vertices, vertices);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[test]
+    fn it_reuses_cached_vertices_until_the_generation_changes() {
+        let chart_config = ChartsConfig {
+            charts: (0..6)
+                .map(|chart_idx| TimeSeriesChart {
+                    name: format!("chart {}", chart_idx),
+                    sources: vec![if chart_idx == 0 {
+                        TimeSeriesSource::AlacrittyScroll(Default::default())
+                    } else {
+                        TimeSeriesSource::AsyncLoadedItems(Default::default())
+                    }],
+                    dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                    ..TimeSeriesChart::default()
+                })
+                .collect(),
+            ..ChartsConfig::default()
+        };
+        let size_info = SizeInfo { width: 2000., height: 800., ..SizeInfo::default() };
+        let (handle, tokio_thread) =
+            crate::term::TermChartsHandle::new_with_thread(&chart_config, size_info, VoidListener);
+        let (tx, tokio_handle) = (handle.charts_tx.clone(), handle.tokio_handle.clone());
+        let stats = || {
+            let (stats_tx, stats_rx) = oneshot::channel();
+            tx.blocking_send(AsyncTask::SendCoordinatorStats(stats_tx)).unwrap();
+            stats_rx.blocking_recv().unwrap()
+        };
+        // Draws the six charts the way the display does on every frame.
+        let draw = |cache: &mut ChartsVertexCache| {
+            let changed = cache.refresh(tx.clone(), tokio_handle.clone());
+            for chart_idx in 0..6 {
+                let window_id = WindowId::default();
+                let chart_idx = ChartIndex(chart_idx);
+                cache.scissor_rect(tx.clone(), window_id, chart_idx, tokio_handle.clone());
+                let series_idx = SeriesIndex(0);
+                cache.series_data(
+                    tx.clone(),
+                    window_id,
+                    chart_idx,
+                    series_idx,
+                    tokio_handle.clone(),
+                );
+            }
+            changed
+        };
+        let mut cache = ChartsVertexCache::default();
+        assert_eq!(draw(&mut cache), 6);
+        assert_eq!(stats().metrics_requests, 6);
+        // Typing in an idle terminal redraws without requesting the vertices again.
+        for epoch in 10..20 {
+            tx.blocking_send(AsyncTask::FreezeClock(epoch)).unwrap();
+            tx.blocking_send(AsyncTask::IncrementInputCounter(1.)).unwrap();
+            assert_eq!(draw(&mut cache), 0);
+        }
+        let idle = stats();
+        assert_eq!(idle.metrics_requests, 6);
+        assert_eq!(idle.scissor_requests, 6);
+        assert_eq!(idle.generation_peeks, 11);
+        // The charts hold at least the vertices that were drawn.
+        assert!(idle.memory_bytes >= 6 * 2 * std::mem::size_of::<f32>());
+        // A chart update is requested on the next frame.
+        tx.blocking_send(AsyncTask::FreezeClock(10)).unwrap();
+        tx.blocking_send(AsyncTask::IncrementScrollCounter(3.)).unwrap();
+        assert_eq!(draw(&mut cache), 6);
+        assert_eq!(stats().metrics_requests, 12);
+        tx.blocking_send(AsyncTask::Shutdown).unwrap();
+        tokio_thread.join().unwrap();
+    }
+
+    #[test]
+    fn it_disables_the_charts_after_a_panic() {
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("async loaded"),
+                sources: vec![TimeSeriesSource::AsyncLoadedItems(Default::default())],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let size_info = SizeInfo { width: 2000., height: 800., ..SizeInfo::default() };
+        let (handle, tokio_thread) =
+            crate::term::TermChartsHandle::new_with_thread(&chart_config, size_info, VoidListener);
+        let (tx, tokio_handle) = (handle.charts_tx.clone(), handle.tokio_handle.clone());
+        assert!(handle.coordinator_running());
+        tx.blocking_send(AsyncTask::Poison).unwrap();
+        let started = std::time::Instant::now();
+        while handle.coordinator_running() {
+            assert!(started.elapsed() < Duration::from_secs(5));
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        // The helpers fall back to their defaults without blocking the render loop.
+        let window_id = WindowId::default();
+        let started = std::time::Instant::now();
+        let rect =
+            get_chart_scissor_rect(tx.clone(), window_id, ChartIndex(0), tokio_handle.clone());
+        assert_eq!(rect, None);
+        let data = get_metric_opengl_data(
+            tx.clone(),
+            window_id,
+            ChartIndex(0),
+            OpenGLDataRequest::Series(SeriesIndex(0)),
+            tokio_handle.clone(),
+        );
+        assert_eq!(data, (vec![], 0., 0));
+        assert!(started.elapsed() < COORDINATOR_REPLY_TIMEOUT * 2 + Duration::from_millis(500));
+        // The shutdown is still handled after the panic.
+        tx.blocking_send(AsyncTask::Shutdown).unwrap();
+        tokio_thread.join().unwrap();
+    }
+
+    #[test]
+    fn it_initializes_all_internal_counters() {
+        let yaml = r#"
+spacing: 10
+charts:
+- name: scroll
+  series:
+  - name: scrolled lines
+    type: alacritty_scroll
+- name: selection
+  series:
+  - name: selected cells
+    type: alacritty_selection
+    granularity: 5
+- name: io
+  series:
+  - name: input
+    type: alacritty_input
+  - name: output
+    type: alacritty_output
+  - name: loaded
+    type: async_loaded_items
+"#;
+        let mut chart_config: ChartsConfig = serde_yaml::from_str(yaml).unwrap();
+        for chart in &mut chart_config.charts {
+            for series in &mut chart.sources {
+                series.init();
+            }
+        }
+        match &chart_config.charts[0].sources[0] {
+            TimeSeriesSource::AlacrittyScroll(counter) => {
+                assert_eq!(counter.name, "scrolled lines");
+                assert_eq!(counter.granularity, 1);
+                assert_eq!(
+                    counter.series.collision_policy,
+                    crate::charts::ValueCollisionPolicy::Increment
+                );
+            },
+            source => panic!("Unexpected source: {:?}", source),
+        }
+        match &chart_config.charts[1].sources[0] {
+            TimeSeriesSource::AlacrittySelection(counter) => assert_eq!(counter.granularity, 5),
+            source => panic!("Unexpected source: {:?}", source),
+        }
+        assert_eq!(chart_config.charts[2].sources.len(), 3);
+    }
+
+    #[test]
+    fn it_routes_the_loaded_items_to_the_watching_counters() {
+        let yaml = r#"
+charts:
+- name: loaded
+  series:
+  - name: all
+    type: async_loaded_items
+  - name: up loads
+    type: async_loaded_items
+    per_source: true
+    watch_chart: load
+    watch_series: up
+  - name: down loads
+    type: async_loaded_items
+    per_source: true
+    watch_chart: load
+    watch_series: down
+  - name: typo loads
+    type: async_loaded_items
+    per_source: true
+    watch_chart: load
+    watch_series: dwon
+"#;
+        let mut chart_config: ChartsConfig = serde_yaml::from_str(yaml).unwrap();
+        chart_config.charts.extend(multi_source_config(&["up", "down"]).charts);
+        assert_eq!(
+            chart_config.validate_loaded_items_watchers(),
+            Err(String::from(
+                "Unknown sources watched by async_loaded_items: loaded/typo loads watches \
+                 load/dwon"
+            ))
+        );
+        let load = |charts: &mut Vec<TimeSeriesChart>, series_idx, samples: &[(u64, f64)], now| {
+            let response = MetricRequest {
+                pull_interval: 15,
+                source_url: String::from("http://test"),
+                chart_index: ChartIndex(1),
+                series_index: SeriesIndex(series_idx),
+                data: Some(matrix_response(vec![matrix_result(HashMap::new(), samples)])),
+                capacity: 300,
+                max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+            };
+            load_http_response(charts, response, ChartSizeInfo::default(), now)
+        };
+        let charts = &mut chart_config.charts;
+        assert_eq!(load(charts, 0, &[(10, 1.), (11, 1.), (12, 1.)], 100), Some(3));
+        assert_eq!(load(charts, 1, &[(10, 0.), (11, 0.)], 101), Some(2));
+        assert_eq!(load(charts, 0, &[(13, 1.)], 102), Some(1));
+        // The counted items by epoch of each counter.
+        let counts = |series_idx: usize| -> Vec<(u64, f64)> {
+            let series = chart_config.charts[0].sources[series_idx].series_ref();
+            series.as_vec().iter().filter_map(|(epoch, value)| Some((*epoch, (*value)?))).collect()
+        };
+        assert_eq!(counts(0), vec![(100, 3.), (101, 2.), (102, 1.)]);
+        assert_eq!(counts(1), vec![(100, 3.), (102, 1.)]);
+        assert_eq!(counts(2), vec![(101, 2.)]);
+        // The counter watching an unknown source counts nothing.
+        assert_eq!(counts(3), vec![]);
+    }
+
+    #[tokio::test]
+    async fn it_routes_scroll_and_selection_counters() {
+        let counter_chart = |name: &str, source: TimeSeriesSource| TimeSeriesChart {
+            name: name.to_string(),
+            sources: vec![source],
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            ..TimeSeriesChart::default()
+        };
+        let chart_config = ChartsConfig {
+            charts: vec![
+                counter_chart("scroll", TimeSeriesSource::AlacrittyScroll(Default::default())),
+                counter_chart(
+                    "selection",
+                    TimeSeriesSource::AlacrittySelection(Default::default()),
+                ),
+                counter_chart("input", TimeSeriesSource::AlacrittyInput(Default::default())),
+            ],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (size_tx, size_rx) = oneshot::channel();
+        tx.send(AsyncTask::ChangeDisplaySize(
+            WindowId::default(),
+            800.,
+            2000.,
+            0.,
+            0.,
+            10.,
+            20.,
+            size_tx,
+        ))
+        .await
+        .unwrap();
+        assert!(size_rx.await.unwrap());
+        let chart_vertices = |chart_index: usize| {
+            let tx = tx.clone();
+            async move {
+                let (opengl_tx, opengl_rx) = oneshot::channel();
+                let (window_id, chart_index) = (WindowId::default(), ChartIndex(chart_index));
+                let task = AsyncTask::SendMetricsOpenGLData(
+                    window_id,
+                    chart_index,
+                    SeriesIndex(0),
+                    opengl_tx,
+                );
+                tx.send(task).await.unwrap();
+                opengl_rx.await.unwrap().0
+            }
+        };
+        // Scroll events only reach the charts with scroll sources.
+        for (epoch, lines) in [(10, 3.), (10, 2.), (11, 1.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementScrollCounter(lines)).await.unwrap();
+        }
+        assert!(!chart_vertices(0).await.is_empty());
+        assert!(chart_vertices(1).await.is_empty());
+        assert!(chart_vertices(2).await.is_empty());
+        // Completed selections only reach the charts with selection sources.
+        for (epoch, cells) in [(10, 40.), (11, 8.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementSelectionCounter(cells)).await.unwrap();
+        }
+        assert!(!chart_vertices(1).await.is_empty());
+        assert!(chart_vertices(2).await.is_empty());
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+        // The counters increment the values collected in the same epoch.
+        let mut charts =
+            vec![counter_chart("scroll", TimeSeriesSource::AlacrittyScroll(Default::default()))];
+        let size = ChartSizeInfo::default();
+        increment_internal_counter(&mut charts, "scroll", 10, 3., size);
+        increment_internal_counter(&mut charts, "scroll", 10, 2., size);
+        increment_internal_counter(&mut charts, "selection", 10, 40., size);
+        assert_eq!(charts[0].sources[0].series().as_vec(), vec![(10, Some(5.))]);
+    }
+
+    #[tokio::test]
+    async fn it_replies_with_the_discovered_series_to_poll() {
+        let discover = prometheus::DiscoveryConfig {
+            label: String::from("mountpoint"),
+            matcher: String::from("node_filesystem_avail_bytes"),
+            query_template: String::from(
+                "http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes{mountpoint=\"{value}\"}",
+            ),
+            interval: 60,
+            grace_period: 0,
+            max_series: 4,
+        };
+        let prom = prometheus::PrometheusTimeSeries {
+            source: String::from("http://localhost:9090/api/v1/query?query=up"),
+            color: Rgb { r: 1, g: 2, b: 3 }.into(),
+            discover: Some(discover),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("disk"),
+                sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let load_label_values = |values: Vec<&str>| {
+            let tx = tx.clone();
+            let values = values.into_iter().map(String::from).collect();
+            async move {
+                let (changes_tx, changes_rx) = oneshot::channel();
+                let task =
+                    AsyncTask::LoadLabelValues(ChartIndex(0), SeriesIndex(0), values, changes_tx);
+                tx.send(task).await.unwrap();
+                changes_rx.await.unwrap()
+            }
+        };
+        let (started, retired) = load_label_values(vec!["/", "/boot"]).await;
+        let started_series: Vec<SeriesIndex> = started.iter().map(|x| x.series_index).collect();
+        assert_eq!(started_series, vec![SeriesIndex(1), SeriesIndex(2)]);
+        assert!(started[1].source_url.contains(r#"{mountpoint="/boot"}"#));
+        assert!(retired.is_empty());
+        // The display draws the discovered series from their styles.
+        let (styles_tx, styles_rx) = oneshot::channel();
+        tx.send(AsyncTask::SendChartSeriesStyles(ChartIndex(0), styles_tx)).await.unwrap();
+        let color = Rgb { r: 1, g: 2, b: 3 };
+        let style = SeriesStyle { color, pattern: LinePattern::Solid, ..SeriesStyle::default() };
+        assert_eq!(styles_rx.await.unwrap(), vec![style; 3]);
+        // Without a grace period a missing value is retired on the next discovery.
+        let (started, retired) = load_label_values(vec!["/"]).await;
+        assert!(started.is_empty());
+        assert_eq!(retired, vec![SeriesIndex(2)]);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_resolves_the_chart_colors_against_the_palette() {
+        let alert = ActiveAlertUnderLineDecoration {
+            color: ChartColor::named("chartreuse"),
+            ..ActiveAlertUnderLineDecoration::default()
+        };
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("input"),
+                sources: vec![TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                    color: ChartColor::named("blue"),
+                    ..ManualTimeSeries::default()
+                })],
+                decorations: vec![Decoration::Alert(alert)],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let set_palette = |blue: Rgb| {
+            let tx = tx.clone();
+            let colors = vec![
+                (String::from("foreground"), Rgb { r: 0xd8, g: 0xd8, b: 0xd8 }),
+                (String::from("blue"), blue),
+            ];
+            async move {
+                let (unknown_tx, unknown_rx) = oneshot::channel();
+                tx.send(AsyncTask::SetPalette(colors, unknown_tx)).await.unwrap();
+                unknown_rx.await.unwrap()
+            }
+        };
+        let series_color = || {
+            let tx = tx.clone();
+            async move {
+                let (styles_tx, styles_rx) = oneshot::channel();
+                tx.send(AsyncTask::SendChartSeriesStyles(ChartIndex(0), styles_tx)).await.unwrap();
+                styles_rx.await.unwrap()[0].color
+            }
+        };
+        let blue = Rgb { r: 0x7c, g: 0xaf, b: 0xc2 };
+        assert_eq!(set_palette(blue).await, vec![String::from("chartreuse")]);
+        assert_eq!(series_color().await, blue);
+        // A reloaded color scheme re-resolves the named colors.
+        let blue = Rgb { r: 0, g: 0, b: 255 };
+        assert_eq!(set_palette(blue).await, vec![String::from("chartreuse")]);
+        assert_eq!(series_color().await, blue);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_orders_the_discovered_series_regardless_of_arrival() {
+        let run = |values: &[&str]| {
+            let values: Vec<String> = values.iter().map(|value| value.to_string()).collect();
+            async move {
+                let discover = prometheus::DiscoveryConfig {
+                    label: String::from("mountpoint"),
+                    matcher: String::from("node_filesystem_avail_bytes"),
+                    query_template: String::from(
+                        "http://localhost:9090/api/v1/query?query=avail{mountpoint=\"{value}\"}",
+                    ),
+                    interval: 60,
+                    grace_period: 0,
+                    max_series: 4,
+                };
+                let prom = prometheus::PrometheusTimeSeries {
+                    source: String::from("http://localhost:9090/api/v1/query?query=up"),
+                    discover: Some(discover),
+                    ..prometheus::PrometheusTimeSeries::default()
+                };
+                let chart_config = ChartsConfig {
+                    charts: vec![TimeSeriesChart {
+                        name: String::from("disk"),
+                        sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                        ..TimeSeriesChart::default()
+                    }],
+                    ..ChartsConfig::default()
+                };
+                let (tx, rx) = mpsc::channel(64);
+                let coordinator = tokio::spawn(async_coordinator(
+                    rx,
+                    chart_config,
+                    SizeInfo::default(),
+                    VoidListener,
+                    status_tx(),
+                ));
+                let (changes_tx, changes_rx) = oneshot::channel();
+                let task =
+                    AsyncTask::LoadLabelValues(ChartIndex(0), SeriesIndex(0), values, changes_tx);
+                tx.send(task).await.unwrap();
+                let (started, _) = changes_rx.await.unwrap();
+                tx.send(AsyncTask::Shutdown).await.unwrap();
+                coordinator.await.unwrap();
+                started
+                    .into_iter()
+                    .map(|request| (request.series_index, request.source_url))
+                    .collect::<Vec<_>>()
+            }
+        };
+        let started = run(&["/var", "/", "/boot"]).await;
+        assert_eq!(started.len(), 3);
+        assert!(started[0].1.contains(r#"{mountpoint="/"}"#));
+        assert!(started[1].1.contains(r#"{mountpoint="/boot"}"#));
+        assert!(started[2].1.contains(r#"{mountpoint="/var"}"#));
+        assert_eq!(run(&["/boot", "/var", "/", "/var"]).await, started);
+    }
+
+    #[tokio::test]
+    async fn it_loads_the_sub_series_regardless_of_arrival() {
+        let result = |instance: &str, first: &str, second: &str| prometheus::HTTPMatrixResult {
+            labels: HashMap::from([(String::from("instance"), instance.to_string())]),
+            values: vec![
+                vec![serde_json::json!(8), serde_json::json!(first)],
+                vec![serde_json::json!(9), serde_json::json!(second)],
+            ],
+        };
+        let run = |results: Vec<prometheus::HTTPMatrixResult>| async move {
+            let mut prom = prometheus::PrometheusTimeSeries {
+                source: String::from("http://localhost:9090/api/v1/query_range?query=up"),
+                data_type: String::from("matrix"),
+                ..prometheus::PrometheusTimeSeries::default()
+            };
+            // The sub-series collide, the last one loaded is kept.
+            prom.init();
+            let chart_config = ChartsConfig {
+                charts: vec![TimeSeriesChart {
+                    name: String::from("up"),
+                    sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                    dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                    ..TimeSeriesChart::default()
+                }],
+                ..ChartsConfig::default()
+            };
+            let size = SizeInfo { width: 800., height: 600., ..SizeInfo::default() };
+            let (tx, rx) = mpsc::channel(64);
+            let coordinator =
+                tokio::spawn(async_coordinator(rx, chart_config, size, VoidListener, status_tx()));
+            tx.send(AsyncTask::FreezeClock(10)).await.unwrap();
+            let response = MetricRequest {
+                pull_interval: 15,
+                source_url: String::from("http://localhost:9090/api/v1/query_range?query=up"),
+                chart_index: ChartIndex(0),
+                series_index: SeriesIndex(0),
+                data: Some(prometheus::HTTPResponse {
+                    status: String::from("success"),
+                    data: prometheus::HTTPResponseData::Matrix { result: results },
+                }),
+                capacity: 30,
+                max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+            };
+            tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+            let (opengl_tx, opengl_rx) = oneshot::channel();
+            let task = AsyncTask::SendMetricsOpenGLData(
+                WindowId::default(),
+                ChartIndex(0),
+                SeriesIndex(0),
+                opengl_tx,
+            );
+            tx.send(task).await.unwrap();
+            let (vertices, ..) = opengl_rx.await.unwrap();
+            tx.send(AsyncTask::Shutdown).await.unwrap();
+            coordinator.await.unwrap();
+            vertices
+        };
+        let vertices = run(vec![result("a", "1", "3"), result("b", "3", "1")]).await;
+        assert!(!vertices.is_empty());
+        assert_eq!(run(vec![result("b", "3", "1"), result("a", "1", "3")]).await, vertices);
+    }
+
+    #[tokio::test]
+    async fn it_journals_the_anomalies_of_the_loaded_data() {
+        let prom = prometheus::PrometheusTimeSeries {
+            name: String::from("load"),
+            source: String::from("http://localhost:9090/api/v1/query?query=node_load1"),
+            data_type: String::from("scalar"),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("node"),
+                sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            journal: Some(JournalConfig {
+                max_entries: Some(2),
+                record: None,
+                std_devs: Some(3.),
+                gap_intervals: Some(2),
+            }),
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        tx.send(AsyncTask::FreezeClock(1000)).await.unwrap();
+        let load = |result: Vec<serde_json::Value>| {
+            let tx = tx.clone();
+            async move {
+                let response = MetricRequest {
+                    pull_interval: 1,
+                    source_url: String::from("http://localhost:9090/api/v1/query?query=node_load1"),
+                    chart_index: ChartIndex(0),
+                    series_index: SeriesIndex(0),
+                    data: Some(prometheus::HTTPResponse {
+                        status: String::from("success"),
+                        data: prometheus::HTTPResponseData::Scalar { result },
+                    }),
+                    capacity: 300,
+                    max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+                };
+                tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+            }
+        };
+        let sample =
+            |epoch: u64, value: &str| vec![serde_json::json!(epoch), serde_json::json!(value)];
+        let journal = || {
+            let tx = tx.clone();
+            async move {
+                let (journal_tx, journal_rx) = oneshot::channel();
+                tx.send(AsyncTask::GetJournal(journal_tx)).await.unwrap();
+                journal_rx.await.unwrap()
+            }
+        };
+        // Normal data produces no entries.
+        for epoch in 100..110 {
+            load(sample(epoch, if epoch % 2 == 0 { "10" } else { "11" })).await;
+        }
+        assert!(journal().await.is_empty());
+        load(sample(110, "50")).await;
+        let entries = journal().await;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].kind, AnomalyKind::Outlier);
+        assert_eq!(entries[0].samples, vec![(110, Some(50.))]);
+        assert_eq!((entries[0].chart.as_str(), entries[0].source.as_str()), ("node", "load"));
+        assert_eq!(entries[0].wall_time, 1000);
+        assert!(entries[0].response.contains("scalar"));
+        load(vec![]).await;
+        load(sample(200, "11")).await;
+        // The oldest entry is evicted.
+        let entries = journal().await;
+        let kinds: Vec<AnomalyKind> = entries.iter().map(|entry| entry.kind).collect();
+        assert_eq!(kinds, vec![AnomalyKind::Empty, AnomalyKind::Gap]);
+        assert_eq!(entries[1].samples, vec![(110, Some(50.)), (200, Some(11.))]);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_batches_the_redraws_of_visible_charts() {
+        let chart = |name: &str, position| {
+            let prom = prometheus::PrometheusTimeSeries {
+                source: String::from("http://localhost:9090/api/v1/query?query=1"),
+                data_type: String::from("scalar"),
+                ..prometheus::PrometheusTimeSeries::default()
+            };
+            TimeSeriesChart {
+                name: name.to_string(),
+                sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                position: Some(position),
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }
+        };
+        let mut collector = chart("collector", ChartLength2D::pixels(200., 0.));
+        collector.suppress_redraw = true;
+        let chart_config = ChartsConfig {
+            charts: vec![
+                chart("visible", ChartLength2D::pixels(0., 0.)),
+                collector,
+                // Does not fit in the window.
+                chart("hidden", ChartLength2D::pixels(5000., 0.)),
+            ],
+            overflow: ChartOverflow::Hide,
+            ..ChartsConfig::default()
+        };
+        let size = SizeInfo { width: 800., height: 600., ..SizeInfo::default() };
+        let (event_tx, event_rx) = std::sync::mpsc::channel();
+        let event_proxy = dashboard::ChannelEventSink(event_tx);
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator =
+            tokio::spawn(async_coordinator(rx, chart_config, size, event_proxy, status_tx()));
+        let load_responses = |chart_idx: usize, count: usize| {
+            let tx = tx.clone();
+            async move {
+                for _ in 0..count {
+                    let empty = prometheus::HTTPResponse {
+                        status: String::from("success"),
+                        data: prometheus::HTTPResponseData::Scalar { result: vec![] },
+                    };
+                    let response = MetricRequest {
+                        pull_interval: 15,
+                        source_url: String::from("http://localhost:9090/api/v1/query?query=1"),
+                        chart_index: ChartIndex(chart_idx),
+                        series_index: SeriesIndex(0),
+                        data: Some(empty),
+                        capacity: 300,
+                        max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+                    };
+                    tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+                }
+                time::sleep(LOAD_REDRAW_BATCH * 4).await;
+            }
+        };
+        let chart_events =
+            || event_rx.try_iter().filter(|event| matches!(event, Event::ChartEvent)).count();
+        // A burst of responses is redrawn once.
+        load_responses(0, 5).await;
+        assert_eq!(chart_events(), 1);
+        load_responses(0, 1).await;
+        assert_eq!(chart_events(), 1);
+        // Data collectors and charts out of the window do not redraw.
+        load_responses(1, 5).await;
+        load_responses(2, 5).await;
+        assert_eq!(chart_events(), 0);
+        // Neither do the charts while the terminal hides them.
+        tx.send(AsyncTask::SetChartsVisible(false)).await.unwrap();
+        load_responses(0, 5).await;
+        assert_eq!(chart_events(), 0);
+        tx.send(AsyncTask::SetChartsVisible(true)).await.unwrap();
+        load_responses(0, 5).await;
+        assert_eq!(chart_events(), 1);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_shows_the_chart_decorations_by_visibility_group() {
+        let groups = [
+            None,
+            Some(VisibilityGroup::Always),
+            Some(VisibilityGroup::WithCharts),
+            Some(VisibilityGroup::WithDecorToggle),
+        ];
+        let mut chart_config = decorated_config(
+            groups
+                .iter()
+                .map(|group| {
+                    Decoration::Reference(ReferencePointDecoration {
+                        visibility_group: *group,
+                        ..Default::default()
+                    })
+                })
+                .collect(),
+        );
+        let chart = &mut chart_config.charts[0];
+        for (epoch, value) in [(10, 1.), (11, 2.)] {
+            chart.sources[0].series_mut().upsert((epoch, Some(value)));
+        }
+        let size = SizeInfo { width: 800., height: 600., ..SizeInfo::default() };
+        chart
+            .update_all_series_opengl_vecs(ChartSizeInfo { term_size: size, ..Default::default() });
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator =
+            tokio::spawn(async_coordinator(rx, chart_config, size, VoidListener, status_tx()));
+        // The decorations with vertices for each (charts_visible, decorations_enabled).
+        let matrix = [
+            ((true, true), [true, true, true, true]),
+            ((true, false), [true, true, true, false]),
+            ((false, true), [false, true, false, true]),
+            ((false, false), [false, true, false, false]),
+        ];
+        for ((charts_visible, decorations_enabled), expected) in matrix {
+            tx.send(AsyncTask::SetChartsVisible(charts_visible)).await.unwrap();
+            tx.send(AsyncTask::SetDecorationsEnabled(decorations_enabled)).await.unwrap();
+            let mut drawn = [false; 4];
+            for (idx, drawn) in drawn.iter_mut().enumerate() {
+                let (opengl_tx, opengl_rx) = oneshot::channel();
+                tx.send(AsyncTask::SendChartDecorationsOpenGLData(
+                    WindowId::default(),
+                    ChartIndex(0),
+                    DecorationIndex(idx),
+                    opengl_tx,
+                ))
+                .await
+                .unwrap();
+                *drawn = !opengl_rx.await.unwrap().0.is_empty();
+            }
+            assert_eq!(
+                drawn, expected,
+                "charts {} decorations {}",
+                charts_visible, decorations_enabled
+            );
+        }
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_shows_a_placeholder_until_the_first_response_loads() {
+        let prom = prometheus::PrometheusTimeSeries {
+            source: String::from("http://localhost:9090/api/v1/query?query=1"),
+            data_type: String::from("scalar"),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("scalar"),
+                sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let size = SizeInfo { width: 800., height: 600., ..SizeInfo::default() };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator =
+            tokio::spawn(async_coordinator(rx, chart_config, size, VoidListener, status_tx()));
+        let request_placeholder = || {
+            let tx = tx.clone();
+            async move {
+                let (placeholder_tx, placeholder_rx) = oneshot::channel();
+                let task = AsyncTask::SendChartPlaceholder(
+                    WindowId::default(),
+                    ChartIndex(0),
+                    placeholder_tx,
+                );
+                tx.send(task).await.unwrap();
+                placeholder_rx.await.unwrap()
+            }
+        };
+        let load_response = |data| {
+            let tx = tx.clone();
+            async move {
+                let response = MetricRequest {
+                    pull_interval: 15,
+                    source_url: String::from("http://localhost:9090/api/v1/query?query=1"),
+                    chart_index: ChartIndex(0),
+                    series_index: SeriesIndex(0),
+                    data,
+                    capacity: 300,
+                    max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+                };
+                tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+            }
+        };
+        // A fresh chart is awaiting data, its placeholder pulses with the decoration ticks.
+        let mut alphas = vec![request_placeholder().await.unwrap().alpha];
+        for _ in 0..PLACEHOLDER_PULSE_TICKS {
+            let task = AsyncTask::DecorUpdate(ChartIndex(0), DECORATION_TICK_MS as f32);
+            tx.send(task).await.unwrap();
+            alphas.push(request_placeholder().await.unwrap().alpha);
+        }
+        assert!(alphas[1] > alphas[0]);
+        assert!(alphas[PLACEHOLDER_PULSE_TICKS as usize] < alphas[1]);
+        assert_eq!(alphas[0], alphas[PLACEHOLDER_PULSE_TICKS as usize]);
+        // Failed responses and responses without samples do not clear it.
+        let failed =
+            prometheus::HTTPResponse { status: String::from("error"), ..Default::default() };
+        load_response(Some(failed)).await;
+        load_response(None).await;
+        let empty = prometheus::HTTPResponse {
+            status: String::from("success"),
+            data: prometheus::HTTPResponseData::Scalar { result: vec![] },
+        };
+        load_response(Some(empty)).await;
+        assert!(request_placeholder().await.is_some());
+        // The first loaded response clears it for the rest of the session.
+        let loaded = prometheus::HTTPResponse {
+            status: String::from("success"),
+            data: prometheus::HTTPResponseData::Scalar {
+                result: vec![serde_json::json!(1558283674.829), serde_json::json!("1")],
+            },
+        };
+        load_response(Some(loaded)).await;
+        assert_eq!(request_placeholder().await, None);
+        load_response(None).await;
+        assert_eq!(request_placeholder().await, None);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_stops_generating_vertices_without_rendering() {
+        let chart_config = ChartsConfig {
+            charts: vec![TimeSeriesChart {
+                name: String::from("input"),
+                sources: vec![TimeSeriesSource::default()],
+                dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                ..TimeSeriesChart::default()
+            }],
+            ..ChartsConfig::default()
+        };
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (capacity_tx, capacity_rx) = oneshot::channel();
+        let task = AsyncTask::SendSeriesCapacity(ChartIndex(0), SeriesIndex(0), capacity_tx);
+        tx.send(task).await.unwrap();
+        assert!(capacity_rx.await.is_ok());
+        tx.send(AsyncTask::DisableRendering).await.unwrap();
+        // Requests are dropped without generating vertices, the requesters are not left waiting.
+        tx.send(AsyncTask::FreezeClock(10)).await.unwrap();
+        tx.send(AsyncTask::IncrementInputCounter(1.)).await.unwrap();
+        let (vertices_tx, vertices_rx) = oneshot::channel();
+        let (chart_index, series_index) = (ChartIndex(0), SeriesIndex(0));
+        let window_id = WindowId::default();
+        tx.send(AsyncTask::SendMetricsOpenGLData(
+            window_id,
+            chart_index,
+            series_index,
+            vertices_tx,
+        ))
+        .await
+        .unwrap();
+        assert!(vertices_rx.await.is_err());
+        let (size_tx, size_rx) = oneshot::channel();
+        tx.send(AsyncTask::ChangeDisplaySize(
+            WindowId::default(),
+            800.,
+            2000.,
+            0.,
+            0.,
+            10.,
+            20.,
+            size_tx,
+        ))
+        .await
+        .unwrap();
+        assert!(size_rx.await.is_err());
+        // The coordinator still shuts down.
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_scales_vertices_per_window() {
+        let chart_config = single_chart_config();
+        let (tx, rx) = mpsc::channel(16);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (small, large) = (WindowId(1), WindowId(2));
+        for (window_id, height, width) in [(small, 400., 1000.), (large, 800., 2000.)] {
+            let (size_tx, size_rx) = oneshot::channel();
+            tx.send(AsyncTask::ChangeDisplaySize(
+                window_id, height, width, 0., 0., 10., 20., size_tx,
+            ))
+            .await
+            .unwrap();
+            assert!(size_rx.await.unwrap());
+        }
+        for (epoch, value) in [(10, 1.), (11, 2.), (12, 3.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementInputCounter(value)).await.unwrap();
+        }
+        let small_vertices = request_series_vertices(&tx, small).await;
+        let large_vertices = request_series_vertices(&tx, large).await;
+        assert!(!small_vertices.is_empty());
+        assert_eq!(small_vertices.len(), large_vertices.len());
+        assert_ne!(small_vertices, large_vertices);
+        // The first value, 1.0, is at 1/3 of the 50px chart height.
+        assert!((small_vertices[1] - -(400. - 50. / 3. - 200.) / 200.).abs() < 1e-5);
+        assert!((large_vertices[1] - -(800. - 50. / 3. - 400.) / 400.).abs() < 1e-5);
+        // Requests alternating between the windows keep their own scale.
+        assert_eq!(request_series_vertices(&tx, small).await, small_vertices);
+        assert_eq!(request_series_vertices(&tx, large).await, large_vertices);
+        // Unknown windows use the default window size.
+        let default_vertices = request_series_vertices(&tx, WindowId(3)).await;
+        assert_ne!(default_vertices, small_vertices);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_applies_the_counters_deferred_while_degraded() {
+        let run = |degraded: bool| async move {
+            let chart_config = ChartsConfig {
+                charts: vec![TimeSeriesChart {
+                    name: String::from("output"),
+                    sources: vec![TimeSeriesSource::AlacrittyOutput(Default::default())],
+                    dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                    ..TimeSeriesChart::default()
+                }],
+                load_governor: Some(crate::charts::GovernorConfig {
+                    p95_threshold_ms: 1000.,
+                    recover_ratio: None,
+                    window: None,
+                    hold_ms: Some(60_000),
+                }),
+                ..ChartsConfig::default()
+            };
+            let (status_tx, status_rx) = watch::channel(ChartsStatus::Running);
+            let (tx, rx) = mpsc::channel(64);
+            let coordinator = tokio::spawn(async_coordinator(
+                rx,
+                chart_config,
+                SizeInfo::default(),
+                VoidListener,
+                status_tx,
+            ));
+            let series_values = || {
+                let tx = tx.clone();
+                async move {
+                    let (values_tx, values_rx) = oneshot::channel();
+                    let task =
+                        AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                    tx.send(task).await.unwrap();
+                    values_rx.await.unwrap()
+                }
+            };
+            if degraded {
+                tx.send(AsyncTask::ForceGovernorMode(GovernorMode::Degraded)).await.unwrap();
+            }
+            // A burst of output spread over a few seconds.
+            for idx in 0..10_000u64 {
+                if idx % 2500 == 0 {
+                    tx.send(AsyncTask::FreezeClock(100 + idx / 2500)).await.unwrap();
+                }
+                tx.send(AsyncTask::IncrementOutputCounter(1.)).await.unwrap();
+                if degraded && idx == 100 {
+                    assert_eq!(*status_rx.borrow(), ChartsStatus::Degraded);
+                    // The increments wait for the next flush.
+                    assert!(series_values().await.is_empty());
+                }
+            }
+            if degraded {
+                time::sleep(DEGRADED_FLUSH_INTERVAL + LOAD_REDRAW_BATCH).await;
+            }
+            let values = series_values().await;
+            tx.send(AsyncTask::Shutdown).await.unwrap();
+            coordinator.await.unwrap();
+            values
+        };
+        let normal = run(false).await;
+        assert_eq!(normal.iter().filter_map(|(_, value)| *value).sum::<f64>(), 10_000.);
+        assert_eq!(normal.last(), Some(&(103, Some(2500.))));
+        assert_eq!(run(true).await, normal);
+    }
+
+    #[tokio::test]
+    async fn it_waits_for_the_display_size_to_generate_vertices() {
+        let run = |size_first: bool| async move {
+            let prom = prometheus::PrometheusTimeSeries {
+                source: String::from("http://localhost:9090/api/v1/query?query=1"),
+                data_type: String::from("scalar"),
+                ..prometheus::PrometheusTimeSeries::default()
+            };
+            let chart_config = ChartsConfig {
+                charts: vec![TimeSeriesChart {
+                    name: String::from("scalar"),
+                    sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+                    position: Some(ChartLength2D::pixels(0., 0.)),
+                    dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                    ..TimeSeriesChart::default()
+                }],
+                ..ChartsConfig::default()
+            };
+            let (event_tx, event_rx) = std::sync::mpsc::channel();
+            let event_proxy = dashboard::ChannelEventSink(event_tx);
+            let (tx, rx) = mpsc::channel(64);
+            // The display has not sent its size when the coordinator starts.
+            let coordinator = tokio::spawn(async_coordinator(
+                rx,
+                chart_config,
+                SizeInfo::default(),
+                event_proxy,
+                status_tx(),
+            ));
+            let chart_events =
+                || event_rx.try_iter().filter(|event| matches!(event, Event::ChartEvent)).count();
+            let change_display_size = || {
+                let tx = tx.clone();
+                async move {
+                    let (size_tx, size_rx) = oneshot::channel();
+                    let window_id = WindowId::default();
+                    let task = AsyncTask::ChangeDisplaySize(
+                        window_id, 800., 2000., 0., 0., 10., 20., size_tx,
+                    );
+                    tx.send(task).await.unwrap();
+                    assert!(size_rx.await.unwrap());
+                }
+            };
+            if size_first {
+                change_display_size().await;
+            }
+            tx.send(AsyncTask::FreezeClock(12)).await.unwrap();
+            for (epoch, value) in [(10., 1.), (11., 2.), (12., 3.)] {
+                let response = MetricRequest {
+                    pull_interval: 15,
+                    source_url: String::from("http://localhost:9090/api/v1/query?query=1"),
+                    chart_index: ChartIndex(0),
+                    series_index: SeriesIndex(0),
+                    data: Some(scalar_response(epoch, value)),
+                    capacity: 300,
+                    max_body_bytes: prometheus::MAX_RESPONSE_BODY_BYTES,
+                };
+                tx.send(AsyncTask::LoadResponse(response)).await.unwrap();
+            }
+            time::sleep(LOAD_REDRAW_BATCH * 4).await;
+            if !size_first {
+                // The samples are loaded, their vertices and the redraw wait for the size.
+                let (values_tx, values_rx) = oneshot::channel();
+                let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                tx.send(task).await.unwrap();
+                let values = values_rx.await.unwrap();
+                assert_eq!(values.iter().filter(|(_, value)| value.is_some()).count(), 3);
+                assert!(request_series_vertices(&tx, WindowId::default()).await.is_empty());
+                assert_eq!(chart_events(), 0);
+                change_display_size().await;
+            }
+            let vertices = request_series_vertices(&tx, WindowId::default()).await;
+            let events = chart_events();
+            tx.send(AsyncTask::Shutdown).await.unwrap();
+            coordinator.await.unwrap();
+            (vertices, events)
+        };
+        let (vertices, events) = run(false).await;
+        assert!(!vertices.is_empty());
+        assert!(vertices.iter().all(|vertex| vertex.is_finite()));
+        // The vertices are regenerated once for the size, as if it had arrived first.
+        assert_eq!(events, 1);
+        assert_eq!(run(true).await, (vertices, events));
+    }
+
+    #[tokio::test]
+    async fn it_redraws_the_decorations_at_their_own_rates() {
+        let run = |decoration_rates: Vec<f32>| async move {
+            let chart_config = ChartsConfig { decoration_rates, ..ChartsConfig::default() };
+            let (event_tx, event_rx) = std::sync::mpsc::channel();
+            let event_proxy = dashboard::ChannelEventSink(event_tx);
+            let (tx, rx) = mpsc::channel(64);
+            let coordinator = tokio::spawn(async_coordinator(
+                rx,
+                chart_config,
+                SizeInfo::default(),
+                event_proxy,
+                status_tx(),
+            ));
+            // 2 seconds of decoration ticks.
+            for _ in 0..20 {
+                let task = AsyncTask::DecorUpdate(ChartIndex(0), DECORATION_TICK_MS as f32);
+                tx.send(task).await.unwrap();
+            }
+            tx.send(AsyncTask::Shutdown).await.unwrap();
+            coordinator.await.unwrap();
+            event_rx.try_iter().filter(|event| matches!(event, Event::DecorEvent)).count()
+        };
+        // The ticks follow the 10 Hz decoration.
+        let config =
+            ChartsConfig { decoration_rates: vec![1., 10., 0.], ..ChartsConfig::default() };
+        assert_eq!(config.decoration_tick_interval(), Duration::from_millis(DECORATION_TICK_MS));
+        assert_eq!(run(vec![1., 10., 0.]).await, 20);
+        // Only the updates of the 1 Hz decoration are drawn without it.
+        assert_eq!(run(vec![1., 0.]).await, 2);
+        assert_eq!(run(vec![0.]).await, 0);
+    }
+
+    #[tokio::test]
+    async fn it_restores_the_samples_of_the_previous_session() {
+        let dir = std::env::temp_dir()
+            .join(format!("chartacritty-coordinator-state-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("charts.state");
+        let chart_config = ChartsConfig {
+            persistence: Some(PersistenceConfig { path: path.clone(), snapshot_interval_s: None }),
+            ..single_chart_config()
+        };
+        let session = |increments: Vec<(u64, f64)>| {
+            let chart_config = chart_config.clone();
+            async move {
+                let (tx, rx) = mpsc::channel(64);
+                let coordinator = tokio::spawn(async_coordinator(
+                    rx,
+                    chart_config,
+                    SizeInfo::default(),
+                    VoidListener,
+                    status_tx(),
+                ));
+                for (epoch, value) in increments {
+                    tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+                    tx.send(AsyncTask::IncrementInputCounter(value)).await.unwrap();
+                }
+                let (values_tx, values_rx) = oneshot::channel();
+                let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                tx.send(task).await.unwrap();
+                let values = values_rx.await.unwrap();
+                tx.send(AsyncTask::Shutdown).await.unwrap();
+                coordinator.await.unwrap();
+                values
+            }
+        };
+        // The first session starts without a state file and writes it on shutdown.
+        let first = session(vec![(100, 1.), (101, 2.)]).await;
+        assert_eq!(first, vec![(100, Some(1.)), (101, Some(2.))]);
+        assert!(path.exists());
+        assert!(!persistence::temp_path(&path).exists());
+        // The next one starts with its samples.
+        assert_eq!(session(vec![]).await, first);
+        // A state file written for another chart config is not loaded.
+        let mut renamed = chart_config.clone();
+        renamed.charts[0].name = String::from("keys");
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            renamed,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (values_tx, values_rx) = oneshot::channel();
+        let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+        tx.send(task).await.unwrap();
+        assert!(values_rx.await.unwrap().is_empty());
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_summarizes_the_charts_since_an_epoch() {
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            single_chart_config(),
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let summarize = |epoch| {
+            let tx = tx.clone();
+            async move {
+                let (reply, summaries_rx) = oneshot::channel();
+                tx.send(AsyncTask::SummarizeSince { epoch, reply }).await.unwrap();
+                summaries_rx.await.unwrap()
+            }
+        };
+        for (epoch, value) in [(1000, 2.), (1030, 5.), (1060, 1.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementInputCounter(value)).await.unwrap();
+        }
+        let summaries = summarize(1010).await;
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].to_string(), "input: from 5 to 1 over 30s, min 1, max 5, avg 3");
+        // The charts without values since the epoch are not summarized.
+        assert!(summarize(1070).await.is_empty());
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_loads_the_samples_of_a_source() {
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            single_chart_config(),
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let identity = SourceIdentity {
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(0),
+            name: String::from("file"),
+        };
+        let series_values = || {
+            let tx = tx.clone();
+            async move {
+                let (values_tx, values_rx) = oneshot::channel();
+                let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                tx.send(task).await.unwrap();
+                values_rx.await.unwrap()
+            }
+        };
+        tx.send(AsyncTask::FreezeClock(1002)).await.unwrap();
+        let samples = Ok(vec![(1000, Some(1.)), (1001, None), (1002, Some(3.))]);
+        tx.send(AsyncTask::LoadSamples { identity: identity.clone(), samples }).await.unwrap();
+        let loaded = series_values().await;
+        assert_eq!(loaded, vec![(1000, Some(1.)), (1001, None), (1002, Some(3.))]);
+        // A failed fetch and a source of an unknown series leave the series unchanged.
+        let samples = Err(FetchError::Failed(String::from("unreadable")));
+        tx.send(AsyncTask::LoadSamples { identity: identity.clone(), samples }).await.unwrap();
+        let unknown = SourceIdentity { series_index: SeriesIndex(3), ..identity };
+        let samples = Ok(vec![(1003, Some(4.))]);
+        tx.send(AsyncTask::LoadSamples { identity: unknown, samples }).await.unwrap();
+        assert_eq!(series_values().await, loaded);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_replaces_the_live_edge_at_the_epoch_boundary() {
+        let mut chart_config = single_chart_config();
+        chart_config.charts[0].live_edge = true;
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            chart_config,
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (size_tx, size_rx) = oneshot::channel();
+        let window_id = WindowId::default();
+        let task = AsyncTask::ChangeDisplaySize(window_id, 800., 2000., 0., 0., 10., 20., size_tx);
+        tx.send(task).await.unwrap();
+        assert!(size_rx.await.unwrap());
+        let live_edge = || {
+            let tx = tx.clone();
+            async move {
+                let (opengl_tx, opengl_rx) = oneshot::channel();
+                let task = AsyncTask::SendLiveEdgeOpenGLData(
+                    window_id,
+                    ChartIndex(0),
+                    SeriesIndex(0),
+                    opengl_tx,
+                );
+                tx.send(task).await.unwrap();
+                opengl_rx.await.unwrap()
+            }
+        };
+        for (epoch, value) in [(10, 1.), (11, 4.), (12, 2.)] {
+            tx.send(AsyncTask::FreezeClock(epoch)).await.unwrap();
+            tx.send(AsyncTask::IncrementInputCounter(value)).await.unwrap();
+        }
+        let (edge, alpha, _) = live_edge().await;
+        assert_eq!(edge.len(), 4);
+        assert_eq!(alpha, LIVE_EDGE_ALPHA);
+        // The increments within the second move the live edge.
+        tx.send(AsyncTask::IncrementInputCounter(1.)).await.unwrap();
+        let (grown, ..) = live_edge().await;
+        assert!(grown[3] > edge[3]);
+        let (values_tx, values_rx) = oneshot::channel();
+        tx.send(AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx))
+            .await
+            .unwrap();
+        assert_eq!(values_rx.await.unwrap(), vec![(10, Some(1.)), (11, Some(4.))]);
+        // The next decoration tick after the second completes turns it into a sample.
+        tx.send(AsyncTask::FreezeClock(13)).await.unwrap();
+        tx.send(AsyncTask::DecorUpdate(ChartIndex(0), 16.)).await.unwrap();
+        assert!(live_edge().await.0.is_empty());
+        let vertices = request_series_vertices(&tx, window_id).await;
+        assert_eq!(vertices.len(), 6);
+        assert_eq!(vertices[4], grown[2]);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_replies_not_found_for_a_missing_chart() {
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            single_chart_config(),
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let (changed_tx, changed_rx) = oneshot::channel();
+        tx.send(AsyncTask::SetWindowSeconds(Some(ChartIndex(1)), 60, changed_tx)).await.unwrap();
+        let reply = changed_rx.await.unwrap();
+        assert_eq!(reply, Err(ChartTaskError::NotFound(ChartIndex(1))));
+        assert_eq!(reply.unwrap_err().to_string(), "chart #1 not found");
+        // The charts that exist are still resized.
+        let (changed_tx, changed_rx) = oneshot::channel();
+        tx.send(AsyncTask::SetWindowSeconds(Some(ChartIndex(0)), 60, changed_tx)).await.unwrap();
+        assert_eq!(changed_rx.await.unwrap(), Ok(true));
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_resizes_a_series_at_runtime() {
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            single_chart_config(),
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let series_capacity = || {
+            let tx = tx.clone();
+            async move {
+                let (capacity_tx, capacity_rx) = oneshot::channel();
+                let task =
+                    AsyncTask::SendSeriesCapacity(ChartIndex(0), SeriesIndex(0), capacity_tx);
+                tx.send(task).await.unwrap();
+                capacity_rx.await.unwrap()
+            }
+        };
+        let series_values = || {
+            let tx = tx.clone();
+            async move {
+                let (values_tx, values_rx) = oneshot::channel();
+                let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                tx.send(task).await.unwrap();
+                values_rx.await.unwrap()
+            }
+        };
+        let identity = SourceIdentity {
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(0),
+            name: String::from("file"),
+        };
+        tx.send(AsyncTask::FreezeClock(1002)).await.unwrap();
+        let samples = Ok(vec![(1000, Some(1.)), (1001, Some(2.)), (1002, Some(3.))]);
+        tx.send(AsyncTask::LoadSamples { identity, samples }).await.unwrap();
+        let capacity = series_capacity().await;
+        assert!(capacity > 2);
+        // Shrinking keeps the newest samples.
+        let task = AsyncTask::ResizeSeriesCapacity(ChartIndex(0), SeriesIndex(0), 2);
+        tx.send(task).await.unwrap();
+        assert_eq!(series_capacity().await, 2);
+        assert_eq!(series_values().await, vec![(1001, Some(2.)), (1002, Some(3.))]);
+        // An unknown series or an empty capacity leave the series unchanged.
+        let task = AsyncTask::ResizeSeriesCapacity(ChartIndex(0), SeriesIndex(3), 5);
+        tx.send(task).await.unwrap();
+        let task = AsyncTask::ResizeSeriesCapacity(ChartIndex(0), SeriesIndex(0), 0);
+        tx.send(task).await.unwrap();
+        assert_eq!(series_capacity().await, 2);
+        // Growing keeps the samples and makes room for the older ones.
+        let task = AsyncTask::ResizeSeriesCapacity(ChartIndex(0), SeriesIndex(0), capacity);
+        tx.send(task).await.unwrap();
+        assert_eq!(series_capacity().await, capacity);
+        assert_eq!(series_values().await, vec![(1001, Some(2.)), (1002, Some(3.))]);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn it_clears_a_chart_at_runtime() {
+        let (tx, rx) = mpsc::channel(64);
+        let coordinator = tokio::spawn(async_coordinator(
+            rx,
+            single_chart_config(),
+            SizeInfo::default(),
+            VoidListener,
+            status_tx(),
+        ));
+        let series_values = || {
+            let tx = tx.clone();
+            async move {
+                let (values_tx, values_rx) = oneshot::channel();
+                let task = AsyncTask::SendSeriesValues(ChartIndex(0), SeriesIndex(0), values_tx);
+                tx.send(task).await.unwrap();
+                values_rx.await.unwrap()
+            }
+        };
+        let identity = SourceIdentity {
+            chart_index: ChartIndex(0),
+            series_index: SeriesIndex(0),
+            name: String::from("file"),
+        };
+        tx.send(AsyncTask::FreezeClock(1002)).await.unwrap();
+        let samples = Ok(vec![(1000, Some(1.)), (1001, Some(2.)), (1002, Some(3.))]);
+        let task = AsyncTask::LoadSamples { identity: identity.clone(), samples };
+        tx.send(task).await.unwrap();
+        assert_eq!(series_values().await.len(), 3);
+        tx.send(AsyncTask::ClearChart(ChartIndex(0))).await.unwrap();
+        assert_eq!(series_values().await, vec![]);
+        // An unknown chart is ignored.
+        tx.send(AsyncTask::ClearChart(ChartIndex(3))).await.unwrap();
+        // The next samples start a fresh window.
+        let samples = Ok(vec![(1002, Some(7.))]);
+        tx.send(AsyncTask::LoadSamples { identity, samples }).await.unwrap();
+        assert_eq!(series_values().await, vec![(1002, Some(7.))]);
+        tx.send(AsyncTask::Shutdown).await.unwrap();
+        coordinator.await.unwrap();
+    }
 }
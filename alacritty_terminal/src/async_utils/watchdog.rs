@@ -0,0 +1,336 @@
+//! `watchdog` tells when the charts stop receiving data: the items loaded by the Prometheus
+//! series are added up over a window of seconds and the charts are starved when they fall below
+//! a minimum. A series that loaded nothing in the window is starved on its own, i.e. when a
+//! single source is dead while the others are fine. Only the transitions are notified, so that a
+//! long outage does not flood the terminal.
+
+use crate::charts::{ChartIndex, SeriesIndex, TimeSeriesChart, TimeSeriesSource, WatchdogConfig};
+use crate::event::{Event, EventListener};
+use std::collections::{BTreeMap, HashMap, VecDeque};
+use std::fmt;
+use tracing::{event, Level};
+
+/// `WatchdogTransition` is a change of the starvation of the charts or of one of their sources
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogTransition {
+    /// The items loaded across the window fell below the minimum, with the sources that loaded
+    /// nothing
+    Starved { items: usize, min_items: usize, window: u64, sources: Vec<String> },
+    /// The items loaded across the window are back to the minimum
+    Recovered { items: usize, window: u64 },
+    /// A source loaded nothing in the window while the others did
+    SourceStarved { source: String, window: u64 },
+    /// A starved source loaded items again
+    SourceRecovered { source: String },
+}
+
+impl fmt::Display for WatchdogTransition {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WatchdogTransition::Starved { items, min_items, window, sources } => {
+                write!(
+                    f,
+                    "Charts data starved: {} items loaded in the last {}s, expected {}",
+                    items, window, min_items
+                )?;
+                if !sources.is_empty() {
+                    write!(f, " (no data from {})", sources.join(", "))?;
+                }
+                Ok(())
+            },
+            WatchdogTransition::Recovered { items, window } => {
+                write!(f, "Charts data recovered: {} items loaded in the last {}s", items, window)
+            },
+            WatchdogTransition::SourceStarved { source, window } => {
+                write!(
+                    f,
+                    "Charts source {} starved: no items loaded in the last {}s",
+                    source, window
+                )
+            },
+            WatchdogTransition::SourceRecovered { source } => {
+                write!(f, "Charts source {} recovered", source)
+            },
+        }
+    }
+}
+
+/// `DataWatchdog` keeps the items loaded by the Prometheus series in the window of its config.
+/// The time is given by the caller as epoch seconds.
+#[derive(Debug, Clone)]
+pub struct DataWatchdog {
+    /// The window and minimum items
+    config: WatchdogConfig,
+
+    /// The epoch at which the watchdog started, nothing is starved before a whole window passed
+    started: u64,
+
+    /// The loads in the window, with their epoch and items
+    loads: VecDeque<(u64, ChartIndex, SeriesIndex, usize)>,
+
+    /// The epoch at which each source was first watched, discovered series get their own window
+    first_seen: HashMap<(ChartIndex, SeriesIndex), u64>,
+
+    /// Whether the items of all the sources are below the minimum
+    starved: bool,
+
+    /// The chart/series names of the sources whose starvation has been reported, in the order of
+    /// the config
+    starved_sources: BTreeMap<(ChartIndex, SeriesIndex), String>,
+}
+
+impl DataWatchdog {
+    /// `new` starts watching the loads at the `now` epoch
+    pub fn new(config: WatchdogConfig, now: u64) -> Self {
+        Self {
+            config,
+            started: now,
+            loads: VecDeque::new(),
+            first_seen: HashMap::new(),
+            starved: false,
+            starved_sources: BTreeMap::new(),
+        }
+    }
+
+    /// `record` adds the items loaded by a source at the `now` epoch
+    pub fn record(
+        &mut self,
+        chart_index: ChartIndex,
+        series_index: SeriesIndex,
+        items: usize,
+        now: u64,
+    ) {
+        self.loads.push_back((now, chart_index, series_index, items));
+    }
+
+    /// `is_starved` returns whether the items of all the sources are below the minimum
+    pub fn is_starved(&self) -> bool {
+        self.starved
+    }
+
+    /// `starved_sources` returns the names of the sources that loaded nothing in the window, in
+    /// the order of the config
+    pub fn starved_sources(&self) -> Vec<String> {
+        self.starved_sources.values().cloned().collect()
+    }
+
+    /// `evaluate` drops the loads older than the window and returns the transitions since the
+    /// previous evaluation. While the charts are starved the dead sources are part of the
+    /// starvation, they are reported on their own once the others recover.
+    pub fn evaluate(&mut self, charts: &[TimeSeriesChart], now: u64) -> Vec<WatchdogTransition> {
+        let window = self.config.window;
+        let oldest = now.saturating_sub(window);
+        while self.loads.front().map_or(false, |(epoch, ..)| *epoch < oldest) {
+            self.loads.pop_front();
+        }
+        let mut items = 0usize;
+        let mut loaded: HashMap<(ChartIndex, SeriesIndex), usize> = HashMap::new();
+        for (_, chart_index, series_index, load_items) in &self.loads {
+            items += load_items;
+            *loaded.entry((*chart_index, *series_index)).or_default() += load_items;
+        }
+        let mut watched = 0usize;
+        let mut dead = BTreeMap::new();
+        for (chart_idx, chart) in charts.iter().enumerate() {
+            for (series_idx, source) in chart.sources.iter().enumerate() {
+                if !matches!(source, TimeSeriesSource::PrometheusTimeSeries(_)) {
+                    continue;
+                }
+                watched += 1;
+                let key = (ChartIndex(chart_idx), SeriesIndex(series_idx));
+                let first_seen = *self.first_seen.entry(key).or_insert(now);
+                if now.saturating_sub(first_seen) < window {
+                    continue;
+                }
+                if loaded.get(&key).copied().unwrap_or_default() == 0 {
+                    dead.insert(key, format!("{}/{}", chart.name, source.name()));
+                }
+            }
+        }
+        let mut transitions = vec![];
+        if watched == 0 || now.saturating_sub(self.started) < window {
+            return transitions;
+        }
+        let min_items = self.config.min_items();
+        let starved = items < min_items;
+        if starved && !self.starved {
+            let sources = dead.values().cloned().collect();
+            transitions.push(WatchdogTransition::Starved { items, min_items, window, sources });
+        } else if !starved && self.starved {
+            transitions.push(WatchdogTransition::Recovered { items, window });
+            // The sources that are still dead have not been reported on their own yet.
+            self.starved_sources.clear();
+        }
+        self.starved = starved;
+        if !starved {
+            for (key, source) in &dead {
+                if !self.starved_sources.contains_key(key) {
+                    let source = source.clone();
+                    transitions.push(WatchdogTransition::SourceStarved { source, window });
+                }
+            }
+            for (key, source) in &self.starved_sources {
+                if !dead.contains_key(key) {
+                    let source = source.clone();
+                    transitions.push(WatchdogTransition::SourceRecovered { source });
+                }
+            }
+        }
+        self.starved_sources = dead;
+        transitions
+    }
+
+    /// `check` evaluates the watchdog and sends a ChartAlert for each of the transitions
+    pub fn check<U>(&mut self, charts: &[TimeSeriesChart], now: u64, event_proxy: &U) -> usize
+    where
+        U: EventListener,
+    {
+        let transitions = self.evaluate(charts, now);
+        for transition in &transitions {
+            event!(Level::WARN, "DataWatchdog: {}", transition);
+            event_proxy.send_event(Event::ChartAlert(transition.to_string()));
+        }
+        transitions.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charts::fixtures::{multi_source_config, MockEventListener};
+
+    /// `prometheus_charts` returns a chart with a Prometheus series per name
+    fn prometheus_charts(names: &[&str]) -> Vec<TimeSeriesChart> {
+        multi_source_config(names).charts
+    }
+
+    #[test]
+    fn it_alerts_once_per_starvation_transition() {
+        let charts = prometheus_charts(&["up"]);
+        let config = WatchdogConfig { window: 30, min_items: Some(10) };
+        let recorder = MockEventListener::default();
+        let mut watchdog = DataWatchdog::new(config, 1000);
+        // Nothing is starved before a whole window passed.
+        assert_eq!(watchdog.check(&charts, 1010, &recorder), 0);
+        for epoch in (1000..1060).step_by(15) {
+            watchdog.record(ChartIndex(0), SeriesIndex(0), 5, epoch);
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        assert!(recorder.take_alerts().is_empty());
+        assert!(!watchdog.is_starved());
+        // The loads stop, the window empties and the charts starve once.
+        for epoch in 1060..1200 {
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        let alerts = recorder.take_alerts();
+        assert_eq!(alerts.len(), 1);
+        assert!(alerts[0].starts_with("Charts data starved"));
+        assert!(watchdog.is_starved());
+        assert_eq!(watchdog.starved_sources(), vec![String::from("load/up")]);
+        // A load below the minimum does not recover them.
+        watchdog.record(ChartIndex(0), SeriesIndex(0), 3, 1200);
+        watchdog.check(&charts, 1200, &recorder);
+        assert!(recorder.take_alerts().is_empty());
+        // The loads are back, the recovery is notified once.
+        for epoch in 1201..1210 {
+            watchdog.record(ChartIndex(0), SeriesIndex(0), 5, epoch);
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        assert_eq!(
+            recorder.take_alerts(),
+            vec![String::from("Charts data recovered: 13 items loaded in the last 30s")]
+        );
+        assert!(!watchdog.is_starved());
+        assert!(watchdog.starved_sources().is_empty());
+    }
+
+    #[test]
+    fn it_names_a_starved_source() {
+        let charts = prometheus_charts(&["up", "down"]);
+        let config = WatchdogConfig { window: 30, min_items: None };
+        let recorder = MockEventListener::default();
+        let mut watchdog = DataWatchdog::new(config, 0);
+        // Only the first source loads items.
+        for epoch in 0..100 {
+            watchdog.record(ChartIndex(0), SeriesIndex(0), 1, epoch);
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        assert_eq!(
+            recorder.take_alerts(),
+            vec![String::from("Charts source load/down starved: no items loaded in the last 30s")]
+        );
+        assert!(!watchdog.is_starved());
+        assert_eq!(watchdog.starved_sources(), vec![String::from("load/down")]);
+        // The dead source loads again.
+        watchdog.record(ChartIndex(0), SeriesIndex(0), 1, 100);
+        watchdog.record(ChartIndex(0), SeriesIndex(1), 1, 100);
+        watchdog.check(&charts, 100, &recorder);
+        assert_eq!(recorder.take_alerts(), vec![String::from("Charts source load/down recovered")]);
+        // Both sources stop, the starvation names both without alerting for each.
+        for epoch in 101..200 {
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        let alerts = recorder.take_alerts();
+        assert_eq!(alerts.len(), 1);
+        // The sources are listed in the order of the config.
+        assert!(alerts[0].ends_with("(no data from load/up, load/down)"));
+        // One source is back, the one still dead is reported on its own.
+        watchdog.record(ChartIndex(0), SeriesIndex(0), 1, 200);
+        watchdog.check(&charts, 200, &recorder);
+        assert_eq!(
+            recorder.take_alerts(),
+            vec![
+                String::from("Charts data recovered: 1 items loaded in the last 30s"),
+                String::from("Charts source load/down starved: no items loaded in the last 30s"),
+            ]
+        );
+    }
+
+    #[test]
+    fn it_lists_the_starved_sources_in_config_order() {
+        let charts = prometheus_charts(&["zeta", "beta", "alpha", "live"]);
+        let config = WatchdogConfig { window: 10, min_items: None };
+        let run = |order: &[usize]| {
+            let recorder = MockEventListener::default();
+            let mut watchdog = DataWatchdog::new(config, 0);
+            for epoch in 0..20 {
+                for series_idx in order {
+                    watchdog.record(ChartIndex(0), SeriesIndex(*series_idx), 1, epoch);
+                }
+                watchdog.check(&charts, epoch, &recorder);
+            }
+            // Only the last series keeps loading.
+            for epoch in 20..40 {
+                watchdog.record(ChartIndex(0), SeriesIndex(3), 1, epoch);
+                watchdog.check(&charts, epoch, &recorder);
+            }
+            (watchdog.starved_sources(), recorder.take_alerts())
+        };
+        let (sources, alerts) = run(&[0, 1, 2, 3]);
+        assert_eq!(sources, vec!["load/zeta", "load/beta", "load/alpha"]);
+        assert_eq!(alerts.len(), 3);
+        assert!(alerts[0].contains("load/zeta"));
+        assert_eq!(run(&[3, 2, 1, 0]), (sources, alerts));
+    }
+
+    #[test]
+    fn it_waits_a_window_for_discovered_sources() {
+        let mut charts = prometheus_charts(&["up"]);
+        let config = WatchdogConfig { window: 10, min_items: None };
+        let recorder = MockEventListener::default();
+        let mut watchdog = DataWatchdog::new(config, 0);
+        for epoch in 0..20 {
+            watchdog.record(ChartIndex(0), SeriesIndex(0), 1, epoch);
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        charts[0].sources.extend(prometheus_charts(&["discovered"]).remove(0).sources);
+        for epoch in 20..30 {
+            watchdog.record(ChartIndex(0), SeriesIndex(0), 1, epoch);
+            watchdog.check(&charts, epoch, &recorder);
+        }
+        assert!(recorder.take_alerts().is_empty());
+        watchdog.record(ChartIndex(0), SeriesIndex(0), 1, 30);
+        watchdog.check(&charts, 30, &recorder);
+        assert_eq!(recorder.take_alerts().len(), 1);
+    }
+}
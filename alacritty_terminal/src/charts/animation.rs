@@ -0,0 +1,313 @@
+//! `animation` interpolates the vertices of a series from the previous regeneration to the new
+//! one, so that a new sample does not pop in. The vertices are matched by the epoch of their
+//! sample, the shared epochs move from their old position to the new one and the samples that
+//! only exist in the new vertices are snapped in place. The transitions advance with the
+//! decoration ticks of the coordinator.
+
+use crate::charts::ChartSizeInfo;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// The interval of the decoration ticks of the coordinator, the transitions advance with them
+pub const DECORATION_TICK_MS: u64 = 100u64;
+
+/// The update rate of the decorations and charts that do not configure one, one per decoration
+/// tick
+pub const DEFAULT_UPDATE_HZ: f32 = 1000f32 / DECORATION_TICK_MS as f32;
+
+/// The duration of a transition when the animation does not specify one
+pub const DEFAULT_ANIMATION_DURATION_MS: u64 = 300u64;
+
+/// `Easing` shapes the progress of a transition over its duration
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Easing {
+    #[default]
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+}
+
+impl Easing {
+    /// `apply` returns the eased progress of a linear progress `t` in [0, 1]
+    pub fn apply(&self, t: f32) -> f32 {
+        let t = t.clamp(0f32, 1f32);
+        match self {
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2f32 - t),
+            Easing::EaseInOut => {
+                if t < 0.5f32 {
+                    2f32 * t * t
+                } else {
+                    1f32 - (-2f32 * t + 2f32).powi(2) / 2f32
+                }
+            },
+        }
+    }
+}
+
+/// `AnimationConfig` enables the transitions of the vertices of a chart when they are regenerated
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct AnimationConfig {
+    /// The duration of a transition, defaults to DEFAULT_ANIMATION_DURATION_MS
+    #[serde(default)]
+    pub duration_ms: Option<u64>,
+
+    /// How the progress of a transition is shaped
+    #[serde(default)]
+    pub easing: Easing,
+}
+
+impl AnimationConfig {
+    /// `duration_ms` returns the duration of a transition
+    pub fn duration_ms(&self) -> u64 {
+        self.duration_ms.unwrap_or(DEFAULT_ANIMATION_DURATION_MS)
+    }
+
+    /// `ticks` returns the decoration ticks of `tick_ms` a transition lasts, at least one
+    pub fn ticks(&self, tick_ms: u64) -> u32 {
+        self.duration_ms().div_ceil(tick_ms.max(1)).max(1) as u32
+    }
+}
+
+/// `VertexTransition` is a running transition of the vertices of a series, the vertices are drawn
+/// between `from` and the vertices of the series until `ticks` decoration ticks elapsed.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct VertexTransition {
+    /// The vertices drawn when the transition started, aligned by epoch to the new vertices
+    from: Vec<f32>,
+
+    /// The decoration ticks elapsed since the transition started
+    elapsed: u32,
+
+    /// The decoration ticks the transition lasts
+    ticks: u32,
+
+    /// How the progress is shaped
+    easing: Easing,
+}
+
+impl VertexTransition {
+    /// `new` starts a transition from the aligned vertices `from`
+    pub fn new(from: Vec<f32>, config: &AnimationConfig, tick_ms: u64) -> Self {
+        VertexTransition { from, elapsed: 0, ticks: config.ticks(tick_ms), easing: config.easing }
+    }
+
+    /// `progress` returns the eased progress of the transition in [0, 1]
+    pub fn progress(&self) -> f32 {
+        self.easing.apply(self.elapsed as f32 / self.ticks.max(1) as f32)
+    }
+
+    /// `advance` counts a decoration tick, returns true once the transition is finished
+    pub fn advance(&mut self) -> bool {
+        self.elapsed = self.elapsed.saturating_add(1);
+        self.elapsed >= self.ticks
+    }
+
+    /// `interpolate` returns the vertices drawn towards `target` at the current progress, each
+    /// component is interpolated linearly. Vertices missing from `from` are drawn as the target.
+    pub fn interpolate(&self, target: &[f32]) -> Vec<f32> {
+        let t = self.progress();
+        target
+            .iter()
+            .enumerate()
+            .map(|(idx, to)| match self.from.get(idx) {
+                Some(from) => from + (to - from) * t,
+                None => *to,
+            })
+            .collect()
+    }
+}
+
+/// `SeriesAnimation` is what is needed to animate the next regeneration of a series: the epochs
+/// of its current vertices, the size they were generated for and the running transition.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct SeriesAnimation {
+    /// The epoch of the sample of each x,y vertex
+    pub epochs: Vec<u64>,
+
+    /// The size the vertices were generated for, the transitions are skipped on resize
+    pub size: ChartSizeInfo,
+
+    /// The transition towards the current vertices, if running
+    pub transition: Option<VertexTransition>,
+}
+
+impl SeriesAnimation {
+    /// `drawn` returns the vertices currently drawn for the `current` vertices of the series
+    pub fn drawn(&self, current: &[f32]) -> Vec<f32> {
+        match &self.transition {
+            Some(transition) => transition.interpolate(current),
+            None => current.to_vec(),
+        }
+    }
+}
+
+/// `TickSchedule` is the update cadence of a decoration or a chart. The decoration ticks deliver
+/// the time elapsed since the previous one, the work is skipped until the period of the update
+/// rate elapsed. A rate of 0 Hz never updates.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct TickSchedule {
+    /// The time between updates, None never updates
+    period: Option<Duration>,
+
+    /// The time elapsed since the last update
+    pending: Duration,
+
+    /// The updates since the schedule started
+    updates: u64,
+}
+
+impl TickSchedule {
+    /// `new` returns the schedule of an update rate in Hz
+    pub fn new(update_hz: f32) -> Self {
+        let period = if update_hz.is_finite() && update_hz > 0f32 {
+            Some(Duration::from_secs_f64(1f64 / f64::from(update_hz)))
+        } else {
+            None
+        };
+        TickSchedule { period, ..TickSchedule::default() }
+    }
+
+    /// `period` returns the time between updates, None never updates
+    pub fn period(&self) -> Option<Duration> {
+        self.period
+    }
+
+    /// `updates` returns the updates since the schedule started
+    pub fn updates(&self) -> u64 {
+        self.updates
+    }
+
+    /// `advance` counts the `elapsed` time of a decoration tick, returns true when the period
+    /// elapsed and the work should be done. The periods missed during a stall are not caught up.
+    pub fn advance(&mut self, elapsed: Duration) -> bool {
+        let period = match self.period {
+            Some(period) => period,
+            None => return false,
+        };
+        self.pending += elapsed;
+        if self.pending < period {
+            return false;
+        }
+        self.pending -= period;
+        if self.pending >= period {
+            self.pending = Duration::ZERO;
+        }
+        self.updates += 1;
+        true
+    }
+}
+
+/// `tick_interval` returns the interval of the decoration ticks for the update rates, the ticks
+/// follow the fastest rate so that none is late. Without a positive rate the ticks follow the
+/// DEFAULT_UPDATE_HZ.
+pub fn tick_interval(rates: impl IntoIterator<Item = f32>) -> Duration {
+    let fastest = rates.into_iter().filter(|hz| hz.is_finite()).fold(0f32, f32::max);
+    let update_hz = if fastest > 0f32 { fastest } else { DEFAULT_UPDATE_HZ };
+    Duration::from_secs_f64(1f64 / f64::from(update_hz))
+}
+
+/// `align_by_epoch` returns the `old` x,y vertices rearranged to match the `new_epochs`, so that
+/// each vertex moves from the position of its sample in the old vertices. The samples that are
+/// not in the `old_epochs`, i.e. appended after a rotation of the window, keep their `new` vertex.
+pub fn align_by_epoch(
+    old: &[f32],
+    old_epochs: &[u64],
+    new: &[f32],
+    new_epochs: &[u64],
+) -> Vec<f32> {
+    let mut aligned = new.to_vec();
+    for (idx, epoch) in new_epochs.iter().enumerate() {
+        if (idx + 1) * 2 > aligned.len() {
+            break;
+        }
+        // The epochs of a series are sorted.
+        if let Ok(old_idx) = old_epochs.binary_search(epoch) {
+            if let (Some(x), Some(y)) = (old.get(old_idx * 2), old.get(old_idx * 2 + 1)) {
+                aligned[idx * 2] = *x;
+                aligned[idx * 2 + 1] = *y;
+            }
+        }
+    }
+    aligned
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_interpolates_the_vertices_over_the_ticks() {
+        let config = AnimationConfig { duration_ms: Some(200), easing: Easing::Linear };
+        let mut transition = VertexTransition::new(vec![0f32, 0f32, 1f32, -1f32], &config, 100);
+        let target = [1f32, 0.5f32, 1f32, 1f32];
+        // t=0 draws the previous vertices.
+        assert_eq!(transition.interpolate(&target), vec![0f32, 0f32, 1f32, -1f32]);
+        assert!(!transition.advance());
+        // t=0.5 is halfway through each component.
+        assert_eq!(transition.interpolate(&target), vec![0.5f32, 0.25f32, 1f32, 0f32]);
+        // t=1 settles on the new vertices and the transition is finished.
+        assert!(transition.advance());
+        assert_eq!(transition.interpolate(&target), target.to_vec());
+        // The eased progress keeps its bounds.
+        for easing in [Easing::EaseIn, Easing::EaseOut, Easing::EaseInOut] {
+            assert_eq!(easing.apply(0f32), 0f32);
+            assert_eq!(easing.apply(1f32), 1f32);
+        }
+        assert_eq!(Easing::EaseIn.apply(0.5f32), 0.25f32);
+        assert_eq!(Easing::EaseOut.apply(0.5f32), 0.75f32);
+        assert_eq!(Easing::EaseInOut.apply(0.5f32), 0.5f32);
+        assert_eq!(AnimationConfig::default().ticks(100), 3);
+    }
+
+    #[test]
+    fn it_aligns_a_rotated_window_by_epoch() {
+        // The window rotated by a sample, 10 is dropped and 13 is appended.
+        let old_epochs = [10u64, 11u64, 12u64];
+        let old = [-1f32, 0.1f32, 0f32, 0.2f32, 1f32, 0.3f32];
+        let new_epochs = [11u64, 12u64, 13u64];
+        let new = [-1f32, 0.2f32, 0f32, 0.3f32, 1f32, 0.4f32];
+        let from = align_by_epoch(&old, &old_epochs, &new, &new_epochs);
+        // The shared samples start at their old position, the appended one is snapped.
+        assert_eq!(from, vec![0f32, 0.2f32, 1f32, 0.3f32, 1f32, 0.4f32]);
+        let config = AnimationConfig { duration_ms: Some(100), easing: Easing::Linear };
+        let mut animation = SeriesAnimation {
+            epochs: new_epochs.to_vec(),
+            transition: Some(VertexTransition::new(from, &config, 50)),
+            ..SeriesAnimation::default()
+        };
+        // The shared samples slide left keeping their values instead of morphing into the next.
+        animation.transition.as_mut().unwrap().advance();
+        assert_eq!(animation.drawn(&new), vec![-0.5f32, 0.2f32, 0.5f32, 0.3f32, 1f32, 0.4f32]);
+        animation.transition = None;
+        assert_eq!(animation.drawn(&new), new.to_vec());
+    }
+
+    #[test]
+    fn it_skips_the_ticks_until_the_period_elapsed() {
+        let tick = Duration::from_millis(DECORATION_TICK_MS);
+        let mut schedules =
+            [TickSchedule::new(1f32), TickSchedule::new(10f32), TickSchedule::new(0f32)];
+        // 2 seconds of decoration ticks.
+        for _ in 0..20 {
+            for schedule in schedules.iter_mut() {
+                schedule.advance(tick);
+            }
+        }
+        let updates: Vec<u64> = schedules.iter().map(|schedule| schedule.updates()).collect();
+        assert_eq!(updates, vec![2, 20, 0]);
+        // A stall updates once instead of catching up the missed periods.
+        let mut schedule = TickSchedule::new(10f32);
+        assert!(schedule.advance(Duration::from_secs(1)));
+        assert!(!schedule.advance(Duration::from_millis(50)));
+        assert!(schedule.advance(Duration::from_millis(50)));
+        assert_eq!(schedule.updates(), 2);
+        assert_eq!(TickSchedule::new(f32::NAN).period(), None);
+        // The ticks follow the fastest rate.
+        assert_eq!(tick_interval([1f32, 10f32, 0f32]), tick);
+        assert_eq!(tick_interval([1f32, 0f32]), Duration::from_secs(1));
+        assert_eq!(tick_interval(std::iter::empty()), tick);
+    }
+}
@@ -0,0 +1,204 @@
+//! `color` provides the operations the charts use to derive colors: conversions from integers
+//! and HSL, blending, luminance and the normalized channels of the vertices. The Rgb is defined
+//! by vte, so the operations are provided by the `RgbExt` trait rather than by `From` and
+//! inherent methods.
+
+use crate::vte::ansi::Rgb;
+
+/// The Rec. 709 weights of the linear red, green and blue channels in the relative luminance
+const LUMINANCE_WEIGHTS: [f32; 3] = [0.2126f32, 0.7152f32, 0.0722f32];
+
+/// `RgbExt` extends the Rgb with the color operations of the charts
+pub trait RgbExt: Sized {
+    /// `from_tuple` builds a color from its (r, g, b) channels
+    fn from_tuple(rgb: (u8, u8, u8)) -> Self;
+
+    /// `from_u32` builds a color from its 0xRRGGBB value, the upper byte is ignored
+    fn from_u32(rgb: u32) -> Self;
+
+    /// `from_hsl` builds a color from a hue in degrees and a saturation and lightness in the
+    /// [0, 1] range, the hue wraps around and the saturation and lightness are clamped.
+    fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self;
+
+    /// `to_hsl` returns the (hue, saturation, lightness) of the color, the hue is in degrees in
+    /// the [0, 360) range and is 0 for the grays.
+    fn to_hsl(self) -> (f32, f32, f32);
+
+    /// `lerp` blends the color towards `other`, `t` is clamped to [0, 1] where 0 is the color and
+    /// 1 is `other`.
+    fn lerp(self, other: Self, t: f32) -> Self;
+
+    /// `luminance` returns the relative luminance of the color, 0 for black and 1 for white
+    fn luminance(self) -> f32;
+
+    /// `to_normalized` returns the r, g, b channels in the [0, 1] range used by the vertices
+    fn to_normalized(self) -> [f32; 3];
+
+    /// `with_alpha` returns the normalized r, g, b channels followed by `alpha`
+    fn with_alpha(self, alpha: f32) -> [f32; 4];
+}
+
+/// `to_channel` converts a channel in the [0, 1] range to its byte value
+fn to_channel(value: f32) -> u8 {
+    (value * 255.).round().clamp(0., 255.) as u8
+}
+
+/// `linearize` removes the sRGB gamma of a channel in the [0, 1] range
+fn linearize(value: f32) -> f32 {
+    if value <= 0.04045 {
+        value / 12.92
+    } else {
+        ((value + 0.055) / 1.055).powf(2.4)
+    }
+}
+
+impl RgbExt for Rgb {
+    fn from_tuple((r, g, b): (u8, u8, u8)) -> Self {
+        Rgb { r, g, b }
+    }
+
+    fn from_u32(rgb: u32) -> Self {
+        Rgb { r: (rgb >> 16) as u8, g: (rgb >> 8) as u8, b: rgb as u8 }
+    }
+
+    fn from_hsl(hue: f32, saturation: f32, lightness: f32) -> Self {
+        let saturation = saturation.clamp(0., 1.);
+        let lightness = lightness.clamp(0., 1.);
+        let chroma = (1. - (2. * lightness - 1.).abs()) * saturation;
+        let sector = hue.rem_euclid(360.) / 60.;
+        let x = chroma * (1. - (sector % 2. - 1.).abs());
+        let (r, g, b) = match sector as u32 {
+            0 => (chroma, x, 0.),
+            1 => (x, chroma, 0.),
+            2 => (0., chroma, x),
+            3 => (0., x, chroma),
+            4 => (x, 0., chroma),
+            _ => (chroma, 0., x),
+        };
+        let m = lightness - chroma / 2.;
+        Rgb { r: to_channel(r + m), g: to_channel(g + m), b: to_channel(b + m) }
+    }
+
+    fn to_hsl(self) -> (f32, f32, f32) {
+        let [r, g, b] = self.to_normalized();
+        let max = r.max(g).max(b);
+        let min = r.min(g).min(b);
+        let lightness = (max + min) / 2.;
+        let delta = max - min;
+        if delta == 0. {
+            return (0., 0., lightness);
+        }
+        let saturation = delta / (1. - (2. * lightness - 1.).abs());
+        let hue = if max == r {
+            ((g - b) / delta).rem_euclid(6.)
+        } else if max == g {
+            (b - r) / delta + 2.
+        } else {
+            (r - g) / delta + 4.
+        };
+        (hue * 60., saturation, lightness)
+    }
+
+    fn lerp(self, other: Self, t: f32) -> Self {
+        let t = t.clamp(0., 1.);
+        let blend = |from: u8, to: u8| {
+            to_channel((f32::from(from) + (f32::from(to) - f32::from(from)) * t) / 255.)
+        };
+        Rgb { r: blend(self.r, other.r), g: blend(self.g, other.g), b: blend(self.b, other.b) }
+    }
+
+    fn luminance(self) -> f32 {
+        self.to_normalized()
+            .iter()
+            .zip(LUMINANCE_WEIGHTS.iter())
+            .map(|(channel, weight)| linearize(*channel) * weight)
+            .sum()
+    }
+
+    fn to_normalized(self) -> [f32; 3] {
+        [f32::from(self.r) / 255., f32::from(self.g) / 255., f32::from(self.b) / 255.]
+    }
+
+    fn with_alpha(self, alpha: f32) -> [f32; 4] {
+        let [r, g, b] = self.to_normalized();
+        [r, g, b, alpha]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BLACK: Rgb = Rgb { r: 0, g: 0, b: 0 };
+    const WHITE: Rgb = Rgb { r: 255, g: 255, b: 255 };
+
+    #[test]
+    fn it_builds_colors_from_integers() {
+        assert_eq!(Rgb::from_tuple((1, 2, 3)), Rgb { r: 1, g: 2, b: 3 });
+        assert_eq!(Rgb::from_u32(0x0012_34ab), Rgb { r: 0x12, g: 0x34, b: 0xab });
+        assert_eq!(Rgb::from_u32(0xff00_0000), BLACK);
+        assert_eq!(Rgb::from_u32(0x00ff_ffff), WHITE);
+    }
+
+    #[test]
+    fn it_converts_to_and_from_hsl() {
+        assert_eq!(Rgb::from_u32(0xff0000).to_hsl(), (0., 1., 0.5));
+        assert_eq!(Rgb::from_u32(0x00ff00).to_hsl(), (120., 1., 0.5));
+        assert_eq!(Rgb::from_u32(0x0000ff).to_hsl(), (240., 1., 0.5));
+        assert_eq!(Rgb::from_hsl(60., 1., 0.5), Rgb::from_u32(0xffff00));
+        assert_eq!(Rgb::from_hsl(300., 1., 0.5), Rgb::from_u32(0xff00ff));
+        // The hue wraps around and the saturation and lightness are clamped.
+        assert_eq!(Rgb::from_hsl(-240., 2., 0.5), Rgb::from_u32(0x00ff00));
+        assert_eq!(Rgb::from_hsl(480., 1., 0.5), Rgb::from_u32(0x00ff00));
+        assert_eq!(Rgb::from_hsl(0., 0., 2.), WHITE);
+        // The corners of the color cube and the grays survive the round trip.
+        for corner in 0..8u32 {
+            let channel = |bit: u32| if corner & bit != 0 { 255 } else { 0 };
+            let color = Rgb { r: channel(4), g: channel(2), b: channel(1) };
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_eq!(Rgb::from_hsl(hue, saturation, lightness), color);
+        }
+        for gray in 0..=255u8 {
+            let color = Rgb { r: gray, g: gray, b: gray };
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_eq!((hue, saturation), (0., 0.));
+            assert_eq!(Rgb::from_hsl(hue, saturation, lightness), color);
+        }
+        for rgb in (0..0x0100_0000u32).step_by(0x0f0f0f) {
+            let color = Rgb::from_u32(rgb);
+            let (hue, saturation, lightness) = color.to_hsl();
+            assert_eq!(Rgb::from_hsl(hue, saturation, lightness), color, "{:06x}", rgb);
+        }
+    }
+
+    #[test]
+    fn it_blends_colors() {
+        let red = Rgb::from_u32(0xff0000);
+        let blue = Rgb::from_u32(0x0000ff);
+        assert_eq!(red.lerp(blue, 0.), red);
+        assert_eq!(red.lerp(blue, 1.), blue);
+        assert_eq!(red.lerp(blue, 0.5), Rgb::from_u32(0x800080));
+        assert_eq!(BLACK.lerp(WHITE, 0.25), Rgb::from_u32(0x404040));
+        // t is clamped to the colors.
+        assert_eq!(red.lerp(blue, -1.), red);
+        assert_eq!(red.lerp(blue, 2.), blue);
+    }
+
+    #[test]
+    fn it_calculates_the_luminance() {
+        assert_eq!(BLACK.luminance(), 0.);
+        assert!((WHITE.luminance() - 1.).abs() < 1e-6);
+        assert!((Rgb::from_u32(0xff0000).luminance() - 0.2126).abs() < 1e-6);
+        assert!((Rgb::from_u32(0x00ff00).luminance() - 0.7152).abs() < 1e-6);
+        assert!((Rgb::from_u32(0x0000ff).luminance() - 0.0722).abs() < 1e-6);
+        // The mid gray is darker than half because of the gamma.
+        assert!((Rgb::from_u32(0x808080).luminance() - 0.2158605).abs() < 1e-4);
+    }
+
+    #[test]
+    fn it_normalizes_the_channels() {
+        assert_eq!(WHITE.to_normalized(), [1., 1., 1.]);
+        assert_eq!(Rgb::from_u32(0xff0033).with_alpha(0.5), [1., 0., 0.2, 0.5]);
+        assert_eq!(BLACK.with_alpha(1.), [0., 0., 0., 1.]);
+    }
+}
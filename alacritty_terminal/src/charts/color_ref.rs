@@ -0,0 +1,299 @@
+//! `color_ref` lets the chart colors name a color of the terminal palette, i.e. "blue",
+//! "bright_red" or "foreground", instead of a hex value, so that the charts follow the color
+//! scheme. The names are resolved by the coordinator when the display sends it the palette, at
+//! startup and when the colors are reloaded.
+
+use crate::vte::ansi::{NamedColor, Rgb};
+use serde::de::{self, MapAccess, Visitor};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+
+/// The names of the terminal colors that the charts may reference
+pub const TERMINAL_COLOR_NAMES: [(&str, NamedColor); 29] = [
+    ("black", NamedColor::Black),
+    ("red", NamedColor::Red),
+    ("green", NamedColor::Green),
+    ("yellow", NamedColor::Yellow),
+    ("blue", NamedColor::Blue),
+    ("magenta", NamedColor::Magenta),
+    ("cyan", NamedColor::Cyan),
+    ("white", NamedColor::White),
+    ("bright_black", NamedColor::BrightBlack),
+    ("bright_red", NamedColor::BrightRed),
+    ("bright_green", NamedColor::BrightGreen),
+    ("bright_yellow", NamedColor::BrightYellow),
+    ("bright_blue", NamedColor::BrightBlue),
+    ("bright_magenta", NamedColor::BrightMagenta),
+    ("bright_cyan", NamedColor::BrightCyan),
+    ("bright_white", NamedColor::BrightWhite),
+    ("dim_black", NamedColor::DimBlack),
+    ("dim_red", NamedColor::DimRed),
+    ("dim_green", NamedColor::DimGreen),
+    ("dim_yellow", NamedColor::DimYellow),
+    ("dim_blue", NamedColor::DimBlue),
+    ("dim_magenta", NamedColor::DimMagenta),
+    ("dim_cyan", NamedColor::DimCyan),
+    ("dim_white", NamedColor::DimWhite),
+    ("foreground", NamedColor::Foreground),
+    ("background", NamedColor::Background),
+    ("cursor", NamedColor::Cursor),
+    ("bright_foreground", NamedColor::BrightForeground),
+    ("dim_foreground", NamedColor::DimForeground),
+];
+
+/// The palette color the unknown names fall back to
+pub const FALLBACK_COLOR_NAME: &str = "foreground";
+
+/// `ColorRef` is a color as written in the config: a literal color, as a hex string or as its
+/// r, g and b, or the name of a color of the terminal palette
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ColorRef {
+    Literal(Rgb),
+    Named(String),
+}
+
+impl Default for ColorRef {
+    fn default() -> ColorRef {
+        ColorRef::Literal(Rgb::default())
+    }
+}
+
+impl FromStr for ColorRef {
+    type Err = String;
+
+    /// The strings that start as a hex color must be one, the other strings are names
+    fn from_str(s: &str) -> Result<ColorRef, String> {
+        if s.starts_with('#') || s.starts_with("0x") {
+            return Rgb::from_str(s)
+                .map(ColorRef::Literal)
+                .map_err(|()| format!("Invalid Rgb value: {}", s));
+        }
+        if s.is_empty() || !s.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+            return Err(format!("Invalid color name: {:?}", s));
+        }
+        Ok(ColorRef::Named(s.to_ascii_lowercase()))
+    }
+}
+
+impl Serialize for ColorRef {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            ColorRef::Literal(rgb) => rgb.serialize(ser),
+            ColorRef::Named(name) => ser.serialize_str(name),
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for ColorRef {
+    fn deserialize<D>(de: D) -> Result<ColorRef, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct ColorRefVisitor;
+
+        impl<'de> Visitor<'de> for ColorRefVisitor {
+            type Value = ColorRef;
+
+            fn expecting(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+                f.write_str("a hex color like \"0xff0000\", a terminal color name or {r, g, b}")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<ColorRef, E>
+            where
+                E: de::Error,
+            {
+                ColorRef::from_str(value).map_err(E::custom)
+            }
+
+            fn visit_map<A>(self, map: A) -> Result<ColorRef, A::Error>
+            where
+                A: MapAccess<'de>,
+            {
+                Rgb::deserialize(de::value::MapAccessDeserializer::new(map)).map(ColorRef::Literal)
+            }
+        }
+
+        de.deserialize_any(ColorRefVisitor)
+    }
+}
+
+/// `ChartColor` is a configured color and the Rgb it is drawn with. A literal color is drawn as
+/// is, a named color is drawn as the default Rgb until the palette is received.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChartColor {
+    /// The color as configured
+    reference: ColorRef,
+
+    /// The color resolved against the last palette
+    rgb: Rgb,
+}
+
+impl ChartColor {
+    /// `named` returns an unresolved color referencing a terminal color
+    pub fn named(name: &str) -> ChartColor {
+        ChartColor::from(ColorRef::Named(name.to_ascii_lowercase()))
+    }
+
+    /// `reference` returns the color as configured
+    pub fn reference(&self) -> &ColorRef {
+        &self.reference
+    }
+
+    /// `rgb` returns the color to draw
+    pub fn rgb(&self) -> Rgb {
+        self.rgb
+    }
+
+    /// `resolve` looks up a named color in the palette, an unknown name falls back to the
+    /// foreground and is returned as the error.
+    pub fn resolve(&mut self, palette: &TerminalPalette) -> Result<(), String> {
+        let name = match &self.reference {
+            ColorRef::Literal(rgb) => {
+                self.rgb = *rgb;
+                return Ok(());
+            },
+            ColorRef::Named(name) => name,
+        };
+        match palette.get(name) {
+            Some(rgb) => {
+                self.rgb = rgb;
+                Ok(())
+            },
+            None => {
+                self.rgb = palette.foreground();
+                Err(name.clone())
+            },
+        }
+    }
+}
+
+impl From<Rgb> for ChartColor {
+    fn from(rgb: Rgb) -> ChartColor {
+        ChartColor { reference: ColorRef::Literal(rgb), rgb }
+    }
+}
+
+impl From<ColorRef> for ChartColor {
+    fn from(reference: ColorRef) -> ChartColor {
+        let rgb = match reference {
+            ColorRef::Literal(rgb) => rgb,
+            ColorRef::Named(_) => Rgb::default(),
+        };
+        ChartColor { reference, rgb }
+    }
+}
+
+impl Serialize for ChartColor {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.reference.serialize(ser)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChartColor {
+    fn deserialize<D>(de: D) -> Result<ChartColor, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        ColorRef::deserialize(de).map(ChartColor::from)
+    }
+}
+
+/// `TerminalPalette` is the color of each name of the terminal color scheme
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TerminalPalette {
+    colors: HashMap<String, Rgb>,
+}
+
+impl TerminalPalette {
+    /// `new` keeps the colors by their lowercase name
+    pub fn new(colors: Vec<(String, Rgb)>) -> TerminalPalette {
+        TerminalPalette {
+            colors: colors
+                .into_iter()
+                .map(|(name, rgb)| (name.to_ascii_lowercase(), rgb))
+                .collect(),
+        }
+    }
+
+    /// `get` returns the color of a name, if known
+    pub fn get(&self, name: &str) -> Option<Rgb> {
+        self.colors.get(name).copied()
+    }
+
+    /// `foreground` returns the color the unknown names are drawn with
+    pub fn foreground(&self) -> Rgb {
+        self.get(FALLBACK_COLOR_NAME).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, Deserialize, Serialize)]
+    struct Colored {
+        color: ChartColor,
+    }
+
+    fn sample_palette() -> TerminalPalette {
+        TerminalPalette::new(vec![
+            (String::from("foreground"), Rgb { r: 0xd8, g: 0xd8, b: 0xd8 }),
+            (String::from("blue"), Rgb { r: 0x7c, g: 0xaf, b: 0xc2 }),
+            (String::from("bright_red"), Rgb { r: 0xff, g: 0x55, b: 0x55 }),
+        ])
+    }
+
+    #[test]
+    fn it_deserializes_the_three_color_forms() {
+        let color = |yaml: &str| serde_yaml::from_str::<Colored>(yaml).map(|colored| colored.color);
+        let red = Rgb { r: 0xff, g: 0, b: 0 };
+        assert_eq!(color("color: \"0xff0000\"").unwrap(), ChartColor::from(red));
+        assert_eq!(color("color: \"#ff0000\"").unwrap(), ChartColor::from(red));
+        assert_eq!(color("color: { r: 255, g: 0, b: 0 }").unwrap(), ChartColor::from(red));
+        let named = color("color: Bright_Red").unwrap();
+        assert_eq!(named.reference(), &ColorRef::Named(String::from("bright_red")));
+        assert_eq!(named.rgb(), Rgb::default());
+        assert!(color("color: \"0xff00\"").is_err());
+        assert!(color("color: \"not a name\"").is_err());
+        // The colors are written back as configured.
+        let yaml = serde_yaml::to_string(&Colored { color: ChartColor::named("blue") }).unwrap();
+        assert_eq!(yaml, "color: blue\n");
+    }
+
+    #[test]
+    fn it_resolves_named_colors_against_the_palette() {
+        let palette = sample_palette();
+        let mut blue = ChartColor::named("blue");
+        assert_eq!(blue.resolve(&palette), Ok(()));
+        assert_eq!(blue.rgb(), Rgb { r: 0x7c, g: 0xaf, b: 0xc2 });
+        let literal = Rgb { r: 1, g: 2, b: 3 };
+        let mut color = ChartColor::from(literal);
+        assert_eq!(color.resolve(&palette), Ok(()));
+        assert_eq!(color.rgb(), literal);
+        // A new color scheme changes the named colors only.
+        let scheme = TerminalPalette::new(vec![(String::from("blue"), Rgb { r: 0, g: 0, b: 255 })]);
+        blue.resolve(&scheme).unwrap();
+        color.resolve(&scheme).unwrap();
+        assert_eq!(blue.rgb(), Rgb { r: 0, g: 0, b: 255 });
+        assert_eq!(color.rgb(), literal);
+    }
+
+    #[test]
+    fn it_falls_back_to_the_foreground_for_unknown_names() {
+        let palette = sample_palette();
+        let mut color = ChartColor::named("chartreuse");
+        assert_eq!(color.resolve(&palette), Err(String::from("chartreuse")));
+        assert_eq!(color.rgb(), Rgb { r: 0xd8, g: 0xd8, b: 0xd8 });
+        // Without a foreground the default color is drawn.
+        assert!(color.resolve(&TerminalPalette::default()).is_err());
+        assert_eq!(color.rgb(), Rgb::default());
+    }
+}
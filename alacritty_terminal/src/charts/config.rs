@@ -1,7 +1,8 @@
 //! Reading configuration from a yaml file
-use crate::charts::ChartsConfig;
+use crate::charts::{migrate, ChartsConfig};
 use log::*;
-use serde::Deserialize;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
 use std::fs::File;
 use std::io::Read;
 use std::path::{Path, PathBuf};
@@ -11,8 +12,18 @@ static DEFAULT_CHART_CONFIG: &str =
 /// Top-level config type
 #[derive(Debug, PartialEq, Deserialize, Clone)]
 pub struct Config {
+    #[serde(default, deserialize_with = "deserialize_migrated_charts")]
     pub charts: Option<ChartsConfig>,
 }
+
+/// `deserialize_migrated_charts` reads the charts of any supported config version
+fn deserialize_migrated_charts<'de, D>(de: D) -> Result<Option<ChartsConfig>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Option::<serde_yaml::Value>::deserialize(de)?;
+    value.map(migrate::deserialize_migrated).transpose().map_err(D::Error::custom)
+}
 impl Default for Config {
     fn default() -> Self {
         serde_yaml::from_str(DEFAULT_CHART_CONFIG).expect("default config is invalid")
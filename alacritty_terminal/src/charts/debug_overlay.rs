@@ -0,0 +1,148 @@
+//! `debug_overlay` lays out the state of the charts as lines of text drawn over the terminal, the
+//! lines are described by the async_coordinator and placed in a column that starts at one of the
+//! corners of the terminal. Long lines are wrapped to the terminal width and the last line tells
+//! how many lines did not fit.
+
+use crate::index::{Column, Point};
+use crate::vte::ansi::Rgb;
+use serde::{Deserialize, Serialize};
+
+/// The color of the debug overlay lines
+pub const OVERLAY_FG: Rgb = Rgb { r: 0xd0, g: 0xd0, b: 0xd0 };
+
+/// The color of the debug overlay lines that describe a failure, i.e. a failed fetch
+pub const OVERLAY_ERROR_FG: Rgb = Rgb { r: 0xff, g: 0x55, b: 0x55 };
+
+/// `OverlayCorner` is the corner of the terminal at which the debug overlay starts
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum OverlayCorner {
+    #[default]
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// `OverlayEntry` is a line of the debug overlay before it is laid out
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayEntry {
+    pub text: String,
+    pub fg: Rgb,
+}
+
+impl OverlayEntry {
+    pub fn new(text: String, fg: Rgb) -> Self {
+        OverlayEntry { text, fg }
+    }
+}
+
+/// `OverlayLine` is a line of the debug overlay at its cell in the viewport
+#[derive(Debug, Clone, PartialEq)]
+pub struct OverlayLine {
+    pub point: Point<usize>,
+    pub text: String,
+    pub fg: Rgb,
+}
+
+/// `layout` places the entries in a column starting at `corner` of a terminal of `columns` by
+/// `lines` cells. Entries longer than the terminal width are wrapped, when the rows do not fit
+/// the last visible row tells how many were left out.
+pub fn layout(
+    entries: &[OverlayEntry],
+    corner: OverlayCorner,
+    columns: usize,
+    lines: usize,
+) -> Vec<OverlayLine> {
+    if columns == 0 || lines == 0 {
+        return vec![];
+    }
+    let mut rows: Vec<(String, Rgb)> = vec![];
+    for entry in entries {
+        let chars: Vec<char> = entry.text.chars().collect();
+        if chars.is_empty() {
+            rows.push((String::new(), entry.fg));
+        }
+        for chunk in chars.chunks(columns) {
+            rows.push((chunk.iter().collect(), entry.fg));
+        }
+    }
+    if rows.len() > lines {
+        let hidden = rows.len() - (lines - 1);
+        rows.truncate(lines - 1);
+        let more: String = format!("... {} more lines", hidden).chars().take(columns).collect();
+        rows.push((more, OVERLAY_FG));
+    }
+    let first_line = match corner {
+        OverlayCorner::TopLeft | OverlayCorner::TopRight => 0,
+        OverlayCorner::BottomLeft | OverlayCorner::BottomRight => lines - rows.len(),
+    };
+    rows.into_iter()
+        .enumerate()
+        .map(|(idx, (text, fg))| {
+            let column = match corner {
+                OverlayCorner::TopLeft | OverlayCorner::BottomLeft => 0,
+                OverlayCorner::TopRight | OverlayCorner::BottomRight => {
+                    columns - text.chars().count()
+                },
+            };
+            OverlayLine { point: Point::new(first_line + idx, Column(column)), text, fg }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entries(texts: &[&str]) -> Vec<OverlayEntry> {
+        texts.iter().map(|text| OverlayEntry::new(text.to_string(), OVERLAY_FG)).collect()
+    }
+
+    #[test]
+    fn it_lays_out_the_overlay_from_each_corner() {
+        let entries = entries(&["cpu", "memory"]);
+        let top_left = layout(&entries, OverlayCorner::TopLeft, 10, 5);
+        assert_eq!(top_left[0].point, Point::new(0, Column(0)));
+        assert_eq!(top_left[1].point, Point::new(1, Column(0)));
+        assert_eq!(top_left[1].text, "memory");
+        let top_right = layout(&entries, OverlayCorner::TopRight, 10, 5);
+        assert_eq!(top_right[0].point, Point::new(0, Column(7)));
+        assert_eq!(top_right[1].point, Point::new(1, Column(4)));
+        let bottom_left = layout(&entries, OverlayCorner::BottomLeft, 10, 5);
+        assert_eq!(bottom_left[0].point, Point::new(3, Column(0)));
+        assert_eq!(bottom_left[1].point, Point::new(4, Column(0)));
+        let bottom_right = layout(&entries, OverlayCorner::BottomRight, 10, 5);
+        assert_eq!(bottom_right[1].point, Point::new(4, Column(4)));
+        assert!(layout(&entries, OverlayCorner::TopLeft, 0, 5).is_empty());
+    }
+
+    #[test]
+    fn it_wraps_long_lines_to_the_terminal_width() {
+        let mut entries = entries(&["abcdefghij", ""]);
+        entries[0].fg = OVERLAY_ERROR_FG;
+        let lines = layout(&entries, OverlayCorner::TopLeft, 4, 10);
+        let texts: Vec<&str> = lines.iter().map(|line| line.text.as_str()).collect();
+        assert_eq!(texts, vec!["abcd", "efgh", "ij", ""]);
+        // The wrapped rows keep the color of their entry.
+        assert!(lines[..3].iter().all(|line| line.fg == OVERLAY_ERROR_FG));
+        assert_eq!(lines[3].point, Point::new(3, Column(0)));
+        // Right aligned rows are aligned by their own width.
+        let lines = layout(&entries, OverlayCorner::TopRight, 4, 10);
+        assert_eq!(lines[2].point, Point::new(2, Column(2)));
+    }
+
+    #[test]
+    fn it_truncates_the_rows_that_do_not_fit() {
+        let texts: Vec<String> = (0..10).map(|idx| format!("chart {}", idx)).collect();
+        let texts: Vec<&str> = texts.iter().map(String::as_str).collect();
+        let lines = layout(&entries(&texts), OverlayCorner::BottomLeft, 40, 4);
+        assert_eq!(lines.len(), 4);
+        assert_eq!(lines[2].text, "chart 2");
+        assert_eq!(lines[3].text, "... 7 more lines");
+        assert_eq!(lines[0].point, Point::new(0, Column(0)));
+        // The notice itself is cut to the terminal width.
+        let lines = layout(&entries(&texts), OverlayCorner::TopLeft, 8, 2);
+        assert_eq!(lines[0].text, "chart 0");
+        assert_eq!(lines[1].text, "... 9 mo");
+    }
+}
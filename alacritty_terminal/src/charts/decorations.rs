@@ -13,6 +13,10 @@
 //    threshold: 0
 //    comparator: '>'
 //    color: "0xff0000"
+//  - type: histogram            # The distribution of the values on the right edge
+//    buckets: 8
+//    width: 20
+//    color: "0x00ff00"
 //  series:
 //  - name: prometheus alerts  # this series matches
 //    type: prometheus
@@ -25,8 +29,10 @@
 //
 // TODO: There are several RFCs in rust to allow enum variants to impl a specific Trait but they
 // haven't been merged
-use super::super::charts::deserialize_rgb_from_str;
+use crate::charts::color_ref::{ChartColor, TerminalPalette};
+use crate::charts::draw_batch::DrawMode;
 use crate::charts::{ChartSizeInfo, TimeSeriesSource, TimeSeriesStats, Value2D};
+use crate::decorations::{VisibilityGroup, DEFAULT_CHART_DECORATION_VISIBILITY};
 use serde::{Deserialize, Serialize};
 use tracing::{event, span, Level};
 use vte::ansi::Rgb;
@@ -39,6 +45,8 @@ pub enum Decoration {
     Reference(ReferencePointDecoration),
     #[serde(rename = "alert")]
     Alert(ActiveAlertUnderLineDecoration),
+    #[serde(rename = "histogram")]
+    Histogram(HistogramDecoration),
     #[default]
     None,
     /* Maybe add Average, threshold coloring (turn line red after a certain
@@ -51,6 +59,7 @@ impl Decoration {
         match self {
             Decoration::Reference(ref mut d) => d.init(display_size),
             Decoration::Alert(ref mut d) => d.init(display_size),
+            Decoration::Histogram(ref mut d) => d.init(display_size),
             Decoration::None => (),
         };
     }
@@ -69,15 +78,50 @@ impl Decoration {
             Decoration::Alert(ref mut d) => {
                 d.update_opengl_vecs(display_size, offset, stats, sources)
             },
+            Decoration::Histogram(ref mut d) => {
+                d.update_opengl_vecs(display_size, offset, stats, sources)
+            },
             Decoration::None => (),
         };
     }
 
+    /// `kind` returns the name of the type of the decoration as it is configured
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Decoration::Reference(_) => "reference",
+            Decoration::Alert(_) => "alert",
+            Decoration::Histogram(_) => "histogram",
+            Decoration::None => "none",
+        }
+    }
+
+    /// `draw_mode` returns how the vertices of the decoration are drawn, the histogram buckets
+    /// are filled quads, the other decorations are lines
+    pub fn draw_mode(&self) -> DrawMode {
+        match self {
+            Decoration::Histogram(_) => DrawMode::Triangles,
+            _ => DrawMode::LineStrip,
+        }
+    }
+
+    /// `visibility_group` returns the toggle the decoration is shown with, the decorations are
+    /// only drawn with their chart so `Always` is the same as `WithCharts`
+    pub fn visibility_group(&self) -> VisibilityGroup {
+        let group = match self {
+            Decoration::Reference(d) => d.visibility_group,
+            Decoration::Alert(d) => d.visibility_group,
+            Decoration::Histogram(d) => d.visibility_group,
+            Decoration::None => None,
+        };
+        group.unwrap_or(DEFAULT_CHART_DECORATION_VISIBILITY)
+    }
+
     /// Calls the internal methods to get the width
     pub fn width(&self) -> f32 {
         match self {
             Decoration::Reference(d) => d.width(),
             Decoration::Alert(d) => d.width(),
+            Decoration::Histogram(d) => d.width(),
             Decoration::None => Decoration::default_width(),
         }
     }
@@ -87,6 +131,7 @@ impl Decoration {
         match self {
             Decoration::Reference(d) => d.opengl_vertices(),
             Decoration::Alert(d) => d.opengl_vertices(),
+            Decoration::Histogram(d) => d.opengl_vertices(),
             Decoration::None => Decoration::default_opengl_vertices(),
         }
     }
@@ -94,17 +139,30 @@ impl Decoration {
     /// Calls the internal methods to get the color
     pub fn color(&self) -> Rgb {
         match self {
-            Decoration::Reference(d) => d.color,
-            Decoration::Alert(d) => d.color,
+            Decoration::Reference(d) => d.color.rgb(),
+            Decoration::Alert(d) => d.color.rgb(),
+            Decoration::Histogram(d) => d.color.rgb(),
             Decoration::None => Decoration::default_color(),
         }
     }
 
+    /// `resolve_color` resolves a named color against the palette, the unknown name is returned
+    /// as the error
+    pub fn resolve_color(&mut self, palette: &TerminalPalette) -> Result<(), String> {
+        match self {
+            Decoration::Reference(d) => d.color.resolve(palette),
+            Decoration::Alert(d) => d.color.resolve(palette),
+            Decoration::Histogram(d) => d.color.resolve(palette),
+            Decoration::None => Ok(()),
+        }
+    }
+
     /// Calls the internal methods to get the alpha
     pub fn alpha(&self) -> f32 {
         match self {
             Decoration::Reference(d) => d.alpha,
             Decoration::Alert(d) => d.alpha,
+            Decoration::Histogram(d) => d.alpha,
             Decoration::None => Decoration::default_alpha(),
         }
     }
@@ -114,6 +172,7 @@ impl Decoration {
         match self {
             Decoration::Reference(d) => d.bottom_value(),
             Decoration::Alert(d) => d.bottom_value(),
+            Decoration::Histogram(d) => d.bottom_value(),
             Decoration::None => Decoration::default_bottom_value(),
         }
     }
@@ -123,6 +182,7 @@ impl Decoration {
         match self {
             Decoration::Reference(d) => d.top_value(),
             Decoration::Alert(d) => d.top_value(),
+            Decoration::Histogram(d) => d.top_value(),
             Decoration::None => Decoration::default_top_value(),
         }
     }
@@ -226,9 +286,9 @@ pub struct ReferencePointDecoration {
     #[serde(default)]
     pub height_multiplier: f64,
 
-    /// RGB color
-    #[serde(deserialize_with = "deserialize_rgb_from_str", default)]
-    pub color: Rgb,
+    /// RGB color, a literal color or the name of a terminal color
+    #[serde(default)]
+    pub color: ChartColor,
 
     /// Transparency
     #[serde(default)]
@@ -241,6 +301,10 @@ pub struct ReferencePointDecoration {
     /// The opengl vertices is stored in this vector
     #[serde(default)]
     pub opengl_data: Vec<f32>,
+
+    /// The toggles that show the decoration, WithCharts when unset
+    #[serde(default)]
+    pub visibility_group: Option<VisibilityGroup>,
 }
 
 impl Default for ReferencePointDecoration {
@@ -248,13 +312,14 @@ impl Default for ReferencePointDecoration {
         ReferencePointDecoration {
             value: 1.0,
             height_multiplier: 0.05,
-            color: Rgb::default(),
+            color: ChartColor::default(),
             alpha: 0.5,
             padding: Value2D {
                 x: 1f32,
                 y: 0f32, // No top/bottom padding
             },
             opengl_data: vec![],
+            visibility_group: None,
         }
     }
 }
@@ -299,9 +364,9 @@ impl Decorate for ReferencePointDecoration {
 
         // Calculate Y, the marker hints are 10% of the current values
         // This means that the
-        let y1 = display_size.scale_y(stats.max, self.value);
-        let y2 = display_size.scale_y(stats.max, self.top_value());
-        let y3 = display_size.scale_y(stats.max, self.bottom_value());
+        let y1 = display_size.value_to_ndc_y(self.value, stats);
+        let y2 = display_size.value_to_ndc_y(self.top_value(), stats);
+        let y3 = display_size.value_to_ndc_y(self.bottom_value(), stats);
 
         // Build the left most axis "tick" mark.
         self.opengl_data[0] = x1;
@@ -327,15 +392,16 @@ impl Decorate for ReferencePointDecoration {
     }
 
     /// `bottom_value` decrements the reference point value by a percentage
-    /// to account for space to draw the axis tick
+    /// to account for space to draw the axis tick, negative values are
+    /// decremented by the same magnitude
     fn bottom_value(&self) -> f64 {
-        self.value - self.value * self.height_multiplier
+        self.value - self.value.abs() * self.height_multiplier
     }
     /// `top_value` is the Y value of the decoration, it needs to be
     /// in the range of the metrics that have been collected, thus f64
     /// this is the highest point the Decoration will use
     fn top_value(&self) -> f64 {
-        self.value + self.value * self.height_multiplier
+        self.value + self.value.abs() * self.height_multiplier
     }
 }
 
@@ -354,6 +420,21 @@ pub enum AlertComparator {
     Equal,
 }
 
+impl AlertComparator {
+    /// `is_triggered` compares a value with the threshold of an alert
+    pub fn is_triggered(&self, value: f64, threshold: f64) -> bool {
+        // Accept less than this much difference as equality
+        let minimum_difference_equality = 0.00001f64;
+        match self {
+            AlertComparator::Equal => (value - threshold).abs() < minimum_difference_equality,
+            AlertComparator::LessThan => value < threshold,
+            AlertComparator::LessThanOrEqual => value <= threshold,
+            AlertComparator::GreaterThan => value > threshold,
+            AlertComparator::GreaterThanOrEqual => value >= threshold,
+        }
+    }
+}
+
 /// `ActiveAlertUnderLineDecoration` draws red triangles alert indicators
 /// below a portion of the screen to denote alert below an alarm is on
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -370,8 +451,8 @@ pub struct ActiveAlertUnderLineDecoration {
 
     /// A target TimeSeries name that we will compare with
     /// Must be in the current chart item
-    #[serde(deserialize_with = "deserialize_rgb_from_str", default)]
-    pub color: Rgb,
+    #[serde(default)]
+    pub color: ChartColor,
 
     /// Transparency
     #[serde(default)]
@@ -385,6 +466,10 @@ pub struct ActiveAlertUnderLineDecoration {
     /// The capacity is static, one triangle on the left and one on the right
     #[serde(default)]
     pub opengl_data: Vec<f32>,
+
+    /// The toggles that show the decoration, WithCharts when unset
+    #[serde(default)]
+    pub visibility_group: Option<VisibilityGroup>,
 }
 
 const ACTIVE_ALERT_UNDER_LINE_DECORATION_VEC_CAPACITY: usize = 12;
@@ -395,13 +480,14 @@ impl Default for ActiveAlertUnderLineDecoration {
             threshold: 1f64, // the value to compare with
             comparator: AlertComparator::default(),
             target: String::from(""),
-            color: Rgb::default(),
+            color: ChartColor::default(),
             alpha: 0.5,
             padding: Value2D {
                 x: 1f32,
                 y: 1f32, // XXX: figure out how to reserve space vertically
             },
             opengl_data: vec![],
+            visibility_group: None,
         }
     }
 }
@@ -447,9 +533,9 @@ impl Decorate for ActiveAlertUnderLineDecoration {
 
         // Calculate Y, the marker hints are by default 10% of the chart height
         // Same as the chart_width to have the same amount of pixels.
-        let y1 =
-            display_size.scale_y(stats.max, stats.min + ((stats.max - stats.min) / 10f64) * 2f64);
-        let y2 = display_size.scale_y(stats.max, stats.min + ((stats.max - stats.min) / 10f64));
+        let y1 = display_size
+            .value_to_ndc_y(stats.min + ((stats.max - stats.min) / 10f64) * 2f64, stats);
+        let y2 = display_size.value_to_ndc_y(stats.min + ((stats.max - stats.min) / 10f64), stats);
 
         // TODO: Fix this part in a for loop overwriting the allocated vector
         // Build the left most triangle
@@ -488,38 +574,165 @@ impl ActiveAlertUnderLineDecoration {
         for series in sources {
             if series.name() == self.target {
                 event!(Level::DEBUG, "Matching target series: {}", series.name());
-                let minimum_difference_equality = 0.00001f64; // Accept less than this much difference as equality
-                match self.comparator {
-                    AlertComparator::Equal => {
-                        if (series.series().stats.last - self.threshold).abs()
-                            < minimum_difference_equality
-                        {
-                            return true;
-                        }
-                    },
-                    AlertComparator::LessThan => {
-                        if series.series().stats.last < self.threshold {
-                            return true;
-                        }
-                    },
-                    AlertComparator::LessThanOrEqual => {
-                        if series.series().stats.last <= self.threshold {
-                            return true;
-                        }
-                    },
-                    AlertComparator::GreaterThan => {
-                        if series.series().stats.last > self.threshold {
-                            return true;
-                        }
-                    },
-                    AlertComparator::GreaterThanOrEqual => {
-                        if series.series().stats.last >= self.threshold {
-                            return true;
-                        }
-                    },
+                if self.comparator.is_triggered(series.series().stats.last, self.threshold) {
+                    return true;
                 }
             }
         }
         false
     }
 }
+
+/// The number of buckets of a histogram when unset
+pub const DEFAULT_HISTOGRAM_BUCKETS: usize = 8;
+
+/// The width in pixels of the strip of a histogram when unset
+pub const DEFAULT_HISTOGRAM_WIDTH: f32 = 20.;
+
+/// The height in pixels of the single bucket of a histogram whose values are all equal
+const HISTOGRAM_DEGENERATE_HEIGHT: f32 = 2.;
+
+/// `HistogramDecoration` draws the distribution of the values of the chart as a strip on its
+/// right edge, one bar per bucket. The bars are aligned with the Y scale of the chart so that a
+/// bar is drawn beside the values it counts, its length is the count of the bucket.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct HistogramDecoration {
+    /// The number of buckets between the min and max of the chart
+    #[serde(default)]
+    pub buckets: Option<usize>,
+
+    /// The width of the strip in pixels
+    #[serde(default)]
+    pub width: Option<f32>,
+
+    /// RGB color, a literal color or the name of a terminal color
+    #[serde(default)]
+    pub color: ChartColor,
+
+    /// Transparency
+    #[serde(default)]
+    pub alpha: f32,
+
+    /// The opengl vertices is stored in this vector, two triangles per non-empty bucket
+    #[serde(default)]
+    pub opengl_data: Vec<f32>,
+
+    /// The toggles that show the decoration, WithCharts when unset
+    #[serde(default)]
+    pub visibility_group: Option<VisibilityGroup>,
+}
+
+impl Default for HistogramDecoration {
+    fn default() -> HistogramDecoration {
+        HistogramDecoration {
+            buckets: None,
+            width: None,
+            color: ChartColor::default(),
+            alpha: 0.5,
+            opengl_data: vec![],
+            visibility_group: None,
+        }
+    }
+}
+
+impl Decorate for HistogramDecoration {
+    /// `width` reserves the strip on both sides as the decorations space is split between the
+    /// left and the right of the chart
+    fn width(&self) -> f32 {
+        self.width.unwrap_or(DEFAULT_HISTOGRAM_WIDTH).max(0.) * 2.
+    }
+
+    fn opengl_vertices(&self) -> Vec<f32> {
+        self.opengl_data.clone()
+    }
+
+    /// `update_opengl_vecs` counts the values of the sources in buckets between the min and max
+    /// of the chart and draws a quad per non-empty bucket, the longest for the fullest bucket.
+    fn update_opengl_vecs(
+        &mut self,
+        display_size: ChartSizeInfo,
+        offset: Value2D,
+        stats: &TimeSeriesStats,
+        sources: &[TimeSeriesSource],
+    ) {
+        let span = span!(Level::TRACE, "HistogramDecoration::update_opengl_vecs");
+        let _enter = span.enter();
+        self.opengl_data.clear();
+        let counts = self.bucket_counts(stats, sources);
+        let peak = match counts.iter().max() {
+            Some(peak) if *peak > 0 => *peak,
+            _ => return,
+        };
+        let dpr = display_size.term_size.scale_factor();
+        let width = self.width.unwrap_or(DEFAULT_HISTOGRAM_WIDTH).max(0.) * dpr;
+        let right = offset.x + display_size.chart_width;
+        let left = right - width;
+        let bucket_range = (stats.max - stats.min) / counts.len() as f64;
+        for (bucket, count) in counts.iter().enumerate() {
+            if *count == 0 {
+                continue;
+            }
+            let (y1, y2) = if bucket_range > 0. {
+                let low = stats.min + bucket_range * bucket as f64;
+                let high = stats.min + bucket_range * (bucket + 1) as f64;
+                (display_size.value_to_ndc_y(low, stats), display_size.value_to_ndc_y(high, stats))
+            } else {
+                // All the values are equal, the bucket is a thin bar at their value.
+                let y = display_size.value_to_ndc_y(stats.min, stats);
+                let half_height = HISTOGRAM_DEGENERATE_HEIGHT * dpr / display_size.term_size.height;
+                (y - half_height, y + half_height)
+            };
+            let x1 = display_size.scale_x(left);
+            let x2 = display_size.scale_x(left + width * (*count as f32 / peak as f32));
+            self.opengl_data.extend_from_slice(&[x1, y1, x2, y1, x2, y2, x1, y1, x2, y2, x1, y2]);
+        }
+        event!(
+            Level::DEBUG,
+            "HistogramDecoration:update_opengl_vecs: Finished: counts: {:?} vecs {:?}",
+            counts,
+            self.opengl_data
+        );
+    }
+
+    /// `bottom_value` does not extend the chart, the histogram follows its values
+    fn bottom_value(&self) -> f64 {
+        f64::MAX
+    }
+
+    /// `top_value` does not extend the chart, the histogram follows its values
+    fn top_value(&self) -> f64 {
+        f64::MIN
+    }
+}
+
+impl HistogramDecoration {
+    /// `bucket_counts` counts the filled values of the sources between the min and max of the
+    /// chart, the values all fall in a single bucket when they are equal
+    pub fn bucket_counts(
+        &self,
+        stats: &TimeSeriesStats,
+        sources: &[TimeSeriesSource],
+    ) -> Vec<usize> {
+        if stats.max < stats.min {
+            return vec![];
+        }
+        let range = stats.max - stats.min;
+        let buckets =
+            if range > 0. { self.buckets.unwrap_or(DEFAULT_HISTOGRAM_BUCKETS).max(1) } else { 1 };
+        let mut counts = vec![0usize; buckets];
+        for source in sources {
+            for value in source.series_ref().iter().filter_map(|(_, value)| *value) {
+                if !(stats.min..=stats.max).contains(&value) {
+                    continue;
+                }
+                let bucket = if range > 0. {
+                    (((value - stats.min) / range * buckets as f64) as usize).min(buckets - 1)
+                } else {
+                    0
+                };
+                counts[bucket] += 1;
+            }
+        }
+        counts
+    }
+}
@@ -0,0 +1,340 @@
+//! `draw_batch` lays out the vertices of all the charts of a frame in a single interleaved
+//! buffer, the renderer uploads the buffer once and draws each item as a sub-range of it.
+//! Each vertex is made of [2(x,y) + 4(r,g,b,a)] floats, as expected by the charts shader.
+
+use crate::charts::color::RgbExt;
+use crate::charts::{ChartIndex, PixelRect};
+use crate::vte::ansi::Rgb;
+use std::ops::Range;
+
+/// The floats of each vertex in the buffer: x, y, r, g, b, a
+pub const FLOATS_PER_VERTEX: usize = 6usize;
+
+/// `DrawMode` is the primitive used to draw the vertices of an item
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DrawMode {
+    Points,
+    LineStrip,
+    LineLoop,
+    /// Each pair of vertices is a separate line, i.e. the dashes of a series
+    Lines,
+    Triangles,
+    /// Each vertex after the first two makes a triangle with the previous two, i.e. the smooth
+    /// lines of a series
+    TriangleStrip,
+}
+
+impl DrawMode {
+    /// `min_vertices` returns the number of vertices needed to draw anything in this mode
+    pub fn min_vertices(&self) -> usize {
+        match self {
+            DrawMode::Points => 1,
+            DrawMode::LineStrip | DrawMode::LineLoop | DrawMode::Lines => 2,
+            DrawMode::Triangles | DrawMode::TriangleStrip => 3,
+        }
+    }
+}
+
+/// `DrawLayer` orders the items of a chart, the decorations are drawn under the series
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum DrawLayer {
+    Decoration,
+    Series,
+}
+
+/// `DrawItem` is the sub-range of the buffer with the vertices of a series or a decoration
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DrawItem {
+    /// The chart the item belongs to, charts are drawn in order
+    pub chart: ChartIndex,
+
+    /// The layer of the item inside of its chart
+    pub layer: DrawLayer,
+
+    /// The primitive used to draw the vertices
+    pub mode: DrawMode,
+
+    /// The chart area the item is restricted to, None if it may overflow
+    pub scissor: Option<PixelRect>,
+
+    /// The index of the first vertex of the item in the buffer
+    pub first: usize,
+
+    /// The number of vertices of the item
+    pub count: usize,
+}
+
+/// `DrawGroup` is a run of consecutive items that share their mode and scissor, the renderer
+/// changes its state once per group.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DrawGroup {
+    /// The primitive used to draw the items
+    pub mode: DrawMode,
+
+    /// The chart area the items are restricted to
+    pub scissor: Option<PixelRect>,
+
+    /// The range of the items in ChartDrawBatch::items
+    pub items: Range<usize>,
+}
+
+/// `ChartDrawBatch` contains the vertices of a frame and the items to draw from them. The
+/// generation changes every time the batch is rebuilt, the renderer only uploads the vertices
+/// of a generation once.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct ChartDrawBatch {
+    /// The interleaved vertices of all the items
+    vertices: Vec<f32>,
+
+    /// The items in draw order
+    items: Vec<DrawItem>,
+
+    /// Incremented when the batch is cleared to be rebuilt
+    generation: u64,
+}
+
+impl ChartDrawBatch {
+    /// `clear` drops the items so that the batch can be rebuilt, the allocations are kept
+    pub fn clear(&mut self) {
+        self.vertices.clear();
+        self.items.clear();
+        self.generation = self.generation.wrapping_add(1);
+    }
+
+    /// `push` appends the x,y `positions` of an item with its color to the buffer, the item is
+    /// placed in draw order: by chart, then by layer, then in the order they are pushed. Items
+    /// without enough vertices for their mode are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push(
+        &mut self,
+        chart: ChartIndex,
+        layer: DrawLayer,
+        mode: DrawMode,
+        scissor: Option<PixelRect>,
+        positions: &[f32],
+        color: Rgb,
+        alpha: f32,
+    ) -> Option<DrawItem> {
+        let count = positions.len() / 2;
+        if count < mode.min_vertices() {
+            return None;
+        }
+        let first = self.vertices.len() / FLOATS_PER_VERTEX;
+        let [r, g, b, a] = color.with_alpha(alpha);
+        self.vertices.reserve(count * FLOATS_PER_VERTEX);
+        for position in positions.chunks_exact(2) {
+            self.vertices.extend_from_slice(&[position[0], position[1], r, g, b, a]);
+        }
+        let item = DrawItem { chart, layer, mode, scissor, first, count };
+        let idx = self.items.partition_point(|other| (other.chart, other.layer) <= (chart, layer));
+        self.items.insert(idx, item);
+        Some(item)
+    }
+
+    /// `push_faded` appends the x,y,fade `vertices` of an item with its color to the buffer, the
+    /// alpha of each vertex is `alpha` scaled by its fade. The item is placed in draw order like
+    /// the items of `push`.
+    #[allow(clippy::too_many_arguments)]
+    pub fn push_faded(
+        &mut self,
+        chart: ChartIndex,
+        layer: DrawLayer,
+        mode: DrawMode,
+        scissor: Option<PixelRect>,
+        vertices: &[f32],
+        color: Rgb,
+        alpha: f32,
+    ) -> Option<DrawItem> {
+        let count = vertices.len() / 3;
+        if count < mode.min_vertices() {
+            return None;
+        }
+        let first = self.vertices.len() / FLOATS_PER_VERTEX;
+        let [r, g, b] = color.to_normalized();
+        self.vertices.reserve(count * FLOATS_PER_VERTEX);
+        for vertex in vertices.chunks_exact(3) {
+            self.vertices.extend_from_slice(&[vertex[0], vertex[1], r, g, b, alpha * vertex[2]]);
+        }
+        let item = DrawItem { chart, layer, mode, scissor, first, count };
+        let idx = self.items.partition_point(|other| (other.chart, other.layer) <= (chart, layer));
+        self.items.insert(idx, item);
+        Some(item)
+    }
+
+    /// `push_colored` appends the x,y,r,g,b,a `vertices` of an item that carry their own color,
+    /// i.e. the runs of a timeline band, the alpha of each vertex is scaled by `alpha`. The item
+    /// is placed in draw order like the items of `push`.
+    pub fn push_colored(
+        &mut self,
+        chart: ChartIndex,
+        layer: DrawLayer,
+        mode: DrawMode,
+        scissor: Option<PixelRect>,
+        vertices: &[f32],
+        alpha: f32,
+    ) -> Option<DrawItem> {
+        let count = vertices.len() / FLOATS_PER_VERTEX;
+        if count < mode.min_vertices() {
+            return None;
+        }
+        let first = self.vertices.len() / FLOATS_PER_VERTEX;
+        self.vertices.reserve(count * FLOATS_PER_VERTEX);
+        for vertex in vertices.chunks_exact(FLOATS_PER_VERTEX) {
+            self.vertices.extend_from_slice(&vertex[..5]);
+            self.vertices.push(alpha * vertex[5]);
+        }
+        let item = DrawItem { chart, layer, mode, scissor, first, count };
+        let idx = self.items.partition_point(|other| (other.chart, other.layer) <= (chart, layer));
+        self.items.insert(idx, item);
+        Some(item)
+    }
+
+    /// `vertices` returns the interleaved vertices of all the items
+    pub fn vertices(&self) -> &[f32] {
+        &self.vertices
+    }
+
+    /// `items` returns the items in draw order
+    pub fn items(&self) -> &[DrawItem] {
+        &self.items
+    }
+
+    /// `generation` returns the generation of the vertices
+    pub fn generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// `is_empty` returns true when there is nothing to draw
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// `groups` returns the runs of consecutive items that share their mode and scissor
+    pub fn groups(&self) -> Vec<DrawGroup> {
+        let mut groups: Vec<DrawGroup> = vec![];
+        for (idx, item) in self.items.iter().enumerate() {
+            match groups.last_mut() {
+                Some(group) if group.mode == item.mode && group.scissor == item.scissor => {
+                    group.items.end = idx + 1;
+                },
+                _ => groups.push(DrawGroup {
+                    mode: item.mode,
+                    scissor: item.scissor,
+                    items: idx..idx + 1,
+                }),
+            }
+        }
+        groups
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_lays_out_items_in_a_single_buffer() {
+        let mut batch = ChartDrawBatch::default();
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let line = [0f32, 0., 1., 1., 2., 0.];
+        let item = batch
+            .push(ChartIndex(0), DrawLayer::Series, DrawMode::LineStrip, None, &line, red, 0.5)
+            .unwrap();
+        assert_eq!((item.first, item.count), (0, 3));
+        assert_eq!(&batch.vertices()[..FLOATS_PER_VERTEX], &[0., 0., 1., 0., 0., 0.5]);
+        let item = batch
+            .push(ChartIndex(0), DrawLayer::Series, DrawMode::LineStrip, None, &line[..4], red, 1.)
+            .unwrap();
+        assert_eq!((item.first, item.count), (3, 2));
+        assert_eq!(batch.vertices().len(), 5 * FLOATS_PER_VERTEX);
+        // Items without enough vertices for their mode are not added.
+        let (chart, layer, single) = (ChartIndex(0), DrawLayer::Series, [0f32, 0.]);
+        assert!(batch.push(chart, layer, DrawMode::LineStrip, None, &single, red, 1.).is_none());
+        assert!(batch.push(chart, layer, DrawMode::Triangles, None, &line[..4], red, 1.).is_none());
+        assert_eq!(batch.items().len(), 2);
+        assert_eq!(batch.vertices().len(), 5 * FLOATS_PER_VERTEX);
+        // Every item covers its own range of the buffer.
+        let item = batch
+            .push(ChartIndex(1), DrawLayer::Series, DrawMode::Points, None, &single, red, 1.)
+            .unwrap();
+        assert_eq!((item.first, item.count), (5, 1));
+        let vertices: usize = batch.items().iter().map(|item| item.count).sum();
+        assert_eq!(vertices * FLOATS_PER_VERTEX, batch.vertices().len());
+        // Rebuilding the batch changes its generation.
+        let generation = batch.generation();
+        batch.clear();
+        assert!(batch.is_empty());
+        assert!(batch.vertices().is_empty());
+        assert_ne!(batch.generation(), generation);
+    }
+
+    #[test]
+    fn it_scales_the_alpha_of_faded_vertices() {
+        let mut batch = ChartDrawBatch::default();
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let (chart, layer, mode) = (ChartIndex(0), DrawLayer::Series, DrawMode::TriangleStrip);
+        let strip = [0f32, 1., 0., 0., 0., 1., 1., 1., 0., 1., 0., 1.];
+        let item = batch.push_faded(chart, layer, mode, None, &strip, red, 0.5).unwrap();
+        assert_eq!((item.first, item.count), (0, 4));
+        let alphas: Vec<f32> =
+            batch.vertices().chunks_exact(FLOATS_PER_VERTEX).map(|vertex| vertex[5]).collect();
+        assert_eq!(alphas, vec![0., 0.5, 0., 0.5]);
+        assert_eq!(&batch.vertices()[6..12], &[0., 0., 1., 0., 0., 0.5]);
+        // A strip needs a triangle.
+        assert!(batch.push_faded(chart, layer, mode, None, &strip[..6], red, 1.).is_none());
+    }
+
+    #[test]
+    fn it_keeps_the_color_of_colored_vertices() {
+        let mut batch = ChartDrawBatch::default();
+        let (chart, layer, mode) = (ChartIndex(0), DrawLayer::Series, DrawMode::Triangles);
+        let triangle = [0f32, 0., 1., 0., 0., 1., 1., 0., 0., 1., 0., 1., 1., 1., 0., 0., 1., 0.5];
+        let item = batch.push_colored(chart, layer, mode, None, &triangle, 0.5).unwrap();
+        assert_eq!((item.first, item.count), (0, 3));
+        assert_eq!(&batch.vertices()[6..12], &[1., 0., 0., 1., 0., 0.5]);
+        assert_eq!(&batch.vertices()[12..], &[1., 1., 0., 0., 1., 0.25]);
+        // A triangle needs three vertices.
+        assert!(batch.push_colored(chart, layer, mode, None, &triangle[..12], 1.).is_none());
+    }
+
+    #[test]
+    fn it_draws_items_in_layer_order() {
+        let mut batch = ChartDrawBatch::default();
+        let color = Rgb::default();
+        let line = [0f32, 0., 1., 1.];
+        let first_rect = Some(PixelRect { x: 0., y: 0., width: 10., height: 10. });
+        let second_rect = Some(PixelRect { x: 10., y: 0., width: 10., height: 10. });
+        let mut push = |chart: usize, layer: DrawLayer, scissor: Option<PixelRect>| {
+            let mode = DrawMode::LineStrip;
+            batch.push(ChartIndex(chart), layer, mode, scissor, &line, color, 1.).unwrap().first
+        };
+        // The series of the second chart arrive before the decorations of the first one.
+        let second_series = push(1, DrawLayer::Series, second_rect);
+        let first_series = push(0, DrawLayer::Series, first_rect);
+        let first_decoration = push(0, DrawLayer::Decoration, first_rect);
+        let second_decoration = push(1, DrawLayer::Decoration, second_rect);
+        let other_first_series = push(0, DrawLayer::Series, first_rect);
+        let order: Vec<usize> = batch.items().iter().map(|item| item.first).collect();
+        assert_eq!(
+            order,
+            vec![
+                first_decoration,
+                first_series,
+                other_first_series,
+                second_decoration,
+                second_series
+            ]
+        );
+        // Consecutive items of the same chart are drawn with a single state change.
+        let groups = batch.groups();
+        assert_eq!(groups.len(), 2);
+        assert_eq!(groups[0].items, 0..3);
+        assert_eq!(groups[0].scissor, first_rect);
+        assert_eq!(groups[1].items, 3..5);
+        assert_eq!(groups[1].scissor, second_rect);
+        // A different mode starts a new group.
+        let (chart, layer, mode) = (ChartIndex(1), DrawLayer::Series, DrawMode::Points);
+        batch.push(chart, layer, mode, second_rect, &line, color, 1.);
+        assert_eq!(batch.groups().len(), 3);
+    }
+}
@@ -0,0 +1,437 @@
+//! `expr` is a tiny arithmetic language used to transform the samples of a source as they are
+//! loaded, i.e. `x / 1073741824` to turn bytes into GiB. The expression is compiled once when
+//! the config is loaded and evaluated for each sample.
+//! The grammar is:
+//!   expr    := term (('+' | '-') term)*
+//!   term    := unary (('*' | '/') unary)*
+//!   unary   := '-' unary | primary
+//!   primary := number | 'x' | function '(' expr (',' expr)* ')' | '(' expr ')'
+//! The functions are abs(a), min(a, b), max(a, b) and clamp(value, low, high).
+
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+
+/// `BinaryOp` is an arithmetic operation between two expressions
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    Add,
+    Sub,
+    Mul,
+    Div,
+}
+
+/// `Function` is one of the functions that can be called in an expression
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Function {
+    Abs,
+    Min,
+    Max,
+    Clamp,
+}
+
+impl Function {
+    /// `from_name` returns the function called `name`, if any
+    fn from_name(name: &str) -> Option<Function> {
+        match name {
+            "abs" => Some(Function::Abs),
+            "min" => Some(Function::Min),
+            "max" => Some(Function::Max),
+            "clamp" => Some(Function::Clamp),
+            _ => None,
+        }
+    }
+
+    /// `arity` returns the number of arguments the function takes
+    fn arity(&self) -> usize {
+        match self {
+            Function::Abs => 1,
+            Function::Min | Function::Max => 2,
+            Function::Clamp => 3,
+        }
+    }
+}
+
+/// `Expr` is a node of the syntax tree of an expression
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// The sample being loaded, `x`
+    Sample,
+    /// A numeric literal
+    Number(f64),
+    /// The negation of an expression
+    Neg(Box<Expr>),
+    /// An arithmetic operation between two expressions
+    Binary(BinaryOp, Box<Expr>, Box<Expr>),
+    /// A function call, the number of arguments matches the arity of the function
+    Call(Function, Vec<Expr>),
+}
+
+impl Expr {
+    /// `eval` returns the value of the expression for a sample, a division by zero yields None
+    pub fn eval(&self, sample: f64) -> Option<f64> {
+        match self {
+            Expr::Sample => Some(sample),
+            Expr::Number(value) => Some(*value),
+            Expr::Neg(expr) => expr.eval(sample).map(|value| -value),
+            Expr::Binary(op, lhs, rhs) => {
+                let lhs = lhs.eval(sample)?;
+                let rhs = rhs.eval(sample)?;
+                match op {
+                    BinaryOp::Add => Some(lhs + rhs),
+                    BinaryOp::Sub => Some(lhs - rhs),
+                    BinaryOp::Mul => Some(lhs * rhs),
+                    BinaryOp::Div if rhs == 0f64 => None,
+                    BinaryOp::Div => Some(lhs / rhs),
+                }
+            },
+            Expr::Call(function, args) => {
+                let args = args.iter().map(|arg| arg.eval(sample)).collect::<Option<Vec<f64>>>()?;
+                match function {
+                    Function::Abs => Some(args[0].abs()),
+                    Function::Min => Some(args[0].min(args[1])),
+                    Function::Max => Some(args[0].max(args[1])),
+                    // Unlike f64::clamp this does not panic when low is greater than high.
+                    Function::Clamp => Some(args[0].max(args[1]).min(args[2])),
+                }
+            },
+        }
+    }
+}
+
+/// `ExprError` is the reason an expression could not be parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExprError {
+    /// The byte offset in the expression where the error was found
+    pub position: usize,
+    /// What was expected or found at the position
+    pub message: String,
+}
+
+impl fmt::Display for ExprError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "at position {}: {}", self.position, self.message)
+    }
+}
+
+/// `Token` is the smallest unit of an expression
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(f64),
+    Ident(String),
+    Plus,
+    Minus,
+    Star,
+    Slash,
+    LeftParen,
+    RightParen,
+    Comma,
+    End,
+}
+
+/// `tokenize` splits an expression into tokens along with their position, the last token is
+/// always Token::End
+fn tokenize(source: &str) -> Result<Vec<(usize, Token)>, ExprError> {
+    let mut tokens = vec![];
+    let mut chars = source.char_indices().peekable();
+    while let Some(&(position, c)) = chars.peek() {
+        let token = match c {
+            c if c.is_whitespace() => {
+                chars.next();
+                continue;
+            },
+            '+' => Token::Plus,
+            '-' => Token::Minus,
+            '*' => Token::Star,
+            '/' => Token::Slash,
+            '(' => Token::LeftParen,
+            ')' => Token::RightParen,
+            ',' => Token::Comma,
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut end = position;
+                let mut prev = c;
+                while let Some(&(idx, c)) = chars.peek() {
+                    let exponent_sign = (c == '+' || c == '-') && (prev == 'e' || prev == 'E');
+                    if !(c.is_ascii_digit() || c == '.' || c == 'e' || c == 'E' || exponent_sign) {
+                        break;
+                    }
+                    end = idx + c.len_utf8();
+                    prev = c;
+                    chars.next();
+                }
+                let literal = &source[position..end];
+                match literal.parse() {
+                    Ok(value) => tokens.push((position, Token::Number(value))),
+                    Err(_) => {
+                        return Err(ExprError {
+                            position,
+                            message: format!("Invalid number '{}'", literal),
+                        })
+                    },
+                }
+                continue;
+            },
+            c if c.is_ascii_alphabetic() || c == '_' => {
+                let mut end = position;
+                while let Some(&(idx, c)) = chars.peek() {
+                    if !(c.is_ascii_alphanumeric() || c == '_') {
+                        break;
+                    }
+                    end = idx + c.len_utf8();
+                    chars.next();
+                }
+                tokens.push((position, Token::Ident(source[position..end].to_string())));
+                continue;
+            },
+            c => {
+                return Err(ExprError {
+                    position,
+                    message: format!("Unexpected character '{}'", c),
+                })
+            },
+        };
+        tokens.push((position, token));
+        chars.next();
+    }
+    tokens.push((source.len(), Token::End));
+    Ok(tokens)
+}
+
+/// `Parser` is a recursive descent parser over the tokens of an expression
+struct Parser {
+    tokens: Vec<(usize, Token)>,
+    pos: usize,
+}
+
+impl Parser {
+    /// `peek` returns the current token and its position
+    fn peek(&self) -> &(usize, Token) {
+        // The last token is End and it is never consumed.
+        &self.tokens[self.pos.min(self.tokens.len() - 1)]
+    }
+
+    /// `advance` consumes the current token
+    fn advance(&mut self) -> (usize, Token) {
+        let token = self.peek().clone();
+        if token.1 != Token::End {
+            self.pos += 1;
+        }
+        token
+    }
+
+    /// `expect` consumes the current token, it must be `expected`
+    fn expect(&mut self, expected: Token, what: &str) -> Result<(), ExprError> {
+        let (position, token) = self.advance();
+        if token == expected {
+            Ok(())
+        } else {
+            Err(ExprError { position, message: format!("Expected {}", what) })
+        }
+    }
+
+    fn expr(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.term()?;
+        loop {
+            let op = match self.peek().1 {
+                Token::Plus => BinaryOp::Add,
+                Token::Minus => BinaryOp::Sub,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.term()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn term(&mut self) -> Result<Expr, ExprError> {
+        let mut lhs = self.unary()?;
+        loop {
+            let op = match self.peek().1 {
+                Token::Star => BinaryOp::Mul,
+                Token::Slash => BinaryOp::Div,
+                _ => return Ok(lhs),
+            };
+            self.advance();
+            let rhs = self.unary()?;
+            lhs = Expr::Binary(op, Box::new(lhs), Box::new(rhs));
+        }
+    }
+
+    fn unary(&mut self) -> Result<Expr, ExprError> {
+        if self.peek().1 == Token::Minus {
+            self.advance();
+            return Ok(Expr::Neg(Box::new(self.unary()?)));
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Expr, ExprError> {
+        let (position, token) = self.advance();
+        match token {
+            Token::Number(value) => Ok(Expr::Number(value)),
+            Token::Ident(name) if name == "x" => Ok(Expr::Sample),
+            Token::Ident(name) => {
+                let function = Function::from_name(&name).ok_or_else(|| ExprError {
+                    position,
+                    message: format!("Unknown identifier '{}'", name),
+                })?;
+                self.expect(Token::LeftParen, "'(' after the function name")?;
+                let mut args = vec![self.expr()?];
+                while self.peek().1 == Token::Comma {
+                    self.advance();
+                    args.push(self.expr()?);
+                }
+                self.expect(Token::RightParen, "')' to close the function call")?;
+                if args.len() != function.arity() {
+                    return Err(ExprError {
+                        position,
+                        message: format!(
+                            "'{}' takes {} arguments, found {}",
+                            name,
+                            function.arity(),
+                            args.len()
+                        ),
+                    });
+                }
+                Ok(Expr::Call(function, args))
+            },
+            Token::LeftParen => {
+                let expr = self.expr()?;
+                self.expect(Token::RightParen, "')'")?;
+                Ok(expr)
+            },
+            Token::End => Err(ExprError { position, message: String::from("Unexpected end") }),
+            token => Err(ExprError { position, message: format!("Unexpected {:?}", token) }),
+        }
+    }
+}
+
+/// `Expression` is a compiled expression along with its source, it is read from the config as a
+/// string
+#[derive(Debug, Clone, PartialEq)]
+pub struct Expression {
+    /// The expression as written in the config
+    pub source: String,
+
+    /// The syntax tree evaluated for each sample
+    pub ast: Expr,
+}
+
+impl Expression {
+    /// `parse` compiles an expression, the errors contain the position of the offending token
+    pub fn parse(source: &str) -> Result<Expression, ExprError> {
+        let mut parser = Parser { tokens: tokenize(source)?, pos: 0 };
+        let ast = parser.expr()?;
+        let (position, token) = parser.advance();
+        if token != Token::End {
+            return Err(ExprError { position, message: format!("Unexpected {:?}", token) });
+        }
+        Ok(Expression { source: source.to_string(), ast })
+    }
+
+    /// `eval` transforms a sample, a division by zero yields None
+    pub fn eval(&self, sample: f64) -> Option<f64> {
+        self.ast.eval(sample)
+    }
+}
+
+impl Serialize for Expression {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ser.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Expression {
+    fn deserialize<D>(de: D) -> Result<Expression, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let source = String::deserialize(de)?;
+        Expression::parse(&source)
+            .map_err(|err| serde::de::Error::custom(format!("Invalid expr '{}' {}", source, err)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(source: &str) -> Expr {
+        Expression::parse(source).unwrap().ast
+    }
+
+    fn num(value: f64) -> Box<Expr> {
+        Box::new(Expr::Number(value))
+    }
+
+    #[test]
+    fn it_parses_expressions() {
+        // Multiplication binds tighter than addition.
+        assert_eq!(
+            parse("1 + 2 * x"),
+            Expr::Binary(
+                BinaryOp::Add,
+                num(1.),
+                Box::new(Expr::Binary(BinaryOp::Mul, num(2.), Box::new(Expr::Sample)))
+            )
+        );
+        // Parentheses override the precedence.
+        assert_eq!(
+            parse("(1 + 2) * x"),
+            Expr::Binary(
+                BinaryOp::Mul,
+                Box::new(Expr::Binary(BinaryOp::Add, num(1.), num(2.))),
+                Box::new(Expr::Sample)
+            )
+        );
+        // Operators of the same precedence are left associative.
+        assert_eq!(
+            parse("8 - 4 - 2"),
+            Expr::Binary(
+                BinaryOp::Sub,
+                Box::new(Expr::Binary(BinaryOp::Sub, num(8.), num(4.))),
+                num(2.)
+            )
+        );
+        assert_eq!(parse("-x"), Expr::Neg(Box::new(Expr::Sample)));
+        assert_eq!(parse("1.5e3"), Expr::Number(1500.));
+        assert_eq!(
+            parse("clamp(x, 0, 100)"),
+            Expr::Call(Function::Clamp, vec![Expr::Sample, Expr::Number(0.), Expr::Number(100.)])
+        );
+    }
+
+    #[test]
+    fn it_reports_the_position_of_parse_errors() {
+        let error = |source: &str| Expression::parse(source).unwrap_err().position;
+        assert_eq!(error(""), 0);
+        assert_eq!(error("x +"), 3);
+        assert_eq!(error("(x + 1"), 6);
+        assert_eq!(error("x ^ 2"), 2);
+        assert_eq!(error("y * 2"), 0);
+        assert_eq!(error("abs(x, 1)"), 0);
+        assert_eq!(error("min x"), 4);
+        assert_eq!(error("x 2"), 2);
+        assert_eq!(error("1.2.3"), 0);
+        // The position is part of the config error.
+        let err = serde_yaml::from_str::<Expression>("x / (2").unwrap_err();
+        assert!(err.to_string().contains("at position 6"));
+    }
+
+    #[test]
+    fn it_evaluates_expressions() {
+        let eval = |source: &str, x: f64| Expression::parse(source).unwrap().eval(x);
+        assert_eq!(eval("x / 1073741824", 2147483648.), Some(2.));
+        assert_eq!(eval("x - 100", 42.), Some(-58.));
+        assert_eq!(eval("-(x + 1) * 2", 1.), Some(-4.));
+        assert_eq!(eval("abs(x)", -3.), Some(3.));
+        assert_eq!(eval("min(x, 10) + max(x, 10)", 4.), Some(14.));
+        assert_eq!(eval("clamp(x, 0, 100)", 250.), Some(100.));
+        assert_eq!(eval("clamp(x, 0, 100)", -5.), Some(0.));
+        // Division by zero is a missing sample.
+        assert_eq!(eval("100 / x", 0.), None);
+        assert_eq!(eval("abs(1 / (x - 1))", 1.), None);
+    }
+}
@@ -0,0 +1,221 @@
+//! `fixtures` are the canned Prometheus responses, series states and chart configs shared by the
+//! tests of the charts, so that they are not copied across the test modules. They are built for
+//! the tests of this crate and, with the `test-fixtures` feature, for the tests of the other
+//! crates of the workspace.
+//!
+//! The builders of canned responses are not exported with the feature, they return the Prometheus
+//! HTTP types which are kept out of the public API, see `charts::prelude`. The tests of the other
+//! crates get the series states, chart configs, MockEventListener and MockClock.
+
+use crate::charts::decorations::Decoration;
+use crate::charts::prometheus::PrometheusTimeSeries;
+#[cfg(test)]
+use crate::charts::prometheus::{
+    HTTPMatrixResult, HTTPResponse, HTTPResponseData, HTTPVectorResult,
+};
+use crate::charts::{ChartLength2D, ChartsConfig, TimeSeries, TimeSeriesChart, TimeSeriesSource};
+use crate::event::{Event, EventListener};
+use std::cell::Cell;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+/// `labels` returns the labels of a result from its name and value pairs
+pub fn labels(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+}
+
+/// `matrix_result` returns a range vector with a sample per epoch and value, the values are sent
+/// as strings like Prometheus does
+#[cfg(test)]
+pub(crate) fn matrix_result(
+    labels: HashMap<String, String>,
+    samples: &[(u64, f64)],
+) -> HTTPMatrixResult {
+    let values = samples
+        .iter()
+        .map(|(epoch, value)| vec![serde_json::json!(epoch), serde_json::json!(value.to_string())])
+        .collect();
+    HTTPMatrixResult { labels, values }
+}
+
+/// `matrix_response` returns a successful query_range response of the results
+#[cfg(test)]
+pub(crate) fn matrix_response(result: Vec<HTTPMatrixResult>) -> HTTPResponse {
+    HTTPResponse { data: HTTPResponseData::Matrix { result }, status: String::from("success") }
+}
+
+/// `vector_result` returns an instant vector sampled at an epoch, instant queries are evaluated
+/// at fractional epochs
+#[cfg(test)]
+pub(crate) fn vector_result(
+    labels: HashMap<String, String>,
+    epoch: f64,
+    value: f64,
+) -> HTTPVectorResult {
+    HTTPVectorResult {
+        labels,
+        value: vec![serde_json::json!(epoch), serde_json::json!(value.to_string())],
+    }
+}
+
+/// `vector_response` returns a successful query response of the results
+#[cfg(test)]
+pub(crate) fn vector_response(result: Vec<HTTPVectorResult>) -> HTTPResponse {
+    HTTPResponse { data: HTTPResponseData::Vector { result }, status: String::from("success") }
+}
+
+/// `scalar_response` returns a successful query response of a scalar
+#[cfg(test)]
+pub(crate) fn scalar_response(epoch: f64, value: f64) -> HTTPResponse {
+    HTTPResponse {
+        data: HTTPResponseData::Scalar {
+            result: vec![serde_json::json!(epoch), serde_json::json!(value.to_string())],
+        },
+        status: String::from("success"),
+    }
+}
+
+/// `response_body` returns the JSON body of a response as served by Prometheus
+#[cfg(test)]
+pub(crate) fn response_body(response: &HTTPResponse) -> bytes::Bytes {
+    bytes::Bytes::from(serde_json::to_vec(response).unwrap())
+}
+
+/// `empty_series` returns a series without samples
+pub fn empty_series(capacity: usize) -> TimeSeries {
+    TimeSeries::default().with_capacity(capacity)
+}
+
+/// `series_with_gaps` returns a series with the samples upserted, the epochs between them are
+/// filled as missing values
+pub fn series_with_gaps(capacity: usize, samples: &[(u64, Option<f64>)]) -> TimeSeries {
+    let mut series = empty_series(capacity);
+    for sample in samples {
+        series.upsert(*sample);
+    }
+    series
+}
+
+/// `wrapped_series` returns a series with its circular buffer as is, the active items start at
+/// `first_idx` and may wrap around the end of the metrics
+pub fn wrapped_series(
+    metrics: Vec<(u64, Option<f64>)>,
+    first_idx: usize,
+    active_items: usize,
+) -> TimeSeries {
+    TimeSeries {
+        metrics_capacity: metrics.len(),
+        metrics,
+        first_idx,
+        active_items,
+        ..TimeSeries::default()
+    }
+}
+
+/// `corrupted_series` returns a full series whose epochs are not in order, as the sanity checks
+/// should find it
+pub fn corrupted_series(metrics: Vec<(u64, Option<f64>)>) -> TimeSeries {
+    let active_items = metrics.len();
+    wrapped_series(metrics, 0, active_items)
+}
+
+/// `chart` returns a chart of the sources at the top left of the window, 100x50 pixels
+pub fn chart(name: &str, sources: Vec<TimeSeriesSource>) -> TimeSeriesChart {
+    TimeSeriesChart {
+        name: name.to_string(),
+        sources,
+        position: Some(ChartLength2D::pixels(0., 0.)),
+        dimensions: Some(ChartLength2D::pixels(100., 50.)),
+        ..TimeSeriesChart::default()
+    }
+}
+
+/// `single_chart_config` returns the config of a single chart with a default series
+pub fn single_chart_config() -> ChartsConfig {
+    ChartsConfig {
+        charts: vec![chart("input", vec![TimeSeriesSource::default()])],
+        ..ChartsConfig::default()
+    }
+}
+
+/// `multi_source_config` returns the config of a chart "load" with a Prometheus series per name
+pub fn multi_source_config(names: &[&str]) -> ChartsConfig {
+    let sources = names
+        .iter()
+        .map(|name| {
+            TimeSeriesSource::PrometheusTimeSeries(Box::new(PrometheusTimeSeries {
+                name: name.to_string(),
+                ..PrometheusTimeSeries::default()
+            }))
+        })
+        .collect();
+    ChartsConfig { charts: vec![chart("load", sources)], ..ChartsConfig::default() }
+}
+
+/// `decorated_config` returns the config of a single chart "decorated" with the decorations
+pub fn decorated_config(decorations: Vec<Decoration>) -> ChartsConfig {
+    ChartsConfig {
+        charts: vec![TimeSeriesChart {
+            decorations,
+            ..chart("decorated", vec![TimeSeriesSource::default()])
+        }],
+        ..ChartsConfig::default()
+    }
+}
+
+/// `MockEventListener` keeps the events sent to it, its clones share them
+#[derive(Default, Clone)]
+pub struct MockEventListener(Arc<Mutex<Vec<Event>>>);
+
+impl EventListener for MockEventListener {
+    fn send_event(&self, event: Event) {
+        self.0.lock().unwrap().push(event);
+    }
+}
+
+impl MockEventListener {
+    /// `take` returns the events sent since the last take
+    pub fn take(&self) -> Vec<Event> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+
+    /// `take_alerts` returns the text of the chart alerts sent since the last take, the other
+    /// events are dropped
+    pub fn take_alerts(&self) -> Vec<String> {
+        self.take()
+            .into_iter()
+            .filter_map(|event| match event {
+                Event::ChartAlert(text) => Some(text),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// `MockClock` is an epoch in seconds that only moves when told, for the code that is given the
+/// `now` epoch
+#[derive(Debug, Default)]
+pub struct MockClock(Cell<u64>);
+
+impl MockClock {
+    /// `new` returns a clock stopped at an epoch
+    pub fn new(epoch: u64) -> MockClock {
+        MockClock(Cell::new(epoch))
+    }
+
+    /// `now` returns the current epoch
+    pub fn now(&self) -> u64 {
+        self.0.get()
+    }
+
+    /// `set` moves the clock to an epoch, backwards too
+    pub fn set(&self, epoch: u64) {
+        self.0.set(epoch);
+    }
+
+    /// `advance` moves the clock forward, returns the new epoch
+    pub fn advance(&self, seconds: u64) -> u64 {
+        self.0.set(self.0.get() + seconds);
+        self.0.get()
+    }
+}
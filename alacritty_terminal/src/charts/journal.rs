@@ -0,0 +1,376 @@
+//! `journal` keeps the anomalies found in the data loaded by the Prometheus series, so that a
+//! spike or a gap seen in a chart can be traced back to what the source returned. Each response
+//! is compared with the series as it was before loading it: a sample far from the mean, samples
+//! too far apart, a counter that went down or a response without items. The journal is bounded,
+//! the oldest entries are evicted.
+
+use crate::charts::timefmt::ChartTimezone;
+use crate::charts::{JournalConfig, SeriesIndex, TimeSeries, TimeSeriesChart};
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::fmt::Write;
+
+/// The filled samples a series needs before its outliers and counter resets are detected, the
+/// standard deviation or the growth of fewer samples is not meaningful
+pub const MIN_BASELINE_SAMPLES: usize = 5usize;
+
+/// The offending samples written in the description of an entry, the rest are counted
+pub const MAX_DESCRIBED_SAMPLES: usize = 3usize;
+
+/// The latest journal entries shown in the debug overlay
+pub const JOURNAL_OVERLAY_ENTRIES: usize = 3usize;
+
+/// `AnomalyKind` is an anomaly of the loaded data
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// A sample beyond the configured standard deviations from the mean of the series
+    Outlier,
+    /// Consecutive samples further apart than the configured pull intervals
+    Gap,
+    /// A sample lower than the previous one of a series that only grew so far
+    CounterReset,
+    /// A successful response that loaded no items
+    Empty,
+}
+
+impl fmt::Display for AnomalyKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AnomalyKind::Outlier => write!(f, "outlier"),
+            AnomalyKind::Gap => write!(f, "gap"),
+            AnomalyKind::CounterReset => write!(f, "counter reset"),
+            AnomalyKind::Empty => write!(f, "empty"),
+        }
+    }
+}
+
+/// `SeriesBaseline` is the state of a series before a response is loaded into it, the samples
+/// of the response are compared with it
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SeriesBaseline {
+    /// The mean of the filled samples, from the stats of the series
+    mean: f64,
+
+    /// The standard deviation of the filled samples around the mean
+    std_dev: f64,
+
+    /// The filled samples, from the stats of the series
+    count: usize,
+
+    /// The newest epoch of the series, the later samples are the loaded ones
+    last_epoch: u64,
+
+    /// The newest filled sample
+    last: Option<(u64, f64)>,
+
+    /// Whether the filled samples only grew, i.e. the series is a counter
+    monotonic: bool,
+}
+
+impl SeriesBaseline {
+    /// `new` takes the mean, the count and the newest epoch from the stats of the series, the
+    /// standard deviation is calculated around that mean
+    pub fn new(series: &TimeSeries) -> Self {
+        let stats = &series.stats;
+        let filled: Vec<(u64, f64)> =
+            series.iter().filter_map(|&(epoch, value)| Some((epoch, value?))).collect();
+        let std_dev = if filled.len() > 1 {
+            let squares: f64 = filled.iter().map(|(_, value)| (value - stats.avg).powi(2)).sum();
+            (squares / filled.len() as f64).sqrt()
+        } else {
+            0f64
+        };
+        let monotonic = match (filled.first(), filled.last()) {
+            (Some(first), Some(last)) if filled.len() >= MIN_BASELINE_SAMPLES => {
+                first.1 < last.1 && filled.windows(2).all(|pair| pair[0].1 <= pair[1].1)
+            },
+            _ => false,
+        };
+        SeriesBaseline {
+            mean: stats.avg,
+            std_dev,
+            count: stats.count,
+            last_epoch: stats.last_epoch,
+            last: filled.last().copied(),
+            monotonic,
+        }
+    }
+
+    /// `anomalies` compares the samples loaded into `series` after the baseline with it, the
+    /// gaps are measured in pull `interval`s. Only the kinds recorded by the config are returned,
+    /// each with its offending samples.
+    pub fn anomalies(
+        &self,
+        series: &TimeSeries,
+        loaded: usize,
+        interval: u64,
+        config: &JournalConfig,
+    ) -> Vec<(AnomalyKind, Vec<(u64, Option<f64>)>)> {
+        if loaded == 0 {
+            return [(AnomalyKind::Empty, vec![])]
+                .into_iter()
+                .filter(|(kind, _)| config.records(*kind))
+                .collect();
+        }
+        let mut outliers = vec![];
+        let mut gaps = vec![];
+        let mut resets = vec![];
+        let max_distance = config.std_devs() * self.std_dev;
+        let max_gap = config.gap_intervals() * interval.max(1);
+        let mut previous = self.last;
+        let loaded_samples = series
+            .iter()
+            .filter(|(epoch, _)| *epoch > self.last_epoch)
+            .filter_map(|&(epoch, value)| Some((epoch, value?)));
+        for (epoch, value) in loaded_samples {
+            if self.count >= MIN_BASELINE_SAMPLES
+                && self.std_dev > 0f64
+                && (value - self.mean).abs() > max_distance
+            {
+                outliers.push((epoch, Some(value)));
+            }
+            if let Some((previous_epoch, previous_value)) = previous {
+                if epoch.saturating_sub(previous_epoch) > max_gap {
+                    gaps.push((previous_epoch, Some(previous_value)));
+                    gaps.push((epoch, Some(value)));
+                }
+                if self.monotonic && value < previous_value {
+                    resets.push((epoch, Some(value)));
+                }
+            }
+            previous = Some((epoch, value));
+        }
+        [
+            (AnomalyKind::Outlier, outliers),
+            (AnomalyKind::Gap, gaps),
+            (AnomalyKind::CounterReset, resets),
+        ]
+        .into_iter()
+        .filter(|(kind, samples)| !samples.is_empty() && config.records(*kind))
+        .collect()
+    }
+}
+
+/// `JournalEntry` is an anomaly found in a response loaded into a series
+#[derive(Debug, Clone, PartialEq)]
+pub struct JournalEntry {
+    /// The wall time the response was loaded at, in epoch seconds
+    pub wall_time: u64,
+
+    /// The name of the chart
+    pub chart: String,
+
+    /// The name of the source
+    pub source: String,
+
+    /// The anomaly found
+    pub kind: AnomalyKind,
+
+    /// The offending samples, the samples on both sides of each gap
+    pub samples: Vec<(u64, Option<f64>)>,
+
+    /// The summary of the response
+    pub response: String,
+}
+
+impl JournalEntry {
+    /// `describe` returns the entry as a line, its wall time is shown in the `timezone`
+    pub fn describe(&self, timezone: &ChartTimezone) -> String {
+        let wall_time = timezone.clock_secs(self.wall_time);
+        let mut line = format!("{} {}/{}: {}", wall_time, self.chart, self.source, self.kind);
+        for (idx, (epoch, value)) in self.samples.iter().take(MAX_DESCRIBED_SAMPLES).enumerate() {
+            let separator = if idx == 0 { " at " } else { ", " };
+            let _ = match value {
+                Some(value) => write!(line, "{}{}={}", separator, epoch, value),
+                None => write!(line, "{}{}=missing", separator, epoch),
+            };
+        }
+        if self.samples.len() > MAX_DESCRIBED_SAMPLES {
+            let _ = write!(line, " (+{})", self.samples.len() - MAX_DESCRIBED_SAMPLES);
+        }
+        let _ = write!(line, " [{}]", self.response);
+        line
+    }
+}
+
+/// `AnomalyJournal` keeps the latest anomalies found in the loaded data, up to the max entries
+/// of its config
+#[derive(Debug, Clone)]
+pub struct AnomalyJournal {
+    /// The anomalies recorded and the entries kept
+    config: JournalConfig,
+
+    /// The entries, the oldest first
+    entries: VecDeque<JournalEntry>,
+}
+
+impl AnomalyJournal {
+    pub fn new(config: JournalConfig) -> Self {
+        AnomalyJournal { config, entries: VecDeque::new() }
+    }
+
+    /// `check` records the anomalies of the response loaded into a series of a chart, `loaded`
+    /// are the items loaded from it and `now` the wall time. Returns the entries added.
+    #[allow(clippy::too_many_arguments)]
+    pub fn check(
+        &mut self,
+        chart: &TimeSeriesChart,
+        series_index: SeriesIndex,
+        baseline: &SeriesBaseline,
+        loaded: usize,
+        interval: u64,
+        response: &str,
+        now: u64,
+    ) -> usize {
+        let source = match chart.sources.get(series_index.0) {
+            Some(source) => source,
+            None => return 0,
+        };
+        let anomalies = baseline.anomalies(source.series_ref(), loaded, interval, &self.config);
+        let added = anomalies.len();
+        for (kind, samples) in anomalies {
+            self.push(JournalEntry {
+                wall_time: now,
+                chart: chart.name.clone(),
+                source: source.name(),
+                kind,
+                samples,
+                response: response.to_string(),
+            });
+        }
+        added
+    }
+
+    /// `push` adds an entry, the oldest one is evicted when the journal is full
+    pub fn push(&mut self, entry: JournalEntry) {
+        while self.entries.len() >= self.config.max_entries() {
+            self.entries.pop_front();
+        }
+        self.entries.push_back(entry);
+    }
+
+    /// `entries` returns the entries, the oldest first
+    pub fn entries(&self) -> Vec<JournalEntry> {
+        self.entries.iter().cloned().collect()
+    }
+
+    /// `recent` returns the descriptions of the latest `count` entries, the oldest first, their
+    /// wall times are shown in the `timezone`
+    pub fn recent(&self, count: usize, timezone: &ChartTimezone) -> Vec<String> {
+        let skip = self.entries.len().saturating_sub(count);
+        self.entries.iter().skip(skip).map(|entry| entry.describe(timezone)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn series_of(samples: &[(u64, f64)]) -> TimeSeries {
+        let mut series = TimeSeries::default().with_capacity(60);
+        for (epoch, value) in samples {
+            series.upsert((*epoch, Some(*value)));
+        }
+        series.calculate_stats();
+        series
+    }
+
+    fn load(series: &mut TimeSeries, samples: &[(u64, f64)]) -> usize {
+        let loaded =
+            samples.iter().map(|(epoch, value)| series.upsert((*epoch, Some(*value)))).sum();
+        series.calculate_stats();
+        loaded
+    }
+
+    fn config() -> JournalConfig {
+        JournalConfig {
+            max_entries: None,
+            record: None,
+            std_devs: Some(3.),
+            gap_intervals: Some(2),
+        }
+    }
+
+    #[test]
+    fn it_finds_each_anomaly_of_the_loaded_data() {
+        let steady: Vec<(u64, f64)> =
+            (100..110).map(|epoch| (epoch, 10. + (epoch % 2) as f64)).collect();
+        // A spike far from the mean.
+        let mut series = series_of(&steady);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(110, 10.), (111, 50.)]);
+        assert_eq!(baseline.anomalies(&series, loaded, 1, &config()), vec![(
+            AnomalyKind::Outlier,
+            vec![(111, Some(50.))]
+        )]);
+        // Samples further apart than the gap intervals.
+        let mut series = series_of(&steady);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(115, 11.)]);
+        assert_eq!(baseline.anomalies(&series, loaded, 1, &config()), vec![(
+            AnomalyKind::Gap,
+            vec![(109, Some(11.)), (115, Some(11.))]
+        )]);
+        // A counter going down.
+        let counter: Vec<(u64, f64)> = (100..110).map(|epoch| (epoch, epoch as f64)).collect();
+        let mut series = series_of(&counter);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(110, 110.), (111, 2.)]);
+        let anomalies = baseline.anomalies(&series, loaded, 1, &config());
+        assert!(anomalies.contains(&(AnomalyKind::CounterReset, vec![(111, Some(2.))])));
+        // A response without items.
+        let series = series_of(&steady);
+        let baseline = SeriesBaseline::new(&series);
+        assert_eq!(baseline.anomalies(&series, 0, 1, &config()), vec![(
+            AnomalyKind::Empty,
+            vec![]
+        )]);
+        // The kinds not recorded are ignored.
+        let only_gaps = JournalConfig { record: Some(vec![AnomalyKind::Gap]), ..config() };
+        assert!(baseline.anomalies(&series, 0, 1, &only_gaps).is_empty());
+    }
+
+    #[test]
+    fn it_finds_nothing_in_normal_data() {
+        let steady: Vec<(u64, f64)> =
+            (100..110).map(|epoch| (epoch, 10. + (epoch % 2) as f64)).collect();
+        let mut series = series_of(&steady);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(110, 10.), (111, 11.), (112, 10.)]);
+        assert!(baseline.anomalies(&series, loaded, 1, &config()).is_empty());
+        // A growing counter is not reset and the first load has no baseline to compare with.
+        let counter: Vec<(u64, f64)> = (100..110).map(|epoch| (epoch, epoch as f64)).collect();
+        let mut series = series_of(&counter);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(110, 110.), (111, 111.)]);
+        assert!(baseline.anomalies(&series, loaded, 1, &config()).is_empty());
+        let mut series = TimeSeries::default().with_capacity(60);
+        let baseline = SeriesBaseline::new(&series);
+        let loaded = load(&mut series, &[(100, 1.), (101, 1000.)]);
+        assert!(baseline.anomalies(&series, loaded, 1, &config()).is_empty());
+    }
+
+    #[test]
+    fn it_evicts_the_oldest_entries() {
+        let mut journal = AnomalyJournal::new(JournalConfig { max_entries: Some(2), ..config() });
+        let entry = |wall_time| JournalEntry {
+            wall_time,
+            chart: String::from("load"),
+            source: String::from("node"),
+            kind: AnomalyKind::Empty,
+            samples: vec![],
+            response: String::from("status: success, vector with 0 samples"),
+        };
+        for wall_time in 1..=3 {
+            journal.push(entry(wall_time));
+        }
+        assert_eq!(journal.entries(), vec![entry(2), entry(3)]);
+        assert_eq!(journal.recent(1, &ChartTimezone::Utc), vec![String::from(
+            "00:00:03 load/node: empty [status: success, vector with 0 samples]"
+        )]);
+        assert_eq!(journal.recent(1, &ChartTimezone::Fixed(-3600)), vec![String::from(
+            "23:00:03 load/node: empty [status: success, vector with 0 samples]"
+        )]);
+    }
+}
@@ -0,0 +1,246 @@
+//! `migrate` translates the charts configs of older versions into the current format. The
+//! `version` of a config selects the migrations applied to it, a config without version is of
+//! DEFAULT_CHARTS_CONFIG_VERSION. The migrations work on the YAML value so that the migrated
+//! config can also be written back for the users to adopt it.
+use crate::charts::ChartsConfig;
+use log::*;
+use serde::de::Error as _;
+use serde::{Deserialize, Deserializer};
+use serde_yaml::{Mapping, Value};
+use std::fmt;
+use std::path::Path;
+
+/// The version of the charts config format read by this crate
+pub const CHARTS_CONFIG_VERSION: u32 = 2u32;
+
+/// The version of a charts config that does not set one, the format before versioning
+pub const DEFAULT_CHARTS_CONFIG_VERSION: u32 = 1u32;
+
+/// `MigrationNote` describes a field of the config translated into the current format
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationNote {
+    /// The location of the field, i.e. charts[0].series[1]
+    pub path: String,
+
+    /// The field before the migration
+    pub from: String,
+
+    /// The field after the migration
+    pub to: String,
+}
+
+impl fmt::Display for MigrationNote {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{} → {}", self.path, self.from, self.to)
+    }
+}
+
+/// `Migration` translates a config of a version into the next one
+type Migration = fn(&mut Mapping, &mut Vec<MigrationNote>);
+
+/// The migrations of each version, the first one migrates version 1 into version 2
+const MIGRATIONS: [Migration; 1] = [migrate_v1_series_names];
+
+/// `migrate_value` applies the migrations of the version of a charts config, returning the config
+/// in the current format. The versions newer than CHARTS_CONFIG_VERSION are rejected.
+pub fn migrate_value(mut value: Value) -> Result<(Value, Vec<MigrationNote>), String> {
+    let config = value.as_mapping_mut().ok_or("The charts config must be a mapping")?;
+    let version = match config.get("version") {
+        None | Some(Value::Null) => DEFAULT_CHARTS_CONFIG_VERSION,
+        Some(version) => version
+            .as_u64()
+            .and_then(|version| u32::try_from(version).ok())
+            .filter(|version| *version >= DEFAULT_CHARTS_CONFIG_VERSION)
+            .ok_or_else(|| format!("Invalid charts config version: {}", field_text(version)))?,
+    };
+    if version > CHARTS_CONFIG_VERSION {
+        return Err(format!(
+            "The charts config version {} is newer than the supported version {}",
+            version, CHARTS_CONFIG_VERSION
+        ));
+    }
+    let mut notes = vec![];
+    for migration in &MIGRATIONS[(version - DEFAULT_CHARTS_CONFIG_VERSION) as usize..] {
+        migration(config, &mut notes);
+    }
+    config.insert(Value::from("version"), Value::from(CHARTS_CONFIG_VERSION));
+    Ok((value, notes))
+}
+
+impl ChartsConfig {
+    /// `migrate` reads a charts config of any supported version, the notes list the fields
+    /// that were translated into the current format.
+    pub fn migrate(value: Value) -> Result<(ChartsConfig, Vec<MigrationNote>), String> {
+        let (value, notes) = migrate_value(value)?;
+        let config = serde_yaml::from_value(value).map_err(|err| err.to_string())?;
+        Ok((config, notes))
+    }
+}
+
+/// `deserialize_migrated` deserializes a charts config of any supported version, the applied
+/// migrations are logged.
+pub fn deserialize_migrated<'de, D>(de: D) -> Result<ChartsConfig, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let value = Value::deserialize(de)?;
+    let (config, notes) = ChartsConfig::migrate(value).map_err(D::Error::custom)?;
+    for note in &notes {
+        info!("Migrated the charts config: {}", note);
+    }
+    Ok(config)
+}
+
+/// `migrated_charts_yaml` reads the charts of a YAML file, i.e. charts.yml, and writes the file
+/// back with the charts in the current format. The comments of the file are not kept.
+pub fn migrated_charts_yaml(path: &Path) -> Result<String, String> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|err| format!("Unable to read {}: {}", path.display(), err))?;
+    let mut file: Value = serde_yaml::from_str(&contents)
+        .map_err(|err| format!("Unable to parse {}: {}", path.display(), err))?;
+    let charts =
+        file.get_mut("charts").ok_or_else(|| format!("No charts found in {}", path.display()))?;
+    let (migrated, notes) = migrate_value(std::mem::take(charts))?;
+    // The migrated charts must be readable before they are adopted.
+    ChartsConfig::migrate(migrated.clone())?;
+    for note in &notes {
+        info!("Migrated the charts config: {}", note);
+    }
+    *charts = migrated;
+    serde_yaml::to_string(&file).map_err(|err| err.to_string())
+}
+
+/// `field_text` writes a field value the way it is written in YAML
+fn field_text(value: &Value) -> String {
+    serde_yaml::to_string(value).map_or_else(|_| format!("{:?}", value), |text| text.trim().into())
+}
+
+/// `rename_field` moves the value of a field to a new key, keeping a note of it
+fn rename_field(
+    mapping: &mut Mapping,
+    path: &str,
+    from: &str,
+    to: &str,
+    notes: &mut Vec<MigrationNote>,
+) {
+    if let Some(value) = mapping.remove(from) {
+        let text = field_text(&value);
+        notes.push(MigrationNote {
+            path: path.to_owned(),
+            from: format!("{}: {}", from, text),
+            to: format!("{}: {}", to, text),
+        });
+        mapping.insert(Value::from(to), value);
+    }
+}
+
+/// `migrate_v1_series_names` renames the fields of the series whose names did not match the rest
+/// of the config: the `refresh` of the Prometheus series is their `pull_interval` and the
+/// `async_items_loaded` type is the `async_loaded_items` internal counter.
+fn migrate_v1_series_names(config: &mut Mapping, notes: &mut Vec<MigrationNote>) {
+    let charts = match config.get_mut("charts").and_then(Value::as_sequence_mut) {
+        Some(charts) => charts,
+        None => return,
+    };
+    for (chart_idx, chart) in charts.iter_mut().enumerate() {
+        let series = match chart.get_mut("series").and_then(Value::as_sequence_mut) {
+            Some(series) => series,
+            None => continue,
+        };
+        for (series_idx, series) in series.iter_mut().filter_map(Value::as_mapping_mut).enumerate()
+        {
+            let path = format!("charts[{}].series[{}]", chart_idx, series_idx);
+            rename_field(series, &path, "refresh", "pull_interval", notes);
+            if series.get("type").and_then(Value::as_str) == Some("async_items_loaded") {
+                series.insert(Value::from("type"), Value::from("async_loaded_items"));
+                notes.push(MigrationNote {
+                    path,
+                    from: String::from("type: async_items_loaded"),
+                    to: String::from("type: async_loaded_items"),
+                });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charts::TimeSeriesSource;
+
+    /// A config of version 1 with every migrated field
+    static V1_CONFIG: &str = r#"
+spacing: 10
+charts:
+- name: load
+  series:
+  - name: load average
+    type: prometheus
+    refresh: 15
+    source: 'http://localhost:9090/api/v1/query_range?query=node_load1'
+  - name: loaded
+    type: async_items_loaded
+- name: no series
+  series: []
+"#;
+
+    #[test]
+    fn it_migrates_a_v1_config() {
+        let value: Value = serde_yaml::from_str(V1_CONFIG).unwrap();
+        let (config, notes) = ChartsConfig::migrate(value).unwrap();
+        assert_eq!(config.version, Some(CHARTS_CONFIG_VERSION));
+        let notes: Vec<String> = notes.iter().map(ToString::to_string).collect();
+        assert_eq!(
+            notes,
+            vec![
+                String::from("charts[0].series[0].refresh: 15 → pull_interval: 15"),
+                String::from(
+                    "charts[0].series[1].type: async_items_loaded → type: async_loaded_items"
+                ),
+            ]
+        );
+        match &config.charts[0].sources[0] {
+            TimeSeriesSource::PrometheusTimeSeries(prom) => assert_eq!(prom.pull_interval, 15),
+            source => panic!("Unexpected source: {:?}", source),
+        }
+        assert!(matches!(config.charts[0].sources[1], TimeSeriesSource::AsyncLoadedItems(_)));
+        // The migrated value is in the current format, it is not migrated again.
+        let value: Value = serde_yaml::from_str(V1_CONFIG).unwrap();
+        let (migrated, _) = migrate_value(value).unwrap();
+        let (again, notes) = ChartsConfig::migrate(migrated).unwrap();
+        assert!(notes.is_empty());
+        assert_eq!(again, config);
+    }
+
+    #[test]
+    fn it_prints_the_migrated_charts() {
+        let path =
+            std::env::temp_dir().join(format!("chartacritty-migrate-{}.yml", std::process::id()));
+        let yaml = V1_CONFIG.replace('\n', "\n  ");
+        std::fs::write(&path, format!("font:\n  size: 10\ncharts:{}", yaml)).unwrap();
+        let migrated = migrated_charts_yaml(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(migrated.starts_with("font:\n  size: 10\n"), "{}", migrated);
+        assert!(migrated.contains("pull_interval: 15"), "{}", migrated);
+        assert!(migrated.contains("type: async_loaded_items"), "{}", migrated);
+        assert!(!migrated.contains("refresh"), "{}", migrated);
+        let file: Value = serde_yaml::from_str(&migrated).unwrap();
+        let (_, notes) = ChartsConfig::migrate(file["charts"].clone()).unwrap();
+        assert!(notes.is_empty());
+        assert!(migrated_charts_yaml(&path).is_err());
+    }
+
+    #[test]
+    fn it_rejects_unsupported_versions() {
+        let value: Value = serde_yaml::from_str("version: 3\ncharts: []").unwrap();
+        let err = ChartsConfig::migrate(value).unwrap_err();
+        assert_eq!(err, "The charts config version 3 is newer than the supported version 2");
+        let value: Value = serde_yaml::from_str("version: 0\ncharts: []").unwrap();
+        assert!(ChartsConfig::migrate(value).unwrap_err().starts_with("Invalid charts config"));
+        // The current format does not have the old names.
+        let value: Value = serde_yaml::from_str(V1_CONFIG).unwrap();
+        let mut value = value.as_mapping().unwrap().clone();
+        value.insert(Value::from("version"), Value::from(2));
+        assert!(ChartsConfig::migrate(Value::Mapping(value)).is_err());
+    }
+}
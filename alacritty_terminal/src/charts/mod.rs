@@ -18,23 +18,45 @@
 // -- Create a TimeSeries inside the Term itself so that increments can be done synchronously but
 //    send/fetch the updates to the background every half a second or so?
 
+pub mod animation;
+pub mod color;
+pub mod color_ref;
 pub mod config;
+pub mod debug_overlay;
 pub mod decorations;
+pub mod draw_batch;
+pub mod expr;
+#[cfg(any(test, feature = "test-fixtures"))]
+pub mod fixtures;
+pub mod journal;
+pub mod migrate;
+pub mod order;
+pub mod persistence;
+pub mod prelude;
 pub mod prometheus;
+pub mod timefmt;
+pub mod timeline;
 
+use crate::index::Line;
 use crate::term::SizeInfo;
 use crate::vte::ansi::Rgb;
+use animation::{AnimationConfig, SeriesAnimation, TickSchedule, VertexTransition};
+use color_ref::{ChartColor, TerminalPalette};
 use decorations::*;
 use log::*;
-use serde::{de::Error, Deserialize, Deserializer, Serialize};
+use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
 use std::cmp::Ordering;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
 use std::str::FromStr;
-use std::time::UNIX_EPOCH;
+use std::time::{Duration, UNIX_EPOCH};
+use timeline::{RenderAs, TimelineBand, TimelineConfig, TimelineReadout};
 use tracing::{event, span, Level};
 
 /// `MissingValuesPolicy` provides several ways to deal with missing values
 /// when drawing the Metric
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[non_exhaustive]
 pub enum MissingValuesPolicy {
     #[default]
     Zero,
@@ -45,6 +67,9 @@ pub enum MissingValuesPolicy {
     Avg,
     Max,
     Min,
+    /// The missing values are drawn on the line between the filled values around them, the
+    /// nearest filled value is used when there is none on one side
+    Interpolate,
 }
 
 impl MissingValuesPolicy {
@@ -95,18 +120,719 @@ pub enum ValueCollisionPolicy {
     Ignore,
 }
 
+/// `SeriesTransform` is how the values of a source are drawn, the loaded values are kept in the
+/// series and only the values read to draw it are transformed
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeriesTransform {
+    #[default]
+    None,
+    /// The change from the previous filled value, i.e. for gauges
+    Delta,
+    /// The increase per second since the previous filled value, i.e. for counters. A value lower
+    /// than the previous one is a counter reset, the counter is taken as restarted from zero
+    #[serde(rename = "rate")]
+    RatePerSecond,
+}
+
+impl SeriesTransform {
+    pub fn is_none(&self) -> bool {
+        *self == SeriesTransform::None
+    }
+
+    /// `apply` returns the transformed value of a sample at `epoch` from the previous filled
+    /// sample, the first filled sample has no previous one and is missing
+    pub fn apply(self, previous: Option<(u64, f64)>, epoch: u64, value: f64) -> Option<f64> {
+        let (previous_epoch, previous_value) = match (self, previous) {
+            (SeriesTransform::None, _) => return Some(value),
+            (_, None) => return None,
+            (_, Some(previous)) => previous,
+        };
+        match self {
+            SeriesTransform::Delta => Some(value - previous_value),
+            _ => {
+                let gap = epoch.checked_sub(previous_epoch).filter(|gap| *gap > 0)?;
+                let increase = if value < previous_value { value } else { value - previous_value };
+                Some(increase / gap as f64)
+            },
+        }
+    }
+}
+
+/// `YScaleType` is how metric values are mapped to the Y axis of a chart
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum YScaleType {
+    #[default]
+    Linear,
+    /// Values below min_clamp, including zero and negative values, are drawn at min_clamp
+    Log10 { min_clamp: f64 },
+}
+
+impl YScaleType {
+    /// `is_clamped` returns true when the value cannot be drawn as is and is clamped
+    pub fn is_clamped(&self, value: f64) -> bool {
+        match self {
+            YScaleType::Linear => false,
+            YScaleType::Log10 { .. } => value < self.min_clamp(),
+        }
+    }
+
+    /// `min_clamp` returns the lowest value that can be drawn in Log10 mode, it must be positive
+    fn min_clamp(&self) -> f64 {
+        match self {
+            YScaleType::Linear => f64::MIN,
+            YScaleType::Log10 { min_clamp } => min_clamp.max(f64::MIN_POSITIVE),
+        }
+    }
+
+    /// `bounds` returns the values at the bottom and at the top of the chart, the stats are in
+    /// the linear domain. The bottom of linear charts is zero unless there are negative values.
+    pub fn bounds(&self, stats: &TimeSeriesStats) -> (f64, f64) {
+        match self {
+            YScaleType::Linear => (stats.min.min(0f64), stats.max),
+            YScaleType::Log10 { .. } => {
+                let lo = stats.min.max(self.min_clamp());
+                (lo, stats.max.max(lo))
+            },
+        }
+    }
+
+    /// `ticks` returns the values at which the Y axis ticks are placed, these are the decades
+    /// between the bounds in Log10 mode, the bottom and top of the chart otherwise.
+    pub fn ticks(&self, stats: &TimeSeriesStats) -> Vec<f64> {
+        let (lo, hi) = self.bounds(stats);
+        match self {
+            YScaleType::Linear => vec![lo, hi],
+            YScaleType::Log10 { .. } => {
+                let first = lo.log10().ceil() as i32;
+                let last = hi.log10().floor() as i32;
+                (first..=last).map(|decade| 10f64.powi(decade)).collect()
+            },
+        }
+    }
+}
+
+/// The fraction of the nice Y range under which the range of the values must shrink before the
+/// nice bounds are chosen again
+pub const NICE_SCALE_HYSTERESIS: f64 = 0.6f64;
+
+/// `nice_number` rounds a value away from zero to 1, 2 or 5 times a power of ten,
+/// i.e. 4.83 is rounded to 5.0 and -0.034 to -0.05
+pub fn nice_number(value: f64) -> f64 {
+    if value == 0f64 || !value.is_finite() {
+        return value;
+    }
+    let magnitude = 10f64.powf(value.abs().log10().floor());
+    let fraction = value.abs() / magnitude;
+    let nice_fraction = if fraction <= 1f64 {
+        1f64
+    } else if fraction <= 2f64 {
+        2f64
+    } else if fraction <= 5f64 {
+        5f64
+    } else {
+        10f64
+    };
+    (nice_fraction * magnitude).copysign(value)
+}
+
+/// `nice_bounds` expands the bottom and top of a chart to nice numbers
+pub fn nice_bounds(lo: f64, hi: f64) -> (f64, f64) {
+    let nice_lo = if lo < 0f64 { nice_number(lo) } else { lo };
+    (nice_lo, nice_number(hi))
+}
+
+/// `baseline_bounds` returns the bounds that contain `lo` and `hi` with zero at the `baseline`
+/// fraction of the chart height, from the bottom. A chart with only zeros is one unit high.
+pub fn baseline_bounds(baseline: f64, lo: f64, hi: f64) -> (f64, f64) {
+    let baseline = baseline.clamp(0f64, 1f64);
+    let below = if baseline > 0f64 { (-lo).max(0f64) / baseline } else { 0f64 };
+    let above = if baseline < 1f64 { hi.max(0f64) / (1f64 - baseline) } else { 0f64 };
+    let range = below.max(above);
+    let range = if range > 0f64 { range } else { 1f64 };
+    (-baseline * range, (1f64 - baseline) * range)
+}
+
+/// `ChartStyle` is how a chart presents its metrics
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChartStyle {
+    /// The series are drawn as lines
+    #[default]
+    Graph,
+    /// The latest value of the first series is drawn as text, no vertices are generated
+    Numeric,
+}
+
+/// `CapacityPolicy` decides how a chart draws sources with different capacities, each source
+/// spread over the chart by its own capacity would draw a different time range per line.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum CapacityPolicy {
+    /// The capacities must match, a mismatch is reported as an error when the charts are loaded
+    /// and the sources are drawn as in AlignToMax
+    Strict,
+    /// The sources are drawn against the largest capacity of the chart, the samples of the
+    /// smaller ones take the rightmost part of the X axis
+    #[default]
+    AlignToMax,
+    /// Each source is spread over the whole X axis by its own capacity
+    Independent,
+}
+
+/// `SeriesLayout` is how the line series of a chart are drawn together
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum SeriesLayout {
+    /// Each series is drawn at its own values
+    #[default]
+    Overlay,
+    /// Each series is drawn on top of the series before it, at the sum of their filled values at
+    /// each epoch, i.e. the CPU user, system and iowait time
+    Stacked,
+}
+
+/// `ChartOverflow` decides what happens to a chart that does not fit in the window after the
+/// layout. The configured position and dimensions are kept, so a bigger window restores them.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChartOverflow {
+    /// The chart is moved inward, it is shrunk when it is bigger than the window
+    #[default]
+    Shift,
+    /// The chart is shrunk proportionally to end at the edge of the window
+    Shrink,
+    /// The chart is not drawn
+    Hide,
+}
+
+impl ChartOverflow {
+    /// `resolve` returns the position and dimensions, in window pixels, of a chart clamped to the
+    /// terminal size, None when the chart is hidden. Windows that have not been sized yet keep
+    /// the chart as configured.
+    pub fn resolve(
+        self,
+        position: Value2D,
+        dimensions: Value2D,
+        term_size: &SizeInfo,
+    ) -> Option<(Value2D, Value2D)> {
+        // Charts start after the horizontal padding and are drawn up from the bottom.
+        let available = Value2D { x: term_size.width - term_size.padding_x, y: term_size.height };
+        if available.x <= 0. || available.y <= 0. {
+            return Some((position, dimensions));
+        }
+        let fits_x = dimensions.x <= available.x;
+        let fits_y = dimensions.y <= available.y;
+        if position.x >= 0. && position.x + dimensions.x <= available.x && fits_y {
+            return Some((position, dimensions));
+        }
+        let x = match self {
+            ChartOverflow::Hide => return None,
+            ChartOverflow::Shift if fits_x && fits_y => {
+                let x = position.x.clamp(0., available.x - dimensions.x);
+                return Some((Value2D { x, ..position }, dimensions));
+            },
+            ChartOverflow::Shift => 0.,
+            ChartOverflow::Shrink => position.x.clamp(0., available.x),
+        };
+        let factor = ((available.x - x) / dimensions.x).min(available.y / dimensions.y).min(1.);
+        if factor <= 0. || !factor.is_finite() {
+            return None;
+        }
+        Some((Value2D { x, ..position }, dimensions.scaled(factor)))
+    }
+}
+
+/// `PaletteMode` selects the colors of the series that do not configure one, the modes other
+/// than Default use curated palettes that stay distinguishable with a color vision deficiency.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum PaletteMode {
+    /// The series keep their configured colors
+    #[default]
+    Default,
+    /// Saturated colors that stand out from a dark background
+    HighContrast,
+    /// Safe for a reduced sensitivity to green, the Okabe-Ito palette
+    Deuteranopia,
+    /// Safe for a reduced sensitivity to red, the IBM design palette
+    Protanopia,
+    /// Safe for a reduced sensitivity to blue, avoids blue and yellow pairs
+    Tritanopia,
+}
+
+impl PaletteMode {
+    /// `palette` returns the colors assigned in order to the series without a color, None in
+    /// Default mode
+    pub fn palette(self) -> Option<&'static [Rgb]> {
+        const HIGH_CONTRAST: [Rgb; 6] = [
+            Rgb { r: 0xff, g: 0xff, b: 0xff },
+            Rgb { r: 0xff, g: 0xff, b: 0x00 },
+            Rgb { r: 0x00, g: 0xff, b: 0xff },
+            Rgb { r: 0xff, g: 0x00, b: 0xff },
+            Rgb { r: 0xff, g: 0x80, b: 0x00 },
+            Rgb { r: 0x80, g: 0xff, b: 0x00 },
+        ];
+        const OKABE_ITO: [Rgb; 7] = [
+            Rgb { r: 0xe6, g: 0x9f, b: 0x00 },
+            Rgb { r: 0x56, g: 0xb4, b: 0xe9 },
+            Rgb { r: 0x00, g: 0x9e, b: 0x73 },
+            Rgb { r: 0xf0, g: 0xe4, b: 0x42 },
+            Rgb { r: 0x00, g: 0x72, b: 0xb2 },
+            Rgb { r: 0xd5, g: 0x5e, b: 0x00 },
+            Rgb { r: 0xcc, g: 0x79, b: 0xa7 },
+        ];
+        const IBM: [Rgb; 5] = [
+            Rgb { r: 0x64, g: 0x8f, b: 0xff },
+            Rgb { r: 0xfe, g: 0x61, b: 0x00 },
+            Rgb { r: 0x78, g: 0x5e, b: 0xf0 },
+            Rgb { r: 0xff, g: 0xb0, b: 0x00 },
+            Rgb { r: 0xdc, g: 0x26, b: 0x7f },
+        ];
+        const TRITAN: [Rgb; 5] = [
+            Rgb { r: 0xdc, g: 0x32, b: 0x20 },
+            Rgb { r: 0x00, g: 0x99, b: 0x88 },
+            Rgb { r: 0xee, g: 0x33, b: 0x77 },
+            Rgb { r: 0xbb, g: 0xbb, b: 0xbb },
+            Rgb { r: 0x88, g: 0x22, b: 0x55 },
+        ];
+        match self {
+            PaletteMode::Default => None,
+            PaletteMode::HighContrast => Some(&HIGH_CONTRAST),
+            PaletteMode::Deuteranopia => Some(&OKABE_ITO),
+            PaletteMode::Protanopia => Some(&IBM),
+            PaletteMode::Tritanopia => Some(&TRITAN),
+        }
+    }
+
+    /// `series_color` returns the color drawn for the series at `series_idx`, a configured color
+    /// always wins. The series without a color are left as the default Rgb by serde.
+    pub fn series_color(self, configured: Rgb, series_idx: usize) -> Rgb {
+        match self.palette() {
+            Some(palette) if configured == Rgb::default() => palette[series_idx % palette.len()],
+            _ => configured,
+        }
+    }
+}
+
+/// The (drawn, gap) pixels of a dashed line
+pub const DASHED_LINE_PIXELS: (f32, f32) = (6f32, 4f32);
+
+/// The (drawn, gap) pixels of a dotted line
+pub const DOTTED_LINE_PIXELS: (f32, f32) = (2f32, 4f32);
+
+/// The dashes shorter than these pixels are not drawn
+const DASH_SLIVER_PIXELS: f32 = 0.001f32;
+
+/// `LinePattern` is how the line of a series is drawn, so that series can be told apart without
+/// relying on their color
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LinePattern {
+    #[default]
+    Solid,
+    Dashed,
+    Dotted,
+}
+
+impl LinePattern {
+    /// `dash` returns the (drawn, gap) pixels of the pattern, None for a solid line
+    pub fn dash(self) -> Option<(f32, f32)> {
+        match self {
+            LinePattern::Solid => None,
+            LinePattern::Dashed => Some(DASHED_LINE_PIXELS),
+            LinePattern::Dotted => Some(DOTTED_LINE_PIXELS),
+        }
+    }
+
+    /// `alerted` returns the pattern of a series while one of its alerts is triggered
+    pub fn alerted(self) -> LinePattern {
+        match self {
+            LinePattern::Dotted => LinePattern::Dashed,
+            _ => LinePattern::Dotted,
+        }
+    }
+
+    /// `draw_mode` returns the primitive for the vertices of `segment_vertices`
+    pub fn draw_mode(self) -> draw_batch::DrawMode {
+        match self {
+            LinePattern::Solid => draw_batch::DrawMode::LineStrip,
+            _ => draw_batch::DrawMode::Lines,
+        }
+    }
+
+    /// `segment_vertices` splits a line strip of x,y vertices into the pairs of vertices of each
+    /// dash, the dashes continue across the vertices of the strip. The pixels_per_ndc convert
+    /// the x,y distances to pixels. A solid line is returned as is.
+    pub fn segment_vertices(self, strip: &[f32], pixels_per_ndc: (f32, f32)) -> Vec<f32> {
+        let (drawn, gap) = match self.dash() {
+            Some(dash) => dash,
+            None => return strip.to_vec(),
+        };
+        let period = drawn + gap;
+        let mut res = vec![];
+        // The pixels into the current dash period
+        let mut phase = 0f32;
+        for segment in strip.windows(4).step_by(2) {
+            let (x1, y1, x2, y2) = (segment[0], segment[1], segment[2], segment[3]);
+            let length = ((x2 - x1) * pixels_per_ndc.0).hypot((y2 - y1) * pixels_per_ndc.1);
+            if length.is_nan() || length <= 0f32 {
+                continue;
+            }
+            let mut start = 0f32;
+            while start < length {
+                let is_drawn = phase < drawn;
+                let end = if is_drawn { start + drawn - phase } else { start + period - phase };
+                let end = end.min(length);
+                // Rounding may leave slivers of a dash at the end of a period.
+                if is_drawn && end - start > DASH_SLIVER_PIXELS {
+                    let (from, to) = (start / length, end / length);
+                    res.extend_from_slice(&[
+                        x1 + (x2 - x1) * from,
+                        y1 + (y2 - y1) * from,
+                        x1 + (x2 - x1) * to,
+                        y1 + (y2 - y1) * to,
+                    ]);
+                }
+                phase = (phase + end - start) % period;
+                start = end;
+            }
+        }
+        res
+    }
+}
+
+/// The pixels across a smooth line, the alpha fades from its center to both edges
+pub const SMOOTH_LINE_PIXELS: f32 = 3f32;
+
+/// The longest offset of a smooth line corner, relative to half of SMOOTH_LINE_PIXELS, so that
+/// the sharp corners do not grow long spikes
+const SMOOTH_MITER_LIMIT: f32 = 2f32;
+
+/// `smooth_strip_vertices` expands a line strip of x,y vertices into a triangle strip
+/// SMOOTH_LINE_PIXELS wide made of x,y,fade vertices. The fade is the alpha of each vertex
+/// relative to the series alpha: 1 along the line and 0 on its edges, so that the edges blend
+/// into the background. Each point of the line has a left, center and right vertex, the strip
+/// runs along the left half of the line and comes back along the right half, a line of n points
+/// has 4n - 1 vertices. The corners are mitered and the pixels_per_ndc convert the offsets of
+/// the edges from pixels.
+pub fn smooth_strip_vertices(strip: &[f32], pixels_per_ndc: (f32, f32)) -> Vec<f32> {
+    let points: Vec<(f32, f32)> = strip.chunks_exact(2).map(|point| (point[0], point[1])).collect();
+    if points.len() < 2 || pixels_per_ndc.0 <= 0f32 || pixels_per_ndc.1 <= 0f32 {
+        return vec![];
+    }
+    // The unit normal of each segment in pixels, a collapsed segment keeps the previous normal.
+    let mut normals: Vec<(f32, f32)> = Vec::with_capacity(points.len() - 1);
+    for segment in points.windows(2) {
+        let dx = (segment[1].0 - segment[0].0) * pixels_per_ndc.0;
+        let dy = (segment[1].1 - segment[0].1) * pixels_per_ndc.1;
+        let length = dx.hypot(dy);
+        let normal = if length > 0f32 {
+            (-dy / length, dx / length)
+        } else {
+            normals.last().copied().unwrap_or((0f32, 1f32))
+        };
+        normals.push(normal);
+    }
+    let half_width = SMOOTH_LINE_PIXELS / 2f32;
+    // The offset in normalized device coordinates from each point to its left edge.
+    let offsets: Vec<(f32, f32)> = (0..points.len())
+        .map(|idx| {
+            let after = normals[idx.min(normals.len() - 1)];
+            let before = normals[idx.saturating_sub(1)];
+            let (mx, my) = (before.0 + after.0, before.1 + after.1);
+            let miter_length = mx.hypot(my);
+            let (mx, my) = if miter_length > 0f32 {
+                (mx / miter_length, my / miter_length)
+            } else {
+                // The line turns back on itself.
+                after
+            };
+            let cos = mx * after.0 + my * after.1;
+            let length = (half_width / cos.max(f32::EPSILON)).min(half_width * SMOOTH_MITER_LIMIT);
+            (mx * length / pixels_per_ndc.0, my * length / pixels_per_ndc.1)
+        })
+        .collect();
+    let mut res = Vec::with_capacity((points.len() * 4 - 1) * 3);
+    for ((x, y), (ox, oy)) in points.iter().zip(offsets.iter()) {
+        res.extend_from_slice(&[x + ox, y + oy, 0f32, *x, *y, 1f32]);
+    }
+    let (x, y) = points[points.len() - 1];
+    let (ox, oy) = offsets[points.len() - 1];
+    res.extend_from_slice(&[x - ox, y - oy, 0f32]);
+    for ((x, y), (ox, oy)) in points.iter().zip(offsets.iter()).rev().skip(1) {
+        res.extend_from_slice(&[*x, *y, 1f32, x - ox, y - oy, 0f32]);
+    }
+    res
+}
+
+/// `join_strips` concatenates triangle strips of x,y,fade vertices into a single one, the
+/// strips are joined by repeating the last vertex of a strip and the first of the next, the
+/// triangles in between have no area.
+pub fn join_strips<I: IntoIterator<Item = Vec<f32>>>(strips: I) -> Vec<f32> {
+    let mut res: Vec<f32> = vec![];
+    for strip in strips.into_iter().filter(|strip| strip.len() >= 3) {
+        if res.len() >= 3 {
+            let last = res[res.len() - 3..].to_vec();
+            res.extend_from_slice(&last);
+            res.extend_from_slice(&strip[..3]);
+        }
+        res.extend_from_slice(&strip);
+    }
+    res
+}
+
+/// `downsample_polyline` reduces a line strip of x,y vertices to at most `max_points` vertices,
+/// the first and last vertices are kept and the others are split into buckets that keep their
+/// lowest and highest vertex in order, so that the peaks are not lost. A strip within
+/// `max_points` or a `max_points` under 4 is returned as is.
+pub fn downsample_polyline(strip: &[f32], max_points: usize) -> Vec<f32> {
+    let points = strip.len() / 2;
+    if points <= max_points || max_points < 4 {
+        return strip.to_vec();
+    }
+    let inner = &strip[2..(points - 1) * 2];
+    let inner_points = points - 2;
+    let buckets = (max_points - 2) / 2;
+    let mut res = Vec::with_capacity(max_points * 2);
+    res.extend_from_slice(&strip[..2]);
+    for bucket in 0..buckets {
+        let from = bucket * inner_points / buckets;
+        let to = (bucket + 1) * inner_points / buckets;
+        let chunk = &inner[from * 2..to * 2];
+        let (mut lowest, mut highest) = (0usize, 0usize);
+        for (idx, point) in chunk.chunks_exact(2).enumerate() {
+            if point[1] < chunk[lowest * 2 + 1] {
+                lowest = idx;
+            }
+            if point[1] > chunk[highest * 2 + 1] {
+                highest = idx;
+            }
+        }
+        let (first, second) = (lowest.min(highest), lowest.max(highest));
+        res.extend_from_slice(&chunk[first * 2..first * 2 + 2]);
+        if second != first {
+            res.extend_from_slice(&chunk[second * 2..second * 2 + 2]);
+        }
+    }
+    res.extend_from_slice(&strip[(points - 1) * 2..]);
+    res
+}
+
+/// `SeriesStyle` is how a series is drawn once the palette mode and its alerts are applied
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct SeriesStyle {
+    pub color: Rgb,
+    pub pattern: LinePattern,
+
+    /// The vertices are a triangle strip of x,y,fade vertices with faded edges
+    pub smooth: bool,
+
+    /// The vertices are the triangles of a timeline band, each with its x,y,r,g,b,a
+    pub timeline: bool,
+
+    /// The vertices are the pairs of the bottom and the top of a bar for each sample
+    pub bars: bool,
+
+    /// The vertices are a point for each sample
+    pub points: bool,
+
+    /// The series has a live edge segment drawn after its vertices
+    pub live_edge: bool,
+}
+
+impl SeriesStyle {
+    /// `draw_mode` returns the primitive for the vertices of the series
+    pub fn draw_mode(self) -> draw_batch::DrawMode {
+        if self.timeline {
+            draw_batch::DrawMode::Triangles
+        } else if self.bars {
+            draw_batch::DrawMode::Lines
+        } else if self.points {
+            draw_batch::DrawMode::Points
+        } else if self.smooth {
+            draw_batch::DrawMode::TriangleStrip
+        } else {
+            self.pattern.draw_mode()
+        }
+    }
+}
+
+/// `SmallMultiplesConfig` splits a chart into a grid of cells, each series of the chart is drawn
+/// in its own cell instead of overlaying all of them.
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub struct SmallMultiplesConfig {
+    /// The number of cells of each row
+    pub columns: usize,
+
+    /// The dimensions of each cell, if unspecified the chart dimensions are divided evenly
+    #[serde(default)]
+    pub cell_dimensions: Option<Value2D>,
+
+    /// Draw all the cells with the merged stats of the chart, otherwise each cell is scaled to
+    /// the stats of its series
+    #[serde(default)]
+    pub shared_y_scale: bool,
+}
+
+impl SmallMultiplesConfig {
+    /// `cells` splits the chart `area` into `count` cells, in rows of `columns` from the top left.
+    /// The grid is anchored to the bottom of the chart, as the charts are drawn up from the bottom
+    /// of the window.
+    pub fn cells(
+        &self,
+        count: usize,
+        area: PixelRect,
+        dpr: f32,
+        term_size: &SizeInfo,
+    ) -> Vec<ChartCell> {
+        if count == 0 {
+            return vec![];
+        }
+        let columns = self.columns.clamp(1, count);
+        let rows = count.div_ceil(columns);
+        let dimensions = match self.cell_dimensions {
+            Some(dimensions) => dimensions.scaled(dpr),
+            None => Value2D { x: area.width / columns as f32, y: area.height / rows as f32 },
+        };
+        let grid_top = area.y + area.height - rows as f32 * dimensions.y;
+        (0..count)
+            .map(|idx| {
+                let (column, row) = (idx % columns, idx / columns);
+                let rect = PixelRect {
+                    x: area.x + column as f32 * dimensions.x,
+                    y: grid_top + row as f32 * dimensions.y,
+                    width: dimensions.x,
+                    height: dimensions.y,
+                };
+                ChartCell { rect, ndc_rect: NdcRect::from_pixel_rect(&rect, term_size) }
+            })
+            .collect()
+    }
+}
+
+/// `ChartCell` is the area of a series of a chart split into small multiples
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct ChartCell {
+    /// The area of the cell in window pixels
+    pub rect: PixelRect,
+
+    /// The area of the cell in normalized device coordinates
+    pub ndc_rect: NdcRect,
+}
+
+/// `Trend` is the direction of the latest value of a series versus the previous sample
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Trend {
+    Up,
+    Down,
+    Flat,
+}
+
+impl Trend {
+    /// `between` returns the trend from `previous` to `latest`, differences up to `epsilon` are
+    /// considered flat.
+    pub fn between(previous: f64, latest: f64, epsilon: f64) -> Trend {
+        let difference = latest - previous;
+        if difference.abs() <= epsilon {
+            Trend::Flat
+        } else if difference > 0f64 {
+            Trend::Up
+        } else {
+            Trend::Down
+        }
+    }
+
+    /// `symbol` returns the character drawn next to a numeric readout
+    pub fn symbol(&self) -> char {
+        match self {
+            Trend::Up => '↑',
+            Trend::Down => '↓',
+            Trend::Flat => '→',
+        }
+    }
+}
+
+/// The default difference between samples that is still considered a flat trend
+pub const DEFAULT_TREND_EPSILON: f64 = 0.001f64;
+
+/// The number of samples drawn in the sparkline of a numeric readout
+pub const NUMERIC_SPARKLINE_WIDTH: usize = 16usize;
+
+/// `NumericReadout` is the latest value of a chart in Numeric style, ready to be drawn as text
+#[derive(Debug, Clone, PartialEq)]
+pub struct NumericReadout {
+    /// The chart area in window pixels
+    pub rect: PixelRect,
+
+    /// The formatted latest value
+    pub value: String,
+
+    /// The direction of the latest value versus the previous sample
+    pub trend: Trend,
+
+    /// The color of the series, or of the first alert triggered by the latest value
+    pub color: Rgb,
+
+    /// The most recent samples, drawn underneath the value
+    pub sparkline: String,
+}
+
+/// `format_readout_value` formats a value for a numeric readout, up to two decimals are kept
+/// and very large or very small values use scientific notation.
+pub fn format_readout_value(value: f64) -> String {
+    let magnitude = value.abs();
+    if magnitude >= 1e6 || (magnitude > 0f64 && magnitude < 1e-2) {
+        return format!("{:.2e}", value);
+    }
+    let formatted = format!("{:.2}", value);
+    let formatted = formatted.trim_end_matches('0').trim_end_matches('.');
+    if formatted == "-0" {
+        String::from("0")
+    } else {
+        formatted.to_string()
+    }
+}
+
+/// `sparkline_string` draws the values with block characters, one per value, from the lowest
+/// to the highest of the values.
+pub fn sparkline_string(values: &[f64]) -> String {
+    const BLOCKS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+    let min = values.iter().copied().fold(f64::INFINITY, f64::min);
+    let max = values.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let range = max - min;
+    values
+        .iter()
+        .map(|value| {
+            if range > 0f64 {
+                let level = ((value - min) / range * (BLOCKS.len() - 1) as f64).round();
+                BLOCKS[level as usize]
+            } else {
+                BLOCKS[0]
+            }
+        })
+        .collect()
+}
+
 /// `TimeSeriesStats` contains statistics about the current TimeSeries
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Copy)]
 pub struct TimeSeriesStats {
+    #[serde(serialize_with = "ser_f64_compact")]
     max: f64,
+    #[serde(serialize_with = "ser_f64_compact")]
     min: f64,
+    #[serde(serialize_with = "ser_f64_compact")]
     avg: f64, // Calculation may lead to overflow
+    #[serde(serialize_with = "ser_f64_compact")]
     first: f64,
+    #[serde(serialize_with = "ser_f64_compact")]
     last: f64,
     count: usize,
+    #[serde(serialize_with = "ser_f64_compact")]
     sum: f64, // May overflow
     last_epoch: u64,
     is_dirty: bool,
+    /// The entries of the active window without a value
+    #[serde(default)]
+    missing_count: usize,
+    /// The population standard deviation of the filled values of the active window
+    #[serde(default, serialize_with = "ser_f64_compact")]
+    stddev: f64,
 }
 
 impl Default for TimeSeriesStats {
@@ -121,14 +847,184 @@ impl Default for TimeSeriesStats {
             sum: 0f64,
             last_epoch: 0u64,
             is_dirty: false,
+            missing_count: 0usize,
+            stddev: 0f64,
+        }
+    }
+}
+
+/// `stddev_of` returns the population standard deviation of `count` values from the sum of their
+/// squared distances to their mean, 0 without values
+fn stddev_of(m2: f64, count: usize) -> f64 {
+    if count == 0 {
+        return 0f64;
+    }
+    // The removals of the running stats may leave a tiny negative sum.
+    (m2.max(0f64) / count as f64).sqrt()
+}
+
+/// `merge_moments` combines the count, mean and sum of the squared distances to the mean of two
+/// sets of values into the ones of their union, the sets without values are skipped.
+fn merge_moments(a: (usize, f64, f64), b: (usize, f64, f64)) -> (usize, f64, f64) {
+    let ((count_a, mean_a, m2_a), (count_b, mean_b, m2_b)) = (a, b);
+    if count_b == 0 {
+        return a;
+    }
+    if count_a == 0 {
+        return b;
+    }
+    let count = count_a + count_b;
+    let delta = mean_b - mean_a;
+    let mean = mean_a + delta * count_b as f64 / count as f64;
+    let m2 = m2_a + m2_b + delta * delta * (count_a * count_b) as f64 / count as f64;
+    (count, mean, m2)
+}
+
+/// `StatsMode` is how the stats of a TimeSeries are calculated
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum StatsMode {
+    /// The sum, count, min and max follow the upserts, the active window is only rescanned when
+    /// an evicted or overwritten value was the min or the max
+    #[default]
+    Incremental,
+    /// The active window is rescanned on each calculation
+    FullRescan,
+}
+
+/// `RunningStats` keeps the sum, count, min and max of the filled values of the active window as
+/// the values are added and removed, so that the stats of a series are not recalculated from the
+/// whole window on each refresh.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RunningStats {
+    sum: f64,
+    count: usize,
+    max: f64,
+    min: f64,
+    /// The mean and the sum of the squared distances to it, kept with Welford's algorithm
+    mean: f64,
+    m2: f64,
+    /// Whether the running values follow the active window, otherwise the next calculation of the
+    /// stats rescans it
+    valid: bool,
+    /// The values removed since the last rescan, the sum drifts with each of them
+    removed: usize,
+}
+
+impl Default for RunningStats {
+    fn default() -> RunningStats {
+        RunningStats {
+            sum: 0f64,
+            count: 0usize,
+            max: f64::MIN,
+            min: f64::MAX,
+            mean: 0f64,
+            m2: 0f64,
+            valid: false,
+            removed: 0,
+        }
+    }
+}
+
+impl RunningStats {
+    /// `add` accounts for a value that entered the active window
+    fn add(&mut self, value: Option<f64>) {
+        if let Some(value) = value {
+            self.sum += value;
+            self.count += 1;
+            self.max = self.max.max(value);
+            self.min = self.min.min(value);
+            let delta = value - self.mean;
+            self.mean += delta / self.count as f64;
+            self.m2 += delta * (value - self.mean);
+        }
+    }
+
+    /// `remove` accounts for a value that left the active window, when it was the min or the max
+    /// the next extremum is unknown and the window needs a rescan
+    fn remove(&mut self, value: Option<f64>) {
+        if let Some(value) = value {
+            self.sum -= value;
+            self.count = self.count.saturating_sub(1);
+            self.removed += 1;
+            if value >= self.max || value <= self.min {
+                self.valid = false;
+            }
+            if self.count == 0 {
+                self.mean = 0f64;
+                self.m2 = 0f64;
+            } else {
+                let delta = value - self.mean;
+                self.mean -= delta / self.count as f64;
+                self.m2 -= delta * (value - self.mean);
+            }
         }
     }
+
+    /// `replace` accounts for a value of the active window that changed, an extremum that grows
+    /// further, i.e. a counter incremented at the newest epoch, does not need a rescan
+    fn replace(&mut self, old: Option<f64>, new: Option<f64>) {
+        match (old, new) {
+            (Some(old), Some(new)) => {
+                let keeps_max = old < self.max || new >= old;
+                let keeps_min = old > self.min || new <= old;
+                if !keeps_max || !keeps_min {
+                    self.valid = false;
+                }
+                self.sum += new - old;
+                self.removed += 1;
+                self.max = self.max.max(new);
+                self.min = self.min.min(new);
+                let mean = self.mean + (new - old) / self.count.max(1) as f64;
+                self.m2 += (new - old) * (new - mean + old - self.mean);
+                self.mean = mean;
+            },
+            (old, new) => {
+                self.remove(old);
+                self.add(new);
+            },
+        }
+    }
+
+    /// `invalidate` forces a rescan on the next calculation, for the changes of the active window
+    /// that are not tracked
+    fn invalidate(&mut self) {
+        self.valid = false;
+    }
+
+    /// `is_valid` returns true when the running values can be used for a window of `capacity`,
+    /// the window is rescanned once as many values as it holds were removed to reset the drift of
+    /// the sum, which keeps the rescans amortized
+    fn is_valid(&self, capacity: usize) -> bool {
+        self.valid && self.removed < capacity
+    }
+}
+
+/// `same_stats` returns true when the stats are the same, the sums may drift by rounding errors
+/// when they are kept incrementally
+#[cfg(test)]
+fn same_stats(a: &TimeSeriesStats, b: &TimeSeriesStats) -> bool {
+    let close = |a: f64, b: f64| {
+        a == b || (a.is_nan() && b.is_nan()) || (a - b).abs() <= 1e-9 * a.abs().max(b.abs()).max(1.)
+    };
+    close(a.max, b.max)
+        && close(a.min, b.min)
+        && close(a.avg, b.avg)
+        && close(a.first, b.first)
+        && close(a.last, b.last)
+        && close(a.sum, b.sum)
+        && a.count == b.count
+        && a.last_epoch == b.last_epoch
+        && a.missing_count == b.missing_count
+        // The squared distances drift more than the sums, they are compared in the scale of the
+        // squared values.
+        && (a.stddev.powi(2) - b.stddev.powi(2)).abs()
+            <= 1e-9 * a.max.abs().max(a.min.abs()).max(1.).powi(2)
 }
 
 /// This enum is tied to the upsert() function and aids in a bug finding for synchronicity loss.
 /// TODO: Remove later
 #[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq, Eq)]
-pub enum UpsertType {
+pub(crate) enum UpsertType {
     #[default]
     Empty,
     TooOld,
@@ -146,7 +1042,10 @@ pub enum UpsertType {
 /// time has passed without metrics, the vecotr is allowed to shrink without
 /// memory rellocation, this is achieved by using two indexes for the first
 /// and last item.
+/// Missing fields in the configuration are taken from the default TimeSeries, it is written in
+/// the form of a TimeSeriesConfig.
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(from = "TimeSeriesConfig", into = "TimeSeriesConfig")]
 pub struct TimeSeries {
     /// Capture events through time
     /// Contains one entry per time unit
@@ -155,6 +1054,10 @@ pub struct TimeSeries {
     /// Number of items request to the metric store
     pub metrics_capacity: usize,
 
+    /// The milliseconds between two samples, the epochs are in milliseconds when it is under a
+    /// second and in seconds otherwise
+    pub granularity_ms: u64,
+
     /// Stats for the TimeSeries
     pub stats: TimeSeriesStats,
 
@@ -173,58 +1076,463 @@ pub struct TimeSeries {
     pub active_items: usize,
 
     /// The previous to current metric snapshot, for debug purposes
-    /// TODO: drop when upsert is sttable
+    #[cfg(feature = "upsert-debug")]
     pub prev_snapshot: Vec<(u64, Option<f64>)>,
 
-    /// The previous value inserted
-    /// TODO: drop when upsert is stable
+    /// The previous value inserted, for debug purposes
+    #[cfg(feature = "upsert-debug")]
     pub prev_value: (u64, Option<f64>),
 
-    /// The last upsert type
-    /// TODO: drop when upsert is stable
-    pub upsert_type: UpsertType,
-}
+    /// The last upsert type, for debug purposes
+    #[cfg(feature = "upsert-debug")]
+    pub(crate) upsert_type: UpsertType,
 
-/// `IterTimeSeries` provides the Iterator Trait for TimeSeries metrics.
-/// The state for the iteration is held en "pos" field. The "current_item" is
-/// used to determine if further iterations on the circular buffer is needed.
-pub struct IterTimeSeries<'a> {
-    /// The reference to the TimeSeries struct to iterate over.
-    inner: &'a TimeSeries,
-    /// The current position state
-    pos: usize,
-    /// The current item number, to be compared with the active_items
-    current_item: usize,
-}
+    /// When enabled, the capacity follows the chart width, one sample per horizontal pixel
+    pub adaptive_capacity: bool,
 
-/// `ManualTimeSeries` is a basic time series that we feed ourselves, used for internal counters
-/// for example keyboard input, output newlines, loaded items count.
-#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
-pub struct ManualTimeSeries {
-    /// The name of the ManualTimeSeries
-    pub name: String,
+    /// The maximum capacity the series can grow to when adaptive_capacity is enabled
+    pub max_capacity: usize,
+    /// The lowest value an Increment or Decrement collision may resolve to, i.e. to keep
+    /// counters from going negative
+    pub collision_floor: Option<f64>,
 
-    /// The TimeSeries that contains the data
-    #[serde(default)]
-    pub series: TimeSeries,
+    /// The highest value an Increment or Decrement collision may resolve to, i.e. to guard
+    /// against runaway sums from duplicated batches
+    pub collision_ceiling: Option<f64>,
 
-    /// The granularity to store
-    #[serde(default)]
-    pub granularity: u64,
+    /// How the stats are calculated, FullRescan rescans the active window each time
+    pub stats_mode: StatsMode,
 
-    /// The color of the TimeSeries
-    #[serde(deserialize_with = "deserialize_rgb_from_str", default)]
-    pub color: Rgb,
+    /// The sum, count, min and max of the active window kept by the upserts
+    pub running_stats: RunningStats,
 
-    /// The transparency of the TimeSeries
-    #[serde(default)]
-    pub alpha: f32,
+    /// The epochs of the None values upserted by `pad_to_epoch` to keep the charts at the same
+    /// latest epoch, they are not gaps of the source
+    pub sync_padding: BTreeSet<u64>,
 }
 
-pub fn deserialize_rgb_from_str<'de, D>(de: D) -> Result<Rgb, D::Error>
-where
-    D: Deserializer<'de>,
-{
+/// `TimeSeriesConfig` is the form of a TimeSeries in the YAML and in the dumps. The active samples
+/// are written from the epoch of the oldest one as runs of the same value, the epochs of the
+/// active window are consecutive. The circular buffer and the debug fields are not written.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(default)]
+struct TimeSeriesConfig {
+    metrics_capacity: usize,
+    granularity_ms: u64,
+    stats: TimeSeriesStats,
+    collision_policy: ValueCollisionPolicy,
+    missing_values_policy: MissingValuesPolicy,
+    adaptive_capacity: bool,
+    max_capacity: usize,
+    collision_floor: Option<f64>,
+    collision_ceiling: Option<f64>,
+    stats_mode: StatsMode,
+    /// The epoch of the oldest active sample
+    #[serde(skip_serializing_if = "Option::is_none")]
+    first_epoch: Option<u64>,
+    /// The active values from first_epoch, one per epoch step, as a value and the number of
+    /// consecutive samples with it
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    values: Vec<(Option<f64>, usize)>,
+}
+
+impl Default for TimeSeriesConfig {
+    fn default() -> TimeSeriesConfig {
+        TimeSeries::default().into()
+    }
+}
+
+impl From<TimeSeries> for TimeSeriesConfig {
+    fn from(series: TimeSeries) -> TimeSeriesConfig {
+        let mut values: Vec<(Option<f64>, usize)> = vec![];
+        for (_, value) in series.iter() {
+            match values.last_mut() {
+                Some((run_value, count)) if *run_value == *value => *count += 1,
+                _ => values.push((*value, 1)),
+            }
+        }
+        let first_epoch = series.oldest_epoch();
+        TimeSeriesConfig {
+            metrics_capacity: series.metrics_capacity,
+            granularity_ms: series.granularity_ms,
+            stats: series.stats,
+            collision_policy: series.collision_policy,
+            missing_values_policy: series.missing_values_policy,
+            adaptive_capacity: series.adaptive_capacity,
+            max_capacity: series.max_capacity,
+            collision_floor: series.collision_floor,
+            collision_ceiling: series.collision_ceiling,
+            stats_mode: series.stats_mode,
+            first_epoch,
+            values,
+        }
+    }
+}
+
+impl From<TimeSeriesConfig> for TimeSeries {
+    fn from(config: TimeSeriesConfig) -> TimeSeries {
+        let mut series = TimeSeries {
+            metrics_capacity: config.metrics_capacity,
+            granularity_ms: config.granularity_ms,
+            stats: config.stats,
+            collision_policy: config.collision_policy,
+            missing_values_policy: config.missing_values_policy,
+            adaptive_capacity: config.adaptive_capacity,
+            max_capacity: config.max_capacity,
+            collision_floor: config.collision_floor,
+            collision_ceiling: config.collision_ceiling,
+            stats_mode: config.stats_mode,
+            ..TimeSeries::default()
+        };
+        if let Some(first_epoch) = config.first_epoch {
+            let step = series.epoch_step();
+            let values = config
+                .values
+                .into_iter()
+                .flat_map(|(value, count)| std::iter::repeat(value).take(count));
+            let metrics = (0..).map(|idx| first_epoch + idx * step).zip(values);
+            series.metrics.extend(metrics);
+        }
+        // More values than the capacity keep the newest ones.
+        let excess = series.metrics.len().saturating_sub(series.metrics_capacity());
+        series.metrics.drain(..excess);
+        series.active_items = series.metrics.len();
+        series
+    }
+}
+
+/// The maximum number of samples an adaptive series can hold, one hour of metrics.
+pub const DEFAULT_MAX_CAPACITY: usize = 3600usize;
+
+/// The default granularity of the series, one sample per second
+pub const DEFAULT_GRANULARITY_MS: u64 = 1000u64;
+
+/// The bytes used by a sample of a TimeSeries
+pub const SAMPLE_BYTES: usize = std::mem::size_of::<(u64, Option<f64>)>();
+
+/// The bytes used by the vertex of a sample, its X and Y coordinates
+pub const VERTEX_BYTES: usize = 2 * std::mem::size_of::<f32>();
+
+/// The capacity the memory budget does not reduce a series under, one minute of metrics.
+pub const MIN_BUDGET_CAPACITY: usize = 60usize;
+
+/// `capacity_for_width` returns the number of samples needed to draw one sample per horizontal
+/// pixel of the chart, bounded by max_capacity
+pub fn capacity_for_width(width: f32, max_capacity: usize) -> usize {
+    if width.is_nan() || width < 1f32 {
+        return 1usize;
+    }
+    (width.floor() as usize).clamp(1usize, max_capacity.max(1usize))
+}
+
+/// The physical pixels of a chart that are kept for its series, the space of the decorations is
+/// reduced to leave them
+pub const MIN_DRAWABLE_WIDTH: f32 = 1f32;
+
+/// The fraction of the chart width the decorations may take before their space is scaled down
+pub const DEFAULT_MAX_DECORATIONS_FRACTION: f32 = 0.5f32;
+
+/// The fraction of the alpha of a series its live edge is drawn with
+pub const LIVE_EDGE_ALPHA: f32 = 0.5f32;
+
+/// `fit_decorations_space` returns the space of the decorations that fits in a chart of
+/// `chart_width` pixels: the requested space is scaled down to `max_fraction` of the width and
+/// leaves at least MIN_DRAWABLE_WIDTH for the series. None when the chart is narrower than
+/// MIN_DRAWABLE_WIDTH, it is too small to draw.
+pub fn fit_decorations_space(
+    chart_width: f32,
+    decorations_space: f32,
+    max_fraction: f32,
+) -> Option<f32> {
+    if chart_width.is_nan() || chart_width < MIN_DRAWABLE_WIDTH {
+        return None;
+    }
+    let max_fraction = if max_fraction.is_finite() {
+        max_fraction.clamp(0f32, 1f32)
+    } else {
+        DEFAULT_MAX_DECORATIONS_FRACTION
+    };
+    let space = decorations_space.max(0f32).min(chart_width * max_fraction);
+    Some(space.min(chart_width - MIN_DRAWABLE_WIDTH))
+}
+
+/// `TimeSeriesSummary` describes a TimeSeries in a bounded line for the logs, the metrics are not
+/// included, they can be written with compact_dump.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimeSeriesSummary {
+    /// The name of the series
+    pub name: String,
+
+    /// The capacity of the circular buffer
+    pub capacity: usize,
+
+    /// The active items in the circular buffer
+    pub active_items: usize,
+
+    /// The epoch of the oldest metric
+    pub first_epoch: Option<u64>,
+
+    /// The epoch of the newest metric
+    pub last_epoch: Option<u64>,
+
+    /// The min, max, avg and count of the last calculated stats
+    pub stats: String,
+}
+
+impl fmt::Display for TimeSeriesSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let epoch = |epoch: Option<u64>| epoch.map_or(String::from("-"), |epoch| epoch.to_string());
+        write!(
+            f,
+            "{}: {}/{} items, epochs {}..{}, {}",
+            self.name,
+            self.active_items,
+            self.capacity,
+            epoch(self.first_epoch),
+            epoch(self.last_epoch),
+            self.stats
+        )
+    }
+}
+
+/// `WindowSummary` describes the values of a series between two epochs, i.e. how a chart changed
+/// while the terminal was not looked at. The missing values are not counted.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct WindowSummary {
+    /// The epoch of the first value in the range
+    pub from_epoch: u64,
+
+    /// The epoch of the last value in the range
+    pub to_epoch: u64,
+
+    /// The first value in the range
+    pub first: f64,
+
+    /// The last value in the range
+    pub last: f64,
+
+    pub min: f64,
+
+    pub max: f64,
+
+    pub avg: f64,
+
+    /// The values in the range
+    pub count: usize,
+
+    /// Whether the range started before the oldest retained metric, the older values were
+    /// already dropped and are not summarized
+    pub truncated: bool,
+}
+
+impl WindowSummary {
+    /// `merge` combines the summaries of the sources of a chart, the first value is the one of
+    /// the oldest epoch, the last value the one of the newest epoch and the avg is weighted by
+    /// the count of each summary.
+    pub fn merge(self, other: WindowSummary) -> WindowSummary {
+        let count = self.count + other.count;
+        let avg = (self.avg * self.count as f64 + other.avg * other.count as f64) / count as f64;
+        let (from_epoch, first) = if other.from_epoch < self.from_epoch {
+            (other.from_epoch, other.first)
+        } else {
+            (self.from_epoch, self.first)
+        };
+        let (to_epoch, last) = if other.to_epoch > self.to_epoch {
+            (other.to_epoch, other.last)
+        } else {
+            (self.to_epoch, self.last)
+        };
+        WindowSummary {
+            from_epoch,
+            to_epoch,
+            first,
+            last,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+            avg,
+            count,
+            truncated: self.truncated || other.truncated,
+        }
+    }
+}
+
+/// `ChartSummary` is the WindowSummary of the sources of a chart with its values formatted for
+/// the message bar
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChartSummary {
+    /// The name of the chart
+    pub name: String,
+
+    pub summary: WindowSummary,
+
+    /// The formatted first, last, min, max and avg values
+    pub first: String,
+
+    pub last: String,
+
+    pub min: String,
+
+    pub max: String,
+
+    pub avg: String,
+}
+
+impl ChartSummary {
+    pub fn new(name: &str, summary: WindowSummary) -> ChartSummary {
+        ChartSummary {
+            name: name.to_string(),
+            summary,
+            first: format_readout_value(summary.first),
+            last: format_readout_value(summary.last),
+            min: format_readout_value(summary.min),
+            max: format_readout_value(summary.max),
+            avg: format_readout_value(summary.avg),
+        }
+    }
+}
+
+impl fmt::Display for ChartSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.summary.to_epoch.saturating_sub(self.summary.from_epoch);
+        write!(
+            f,
+            "{}: from {} to {} over {}, min {}, max {}, avg {}",
+            self.name,
+            self.first,
+            self.last,
+            timefmt::format_duration_secs(span),
+            self.min,
+            self.max,
+            self.avg
+        )?;
+        if self.summary.truncated {
+            write!(f, " (older values were dropped)")?;
+        }
+        Ok(())
+    }
+}
+
+/// `IterTimeSeries` provides the Iterator Trait for TimeSeries metrics.
+/// The active items are numbered from the oldest one at "first_idx", the iteration from both
+/// ends stops when the "front" and "back" item numbers meet.
+pub struct IterTimeSeries<'a> {
+    /// The reference to the TimeSeries struct to iterate over.
+    inner: &'a TimeSeries,
+    /// The number of the next item from the oldest end
+    front: usize,
+    /// The number past the next item from the newest end
+    back: usize,
+}
+
+/// `ManualTimeSeries` is a basic time series that we feed ourselves, used for internal counters
+/// for example keyboard input, output newlines, loaded items count.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct ManualTimeSeries {
+    /// The name of the ManualTimeSeries
+    pub name: String,
+
+    /// The TimeSeries that contains the data
+    #[serde(default)]
+    pub series: TimeSeries,
+
+    /// The granularity to store
+    #[serde(default)]
+    pub granularity: u64,
+
+    /// The color of the TimeSeries, a literal color or the name of a terminal color
+    #[serde(default)]
+    pub color: ChartColor,
+
+    /// The transparency of the TimeSeries
+    #[serde(default)]
+    pub alpha: f32,
+
+    /// The line pattern of the series, solid when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<LinePattern>,
+
+    /// Whether the series is drawn as a line or as a timeline band
+    #[serde(default)]
+    pub render_as: RenderAs,
+
+    /// The layout and the colors of the band when rendered as a Timeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<TimelineConfig>,
+
+    /// How the values are drawn, i.e. as the rate of a counter
+    #[serde(default, skip_serializing_if = "SeriesTransform::is_none")]
+    pub transform: SeriesTransform,
+
+    /// Only for async_loaded_items: counts the items loaded by the watch_chart and watch_series
+    /// source instead of the items loaded by all the sources
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub per_source: bool,
+
+    /// The name of the chart of the source watched by a per_source counter
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch_chart: Option<String>,
+
+    /// The name of the source watched by a per_source counter, in the watch_chart
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub watch_series: Option<String>,
+
+    /// The epoch in progress and its running value in a chart with a live_edge, it is upserted
+    /// into the series once the epoch completes
+    #[serde(skip)]
+    pub provisional: Option<(u64, f64)>,
+}
+
+impl ManualTimeSeries {
+    /// `accumulate` adds an increment at `epoch` to the provisional value with the collision
+    /// policy of the series, the provisional value of an earlier epoch is upserted first. The
+    /// increments older than the provisional value are upserted directly.
+    pub fn accumulate(&mut self, epoch: u64, value: f64) {
+        match self.provisional {
+            Some((provisional_epoch, current)) if provisional_epoch == epoch => {
+                let resolved = self.series.resolve_metric_collision(Some(current), Some(value));
+                self.provisional = Some((epoch, resolved.unwrap_or(current)));
+            },
+            Some((provisional_epoch, _)) if provisional_epoch > epoch => {
+                self.series.upsert((epoch, Some(value)));
+            },
+            _ => {
+                self.commit_provisional(epoch);
+                self.provisional = Some((epoch, value));
+            },
+        }
+    }
+
+    /// `commit_provisional` upserts the provisional value once its epoch is before `now`,
+    /// returns true if it was upserted
+    pub fn commit_provisional(&mut self, now: u64) -> bool {
+        match self.provisional {
+            Some((epoch, value)) if epoch < now => {
+                self.series.upsert((epoch, Some(value)));
+                self.provisional = None;
+                true
+            },
+            _ => false,
+        }
+    }
+
+    /// `watched_series` returns the indices of the source watched by a per_source counter, None
+    /// when the counter is not per_source or the watched names do not exist
+    pub fn watched_series(&self, charts: &[TimeSeriesChart]) -> Option<(ChartIndex, SeriesIndex)> {
+        if !self.per_source {
+            return None;
+        }
+        let (watch_chart, watch_series) = (self.watch_chart.as_ref()?, self.watch_series.as_ref()?);
+        let chart_idx = charts.iter().position(|chart| &chart.name == watch_chart)?;
+        let series_idx =
+            charts[chart_idx].sources.iter().position(|source| &source.name() == watch_series)?;
+        Some((ChartIndex(chart_idx), SeriesIndex(series_idx)))
+    }
+}
+
+pub fn deserialize_rgb_from_str<'de, D>(de: D) -> Result<Rgb, D::Error>
+where
+    D: Deserializer<'de>,
+{
     let s = String::deserialize(de)?;
     match Rgb::from_str(&s) {
         Ok(rgb) => Ok(rgb),
@@ -235,14 +1543,104 @@ where
     }
 }
 
+/// The decimal places kept when the chart floats are serialized in compact form
+pub const DEFAULT_COMPACT_DECIMALS: u32 = 6u32;
+
+/// `round_to_decimals` rounds a value to a number of decimal places, values that cannot be
+/// scaled without overflowing are returned as is.
+pub fn round_to_decimals(value: f64, decimals: u32) -> f64 {
+    let factor = 10f64.powi(decimals as i32);
+    let rounded = (value * factor).round() / factor;
+    if rounded.is_finite() {
+        rounded
+    } else {
+        value
+    }
+}
+
+/// `ser_f64_compact` serializes a float rounded to DEFAULT_COMPACT_DECIMALS, computed values such
+/// as the average would otherwise be written with 17 significant digits. The value in memory
+/// keeps its precision.
+pub fn ser_f64_compact<S>(value: &f64, ser: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    ser.serialize_f64(round_to_decimals(*value, DEFAULT_COMPACT_DECIMALS))
+}
+
+/// `deserialize_optional_rgb_from_str` reads an optional color, missing colors require
+/// `#[serde(default)]`
+pub fn deserialize_optional_rgb_from_str<'de, D>(de: D) -> Result<Option<Rgb>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    deserialize_rgb_from_str(de).map(Some)
+}
+
 impl Default for ManualTimeSeries {
     fn default() -> ManualTimeSeries {
         ManualTimeSeries {
             name: String::from("unkown"),
             series: TimeSeries::default(),
             granularity: 1, // 1 second
-            color: Rgb::default(),
+            color: ChartColor::default(),
             alpha: 1.0,
+            pattern: None,
+            render_as: RenderAs::default(),
+            timeline: None,
+            transform: SeriesTransform::None,
+            per_source: false,
+            watch_chart: None,
+            watch_series: None,
+            provisional: None,
+        }
+    }
+}
+
+/// `ChartIndex` is the position of a chart in the charts of the ChartsConfig
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct ChartIndex(pub usize);
+
+/// `SeriesIndex` is the position of a series in the sources of a chart
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SeriesIndex(pub usize);
+
+/// `DecorationIndex` is the position of a decoration in the decorations of a chart
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct DecorationIndex(pub usize);
+
+impl fmt::Display for ChartIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for SeriesIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl fmt::Display for DecorationIndex {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// `ChartRef` is how the key bindings reference a chart, by its name or its position in the
+/// charts of the ChartsConfig. It may not exist once the config is edited.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+#[serde(untagged)]
+pub enum ChartRef {
+    Index(usize),
+    Name(String),
+}
+
+impl fmt::Display for ChartRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartRef::Index(index) => write!(f, "#{}", index),
+            ChartRef::Name(name) => write!(f, "'{}'", name),
         }
     }
 }
@@ -251,6 +1649,7 @@ impl Default for ManualTimeSeries {
 /// with drawable data
 #[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(tag = "type")]
+#[non_exhaustive]
 pub enum TimeSeriesSource {
     #[serde(rename = "prometheus")]
     PrometheusTimeSeries(Box<prometheus::PrometheusTimeSeries>),
@@ -258,8 +1657,12 @@ pub enum TimeSeriesSource {
     AlacrittyInput(ManualTimeSeries),
     #[serde(rename = "alacritty_output")]
     AlacrittyOutput(ManualTimeSeries),
-    #[serde(rename = "async_items_loaded")]
+    #[serde(rename = "async_loaded_items")]
     AsyncLoadedItems(ManualTimeSeries),
+    #[serde(rename = "alacritty_scroll")]
+    AlacrittyScroll(ManualTimeSeries),
+    #[serde(rename = "alacritty_selection")]
+    AlacrittySelection(ManualTimeSeries),
 }
 
 impl Default for TimeSeriesSource {
@@ -284,6 +1687,61 @@ impl TimeSeriesSource {
             TimeSeriesSource::AlacrittyInput(x) => x.series.clone(),
             TimeSeriesSource::AlacrittyOutput(x) => x.series.clone(),
             TimeSeriesSource::AsyncLoadedItems(x) => x.series.clone(),
+            TimeSeriesSource::AlacrittyScroll(x) => x.series.clone(),
+            TimeSeriesSource::AlacrittySelection(x) => x.series.clone(),
+        }
+    }
+
+    /// `series_ref` returns a reference to the underlying series, i.e. to measure it without a
+    /// copy
+    pub fn series_ref(&self) -> &TimeSeries {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => &x.series,
+            TimeSeriesSource::AlacrittyInput(x) => &x.series,
+            TimeSeriesSource::AlacrittyOutput(x) => &x.series,
+            TimeSeriesSource::AsyncLoadedItems(x) => &x.series,
+            TimeSeriesSource::AlacrittyScroll(x) => &x.series,
+            TimeSeriesSource::AlacrittySelection(x) => &x.series,
+        }
+    }
+
+    /// `transform` returns how the values of the source are drawn
+    pub fn transform(&self) -> SeriesTransform {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.transform,
+            TimeSeriesSource::AlacrittyInput(x) => x.transform,
+            TimeSeriesSource::AlacrittyOutput(x) => x.transform,
+            TimeSeriesSource::AsyncLoadedItems(x) => x.transform,
+            TimeSeriesSource::AlacrittyScroll(x) => x.transform,
+            TimeSeriesSource::AlacrittySelection(x) => x.transform,
+        }
+    }
+
+    /// `drawn_series` returns a copy of the series with the transform of the source applied
+    pub fn drawn_series(&self) -> TimeSeries {
+        self.series_ref().transformed(self.transform())
+    }
+
+    /// `live_counter` returns the internal counters incremented by the terminal, their second
+    /// in progress may be drawn at the live edge
+    pub fn live_counter(&self) -> Option<&ManualTimeSeries> {
+        match self {
+            TimeSeriesSource::AlacrittyInput(x)
+            | TimeSeriesSource::AlacrittyOutput(x)
+            | TimeSeriesSource::AlacrittyScroll(x)
+            | TimeSeriesSource::AlacrittySelection(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// `live_counter_mut` returns a mutable reference to the counter of `live_counter`
+    pub fn live_counter_mut(&mut self) -> Option<&mut ManualTimeSeries> {
+        match self {
+            TimeSeriesSource::AlacrittyInput(x)
+            | TimeSeriesSource::AlacrittyOutput(x)
+            | TimeSeriesSource::AlacrittyScroll(x)
+            | TimeSeriesSource::AlacrittySelection(x) => Some(x),
+            _ => None,
         }
     }
 
@@ -294,6 +1752,17 @@ impl TimeSeriesSource {
             TimeSeriesSource::AlacrittyInput(x) => &mut x.series,
             TimeSeriesSource::AlacrittyOutput(x) => &mut x.series,
             TimeSeriesSource::AsyncLoadedItems(x) => &mut x.series,
+            TimeSeriesSource::AlacrittyScroll(x) => &mut x.series,
+            TimeSeriesSource::AlacrittySelection(x) => &mut x.series,
+        }
+    }
+
+    /// `resize_capacity` resizes the series of the source to hold `capacity` samples, the
+    /// query_range URL of a Prometheus source is prepared again for the new range
+    pub fn resize_capacity(&mut self, capacity: usize) {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.resize_capacity(capacity),
+            _ => self.series_mut().resize_capacity(capacity),
         }
     }
 
@@ -303,6 +1772,8 @@ impl TimeSeriesSource {
             TimeSeriesSource::AlacrittyInput(x) => x.name.clone(),
             TimeSeriesSource::AlacrittyOutput(x) => x.name.clone(),
             TimeSeriesSource::AsyncLoadedItems(x) => x.name.clone(),
+            TimeSeriesSource::AlacrittyScroll(x) => x.name.clone(),
+            TimeSeriesSource::AlacrittySelection(x) => x.name.clone(),
         }
     }
 
@@ -310,10 +1781,12 @@ impl TimeSeriesSource {
     // easily available or have like a .prop("color").
     pub fn color(&self) -> Rgb {
         match self {
-            TimeSeriesSource::PrometheusTimeSeries(x) => x.color,
-            TimeSeriesSource::AlacrittyInput(x) => x.color,
-            TimeSeriesSource::AlacrittyOutput(x) => x.color,
-            TimeSeriesSource::AsyncLoadedItems(x) => x.color,
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.color.rgb(),
+            TimeSeriesSource::AlacrittyInput(x) => x.color.rgb(),
+            TimeSeriesSource::AlacrittyOutput(x) => x.color.rgb(),
+            TimeSeriesSource::AsyncLoadedItems(x) => x.color.rgb(),
+            TimeSeriesSource::AlacrittyScroll(x) => x.color.rgb(),
+            TimeSeriesSource::AlacrittySelection(x) => x.color.rgb(),
         }
     }
 
@@ -323,7 +1796,173 @@ impl TimeSeriesSource {
             TimeSeriesSource::AlacrittyInput(x) => x.alpha,
             TimeSeriesSource::AlacrittyOutput(x) => x.alpha,
             TimeSeriesSource::AsyncLoadedItems(x) => x.alpha,
+            TimeSeriesSource::AlacrittyScroll(x) => x.alpha,
+            TimeSeriesSource::AlacrittySelection(x) => x.alpha,
+        }
+    }
+
+    /// `pattern` returns the configured line pattern of the series
+    pub fn pattern(&self) -> Option<LinePattern> {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.pattern,
+            TimeSeriesSource::AlacrittyInput(x) => x.pattern,
+            TimeSeriesSource::AlacrittyOutput(x) => x.pattern,
+            TimeSeriesSource::AsyncLoadedItems(x) => x.pattern,
+            TimeSeriesSource::AlacrittyScroll(x) => x.pattern,
+            TimeSeriesSource::AlacrittySelection(x) => x.pattern,
+        }
+    }
+
+    /// `render_as` returns whether the series is drawn as a line or as a timeline band
+    pub fn render_as(&self) -> RenderAs {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.render_as,
+            TimeSeriesSource::AlacrittyInput(x) => x.render_as,
+            TimeSeriesSource::AlacrittyOutput(x) => x.render_as,
+            TimeSeriesSource::AsyncLoadedItems(x) => x.render_as,
+            TimeSeriesSource::AlacrittyScroll(x) => x.render_as,
+            TimeSeriesSource::AlacrittySelection(x) => x.render_as,
+        }
+    }
+
+    /// `timeline` returns the configured band of a timeline source, or the default one
+    pub fn timeline(&self) -> TimelineConfig {
+        let timeline = match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => &x.timeline,
+            TimeSeriesSource::AlacrittyInput(x) => &x.timeline,
+            TimeSeriesSource::AlacrittyOutput(x) => &x.timeline,
+            TimeSeriesSource::AsyncLoadedItems(x) => &x.timeline,
+            TimeSeriesSource::AlacrittyScroll(x) => &x.timeline,
+            TimeSeriesSource::AlacrittySelection(x) => &x.timeline,
+        };
+        timeline.clone().unwrap_or_default()
+    }
+
+    /// `resolve_colors` resolves the named colors of the source and of its timeline band against
+    /// the palette, the unknown names are added to `unknown`
+    pub fn resolve_colors(&mut self, palette: &TerminalPalette, unknown: &mut Vec<String>) {
+        let (color, timeline) = match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => (&mut x.color, &mut x.timeline),
+            TimeSeriesSource::AlacrittyInput(x) => (&mut x.color, &mut x.timeline),
+            TimeSeriesSource::AlacrittyOutput(x) => (&mut x.color, &mut x.timeline),
+            TimeSeriesSource::AsyncLoadedItems(x) => (&mut x.color, &mut x.timeline),
+            TimeSeriesSource::AlacrittyScroll(x) => (&mut x.color, &mut x.timeline),
+            TimeSeriesSource::AlacrittySelection(x) => (&mut x.color, &mut x.timeline),
+        };
+        if let Err(name) = color.resolve(palette) {
+            unknown.push(name);
+        }
+        if let Some(timeline) = timeline {
+            timeline.resolve_colors(palette, unknown);
+        }
+    }
+
+    /// `is_timeline` returns true when the series is drawn as a timeline band
+    pub fn is_timeline(&self) -> bool {
+        self.render_as() == RenderAs::Timeline
+    }
+
+    /// `ever_loaded` returns true once the source has data to draw, the internal counters are
+    /// considered loaded from the start as they do not wait for a remote source.
+    pub fn ever_loaded(&self) -> bool {
+        match self {
+            TimeSeriesSource::PrometheusTimeSeries(x) => x.ever_loaded,
+            _ => true,
+        }
+    }
+}
+
+/// `DerivedOperation` is how the samples of a derived series are computed from its base series
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DerivedOperation {
+    /// The difference with the previous filled sample
+    Delta,
+    /// The difference with the previous filled sample divided by the seconds between them,
+    /// a value lower than the previous one is a counter reset and has no rate
+    RatePerSecond,
+    /// The running sum of the filled samples
+    CumulativeSum,
+}
+
+impl DerivedOperation {
+    /// `derive` returns a value for each of the samples, missing samples stay missing and the
+    /// Delta and RatePerSecond are calculated against the previous filled sample.
+    pub fn derive<'a, I>(self, samples: I) -> Vec<Option<f64>>
+    where
+        I: Iterator<Item = &'a (u64, Option<f64>)>,
+    {
+        let mut previous: Option<(u64, f64)> = None;
+        let mut sum = 0f64;
+        samples
+            .map(|&(epoch, value)| {
+                let value = value?;
+                match self {
+                    DerivedOperation::Delta => {
+                        previous.replace((epoch, value)).map(|(_, prev_value)| value - prev_value)
+                    },
+                    DerivedOperation::RatePerSecond => match previous.replace((epoch, value)) {
+                        Some((prev_epoch, prev_value))
+                            if value >= prev_value && epoch > prev_epoch =>
+                        {
+                            Some((value - prev_value) / (epoch - prev_epoch) as f64)
+                        },
+                        _ => None,
+                    },
+                    DerivedOperation::CumulativeSum => {
+                        sum += value;
+                        Some(sum)
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// `DerivedSeries` is an overlay series computed from a source of the chart when its vertices
+/// are generated, it is never polled.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct DerivedSeries {
+    /// The name of the derived series
+    pub name: String,
+
+    /// The name of the source the samples are computed from
+    pub source: String,
+
+    /// How the samples are computed
+    pub operation: DerivedOperation,
+
+    /// The color of the derived series, a literal color or the name of a terminal color
+    #[serde(default)]
+    pub color: ChartColor,
+
+    /// The transparency of the derived series
+    #[serde(default)]
+    pub alpha: f32,
+
+    /// The line pattern of the derived series, solid when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<LinePattern>,
+
+    /// The index of the source in the chart, resolved by name when the coordinator starts
+    #[serde(skip)]
+    pub base: Option<usize>,
+}
+
+impl DerivedSeries {
+    /// `series` computes the derived samples from the base source, the circular buffer of the
+    /// base series is kept so that both are drawn with the same epochs. None is returned while
+    /// the base source is not resolved.
+    pub fn series(&self, sources: &[TimeSeriesSource]) -> Option<TimeSeries> {
+        let mut res = sources.get(self.base?)?.series();
+        let values = self.operation.derive(res.iter());
+        let len = res.metrics.len();
+        for (offset, value) in values.into_iter().enumerate() {
+            res.metrics[(res.first_idx + offset) % len].1 = value;
         }
+        #[cfg(feature = "upsert-debug")]
+        res.prev_snapshot.clear();
+        res.calculate_stats();
+        Some(res)
     }
 }
 
@@ -336,72 +1975,1154 @@ pub struct Value2D {
     pub y: f32,
 }
 
-/// `ChartSizeInfo` Contains the current chart size information plus the terminal size info
-#[derive(Debug, Serialize, Default, Deserialize, PartialEq, Clone, Copy)]
-pub struct ChartSizeInfo {
-    pub term_size: SizeInfo,
-    pub chart_width: f32,
-    pub chart_height: f32,
+impl Value2D {
+    /// `scaled` multiplies both coordinates by `factor`, used to turn logical pixels into
+    /// physical pixels.
+    pub fn scaled(&self, factor: f32) -> Value2D {
+        Value2D { x: self.x * factor, y: self.y * factor }
+    }
 }
 
-impl ChartSizeInfo {
-    /// `scale_x` Calls the SizeInfo scale_x method, the input value is already a f32 pixel
-    /// 1.0 is the `display_width` parameter (right-most), i.e. 1024px.
-    pub fn scale_x(&self, input_value: f32) -> f32 {
-        self.term_size.scale_x(input_value)
+/// `ChartLength2D` is a position or dimensions of the charts layout in logical pixels plus
+/// terminal cells, the cells follow the font size. In the YAML it is written in pixels as
+/// `{x: 200, y: 30}`, in cells as `{cells: {x: 10, y: 2}}` or as a string like "10ch x 2ln".
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(try_from = "ChartLength2DConfig", into = "ChartLength2DConfig")]
+pub struct ChartLength2D {
+    /// The logical pixels
+    pub pixels: Value2D,
+
+    /// The columns on the X axis and the lines on the Y axis
+    pub cells: Value2D,
+}
+
+impl ChartLength2D {
+    /// `pixels` creates a length in logical pixels
+    pub fn pixels(x: f32, y: f32) -> ChartLength2D {
+        ChartLength2D { pixels: Value2D { x, y }, cells: Value2D::default() }
     }
 
-    /// `scale_x` Scales an input value considering a max_value that must be set to the height of a
-    /// chart
-    pub fn scale_y(&self, max_value: f64, input_value: f64) -> f32 {
-        // Scale the metric value so that the max_value is the chart height
-        let scaled_metric_value = (input_value as f32 * self.chart_height) / max_value as f32;
-        self.term_size.scale_y(scaled_metric_value)
+    /// `cells` creates a length of `x` columns and `y` lines
+    pub fn cells(x: f32, y: f32) -> ChartLength2D {
+        ChartLength2D { pixels: Value2D::default(), cells: Value2D { x, y } }
     }
-}
 
-/// `ChartsConfig` contains a vector of charts and basic position of the charts,
+    /// `resolve` returns the length in logical pixels for the current cell size of the terminal,
+    /// the layout of the charts resolves the cells only here.
+    pub fn resolve(&self, term_size: &SizeInfo) -> Value2D {
+        let dpr = term_size.scale_factor();
+        Value2D {
+            x: self.pixels.x + self.cells.x * term_size.cell_width / dpr,
+            y: self.pixels.y + self.cells.y * term_size.cell_height / dpr,
+        }
+    }
+
+    /// `right_of` returns the position after this position, `width` away from it plus `spacing`,
+    /// at the bottom of the charts area
+    fn right_of(&self, width: &ChartLength2D, spacing: ChartLength) -> ChartLength2D {
+        ChartLength2D {
+            pixels: Value2D { x: self.pixels.x + width.pixels.x + spacing.pixels, y: 0. },
+            cells: Value2D { x: self.cells.x + width.cells.x + spacing.cells, y: 0. },
+        }
+    }
+}
+
+impl From<Value2D> for ChartLength2D {
+    fn from(pixels: Value2D) -> ChartLength2D {
+        ChartLength2D { pixels, cells: Value2D::default() }
+    }
+}
+
+/// `ChartLength2DConfig` are the forms of a ChartLength2D in the YAML
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ChartLength2DConfig {
+    Text(String),
+    Fields {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        x: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        y: Option<f32>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cells: Option<Value2D>,
+    },
+}
+
+impl TryFrom<ChartLength2DConfig> for ChartLength2D {
+    type Error = String;
+
+    fn try_from(config: ChartLength2DConfig) -> Result<ChartLength2D, String> {
+        match config {
+            ChartLength2DConfig::Text(text) => {
+                let invalid = || format!("Invalid chart length {:?}, i.e. \"10ch x 2ln\"", text);
+                let (x, y) = text.split_once('x').ok_or_else(invalid)?;
+                let (pixels_x, cells_x) = parse_length(x, "ch").ok_or_else(invalid)?;
+                let (pixels_y, cells_y) = parse_length(y, "ln").ok_or_else(invalid)?;
+                Ok(ChartLength2D {
+                    pixels: Value2D { x: pixels_x, y: pixels_y },
+                    cells: Value2D { x: cells_x, y: cells_y },
+                })
+            },
+            ChartLength2DConfig::Fields { x, y, cells } => Ok(ChartLength2D {
+                pixels: Value2D { x: x.unwrap_or_default(), y: y.unwrap_or_default() },
+                cells: cells.unwrap_or_default(),
+            }),
+        }
+    }
+}
+
+impl From<ChartLength2D> for ChartLength2DConfig {
+    fn from(length: ChartLength2D) -> ChartLength2DConfig {
+        let has_cells = length.cells != Value2D::default();
+        let pixels = (length.pixels != Value2D::default() || !has_cells).then_some(length.pixels);
+        ChartLength2DConfig::Fields {
+            x: pixels.map(|pixels| pixels.x),
+            y: pixels.map(|pixels| pixels.y),
+            cells: has_cells.then_some(length.cells),
+        }
+    }
+}
+
+/// `ChartLength` is a horizontal length of the charts layout, i.e. the spacing, in logical
+/// pixels plus terminal columns. In the YAML it is written as a number of pixels, in columns as
+/// `{cells: 2}` or as a string like "2ch".
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+#[serde(try_from = "ChartLengthConfig", into = "ChartLengthConfig")]
+pub struct ChartLength {
+    /// The logical pixels
+    pub pixels: f32,
+
+    /// The columns
+    pub cells: f32,
+}
+
+impl ChartLength {
+    /// `pixels` creates a length in logical pixels
+    pub fn pixels(pixels: f32) -> ChartLength {
+        ChartLength { pixels, cells: 0. }
+    }
+
+    /// `cells` creates a length of `cells` columns
+    pub fn cells(cells: f32) -> ChartLength {
+        ChartLength { pixels: 0., cells }
+    }
+}
+
+/// `ChartLengthConfig` are the forms of a ChartLength in the YAML
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(untagged)]
+enum ChartLengthConfig {
+    Pixels(f32),
+    Text(String),
+    Fields {
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        x: Option<f32>,
+        cells: f32,
+    },
+}
+
+impl TryFrom<ChartLengthConfig> for ChartLength {
+    type Error = String;
+
+    fn try_from(config: ChartLengthConfig) -> Result<ChartLength, String> {
+        match config {
+            ChartLengthConfig::Pixels(pixels) => Ok(ChartLength::pixels(pixels)),
+            ChartLengthConfig::Text(text) => match parse_length(&text, "ch") {
+                Some((pixels, cells)) => Ok(ChartLength { pixels, cells }),
+                None => Err(format!("Invalid chart length {:?}, i.e. \"2ch\"", text)),
+            },
+            ChartLengthConfig::Fields { x, cells } => {
+                Ok(ChartLength { pixels: x.unwrap_or_default(), cells })
+            },
+        }
+    }
+}
+
+impl From<ChartLength> for ChartLengthConfig {
+    fn from(length: ChartLength) -> ChartLengthConfig {
+        if length.cells == 0. {
+            ChartLengthConfig::Pixels(length.pixels)
+        } else {
+            let x = (length.pixels != 0.).then_some(length.pixels);
+            ChartLengthConfig::Fields { x, cells: length.cells }
+        }
+    }
+}
+
+/// `parse_length` parses a length of one axis written with a unit, i.e. "10ch" or "200px", into
+/// (pixels, cells). The cells use the unit of the axis, "ch" for columns and "ln" for lines.
+fn parse_length(text: &str, cells_unit: &str) -> Option<(f32, f32)> {
+    let text = text.trim();
+    if let Some(cells) = text.strip_suffix(cells_unit) {
+        Some((0., cells.trim().parse().ok()?))
+    } else {
+        Some((text.strip_suffix("px")?.trim().parse().ok()?, 0.))
+    }
+}
+
+/// `NdcRect` is a rectangle in normalized device coordinates, where both axes go from -1.0 to 1.0
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct NdcRect {
+    pub left: f32,
+    pub right: f32,
+    pub bottom: f32,
+    pub top: f32,
+}
+
+impl NdcRect {
+    /// `from_pixel_rect` converts a rectangle in window pixels to normalized device coordinates,
+    /// the padding is removed as `scale_x` and `scale_y` add it back.
+    pub fn from_pixel_rect(rect: &PixelRect, term_size: &SizeInfo) -> NdcRect {
+        let x = rect.x - term_size.padding_x;
+        let bottom = term_size.height - term_size.padding_y - rect.y - rect.height;
+        NdcRect {
+            left: term_size.scale_x(x),
+            right: term_size.scale_x(x + rect.width),
+            bottom: term_size.scale_y(bottom),
+            top: term_size.scale_y(bottom + rect.height),
+        }
+    }
+
+    /// `is_empty` returns true when the rectangle has not been calculated or has no area
+    pub fn is_empty(&self) -> bool {
+        self.right <= self.left || self.top <= self.bottom
+    }
+
+    /// `contains` returns true if the x,y point is inside the rectangle or on its border
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.left && x <= self.right && y >= self.bottom && y <= self.top
+    }
+
+    /// `clip_segment` returns the visible portion of the segment from x0,y0 to x1,y1 inside the
+    /// rectangle using the Liang-Barsky algorithm, None if the segment is completely outside.
+    pub fn clip_segment(&self, x0: f32, y0: f32, x1: f32, y1: f32) -> Option<[f32; 4]> {
+        let dx = x1 - x0;
+        let dy = y1 - y0;
+        let mut t0 = 0f32;
+        let mut t1 = 1f32;
+        let edges = [
+            (-dx, x0 - self.left),
+            (dx, self.right - x0),
+            (-dy, y0 - self.bottom),
+            (dy, self.top - y0),
+        ];
+        for (p, q) in edges {
+            if p == 0f32 {
+                // The segment is parallel to this edge
+                if q < 0f32 {
+                    return None;
+                }
+                continue;
+            }
+            let r = q / p;
+            if p < 0f32 {
+                if r > t1 {
+                    return None;
+                }
+                t0 = t0.max(r);
+            } else {
+                if r < t0 {
+                    return None;
+                }
+                t1 = t1.min(r);
+            }
+        }
+        // Keep the original points when they are not clipped to avoid precision loss.
+        let (start_x, start_y) = if t0 > 0f32 { (x0 + t0 * dx, y0 + t0 * dy) } else { (x0, y0) };
+        let (end_x, end_y) = if t1 < 1f32 { (x0 + t1 * dx, y0 + t1 * dy) } else { (x1, y1) };
+        Some([start_x, start_y, end_x, end_y])
+    }
+
+    /// `clip_polyline` clips a line strip of x,y vertices to the rectangle. Segments crossing the
+    /// border are split at the intersection so that the slope of the visible portion is kept, the
+    /// segments completely outside of the rectangle are dropped. The point where the line leaves
+    /// the rectangle is joined to the point where it comes back by a straight segment, it runs
+    /// along the border only when both points are on the same edge.
+    pub fn clip_polyline(&self, vertices: &[f32]) -> Vec<f32> {
+        if vertices.chunks_exact(2).all(|point| self.contains(point[0], point[1])) {
+            return vertices.to_vec();
+        }
+        let mut res = Vec::with_capacity(vertices.len() + 4);
+        for segment in vertices.windows(4).step_by(2) {
+            if let Some([x0, y0, x1, y1]) =
+                self.clip_segment(segment[0], segment[1], segment[2], segment[3])
+            {
+                let len = res.len();
+                if len < 2 || res[len - 2] != x0 || res[len - 1] != y0 {
+                    res.push(x0);
+                    res.push(y0);
+                }
+                res.push(x1);
+                res.push(y1);
+            }
+        }
+        res
+    }
+}
+
+/// `PixelRect` is a rectangle in window pixels, the origin is the top left of the window.
+#[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct PixelRect {
+    pub x: f32,
+    pub y: f32,
+    pub width: f32,
+    pub height: f32,
+}
+
+impl PixelRect {
+    /// `is_empty` returns true when the rectangle has no area
+    pub fn is_empty(&self) -> bool {
+        self.width <= 0. || self.height <= 0.
+    }
+
+    /// `contains` returns true if the x,y pixel is inside the rectangle or on its border
+    pub fn contains(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+
+    /// `to_gl_window` returns the (x, y, width, height) expected by glScissor, the Y axis is
+    /// flipped so that the origin is the bottom left of a window of `window_height` pixels.
+    /// Partially covered pixels are included.
+    pub fn to_gl_window(&self, window_height: f32) -> (i32, i32, i32, i32) {
+        let left = self.x.floor();
+        let right = (self.x + self.width).ceil();
+        let bottom = (window_height - self.y - self.height).floor();
+        let top = (window_height - self.y).ceil();
+        (left as i32, bottom as i32, (right - left).max(0.) as i32, (top - bottom).max(0.) as i32)
+    }
+}
+
+/// The number of decoration ticks of a full pulse of the awaiting data placeholder
+pub const PLACEHOLDER_PULSE_TICKS: u64 = 20u64;
+
+/// The alpha range of the pulse of the awaiting data placeholder, it should stay dim
+const PLACEHOLDER_PULSE_ALPHA: (f32, f32) = (0.05, 0.25);
+
+/// `ChartPlaceholder` is drawn over the chart area while none of its sources loaded data yet
+#[derive(Debug, PartialEq, Clone)]
+pub struct ChartPlaceholder {
+    /// The chart area in window pixels
+    pub rect: PixelRect,
+
+    /// The color of the pulse, the color of the first series
+    pub color: Rgb,
+
+    /// The alpha of the pulse for the current decoration tick
+    pub alpha: f32,
+}
+
+impl ChartPlaceholder {
+    /// `pulse_alpha` returns the alpha of the placeholder at a decoration tick, it rises and
+    /// falls linearly between the PLACEHOLDER_PULSE_ALPHA bounds every PLACEHOLDER_PULSE_TICKS.
+    pub fn pulse_alpha(tick: u64) -> f32 {
+        let half = PLACEHOLDER_PULSE_TICKS / 2;
+        let phase = tick % PLACEHOLDER_PULSE_TICKS;
+        let distance = if phase < half { phase } else { PLACEHOLDER_PULSE_TICKS - phase };
+        let (min, max) = PLACEHOLDER_PULSE_ALPHA;
+        min + (max - min) * distance as f32 / half as f32
+    }
+}
+
+/// `ChartSizeInfo` Contains the current chart size information plus the terminal size info
+#[derive(Debug, Serialize, Default, Deserialize, PartialEq, Clone, Copy)]
+pub struct ChartSizeInfo {
+    pub term_size: SizeInfo,
+    pub chart_width: f32,
+    pub chart_height: f32,
+    pub y_scale: YScaleType,
+    /// The bounds of the Y axis when they are not calculated from the stats, i.e. nice bounds
+    pub y_bounds: Option<(f64, f64)>,
+    /// The number of display sizes received for the window, the vertices are tagged with it so
+    /// that the display can tell the vertices generated for a previous size
+    pub size_generation: u64,
+}
+
+impl ChartSizeInfo {
+    /// `has_dimensions` returns true if the window has a width and a height, the vertices scaled
+    /// to a window without them are NaN
+    pub fn has_dimensions(&self) -> bool {
+        self.term_size.width > 0f32 && self.term_size.height > 0f32
+    }
+
+    /// `scale_x` Calls the SizeInfo scale_x method, the input value is already a f32 pixel
+    /// 1.0 is the `display_width` parameter (right-most), i.e. 1024px.
+    pub fn scale_x(&self, input_value: f32) -> f32 {
+        self.term_size.scale_x(input_value)
+    }
+
+    /// `scale_y` Scales an input value considering a max_value that must be set to the height of a
+    /// chart
+    pub fn scale_y(&self, max_value: f64, input_value: f64) -> f32 {
+        self.scale_y_range(0f64, max_value, input_value)
+    }
+
+    /// `scale_y_range` Scales an input value so that min_value is the bottom of the chart and
+    /// max_value is the chart height
+    pub fn scale_y_range(&self, min_value: f64, max_value: f64, input_value: f64) -> f32 {
+        let range = max_value - min_value;
+        let scaled_metric_value = if range > 0f64 {
+            ((input_value - min_value) as f32 * self.chart_height) / range as f32
+        } else {
+            0f32
+        };
+        self.term_size.scale_y(scaled_metric_value)
+    }
+
+    /// `value_to_ndc_y` maps a metric value to the Y axis of the chart. Series and decorations
+    /// must use this so that they are always drawn in the same scale. The bottom of the chart is
+    /// zero, unless there are negative values, in which case the minimum value is used.
+    /// In Log10 mode the bottom is the minimum value and values are clamped to min_clamp.
+    pub fn value_to_ndc_y(&self, value: f64, stats: &TimeSeriesStats) -> f32 {
+        let (lo, hi) = self.y_bounds(stats);
+        match self.y_scale {
+            YScaleType::Linear => self.scale_y_range(lo, hi, value),
+            YScaleType::Log10 { .. } => {
+                let value = value.max(self.y_scale.min_clamp());
+                self.scale_y_range(lo.log10(), hi.log10(), value.log10())
+            },
+        }
+    }
+
+    /// `ndc_y_to_value` is the inverse of `value_to_ndc_y`, it returns the metric value drawn at
+    /// a Y coordinate of the chart, i.e. to show the value under the mouse.
+    pub fn ndc_y_to_value(&self, ndc_y: f32, stats: &TimeSeriesStats) -> f64 {
+        let (lo, hi) = self.y_bounds(stats);
+        let (lo, hi) = match self.y_scale {
+            YScaleType::Linear => (lo, hi),
+            YScaleType::Log10 { .. } => (lo.log10(), hi.log10()),
+        };
+        let pixels = (ndc_y + 1f32) * self.term_size.height / 2f32 - self.term_size.padding_y;
+        let fraction = if self.chart_height > 0f32 { pixels / self.chart_height } else { 0f32 };
+        let value = lo + fraction as f64 * (hi - lo);
+        match self.y_scale {
+            YScaleType::Linear => value,
+            YScaleType::Log10 { .. } => 10f64.powf(value),
+        }
+    }
+
+    /// `y_bounds` returns the values at the bottom and at the top of the chart
+    pub fn y_bounds(&self, stats: &TimeSeriesStats) -> (f64, f64) {
+        self.y_bounds.unwrap_or_else(|| self.y_scale.bounds(stats))
+    }
+
+    /// `zero_ndc_y` returns the Y coordinate where zero is drawn, i.e. for a decoration to mark
+    /// it. None when zero is out of the bounds of the chart or in Log10 mode
+    pub fn zero_ndc_y(&self, stats: &TimeSeriesStats) -> Option<f32> {
+        let (lo, hi) = self.y_bounds(stats);
+        match self.y_scale {
+            YScaleType::Linear if lo <= 0f64 && hi >= 0f64 => Some(self.value_to_ndc_y(0., stats)),
+            _ => None,
+        }
+    }
+
+    /// `bar_base_ndc_y` returns the Y coordinate the bars start from, zero when it is within the
+    /// bounds of the chart, otherwise the edge of the chart that is closest to zero
+    pub fn bar_base_ndc_y(&self, stats: &TimeSeriesStats) -> f32 {
+        if let Some(zero) = self.zero_ndc_y(stats) {
+            return zero;
+        }
+        let (lo, hi) = self.y_bounds(stats);
+        self.value_to_ndc_y(if hi < 0f64 { hi } else { lo }, stats)
+    }
+
+    /// `y_axis_ticks` returns the Y axis ticks of the chart as (value, ndc_y) pairs
+    pub fn y_axis_ticks(&self, stats: &TimeSeriesStats) -> Vec<(f64, f32)> {
+        let ticks = match self.y_bounds {
+            Some((lo, hi)) => vec![lo, hi],
+            None => self.y_scale.ticks(stats),
+        };
+        ticks.into_iter().map(|value| (value, self.value_to_ndc_y(value, stats))).collect()
+    }
+
+    /// `chart_pixel_rect` returns the area of a chart at `position` in window pixels. Charts are
+    /// drawn from the bottom of the padded area up to their height, the padding is added the
+    /// same way as `scale_x` and `scale_y` do.
+    pub fn chart_pixel_rect(&self, position: Value2D) -> PixelRect {
+        PixelRect {
+            x: self.term_size.padding_x + position.x,
+            y: self.term_size.height - self.term_size.padding_y - self.chart_height,
+            width: self.chart_width,
+            height: self.chart_height,
+        }
+    }
+}
+
+/// `ChartsConfig` contains a vector of charts and basic position of the charts,
 /// allowing to use a global position instead of individually setting up the chart position
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct ChartsConfig {
+    /// The version of the config format, the older versions are migrated when the config is read
+    #[serde(default)]
+    pub version: Option<u32>,
+
     /// The x,y coordinates in which chart drawing should start
-    pub position: Option<Value2D>,
+    pub position: Option<ChartLength2D>,
 
     /// The default dimensions of the chart
-    pub default_dimensions: Option<Value2D>,
+    pub default_dimensions: Option<ChartLength2D>,
 
     /// The default spacing between the charts
-    pub spacing: f32,
+    pub spacing: ChartLength,
 
     /// An array of charts to draw
     pub charts: Vec<TimeSeriesChart>,
+
+    /// The milliseconds the display size must be stable before the charts are regenerated,
+    /// defaults to DEFAULT_RESIZE_DEBOUNCE_MS
+    #[serde(default)]
+    pub resize_debounce_ms: Option<u64>,
+
+    /// The decimal places of the metrics written by compact_dump, defaults to
+    /// DEFAULT_COMPACT_DECIMALS
+    #[serde(default)]
+    pub compact_decimals: Option<u32>,
+
+    /// The quick retries of the first poll of each series when it fails, before falling back to
+    /// its pull_interval, defaults to DEFAULT_STARTUP_RETRIES
+    #[serde(default)]
+    pub startup_retries: Option<u32>,
+
+    /// The milliseconds between the quick retries of the first poll, defaults to
+    /// DEFAULT_STARTUP_RETRY_DELAY_MS
+    #[serde(default)]
+    pub startup_retry_delay_ms: Option<u64>,
+
+    /// The Prometheus requests that may be in flight at once across all the series, defaults to
+    /// DEFAULT_FETCH_CONCURRENCY
+    #[serde(default)]
+    pub fetch_concurrency: Option<usize>,
+
+    /// What happens to the charts that do not fit in the window
+    #[serde(default)]
+    pub overflow: ChartOverflow,
+
+    /// The megabytes the series of all the charts may use, the capacities of the series are
+    /// reduced proportionally when their projected memory is over it
+    #[serde(default)]
+    pub memory_budget_mb: Option<usize>,
+
+    /// The palette of the series that do not configure a color, the colorblind-safe modes also
+    /// change the line pattern of the series with a triggered alert
+    #[serde(default)]
+    pub palette_mode: PaletteMode,
+
+    /// The seconds the clock may move backwards before the series are recovered from a clock
+    /// jump, defaults to DEFAULT_CLOCK_JUMP_THRESHOLD_S
+    #[serde(default)]
+    pub clock_jump_threshold_s: Option<u64>,
+
+    /// The corner of the terminal at which the debug overlay starts
+    #[serde(default)]
+    pub debug_overlay_corner: debug_overlay::OverlayCorner,
+
+    /// The time windows in seconds the charts cycle through with the CycleChartWindow action,
+    /// defaults to DEFAULT_WINDOW_PRESETS
+    #[serde(default)]
+    pub window_presets: Option<Vec<u64>>,
+
+    /// The seconds the terminal must be unfocused before the summaries of the charts since the
+    /// focus was lost are shown in the message bar when it is focused again, disabled when None
+    #[serde(default)]
+    pub absence_summary_s: Option<u64>,
+
+    /// The watchdog that alerts when the Prometheus series stop loading data
+    #[serde(default)]
+    pub data_watchdog: Option<WatchdogConfig>,
+
+    /// The governor that degrades the charts while the coordinator is slow to handle messages
+    #[serde(default)]
+    pub load_governor: Option<GovernorConfig>,
+
+    /// The journal of the anomalies found in the loaded data, kept for postmortems
+    #[serde(default)]
+    pub journal: Option<JournalConfig>,
+
+    /// The state file the samples of the charts are kept in across sessions
+    #[serde(default)]
+    pub persistence: Option<persistence::PersistenceConfig>,
+
+    /// How the charts with missing data are marked, the charts may override it
+    #[serde(default)]
+    pub data_quality: Option<DataQualityConfig>,
+
+    /// The zone the absolute times of the charts and the polar clock are shown in: Local, Utc or
+    /// a fixed offset like +05:30
+    #[serde(default)]
+    pub timezone: timefmt::ChartTimezone,
+
+    /// The updates per second of the background decorations, set from the decorations config
+    /// when the coordinator is spawned
+    #[serde(skip)]
+    pub decoration_rates: Vec<f32>,
+}
+
+/// `WatchdogConfig` sets when the loaded data is considered starved: fewer than `min_items`
+/// samples were loaded across all the Prometheus series in the last `window` seconds.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub struct WatchdogConfig {
+    /// The seconds of loaded items that are added up
+    pub window: u64,
+
+    /// The minimum items expected in the window, fewer items starve the charts, defaults to
+    /// DEFAULT_WATCHDOG_MIN_ITEMS
+    #[serde(default)]
+    pub min_items: Option<usize>,
+}
+
+impl WatchdogConfig {
+    /// `min_items` returns the configured minimum items or the default one
+    pub fn min_items(&self) -> usize {
+        self.min_items.unwrap_or(DEFAULT_WATCHDOG_MIN_ITEMS)
+    }
+}
+
+/// `GovernorConfig` sets when the coordinator is overloaded: the p95 of the time it took to
+/// handle its last `window` messages is over `p95_threshold_ms`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct GovernorConfig {
+    /// The p95 in milliseconds over which the charts are degraded
+    pub p95_threshold_ms: f64,
+
+    /// The fraction of the threshold the p95 must fall under to recover, defaults to
+    /// DEFAULT_GOVERNOR_RECOVER_RATIO
+    #[serde(default)]
+    pub recover_ratio: Option<f64>,
+
+    /// The messages whose handling time is sampled, defaults to DEFAULT_GOVERNOR_WINDOW
+    #[serde(default)]
+    pub window: Option<usize>,
+
+    /// The milliseconds the charts stay degraded at least, defaults to DEFAULT_GOVERNOR_HOLD_MS
+    #[serde(default)]
+    pub hold_ms: Option<u64>,
+}
+
+impl GovernorConfig {
+    /// `threshold` returns the p95 over which the charts are degraded
+    pub fn threshold(&self) -> Duration {
+        let seconds = self.p95_threshold_ms.max(0.) / 1000.;
+        Duration::try_from_secs_f64(seconds).unwrap_or(Duration::MAX)
+    }
+
+    /// `recover_threshold` returns the p95 under which the degraded charts recover
+    pub fn recover_threshold(&self) -> Duration {
+        let ratio = self.recover_ratio.unwrap_or(DEFAULT_GOVERNOR_RECOVER_RATIO).clamp(0., 1.);
+        self.threshold().mul_f64(ratio)
+    }
+
+    /// `window` returns the configured messages sampled or the default ones, at least one
+    pub fn window(&self) -> usize {
+        self.window.unwrap_or(DEFAULT_GOVERNOR_WINDOW).max(1)
+    }
+
+    /// `hold` returns the time the charts stay degraded at least
+    pub fn hold(&self) -> Duration {
+        Duration::from_millis(self.hold_ms.unwrap_or(DEFAULT_GOVERNOR_HOLD_MS))
+    }
+}
+
+/// `JournalConfig` sets the anomalies of the loaded data that are kept in the journal of the
+/// coordinator, the oldest entries are evicted after `max_entries`.
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
+pub struct JournalConfig {
+    /// The entries kept, defaults to DEFAULT_JOURNAL_MAX_ENTRIES
+    #[serde(default)]
+    pub max_entries: Option<usize>,
+
+    /// The anomalies recorded, all of them when not set
+    #[serde(default)]
+    pub record: Option<Vec<journal::AnomalyKind>>,
+
+    /// The standard deviations from the mean of the series over which a sample is an outlier,
+    /// defaults to DEFAULT_JOURNAL_STD_DEVS
+    #[serde(default)]
+    pub std_devs: Option<f64>,
+
+    /// The pull intervals between two samples over which they are a gap, defaults to
+    /// DEFAULT_JOURNAL_GAP_INTERVALS
+    #[serde(default)]
+    pub gap_intervals: Option<u64>,
+}
+
+impl JournalConfig {
+    /// `max_entries` returns the configured entries kept or the default ones, at least one
+    pub fn max_entries(&self) -> usize {
+        self.max_entries.unwrap_or(DEFAULT_JOURNAL_MAX_ENTRIES).max(1)
+    }
+
+    /// `records` returns true when the anomalies of the kind are recorded
+    pub fn records(&self, kind: journal::AnomalyKind) -> bool {
+        self.record.as_ref().map_or(true, |record| record.contains(&kind))
+    }
+
+    /// `std_devs` returns the standard deviations over which a sample is an outlier
+    pub fn std_devs(&self) -> f64 {
+        self.std_devs.unwrap_or(DEFAULT_JOURNAL_STD_DEVS)
+    }
+
+    /// `gap_intervals` returns the pull intervals over which two samples are a gap
+    pub fn gap_intervals(&self) -> u64 {
+        self.gap_intervals.unwrap_or(DEFAULT_JOURNAL_GAP_INTERVALS).max(1)
+    }
+}
+
+/// `DataQualityConfig` sets how the missing data of a chart is marked: the share of gaps is
+/// appended to the title over `title_gap_ratio` and the series filled under `faint_fill_ratio`
+/// are drawn faint.
+#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone, Copy)]
+pub struct DataQualityConfig {
+    /// The fraction of gaps over which it is appended to the chart title, defaults to
+    /// DEFAULT_TITLE_GAP_RATIO
+    #[serde(default)]
+    pub title_gap_ratio: Option<f64>,
+
+    /// The fill ratio under which the alpha of a series is reduced, defaults to
+    /// DEFAULT_FAINT_FILL_RATIO
+    #[serde(default)]
+    pub faint_fill_ratio: Option<f64>,
+
+    /// The factor the alpha of the series filled under the faint_fill_ratio is multiplied by,
+    /// defaults to DEFAULT_FAINT_ALPHA_FACTOR
+    #[serde(default)]
+    pub faint_alpha_factor: Option<f32>,
+
+    /// Count the None values padded to keep the charts at the same latest epoch as gaps
+    #[serde(default)]
+    pub count_sync_padding: bool,
+}
+
+impl DataQualityConfig {
+    /// `title_gap_ratio` returns the fraction of gaps over which it is shown in the title
+    pub fn title_gap_ratio(&self) -> f64 {
+        self.title_gap_ratio.unwrap_or(DEFAULT_TITLE_GAP_RATIO).clamp(0., 1.)
+    }
+
+    /// `faint_fill_ratio` returns the fill ratio under which the series are faint
+    pub fn faint_fill_ratio(&self) -> f64 {
+        self.faint_fill_ratio.unwrap_or(DEFAULT_FAINT_FILL_RATIO).clamp(0., 1.)
+    }
+
+    /// `faint_alpha_factor` returns the factor of the alpha of the faint series
+    pub fn faint_alpha_factor(&self) -> f32 {
+        self.faint_alpha_factor.unwrap_or(DEFAULT_FAINT_ALPHA_FACTOR).clamp(0., 1.)
+    }
+
+    /// `fill_ratio` returns the fill ratio of the series, with the sync padding when counted
+    pub fn fill_ratio(&self, series: &TimeSeries) -> Option<f64> {
+        if self.count_sync_padding {
+            series.fill_ratio_with_padding()
+        } else {
+            series.fill_ratio()
+        }
+    }
+}
+
+/// `fill_ratio` returns the fraction of the `considered` entries that are `filled`, None when no
+/// entries are considered
+fn fill_ratio((filled, considered): (usize, usize)) -> Option<f64> {
+    if considered == 0 {
+        None
+    } else {
+        Some(filled.min(considered) as f64 / considered as f64)
+    }
+}
+
+/// `MemoryBudgetAdjustment` is a series whose capacity was reduced to fit in the memory budget
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MemoryBudgetAdjustment {
+    /// The name of the chart
+    pub chart: String,
+
+    /// The name of the series
+    pub series: String,
+
+    /// The configured capacity
+    pub from: usize,
+
+    /// The capacity after the reduction
+    pub to: usize,
+}
+
+impl fmt::Display for MemoryBudgetAdjustment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}: {} -> {}", self.chart, self.series, self.from, self.to)
+    }
+}
+
+/// The default time the display size must be stable before the charts are regenerated
+pub const DEFAULT_RESIZE_DEBOUNCE_MS: u64 = 100u64;
+
+/// The default quick retries of the first poll of a series, the network may not be up yet when
+/// the terminal starts
+pub const DEFAULT_STARTUP_RETRIES: u32 = 3u32;
+
+/// The default time between the quick retries of the first poll of a series
+pub const DEFAULT_STARTUP_RETRY_DELAY_MS: u64 = 2000u64;
+
+/// The default Prometheus requests in flight at once, the series with aligned intervals would
+/// otherwise request all at once, i.e. at startup
+pub const DEFAULT_FETCH_CONCURRENCY: usize = 8usize;
+
+/// The default time windows the charts cycle through: 5 minutes, 15 minutes and 1 hour
+pub const DEFAULT_WINDOW_PRESETS: [u64; 3] = [300u64, 900u64, 3600u64];
+
+/// The default minimum items loaded in the window of the data watchdog, a window without any
+/// loaded item is starved
+pub const DEFAULT_WATCHDOG_MIN_ITEMS: usize = 1usize;
+
+/// The default fraction of the governor threshold the p95 must fall under to recover, the gap
+/// keeps the charts from flapping between the modes
+pub const DEFAULT_GOVERNOR_RECOVER_RATIO: f64 = 0.5f64;
+
+/// The default messages whose handling time is sampled by the governor
+pub const DEFAULT_GOVERNOR_WINDOW: usize = 200usize;
+
+/// The default time the charts stay degraded at least, the deferred counters are cheap to handle
+/// and the p95 drops as soon as the charts are degraded
+pub const DEFAULT_GOVERNOR_HOLD_MS: u64 = 5000u64;
+
+/// The default entries kept in the anomaly journal
+pub const DEFAULT_JOURNAL_MAX_ENTRIES: usize = 100usize;
+
+/// The default standard deviations from the mean over which a loaded sample is an outlier
+pub const DEFAULT_JOURNAL_STD_DEVS: f64 = 4f64;
+
+/// The default pull intervals between two loaded samples over which they are a gap
+pub const DEFAULT_JOURNAL_GAP_INTERVALS: u64 = 3u64;
+
+/// The default fraction of gaps over which it is appended to the chart title
+pub const DEFAULT_TITLE_GAP_RATIO: f64 = 0.2f64;
+
+/// The default fill ratio under which the series are drawn faint
+pub const DEFAULT_FAINT_FILL_RATIO: f64 = 0.8f64;
+
+/// The default factor of the alpha of the faint series, sparse data looks faint but visible
+pub const DEFAULT_FAINT_ALPHA_FACTOR: f32 = 0.5f32;
+
+/// The default seconds the clock may move backwards before the series are recovered
+pub const DEFAULT_CLOCK_JUMP_THRESHOLD_S: u64 = 60u64;
+
+/// The timezone offsets are multiples of a quarter of an hour
+pub const CLOCK_JUMP_SHIFT_UNIT_S: u64 = 900u64;
+
+/// The seconds a clock jump may differ from a multiple of CLOCK_JUMP_SHIFT_UNIT_S to be shifted,
+/// the newest stored epoch may be older than the clock was before the jump
+pub const CLOCK_JUMP_SHIFT_TOLERANCE_S: u64 = 60u64;
+
+/// `ClockJump` is how the series recover after the system clock moved backwards, i.e. after an
+/// NTP correction or a suspend and resume, otherwise the new metrics would be discarded as too
+/// old until the clock reaches the newest stored epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClockJump {
+    /// The epochs of the series are moved back by these seconds
+    Shift(u64),
+    /// The metrics of the series are dropped and their window restarts at the current epoch
+    Rebase,
+}
+
+impl ClockJump {
+    /// `from_delta` shifts the series when the clock moved back by a multiple of
+    /// CLOCK_JUMP_SHIFT_UNIT_S, i.e. a timezone change, otherwise the jump is ambiguous and the
+    /// series are rebased.
+    pub fn from_delta(delta: u64) -> ClockJump {
+        let units = (delta + CLOCK_JUMP_SHIFT_UNIT_S / 2) / CLOCK_JUMP_SHIFT_UNIT_S;
+        let shift = units * CLOCK_JUMP_SHIFT_UNIT_S;
+        if units > 0 && shift.abs_diff(delta) <= CLOCK_JUMP_SHIFT_TOLERANCE_S {
+            ClockJump::Shift(shift)
+        } else {
+            ClockJump::Rebase
+        }
+    }
 }
 
 impl ChartsConfig {
+    /// `decoration_tick_interval` returns the interval of the decoration ticks of the
+    /// coordinator, they follow the fastest of the background decorations and the charts
+    pub fn decoration_tick_interval(&self) -> Duration {
+        let chart_rates = self.charts.iter().map(|chart| chart.update_hz());
+        animation::tick_interval(self.decoration_rates.iter().copied().chain(chart_rates))
+    }
+
+    /// `resolve_chart` returns the index of the chart referenced by a key binding, None when
+    /// there is no such chart.
+    pub fn resolve_chart(&self, chart: &ChartRef) -> Option<ChartIndex> {
+        match chart {
+            ChartRef::Index(index) => (*index < self.charts.len()).then_some(ChartIndex(*index)),
+            ChartRef::Name(name) => {
+                self.charts.iter().position(|chart| &chart.name == name).map(ChartIndex)
+            },
+        }
+    }
+
+    /// `resolve_colors` resolves the named colors of all the charts against the terminal
+    /// palette, the unknown names fall back to the foreground and are returned once each.
+    pub fn resolve_colors(&mut self, palette: &TerminalPalette) -> Vec<String> {
+        let mut unknown = vec![];
+        for chart in &mut self.charts {
+            chart.resolve_colors(palette, &mut unknown);
+        }
+        unknown.sort();
+        unknown.dedup();
+        unknown
+    }
+
+    /// `validate_loaded_items_watchers` reports the per_source async_loaded_items series whose
+    /// watch_chart and watch_series do not name an existing chart and one of its sources, these
+    /// count no loads
+    pub fn validate_loaded_items_watchers(&self) -> Result<(), String> {
+        let mut invalid = vec![];
+        for chart in &self.charts {
+            for source in &chart.sources {
+                if let TimeSeriesSource::AsyncLoadedItems(counter) = source {
+                    if counter.per_source && counter.watched_series(&self.charts).is_none() {
+                        invalid.push(format!(
+                            "{}/{} watches {}/{}",
+                            chart.name,
+                            counter.name,
+                            counter.watch_chart.as_deref().unwrap_or("<unset>"),
+                            counter.watch_series.as_deref().unwrap_or("<unset>")
+                        ));
+                    }
+                }
+            }
+        }
+        if invalid.is_empty() {
+            Ok(())
+        } else {
+            Err(format!("Unknown sources watched by async_loaded_items: {}", invalid.join(", ")))
+        }
+    }
+
+    /// `projected_memory_bytes` returns the memory the series of all the charts use once their
+    /// capacities are filled, including their vertices
+    pub fn projected_memory_bytes(&self) -> usize {
+        self.charts
+            .iter()
+            .flat_map(|chart| chart.sources.iter())
+            .map(|source| source.series_ref().projected_memory_bytes())
+            .sum()
+    }
+
+    /// `memory_bytes` returns the memory currently held by the series and vertices of all the
+    /// charts
+    pub fn memory_bytes(&self) -> usize {
+        self.charts.iter().map(|chart| chart.memory_bytes()).sum()
+    }
+
+    /// `apply_memory_budget` reduces the capacities of all the series proportionally so that
+    /// their projected memory fits in memory_budget_mb, no series is reduced under
+    /// MIN_BUDGET_CAPACITY so the projection may still be over the budget.
+    pub fn apply_memory_budget(&mut self) -> Vec<MemoryBudgetAdjustment> {
+        let budget = match self.memory_budget_mb {
+            Some(budget_mb) => budget_mb * 1024 * 1024,
+            None => return vec![],
+        };
+        let projected = self.projected_memory_bytes();
+        if projected <= budget {
+            return vec![];
+        }
+        let ratio = budget as f64 / projected as f64;
+        let mut adjustments = vec![];
+        for chart in &mut self.charts {
+            for source in &mut chart.sources {
+                let series = source.series_mut();
+                let capacity = series.budget_capacity();
+                let reduced =
+                    ((capacity as f64 * ratio) as usize).max(MIN_BUDGET_CAPACITY.min(capacity));
+                if reduced >= capacity {
+                    continue;
+                }
+                series.limit_capacity(reduced);
+                adjustments.push(MemoryBudgetAdjustment {
+                    chart: chart.name.clone(),
+                    series: source.name(),
+                    from: capacity,
+                    to: reduced,
+                });
+            }
+        }
+        let adjusted: Vec<String> = adjustments.iter().map(|x| x.to_string()).collect();
+        event!(
+            Level::WARN,
+            "apply_memory_budget: Projected {} bytes over the budget of {} bytes, reduced the \
+             capacities: {}",
+            projected,
+            budget,
+            adjusted.join(", ")
+        );
+        let projected = self.projected_memory_bytes();
+        if projected > budget {
+            event!(
+                Level::WARN,
+                "apply_memory_budget: Projected {} bytes are still over the budget, the series \
+                 are at their minimum capacity of {}",
+                projected,
+                MIN_BUDGET_CAPACITY
+            );
+        }
+        adjustments
+    }
+
+    /// `summarize_since` returns the summary of each chart with values between `epoch` and `now`
+    pub fn summarize_since(&self, epoch: u64, now: u64) -> Vec<ChartSummary> {
+        self.charts.iter().filter_map(|chart| chart.summary_since(epoch, now)).collect()
+    }
+
+    /// `newest_epoch` returns the newest epoch stored by the series of all the charts
+    pub fn newest_epoch(&self) -> Option<u64> {
+        self.charts
+            .iter()
+            .flat_map(|chart| chart.sources.iter())
+            .filter_map(|source| source.series_ref().newest_epoch())
+            .max()
+    }
+
+    /// `recover_clock_jump` detects that the clock moved backwards when `now` is older than the
+    /// newest stored epoch by more than the threshold. The series are shifted back when the jump
+    /// is a timezone offset and rebased at `now` otherwise. The prometheus series are always
+    /// rebased, their next poll loads the samples with the timestamps of the server.
+    pub fn recover_clock_jump(&mut self, now: u64) -> Option<ClockJump> {
+        let threshold = self.clock_jump_threshold_s.unwrap_or(DEFAULT_CLOCK_JUMP_THRESHOLD_S);
+        let delta = self.newest_epoch()?.checked_sub(now)?;
+        if delta <= threshold {
+            return None;
+        }
+        let jump = ClockJump::from_delta(delta);
+        event!(
+            Level::WARN,
+            "recover_clock_jump: The clock moved back {}s to {}, recovering the series: {:?}",
+            delta,
+            now,
+            jump
+        );
+        for chart in &mut self.charts {
+            for source in &mut chart.sources {
+                let is_prometheus = matches!(source, TimeSeriesSource::PrometheusTimeSeries(_));
+                match jump {
+                    ClockJump::Shift(shift) if !is_prometheus => {
+                        source.series_mut().shift_epochs(shift)
+                    },
+                    _ => source.series_mut().rebase(now),
+                }
+            }
+            chart.calculate_stats();
+        }
+        Some(jump)
+    }
+
     /// Goes through the charts inside the ChartConfig and if position is not set it calculates it.
     pub fn setup_chart_spacing(&mut self) {
-        let mut current_position = self.position;
-        for chart in &mut self.charts {
+        let positions = self.chart_positions();
+        for (chart, position) in self.charts.iter_mut().zip(positions) {
             if chart.position.is_none() {
-                current_position = if let (Some(position), Some(dimensions)) =
-                    (current_position, self.default_dimensions)
-                {
-                    chart.position = current_position;
-                    Some(Value2D { x: position.x + dimensions.x + self.spacing, y: 0. })
-                } else {
+                if position.is_none() {
                     event!(
                         Level::ERROR,
                         "setup_chart_spacing: default dimensions and position were not given for \
                          charts and dimensions and positions are missing for chart: {}",
                         chart.name
                     );
-                    self.position
                 }
+                chart.position = position;
             }
             if chart.dimensions.is_none() {
                 chart.dimensions = self.default_dimensions;
             }
+            if chart.data_quality.is_none() {
+                chart.data_quality = self.data_quality;
+            }
+            chart.window_overflow = self.overflow;
+            chart.palette_mode = self.palette_mode;
+        }
+    }
+
+    /// `chart_positions` returns the position of each chart, the charts without a position are
+    /// placed one after the other starting at the ChartsConfig position.
+    fn chart_positions(&self) -> Vec<Option<ChartLength2D>> {
+        let mut current_position = self.position;
+        self.charts
+            .iter()
+            .map(|chart| {
+                if chart.position.is_some() {
+                    return chart.position;
+                }
+                match (current_position, self.default_dimensions) {
+                    (Some(position), Some(dimensions)) => {
+                        current_position = Some(position.right_of(&dimensions, self.spacing));
+                        Some(position)
+                    },
+                    _ => {
+                        current_position = self.position;
+                        None
+                    },
+                }
+            })
+            .collect()
+    }
+
+    /// `compact_dump` serializes the charts with their metrics rounded to compact_decimals, this
+    /// keeps dumps of many series small and readable in diffs. The charts are not modified.
+    pub fn compact_dump(&self) -> Result<String, serde_json::Error> {
+        let decimals = self.compact_decimals.unwrap_or(DEFAULT_COMPACT_DECIMALS);
+        let mut compact = self.clone();
+        for source in compact.charts.iter_mut().flat_map(|chart| chart.sources.iter_mut()) {
+            let series = source.series_mut();
+            *series = series.compact(decimals);
+        }
+        serde_json::to_string(&compact)
+    }
+
+    /// `link_at_pixel` returns the rendered link of the chart drawn at the x,y window pixel, if
+    /// the chart has a link configured.
+    pub fn link_at_pixel(&self, x: f32, y: f32, size: SizeInfo) -> Option<String> {
+        let dpr = size.scale_factor();
+        let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+        self.charts.iter().zip(self.chart_positions()).find_map(|(chart, position)| {
+            chart.link.as_ref()?;
+            let dimensions = chart.dimensions.or(self.default_dimensions)?.resolve(&size);
+            // The link follows the chart where it is drawn, after it was fit in the window.
+            let position = position?.resolve(&size).scaled(dpr);
+            let (position, dimensions) =
+                self.overflow.resolve(position, dimensions.scaled(dpr), &size)?;
+            let chart_size = ChartSizeInfo {
+                term_size: size,
+                chart_width: dimensions.x,
+                chart_height: dimensions.y,
+                ..ChartSizeInfo::default()
+            };
+            let rect = chart_size.chart_pixel_rect(position);
+            if !rect.contains(x, y) {
+                return None;
+            }
+            // Charts split into small multiples link to the series of the clicked cell.
+            let series = chart.small_multiples.and_then(|small_multiples| {
+                let cells = small_multiples.cells(chart.series_count(), rect, dpr, &size);
+                cells.iter().position(|cell| cell.rect.contains(x, y)).map(SeriesIndex)
+            });
+            chart.render_series_link(series, now)
+        })
+    }
+
+    /// `covered_rows` returns the terminal lines, relative to the top of the viewport, that are
+    /// fully or partially covered by the charts. Charts are drawn from the bottom of the padded
+    /// area up to their height, so the tallest chart decides which lines are covered.
+    pub fn covered_rows(&self, size: &SizeInfo) -> Vec<Line> {
+        if size.cell_height <= 0. || size.screen_lines == 0 {
+            return vec![];
+        }
+        let max_chart_height = self
+            .charts
+            .iter()
+            .filter_map(|chart| chart.dimensions.or(self.default_dimensions))
+            .map(|dimensions| dimensions.resolve(size).y * size.scale_factor())
+            .fold(0f32, f32::max);
+        if max_chart_height <= 0. {
+            return vec![];
         }
+        // The pixel, from the top of the window, at which the tallest chart starts.
+        let chart_top = size.height - size.padding_y - max_chart_height;
+        let first_line = ((chart_top - size.padding_y) / size.cell_height).floor().max(0.) as usize;
+        (first_line..size.screen_lines).map(|line| Line(line as i32)).collect()
     }
 
     /// Ensures that all the dashboards contain the same latest epoch.
@@ -413,7 +3134,7 @@ impl ChartsConfig {
             .map(|x| {
                 if x.last_updated < max {
                     let total_updated =
-                        x.sources.iter_mut().map(|x| x.series_mut().upsert((max, None))).sum();
+                        x.sources.iter_mut().map(|x| x.series_mut().pad_to_epoch(max)).sum();
                     x.update_all_series_opengl_vecs(size_info);
                     total_updated
                 } else {
@@ -425,6 +3146,17 @@ impl ChartsConfig {
     }
 }
 
+/// `DiscoveryChanges` contains the indexes of the series whose polling should start or stop
+/// after the label values of a discovery are applied
+#[derive(Debug, Default, Clone, PartialEq, Eq)]
+pub struct DiscoveryChanges {
+    /// The series that were added, reused a slot or whose label value returned
+    pub started: Vec<usize>,
+
+    /// The series whose label value has been missing for the grace period
+    pub retired: Vec<usize>,
+}
+
 /// `TimeSeriesChart` has an array of TimeSeries to display, it contains the
 /// X, Y position and has methods to draw in opengl.
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone)]
@@ -436,6 +3168,10 @@ pub struct TimeSeriesChart {
     #[serde(rename = "series")]
     pub sources: Vec<TimeSeriesSource>,
 
+    /// Overlay series computed from the sources, i.e. the rate of change of a counter
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub derived: Vec<DerivedSeries>,
+
     /// Decorations such as color, transparency, etc
     #[serde(default)]
     pub decorations: Vec<Decoration>,
@@ -447,23 +3183,257 @@ pub struct TimeSeriesChart {
     /// The x,y position in which the time series should be drawn
     /// If unspecified, a position will be reserved from the ChartsConfig offset values.
     #[serde(default)]
-    pub position: Option<Value2D>,
+    pub position: Option<ChartLength2D>,
 
     /// The dimensions of the chart.
     /// If unspecified the default_dimensions are used from ChartsConfig
     #[serde(default)]
-    pub dimensions: Option<Value2D>,
+    pub dimensions: Option<ChartLength2D>,
 
     /// The opengl representation of the each series.
-    #[serde(default)]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
     pub opengl_vecs: Vec<Vec<f32>>,
 
     /// Last updated epoch
     #[serde(default)]
     pub last_updated: u64,
+
+    /// The area of the chart in normalized device coordinates
+    #[serde(default)]
+    pub ndc_rect: NdcRect,
+
+    /// Allow the series lines to be drawn outside of the chart area, by default they are clipped
+    #[serde(default)]
+    pub overflow: bool,
+
+    /// The area of the chart in window pixels, used to scissor the draw calls
+    #[serde(default)]
+    pub pixel_rect: PixelRect,
+
+    /// How the metric values are mapped to the Y axis
+    #[serde(default)]
+    pub y_scale_type: YScaleType,
+
+    /// Expand the linear Y axis to round numbers that only change when the values leave them or
+    /// shrink under NICE_SCALE_HYSTERESIS of them, ignored in Log10 mode
+    #[serde(default)]
+    pub nice_scale: bool,
+
+    /// The nice bounds of the Y axis currently in use
+    #[serde(skip)]
+    pub nice_bounds: Option<(f64, f64)>,
+
+    /// The fraction of the chart height, from the bottom, where zero is drawn, the Y axis is
+    /// expanded to keep it there, i.e. 0.5 for values around zero. Ignored in Log10 mode
+    #[serde(default)]
+    pub baseline: Option<f64>,
+
+    /// The value at the bottom of the Y axis instead of the min of the values, the lower values
+    /// are drawn at the bottom edge. Ignored in Log10 mode
+    #[serde(default)]
+    pub y_min: Option<f64>,
+
+    /// The value at the top of the Y axis instead of the max of the values, the higher values
+    /// are drawn at the top edge. Ignored in Log10 mode
+    #[serde(default)]
+    pub y_max: Option<f64>,
+
+    /// Do not ask the terminal to redraw when data is loaded, i.e. for charts used to collect
+    /// data for an export
+    #[serde(default)]
+    pub suppress_redraw: bool,
+
+    /// The number of samples of each source that are currently clamped by the Y scale
+    #[serde(skip)]
+    pub clamped_samples: Vec<usize>,
+
+    /// A URL opened when the chart is clicked, the placeholders {query}, {start} and {end} are
+    /// replaced with the query of the first prometheus source and the visible epoch window
+    #[serde(default)]
+    pub link: Option<String>,
+
+    /// Whether the series are drawn as lines or the latest value as a numeric readout
+    #[serde(default)]
+    pub chart_style: ChartStyle,
+
+    /// The difference between the latest samples that the numeric readout shows as a flat
+    /// trend, defaults to DEFAULT_TREND_EPSILON
+    #[serde(default)]
+    pub trend_epsilon: Option<f64>,
+
+    /// The alpha of an exponential moving average of the drawn values of the lines, between 0
+    /// and 1, a lower alpha draws a smoother line. The values are drawn as they are when not set
+    #[serde(default)]
+    pub smoothing: Option<f32>,
+
+    /// How the sources with different capacities are drawn
+    #[serde(default)]
+    pub capacity_policy: CapacityPolicy,
+
+    /// Whether the line series are drawn over each other or stacked
+    #[serde(default)]
+    pub layout: SeriesLayout,
+
+    /// Draw the series as SMOOTH_LINE_PIXELS wide triangle strips with faded edges, a cheap
+    /// anti-aliasing of the lines
+    #[serde(default)]
+    pub smooth: bool,
+
+    /// Split the chart into a cell per series instead of overlaying them
+    #[serde(default)]
+    pub small_multiples: Option<SmallMultiplesConfig>,
+
+    /// The small multiples cells of each series, empty when the chart is not split
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub cells: Vec<ChartCell>,
+
+    /// What happens when the chart does not fit in the window, set from the ChartsConfig
+    #[serde(skip)]
+    pub window_overflow: ChartOverflow,
+
+    /// The palette of the series without a color, set from the ChartsConfig
+    #[serde(skip)]
+    pub palette_mode: PaletteMode,
+
+    /// The chart does not fit in the window and is not drawn
+    #[serde(skip)]
+    pub hidden: bool,
+
+    /// Stamp the internal counters of the chart with the clock of its Prometheus series, shifted
+    /// by the epoch_skew_secs, so that they line up when the clocks disagree
+    #[serde(default)]
+    pub align_to_remote: bool,
+
+    /// The seconds the newest epoch of the Prometheus series is ahead of the coordinator clock,
+    /// negative when it is behind, None until the series loaded data
+    #[serde(skip)]
+    pub epoch_skew_secs: Option<i64>,
+
+    /// The bands of the timeline sources as drawn in window pixels, for the hover readout
+    #[serde(skip)]
+    pub timeline_bands: Vec<TimelineBand>,
+
+    /// The epoch drawn at the first tick of the X axis of the first series. The epochs are
+    /// converted to pixels relative to it with `epoch_to_x` and `x_to_epoch`, as an f32 cannot
+    /// tell apart the seconds of an epoch.
+    #[serde(skip)]
+    pub time_origin: u64,
+
+    /// The window pixel of the first tick of the X axis
+    #[serde(skip)]
+    pub time_origin_x: f32,
+
+    /// The pixels between the ticks of the X axis, a tick per second
+    #[serde(skip)]
+    pub tick_spacing: f32,
+
+    /// Move the vertices of the series from their previous position when they are regenerated
+    /// instead of redrawing them at once
+    #[serde(default)]
+    pub animate_updates: Option<AnimationConfig>,
+
+    /// The transitions of each series, empty unless animate_updates is set
+    #[serde(skip)]
+    pub animations: Vec<SeriesAnimation>,
+
+    /// The decoration ticks per second that advance the transitions, defaults to
+    /// DEFAULT_UPDATE_HZ, 0 draws the regenerated vertices at once
+    #[serde(default)]
+    pub update_hz: Option<f32>,
+
+    /// The cadence of the transitions, set from update_hz by the coordinator
+    #[serde(skip)]
+    pub tick_schedule: TickSchedule,
+
+    /// The fraction of the chart width the decorations may take, their space is scaled down
+    /// when they request more, defaults to DEFAULT_MAX_DECORATIONS_FRACTION
+    #[serde(default)]
+    pub max_decorations_fraction: Option<f32>,
+
+    /// The space of the decorations is scaled down to fit in the chart
+    #[serde(skip)]
+    pub decorations_scaled: bool,
+
+    /// The chart is too narrow to draw the series, the placeholder is drawn instead
+    #[serde(skip)]
+    pub too_narrow: bool,
+
+    /// Draw the running value of the second in progress of the internal counters at the right
+    /// edge, the samples only land once their second completes
+    #[serde(default)]
+    pub live_edge: bool,
+
+    /// The provisional vertex of each series at the live edge, None without a running value
+    #[serde(skip)]
+    pub live_edge_vertices: Vec<Option<(f32, f32)>>,
+
+    /// The Y coordinate the bars of each series start from
+    #[serde(skip)]
+    pub bar_bases: Vec<f32>,
+
+    /// How the missing data of the chart is marked, defaults to the data_quality of the
+    /// ChartsConfig
+    #[serde(default)]
+    pub data_quality: Option<DataQualityConfig>,
 }
 
-impl TimeSeriesChart {
+impl TimeSeriesChart {
+    /// `summary_since` summarizes the values of the sources of the chart between `epoch` and
+    /// `now`, merged across the sources. None when none of them has values in the range.
+    pub fn summary_since(&self, epoch: u64, now: u64) -> Option<ChartSummary> {
+        self.sources
+            .iter()
+            .filter_map(|source| source.series_ref().window_summary(epoch, now))
+            .reduce(WindowSummary::merge)
+            .map(|summary| ChartSummary::new(&self.name, summary))
+    }
+
+    /// `update_epoch_skew` measures the epoch_skew_secs of the chart against the coordinator
+    /// clock, the charts without Prometheus data do not have a skew.
+    pub fn update_epoch_skew(&mut self, now: u64) {
+        self.epoch_skew_secs = self
+            .sources
+            .iter()
+            .filter(|source| matches!(source, TimeSeriesSource::PrometheusTimeSeries(_)))
+            .filter_map(|source| source.series_ref().newest_epoch())
+            .max()
+            .map(|newest| newest as i64 - now as i64);
+    }
+
+    /// `counter_epoch` returns the epoch at which the internal counters of the chart are
+    /// incremented at the coordinator clock `now`, shifted by the epoch skew when aligned.
+    pub fn counter_epoch(&self, now: u64) -> u64 {
+        match self.epoch_skew_secs {
+            Some(skew) if self.align_to_remote => now.saturating_add_signed(skew),
+            _ => now,
+        }
+    }
+
+    /// `epoch_to_x` returns the window pixel of the X axis where `epoch` is drawn. The epoch is
+    /// made relative to the time_origin before it is converted to a float.
+    pub fn epoch_to_x(&self, epoch: u64) -> f32 {
+        let seconds = if epoch >= self.time_origin {
+            (epoch - self.time_origin) as f64
+        } else {
+            -((self.time_origin - epoch) as f64)
+        };
+        self.time_origin_x + (seconds * self.tick_spacing as f64) as f32
+    }
+
+    /// `x_to_epoch` returns the epoch drawn at the `x` window pixel, rounded to the nearest tick,
+    /// None before the vertices are generated or for a pixel before the epoch 0.
+    pub fn x_to_epoch(&self, x: f32) -> Option<u64> {
+        if self.tick_spacing <= 0f32 {
+            return None;
+        }
+        let seconds = ((x - self.time_origin_x) as f64 / self.tick_spacing as f64).round();
+        if seconds >= 0f64 {
+            self.time_origin.checked_add(seconds as u64)
+        } else {
+            self.time_origin.checked_sub(-seconds as u64)
+        }
+    }
+
     /// `update_series_opengl_vecs` Represents the metric TimeSeries in a
     /// drawable vector for opengl, for a specific index in the series array
     pub fn update_series_opengl_vecs(&mut self, series_idx: usize, display_size: ChartSizeInfo) {
@@ -474,28 +3444,130 @@ impl TimeSeriesChart {
             series_idx = series_idx,
         );
         let _enter = span.enter();
-        if series_idx >= self.sources.len() {
+        if series_idx >= self.series_count() {
             event!(
-                Level::ERROR,
+                Level::WARN,
                 "update_series_opengl_vecs: Request for index out of bounds: {}",
                 series_idx
             );
             return;
         }
-        while self.opengl_vecs.len() <= self.sources.len() {
-            self.opengl_vecs.push(vec![]);
-        }
+        // Dimensions, positions and decoration widths are configured in logical pixels.
+        let dpr = display_size.term_size.scale_factor();
         let mut display_size = display_size;
+        display_size.y_scale = self.y_scale_type;
         if let Some(dimensions) = self.dimensions {
-            display_size.chart_height = dimensions.y;
-            display_size.chart_width = dimensions.x;
+            let dimensions = dimensions.resolve(&display_size.term_size);
+            display_size.chart_height = dimensions.y * dpr;
+            display_size.chart_width = dimensions.x * dpr;
         } else {
             // TODO: When the charts are first read, they should compose the dimensions.
             // If we hit this, then we should recalculate from the global ChartsConfig default
             // dimensions somehow
         }
+        // Join all the stats max/min/etc, this time not for individual metrics but from them
+        // together
+        self.calculate_stats();
+        display_size.y_bounds = self.pinned_y_bounds();
+        // The vertices are generated for the chart as it fits in the window, the configured
+        // position and dimensions are kept for when the window grows again.
+        let configured_position =
+            self.position.unwrap_or_default().resolve(&display_size.term_size).scaled(dpr);
+        let configured_dimensions =
+            Value2D { x: display_size.chart_width, y: display_size.chart_height };
+        let resolved = self.window_overflow.resolve(
+            configured_position,
+            configured_dimensions,
+            &display_size.term_size,
+        );
+        let position = match resolved {
+            Some((position, dimensions)) => {
+                self.hidden = false;
+                display_size.chart_width = dimensions.x;
+                display_size.chart_height = dimensions.y;
+                position
+            },
+            None => {
+                if !self.hidden {
+                    event!(
+                        Level::WARN,
+                        "update_series_opengl_vecs: Chart {} does not fit in the window, hiding it",
+                        self.name
+                    );
+                }
+                self.hide();
+                return;
+            },
+        };
+        if self.chart_style == ChartStyle::Numeric {
+            // The numeric readout is drawn as text inside the chart area.
+            self.pixel_rect = display_size.chart_pixel_rect(position);
+            self.last_updated =
+                std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+            return;
+        }
+        let mut decorations_space = 0f32;
+        for decoration in &self.decorations {
+            event!(
+                Level::DEBUG,
+                "update_series_opengl_vecs: Adding width of decoration: {}",
+                decoration.width()
+            );
+            decorations_space += decoration.width() * dpr;
+        }
+        event!(
+            Level::DEBUG,
+            "update_series_opengl_vecs: width: {}, decorations_space: {}",
+            display_size.chart_width,
+            decorations_space
+        );
+        let max_fraction =
+            self.max_decorations_fraction.unwrap_or(DEFAULT_MAX_DECORATIONS_FRACTION);
+        let chart_width = display_size.chart_width;
+        let decorations_space =
+            match fit_decorations_space(chart_width, decorations_space, max_fraction) {
+                Some(fitted) => {
+                    let scaled = fitted < decorations_space;
+                    if scaled && !self.decorations_scaled {
+                        event!(
+                            Level::WARN,
+                            "update_series_opengl_vecs: Chart {} is {}px wide, the {}px of its \
+                             decorations are scaled down to {}px",
+                            self.name,
+                            chart_width,
+                            decorations_space,
+                            fitted
+                        );
+                    }
+                    self.decorations_scaled = scaled;
+                    fitted
+                },
+                None => {
+                    if !self.too_narrow {
+                        event!(
+                            Level::WARN,
+                            "update_series_opengl_vecs: Chart {} is {}px wide, too narrow to draw",
+                            self.name,
+                            chart_width
+                        );
+                    }
+                    self.skip_too_narrow(display_size.chart_pixel_rect(position));
+                    return;
+                },
+            };
+        self.too_narrow = false;
+        while self.opengl_vecs.len() <= self.series_count() {
+            self.opengl_vecs.push(vec![]);
+        }
+        let series = match self.series_at(series_idx) {
+            Some(series) => series,
+            None => return,
+        };
+        let (x_capacity, x_offset_ticks) = self.x_ticks(series.metrics_capacity());
+        let is_timeline = self.is_timeline(series_idx);
+        let has_fixed_y_bounds = self.has_fixed_y_bounds();
         // Get the opengl representation of the vector
-        let opengl_vecs_capacity = self.sources[series_idx].series().active_items;
+        let opengl_vecs_capacity = series.active_items;
         event!(
             Level::DEBUG,
             "self: {:?}, self.opengl_vecs.capacity(): {}, self.sources.capacity(): {}, \
@@ -505,9 +3577,13 @@ impl TimeSeriesChart {
             self.sources.capacity(),
             series_idx,
         );
-        if opengl_vecs_capacity > self.opengl_vecs[series_idx].capacity() {
-            let missing_capacity = opengl_vecs_capacity - self.opengl_vecs[series_idx].capacity();
-            self.opengl_vecs[series_idx].reserve(missing_capacity);
+        let opengl_vecs = match self.opengl_vecs.get_mut(series_idx) {
+            Some(opengl_vecs) => opengl_vecs,
+            None => return,
+        };
+        if opengl_vecs_capacity > opengl_vecs.capacity() {
+            let missing_capacity = opengl_vecs_capacity - opengl_vecs.capacity();
+            opengl_vecs.reserve(missing_capacity);
         }
         event!(
             Level::DEBUG,
@@ -517,73 +3593,1006 @@ impl TimeSeriesChart {
             display_size,
             self.position,
         );
-        // Join all the stats max/min/etc, this time not for individual metrics but from them
-        // together
-        self.calculate_stats();
-        let mut decorations_space = 0f32;
-        for decoration in &self.decorations {
-            event!(
-                Level::DEBUG,
-                "update_series_opengl_vecs: Adding width of decoration: {}",
-                decoration.width()
-            );
-            decorations_space += decoration.width();
-        }
-        event!(
-            Level::DEBUG,
-            "update_series_opengl_vecs: width: {}, decorations_space: {}",
-            display_size.chart_width,
-            decorations_space
-        );
-        let missing_values_fill = self.sources[series_idx].series().get_missing_values_fill();
         event!(
             Level::DEBUG,
-            "update_series_opengl_vecs: Using {} to fill missing values. Metrics[{}]: {:?}",
-            missing_values_fill,
-            self.sources[series_idx].series().metrics_capacity,
-            self.sources[series_idx].series()
+            "update_series_opengl_vecs: Using {:?} to fill missing values. Metrics[{}]: {:?}",
+            series.missing_values_policy,
+            series.metrics_capacity(),
+            series
         );
+        self.ndc_rect = NdcRect {
+            left: display_size.scale_x(position.x),
+            right: display_size.scale_x(position.x + display_size.chart_width),
+            bottom: display_size.term_size.scale_y(0f32),
+            top: display_size.term_size.scale_y(display_size.chart_height),
+        };
+        self.pixel_rect = display_size.chart_pixel_rect(position);
+        self.cells = match &self.small_multiples {
+            Some(small_multiples) => small_multiples.cells(
+                self.sources.len() + self.derived.len(),
+                self.pixel_rect,
+                dpr,
+                &display_size.term_size,
+            ),
+            None => vec![],
+        };
+        // Small multiples draw the series in its own cell, the decorations span the whole chart.
+        let (area_size, area_x, area_bottom, area_decorations_space, stats) =
+            match self.cells.get(series_idx) {
+                Some(cell) => {
+                    let term_size = display_size.term_size;
+                    let shared = self.small_multiples.map_or(true, |sm| sm.shared_y_scale);
+                    // The nice bounds are chosen for the stats of the whole chart.
+                    let area_size = ChartSizeInfo {
+                        chart_width: cell.rect.width,
+                        chart_height: cell.rect.height,
+                        y_bounds: if shared { display_size.y_bounds } else { None },
+                        ..display_size
+                    };
+                    // The vertices are scaled from the bottom of the padded area, then moved up
+                    // to the bottom of the cell.
+                    let bottom =
+                        term_size.height - term_size.padding_y - cell.rect.y - cell.rect.height;
+                    let stats = if shared { &self.stats } else { &series.stats };
+                    (area_size, cell.rect.x - term_size.padding_x, bottom, 0f32, stats)
+                },
+                None => (display_size, position.x, 0f32, decorations_space, &self.stats),
+            };
+        let y_shift = 2f32 * area_bottom / display_size.term_size.height;
+        // The values out of the fixed bounds are drawn at the edge of the chart.
+        let clamp_bounds = area_size.y_bounds.filter(|_| has_fixed_y_bounds);
+        let bar_base = area_size.bar_base_ndc_y(stats) + y_shift;
         // The tick spacing determines the distance between one drawable metric and the next
-        let tick_spacing = (display_size.chart_width - decorations_space)
-            / self.sources[series_idx].series().metrics_capacity as f32;
+        let drawable_width =
+            (area_size.chart_width - area_decorations_space).max(MIN_DRAWABLE_WIDTH);
+        let tick_spacing = drawable_width / x_capacity as f32;
         event!(Level::DEBUG, "update_series_opengl_vecs: Using tick_spacing {}", tick_spacing);
         // The decorations width request is on both left and right sides.
-        let decoration_offset = decorations_space / 2f32;
-        for (idx, metric) in self.sources[series_idx].series().iter().enumerate() {
-            let x_value = idx as f32 * tick_spacing + decoration_offset;
-            // If there is a Marker Line, it takes 10% of the initial horizontal space
-            let y_value = match metric.1 {
-                Some(x) => x,
-                None => missing_values_fill,
+        let decoration_offset = area_decorations_space / 2f32;
+        // The time axis of the chart follows its first series, its oldest sample is drawn
+        // x_offset_ticks from the first tick.
+        if series_idx == 0 {
+            let oldest_epoch = series.oldest_epoch().unwrap_or_default();
+            self.time_origin = oldest_epoch.saturating_sub(x_offset_ticks as u64);
+            self.time_origin_x = display_size.term_size.padding_x + area_x + decoration_offset;
+            self.tick_spacing = tick_spacing;
+        }
+        let mut clamped_samples = 0usize;
+        let mut live_edge_vertex = None;
+        if is_timeline {
+            // The runs extend to the next sample, the newest one a tick past the last sample.
+            let x_values: Vec<f32> = (0..=series.active_items)
+                .map(|idx| {
+                    (idx + x_offset_ticks) as f32 * tick_spacing + decoration_offset + area_x
+                })
+                .collect();
+            self.update_timeline_vecs(series_idx, &series, area_size, area_bottom, &x_values);
+        } else {
+            // The vertices are only kept for the transitions of the animated charts.
+            let previous = self.animate_updates.map(|_| opengl_vecs.clone());
+            for (idx, (_, y_value)) in series.filled_iter().enumerate() {
+                let x_value = (idx + x_offset_ticks) as f32 * tick_spacing + decoration_offset;
+                // If there is a Marker Line, it takes 10% of the initial horizontal space
+                if self.y_scale_type.is_clamped(y_value) {
+                    clamped_samples += 1;
+                }
+                let y_value = match clamp_bounds {
+                    Some((lo, hi)) if y_value < lo || y_value > hi => {
+                        clamped_samples += 1;
+                        y_value.clamp(lo, hi)
+                    },
+                    _ => y_value,
+                };
+                let scaled_x = area_size.scale_x(x_value + area_x);
+                let scaled_y = area_size.value_to_ndc_y(y_value, stats) + y_shift;
+                // Adding twice to a vec, could this be made into one operation? Is this slow?
+                // need to transform activity line values from varying levels into scaled [-1, 1]
+                // XXX: Move to Circular Buffer? Problem is Circular buffer is only meant for epochs
+                if (idx + 1) * 2 > opengl_vecs.len() {
+                    opengl_vecs.push(scaled_x);
+                    opengl_vecs.push(scaled_y);
+                } else {
+                    opengl_vecs[idx * 2] = scaled_x;
+                    opengl_vecs[idx * 2 + 1] = scaled_y;
+                }
+            }
+            // The provisional value is drawn at the tick of its epoch, at most at the right edge.
+            // It is the running value of the epoch, so it is not drawn for a transformed series.
+            let provisional = match self.sources.get(series_idx).and_then(|x| x.live_counter()) {
+                Some(counter) if self.has_live_edge(series_idx) && counter.transform.is_none() => {
+                    counter.provisional
+                },
+                _ => None,
+            };
+            if let (Some((epoch, value)), Some(oldest_epoch)) = (provisional, series.oldest_epoch())
+            {
+                let ticks = epoch.saturating_sub(oldest_epoch) as usize + x_offset_ticks;
+                let x_value = ticks.min(x_capacity) as f32 * tick_spacing + decoration_offset;
+                let value = clamp_bounds.map_or(value, |(lo, hi)| value.clamp(lo, hi));
+                let scaled_y = area_size.value_to_ndc_y(value, stats) + y_shift;
+                live_edge_vertex = Some((area_size.scale_x(x_value + area_x), scaled_y));
+            }
+            if let Some(previous) = previous {
+                self.animate_series(series_idx, &previous, &series, display_size);
+            }
+        }
+        if self.live_edge_vertices.len() < self.series_count() {
+            self.live_edge_vertices.resize(self.series_count(), None);
+        }
+        self.live_edge_vertices[series_idx] = live_edge_vertex;
+        if self.bar_bases.len() < self.series_count() {
+            self.bar_bases.resize(self.series_count(), 0f32);
+        }
+        self.bar_bases[series_idx] = bar_base;
+        if self.clamped_samples.len() < self.series_count() {
+            self.clamped_samples.resize(self.series_count(), 0usize);
+        }
+        if clamped_samples > 0 {
+            event!(
+                Level::DEBUG,
+                "update_series_opengl_vecs: {} samples clamped by {:?}",
+                clamped_samples,
+                self.y_scale_type
+            );
+        }
+        self.clamped_samples[series_idx] = clamped_samples;
+        for decoration in &mut self.decorations {
+            event!(
+                Level::DEBUG,
+                "update_series_opengl_vecs: Updating decoration {:?} vertices",
+                decoration
+            );
+            decoration.update_opengl_vecs(display_size, position, &self.stats, &self.sources);
+        }
+        self.last_updated =
+            std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
+    }
+
+    /// `animate_series` starts a transition of a series from the `previous` vertices, as they are
+    /// drawn, to the ones just generated. The vertices are aligned by the epochs of their samples.
+    /// The transition is skipped when the chart is resized, the vertices are then drawn at once.
+    fn animate_series(
+        &mut self,
+        series_idx: usize,
+        previous: &[f32],
+        series: &TimeSeries,
+        display_size: ChartSizeInfo,
+    ) {
+        let config = match self.animate_updates {
+            Some(config) => config,
+            None => return,
+        };
+        if self.animations.len() < self.series_count() {
+            self.animations.resize(self.series_count(), SeriesAnimation::default());
+        }
+        let epochs: Vec<u64> = series.iter().map(|(epoch, _)| *epoch).collect();
+        // The transitions last the decoration ticks of the chart, there are none at 0 Hz.
+        let tick_ms =
+            TickSchedule::new(self.update_hz()).period().map(|tick| tick.as_millis() as u64);
+        let vertices = &self.opengl_vecs[series_idx];
+        let current = &vertices[..vertices.len().min(epochs.len() * 2)];
+        let animation = &mut self.animations[series_idx];
+        let previous = &previous[..previous.len().min(animation.epochs.len() * 2)];
+        let from = animation::align_by_epoch(
+            &animation.drawn(previous),
+            &animation.epochs,
+            current,
+            &epochs,
+        );
+        let resized = animation.size != display_size;
+        animation.transition = match tick_ms {
+            Some(tick_ms) if !resized && !previous.is_empty() && from != current => {
+                Some(VertexTransition::new(from, &config, tick_ms))
+            },
+            _ => None,
+        };
+        animation.epochs = epochs;
+        animation.size = display_size;
+    }
+
+    /// `update_hz` returns the decoration ticks per second that advance the transitions
+    pub fn update_hz(&self) -> f32 {
+        self.update_hz.unwrap_or(animation::DEFAULT_UPDATE_HZ)
+    }
+
+    /// `advance_animations` counts a decoration tick for the running transitions of the series,
+    /// returns true if any was running so that the chart is drawn again.
+    pub fn advance_animations(&mut self) -> bool {
+        let mut running = false;
+        for animation in &mut self.animations {
+            if let Some(transition) = animation.transition.as_mut() {
+                running = true;
+                if transition.advance() {
+                    animation.transition = None;
+                }
+            }
+        }
+        running
+    }
+
+    /// `animated_opengl_vecs` returns the vertices of a series as drawn by its running transition
+    fn animated_opengl_vecs(&self, series_idx: usize) -> Option<Vec<f32>> {
+        let transition = self.animations.get(series_idx)?.transition.as_ref()?;
+        self.opengl_vecs.get(series_idx).map(|opengl_vecs| transition.interpolate(opengl_vecs))
+    }
+
+    /// `update_timeline_vecs` replaces the vertices of a timeline source with a quad per run of
+    /// equal values. The band is placed in its slot from `area_bottom`, the pixels above the
+    /// padded bottom of the window where the area of the series starts. `x_values` are the
+    /// pixels of each sample from the left padding, followed by the end of the newest run.
+    fn update_timeline_vecs(
+        &mut self,
+        series_idx: usize,
+        series: &TimeSeries,
+        area_size: ChartSizeInfo,
+        area_bottom: f32,
+        x_values: &[f32],
+    ) {
+        let source = match self.sources.get(series_idx) {
+            Some(source) => source,
+            None => return,
+        };
+        let config = source.timeline();
+        let slot = config.slot.unwrap_or_else(|| {
+            self.sources[..series_idx].iter().filter(|source| source.is_timeline()).count()
+        });
+        let fallback_color = self.series_color(series_idx).unwrap_or_default();
+        let term_size = area_size.term_size;
+        let band_height = config.height() * area_size.chart_height;
+        let band_bottom = area_bottom + slot as f32 * band_height;
+        let (y0, y1) =
+            (term_size.scale_y(band_bottom), term_size.scale_y(band_bottom + band_height));
+        let samples: Vec<(u64, Option<f64>)> = series.iter().copied().collect();
+        let values: Vec<Option<f64>> = samples.iter().map(|(_, value)| *value).collect();
+        let runs = timeline::timeline_runs(&values);
+        let mut vertices = Vec::with_capacity(
+            runs.len() * timeline::VERTICES_PER_RUN * timeline::TIMELINE_FLOATS_PER_VERTEX,
+        );
+        let mut band = TimelineBand {
+            series: SeriesIndex(series_idx),
+            name: source.name(),
+            rect: PixelRect {
+                x: term_size.padding_x + x_values.first().copied().unwrap_or_default(),
+                y: term_size.height - term_size.padding_y - band_bottom - band_height,
+                width: x_values.last().copied().unwrap_or_default()
+                    - x_values.first().copied().unwrap_or_default(),
+                height: band_height,
+            },
+            runs: Vec::with_capacity(runs.len()),
+        };
+        for (run_idx, run) in runs.iter().enumerate() {
+            let (color, label) = config.state_of(values[run.start], fallback_color);
+            let (x0, x1) = (x_values[run.start], x_values[run.end]);
+            timeline::push_run_quad(
+                &mut vertices,
+                area_size.scale_x(x0),
+                y0,
+                area_size.scale_x(x1),
+                y1,
+                color,
+            );
+            let until =
+                runs.get(run_idx + 1).map_or(samples[run.end - 1].0, |next| samples[next.start].0);
+            band.runs.push(timeline::TimelineRun {
+                start_x: term_size.padding_x + x0,
+                end_x: term_size.padding_x + x1,
+                label,
+                color,
+                duration_secs: until.saturating_sub(samples[run.start].0),
+            });
+        }
+        self.opengl_vecs[series_idx] = vertices;
+        match self.timeline_bands.iter_mut().find(|other| other.series == band.series) {
+            Some(other) => *other = band,
+            None => self.timeline_bands.push(band),
+        }
+    }
+
+    /// `hide` drops the vertices and the area of a chart that does not fit in the window
+    /// `skip_too_narrow` drops the vertices of the chart when it is too narrow to draw, the
+    /// placeholder is drawn over `pixel_rect` instead
+    fn skip_too_narrow(&mut self, pixel_rect: PixelRect) {
+        self.too_narrow = true;
+        self.pixel_rect = pixel_rect;
+        self.cells.clear();
+        self.timeline_bands.clear();
+        self.animations.clear();
+        for opengl_vecs in &mut self.opengl_vecs {
+            opengl_vecs.clear();
+        }
+    }
+
+    fn hide(&mut self) {
+        self.hidden = true;
+        self.ndc_rect = NdcRect::default();
+        self.pixel_rect = PixelRect::default();
+        self.cells.clear();
+        self.timeline_bands.clear();
+        self.animations.clear();
+        for opengl_vecs in &mut self.opengl_vecs {
+            opengl_vecs.clear();
+        }
+    }
+
+    /// `scissor_rect` returns the chart area in window pixels for the renderer to restrict drawing
+    /// to it, None is returned when the chart allows overflow or it has not been sized yet.
+    pub fn scissor_rect(&self) -> Option<PixelRect> {
+        if self.overflow || self.pixel_rect.is_empty() {
+            None
+        } else {
+            Some(self.pixel_rect)
+        }
+    }
+
+    /// `cell_scissor_rects` returns the small multiples cells in window pixels for the renderer to
+    /// restrict each series to its cell, empty when the chart is not split or allows overflow.
+    pub fn cell_scissor_rects(&self) -> Vec<PixelRect> {
+        if self.overflow {
+            return vec![];
+        }
+        self.cells.iter().map(|cell| cell.rect).collect()
+    }
+
+    /// `series_at_pixel` returns the series whose small multiples cell is drawn at the x,y window
+    /// pixel, None when the chart is not split.
+    pub fn series_at_pixel(&self, x: f32, y: f32) -> Option<SeriesIndex> {
+        self.cells.iter().position(|cell| cell.rect.contains(x, y)).map(SeriesIndex)
+    }
+
+    /// `series_count` returns the number of series drawn in the chart, the sources followed by
+    /// the derived series
+    pub fn series_count(&self) -> usize {
+        self.sources.len() + self.derived.len()
+    }
+
+    /// `is_timeline` returns true when the series at `series_idx` is drawn as a timeline band,
+    /// derived series are always drawn as lines.
+    pub fn is_timeline(&self, series_idx: usize) -> bool {
+        self.sources.get(series_idx).is_some_and(TimeSeriesSource::is_timeline)
+    }
+
+    /// `render_as` returns how the series at `series_idx` is drawn, derived series are always
+    /// drawn as lines.
+    pub fn render_as(&self, series_idx: usize) -> RenderAs {
+        self.sources.get(series_idx).map_or(RenderAs::Line, TimeSeriesSource::render_as)
+    }
+
+    /// `series_at` returns the samples of the series at `series_idx`, derived series are
+    /// computed from their base source. In a Stacked layout the values are stacked on the series
+    /// before it.
+    pub fn series_at(&self, series_idx: usize) -> Option<TimeSeries> {
+        let series = self.layer_at(series_idx)?;
+        if self.layout != SeriesLayout::Stacked || self.is_timeline(series_idx) {
+            return Some(series);
+        }
+        // The missing values are filled, so that a gap in a series does not shift the ones above.
+        let mut below: BTreeMap<u64, f64> = BTreeMap::new();
+        for idx in (0..series_idx).filter(|idx| !self.is_timeline(*idx)) {
+            if let Some(layer) = self.layer_at(idx) {
+                for (epoch, value) in layer.filled_iter() {
+                    *below.entry(epoch).or_default() += value;
+                }
+            }
+        }
+        Some(series.stacked_on(&below))
+    }
+
+    /// `layer_at` returns the samples of the series at `series_idx` as they are drawn before they
+    /// are stacked
+    fn layer_at(&self, series_idx: usize) -> Option<TimeSeries> {
+        let series = match self.sources.get(series_idx) {
+            Some(source) => source.drawn_series(),
+            None => self.derived.get(series_idx - self.sources.len())?.series(&self.sources)?,
+        };
+        // The timeline bands are colored by the state of each sample, they are not smoothed.
+        match self.smoothing_alpha() {
+            Some(alpha) if !self.is_timeline(series_idx) => Some(series.smoothed(alpha)),
+            _ => Some(series),
+        }
+    }
+
+    /// `smoothing_alpha` returns the alpha of the smoothing of the chart, None when the lines are
+    /// not smoothed or the smoothing is out of range
+    pub fn smoothing_alpha(&self) -> Option<f64> {
+        self.smoothing.filter(|alpha| *alpha > 0f32 && *alpha <= 1f32).map(f64::from)
+    }
+
+    /// `triggered_alert_color` returns the color of the first alert of the series `name` that is
+    /// triggered by `value`
+    fn triggered_alert_color(&self, name: &str, value: f64) -> Option<Rgb> {
+        self.decorations.iter().find_map(|decoration| match decoration {
+            Decoration::Alert(alert)
+                if (alert.target.is_empty() || alert.target == name)
+                    && alert.comparator.is_triggered(value, alert.threshold) =>
+            {
+                Some(alert.color.rgb())
+            },
+            _ => None,
+        })
+    }
+
+    /// `series_color` returns the color drawn for the series at `series_idx`, the series without
+    /// a color take it from the palette of the palette mode
+    pub fn series_color(&self, series_idx: usize) -> Option<Rgb> {
+        let configured = match self.sources.get(series_idx) {
+            Some(source) => source.color(),
+            None => self.derived.get(series_idx - self.sources.len())?.color.rgb(),
+        };
+        Some(self.palette_mode.series_color(configured, series_idx))
+    }
+
+    /// `series_colors` returns the color of each series drawn in the chart
+    pub fn series_colors(&self) -> Vec<Rgb> {
+        (0..self.series_count()).filter_map(|series_idx| self.series_color(series_idx)).collect()
+    }
+
+    /// `series_pattern` returns the line pattern of the series at `series_idx`, outside of the
+    /// Default palette mode a triggered alert of the series also changes its pattern so that
+    /// the alert does not rely on its color alone.
+    pub fn series_pattern(&self, series_idx: usize) -> LinePattern {
+        let (pattern, name, latest) = match self.sources.get(series_idx) {
+            Some(source) => (source.pattern(), source.name(), source.series_ref().stats.last),
+            None => match self.derived.get(series_idx - self.sources.len()) {
+                Some(derived) => (
+                    derived.pattern,
+                    derived.name.clone(),
+                    derived.series(&self.sources).map_or(f64::NAN, |series| series.stats.last),
+                ),
+                None => return LinePattern::default(),
+            },
+        };
+        let pattern = pattern.unwrap_or_default();
+        if self.palette_mode != PaletteMode::Default
+            && self.triggered_alert_color(&name, latest).is_some()
+        {
+            pattern.alerted()
+        } else {
+            pattern
+        }
+    }
+
+    /// `series_styles` returns the color and line pattern of each series drawn in the chart
+    pub fn series_styles(&self) -> Vec<SeriesStyle> {
+        (0..self.series_count())
+            .filter_map(|series_idx| {
+                let render_as = self.render_as(series_idx);
+                Some(SeriesStyle {
+                    color: self.series_color(series_idx)?,
+                    pattern: self.series_pattern(series_idx),
+                    smooth: self.smooth && render_as == RenderAs::Line,
+                    timeline: render_as == RenderAs::Timeline,
+                    bars: render_as == RenderAs::Bars,
+                    points: render_as == RenderAs::Points,
+                    live_edge: self.has_live_edge(series_idx),
+                })
+            })
+            .collect()
+    }
+
+    /// `has_live_edge` returns true when the second in progress of the series at `series_idx` is
+    /// drawn at the live edge, only the internal counters drawn as lines have one.
+    pub fn has_live_edge(&self, series_idx: usize) -> bool {
+        let counter = self.sources.get(series_idx).and_then(TimeSeriesSource::live_counter);
+        let is_line = self.render_as(series_idx) == RenderAs::Line;
+        self.live_edge && !self.smooth && is_line && counter.is_some()
+    }
+
+    /// `commit_live_edges` upserts the provisional values of the internal counters whose epoch
+    /// completed before `now`, returns true if any was upserted
+    pub fn commit_live_edges(&mut self, now: u64) -> bool {
+        let epoch = self.counter_epoch(now);
+        let mut committed = false;
+        for counter in self.sources.iter_mut().filter_map(TimeSeriesSource::live_counter_mut) {
+            committed |= counter.commit_provisional(epoch);
+        }
+        committed
+    }
+
+    /// `series_alpha` returns the transparency of the series at `series_idx`, the series filled
+    /// under the faint_fill_ratio of the data_quality are faint
+    pub fn series_alpha(&self, series_idx: usize) -> Option<f32> {
+        let alpha = match self.sources.get(series_idx) {
+            Some(source) => source.alpha(),
+            None => self.derived.get(series_idx - self.sources.len())?.alpha,
+        };
+        Some(alpha * self.faint_alpha_factor(series_idx))
+    }
+
+    /// `faint_alpha_factor` returns the factor of the alpha of the series at `series_idx`, 1
+    /// unless it is filled under the faint_fill_ratio. The derived series follow their source.
+    fn faint_alpha_factor(&self, series_idx: usize) -> f32 {
+        let quality = match &self.data_quality {
+            Some(quality) => quality,
+            None => return 1.,
+        };
+        let source = match self.sources.get(series_idx) {
+            Some(source) => Some(source),
+            None => self
+                .derived
+                .get(series_idx - self.sources.len())
+                .and_then(|derived| self.sources.get(derived.base?)),
+        };
+        match source.and_then(|source| quality.fill_ratio(source.series_ref())) {
+            Some(ratio) if ratio < quality.faint_fill_ratio() => quality.faint_alpha_factor(),
+            _ => 1.,
+        }
+    }
+
+    /// `fill_ratio` merges the fill ratios of the sources of the chart, weighted by their
+    /// entries. The sync padding is a gap only when the data_quality counts it.
+    pub fn fill_ratio(&self) -> Option<f64> {
+        let with_padding = self.data_quality.map_or(false, |quality| quality.count_sync_padding);
+        let counts = self
+            .sources
+            .iter()
+            .map(|source| source.series_ref().fill_counts(with_padding))
+            .fold((0, 0), |(filled, considered), counts| {
+                (filled + counts.0, considered + counts.1)
+            });
+        fill_ratio(counts)
+    }
+
+    /// `title` returns the name of the chart, followed by its share of gaps when it is over the
+    /// title_gap_ratio of the data_quality, i.e. "load avg ⚠ 38% gaps"
+    pub fn title(&self) -> String {
+        let quality = match &self.data_quality {
+            Some(quality) => quality,
+            None => return self.name.clone(),
+        };
+        match self.fill_ratio() {
+            Some(ratio) if 1. - ratio > quality.title_gap_ratio() => {
+                format!("{} ⚠ {:.0}% gaps", self.name, (1. - ratio) * 100.)
+            },
+            _ => self.name.clone(),
+        }
+    }
+
+    /// `resolve_colors` resolves the named colors of the series and the decorations against the
+    /// palette, the unknown names are added to `unknown`
+    pub fn resolve_colors(&mut self, palette: &TerminalPalette, unknown: &mut Vec<String>) {
+        for source in &mut self.sources {
+            source.resolve_colors(palette, unknown);
+        }
+        let derived = self.derived.iter_mut().map(|derived| derived.color.resolve(palette));
+        let decorations =
+            self.decorations.iter_mut().map(|decoration| decoration.resolve_color(palette));
+        unknown.extend(derived.chain(decorations).filter_map(Result::err));
+    }
+
+    /// `resolve_derived_series` finds the source of each derived series by name
+    pub fn resolve_derived_series(&mut self) -> Result<(), String> {
+        for derived in &mut self.derived {
+            derived.base = self.sources.iter().position(|source| source.name() == derived.source);
+            if derived.base.is_none() {
+                return Err(format!(
+                    "Derived series '{}' of chart '{}' has no source named '{}'",
+                    derived.name, self.name, derived.source
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// `capacity_range` returns the smallest and the largest capacity of the sources
+    pub fn capacity_range(&self) -> Option<(usize, usize)> {
+        let capacities = self.sources.iter().map(|source| source.series_ref().metrics_capacity());
+        Some((capacities.clone().min()?, capacities.max()?))
+    }
+
+    /// `validate_capacities` reports the sources with different capacities when the
+    /// capacity_policy is Strict
+    pub fn validate_capacities(&self) -> Result<(), String> {
+        match self.capacity_range() {
+            Some((min, max)) if min != max && self.capacity_policy == CapacityPolicy::Strict => {
+                let capacities: Vec<String> = self
+                    .sources
+                    .iter()
+                    .map(|source| {
+                        format!("{}: {}", source.name(), source.series_ref().metrics_capacity())
+                    })
+                    .collect();
+                Err(format!(
+                    "Chart '{}' has sources with different capacities ({}), the capacity_policy \
+                     is Strict",
+                    self.name,
+                    capacities.join(", ")
+                ))
+            },
+            _ => Ok(()),
+        }
+    }
+
+    /// `validate_y_bounds` reports a y_min that is not under the y_max, the Y axis of the chart
+    /// then follows the values
+    pub fn validate_y_bounds(&self) -> Result<(), String> {
+        match (self.y_min, self.y_max) {
+            (Some(y_min), Some(y_max)) if y_min >= y_max => Err(format!(
+                "Chart '{}' has a y_min of {} that is not under its y_max of {}, the Y axis \
+                 follows the values",
+                self.name, y_min, y_max
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// `validate_smoothing` reports a smoothing out of the (0, 1] range, the lines of the chart
+    /// are then drawn without smoothing
+    pub fn validate_smoothing(&self) -> Result<(), String> {
+        match self.smoothing {
+            Some(alpha) if self.smoothing_alpha().is_none() => Err(format!(
+                "Chart '{}' has a smoothing of {}, it must be over 0 and at most 1, the lines are \
+                 not smoothed",
+                self.name, alpha
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// `validate_decorations_space` reports the charts whose width in pixels cannot fit the space
+    /// requested by their decorations, it is scaled down when drawn. The widths in cells depend on
+    /// the font and are not checked.
+    pub fn validate_decorations_space(&self) -> Result<(), String> {
+        let dimensions = match self.dimensions {
+            Some(dimensions) if dimensions.cells.x == 0f32 => dimensions,
+            _ => return Ok(()),
+        };
+        let width = dimensions.pixels.x;
+        let requested: f32 = self.decorations.iter().map(|x| x.width()).sum();
+        let max_fraction =
+            self.max_decorations_fraction.unwrap_or(DEFAULT_MAX_DECORATIONS_FRACTION);
+        match fit_decorations_space(width, requested, max_fraction) {
+            None => Err(format!("Chart '{}' is {}px wide, too narrow to draw", self.name, width)),
+            Some(fitted) if fitted < requested => Err(format!(
+                "Chart '{}' is {}px wide, the {}px requested by its decorations are scaled down \
+                 to {}px",
+                self.name, width, requested, fitted
+            )),
+            Some(_) => Ok(()),
+        }
+    }
+
+    /// `x_ticks` returns the ticks the X axis is divided in for a series of `capacity` and the
+    /// ticks its first sample is moved right by. The vertices are moved before they are deduped,
+    /// so the deduped vertices keep the offset.
+    fn x_ticks(&self, capacity: usize) -> (usize, usize) {
+        match (self.capacity_policy, self.capacity_range()) {
+            (CapacityPolicy::Independent, _) | (_, None) => (capacity, 0),
+            (_, Some((_, max))) => {
+                let max = max.max(capacity);
+                (max, max - capacity)
+            },
+        }
+    }
+
+    /// `redraws_on_load` returns true when the terminal should be redrawn after the chart loads
+    /// data, hidden charts and charts with suppress_redraw cannot show it.
+    pub fn redraws_on_load(&self) -> bool {
+        !self.hidden && !self.suppress_redraw
+    }
+
+    /// `awaiting_data` returns true while none of the sources of the chart has loaded data, once
+    /// a source loads the chart stops awaiting for the rest of the session.
+    pub fn awaiting_data(&self) -> bool {
+        !self.sources.is_empty() && self.sources.iter().all(|source| !source.ever_loaded())
+    }
+
+    /// `placeholder` returns the placeholder to draw at a decoration tick while the chart is
+    /// awaiting data or too narrow to draw, None once data arrived.
+    pub fn placeholder(&self, tick: u64) -> Option<ChartPlaceholder> {
+        if self.hidden || !(self.awaiting_data() || self.too_narrow) {
+            return None;
+        }
+        let first_source = self.sources.first()?;
+        Some(ChartPlaceholder {
+            rect: self.pixel_rect,
+            color: self.palette_mode.series_color(first_source.color(), 0),
+            alpha: ChartPlaceholder::pulse_alpha(tick),
+        })
+    }
+
+    /// `numeric_readout` returns the latest value of the first series of a Numeric chart, None
+    /// for Graph charts or before the series has values.
+    pub fn numeric_readout(&self) -> Option<NumericReadout> {
+        if self.hidden || self.chart_style != ChartStyle::Numeric {
+            return None;
+        }
+        let source = self.sources.first()?;
+        let series = source.drawn_series();
+        // The newest filled values first.
+        let mut recent: Vec<f64> = series
+            .iter()
+            .rev()
+            .filter_map(|(_, value)| *value)
+            .take(NUMERIC_SPARKLINE_WIDTH)
+            .collect();
+        let latest = *recent.first()?;
+        let previous = recent.get(1).copied().unwrap_or(latest);
+        let epsilon = self.trend_epsilon.unwrap_or(DEFAULT_TREND_EPSILON);
+        // The alerts of the series color the readout instead of drawing their markers.
+        let color = self
+            .triggered_alert_color(&source.name(), latest)
+            .unwrap_or_else(|| self.palette_mode.series_color(source.color(), 0));
+        recent.reverse();
+        Some(NumericReadout {
+            rect: self.pixel_rect,
+            value: format_readout_value(latest),
+            trend: Trend::between(previous, latest, epsilon),
+            color,
+            sparkline: sparkline_string(&recent),
+        })
+    }
+
+    /// `prometheus_source` returns the first prometheus source of the chart
+    fn prometheus_source(&self) -> Option<&prometheus::PrometheusTimeSeries> {
+        self.sources.iter().find_map(|source| match source {
+            TimeSeriesSource::PrometheusTimeSeries(prom) => Some(prom.as_ref()),
+            _ => None,
+        })
+    }
+
+    /// `visible_epoch_window` returns the (start, end) epochs covered by the first prometheus
+    /// source, the window ends at the newest sample, or at `now` before any sample arrives,
+    /// and spans the capacity of the series times its pull interval.
+    pub fn visible_epoch_window(&self, now: u64) -> Option<(u64, u64)> {
+        let prom = self.prometheus_source()?;
+        let end = prom.series.iter().map(|(epoch, _)| *epoch).max().unwrap_or(now);
+        let span = (prom.series.metrics_capacity() * prom.pull_interval) as u64;
+        Some((end.saturating_sub(span), end))
+    }
+
+    /// `render_link` fills the link template placeholders, {query} is kept URL encoded as it
+    /// appears in the prometheus source.
+    pub fn render_link(&self, now: u64) -> Option<String> {
+        self.render_series_link(None, now)
+    }
+
+    /// `render_series_link` fills the link template with the query of the series, i.e. the small
+    /// multiples cell that was clicked, derived series use the query of their base source. The
+    /// first prometheus source is used when the series is None or it is not a prometheus source.
+    pub fn render_series_link(&self, series: Option<SeriesIndex>, now: u64) -> Option<String> {
+        let link = self.link.as_ref()?;
+        let source_idx = series.and_then(|idx| match idx.0.checked_sub(self.sources.len()) {
+            Some(derived_idx) => self.derived.get(derived_idx)?.base,
+            None => Some(idx.0),
+        });
+        let series_prom = source_idx.and_then(|idx| match self.sources.get(idx) {
+            Some(TimeSeriesSource::PrometheusTimeSeries(prom)) => Some(prom.as_ref()),
+            _ => None,
+        });
+        let prom = match series_prom.or_else(|| self.prometheus_source()) {
+            Some(prom) => prom,
+            None => return Some(link.clone()),
+        };
+        let query = prom
+            .source
+            .split_once('?')
+            .and_then(|(_, params)| params.split('&').find_map(|x| x.strip_prefix("query=")))
+            .unwrap_or_default();
+        let (start, end) = self.visible_epoch_window(now).unwrap_or((now, now));
+        Some(
+            link.replace("{query}", query)
+                .replace("{start}", &start.to_string())
+                .replace("{end}", &end.to_string()),
+        )
+    }
+
+    /// `apply_discovered_values` adds a series for each new label value discovered by the source
+    /// at `parent_idx`, label values missing for the grace period retire their series. Retired
+    /// series keep their samples until they age out, then their slot may be taken by a new label
+    /// value so that the series indexes do not change. The new values take their slots in
+    /// lexicographic order, whatever the order they were discovered in.
+    pub fn apply_discovered_values(
+        &mut self,
+        parent_idx: usize,
+        values: &[String],
+        now: u64,
+    ) -> DiscoveryChanges {
+        let mut changes = DiscoveryChanges::default();
+        let parent = match self.sources.get(parent_idx) {
+            Some(TimeSeriesSource::PrometheusTimeSeries(prom)) if prom.discover.is_some() => {
+                prom.clone()
+            },
+            _ => {
+                event!(
+                    Level::WARN,
+                    "apply_discovered_values:({}) Series {} has no discovery",
+                    self.name,
+                    parent_idx
+                );
+                return changes;
+            },
+        };
+        let (grace_period, max_series) = match &parent.discover {
+            Some(discover) => (discover.grace_period, discover.max_series),
+            None => return changes,
+        };
+        let mut values = values.to_vec();
+        order::stable_order_by_key(&mut values, |value| value.clone());
+        values.dedup();
+        for value in &values {
+            let existing = self.discovered_series_mut(parent_idx).find(|(_, x)| x.value == *value);
+            if let Some((idx, discovered)) = existing {
+                discovered.last_seen = now;
+                if discovered.retired {
+                    discovered.retired = false;
+                    changes.started.push(idx);
+                }
+                continue;
+            }
+            let prom = match parent.discovered_series(parent_idx, value, now) {
+                Ok(prom) => TimeSeriesSource::PrometheusTimeSeries(Box::new(prom)),
+                Err(err) => {
+                    event!(
+                        Level::ERROR,
+                        "apply_discovered_values:({}) Unable to add '{}': {}",
+                        self.name,
+                        value,
+                        err
+                    );
+                    continue;
+                },
             };
-            let scaled_x = display_size.scale_x(x_value + self.position.unwrap_or_default().x);
-            let scaled_y = display_size.scale_y(self.stats.max, y_value);
-            // Adding twice to a vec, could this be made into one operation? Is this slow?
-            // need to transform activity line values from varying levels into scaled [-1, 1]
-            // XXX: Move to Circular Buffer? Problem is Circular buffer is only meant for epochs
-            if (idx + 1) * 2 > self.opengl_vecs[series_idx].len() {
-                self.opengl_vecs[series_idx].push(scaled_x);
-                self.opengl_vecs[series_idx].push(scaled_y);
+            let reusable_slot = self.sources.iter().position(|source| match source {
+                TimeSeriesSource::PrometheusTimeSeries(x) => {
+                    x.discovered.as_ref().map_or(false, |x| x.parent == parent_idx && x.retired)
+                        && x.series.iter().all(|(_, value)| value.is_none())
+                },
+                _ => false,
+            });
+            if let Some(idx) = reusable_slot {
+                self.sources[idx] = prom;
+                if let Some(opengl_vecs) = self.opengl_vecs.get_mut(idx) {
+                    opengl_vecs.clear();
+                }
+                changes.started.push(idx);
+            } else if self.discovered_series_mut(parent_idx).count() < max_series {
+                self.sources.push(prom);
+                changes.started.push(self.sources.len() - 1);
             } else {
-                self.opengl_vecs[series_idx][idx * 2] = scaled_x;
-                self.opengl_vecs[series_idx][idx * 2 + 1] = scaled_y;
+                event!(
+                    Level::WARN,
+                    "apply_discovered_values:({}) Skipping '{}', max_series {} reached",
+                    self.name,
+                    value,
+                    max_series
+                );
             }
         }
-        for decoration in &mut self.decorations {
-            event!(
-                Level::DEBUG,
-                "update_series_opengl_vecs: Updating decoration {:?} vertices",
-                decoration
-            );
-            decoration.update_opengl_vecs(
-                display_size,
-                self.position.unwrap_or_default(),
-                &self.stats,
-                &self.sources,
-            );
+        for (idx, discovered) in self.discovered_series_mut(parent_idx) {
+            if !discovered.retired
+                && !values.contains(&discovered.value)
+                && now.saturating_sub(discovered.last_seen) >= grace_period
+            {
+                discovered.retired = true;
+                changes.retired.push(idx);
+            }
+        }
+        order::stable_order_by_key(&mut changes.started, |idx| *idx);
+        changes
+    }
+
+    /// `discovered_series_mut` iterates over the (index, discovery state) of the series added by
+    /// the discovery of the source at `parent_idx`
+    fn discovered_series_mut(
+        &mut self,
+        parent_idx: usize,
+    ) -> impl Iterator<Item = (usize, &mut prometheus::DiscoveredSeries)> {
+        self.sources.iter_mut().enumerate().filter_map(move |(idx, source)| match source {
+            TimeSeriesSource::PrometheusTimeSeries(prom) => prom
+                .discovered
+                .as_mut()
+                .filter(|discovered| discovered.parent == parent_idx)
+                .map(|discovered| (idx, discovered)),
+            _ => None,
+        })
+    }
+
+    /// `drawable_width` returns the width in physical pixels of the chart that is available for
+    /// the series, this is, the chart width minus the space requested by decorations
+    pub fn drawable_width(&self, display_size: ChartSizeInfo) -> f32 {
+        let dpr = display_size.term_size.scale_factor();
+        let chart_width = match self.dimensions {
+            Some(dimensions) => dimensions.resolve(&display_size.term_size).x * dpr,
+            None => display_size.chart_width,
+        };
+        let decorations_space: f32 = self.decorations.iter().map(|x| x.width() * dpr).sum();
+        let max_fraction =
+            self.max_decorations_fraction.unwrap_or(DEFAULT_MAX_DECORATIONS_FRACTION);
+        match fit_decorations_space(chart_width, decorations_space, max_fraction) {
+            Some(fitted) => chart_width - fitted,
+            None => chart_width,
+        }
+    }
+
+    /// `adapt_series_capacity` resizes the series that have adaptive_capacity enabled so that
+    /// they hold one sample per horizontal pixel of the chart. Returns true if any series changed.
+    pub fn adapt_series_capacity(&mut self, display_size: ChartSizeInfo) -> bool {
+        let width = self.drawable_width(display_size);
+        let mut changed = false;
+        for (idx, source) in self.sources.iter_mut().enumerate() {
+            let series = source.series_mut();
+            if !series.adaptive_capacity {
+                continue;
+            }
+            let capacity = capacity_for_width(width, series.max_capacity);
+            if capacity != series.metrics_capacity() {
+                event!(
+                    Level::DEBUG,
+                    "adapt_series_capacity:({}) width: {}, capacity: {} -> {}",
+                    self.name,
+                    width,
+                    series.metrics_capacity(),
+                    capacity
+                );
+                series.resize_capacity(capacity);
+                // Vertices past the active items belong to the dropped samples.
+                if let Some(opengl_vecs) = self.opengl_vecs.get_mut(idx) {
+                    opengl_vecs.truncate(series.active_items * 2);
+                }
+                changed = true;
+            }
+        }
+        changed
+    }
+
+    /// `set_window` resizes the sources to hold `capacity` samples, the newest samples are kept
+    /// when shrinking. The series with adaptive_capacity follow the chart width up to the new
+    /// capacity.
+    pub fn set_window(&mut self, capacity: usize, display_size: ChartSizeInfo) {
+        for idx in 0..self.sources.len() {
+            self.resize_source(idx, capacity);
+        }
+        self.adapt_series_capacity(display_size);
+        self.calculate_stats();
+    }
+
+    /// `clear` drops the samples of all the sources and regenerates their vertices, the running
+    /// transitions are dropped with the vertices they started from.
+    pub fn clear(&mut self, display_size: ChartSizeInfo) {
+        for (idx, source) in self.sources.iter_mut().enumerate() {
+            source.series_mut().clear();
+            if let Some(opengl_vecs) = self.opengl_vecs.get_mut(idx) {
+                opengl_vecs.clear();
+            }
+        }
+        for animation in &mut self.animations {
+            animation.transition = None;
+        }
+        self.calculate_stats();
+        self.update_all_series_opengl_vecs(display_size);
+    }
+
+    /// `resize_series_capacity` resizes a source to hold `capacity` samples like `set_window`
+    /// does for all of them, returns false if the capacity did not change or the source does
+    /// not exist.
+    pub fn resize_series_capacity(
+        &mut self,
+        series_idx: usize,
+        capacity: usize,
+        display_size: ChartSizeInfo,
+    ) -> bool {
+        let previous = match self.sources.get(series_idx) {
+            Some(source) => source.series_ref().metrics_capacity(),
+            None => return false,
+        };
+        self.resize_source(series_idx, capacity);
+        self.adapt_series_capacity(display_size);
+        self.calculate_stats();
+        self.sources[series_idx].series_ref().metrics_capacity() != previous
+    }
+
+    /// `resize_source` resizes a source and drops the vertices of the samples it no longer holds,
+    /// an adaptive series follows the chart width up to the new capacity.
+    fn resize_source(&mut self, idx: usize, capacity: usize) {
+        let source = &mut self.sources[idx];
+        if source.series_ref().adaptive_capacity {
+            source.series_mut().max_capacity = capacity;
+        }
+        source.resize_capacity(capacity);
+        // Vertices past the active items belong to the dropped samples.
+        if let Some(opengl_vecs) = self.opengl_vecs.get_mut(idx) {
+            opengl_vecs.truncate(source.series_ref().active_items * 2);
         }
-        self.last_updated =
-            std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
     }
 
     /// `update_all_series_opengl_vecs` Represents the activity levels values in a
@@ -593,7 +4602,7 @@ impl TimeSeriesChart {
             span!(Level::TRACE, "update_all_series_opengl_vecs", name = self.name.clone().as_str());
         let _enter = span.enter();
         event!(Level::DEBUG, "update_all_series_opengl_vecs: Starting");
-        for idx in 0..self.sources.len() {
+        for idx in 0..self.series_count() {
             self.update_series_opengl_vecs(idx, display_size);
         }
         event!(Level::DEBUG, "update_all_series_opengl_vecs: Finished");
@@ -610,6 +4619,8 @@ impl TimeSeriesChart {
         let mut min_metric_value = f64::MAX;
         let mut sum_metric_values = 0f64;
         let mut total_count = 0usize;
+        let mut total_missing = 0usize;
+        let mut moments = (0usize, 0f64, 0f64);
         let mut max_epoch = 0u64;
         // For every timeseries in the current chart, we should calculate what are the max, min,
         // etc values so that we can draw them all together sensibly
@@ -623,18 +4634,31 @@ impl TimeSeriesChart {
                 source.series_mut().calculate_stats();
             }
         }
-        for source in &self.sources {
-            if source.series().stats.max > max_metric_value {
-                max_metric_value = source.series().stats.max;
+        // The derived series are drawn in the same scale as their sources, the timeline bands
+        // have their own height and only move the newest epoch.
+        for idx in 0..self.series_count() {
+            let series = match self.series_at(idx) {
+                Some(series) => series,
+                None => continue,
+            };
+            if series.stats.last_epoch > max_epoch {
+                max_epoch = series.stats.last_epoch;
+            }
+            if self.is_timeline(idx) {
+                continue;
             }
-            if source.series().stats.last_epoch > max_epoch {
-                max_epoch = source.series().stats.last_epoch;
+            if series.stats.max > max_metric_value {
+                max_metric_value = series.stats.max;
             }
-            if source.series().stats.min < min_metric_value {
-                min_metric_value = source.series().stats.min;
+            if series.stats.min < min_metric_value {
+                min_metric_value = series.stats.min;
             }
-            sum_metric_values += source.series().stats.sum;
-            total_count += source.series().stats.count;
+            sum_metric_values += series.stats.sum;
+            total_count += series.stats.count;
+            total_missing += series.stats.missing_count;
+            let count = series.stats.count;
+            let m2 = series.stats.stddev.powi(2) * count as f64;
+            moments = merge_moments(moments, (count, series.stats.avg, m2));
         }
         // Account for the decoration requested height
         for decoration in &self.decorations {
@@ -648,12 +4672,15 @@ impl TimeSeriesChart {
             }
         }
         self.stats.count = total_count;
+        self.stats.missing_count = total_missing;
         self.stats.max = max_metric_value;
         self.stats.min = min_metric_value;
         self.stats.sum = sum_metric_values;
         self.stats.avg = sum_metric_values / total_count as f64;
+        self.stats.stddev = stddev_of(moments.2, moments.0);
         self.stats.is_dirty = false;
         self.stats.last_epoch = max_epoch;
+        self.update_nice_bounds();
         event!(
             Level::DEBUG,
             "TimeSeriesChart::calculate_stats: Updated statistics to: {:?}",
@@ -661,65 +4688,219 @@ impl TimeSeriesChart {
         );
     }
 
+    /// `update_nice_bounds` chooses the nice bounds of the Y axis again when the values leave
+    /// them or their range shrinks under NICE_SCALE_HYSTERESIS of the nice range, so that the
+    /// scale does not change with minor fluctuations.
+    fn update_nice_bounds(&mut self) {
+        if !self.nice_scale || self.y_scale_type != YScaleType::Linear {
+            self.nice_bounds = None;
+            return;
+        }
+        let (lo, hi) = self.y_scale_type.bounds(&self.stats);
+        if hi < lo {
+            // There are no values yet.
+            self.nice_bounds = None;
+            return;
+        }
+        let in_range = self.nice_bounds.map_or(false, |(nice_lo, nice_hi)| {
+            lo >= nice_lo && hi <= nice_hi && hi - lo >= NICE_SCALE_HYSTERESIS * (nice_hi - nice_lo)
+        });
+        if !in_range {
+            self.nice_bounds = Some(nice_bounds(lo, hi));
+            event!(
+                Level::DEBUG,
+                "update_nice_bounds:({}) Using nice bounds {:?}",
+                self.name,
+                self.nice_bounds
+            );
+        }
+    }
+
+    /// `memory_bytes` returns the memory held by the series of the chart and their vertices
+    pub fn memory_bytes(&self) -> usize {
+        let series_bytes: usize =
+            self.sources.iter().map(|source| source.series_ref().memory_bytes()).sum();
+        let vertex_bytes: usize = self
+            .opengl_vecs
+            .iter()
+            .map(|opengl_vecs| opengl_vecs.capacity() * std::mem::size_of::<f32>())
+            .sum();
+        series_bytes + vertex_bytes
+    }
+
+    /// `effective_y_bounds` returns the values at the bottom and at the top of the chart, these
+    /// are the nice bounds when nice_scale is enabled.
+    pub fn effective_y_bounds(&self) -> (f64, f64) {
+        self.pinned_y_bounds().unwrap_or_else(|| self.y_scale_type.bounds(&self.stats))
+    }
+
+    /// `pinned_y_bounds` returns the bounds of the Y axis that are not calculated from the stats
+    /// of the series, the nice bounds expanded to keep zero at the baseline
+    fn pinned_y_bounds(&self) -> Option<(f64, f64)> {
+        let pinned = match self.baseline {
+            Some(baseline) if self.y_scale_type == YScaleType::Linear => {
+                let (lo, hi) =
+                    self.nice_bounds.unwrap_or_else(|| self.y_scale_type.bounds(&self.stats));
+                // There are no values yet.
+                if hi < lo {
+                    return None;
+                }
+                Some(baseline_bounds(baseline, lo, hi))
+            },
+            _ => self.nice_bounds,
+        };
+        if !self.has_fixed_y_bounds() {
+            return pinned;
+        }
+        // The side without a fixed bound follows the values.
+        let (lo, hi) = pinned.unwrap_or_else(|| self.y_scale_type.bounds(&self.stats));
+        let fixed = (self.y_min.unwrap_or(lo), self.y_max.unwrap_or(hi));
+        if fixed.0 < fixed.1 {
+            Some(fixed)
+        } else {
+            pinned
+        }
+    }
+
+    /// `has_fixed_y_bounds` returns true when the chart has a y_min or a y_max that is honored,
+    /// a y_min that is not under the y_max is ignored
+    pub fn has_fixed_y_bounds(&self) -> bool {
+        self.y_scale_type == YScaleType::Linear
+            && (self.y_min.is_some() || self.y_max.is_some())
+            && self.y_min.unwrap_or(f64::MIN) < self.y_max.unwrap_or(f64::MAX)
+    }
+
     /// `get_deduped_opengl_vecs` returns a minimized version of the opengl_vecs, when the metric
     /// doesn't change it doesn't create a new opengl vertex but rather tries to create a wider
-    /// line
+    /// line. Unless overflow is allowed, the line is clipped to the chart area.
     pub fn get_deduped_opengl_vecs(&self, series_idx: usize) -> Vec<f32> {
         let span = span!(Level::TRACE, "get_deduped_opengl_vecs", series_idx);
         let _enter = span.enter();
-        if series_idx >= self.opengl_vecs.len() {
+        if series_idx >= self.opengl_vecs.len() || series_idx >= self.series_count() {
+            event!(
+                Level::WARN,
+                "get_deduped_opengl_vecs: Request for index out of bounds: {}",
+                series_idx
+            );
             return vec![];
         }
-        if self.opengl_vecs[series_idx].len() <= 4 {
-            return self.opengl_vecs[series_idx].clone();
-        }
-        // By default, accomodate memory for as many active items as there are in the series
-        // circular buffer.
-        let mut res = Vec::with_capacity(self.sources[series_idx].series().active_items * 2);
-        // Grab the first reference point
-        let mut cur_x = self.opengl_vecs[series_idx][0];
-        let mut cur_y = self.opengl_vecs[series_idx][1];
-        res.push(cur_x);
-        res.push(cur_y);
-        // Avoid adding the last item twice:
-        let mut last_item_added = false;
-        for (idx, vertex) in self.opengl_vecs[series_idx].iter().enumerate() {
-            if idx == self.sources[series_idx].series().active_items * 2 {
-                break;
-            }
-            if idx % 2 == 1 {
-                // This is a Y value
-                // Let's allow this much difference and consider them equal
-                if (cur_y - *vertex).abs() > f32::EPSILON {
-                    // This means the metric has changed, so let's push old X,Y (old value)
-                    // unless it happens to have been the last instered item
-                    if !last_item_added {
-                        res.push(cur_x);
-                        res.push(cur_y);
-                    }
-                    // Add a point to the new value
-                    res.push(cur_x);
-                    res.push(*vertex);
-                    // And now reset the current y value:
-                    cur_y = *vertex;
-                    last_item_added = true;
-                } else {
-                    last_item_added = false;
-                }
-            } else {
-                cur_x = *vertex;
-            }
+        if self.hidden {
+            return vec![];
         }
-        if !last_item_added {
-            // If there are only two items, we should append the last read
-            // X, Y
-            res.push(cur_x);
-            res.push(cur_y);
+        let res = self.dedup_opengl_vecs(series_idx);
+        // Series split into small multiples are clipped to their cell.
+        let clip_rect = self.cells.get(series_idx).map_or(self.ndc_rect, |cell| cell.ndc_rect);
+        if self.overflow || clip_rect.is_empty() {
+            res
+        } else {
+            clip_rect.clip_polyline(&res)
+        }
+    }
+
+    /// `get_live_edge_opengl_vecs` returns the segment from the newest vertex of a series to its
+    /// provisional vertex, empty when the series has no running value
+    pub fn get_live_edge_opengl_vecs(&self, series_idx: usize) -> Vec<f32> {
+        let (edge_x, edge_y) = match self.live_edge_vertices.get(series_idx) {
+            Some(Some(vertex)) if !self.hidden => *vertex,
+            _ => return vec![],
+        };
+        match self.get_deduped_opengl_vecs(series_idx)[..] {
+            [.., x, y] => vec![x, y, edge_x, edge_y],
+            _ => vec![],
+        }
+    }
+
+    /// `get_styled_opengl_vecs` returns the deduped vertices of a series with its line pattern
+    /// applied, the dashes of a dashed or dotted series are pairs of vertices. The series of a
+    /// smooth chart are a triangle strip of x,y,fade vertices instead, a solid line is first
+    /// downsampled to two vertices per pixel column so that the strip stays bounded by the
+    /// chart width. The quads of a timeline band are returned as generated. The bars are the
+    /// pairs of the bottom and the top vertex of each sample and the points are the vertex of
+    /// each sample, they are not deduped.
+    pub fn get_styled_opengl_vecs(&self, series_idx: usize) -> Vec<f32> {
+        match self.render_as(series_idx) {
+            RenderAs::Timeline => {
+                return match self.opengl_vecs.get(series_idx) {
+                    Some(opengl_vecs) if !self.hidden => opengl_vecs.clone(),
+                    _ => vec![],
+                };
+            },
+            RenderAs::Bars => {
+                let base = self.bar_bases.get(series_idx).copied().unwrap_or(-1f32);
+                return self
+                    .sample_opengl_vecs(series_idx)
+                    .chunks_exact(2)
+                    .flat_map(|vertex| [vertex[0], base, vertex[0], vertex[1]])
+                    .collect();
+            },
+            RenderAs::Points => return self.sample_opengl_vecs(series_idx),
+            RenderAs::Line => (),
+        }
+        let res = self.get_deduped_opengl_vecs(series_idx);
+        let (pattern, pixels_per_ndc) = (self.series_pattern(series_idx), self.pixels_per_ndc());
+        if !self.smooth {
+            return pattern.segment_vertices(&res, pixels_per_ndc);
         }
+        match pattern {
+            LinePattern::Solid => {
+                let max_points = (self.pixel_rect.width.max(0f32) as usize) * 2;
+                smooth_strip_vertices(&downsample_polyline(&res, max_points), pixels_per_ndc)
+            },
+            _ => join_strips(
+                pattern
+                    .segment_vertices(&res, pixels_per_ndc)
+                    .chunks_exact(4)
+                    .map(|dash| smooth_strip_vertices(dash, pixels_per_ndc)),
+            ),
+        }
+    }
+
+    /// `sample_opengl_vecs` returns the x,y vertex of each sample of a series as it is drawn, a
+    /// running transition included
+    fn sample_opengl_vecs(&self, series_idx: usize) -> Vec<f32> {
+        if self.hidden {
+            return vec![];
+        }
+        let animated = self.animated_opengl_vecs(series_idx);
+        let active_items = self.series_at(series_idx).map_or(0, |series| series.active_items);
+        match animated.as_ref().or(self.opengl_vecs.get(series_idx)) {
+            Some(opengl_vecs) => opengl_vecs[..opengl_vecs.len().min(active_items * 2)].to_vec(),
+            None => vec![],
+        }
+    }
+
+    /// `pixels_per_ndc` returns the window pixels of a unit of normalized device coordinates
+    /// along each axis of the chart
+    fn pixels_per_ndc(&self) -> (f32, f32) {
+        let ndc_width = self.ndc_rect.right - self.ndc_rect.left;
+        let ndc_height = self.ndc_rect.top - self.ndc_rect.bottom;
+        if ndc_width <= 0f32 || ndc_height <= 0f32 {
+            return (0f32, 0f32);
+        }
+        (self.pixel_rect.width / ndc_width, self.pixel_rect.height / ndc_height)
+    }
+
+    /// `dedup_opengl_vecs` removes the vertices that do not change the metric value
+    fn dedup_opengl_vecs(&self, series_idx: usize) -> Vec<f32> {
+        // A running transition draws the interpolated vertices.
+        let animated = self.animated_opengl_vecs(series_idx);
+        let opengl_vecs = animated.as_ref().or(self.opengl_vecs.get(series_idx));
+        let (opengl_vecs, series) = match (opengl_vecs, self.series_at(series_idx)) {
+            (Some(opengl_vecs), Some(series)) => (opengl_vecs, series),
+            _ => return vec![],
+        };
+        // The vertices may outlive a larger window, only those of the active items are drawn.
+        let active_len = opengl_vecs.len().min(series.active_items * 2) & !1;
+        let res = dedup_step_vertices(&opengl_vecs[..active_len]);
         debug!("get_deduped_opengl_vecs[{}] len({}) result: {:?}", series_idx, res.len(), res);
         res
     }
 
+    /// `timeline_readout` returns the state of the timeline band under the x,y window pixel
+    pub fn timeline_readout(&self, x: f32, y: f32) -> Option<TimelineReadout> {
+        self.timeline_bands.iter().find_map(|band| band.readout_at(x, y))
+    }
+
     /// `synchronize_series_epoch_range` ensures that, for the items inside a chart.series vector,
     /// the epochs are synchronized so that we can draw them and make sense of their values.
     pub fn synchronize_series_epoch_range(&mut self) {
@@ -736,34 +4917,291 @@ impl TimeSeriesChart {
     }
 }
 
+/// `dedup_step_vertices` reduces the x,y vertices of a line to the points where its value changes,
+/// a run of the same value is drawn as a single segment. The first and the last vertices are
+/// always kept so the line spans the same X range as the raw vertices.
+fn dedup_step_vertices(vertices: &[f32]) -> Vec<f32> {
+    if vertices.len() <= 4 {
+        return vertices.to_vec();
+    }
+    let (mut cur_x, mut cur_y) = (vertices[0], vertices[1]);
+    let mut res = vec![cur_x, cur_y];
+    // Whether the newest vertex read has been pushed already.
+    let mut last_item_added = false;
+    for point in vertices[2..].chunks_exact(2) {
+        let (x, y) = (point[0], point[1]);
+        cur_x = x;
+        if same_value(cur_y as f64, y as f64) {
+            last_item_added = false;
+            continue;
+        }
+        // The metric has changed, the run of the old value ends at this X unless the previous
+        // vertex was a change too.
+        if !last_item_added {
+            res.extend_from_slice(&[cur_x, cur_y]);
+        }
+        res.extend_from_slice(&[cur_x, y]);
+        cur_y = y;
+        last_item_added = true;
+    }
+    if !last_item_added {
+        // Close the run of the last value at the X of the newest vertex.
+        res.extend_from_slice(&[cur_x, cur_y]);
+    }
+    res
+}
+
+/// `same_value` returns true when two values are drawn the same, a run of same values is drawn
+/// as a single line or, for a timeline, a single quad. Let's allow this much difference and
+/// consider them equal.
+pub fn same_value(a: f64, b: f64) -> bool {
+    (a - b).abs() <= f32::EPSILON as f64
+}
+
 impl Default for TimeSeries {
     fn default() -> TimeSeries {
         // This leads to 5 mins of metrics to show by default.
         let default_capacity = 300usize;
         TimeSeries {
             metrics_capacity: default_capacity,
+            granularity_ms: DEFAULT_GRANULARITY_MS,
             metrics: Vec::with_capacity(default_capacity),
             stats: TimeSeriesStats::default(),
             collision_policy: ValueCollisionPolicy::default(),
             missing_values_policy: MissingValuesPolicy::default(),
             first_idx: 0,
             active_items: 0,
+            #[cfg(feature = "upsert-debug")]
             prev_snapshot: Vec::with_capacity(default_capacity),
+            #[cfg(feature = "upsert-debug")]
             prev_value: (0, None),
+            #[cfg(feature = "upsert-debug")]
             upsert_type: UpsertType::default(),
+            adaptive_capacity: false,
+            max_capacity: DEFAULT_MAX_CAPACITY,
+            collision_floor: None,
+            collision_ceiling: None,
+            stats_mode: StatsMode::default(),
+            running_stats: RunningStats::default(),
+            sync_padding: BTreeSet::new(),
         }
     }
 }
 
 impl TimeSeries {
-    /// `with_capacity` builder changes the amount of metrics in the vec
+    /// `with_capacity` builder changes the amount of metrics in the vec. Existing metrics are
+    /// kept, if there are more active items than `n` only the newest `n` are kept.
+    /// A capacity of 0 is invalid, 1 is used instead.
     pub fn with_capacity(self, n: usize) -> TimeSeries {
         let mut new_self = self;
-        new_self.metrics = Vec::with_capacity(n);
-        new_self.metrics_capacity = n;
+        let n = if n == 0 {
+            error!("with_capacity: Invalid capacity 0, using 1 instead");
+            1
+        } else {
+            n
+        };
+        if new_self.metrics.is_empty() {
+            new_self.metrics = Vec::with_capacity(n);
+            new_self.metrics_capacity = n;
+        } else {
+            new_self.resize_capacity(n);
+        }
         new_self
     }
 
+    /// `compact` returns a copy of the series for dumps, the values are rounded to `decimals` and
+    /// the debug snapshot is dropped.
+    pub fn compact(&self, decimals: u32) -> TimeSeries {
+        let round = |(epoch, value): (u64, Option<f64>)| {
+            (epoch, value.map(|value| round_to_decimals(value, decimals)))
+        };
+        TimeSeries {
+            metrics: self.metrics.iter().copied().map(round).collect(),
+            #[cfg(feature = "upsert-debug")]
+            prev_snapshot: vec![],
+            #[cfg(feature = "upsert-debug")]
+            prev_value: round(self.prev_value),
+            running_stats: RunningStats::default(),
+            ..self.clone()
+        }
+    }
+
+    /// `transformed` returns a copy of the series with its values transformed in order, the
+    /// missing values stay missing and the rate spans the gap of the values around them.
+    pub fn transformed(&self, transform: SeriesTransform) -> TimeSeries {
+        if transform.is_none() {
+            return self.clone();
+        }
+        let mut previous = None;
+        self.map_filled(|epoch, value| {
+            let transformed = transform.apply(previous, epoch, value);
+            previous = Some((epoch, value));
+            transformed
+        })
+    }
+
+    /// `smoothed` returns a copy of the series with the exponential moving average of its values
+    /// in order, `alpha` is the weight of each new value. The missing values stay missing.
+    pub fn smoothed(&self, alpha: f64) -> TimeSeries {
+        let mut average: Option<f64> = None;
+        self.map_filled(|_, value| {
+            let smoothed = average.map_or(value, |average| alpha * value + (1. - alpha) * average);
+            average = Some(smoothed);
+            Some(smoothed)
+        })
+    }
+
+    /// `map_filled` returns a copy of the series with `f` applied in order to the epoch and the
+    /// value of its filled metrics, the stats are calculated for the new values
+    fn map_filled(&self, mut f: impl FnMut(u64, f64) -> Option<f64>) -> TimeSeries {
+        let metrics: Vec<(u64, Option<f64>)> = self
+            .iter()
+            .map(|(epoch, value)| (*epoch, value.and_then(|value| f(*epoch, value))))
+            .collect();
+        self.with_metrics(metrics)
+    }
+
+    /// `stacked_on` returns a copy of the series with the values `below` it added to its values
+    /// at the same epoch, the missing values are filled first so the copy has no missing values
+    pub fn stacked_on(&self, below: &BTreeMap<u64, f64>) -> TimeSeries {
+        let metrics: Vec<(u64, Option<f64>)> = self
+            .filled_iter()
+            .map(|(epoch, value)| (epoch, Some(value + below.get(&epoch).copied().unwrap_or(0f64))))
+            .collect();
+        self.with_metrics(metrics)
+    }
+
+    /// `with_metrics` returns a copy of the series with the active `metrics` in order, the stats
+    /// are calculated for them
+    fn with_metrics(&self, metrics: Vec<(u64, Option<f64>)>) -> TimeSeries {
+        let mut res = TimeSeries {
+            active_items: metrics.len(),
+            first_idx: 0,
+            metrics,
+            #[cfg(feature = "upsert-debug")]
+            prev_snapshot: vec![],
+            running_stats: RunningStats::default(),
+            ..self.clone()
+        };
+        res.rescan_stats();
+        res
+    }
+
+    /// `memory_bytes` returns the memory held by the samples of the series
+    pub fn memory_bytes(&self) -> usize {
+        #[cfg(feature = "upsert-debug")]
+        let samples = self.metrics.capacity() + self.prev_snapshot.capacity();
+        #[cfg(not(feature = "upsert-debug"))]
+        let samples = self.metrics.capacity();
+        samples * SAMPLE_BYTES
+    }
+
+    /// `budget_capacity` returns the capacity the series may reach, adaptive series may grow up
+    /// to their max_capacity
+    pub fn budget_capacity(&self) -> usize {
+        if self.adaptive_capacity {
+            self.metrics_capacity().max(self.max_capacity)
+        } else {
+            self.metrics_capacity()
+        }
+    }
+
+    /// `projected_memory_bytes` returns the memory of the series and its vertices once its
+    /// capacity is filled
+    pub fn projected_memory_bytes(&self) -> usize {
+        self.budget_capacity() * (SAMPLE_BYTES + VERTEX_BYTES)
+    }
+
+    /// `limit_capacity` reduces the capacity of the series, adaptive series do not grow over it
+    pub fn limit_capacity(&mut self, capacity: usize) {
+        if self.adaptive_capacity {
+            self.max_capacity = self.max_capacity.min(capacity);
+        }
+        if self.metrics_capacity() > capacity {
+            self.resize_capacity(capacity);
+        }
+    }
+
+    /// `metrics_capacity` returns the number of metrics the circular buffer holds, it is always
+    /// at least 1 even if the configuration sets it to 0.
+    pub fn metrics_capacity(&self) -> usize {
+        self.metrics_capacity.max(1)
+    }
+
+    /// `epoch_step` returns the distance between the epochs of two consecutive samples, in
+    /// milliseconds for a granularity under a second and in seconds otherwise. It is at least 1.
+    pub fn epoch_step(&self) -> u64 {
+        if self.has_millisecond_epochs() {
+            self.granularity_ms.max(1)
+        } else {
+            self.granularity_ms / 1000
+        }
+    }
+
+    /// `has_millisecond_epochs` returns true when the epochs of the series are in milliseconds
+    pub fn has_millisecond_epochs(&self) -> bool {
+        self.granularity_ms < 1000
+    }
+
+    /// `align_epoch` truncates an epoch to the granularity of the series
+    fn align_epoch(&self, epoch: u64) -> u64 {
+        epoch - epoch % self.epoch_step()
+    }
+
+    /// `steps_between` returns the number of samples from the epoch `from` to the epoch `to`,
+    /// negative when `to` is the older one
+    fn steps_between(&self, from: u64, to: u64) -> i64 {
+        (to as i64 - from as i64) / self.epoch_step() as i64
+    }
+
+    /// `resize_capacity` changes the amount of metrics the circular buffer can hold, the active
+    /// items are laid out in order into a new Vec. When shrinking below the active items, the
+    /// oldest entries are dropped.
+    pub fn resize_capacity(&mut self, new_capacity: usize) {
+        if new_capacity == 0 || new_capacity == self.metrics_capacity() {
+            return;
+        }
+        let active = self.as_vec();
+        let skip = active.len().saturating_sub(new_capacity);
+        let mut metrics = Vec::with_capacity(new_capacity);
+        metrics.extend_from_slice(&active[skip..]);
+        event!(
+            Level::DEBUG,
+            "resize_capacity: from {} to {}, dropped {} items",
+            self.metrics_capacity(),
+            new_capacity,
+            skip
+        );
+        self.active_items = metrics.len();
+        self.first_idx = 0;
+        #[cfg(feature = "upsert-debug")]
+        {
+            self.prev_snapshot = metrics.clone();
+        }
+        self.metrics = metrics;
+        self.metrics_capacity = new_capacity;
+        self.running_stats.invalidate();
+        self.calculate_stats();
+    }
+
+    /// `clear` drops the samples and the stats of the series, i.e. after its query changed. The
+    /// capacity and the policies are kept, the next upsert starts a fresh circular buffer.
+    pub fn clear(&mut self) {
+        event!(Level::DEBUG, "clear: dropping {} items", self.active_items);
+        self.metrics.clear();
+        self.first_idx = 0;
+        self.active_items = 0;
+        #[cfg(feature = "upsert-debug")]
+        {
+            self.prev_snapshot.clear();
+            self.prev_value = (0, None);
+            self.upsert_type = UpsertType::default();
+        }
+        self.stats = TimeSeriesStats::default();
+        self.running_stats = RunningStats::default();
+        self.sync_padding.clear();
+    }
+
     /// `with_missing_values_policy` receives a String and returns
     /// a MissingValuesPolicy, TODO: the "Fixed" value is not implemented.
     pub fn with_missing_values_policy(mut self, policy_type: String) -> TimeSeries {
@@ -775,6 +5213,7 @@ impl TimeSeries {
             "last" => MissingValuesPolicy::Last,
             "avg" => MissingValuesPolicy::Avg,
             "first" => MissingValuesPolicy::First,
+            "interpolate" => MissingValuesPolicy::Interpolate,
             _ => {
                 // TODO: Implement FromStr somehow
                 MissingValuesPolicy::fixed(policy_type.clone()).unwrap_or_default()
@@ -783,8 +5222,67 @@ impl TimeSeries {
         self
     }
 
-    /// `calculate_stats` Iterates over the metrics and sets the stats
+    /// `calculate_stats` sets the stats of the active window, the Incremental mode uses the running
+    /// stats kept by the upserts unless they need a rescan
     pub fn calculate_stats(&mut self) {
+        #[cfg(test)]
+        let before = self.clone();
+        // The windows left inconsistent by an upsert are rescanned.
+        let consistent = self.active_items <= self.metrics.len()
+            && self.metrics.len() <= self.metrics_capacity();
+        match self.stats_mode {
+            StatsMode::Incremental
+                if consistent && self.running_stats.is_valid(self.metrics_capacity()) =>
+            {
+                self.apply_running_stats()
+            },
+            _ => self.rescan_stats(),
+        }
+        #[cfg(test)]
+        {
+            let mut rescanned = before;
+            rescanned.rescan_stats();
+            // The epochs of the windows that fail the sanity check are not in order, the newest
+            // epoch may not be the last one.
+            debug_assert!(
+                !consistent || !self.sanity_check() || same_stats(&self.stats, &rescanned.stats),
+                "calculate_stats: {:?} stats differ from the rescan: {:?}",
+                self.stats_mode,
+                (self.stats, rescanned.stats)
+            );
+        }
+    }
+
+    /// `apply_running_stats` sets the stats from the running stats, only the first and the last
+    /// entries are read. They are filled like in a rescan, with the stats before this calculation.
+    fn apply_running_stats(&mut self) {
+        let first = match self.iter().next() {
+            Some((_, Some(metric))) => *metric,
+            Some((_, None)) => self.get_edge_fill(false),
+            None => 0.,
+        };
+        let last = match self.iter().rev().next() {
+            Some((_, Some(metric))) => *metric,
+            Some((_, None)) => self.get_edge_fill(true),
+            None => 0.,
+        };
+        let running = self.running_stats;
+        self.stats.max = running.max;
+        self.stats.min = running.min;
+        self.stats.sum = running.sum;
+        self.stats.avg = running.sum / (running.count as f64);
+        self.stats.count = running.count;
+        self.stats.stddev = stddev_of(running.m2, running.count);
+        self.stats.missing_count = self.active_items.saturating_sub(running.count);
+        self.stats.first = first;
+        self.stats.last = last;
+        // The epochs of the active window are sorted.
+        self.stats.last_epoch = self.newest_epoch().unwrap_or(0);
+        self.stats.is_dirty = false;
+    }
+
+    /// `rescan_stats` iterates over the metrics and sets the stats and the running stats
+    fn rescan_stats(&mut self) {
         // Recalculating seems to be necessary because we are constantly
         // moving items out of the Vec<> so our cache can easily get out of
         // sync
@@ -792,6 +5290,9 @@ impl TimeSeries {
         let mut min_metric_value = f64::MAX;
         let mut sum_metric_values = 0f64;
         let mut filled_metrics = 0usize;
+        let mut missing_metrics = 0usize;
+        let mut mean = 0f64;
+        let mut m2 = 0f64;
         // XXX What is it the vec is empty? what should `first` and `last` be?
         let mut first = 0.;
         let mut last = 0.;
@@ -814,15 +5315,20 @@ impl TimeSeries {
                 }
                 sum_metric_values += metric;
                 filled_metrics += 1;
+                // Welford's update, the squared distances are summed around the running mean.
+                let delta = metric - mean;
+                mean += delta / filled_metrics as f64;
+                m2 += delta * (metric - mean);
                 last = metric;
             } else {
+                missing_metrics += 1;
                 // The vector could be empty, so the `.first` value could be invalid, fill it with
                 // the MissingValuesPolicy
                 if !is_first_filled {
                     is_first_filled = true;
-                    first = self.get_missing_values_fill();
+                    first = self.get_edge_fill(false);
                 }
-                last = self.get_missing_values_fill();
+                last = self.get_edge_fill(true);
             }
         }
         self.stats.max = max_metric_value;
@@ -830,10 +5336,22 @@ impl TimeSeries {
         self.stats.sum = sum_metric_values;
         self.stats.avg = sum_metric_values / (filled_metrics as f64);
         self.stats.count = filled_metrics;
+        self.stats.stddev = stddev_of(m2, filled_metrics);
+        self.stats.missing_count = missing_metrics;
         self.stats.first = first;
         self.stats.last = last;
         self.stats.last_epoch = max_epoch;
         self.stats.is_dirty = false;
+        self.running_stats = RunningStats {
+            sum: sum_metric_values,
+            count: filled_metrics,
+            max: max_metric_value,
+            min: min_metric_value,
+            mean,
+            m2,
+            valid: true,
+            removed: 0,
+        };
     }
 
     /// `get_missing_values_fill` uses the MissingValuesPolicy to decide
@@ -848,18 +5366,67 @@ impl TimeSeries {
             MissingValuesPolicy::First => self.get_first_filled(),
             MissingValuesPolicy::Avg => self.stats.avg,
             MissingValuesPolicy::Fixed(val) => val,
+            // Without the neighbors of the missing value the latest value is the nearest one.
+            MissingValuesPolicy::Interpolate => self.get_last_filled(),
         }
     }
 
+    /// `get_edge_fill` returns the fill of a missing value at the oldest or the `newest` end of
+    /// the active window, an interpolated end has only the nearest filled value on one side
+    fn get_edge_fill(&self, newest: bool) -> f64 {
+        match self.missing_values_policy {
+            MissingValuesPolicy::Interpolate if !newest => self.get_first_filled(),
+            _ => self.get_missing_values_fill(),
+        }
+    }
+
+    /// `filled_iter` returns the epochs and values of the active window from the oldest to the
+    /// newest, the missing values are filled with the MissingValuesPolicy
+    pub fn filled_iter(&self) -> impl Iterator<Item = (u64, f64)> + '_ {
+        let fill = self.get_missing_values_fill();
+        let interpolate = self.missing_values_policy == MissingValuesPolicy::Interpolate;
+        let mut previous: Option<(u64, f64)> = None;
+        let mut next: Option<(u64, f64)> = None;
+        // The filled entries ahead are only looked up for the gaps of an interpolated series.
+        let mut ahead = self.iter().filter_map(|(epoch, value)| value.map(|x| (*epoch, x))).fuse();
+        self.iter().map(move |&(epoch, value)| {
+            if let Some(value) = value {
+                previous = Some((epoch, value));
+                return (epoch, value);
+            }
+            if !interpolate {
+                return (epoch, fill);
+            }
+            while next.map_or(true, |(next_epoch, _)| next_epoch <= epoch) {
+                next = ahead.next();
+                if next.is_none() {
+                    break;
+                }
+            }
+            let value = match (previous, next) {
+                (Some((before_epoch, before)), Some((after_epoch, after)))
+                    if after_epoch > before_epoch =>
+                {
+                    let ratio = epoch.saturating_sub(before_epoch) as f64
+                        / (after_epoch - before_epoch) as f64;
+                    before + (after - before) * ratio
+                },
+                (Some((_, nearest)), _) | (None, Some((_, nearest))) => nearest,
+                (None, None) => fill,
+            };
+            (epoch, value)
+        })
+    }
+
     /// `resolve_metric_collision` ensures the policy for colliding values is
     /// applied.
     pub fn resolve_metric_collision(&self, existing: Option<f64>, new: Option<f64>) -> Option<f64> {
         if let Some(new) = new {
             if let Some(existing) = existing {
                 Some(match self.collision_policy {
-                    ValueCollisionPolicy::Increment => existing + new,
+                    ValueCollisionPolicy::Increment => self.clamp_collision(existing + new),
                     ValueCollisionPolicy::Overwrite => new,
-                    ValueCollisionPolicy::Decrement => existing - new,
+                    ValueCollisionPolicy::Decrement => self.clamp_collision(existing - new),
                     ValueCollisionPolicy::Ignore => existing,
                 })
             } else {
@@ -871,29 +5438,54 @@ impl TimeSeries {
         }
     }
 
+    /// `clamp_collision` bounds the value resulting from a collision to the collision_floor and
+    /// collision_ceiling, when they are set.
+    fn clamp_collision(&self, value: f64) -> f64 {
+        let mut res = value;
+        if let Some(floor) = self.collision_floor {
+            res = res.max(floor);
+        }
+        if let Some(ceiling) = self.collision_ceiling {
+            res = res.min(ceiling);
+        }
+        res
+    }
+
     /// `circular_push` adds an item to the circular buffer
     fn circular_push(&mut self, input: (u64, Option<f64>)) {
-        if self.metrics.len() < self.metrics_capacity {
+        self.circular_write(input);
+        self.stats.is_dirty = true;
+    }
+
+    /// `circular_write` adds an item to the circular buffer without marking the stats dirty, the
+    /// caller marks them once it is done writing
+    fn circular_write(&mut self, input: (u64, Option<f64>)) {
+        if self.metrics.len() < self.metrics_capacity() {
             if self.active_items < self.metrics.len() {
                 // This means that there are items in our array that can be overwritten, basically
                 // the whole array was discarded at some point, but we cannot .push() to the array
                 // because that would leave these items unaccounted for.
-                let next_idx = (self.get_last_idx() + 1) % self.metrics_capacity;
+                let next_idx = (self.get_last_idx() + 1) % self.metrics_capacity();
                 self.metrics[next_idx] = input;
             } else {
                 self.metrics.push(input);
             }
             self.active_items += 1;
         } else {
-            let target_idx = (self.first_idx + self.active_items) % self.metrics_capacity;
+            let target_idx = (self.first_idx + self.active_items) % self.metrics_capacity();
+            let evicted = self.metrics[target_idx].1;
             self.metrics[target_idx] = input;
-            match self.active_items.cmp(&self.metrics_capacity) {
+            match self.active_items.cmp(&self.metrics_capacity()) {
                 Ordering::Less => self.active_items += 1,
-                Ordering::Equal => self.first_idx = (self.first_idx + 1) % self.metrics_capacity,
+                Ordering::Equal => {
+                    // The oldest item is overwritten.
+                    self.first_idx = (self.first_idx + 1) % self.metrics_capacity();
+                    self.running_stats.remove(evicted);
+                },
                 Ordering::Greater => unreachable!(),
             };
         }
-        self.stats.is_dirty = true;
+        self.running_stats.add(input.1);
     }
 
     /// `get_last_idx` returns the last index that was used in the circular buffer
@@ -909,7 +5501,10 @@ impl TimeSeries {
             % self.metrics.len() as i64) as usize
     }
 
+    /// `sync_prev_snapshot` copies the metrics to the debug snapshot before an upsert rewrites
+    /// them, it does nothing without the `upsert-debug` feature
     fn sync_prev_snapshot(&mut self) {
+        #[cfg(feature = "upsert-debug")]
         if self.metrics.len() == self.prev_snapshot.len() {
             for item_num in 0..self.metrics.len() {
                 if self.prev_snapshot[item_num] != self.metrics[item_num] {
@@ -921,47 +5516,175 @@ impl TimeSeries {
         }
     }
 
-    /// `upsert` Adds values to the circular buffer adding empty entries for
-    /// missing entries, may invalidate the buffer if all data is outdated
-    /// it returns the number of inserted records
-    pub fn upsert(&mut self, input: (u64, Option<f64>)) -> usize {
-        // maybe accept a batch to overwrite the data receiving an array.
+    /// `record_upsert` keeps the type and the input of the last upsert for debug purposes, it
+    /// does nothing without the `upsert-debug` feature
+    fn record_upsert(&mut self, upsert_type: UpsertType, input: (u64, Option<f64>)) {
+        #[cfg(feature = "upsert-debug")]
+        {
+            self.upsert_type = upsert_type;
+            self.prev_value = input;
+        }
+        #[cfg(not(feature = "upsert-debug"))]
+        let _ = (upsert_type, input);
+    }
+
+    /// `upsert` Adds values to the circular buffer adding empty entries for
+    /// missing entries, may invalidate the buffer if all data is outdated
+    /// it returns the number of inserted records
+    pub fn upsert(&mut self, input: (u64, Option<f64>)) -> usize {
+        let input = (self.align_epoch(input.0), input.1);
+        // Live data arrives one epoch after the last one or for the last epoch again, these are
+        // handled without the analysis of the other cases.
+        if !self.metrics.is_empty() {
+            let last_idx = self.get_last_idx();
+            let last_epoch = self.metrics[last_idx].0;
+            if input.0 == last_epoch + self.epoch_step() {
+                self.circular_push(input);
+                self.record_upsert(UpsertType::NewEpoch, input);
+                return 1;
+            } else if input.0 == last_epoch {
+                return self.overwrite_last_epoch(last_idx, input);
+            }
+        }
+        self.upsert_slow_path(input)
+    }
+
+    /// `overwrite_last_epoch` resolves the collision of an input for the last indexed epoch
+    fn overwrite_last_epoch(&mut self, last_idx: usize, input: (u64, Option<f64>)) -> usize {
+        let existing = self.metrics[last_idx].1;
+        let resolved = self.resolve_metric_collision(existing, input.1);
+        self.metrics[last_idx].1 = resolved;
+        self.running_stats.replace(existing, resolved);
+        self.record_upsert(UpsertType::OverwriteLastEpoch, input);
+        self.stats.is_dirty = true;
+        0
+    }
+
+    /// `upsert_batch` upserts the items in the order of their epochs, the items of an epoch are
+    /// resolved in the order of the batch. The circular buffer is checked once for the whole
+    /// batch, the epochs newer than the last indexed one are appended with their gaps filled in
+    /// a single pass and the stats are marked dirty once. Returns the number of inserted
+    /// records, the same as upserting the items one by one in the order of their epochs.
+    pub fn upsert_batch(&mut self, items: &[(u64, Option<f64>)]) -> usize {
+        let span = span!(Level::TRACE, "upsert_batch");
+        let _enter = span.enter();
+        let mut items: Vec<(u64, Option<f64>)> =
+            items.iter().map(|(epoch, value)| (self.align_epoch(*epoch), *value)).collect();
+        // The sort is stable, the collisions keep the order of the batch.
+        items.sort_by_key(|(epoch, _)| *epoch);
+        let newer_from = if self.metrics.is_empty() {
+            0
+        } else {
+            if !self.sanity_check() {
+                event!(Level::ERROR, "upsert_batch: Sanity check failed: {:?}", self);
+            }
+            let last_epoch = self.metrics[self.get_last_idx()].0;
+            items.partition_point(|(epoch, _)| *epoch <= last_epoch)
+        };
+        let (older, newer) = items.split_at(newer_from);
+        // The epochs up to the last indexed one are backfilled or overwritten one by one, the
+        // newer epochs only shift the last index.
+        let mut inserted: usize = older.iter().map(|input| self.upsert_unchecked(*input)).sum();
+        for input in newer {
+            inserted += self.append_epoch(*input);
+        }
+        if !newer.is_empty() {
+            self.stats.is_dirty = true;
+        }
+        inserted
+    }
+
+    /// `merge_from` upserts the filled samples of the active window of `other`, the values of the
+    /// epochs of both series are resolved with `policy` instead of the collision_policy of the
+    /// series. Returns the number of samples merged, the ones older than the resulting window
+    /// are dropped and not counted.
+    pub fn merge_from(&mut self, other: &TimeSeries, policy: ValueCollisionPolicy) -> usize {
+        let samples: Vec<(u64, Option<f64>)> =
+            other.iter().filter(|(_, value)| value.is_some()).copied().collect();
+        let collision_policy = std::mem::replace(&mut self.collision_policy, policy);
+        self.upsert_batch(&samples);
+        self.collision_policy = collision_policy;
+        let oldest_epoch = self.oldest_epoch().unwrap_or_default();
+        samples.iter().filter(|(epoch, _)| self.align_epoch(*epoch) >= oldest_epoch).count()
+    }
+
+    /// `append_epoch` writes an input for the last indexed epoch or a newer one, the epochs in
+    /// between are filled with None. The stats are not marked dirty unless the last epoch is
+    /// overwritten.
+    fn append_epoch(&mut self, input: (u64, Option<f64>)) -> usize {
+        if self.metrics.is_empty() {
+            self.circular_write(input);
+            self.record_upsert(UpsertType::Empty, input);
+            return 1;
+        }
+        let last_idx = self.get_last_idx();
+        let last_epoch = self.metrics[last_idx].0;
+        if input.0 == last_epoch {
+            return self.overwrite_last_epoch(last_idx, input);
+        }
+        if self.steps_between(last_epoch, input.0) > self.metrics_capacity() as i64 {
+            self.discard_window(input);
+            return 1;
+        }
+        let step = self.epoch_step();
+        for fill_epoch in ((last_epoch + step)..input.0).step_by(step as usize) {
+            self.circular_write((fill_epoch, None));
+        }
+        self.circular_write(input);
+        self.record_upsert(UpsertType::NewEpoch, input);
+        1
+    }
+
+    /// `discard_window` replaces the whole circular buffer by the input, its epoch is too far
+    /// ahead of the active window to keep any of it
+    fn discard_window(&mut self, input: (u64, Option<f64>)) {
+        self.sync_prev_snapshot();
+        self.first_idx = 0;
+        self.metrics[0] = input;
+        self.active_items = 1;
+        self.running_stats.invalidate();
+        self.stats.is_dirty = true;
+        self.record_upsert(UpsertType::VectorDiscarded, input);
+    }
+
+    /// `upsert_slow_path` handles every input of `upsert`, including the ones of its fast path
+    fn upsert_slow_path(&mut self, input: (u64, Option<f64>)) -> usize {
         let span = span!(Level::TRACE, "upsert");
         let _enter = span.enter();
         if self.metrics.is_empty() {
             self.circular_push(input);
-            self.upsert_type = UpsertType::Empty;
-            self.prev_value = input;
+            self.record_upsert(UpsertType::Empty, input);
             return 1;
         }
         if !self.sanity_check() {
             event!(Level::ERROR, "upsert: Sanity check failed: {:?}", self);
             // return 0usize;
         }
+        self.upsert_unchecked(input)
+    }
+
+    /// `upsert_unchecked` upserts an input into a circular buffer that is not empty, the caller
+    /// runs the sanity check
+    fn upsert_unchecked(&mut self, input: (u64, Option<f64>)) -> usize {
         let last_idx = self.get_last_idx();
-        if (self.metrics[last_idx].0 as i64 - input.0 as i64) >= self.metrics_capacity as i64 {
+        let step = self.epoch_step();
+        if self.steps_between(input.0, self.metrics[last_idx].0) >= self.metrics_capacity() as i64 {
             // The timestamp is too old and should be discarded.
             // This means we cannot scroll back in time.
             // i.e. if the date of the computer needs to go back in time
             // we would need to restart the terminal to see metrics
             // XXX: What about timezones?
-            self.upsert_type = UpsertType::TooOld;
-            self.prev_value = input;
+            self.record_upsert(UpsertType::TooOld, input);
             return 0;
         }
         // as_vec() is 5, 6, 7, 3, 4
         // active_items: 3
         // input.0: 5
         // inactive_time = -2
-        let inactive_time = input.0 as i64 - self.metrics[last_idx].0 as i64;
-        if inactive_time > self.metrics_capacity as i64 {
+        let inactive_time = self.steps_between(self.metrics[last_idx].0, input.0);
+        if inactive_time > self.metrics_capacity() as i64 {
             // The whole vector should be discarded
-            self.sync_prev_snapshot();
-            self.first_idx = 0;
-            self.metrics[0] = input;
-            self.active_items = 1;
-            self.upsert_type = UpsertType::VectorDiscarded;
-            self.prev_value = input;
+            self.discard_window(input);
             1
         } else if inactive_time < 0 {
             // We have a metric for an epoch in the past.
@@ -971,84 +5694,102 @@ impl TimeSeries {
             if current_min_epoch > input.0 {
                 // The input epoch before anything we have registered.
                 // But still within our capacity boundaries
-                let padding_items = (current_min_epoch - input.0) as usize;
-                // XXX: This is wrong, we should add as many padding_items as possible without
-                // breaking the metrics_capacity.
+                let padding_items = self.steps_between(input.0, current_min_epoch) as usize;
+                if padding_items + self.active_items > self.metrics_capacity() {
+                    // The active items and the padding up to the input do not fit in the window,
+                    // i.e. the active epochs were spread by a resize, the input is discarded.
+                    self.record_upsert(UpsertType::TooOld, input);
+                    return 0;
+                }
                 self.sync_prev_snapshot();
-                if self.metrics.len() + 1 < self.metrics_capacity {
-                    // The vector is not full, let's shift the items to the right
-                    // The array items have not been allocated at this point:
-                    self.metrics.insert(0, input);
-                    for idx in 1..padding_items {
-                        self.metrics.insert(idx, (input.0 + idx as u64, None));
+                // The input followed by the padding up to the current min epoch.
+                let older = (0..padding_items as u64).map(move |idx| {
+                    if idx == 0 {
+                        input
+                    } else {
+                        (input.0 + idx * step, None)
                     }
-                    self.active_items += padding_items;
-                    self.upsert_type = UpsertType::PrevEpochInputVecNotFull;
-                    self.prev_value = input;
-                    padding_items
+                });
+                let capacity = self.metrics_capacity();
+                if self.metrics.len() < capacity {
+                    // The vector is not full, the older items are laid out before the active
+                    // ones and the inactive items are dropped.
+                    let mut metrics = Vec::with_capacity(capacity);
+                    metrics.extend(older);
+                    metrics.extend(self.iter().copied());
+                    self.metrics = metrics;
+                    self.first_idx = 0;
+                    self.record_upsert(UpsertType::PrevEpochInputVecNotFull, input);
                 } else {
-                    // The vector is full, write the new epoch at first_idx and then fill the rest
-                    // up to current_min value with None
-                    let previous_min_epoch = self.metrics[self.first_idx].0;
-                    // Find what would be the first index given the current input, in case we need
-                    // to roll back from the end of the array
-                    let target_idx = self.get_tail_backwards_offset_idx(inactive_time);
-                    self.metrics[target_idx] = input;
-                    self.first_idx = target_idx;
-                    // We need to backfill the vector from a previous position, we need to cache the
-                    // previous version of active_items and then add it back after the operation
-                    let previous_active_items = self.active_items;
-                    self.active_items = 1;
-                    for fill_epoch in (input.0 + 1)..previous_min_epoch {
-                        self.circular_push((fill_epoch, None));
+                    // The vector is full, the older items overwrite the inactive slots before
+                    // first_idx, rolling back from the start of the array if needed.
+                    let len = self.metrics.len();
+                    let target_idx = (self.first_idx + len - padding_items) % len;
+                    for (offset, item) in older.enumerate() {
+                        self.metrics[(target_idx + offset) % len] = item;
                     }
-                    self.upsert_type = UpsertType::PrevEpochInputVecFull;
-                    self.prev_value = input;
-                    // XXX: make sure this doesn't go above the metrics_capacity
-                    self.active_items += previous_active_items;
-                    (previous_min_epoch - input.0) as usize
+                    self.first_idx = target_idx;
+                    self.record_upsert(UpsertType::PrevEpochInputVecFull, input);
                 }
+                self.active_items += padding_items;
+                self.running_stats.invalidate();
+                self.stats.is_dirty = true;
+                padding_items
             } else {
                 // The input epoch has already been inserted in our array
                 let target_idx = self.get_tail_backwards_offset_idx(inactive_time);
                 if self.metrics[target_idx].0 == input.0 {
-                    self.metrics[target_idx].1 =
-                        self.resolve_metric_collision(self.metrics[target_idx].1, input.1);
+                    let existing = self.metrics[target_idx].1;
+                    let resolved = self.resolve_metric_collision(existing, input.1);
+                    self.metrics[target_idx].1 = resolved;
+                    self.running_stats.replace(existing, resolved);
+                    self.stats.is_dirty = true;
                 } else {
+                    // Only the epochs around target_idx are logged, the whole vectors are
+                    // written at TRACE.
+                    let len = self.metrics.len();
+                    let around: Vec<u64> = self.metrics
+                        [target_idx.saturating_sub(2)..(target_idx + 3).min(len)]
+                        .iter()
+                        .map(|(epoch, _)| *epoch)
+                        .collect();
                     event!(
                         Level::ERROR,
                         "upsert: lost synchrony len: {}, first_idx: {}, last_idx: {}, target_idx: \
-                         {}, inactive_time: {}, input: {}, target_idx data: {}, prev_value: {:?}, \
-                         upsert_type: {:?}, prev_snapshot: {:?}, metrics: {:?}",
-                        self.metrics.len(),
+                         {}, inactive_time: {}, input: {}, target_idx data: {}, epochs around \
+                         target_idx: {:?}",
+                        len,
                         self.first_idx,
                         last_idx,
                         target_idx,
                         inactive_time,
                         input.0,
                         self.metrics[target_idx].0,
+                        around
+                    );
+                    event!(Level::TRACE, "upsert: lost synchrony metrics: {:?}", self.metrics);
+                    #[cfg(feature = "upsert-debug")]
+                    event!(
+                        Level::TRACE,
+                        "upsert: lost synchrony prev_value: {:?}, upsert_type: {:?}, \
+                         prev_snapshot: {:?}",
                         self.prev_value,
                         self.upsert_type,
-                        self.prev_snapshot,
-                        self.metrics
+                        self.prev_snapshot
                     );
                     // Let's reset the whole vector if we lost synchrony
                     self.first_idx = 0;
                     self.metrics[0] = input;
                     self.active_items = 1;
+                    self.running_stats.invalidate();
+                    self.stats.is_dirty = true;
                 }
-                self.upsert_type = UpsertType::OverwritePrevEpoch;
-                self.prev_value = input;
+                self.record_upsert(UpsertType::OverwritePrevEpoch, input);
                 0
             }
         } else if inactive_time == 0 {
             // We have a metric for the last indexed epoch
-            self.metrics[last_idx].1 =
-                self.resolve_metric_collision(self.metrics[last_idx].1, input.1);
-            self.upsert_type = UpsertType::OverwriteLastEpoch;
-            self.prev_value = input;
-            self.stats.is_dirty = true;
-            0
+            self.overwrite_last_epoch(last_idx, input)
         } else {
             // The input epoch is in the future
             let max_epoch = self.metrics[last_idx].0;
@@ -1057,39 +5798,52 @@ impl TimeSeries {
             // active_items = 1
             // metrics_capacity = 15
             // [9] [2] [3] [4]
-            for fill_epoch in (max_epoch + 1)..input.0 {
+            for fill_epoch in ((max_epoch + step)..input.0).step_by(step as usize) {
                 self.circular_push((fill_epoch, None));
             }
             self.circular_push(input);
-            self.upsert_type = UpsertType::NewEpoch;
-            self.prev_value = input;
+            self.record_upsert(UpsertType::NewEpoch, input);
             1
         }
     }
 
     /// `get_last_filled` Returns the last filled entry in the circular buffer
     pub fn get_last_filled(&self) -> f64 {
-        let mut idx = self.get_last_idx();
-        loop {
-            if let Some(res) = self.metrics[idx].1 {
-                return res;
-            }
-            idx = if idx == 0 { self.metrics.len() } else { idx - 1 };
-            if idx == self.first_idx {
-                break;
-            }
-        }
-        0f64
+        self.iter().rev().find_map(|(_, value)| *value).unwrap_or(0f64)
     }
 
     /// `get_first_filled` Returns the first filled entry in the circular buffer
     pub fn get_first_filled(&self) -> f64 {
-        for entry in self.iter() {
-            if let Some(metric) = entry.1 {
-                return metric;
-            }
+        self.iter().find_map(|(_, value)| *value).unwrap_or(0f64)
+    }
+
+    /// `percentile` returns the `p`th percentile, from 0 to 100, of the filled values of the
+    /// active window, interpolated between the closest ranks. The min and the max are found
+    /// without copying the window. None without filled values or for a `p` out of range.
+    pub fn percentile(&self, p: f64) -> Option<f64> {
+        if !(0f64..=100f64).contains(&p) {
+            return None;
+        }
+        let filled = || self.iter().filter_map(|(_, value)| *value);
+        if p == 0f64 {
+            return filled().reduce(f64::min);
+        }
+        if p == 100f64 {
+            return filled().reduce(f64::max);
+        }
+        let mut values: Vec<f64> = filled().collect();
+        if values.is_empty() {
+            return None;
+        }
+        let rank = p / 100f64 * (values.len() - 1) as f64;
+        let lower_idx = rank.floor() as usize;
+        let (_, lower, above) = values.select_nth_unstable_by(lower_idx, f64::total_cmp);
+        let lower = *lower;
+        // The value of the next rank is the smallest of the values above the lower one.
+        match above.iter().copied().reduce(f64::min) {
+            Some(upper) => Some(lower + (upper - lower) * (rank - lower_idx as f64)),
+            None => Some(lower),
         }
-        0f64
     }
 
     /// `as_vec` Returns the circular buffer in flat vec format
@@ -1110,21 +5864,180 @@ impl TimeSeries {
         if self.metrics.is_empty() {
             return vec![];
         }
-        let mut res: Vec<(u64, Option<f64>)> = Vec::with_capacity(self.metrics_capacity);
+        let mut res: Vec<(u64, Option<f64>)> = Vec::with_capacity(self.metrics_capacity());
         for entry in self.iter() {
             res.push(*entry)
         }
         res
     }
 
+    /// `push_current_epoch` upserts a value for the current time, truncated to the granularity
     pub fn push_current_epoch(&mut self, input: f64) {
-        let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
-        self.upsert((now, Some(input)));
+        let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap();
+        let epoch =
+            if self.has_millisecond_epochs() { now.as_millis() as u64 } else { now.as_secs() };
+        self.upsert((epoch, Some(input)));
+    }
+
+    /// `summary` describes the series in a bounded line for the logs, the stats are the last
+    /// calculated ones.
+    pub fn summary(&self, name: &str) -> TimeSeriesSummary {
+        TimeSeriesSummary {
+            name: name.to_string(),
+            capacity: self.metrics_capacity,
+            active_items: self.active_items,
+            first_epoch: self.oldest_epoch(),
+            last_epoch: self.newest_epoch(),
+            stats: format!(
+                "min: {}, max: {}, avg: {}, count: {}",
+                self.stats.min, self.stats.max, self.stats.avg, self.stats.count
+            ),
+        }
+    }
+
+    /// `newest_epoch` returns the epoch of the newest metric, None when the series is empty
+    pub fn newest_epoch(&self) -> Option<u64> {
+        self.iter().rev().next().map(|(epoch, _)| *epoch)
+    }
+
+    /// `oldest_epoch` returns the epoch of the oldest metric, None when the series is empty
+    pub fn oldest_epoch(&self) -> Option<u64> {
+        self.iter().next().map(|(epoch, _)| *epoch)
+    }
+
+    /// `pad_to_epoch` upserts a None at `epoch` so that the series reaches the latest epoch of
+    /// the other charts, the entries it adds are kept as sync padding instead of gaps of the
+    /// source. Returns the number of inserted records.
+    pub fn pad_to_epoch(&mut self, epoch: u64) -> usize {
+        let from = match self.newest_epoch() {
+            Some(newest) if newest >= epoch => return self.upsert((epoch, None)),
+            Some(newest) => {
+                (newest + 1).max((epoch + 1).saturating_sub(self.metrics_capacity() as u64))
+            },
+            None => epoch,
+        };
+        let inserted = self.upsert((epoch, None));
+        self.sync_padding.extend(from..=epoch);
+        // The padding evicted from the active window is forgotten.
+        if let Some(oldest) = self.oldest_epoch() {
+            self.sync_padding = self.sync_padding.split_off(&oldest);
+        }
+        inserted
+    }
+
+    /// `sync_padding_count` returns the entries of the active window that are still None since
+    /// they were padded by `pad_to_epoch`
+    pub fn sync_padding_count(&self) -> usize {
+        if self.sync_padding.is_empty() {
+            return 0;
+        }
+        self.iter()
+            .filter(|(epoch, value)| value.is_none() && self.sync_padding.contains(epoch))
+            .count()
+    }
+
+    /// `fill_counts` returns the entries of the active window with a value and the entries
+    /// considered, the sync padding is left out unless `with_padding`. The counts are the ones of
+    /// the last calculation of the stats.
+    fn fill_counts(&self, with_padding: bool) -> (usize, usize) {
+        let considered = self.stats.count + self.stats.missing_count;
+        if with_padding {
+            (self.stats.count, considered)
+        } else {
+            (self.stats.count, considered.saturating_sub(self.sync_padding_count()))
+        }
+    }
+
+    /// `fill_ratio` returns the fraction of the entries of the active window that have a value,
+    /// the sync padding is not a gap of the source and is left out. None without entries.
+    pub fn fill_ratio(&self) -> Option<f64> {
+        fill_ratio(self.fill_counts(false))
+    }
+
+    /// `fill_ratio_with_padding` returns the fraction of the entries of the active window that
+    /// have a value, the sync padding counts as gaps. None without entries.
+    pub fn fill_ratio_with_padding(&self) -> Option<f64> {
+        fill_ratio(self.fill_counts(true))
+    }
+
+    /// `window_summary` summarizes the values between `from_epoch` and `to_epoch`, both
+    /// included. The range is clamped to the retained metrics, it is truncated when it starts
+    /// before the oldest of them. None when there are no values in the range.
+    pub fn window_summary(&self, from_epoch: u64, to_epoch: u64) -> Option<WindowSummary> {
+        if from_epoch > to_epoch {
+            return None;
+        }
+        let truncated = from_epoch < self.oldest_epoch()?;
+        let mut values = self
+            .range(from_epoch, to_epoch)
+            .filter_map(|(epoch, value)| value.map(|value| (*epoch, value)));
+        let (first_epoch, first) = values.next()?;
+        let mut summary = WindowSummary {
+            from_epoch: first_epoch,
+            to_epoch: first_epoch,
+            first,
+            last: first,
+            min: first,
+            max: first,
+            avg: 0f64,
+            count: 1,
+            truncated,
+        };
+        let mut sum = first;
+        for (epoch, value) in values {
+            summary.to_epoch = epoch;
+            summary.last = value;
+            summary.min = summary.min.min(value);
+            summary.max = summary.max.max(value);
+            summary.count += 1;
+            sum += value;
+        }
+        summary.avg = sum / summary.count as f64;
+        Some(summary)
+    }
+
+    /// `rebase` drops the metrics and restarts the window of the series at `new_epoch`, the
+    /// configuration and the allocated capacity are kept.
+    pub fn rebase(&mut self, new_epoch: u64) {
+        self.clear();
+        self.upsert((new_epoch, None));
+    }
+
+    /// `shift_epochs` moves the epochs of the metrics `delta` seconds back, the values are kept
+    pub fn shift_epochs(&mut self, delta: u64) {
+        for (epoch, _) in self.metrics.iter_mut() {
+            *epoch = epoch.saturating_sub(delta);
+        }
+        #[cfg(feature = "upsert-debug")]
+        {
+            for (epoch, _) in self.prev_snapshot.iter_mut() {
+                *epoch = epoch.saturating_sub(delta);
+            }
+            self.prev_value.0 = self.prev_value.0.saturating_sub(delta);
+        }
+        self.sync_padding =
+            self.sync_padding.iter().map(|epoch| epoch.saturating_sub(delta)).collect();
+        self.stats.is_dirty = true;
     }
 
-    /// `iter` Returns an Iterator from the current start for our circular buffer
+    /// `iter` Returns an Iterator from the current start for our circular buffer, it is reversed
+    /// to walk from the newest entry. Each active slot is visited once, also when the active items
+    /// wrap around the end of the circular buffer.
     fn iter(&self) -> IterTimeSeries<'_> {
-        IterTimeSeries { inner: self, pos: self.first_idx, current_item: 0 }
+        IterTimeSeries { inner: self, front: 0, back: self.active_items.min(self.metrics.len()) }
+    }
+
+    /// `range` returns the active entries with an epoch from `start_epoch` to `end_epoch`, both
+    /// included, from the oldest to the newest.
+    pub fn range(
+        &self,
+        start_epoch: u64,
+        end_epoch: u64,
+    ) -> impl Iterator<Item = &(u64, Option<f64>)> {
+        // The epochs of the active window are sorted.
+        self.iter()
+            .skip_while(move |(epoch, _)| *epoch < start_epoch)
+            .take_while(move |(epoch, _)| *epoch <= end_epoch)
     }
 
     /// `sanity_check` verifies the state of the circular buffer is valid
@@ -1134,7 +6047,7 @@ impl TimeSeries {
         }
         let mut curr_idx = self.first_idx;
         while curr_idx != self.get_last_idx() {
-            let next_idx = (curr_idx + 1) % self.metrics_capacity;
+            let next_idx = (curr_idx + 1) % self.metrics_capacity();
             if self.metrics[curr_idx].0 >= self.metrics[next_idx].0 {
                 return false;
             }
@@ -1148,19 +6061,36 @@ impl<'a> Iterator for IterTimeSeries<'a> {
     type Item = &'a (u64, Option<f64>);
 
     fn next(&mut self) -> Option<Self::Item> {
-        if self.inner.metrics.is_empty() || self.current_item == self.inner.active_items {
+        if self.front == self.back {
             return None;
         }
-        let curr_pos = self.pos % self.inner.metrics.len();
-        self.pos = (self.pos + 1) % (self.inner.metrics.len() + 1);
-        self.current_item += 1;
+        // The position wraps to the start of the buffer through the modulo.
+        let curr_pos = (self.inner.first_idx + self.front) % self.inner.metrics.len();
+        self.front += 1;
         Some(&self.inner.metrics[curr_pos])
     }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.back - self.front;
+        (remaining, Some(remaining))
+    }
+}
+
+impl<'a> DoubleEndedIterator for IterTimeSeries<'a> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        if self.front == self.back {
+            return None;
+        }
+        self.back -= 1;
+        Some(&self.inner.metrics[(self.inner.first_idx + self.back) % self.inner.metrics.len()])
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::charts::color::RgbExt;
+    use crate::charts::fixtures;
 
     fn init_log() {
         let _ = env_logger::builder().is_test(true).try_init();
@@ -1450,6 +6380,83 @@ mod tests {
         assert!((test.get_last_filled() - 2f64).abs() < f64::EPSILON);
     }
 
+    #[test]
+    fn it_gets_last_filled_value_on_wrapped_buffers() {
+        let wrapped = fixtures::wrapped_series;
+        // The active items are 12, 13, 14, 15 and only the slot at index 0 is filled.
+        let test = wrapped(vec![(14, Some(4f64)), (15, None), (12, None), (13, None)], 2, 4);
+        assert_eq!(test.get_last_filled(), 4f64);
+        assert_eq!(
+            test.iter().rev().map(|(epoch, _)| *epoch).collect::<Vec<_>>(),
+            vec![15, 14, 13, 12]
+        );
+        // All the active items are None.
+        let test = wrapped(vec![(14, None), (15, None), (12, None), (13, None)], 2, 4);
+        assert_eq!(test.get_last_filled(), 0f64);
+        // A single element buffer.
+        let test = wrapped(vec![(10, None)], 0, 1);
+        assert_eq!(test.get_last_filled(), 0f64);
+        let test = wrapped(vec![(10, Some(3f64))], 0, 1);
+        assert_eq!(test.get_last_filled(), 3f64);
+        // The first item is in the middle of the vector, the first active slot is also visited
+        // and the slot after the active items is not.
+        let metrics = vec![
+            (14, None),
+            (15, None),
+            (10, Some(1f64)),
+            (11, None),
+            (12, None),
+            (13, Some(9f64)),
+        ];
+        let test = wrapped(metrics.clone(), 2, 3);
+        assert_eq!(test.get_last_filled(), 1f64);
+        assert_eq!(test.iter().rev().count(), 3);
+        let test = wrapped(metrics, 2, 6);
+        assert_eq!(test.get_last_filled(), 9f64);
+        // Empty buffers have no filled values.
+        assert_eq!(wrapped(vec![], 0, 0).get_last_filled(), 0f64);
+    }
+
+    #[test]
+    fn it_iterates_both_ends_of_wrapped_buffers() {
+        let wrapped = fixtures::wrapped_series;
+        let epochs = |entries: Vec<&(u64, Option<f64>)>| -> Vec<u64> {
+            entries.into_iter().map(|(epoch, _)| *epoch).collect()
+        };
+        // The active items are 12, 13, 14, 15 and the buffer wraps after 13.
+        let test = wrapped(vec![(14, Some(4f64)), (15, None), (12, Some(2f64)), (13, None)], 2, 4);
+        assert_eq!(epochs(test.iter().collect()), vec![12, 13, 14, 15]);
+        assert_eq!(epochs(test.iter().rev().collect()), vec![15, 14, 13, 12]);
+        assert_eq!(test.get_first_filled(), 2f64);
+        assert_eq!(test.get_last_filled(), 4f64);
+        // Both ends meet in the middle, each item is visited once.
+        let mut iter = test.iter();
+        assert_eq!(iter.next(), Some(&(12, Some(2f64))));
+        assert_eq!(iter.next_back(), Some(&(15, None)));
+        assert_eq!(iter.next(), Some(&(13, None)));
+        assert_eq!(iter.next_back(), Some(&(14, Some(4f64))));
+        assert_eq!((iter.next(), iter.next_back()), (None, None));
+        // The range includes both epochs.
+        assert_eq!(epochs(test.range(13, 14).collect()), vec![13, 14]);
+        assert_eq!(epochs(test.range(0, 12).collect()), vec![12]);
+        assert_eq!(epochs(test.range(10, 20).collect()), vec![12, 13, 14, 15]);
+        assert_eq!(test.range(16, 20).count(), 0);
+        assert_eq!(test.range(14, 13).count(), 0);
+        // The slots after the active items are not in the range.
+        let metrics = vec![
+            (14, None),
+            (15, None),
+            (10, Some(1f64)),
+            (11, None),
+            (12, None),
+            (13, Some(9f64)),
+        ];
+        let test = wrapped(metrics, 2, 3);
+        assert_eq!(epochs(test.range(11, 20).collect()), vec![11, 12]);
+        assert_eq!(test.get_first_filled(), 1f64);
+        assert_eq!(wrapped(vec![], 0, 0).range(0, u64::MAX).count(), 0);
+    }
+
     #[test]
     fn it_transforms_to_flat_vec() {
         let mut test = TimeSeries::default().with_capacity(4);
@@ -1570,6 +6577,437 @@ mod tests {
         // TODO: add Fixed value test
     }
 
+    #[test]
+    fn it_interpolates_the_missing_values() {
+        let mut test_interpolate = TimeSeries::default()
+            .with_capacity(8)
+            .with_missing_values_policy("interpolate".to_string());
+        assert_eq!(test_interpolate.missing_values_policy, MissingValuesPolicy::Interpolate);
+        test_interpolate.upsert((10, None));
+        test_interpolate.upsert((11, Some(2f64)));
+        test_interpolate.upsert((14, Some(8f64)));
+        test_interpolate.upsert((15, None));
+        test_interpolate.calculate_stats();
+        // The ends only have a filled value on one side, the gap is on the line from 2 to 8.
+        assert_eq!(test_interpolate.filled_iter().collect::<Vec<(u64, f64)>>(), vec![
+            (10, 2.),
+            (11, 2.),
+            (12, 4.),
+            (13, 6.),
+            (14, 8.),
+            (15, 8.)
+        ]);
+        assert_eq!((test_interpolate.stats.first, test_interpolate.stats.last), (2., 8.));
+        // The window is discarded, without filled values the missing ones are 0.
+        test_interpolate.upsert((30, None));
+        assert_eq!(test_interpolate.filled_iter().collect::<Vec<(u64, f64)>>(), vec![(30, 0.)]);
+        // The other policies fill every missing value the same.
+        let mut test_last =
+            TimeSeries::default().with_capacity(8).with_missing_values_policy("last".to_string());
+        test_last.upsert((10, Some(2f64)));
+        test_last.upsert((12, Some(8f64)));
+        assert_eq!(test_last.filled_iter().collect::<Vec<(u64, f64)>>(), vec![
+            (10, 2.),
+            (11, 8.),
+            (12, 8.)
+        ]);
+        let yaml = r#"
+name: sparse
+series:
+  missing_values_policy: Interpolate
+"#;
+        let sparse: ManualTimeSeries = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(sparse.series.missing_values_policy, MissingValuesPolicy::Interpolate);
+    }
+
+    #[test]
+    fn it_transforms_the_drawn_values() {
+        let mut counter = TimeSeries::default().with_capacity(5);
+        // The circular buffer wraps around, the oldest sample is dropped, 12 is missing.
+        for (epoch, value) in [(9, 0.), (10, 100.), (11, 160.), (13, 200.), (14, 30.)] {
+            counter.upsert((epoch, Some(value)));
+        }
+        let raw = counter.as_vec();
+        let rate = counter.transformed(SeriesTransform::RatePerSecond);
+        // The rate spans the missing value and the counter reset starts from zero.
+        assert_eq!(rate.as_vec(), vec![
+            (10, None),
+            (11, Some(60.)),
+            (12, None),
+            (13, Some(20.)),
+            (14, Some(30.))
+        ]);
+        assert_eq!((rate.stats.min, rate.stats.max, rate.stats.missing_count), (20., 60., 2));
+        let delta = counter.transformed(SeriesTransform::Delta);
+        assert_eq!(delta.as_vec(), vec![
+            (10, None),
+            (11, Some(60.)),
+            (12, None),
+            (13, Some(40.)),
+            (14, Some(-170.))
+        ]);
+        assert_eq!(counter.transformed(SeriesTransform::None), counter);
+        // The loaded values are kept.
+        assert_eq!(counter.as_vec(), raw);
+        let chart = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                series: counter,
+                transform: SeriesTransform::RatePerSecond,
+                ..ManualTimeSeries::default()
+            })],
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(chart.series_at(0).unwrap().as_vec(), rate.as_vec());
+        assert_eq!(chart.sources[0].series_ref().as_vec(), raw);
+        let yaml = r#"
+name: received
+transform: rate
+"#;
+        let received: ManualTimeSeries = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(received.transform, SeriesTransform::RatePerSecond);
+    }
+
+    #[test]
+    fn it_calculates_the_percentiles_and_the_stddev() {
+        let mut series = TimeSeries::default().with_capacity(10);
+        assert_eq!(series.percentile(50.), None);
+        // The values 2, 4, 4, 4, 5, 5, 7, 9 have a mean of 5 and a standard deviation of 2.
+        let values = [9., -1., 2., 4., 5., 4., -1., 7., 4., 5.];
+        for (epoch, value) in (10..).zip(values) {
+            series.upsert((epoch, Some(value).filter(|value| *value >= 0.)));
+        }
+        series.calculate_stats();
+        assert_eq!((series.stats.avg, series.stats.missing_count), (5., 2));
+        assert!((series.stats.stddev - 2.).abs() < 1e-12);
+        assert_eq!(series.percentile(0.), Some(2.));
+        assert_eq!(series.percentile(25.), Some(4.));
+        assert_eq!(series.percentile(50.), Some(4.5));
+        assert!((series.percentile(90.).unwrap() - 7.6).abs() < 1e-12);
+        assert_eq!(series.percentile(100.), Some(9.));
+        assert_eq!(series.percentile(101.), None);
+        assert_eq!(series.percentile(f64::NAN), None);
+        // Incrementing a 4 that is not an extremum keeps the running stats, their stddev is the
+        // same as the one of a rescan.
+        series.upsert((15, Some(1.)));
+        assert!(series.running_stats.is_valid(10));
+        series.calculate_stats();
+        let running = series.stats.stddev;
+        series.rescan_stats();
+        assert!((running - series.stats.stddev).abs() < 1e-12);
+        assert_eq!(series.percentile(50.), Some(5.));
+    }
+
+    #[test]
+    fn it_merges_the_stddev_of_the_sources() {
+        let mut chart = TimeSeriesChart::default();
+        for values in [[2., 4., 4., 4.], [5., 5., 7., 9.]] {
+            let mut source = TimeSeriesSource::default();
+            for (epoch, value) in (10..).zip(values) {
+                source.series_mut().upsert((epoch, Some(value)));
+            }
+            chart.sources.push(source);
+        }
+        chart.calculate_stats();
+        assert!((chart.sources[0].series_ref().stats.stddev - 0.75f64.sqrt()).abs() < 1e-12);
+        assert!((chart.sources[1].series_ref().stats.stddev - 2.75f64.sqrt()).abs() < 1e-12);
+        assert_eq!((chart.stats.count, chart.stats.avg), (8, 5.));
+        assert!((chart.stats.stddev - 2.).abs() < 1e-12);
+    }
+
+    #[test]
+    fn it_keeps_the_incremental_stats_as_a_full_rescan() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        let missing_policies = [
+            MissingValuesPolicy::Zero,
+            MissingValuesPolicy::One,
+            MissingValuesPolicy::First,
+            MissingValuesPolicy::Last,
+            MissingValuesPolicy::Fixed(-1.),
+            MissingValuesPolicy::Avg,
+            MissingValuesPolicy::Max,
+            MissingValuesPolicy::Min,
+        ];
+        let collision_policies = [
+            ValueCollisionPolicy::Increment,
+            ValueCollisionPolicy::Overwrite,
+            ValueCollisionPolicy::Decrement,
+            ValueCollisionPolicy::Ignore,
+        ];
+        for seed in 0..64u64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..16usize);
+            let incremental = TimeSeries {
+                missing_values_policy: missing_policies[seed as usize % 8].clone(),
+                collision_policy: collision_policies[seed as usize % 4].clone(),
+                ..TimeSeries::default()
+            };
+            let mut incremental = incremental.with_capacity(capacity);
+            let mut rescanned =
+                TimeSeries { stats_mode: StatsMode::FullRescan, ..incremental.clone() };
+            for step in 0..200 {
+                // A window left out of order or past its capacity ends the sequence, the backfills
+                // are checked on their own in it_backfills_the_epochs_before_the_window.
+                if incremental.active_items > incremental.metrics.len()
+                    || incremental.metrics.len() > incremental.metrics_capacity()
+                    || !incremental.sanity_check()
+                {
+                    break;
+                }
+                // New epochs that evict the oldest ones or discard the window, overwrites of the
+                // active epochs and backfills before them. The integer values keep the sums exact.
+                let newest = incremental.newest_epoch().unwrap_or(1000) as i64;
+                let span = capacity as i64 + 2;
+                let epoch = (newest + rng.gen_range(-span..=span)) as u64;
+                let value =
+                    if rng.gen_bool(0.2) { None } else { Some(rng.gen_range(-50..50) as f64) };
+                incremental.upsert((epoch, value));
+                rescanned.upsert((epoch, value));
+                incremental.calculate_stats();
+                rescanned.calculate_stats();
+                // The integer values keep the sums exact, the squared distances of the stddev are
+                // divided by the count and only kept close.
+                assert!(same_stats(&incremental.stats, &rescanned.stats));
+                assert_eq!(
+                    format!("{:?}", TimeSeriesStats { stddev: 0., ..incremental.stats }),
+                    format!("{:?}", TimeSeriesStats { stddev: 0., ..rescanned.stats }),
+                    "seed {} step {}: upsert of {:?} into {:?}",
+                    seed,
+                    step,
+                    (epoch, value),
+                    rescanned.as_vec()
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn it_rescans_the_stats_when_an_extremum_is_evicted() {
+        let mut series = TimeSeries::default().with_capacity(3);
+        for (epoch, value) in [(10, 9.), (11, 1.), (12, 5.)] {
+            series.upsert((epoch, Some(value)));
+        }
+        series.calculate_stats();
+        assert!(series.running_stats.is_valid(3));
+        // A counter incremented at the newest epoch keeps the running stats.
+        series.upsert((12, Some(10.)));
+        assert!(series.running_stats.is_valid(3));
+        series.calculate_stats();
+        assert_eq!((series.stats.max, series.stats.min, series.stats.sum), (15., 1., 25.));
+        // Evicting 9 does not change the extremums, evicting 1 does.
+        series.upsert((13, Some(2.)));
+        assert!(series.running_stats.is_valid(3));
+        series.upsert((14, Some(3.)));
+        assert!(!series.running_stats.is_valid(3));
+        series.calculate_stats();
+        assert_eq!((series.stats.max, series.stats.min, series.stats.count), (15., 2., 3));
+        assert!(series.running_stats.is_valid(3));
+    }
+
+    #[test]
+    fn it_upserts_a_batch_older_and_newer_than_the_window() {
+        for capacity in [6, 10, 20] {
+            let mut sequential = TimeSeries {
+                collision_policy: ValueCollisionPolicy::Increment,
+                ..TimeSeries::default()
+            }
+            .with_capacity(capacity);
+            for epoch in 100..104 {
+                sequential.upsert((epoch, Some(epoch as f64)));
+            }
+            let mut batched = sequential.clone();
+            // Unsorted, before the first epoch, colliding and after the last epoch with gaps.
+            let batch =
+                [(106, Some(1.)), (98, Some(2.)), (101, None), (97, Some(3.)), (106, Some(4.))];
+            let mut sorted = batch.to_vec();
+            sorted.sort_by_key(|(epoch, _)| *epoch);
+            let inserted: usize = sorted.iter().map(|input| sequential.upsert(*input)).sum();
+            assert_eq!(batched.upsert_batch(&batch), inserted, "capacity {}", capacity);
+            assert_eq!(batched, sequential, "capacity {}", capacity);
+            batched.calculate_stats();
+            sequential.calculate_stats();
+            assert_eq!(batched.stats, sequential.stats);
+        }
+    }
+
+    #[test]
+    fn it_merges_the_samples_of_another_series() {
+        let mut input = TimeSeries::default().with_capacity(5);
+        let mut output = TimeSeries::default().with_capacity(10);
+        for epoch in 100..104 {
+            input.upsert((epoch, Some(1.)));
+        }
+        // Older than the window of the input, overlapping it with gaps and newer than it.
+        for epoch in [95, 96, 101, 102, 105] {
+            output.upsert((epoch, Some(epoch as f64)));
+        }
+        let mut activity = input.clone();
+        assert_eq!(activity.merge_from(&output, ValueCollisionPolicy::Increment), 3);
+        assert_eq!(activity.as_vec(), vec![
+            (101, Some(102.)),
+            (102, Some(103.)),
+            (103, Some(1.)),
+            (104, None),
+            (105, Some(105.))
+        ]);
+        // The policy of the series is kept for the next upserts.
+        assert_eq!(activity.collision_policy, ValueCollisionPolicy::default());
+        let mut ignored = input.clone();
+        assert_eq!(ignored.merge_from(&output, ValueCollisionPolicy::Ignore), 3);
+        let overlap: Vec<_> = ignored.range(101, 102).copied().collect();
+        assert_eq!(overlap, vec![(101, Some(1.)), (102, Some(1.))]);
+        let empty = TimeSeries::default();
+        assert_eq!(input.merge_from(&empty, ValueCollisionPolicy::Overwrite), 0);
+    }
+
+    #[test]
+    fn it_upserts_a_batch_like_the_individual_upserts() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        for seed in 0..64u64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..16usize);
+            let mut sequential = TimeSeries::default().with_capacity(capacity);
+            let mut batched = sequential.clone();
+            for step in 0..20 {
+                // A window left out of order or past its capacity ends the sequence, the backfills
+                // are checked on their own in it_backfills_the_epochs_before_the_window.
+                if sequential.active_items > sequential.metrics.len()
+                    || sequential.metrics.len() > sequential.metrics_capacity()
+                    || !sequential.sanity_check()
+                {
+                    break;
+                }
+                let newest = sequential.newest_epoch().unwrap_or(1000) as i64;
+                let span = 2 * capacity as i64 + 2;
+                let mut batch: Vec<(u64, Option<f64>)> = (0..rng.gen_range(0..2 * capacity))
+                    .map(|_| {
+                        let epoch = (newest + rng.gen_range(-span..=span)) as u64;
+                        let value = if rng.gen_bool(0.2) {
+                            None
+                        } else {
+                            Some(rng.gen_range(-50..50) as f64)
+                        };
+                        (epoch, value)
+                    })
+                    .collect();
+                let inserted = batched.upsert_batch(&batch);
+                batch.sort_by_key(|(epoch, _)| *epoch);
+                let expected: usize = batch.iter().map(|input| sequential.upsert(*input)).sum();
+                assert_eq!(inserted, expected, "seed {} step {}: {:?}", seed, step, batch);
+                assert_eq!(batched, sequential, "seed {} step {}: {:?}", seed, step, batch);
+            }
+        }
+    }
+
+    #[test]
+    fn it_counts_the_missing_values_of_the_window() {
+        for stats_mode in [StatsMode::Incremental, StatsMode::FullRescan] {
+            let mut series = TimeSeries { stats_mode, ..TimeSeries::default() }.with_capacity(5);
+            assert_eq!(series.fill_ratio(), None);
+            // 10 and 12 are filled, 11 is a gap.
+            series.upsert((10, Some(1.)));
+            series.upsert((12, Some(2.)));
+            series.calculate_stats();
+            assert_eq!((series.stats.count, series.stats.missing_count), (2, 1));
+            assert_eq!(series.fill_ratio(), Some(2. / 3.));
+            // Filling the gap and evicting the oldest values.
+            series.upsert((11, Some(3.)));
+            series.upsert((14, None));
+            series.upsert((16, Some(4.)));
+            series.calculate_stats();
+            assert_eq!(series.as_vec().len(), 5);
+            assert_eq!((series.stats.count, series.stats.missing_count), (2, 3));
+            assert_eq!(series.fill_ratio(), Some(0.4));
+            assert_eq!(series.fill_ratio_with_padding(), Some(0.4));
+        }
+    }
+
+    #[test]
+    fn it_tells_apart_the_sync_padding_from_the_gaps() {
+        let mut lagging = TimeSeriesChart::default();
+        lagging.sources.push(TimeSeriesSource::default());
+        let series = lagging.sources[0].series_mut();
+        series.upsert((10, Some(1.)));
+        series.upsert((11, Some(2.)));
+        series.upsert((13, Some(3.)));
+        lagging.last_updated = 13;
+        let mut latest = TimeSeriesChart::default();
+        latest.sources.push(TimeSeriesSource::default());
+        latest.sources[0].series_mut().upsert((17, Some(1.)));
+        latest.last_updated = 17;
+        let mut chart_config =
+            ChartsConfig { charts: vec![lagging, latest], ..ChartsConfig::default() };
+        chart_config.sync_latest_epoch(ChartSizeInfo::default());
+        let chart = &mut chart_config.charts[0];
+        chart.calculate_stats();
+        // 12 is a gap of the source, 14 to 17 were padded to reach the latest epoch.
+        let series = chart.sources[0].series_ref();
+        assert_eq!(series.newest_epoch(), Some(17));
+        assert_eq!((series.stats.missing_count, series.sync_padding_count()), (5, 4));
+        assert_eq!(series.fill_ratio(), Some(0.75));
+        assert_eq!(series.fill_ratio_with_padding(), Some(3. / 8.));
+        assert_eq!(chart.stats.missing_count, 5);
+        assert_eq!(chart.fill_ratio(), Some(0.75));
+        chart.data_quality =
+            Some(DataQualityConfig { count_sync_padding: true, ..DataQualityConfig::default() });
+        assert_eq!(chart.fill_ratio(), Some(3. / 8.));
+        // A value loaded later for a padded epoch is no longer padding.
+        chart.sources[0].series_mut().upsert((15, Some(4.)));
+        chart.calculate_stats();
+        let series = chart.sources[0].series_ref();
+        assert_eq!((series.stats.missing_count, series.sync_padding_count()), (4, 3));
+        assert_eq!(series.fill_ratio(), Some(0.8));
+        // The chart that was already at the latest epoch is not padded.
+        assert_eq!(chart_config.charts[1].sources[0].series_ref().sync_padding_count(), 0);
+    }
+
+    #[test]
+    fn it_marks_the_charts_with_missing_data() {
+        let mut chart = TimeSeriesChart { name: String::from("load avg"), ..Default::default() };
+        chart.sources.push(TimeSeriesSource::default());
+        chart.derived.push(DerivedSeries {
+            name: String::from("delta"),
+            source: String::new(),
+            operation: DerivedOperation::Delta,
+            color: ChartColor::default(),
+            alpha: 0.8,
+            pattern: None,
+            base: Some(0),
+        });
+        // 6 of the 10 epochs between 10 and 19 are filled.
+        for epoch in [10, 11, 12, 16, 17, 19] {
+            chart.sources[0].series_mut().upsert((epoch, Some(1.)));
+        }
+        chart.calculate_stats();
+        assert_eq!(chart.fill_ratio(), Some(0.6));
+        // Without a data quality config the chart is not marked.
+        assert_eq!(chart.title(), "load avg");
+        assert_eq!(chart.series_alpha(0), Some(1.));
+        // The data quality config of the charts applies to the charts without their own.
+        let mut chart_config = ChartsConfig {
+            charts: vec![chart],
+            data_quality: Some(DataQualityConfig::default()),
+            ..ChartsConfig::default()
+        };
+        chart_config.setup_chart_spacing();
+        let chart = &mut chart_config.charts[0];
+        assert_eq!(chart.title(), "load avg ⚠ 40% gaps");
+        assert_eq!(chart.series_alpha(0), Some(DEFAULT_FAINT_ALPHA_FACTOR));
+        // The derived series are as faint as their source.
+        assert_eq!(chart.series_alpha(1), Some(0.8 * DEFAULT_FAINT_ALPHA_FACTOR));
+        chart.data_quality = Some(DataQualityConfig {
+            title_gap_ratio: Some(0.5),
+            faint_fill_ratio: Some(0.5),
+            faint_alpha_factor: Some(0.2),
+            ..DataQualityConfig::default()
+        });
+        assert_eq!(chart.title(), "load avg");
+        assert_eq!(chart.series_alpha(0), Some(1.));
+        chart.data_quality.as_mut().unwrap().faint_fill_ratio = Some(0.7);
+        assert_eq!(chart.series_alpha(0), Some(0.2));
+    }
+
     #[test]
     fn it_gets_deduped_opengl_vecs() {
         let size_test = ChartSizeInfo {
@@ -1584,7 +7022,7 @@ mod tests {
         };
         let mut all_dups = TimeSeriesChart::default();
         all_dups.sources.push(TimeSeriesSource::default());
-        all_dups.dimensions = Some(Value2D { x: 10., y: 10. });
+        all_dups.dimensions = Some(ChartLength2D::pixels(10., 10.));
         // Test with 10 items only
         // So that every item takes 0.01
         all_dups.sources[0].series_mut().metrics_capacity = 10;
@@ -1599,7 +7037,7 @@ mod tests {
         assert_eq!(all_dups.get_deduped_opengl_vecs(0).len(), 4);
         let mut no_dups = TimeSeriesChart::default();
         no_dups.sources.push(TimeSeriesSource::default());
-        no_dups.dimensions = Some(Value2D { x: 10., y: 10. });
+        no_dups.dimensions = Some(ChartLength2D::pixels(10., 10.));
         // Test with 10 items only
         // So that every item takes 0.01
         no_dups.sources[0].series_mut().metrics_capacity = 10;
@@ -1614,6 +7052,140 @@ mod tests {
         assert_eq!(no_dups.get_deduped_opengl_vecs(0).len(), 14usize);
     }
 
+    #[test]
+    fn it_keeps_the_ends_of_the_deduped_vecs() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 200., height: 200., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        for seed in 0..64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..16usize);
+            let mut chart = TimeSeriesChart {
+                dimensions: Some(ChartLength2D::pixels(100., 100.)),
+                sources: vec![TimeSeriesSource::default()],
+                ..TimeSeriesChart::default()
+            };
+            chart.sources[0].series_mut().metrics_capacity = capacity;
+            // Few distinct values so that runs of duplicates, trailing ones included, are common.
+            for epoch in 10..10 + rng.gen_range(1..2 * capacity as u64) {
+                chart.sources[0].series_mut().upsert((epoch, Some(rng.gen_range(0..3) as f64)));
+            }
+            chart.update_series_opengl_vecs(0, size);
+            let active_items = chart.sources[0].series().active_items;
+            let raw = chart.opengl_vecs[0][..active_items * 2].to_vec();
+            let deduped = chart.dedup_opengl_vecs(0);
+            assert_eq!(deduped.len() % 2, 0, "seed {}: {:?}", seed, deduped);
+            assert_eq!(deduped[..2], raw[..2], "seed {}: {:?} from {:?}", seed, deduped, raw);
+            assert_eq!(
+                deduped[deduped.len() - 2..],
+                raw[raw.len() - 2..],
+                "seed {}: {:?} from {:?}",
+                seed,
+                deduped,
+                raw
+            );
+            // A vertex buffer left over from a larger window is not drawn.
+            chart.opengl_vecs[0].extend_from_slice(&[0.5, 0.5, 0.9, 0.1]);
+            assert_eq!(chart.dedup_opengl_vecs(0), deduped, "seed {}", seed);
+        }
+    }
+
+    #[test]
+    fn it_smooths_the_drawn_lines() {
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let mut series = TimeSeries::default().with_capacity(10);
+        for (epoch, value) in [(10, 0.), (11, 8.), (12, 0.), (13, 8.)] {
+            series.upsert((epoch, Some(value)));
+        }
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            sources: vec![TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                series,
+                ..ManualTimeSeries::default()
+            })],
+            smoothing: Some(0.5),
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(chart.validate_smoothing(), Ok(()));
+        chart.update_all_series_opengl_vecs(size);
+        let values = |chart: &TimeSeriesChart| -> Vec<Option<f64>> {
+            chart.series_at(0).unwrap().as_vec().into_iter().map(|(_, value)| value).collect()
+        };
+        assert_eq!(values(&chart), vec![Some(0.), Some(4.), Some(2.), Some(5.)]);
+        // The chart is scaled to the smoothed values, the series keeps the loaded ones.
+        assert_eq!((chart.stats.min, chart.stats.max), (0., 5.));
+        assert_eq!(chart.sources[0].series_ref().stats.max, 8.);
+        let smoothed_vertices = chart.get_deduped_opengl_vecs(0);
+        chart.smoothing = None;
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.stats.max, 8.);
+        let vertices = chart.get_deduped_opengl_vecs(0);
+        assert_eq!(vertices.len(), smoothed_vertices.len());
+        assert_ne!(vertices, smoothed_vertices);
+        // A smoothing out of range draws the values as they are.
+        chart.smoothing = Some(1.5);
+        assert!(chart.validate_smoothing().is_err());
+        assert_eq!(values(&chart), vec![Some(0.), Some(8.), Some(0.), Some(8.)]);
+    }
+
+    #[test]
+    fn it_stacks_the_series() {
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let source = |samples: &[(u64, f64)]| {
+            let mut series =
+                TimeSeries::default().with_capacity(3).with_missing_values_policy("last".into());
+            for (epoch, value) in samples {
+                series.upsert((*epoch, Some(*value)));
+            }
+            TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                series,
+                ..ManualTimeSeries::default()
+            })
+        };
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            sources: vec![
+                source(&[(10, 1.), (11, 2.), (12, 3.)]),
+                // The missing value is filled with the last value, the series above do not drop.
+                source(&[(10, 1.), (12, 1.)]),
+                source(&[(10, 2.), (11, 2.), (12, 2.)]),
+            ],
+            layout: SeriesLayout::Stacked,
+            ..TimeSeriesChart::default()
+        };
+        chart.update_all_series_opengl_vecs(size);
+        let values = |chart: &TimeSeriesChart, idx| -> Vec<Option<f64>> {
+            chart.series_at(idx).unwrap().as_vec().into_iter().map(|(_, value)| value).collect()
+        };
+        assert_eq!(values(&chart, 0), vec![Some(1.), Some(2.), Some(3.)]);
+        assert_eq!(values(&chart, 1), vec![Some(2.), Some(3.), Some(4.)]);
+        assert_eq!(values(&chart, 2), vec![Some(4.), Some(5.), Some(6.)]);
+        // The chart is scaled to the top of the stack.
+        assert_eq!(chart.stats.max, 6.);
+        let ys = |idx: usize| -> Vec<f32> {
+            chart.opengl_vecs[idx].iter().skip(1).step_by(2).copied().collect()
+        };
+        for idx in 0..2 {
+            assert!(ys(idx).iter().zip(ys(idx + 1)).all(|(below, above)| *below < above));
+        }
+        assert!((0..3).all(|idx| !chart.get_deduped_opengl_vecs(idx).is_empty()));
+        // The sources keep their values and are drawn over each other without a layout.
+        assert_eq!(chart.sources[2].series_ref().stats.max, 2.);
+        chart.layout = SeriesLayout::Overlay;
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(values(&chart, 2), vec![Some(2.), Some(2.), Some(2.)]);
+        assert_eq!(chart.stats.max, 3.);
+    }
+
     #[test]
     fn it_adds_old_items() {
         init_log();
@@ -1654,6 +7226,362 @@ mod tests {
         );
     }
 
+    #[test]
+    fn it_resizes_capacity() {
+        init_log();
+        let mut test0: TimeSeries = TimeSeries::default().with_capacity(4usize);
+        for epoch in 10..16 {
+            test0.upsert((epoch, Some(epoch as f64)));
+        }
+        // The circular buffer has wrapped around.
+        assert_eq!(test0.first_idx, 2usize);
+        let wrapped = vec![(12, Some(12.)), (13, Some(13.)), (14, Some(14.)), (15, Some(15.))];
+        assert_eq!(test0.as_vec(), wrapped);
+        // Growing keeps the items in order
+        test0.resize_capacity(8usize);
+        assert_eq!(test0.metrics_capacity, 8usize);
+        assert_eq!(test0.first_idx, 0usize);
+        assert_eq!(test0.as_vec(), wrapped);
+        assert!(test0.sanity_check());
+        test0.upsert((16, Some(16.)));
+        assert_eq!(test0.active_items, 5usize);
+        assert_eq!(test0.as_vec().last(), Some(&(16, Some(16.))));
+        // Shrinking drops the oldest items
+        test0.resize_capacity(3usize);
+        assert_eq!(test0.metrics_capacity, 3usize);
+        assert_eq!(test0.as_vec(), vec![(14, Some(14.)), (15, Some(15.)), (16, Some(16.))]);
+        assert_eq!(test0.stats.first, 14.);
+        assert_eq!(test0.stats.last, 16.);
+        test0.upsert((17, Some(17.)));
+        assert_eq!(test0.as_vec(), vec![(15, Some(15.)), (16, Some(16.)), (17, Some(17.))]);
+        assert!(test0.sanity_check());
+        // A zero capacity is ignored
+        test0.resize_capacity(0usize);
+        assert_eq!(test0.metrics_capacity, 3usize);
+        // Resizing an empty series
+        let mut test1: TimeSeries = TimeSeries::default().with_capacity(4usize);
+        test1.resize_capacity(10usize);
+        assert_eq!(test1.metrics_capacity, 10usize);
+        assert_eq!(test1.upsert((10, Some(10.))), 1usize);
+        assert_eq!(test1.as_vec(), vec![(10, Some(10.))]);
+    }
+
+    #[test]
+    fn it_steps_by_the_granularity() {
+        init_log();
+        let mut test0 =
+            TimeSeries { granularity_ms: 250, ..TimeSeries::default() }.with_capacity(8);
+        assert!(test0.has_millisecond_epochs());
+        assert_eq!(test0.upsert((1000, Some(1.))), 1usize);
+        // The gaps are filled at the granularity.
+        assert_eq!(test0.upsert((1750, Some(2.))), 1usize);
+        assert_eq!(test0.as_vec(), vec![
+            (1000, Some(1.)),
+            (1250, None),
+            (1500, None),
+            (1750, Some(2.))
+        ]);
+        // The epochs are truncated to the granularity.
+        assert_eq!(test0.upsert((2010, Some(3.))), 1usize);
+        assert_eq!(test0.as_vec().last(), Some(&(2000, Some(3.))));
+        // An older epoch is backfilled at the granularity too.
+        assert_eq!(test0.upsert((500, Some(0.))), 2usize);
+        assert_eq!(test0.as_vec()[..3], [(500, Some(0.)), (750, None), (1000, Some(1.))]);
+        assert!(test0.sanity_check());
+        // The capacity is counted in samples, 8 samples span 2 seconds.
+        assert_eq!(test0.upsert((3999, Some(4.))), 1usize);
+        assert_eq!(test0.upsert((1000, Some(5.))), 0usize);
+        assert_eq!(test0.as_vec().first(), Some(&(2000, Some(3.))));
+        // A batch steps by the granularity like the upserts.
+        let mut test1 =
+            TimeSeries { granularity_ms: 500, ..TimeSeries::default() }.with_capacity(8);
+        assert_eq!(test1.upsert_batch(&[(1600, Some(2.)), (520, Some(1.))]), 2usize);
+        assert_eq!(test1.as_vec(), vec![(500, Some(1.)), (1000, None), (1500, Some(2.))]);
+        // A granularity of seconds steps by seconds.
+        let mut test2 =
+            TimeSeries { granularity_ms: 5000, ..TimeSeries::default() }.with_capacity(8);
+        assert!(!test2.has_millisecond_epochs());
+        test2.upsert((10, Some(1.)));
+        test2.upsert((21, Some(2.)));
+        assert_eq!(test2.as_vec(), vec![(10, Some(1.)), (15, None), (20, Some(2.))]);
+    }
+
+    #[test]
+    fn it_clears_a_series() {
+        init_log();
+        let mut test0: TimeSeries = TimeSeries::default().with_capacity(4usize);
+        for epoch in 10..16 {
+            test0.upsert((epoch, Some(epoch as f64)));
+        }
+        assert_eq!(test0.first_idx, 2usize);
+        test0.clear();
+        assert_eq!(test0.active_items, 0usize);
+        assert_eq!(test0.as_vec(), vec![]);
+        assert_eq!(test0.metrics_capacity(), 4usize);
+        // The next upsert starts a fresh buffer, even with an epoch older than the dropped ones.
+        assert_eq!(test0.upsert((5, Some(5.))), 1usize);
+        assert_eq!(test0.first_idx, 0usize);
+        assert_eq!(test0.active_items, 1usize);
+        assert_eq!(test0.as_vec(), vec![(5, Some(5.))]);
+        assert_eq!((test0.stats.min, test0.stats.max, test0.stats.count), (5., 5., 1));
+        assert!(test0.sanity_check());
+    }
+
+    #[test]
+    fn it_sets_the_window_of_the_chart() {
+        init_log();
+        let mut fixed = TimeSeries::default().with_capacity(4usize);
+        for epoch in 10..16 {
+            fixed.upsert((epoch, Some(epoch as f64)));
+        }
+        let adaptive =
+            TimeSeries { adaptive_capacity: true, ..TimeSeries::default() }.with_capacity(100);
+        let manual = |series| ManualTimeSeries { series, ..ManualTimeSeries::default() };
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(100., 10.)),
+            sources: vec![
+                TimeSeriesSource::AlacrittyInput(manual(fixed)),
+                TimeSeriesSource::AlacrittyOutput(manual(adaptive)),
+            ],
+            opengl_vecs: vec![vec![0f32; 8], vec![]],
+            ..TimeSeriesChart::default()
+        };
+        let size = ChartSizeInfo::default();
+        // Growing keeps the samples.
+        chart.set_window(900, size);
+        let fixed = chart.sources[0].series_ref();
+        assert_eq!(fixed.metrics_capacity(), 900);
+        assert_eq!(fixed.as_vec().first(), Some(&(12, Some(12.))));
+        assert_eq!(fixed.as_vec().len(), 4);
+        // The adaptive series follow the chart width, up to the window.
+        assert_eq!(chart.sources[1].series_ref().metrics_capacity(), 100);
+        assert_eq!(chart.sources[1].series_ref().max_capacity, 900);
+        // Shrinking drops the oldest samples and their vertices.
+        chart.set_window(2, size);
+        assert_eq!(chart.sources[0].series_ref().as_vec(), vec![(14, Some(14.)), (15, Some(15.))]);
+        assert_eq!(chart.sources[1].series_ref().metrics_capacity(), 2);
+        assert_eq!(chart.opengl_vecs[0].len(), 4);
+        assert_eq!((chart.stats.min, chart.stats.max), (14., 15.));
+    }
+
+    #[test]
+    fn it_keeps_metrics_with_capacity() {
+        init_log();
+        let mut test0: TimeSeries = TimeSeries::default().with_capacity(5usize);
+        for epoch in 10..15 {
+            test0.upsert((epoch, Some(epoch as f64)));
+        }
+        // Shrinking a populated series keeps the newest items.
+        let test1 = test0.clone().with_capacity(2usize);
+        assert_eq!(test1.metrics_capacity(), 2usize);
+        assert_eq!(test1.as_vec(), vec![(13, Some(13.)), (14, Some(14.))]);
+        // Growing a populated series keeps all the items.
+        let mut test2 = test0.clone().with_capacity(10usize);
+        assert_eq!(test2.metrics_capacity(), 10usize);
+        assert_eq!(test2.as_vec(), test0.as_vec());
+        test2.upsert((15, Some(15.)));
+        assert_eq!(test2.active_items, 6usize);
+        assert!(test2.sanity_check());
+    }
+
+    #[test]
+    fn it_guards_zero_capacity() {
+        init_log();
+        let test0: TimeSeries = TimeSeries::default().with_capacity(0usize);
+        assert_eq!(test0.metrics_capacity(), 1usize);
+        // The configuration may still set it to zero.
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            sources: vec![TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        chart.sources[0].series_mut().metrics_capacity = 0usize;
+        assert_eq!(chart.sources[0].series().metrics_capacity(), 1usize);
+        chart.sources[0].series_mut().upsert((10, Some(1.)));
+        chart.sources[0].series_mut().upsert((11, Some(2.)));
+        assert_eq!(chart.sources[0].series().as_vec(), vec![(11, Some(2.))]);
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.opengl_vecs[0].len(), 2usize);
+        assert!(chart.opengl_vecs[0].iter().all(|vertex| vertex.is_finite()));
+    }
+
+    #[test]
+    fn it_upserts_live_data_like_the_slow_path() {
+        init_log();
+        // Sequential epochs, collisions on the last epoch, gaps, older epochs and wraps.
+        let inputs = [
+            (10, Some(1.)),
+            (11, Some(2.)),
+            (11, None),
+            (11, Some(3.)),
+            (12, None),
+            (13, Some(4.)),
+            (15, Some(5.)),
+            (16, Some(6.)),
+            (14, Some(7.)),
+            (17, Some(8.)),
+            (18, Some(9.)),
+            (18, Some(1.)),
+            (40, Some(2.)),
+            (41, Some(3.)),
+        ];
+        for policy in [ValueCollisionPolicy::Overwrite, ValueCollisionPolicy::Increment] {
+            let series =
+                TimeSeries { collision_policy: policy, ..TimeSeries::default() }.with_capacity(4);
+            let (mut fast, mut slow) = (series.clone(), series);
+            for input in inputs {
+                assert_eq!(fast.upsert(input), slow.upsert_slow_path(input), "{:?}", input);
+                assert_eq!(fast, slow, "{:?}", input);
+                assert!(fast.sanity_check());
+            }
+        }
+    }
+
+    #[test]
+    #[cfg_attr(debug_assertions, ignore = "the timings are only meaningful with --release")]
+    fn it_upserts_sequential_epochs_quickly() {
+        let series = TimeSeries::default().with_capacity(300);
+        let time_upserts = |upsert: fn(&mut TimeSeries, (u64, Option<f64>)) -> usize| {
+            let mut series = series.clone();
+            let started = std::time::Instant::now();
+            for epoch in 0..1_000_000u64 {
+                upsert(&mut series, (epoch, Some(epoch as f64)));
+            }
+            assert_eq!(series.as_vec().last(), Some(&(999_999, Some(999_999.))));
+            started.elapsed()
+        };
+        let fast = time_upserts(TimeSeries::upsert);
+        let slow = time_upserts(TimeSeries::upsert_slow_path);
+        assert!(fast < slow, "1M sequential upserts: fast path {:?}, slow path {:?}", fast, slow);
+    }
+
+    #[test]
+    fn it_draws_the_live_edge_of_the_internal_counters() {
+        init_log();
+        let counter = ManualTimeSeries {
+            series: TimeSeries::default().with_capacity(10),
+            ..ManualTimeSeries::default()
+        };
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(100., 50.)),
+            sources: vec![TimeSeriesSource::AlacrittyInput(counter)],
+            live_edge: true,
+            ..TimeSeriesChart::default()
+        };
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let accumulate = |chart: &mut TimeSeriesChart, epoch, value| {
+            chart.sources[0].live_counter_mut().unwrap().accumulate(epoch, value);
+            chart.update_all_series_opengl_vecs(size);
+        };
+        for (epoch, value) in [(98, 1.), (99, 5.), (100, 2.), (101, 3.)] {
+            accumulate(&mut chart, epoch, value);
+        }
+        // The second in progress is not a sample yet, its running value is the live edge.
+        let samples = vec![(98, Some(1.)), (99, Some(5.)), (100, Some(2.))];
+        assert_eq!(chart.sources[0].series().as_vec(), samples);
+        assert!(chart.series_styles()[0].live_edge);
+        let edge = chart.get_live_edge_opengl_vecs(0);
+        let vertices = chart.get_deduped_opengl_vecs(0);
+        assert_eq!(edge.len(), 4);
+        assert_eq!(edge[..2], vertices[vertices.len() - 2..]);
+        assert!(edge[2] > edge[0]);
+        accumulate(&mut chart, 101, 1.);
+        let grown = chart.get_live_edge_opengl_vecs(0);
+        assert_eq!(grown[2], edge[2]);
+        assert!(grown[3] > edge[3]);
+        // Once the second completes its value becomes the newest sample, at the same tick.
+        assert!(!chart.commit_live_edges(101));
+        assert!(chart.commit_live_edges(102));
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.sources[0].series().as_vec()[3], (101, Some(4.)));
+        assert!(chart.get_live_edge_opengl_vecs(0).is_empty());
+        let vertices = chart.get_deduped_opengl_vecs(0);
+        assert_eq!(vertices[vertices.len() - 2], grown[2]);
+        // The increments older than the second in progress are upserted at their epoch.
+        accumulate(&mut chart, 103, 1.);
+        accumulate(&mut chart, 101, 1.);
+        assert_eq!(chart.sources[0].series().as_vec()[3], (101, Some(5.)));
+        chart.live_edge = false;
+        assert!(!chart.series_styles()[0].live_edge);
+    }
+
+    #[test]
+    fn it_clamps_collisions() {
+        init_log();
+        let decrement = TimeSeries {
+            collision_policy: ValueCollisionPolicy::Decrement,
+            ..TimeSeries::default()
+        }
+        .with_capacity(4);
+        // Without a floor, out of order events within the same second go below zero.
+        let mut unbounded = decrement.clone();
+        let mut floored = TimeSeries { collision_floor: Some(0.), ..decrement };
+        for value in [1., 1., 1., -1., 1., 1., -1.] {
+            unbounded.upsert((10, Some(value)));
+            floored.upsert((10, Some(value)));
+            assert!(floored.as_vec()[0].1.unwrap() >= 0.);
+        }
+        assert_eq!(unbounded.as_vec(), vec![(10, Some(-1.))]);
+        assert_eq!(floored.as_vec(), vec![(10, Some(1.))]);
+        // The floor applies per slot, the next epoch starts from its own value.
+        floored.upsert((11, Some(2.)));
+        floored.upsert((11, Some(5.)));
+        assert_eq!(floored.as_vec(), vec![(10, Some(1.)), (11, Some(0.))]);
+        let mut ceiled = TimeSeries {
+            collision_policy: ValueCollisionPolicy::Increment,
+            collision_ceiling: Some(3.),
+            ..TimeSeries::default()
+        }
+        .with_capacity(4);
+        for value in [2., 2., 2.] {
+            ceiled.upsert((10, Some(value)));
+        }
+        ceiled.upsert((11, Some(1.)));
+        ceiled.upsert((11, Some(1.)));
+        assert_eq!(ceiled.as_vec(), vec![(10, Some(3.)), (11, Some(2.))]);
+        // Overwrite is not bounded.
+        let mut overwrite = TimeSeries {
+            collision_policy: ValueCollisionPolicy::Overwrite,
+            collision_floor: Some(0.),
+            collision_ceiling: Some(3.),
+            ..TimeSeries::default()
+        }
+        .with_capacity(4);
+        overwrite.upsert((10, Some(1.)));
+        overwrite.upsert((10, Some(5.)));
+        assert_eq!(overwrite.as_vec(), vec![(10, Some(5.))]);
+    }
+
+    #[test]
+    fn it_calculates_capacity_for_width() {
+        assert_eq!(capacity_for_width(300.5, DEFAULT_MAX_CAPACITY), 300usize);
+        assert_eq!(capacity_for_width(5000., DEFAULT_MAX_CAPACITY), DEFAULT_MAX_CAPACITY);
+        assert_eq!(capacity_for_width(5000., 1000usize), 1000usize);
+        assert_eq!(capacity_for_width(0., DEFAULT_MAX_CAPACITY), 1usize);
+        assert_eq!(capacity_for_width(-10., DEFAULT_MAX_CAPACITY), 1usize);
+        assert_eq!(capacity_for_width(f32::NAN, DEFAULT_MAX_CAPACITY), 1usize);
+        let mut chart = TimeSeriesChart {
+            dimensions: Some(ChartLength2D::pixels(500., 100.)),
+            sources: vec![TimeSeriesSource::default(), TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        chart.sources[0].series_mut().adaptive_capacity = true;
+        chart.sources[0].series_mut().max_capacity = 400usize;
+        assert!(chart.adapt_series_capacity(ChartSizeInfo::default()));
+        assert_eq!(chart.sources[0].series().metrics_capacity, 400usize);
+        // Series without adaptive_capacity keep their configured capacity
+        assert_eq!(chart.sources[1].series().metrics_capacity, 300usize);
+        // Nothing changes when the width is the same
+        assert!(!chart.adapt_series_capacity(ChartSizeInfo::default()));
+    }
+
     #[test]
     fn it_iterates_trait() {
         // Iterator Trait
@@ -1773,15 +7701,18 @@ mod tests {
         // The value should return should be the center: 0.0
         let mid = size_test.scale_y(100f64, 50f64);
         assert!((mid - 0.0f32).abs() < f32::EPSILON);
-        // TODO: Padding_y is not being used anymore to calculate the scale_y
         size_test.term_size.padding_y = 25.;
+        // display size: 100 px, input the value: 0, padding_y: 25
+        // The value returned should be the bottom of the padded area: -0.5
+        let min = size_test.scale_y(100f64, 0f64);
+        assert!((min - -0.5f32).abs() < f32::EPSILON);
         // display size: 100 px, input the value: 50, padding_y: 25
-        // The value returned should be upper-most: 1.0
+        // The value returned should be 0.5
         // In this case, the chart (100px) is bigger than the display,
         // which means some values would have been chopped (anything above
         // 50f32)
         let mid = size_test.scale_y(100f64, 50f64);
-        assert!((mid - 0.0f32).abs() < f32::EPSILON);
+        assert!((mid - 0.5f32).abs() < f32::EPSILON);
     }
 
     fn simple_chart_setup_with_none() -> (ChartSizeInfo, TimeSeriesChart) {
@@ -1798,7 +7729,7 @@ mod tests {
         };
         let mut chart_test = TimeSeriesChart::default();
         chart_test.sources.push(TimeSeriesSource::default());
-        chart_test.dimensions = Some(Value2D { x: 10., y: 10. });
+        chart_test.dimensions = Some(ChartLength2D::pixels(10., 10.));
         // Test with 5 items only
         // So that every item takes 0.01
         chart_test.sources[0].series_mut().metrics_capacity = 10;
@@ -1847,7 +7778,7 @@ mod tests {
         // XXX: How does this behave without a reference point?
         prom_test.decorations.push(Decoration::Reference(ReferencePointDecoration::default()));
         prom_test.sources.push(TimeSeriesSource::default());
-        prom_test.dimensions = Some(Value2D { x: 12., y: 10. });
+        prom_test.dimensions = Some(ChartLength2D::pixels(12., 10.));
         prom_test.sources[0].series_mut().metrics_capacity = 24;
         let point_1_metric = 4.5f64;
         let point_2_metric = 4.25f64;
@@ -1983,65 +7914,1360 @@ mod tests {
     }
 
     #[test]
-    fn it_calculates_reference_point() {
+    fn it_calculates_reference_point() {
+        init_log();
+        let (size_test, mut chart_test) = simple_chart_setup_with_none();
+        chart_test.decorations.push(Decoration::Reference(ReferencePointDecoration::default()));
+        // Calling update_series_opengl_vecs also calls the decoration update opengl vecs
+        chart_test.update_series_opengl_vecs(0, size_test);
+        let deco_vecs = chart_test.decorations[0].opengl_vertices();
+
+        assert_eq!(chart_test.decorations[0].opengl_vertices().len(), 12);
+        // At this point we know 1 unit in the current drawn metrics is equals to
+        // 0.025
+        assert_eq!(
+            deco_vecs,
+            vec![
+                -1.0,     // Left-most
+                -0.97375, // 0.25 + 5% height multiplier, so 30% of the line
+                -1.0,     // Left-most
+                -0.97625, // Y value - 5% height multiplier, so 20% of the line
+                -1.0,     // Left-most
+                -0.975,   // Y value, so 25% of the line
+                -0.9,     // Right-most
+                -0.975,   // Y value is 1, so 25% of the line
+                -0.9,     // Right-most
+                -0.97625, // Y value is 1, so 25% of the line
+                -0.9,     // Right-most
+                -0.97375, // Y value is 1, so 25% of the line
+            ]
+        );
+        // Since we have added a Reference point, it needs some space to be
+        // drawn, its default width is 1px, turns out to be 0.9 between ticks
+        // Also there is an offset of 10 px so divided by 2 (for each side) becomes:
+        // 0.05
+        assert_eq!(
+            chart_test.opengl_vecs[0],
+            vec![
+                -0.99,       // 1st X value, leftmost.
+                -1.0,        // Y value is 0, so -1.0 is the bottom-most
+                -0.982,      // X plus 0.01
+                -0.975,      // Y value is 1, so 25% of the line, so 0.025
+                -0.97400004, // leftmost plus  0.01 * 2
+                -0.95,       // Y value is 2, so 50% from bottom to top
+                -0.96599996, // leftmost plus 0.01 * 0.3
+                -1.0,        // A none value means MissingValuesPolicy::Zero
+                -0.958,      // leftmost plus 0.01 * 4
+                -1.0,        // A none value means MissingValuesPolicy::Zero
+                -0.95,       // leftmost plus 0.01 * 5, rightmost
+                -0.9         // A bit below the max
+            ]
+        );
+        // A chart spanning -10..10 with a negative reference point
+        let mut negative_test = TimeSeriesChart::default();
+        negative_test.sources.push(TimeSeriesSource::default());
+        negative_test.dimensions = Some(ChartLength2D::pixels(10., 10.));
+        negative_test.sources[0].series_mut().metrics_capacity = 10;
+        negative_test.sources[0].series_mut().upsert((10, Some(-10f64)));
+        negative_test.sources[0].series_mut().upsert((11, Some(10f64)));
+        negative_test.decorations.push(Decoration::Reference(ReferencePointDecoration {
+            value: -5f64,
+            ..ReferencePointDecoration::default()
+        }));
+        negative_test.update_series_opengl_vecs(0, size_test);
+        assert!(negative_test.decorations[0].top_value() > -5f64);
+        assert!(negative_test.decorations[0].bottom_value() < -5f64);
+        let deco_vecs = negative_test.decorations[0].opengl_vertices();
+        // -5 is 25% of the way from -10 to 10, the chart is 10px high on a 200px display, so the
+        // line sits 2.5px above the bottom.
+        assert!((deco_vecs[5] - -0.975).abs() < f32::EPSILON);
+        assert!((deco_vecs[7] - -0.975).abs() < f32::EPSILON);
+        // The series itself uses the same scale, -10 is the bottom and 10 is the top.
+        assert!((negative_test.opengl_vecs[0][1] - -1.0).abs() < f32::EPSILON);
+        assert!((negative_test.opengl_vecs[0][3] - -0.9).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn it_clips_series_to_chart_rect() {
+        let rect = NdcRect { left: -1.0, right: 0.0, bottom: -1.0, top: 0.0 };
+        // A line going up from the bottom to 10 times the top and back down.
+        let spike = vec![-1.0, -1.0, -0.5, 9.0, 0.0, -1.0];
+        let clipped = rect.clip_polyline(&spike);
+        assert!(clipped.chunks_exact(2).all(|point| point[1] <= rect.top));
+        // The line is y = 20x + 19 on the way up and y = -20x - 1 on the way down, the top edge
+        // is crossed at x = -0.95 and x = -0.05
+        assert_eq!(clipped.len(), 8);
+        assert!((clipped[0] - -1.0).abs() < f32::EPSILON);
+        assert!((clipped[1] - -1.0).abs() < f32::EPSILON);
+        assert!((clipped[2] - -0.95).abs() < 1e-6);
+        assert!((clipped[3] - 0.0).abs() < 1e-6);
+        assert!((clipped[4] - -0.05).abs() < 1e-6);
+        assert!((clipped[5] - 0.0).abs() < 1e-6);
+        assert!((clipped[6] - 0.0).abs() < f32::EPSILON);
+        assert!((clipped[7] - -1.0).abs() < f32::EPSILON);
+        // Lines inside the rect are untouched.
+        let inside = vec![-1.0, -1.0, -0.5, -0.5, 0.0, -1.0];
+        assert_eq!(rect.clip_polyline(&inside), inside);
+        // Lines completely outside are dropped.
+        assert!(rect.clip_polyline(&[0.5, 0.5, 0.75, 0.5]).is_empty());
+        // A line leaving through the top and coming back through the bottom is joined by a
+        // segment across the rect, the path outside of it is not followed.
+        let through = vec![-0.5, -0.5, 0.5, 1.0, 0.5, -2.0, -0.5, -0.5];
+        let clipped = rect.clip_polyline(&through);
+        assert_eq!(clipped.len(), 8);
+        // It leaves at (-1/6, 0) and comes back at (-1/6, -1).
+        assert!((clipped[2] - -1. / 6.).abs() < 1e-6);
+        assert!((clipped[3] - rect.top).abs() < 1e-6);
+        assert!((clipped[4] - -1. / 6.).abs() < 1e-6);
+        assert!((clipped[5] - rect.bottom).abs() < 1e-6);
+    }
+
+    #[test]
+    fn it_draws_the_bottom_sample_at_the_padded_boundary() {
+        let (mut size_test, mut chart_test) = simple_chart_setup_with_none();
+        size_test.term_size.padding_y = 20.;
+        chart_test.update_series_opengl_vecs(0, size_test);
+        let y_values: Vec<f32> =
+            chart_test.opengl_vecs[0].iter().skip(1).step_by(2).copied().collect();
+        let lowest = y_values.iter().copied().fold(f32::MAX, f32::min);
+        let highest = y_values.iter().copied().fold(f32::MIN, f32::max);
+        // Display height: 200px, the padded boundary is 20px above the window edge at -1.0
+        assert!((lowest - -0.8f32).abs() < 1e-6);
+        // The top-most value is the chart height above the padded boundary.
+        assert!((highest - -0.7f32).abs() < 1e-6);
+        // The inverse maps the samples back to their values.
+        size_test.chart_height = 10.;
+        assert!(size_test.ndc_y_to_value(lowest, &chart_test.stats).abs() < 1e-5);
+        assert!((size_test.ndc_y_to_value(highest, &chart_test.stats) - 4.).abs() < 1e-5);
+    }
+
+    #[test]
+    fn it_aligns_the_sources_by_the_capacity_policy() {
+        init_log();
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 1000., height: 800., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let source = |name: &str, capacity: u64| {
+            let mut series = TimeSeries::default().with_capacity(capacity as usize);
+            for epoch in 1000..1000 + capacity {
+                series.upsert((epoch, Some(1.)));
+            }
+            TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                name: name.to_string(),
+                series,
+                ..ManualTimeSeries::default()
+            })
+        };
+        let mut chart = TimeSeriesChart {
+            name: String::from("load"),
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(200., 50.)),
+            sources: vec![source("short", 10), source("long", 20)],
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(chart.capacity_range(), Some((10, 20)));
+        let x_values = |chart: &TimeSeriesChart, idx: usize| -> Vec<f32> {
+            chart.opengl_vecs[idx].iter().step_by(2).copied().collect()
+        };
+        // AlignToMax: 10px per tick, the short source starts 10 ticks to the right.
+        chart.update_all_series_opengl_vecs(size);
+        let short = x_values(&chart, 0);
+        let long = x_values(&chart, 1);
+        assert_eq!(short.len(), 10);
+        assert!((short[0] - (100. / 500. - 1.)).abs() < 1e-6);
+        assert!((long[0] - -1.).abs() < 1e-6);
+        // Both sources end at the same tick.
+        assert!((short[9] - (190. / 500. - 1.)).abs() < 1e-6);
+        assert!((long[19] - short[9]).abs() < 1e-6);
+        // The deduped vertices keep the offset.
+        assert!((chart.get_deduped_opengl_vecs(0)[0] - short[0]).abs() < 1e-6);
+        assert_eq!(chart.validate_capacities(), Ok(()));
+        // Independent: each source is spread over the chart by its own capacity.
+        chart.capacity_policy = CapacityPolicy::Independent;
+        chart.update_all_series_opengl_vecs(size);
+        let short = x_values(&chart, 0);
+        assert!((short[0] - -1.).abs() < 1e-6);
+        assert!((short[9] - (180. / 500. - 1.)).abs() < 1e-6);
+        assert!((x_values(&chart, 1)[19] - (190. / 500. - 1.)).abs() < 1e-6);
+        assert_eq!(chart.validate_capacities(), Ok(()));
+        // Strict: the mismatch is reported and the sources are drawn aligned.
+        chart.capacity_policy = CapacityPolicy::Strict;
+        chart.update_all_series_opengl_vecs(size);
+        assert!((x_values(&chart, 0)[0] - (100. / 500. - 1.)).abs() < 1e-6);
+        assert_eq!(
+            chart.validate_capacities(),
+            Err(String::from(
+                "Chart 'load' has sources with different capacities (short: 10, long: 20), the \
+                 capacity_policy is Strict"
+            ))
+        );
+        chart.sources[0] = source("short", 20);
+        assert_eq!(chart.validate_capacities(), Ok(()));
+    }
+
+    #[test]
+    fn it_calculates_chart_pixel_rect() {
+        init_log();
+        let size = ChartSizeInfo {
+            term_size: SizeInfo {
+                width: 1000.,
+                height: 800.,
+                padding_x: 5.,
+                padding_y: 5.,
+                ..SizeInfo::default()
+            },
+            ..ChartSizeInfo::default()
+        };
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(100., 0.)),
+            dimensions: Some(ChartLength2D::pixels(200., 50.)),
+            sources: vec![TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        // The chart has not been sized yet.
+        assert_eq!(chart.scissor_rect(), None);
+        chart.update_all_series_opengl_vecs(size);
+        let rect = chart.scissor_rect().unwrap();
+        // Top-left origin, the chart sits at the bottom of the padded area.
+        assert_eq!(rect, PixelRect { x: 105., y: 745., width: 200., height: 50. });
+        // In GL window coordinates the origin is the bottom left.
+        assert_eq!(rect.to_gl_window(800.), (105, 5, 200, 50));
+        // The pixel rect matches the normalized device coordinates used to clip the series.
+        assert!((chart.ndc_rect.left - (105. / 500. - 1.)).abs() < f32::EPSILON);
+        assert!((chart.ndc_rect.right - (305. / 500. - 1.)).abs() < f32::EPSILON);
+        assert!((chart.ndc_rect.bottom - (5. / 400. - 1.)).abs() < f32::EPSILON);
+        assert!((chart.ndc_rect.top - (55. / 400. - 1.)).abs() < f32::EPSILON);
+        // A rect away from the bottom is flipped, partially covered pixels are included.
+        let rect = PixelRect { x: 10.5, y: 20.25, width: 5., height: 10. };
+        assert_eq!(rect.to_gl_window(100.), (10, 69, 6, 11));
+        // Charts that allow overflow are not scissored.
+        chart.overflow = true;
+        assert_eq!(chart.scissor_rect(), None);
+    }
+
+    #[test]
+    fn it_scales_layout_by_dpr() {
+        init_log();
+        let size = ChartSizeInfo {
+            term_size: SizeInfo {
+                width: 1000.,
+                height: 800.,
+                padding_x: 5.,
+                padding_y: 5.,
+                dpr: 1.,
+                ..SizeInfo::default()
+            },
+            ..ChartSizeInfo::default()
+        };
+        // The same window on a monitor with twice the pixel density.
+        let hidpi_size = ChartSizeInfo {
+            term_size: SizeInfo {
+                width: 2000.,
+                height: 1600.,
+                padding_x: 10.,
+                padding_y: 10.,
+                dpr: 2.,
+                ..SizeInfo::default()
+            },
+            ..ChartSizeInfo::default()
+        };
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(100., 0.)),
+            dimensions: Some(ChartLength2D::pixels(200., 50.)),
+            sources: vec![TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        chart.sources[0].series_mut().upsert((10, Some(1.)));
+        chart.sources[0].series_mut().upsert((11, Some(2.)));
+        chart.update_all_series_opengl_vecs(size);
+        let (rect, ndc_rect) = (chart.pixel_rect, chart.ndc_rect);
+        let vertices = chart.opengl_vecs[0].clone();
+        chart.update_all_series_opengl_vecs(hidpi_size);
+        assert_eq!(rect, PixelRect { x: 105., y: 745., width: 200., height: 50. });
+        assert_eq!(chart.pixel_rect, PixelRect { x: 210., y: 1490., width: 400., height: 100. });
+        // In normalized device coordinates the chart covers the same area of the window.
+        assert!((chart.ndc_rect.left - ndc_rect.left).abs() < 1e-5);
+        assert!((chart.ndc_rect.right - ndc_rect.right).abs() < 1e-5);
+        assert!((chart.ndc_rect.bottom - ndc_rect.bottom).abs() < 1e-5);
+        assert!((chart.ndc_rect.top - ndc_rect.top).abs() < 1e-5);
+        assert!(!vertices.is_empty());
+        assert_eq!(chart.opengl_vecs[0].len(), vertices.len());
+        for (hidpi, regular) in chart.opengl_vecs[0].iter().zip(vertices.iter()) {
+            assert!((hidpi - regular).abs() < 1e-5);
+        }
+        // Adaptive series hold one sample per physical pixel.
+        assert!((chart.drawable_width(hidpi_size) - 2. * chart.drawable_width(size)).abs() < 1e-5);
+        // An unset ratio behaves as 1.0
+        assert!((SizeInfo::default().scale_factor() - 1.).abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn it_scales_log10_charts() {
+        init_log();
+        let log10 = YScaleType::Log10 { min_clamp: 1. };
+        let term_size = SizeInfo { width: 100., height: 100., ..SizeInfo::default() };
+        let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            sources: vec![TimeSeriesSource::default()],
+            y_scale_type: log10,
+            ..TimeSeriesChart::default()
+        };
+        chart.decorations.push(Decoration::Reference(ReferencePointDecoration {
+            value: 100.,
+            ..ReferencePointDecoration::default()
+        }));
+        {
+            let series = chart.sources[0].series_mut();
+            *series = series.clone().with_capacity(4);
+            for (epoch, value) in [(10, 1.), (11, 10.), (12, 100.), (13, 1000.)] {
+                series.upsert((epoch, Some(value)));
+            }
+        }
+        chart.update_all_series_opengl_vecs(size);
+        // Every decade is the same distance apart.
+        let y_values: Vec<f32> = chart.opengl_vecs[0].iter().skip(1).step_by(2).copied().collect();
+        assert_eq!(y_values.len(), 4);
+        let spacing = y_values[1] - y_values[0];
+        assert!(spacing > 0.);
+        assert!((y_values[2] - y_values[1] - spacing).abs() < 1e-5);
+        assert!((y_values[3] - y_values[2] - spacing).abs() < 1e-5);
+        assert!((y_values[0] - -1.).abs() < 1e-5);
+        // The ticks are placed at the decades, the reference line lands on the third one.
+        let chart_size =
+            ChartSizeInfo { chart_width: 100., chart_height: 100., y_scale: log10, ..size };
+        let ticks = chart_size.y_axis_ticks(&chart.stats);
+        assert_eq!(
+            ticks.iter().map(|tick| tick.0).collect::<Vec<f64>>(),
+            vec![1., 10., 100., 1000.]
+        );
+        if let Decoration::Reference(reference) = &chart.decorations[0] {
+            assert!((reference.opengl_data[5] - ticks[2].1).abs() < 1e-5);
+            assert!((reference.opengl_data[5] - y_values[2]).abs() < 1e-5);
+        }
+        // The inverse mapping returns the value under a Y coordinate.
+        assert!((chart_size.ndc_y_to_value(y_values[2], &chart.stats) - 100.).abs() < 1e-2);
+        // Zero and negative values are drawn at min_clamp and counted.
+        assert_eq!(chart.clamped_samples, vec![0]);
+        chart.sources[0].series_mut().upsert((14, Some(0.)));
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.clamped_samples, vec![1]);
+        assert!((chart.opengl_vecs[0][7] - -1.).abs() < 1e-5);
+        // Linear charts are untouched.
+        chart.y_scale_type = YScaleType::Linear;
+        chart.update_all_series_opengl_vecs(size);
+        let linear_size = ChartSizeInfo { y_scale: YScaleType::Linear, ..chart_size };
+        for (idx, value) in [10., 100., 1000., 0.].iter().enumerate() {
+            let expected = linear_size.scale_y_range(0., chart.stats.max, *value);
+            assert!((chart.opengl_vecs[0][idx * 2 + 1] - expected).abs() < 1e-5);
+        }
+        assert_eq!(chart.clamped_samples, vec![0]);
+        let ndc_y = linear_size.value_to_ndc_y(100., &chart.stats);
+        assert!((linear_size.ndc_y_to_value(ndc_y, &chart.stats) - 100.).abs() < 1e-2);
+    }
+
+    #[test]
+    fn it_selects_nice_numbers() {
+        for (value, expected) in [
+            (0., 0.),
+            (1., 1.),
+            (1.2, 2.),
+            (2., 2.),
+            (2.5, 5.),
+            (4.83, 5.),
+            (7., 10.),
+            (0.034, 0.05),
+            (120., 200.),
+            (-3., -5.),
+        ] {
+            assert!((nice_number(value) - expected).abs() < 1e-9, "{} -> {}", value, expected);
+        }
+        // The bottom is only expanded for negative values.
+        assert_eq!(nice_bounds(0., 4.83), (0., 5.));
+        assert_eq!(nice_bounds(-1.5, 4.83), (-2., 5.));
+    }
+
+    #[test]
+    fn it_keeps_nice_bounds_with_hysteresis() {
+        init_log();
+        let mut chart = TimeSeriesChart { nice_scale: true, ..TimeSeriesChart::default() };
+        let mut bounds_for_max = |max: f64| {
+            chart.stats.min = 0.;
+            chart.stats.max = max;
+            chart.update_nice_bounds();
+            chart.effective_y_bounds()
+        };
+        assert_eq!(bounds_for_max(4.83), (0., 5.));
+        // Minor fluctuations keep the scale.
+        assert_eq!(bounds_for_max(4.9), (0., 5.));
+        assert_eq!(bounds_for_max(3.5), (0., 5.));
+        // Leaving the nice range expands it.
+        assert_eq!(bounds_for_max(5.2), (0., 10.));
+        assert_eq!(bounds_for_max(6.5), (0., 10.));
+        // Shrinking under the hysteresis fraction reduces it.
+        assert_eq!(bounds_for_max(2.8), (0., 5.));
+        // The vertices and the inverse mapping use the nice bounds.
+        let term_size = SizeInfo { width: 100., height: 100., ..SizeInfo::default() };
+        let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+        chart.position = Some(ChartLength2D::pixels(0., 0.));
+        chart.dimensions = Some(ChartLength2D::pixels(100., 100.));
+        chart.sources = vec![TimeSeriesSource::default()];
+        chart.sources[0].series_mut().upsert((10, Some(1.)));
+        chart.sources[0].series_mut().upsert((11, Some(4.)));
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.effective_y_bounds(), (0., 5.));
+        let chart_size = ChartSizeInfo {
+            chart_width: 100.,
+            chart_height: 100.,
+            y_bounds: chart.nice_bounds,
+            ..size
+        };
+        assert!((chart.opengl_vecs[0][3] - chart_size.scale_y_range(0., 5., 4.)).abs() < 1e-5);
+        let value = chart_size.ndc_y_to_value(chart.opengl_vecs[0][3], &chart.stats);
+        assert!((value - 4.).abs() < 1e-2);
+        let ticks = chart_size.y_axis_ticks(&chart.stats);
+        assert_eq!(ticks.iter().map(|tick| tick.0).collect::<Vec<f64>>(), vec![0., 5.]);
+        // Log10 charts keep their decades, nice_scale is ignored.
+        chart.y_scale_type = YScaleType::Log10 { min_clamp: 1. };
+        chart.calculate_stats();
+        assert_eq!(chart.nice_bounds, None);
+        assert_eq!(chart.effective_y_bounds(), (1., 4.));
+    }
+
+    #[test]
+    fn it_scales_negative_values() {
+        init_log();
+        let term_size = SizeInfo { width: 100., height: 100., ..SizeInfo::default() };
+        let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+        let draw = |values: &[f64], baseline: Option<f64>| {
+            let mut chart = TimeSeriesChart {
+                position: Some(ChartLength2D::pixels(0., 0.)),
+                dimensions: Some(ChartLength2D::pixels(100., 100.)),
+                sources: vec![TimeSeriesSource::default()],
+                baseline,
+                ..TimeSeriesChart::default()
+            };
+            for (epoch, value) in values.iter().enumerate() {
+                chart.sources[0].series_mut().upsert((10 + epoch as u64, Some(*value)));
+            }
+            chart.update_all_series_opengl_vecs(size);
+            let ys: Vec<f32> = chart.opengl_vecs[0].iter().skip(1).step_by(2).copied().collect();
+            let chart_size = ChartSizeInfo {
+                chart_width: 100.,
+                chart_height: 100.,
+                y_bounds: chart.pinned_y_bounds(),
+                ..size
+            };
+            (chart.effective_y_bounds(), ys, chart_size.zero_ndc_y(&chart.stats))
+        };
+        let assert_near = |actual: &[f32], expected: &[f32]| {
+            assert_eq!(actual.len(), expected.len());
+            for (actual, expected) in actual.iter().zip(expected) {
+                assert!((actual - expected).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+            }
+        };
+        // The values below zero are drawn within the chart, zero is out of its bounds.
+        let (bounds, ys, zero) = draw(&[-4., -2., -1.], None);
+        assert_eq!(bounds, (-4., -1.));
+        assert_near(&ys, &[-1., 1. / 3., 1.]);
+        assert_eq!(zero, None);
+        // A mixed series is scaled from its min to its max, zero is in between.
+        let (bounds, ys, zero) = draw(&[-2., 0., 2.], None);
+        assert_eq!(bounds, (-2., 2.));
+        assert_near(&ys, &[-1., 0., 1.]);
+        assert_near(&[zero.unwrap()], &[0.]);
+        // A series without a range is drawn at the bottom.
+        let (bounds, ys, zero) = draw(&[-3., -3.], None);
+        assert_eq!(bounds, (-3., -3.));
+        assert_near(&ys, &[-1., -1.]);
+        assert_eq!(zero, None);
+        // The baseline keeps zero at its fraction of the height.
+        let (bounds, ys, zero) = draw(&[-1., 4.], Some(0.5));
+        assert_eq!(bounds, (-4., 4.));
+        assert_near(&ys, &[-0.25, 1.]);
+        assert_near(&[zero.unwrap()], &[0.]);
+        let (bounds, ys, zero) = draw(&[0., 0.], Some(0.25));
+        assert_eq!(bounds, (-0.25, 0.75));
+        assert_near(&ys, &[-0.5, -0.5]);
+        assert_near(&[zero.unwrap()], &[-0.5]);
+        assert_eq!(baseline_bounds(0., -1., 4.), (0., 4.));
+        assert_eq!(baseline_bounds(1., -1., 4.), (-1., 0.));
+    }
+
+    #[test]
+    fn it_fixes_the_y_bounds() {
+        init_log();
+        let term_size = SizeInfo { width: 100., height: 100., ..SizeInfo::default() };
+        let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+        let draw = |values: &[f64], y_min: Option<f64>, y_max: Option<f64>| {
+            let mut chart = TimeSeriesChart {
+                position: Some(ChartLength2D::pixels(0., 0.)),
+                dimensions: Some(ChartLength2D::pixels(100., 100.)),
+                sources: vec![TimeSeriesSource::default()],
+                y_min,
+                y_max,
+                ..TimeSeriesChart::default()
+            };
+            for (epoch, value) in values.iter().enumerate() {
+                chart.sources[0].series_mut().upsert((10 + epoch as u64, Some(*value)));
+            }
+            chart.update_all_series_opengl_vecs(size);
+            chart
+        };
+        // The values out of the bounds are clamped to the edges of the chart.
+        let chart = draw(&[20., 150., -10.], Some(0.), Some(100.));
+        assert_eq!(chart.validate_y_bounds(), Ok(()));
+        assert_eq!(chart.effective_y_bounds(), (0., 100.));
+        let ys: Vec<f32> = chart.opengl_vecs[0].iter().skip(1).step_by(2).copied().collect();
+        assert_eq!(ys.len(), 3);
+        assert!((ys[0] + 0.6).abs() < 1e-5);
+        assert!((ys[1] - 1.).abs() < 1e-5);
+        assert!((ys[2] + 1.).abs() < 1e-5);
+        assert_eq!(chart.clamped_samples, vec![2]);
+        // A new max does not move the fixed side of the axis, the other side follows the values.
+        assert_eq!(draw(&[20., 50.], None, Some(100.)).effective_y_bounds(), (0., 100.));
+        assert_eq!(draw(&[20., 50.], Some(10.), None).effective_y_bounds(), (10., 50.));
+        // A y_min that is not under the y_max is reported and ignored.
+        let chart = draw(&[20., 50.], Some(5.), Some(5.));
+        assert!(chart.validate_y_bounds().is_err());
+        assert!(!chart.has_fixed_y_bounds());
+        assert_eq!(chart.effective_y_bounds(), (0., 50.));
+        // The configs without bounds follow the values.
+        let yaml = r#"
+charts:
+- name: cpu
+  series: []
+  y_min: 0
+  y_max: 100
+- name: load
+  series: []
+"#;
+        let chart_config: ChartsConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(chart_config.charts[0].y_min, Some(0.));
+        assert_eq!(chart_config.charts[0].y_max, Some(100.));
+        assert!(!chart_config.charts[1].has_fixed_y_bounds());
+    }
+
+    #[test]
+    fn it_renders_chart_links() {
+        init_log();
+        let prom = prometheus::PrometheusTimeSeries {
+            source: String::from(
+                "http://localhost:9090/api/v1/query_range?query=node_load1%7Bjob%3D%22node%22%7D&step=15",
+            ),
+            pull_interval: 15,
+            series: TimeSeries::default().with_capacity(4),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let mut chart = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+            ..TimeSeriesChart::default()
+        };
+        // Charts without a link are not clickable.
+        assert_eq!(chart.render_link(1000), None);
+        chart.link =
+            Some(String::from("http://prom:9090/graph?g0.expr={query}&start={start}&end={end}"));
+        // Before any sample arrives the window ends now and spans capacity * pull_interval.
+        assert_eq!(chart.visible_epoch_window(1000), Some((940, 1000)));
+        assert_eq!(
+            chart.render_link(1000),
+            Some(String::from(
+                "http://prom:9090/graph?g0.expr=node_load1%7Bjob%3D%22node%22%7D&start=940&end=1000"
+            ))
+        );
+        // Afterwards the window ends at the newest sample.
+        chart.sources[0].series_mut().upsert((900, Some(1.)));
+        chart.sources[0].series_mut().upsert((915, Some(2.)));
+        assert_eq!(chart.visible_epoch_window(1000), Some((855, 915)));
+        // Charts without prometheus sources have no epoch window.
+        let manual = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::default()],
+            link: Some(String::from("http://example.com")),
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(manual.visible_epoch_window(1000), None);
+        assert_eq!(manual.render_link(1000), Some(String::from("http://example.com")));
+    }
+
+    #[test]
+    fn it_discovers_series_from_label_values() {
+        init_log();
+        let discover = prometheus::DiscoveryConfig {
+            label: String::from("mountpoint"),
+            matcher: String::from("node_filesystem_avail_bytes"),
+            query_template: String::from(
+                "http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes{mountpoint=\"{value}\"}",
+            ),
+            interval: 60,
+            grace_period: 300,
+            max_series: 2,
+        };
+        let prom = prometheus::PrometheusTimeSeries {
+            name: String::from("avail"),
+            source: String::from(
+                "http://localhost:9090/api/v1/query?query=sum(node_filesystem_avail_bytes)",
+            ),
+            color: Rgb { r: 1, g: 2, b: 3 }.into(),
+            discover: Some(discover),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let mut chart = TimeSeriesChart {
+            name: String::from("disk"),
+            sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+            ..TimeSeriesChart::default()
+        };
+        let values = |values: &[&str]| values.iter().map(|x| x.to_string()).collect::<Vec<_>>();
+        // Appearance: each label value adds a series.
+        let changes = chart.apply_discovered_values(0, &values(&["/", "/boot"]), 1000);
+        assert_eq!(changes, DiscoveryChanges { started: vec![1, 2], retired: vec![] });
+        assert_eq!(chart.sources.len(), 3);
+        assert_eq!(chart.sources[2].name(), "avail /boot");
+        assert_eq!(chart.sources[2].color(), Rgb { r: 1, g: 2, b: 3 });
+        match &chart.sources[2] {
+            TimeSeriesSource::PrometheusTimeSeries(prom) => {
+                assert!(prom.source.contains(r#"{mountpoint="/boot"}"#));
+                assert_eq!(prom.required_labels.get("mountpoint"), Some(&String::from("/boot")));
+            },
+            _ => unreachable!(),
+        }
+        // Persistence: known values, or values missing for less than the grace period, do not
+        // change the series.
+        let changes = chart.apply_discovered_values(0, &values(&["/", "/boot"]), 1060);
+        assert_eq!(changes, DiscoveryChanges::default());
+        let changes = chart.apply_discovered_values(0, &values(&["/"]), 1300);
+        assert_eq!(changes, DiscoveryChanges::default());
+        assert_eq!(chart.sources.len(), 3);
+        // Retirement: /boot has been missing for longer than the grace period.
+        let changes = chart.apply_discovered_values(0, &values(&["/"]), 1400);
+        assert_eq!(changes, DiscoveryChanges { started: vec![], retired: vec![2] });
+        // The retired series has no samples left, so its slot is taken by a new value.
+        let changes = chart.apply_discovered_values(0, &values(&["/", "/home", "/var"]), 1460);
+        assert_eq!(changes, DiscoveryChanges { started: vec![2], retired: vec![] });
+        assert_eq!(chart.sources.len(), 3);
+        assert_eq!(chart.sources[2].name(), "avail /home");
+        // Sources without discovery are ignored.
+        chart.sources.push(TimeSeriesSource::default());
+        assert_eq!(chart.apply_discovered_values(3, &values(&["/"]), 1500), Default::default());
+    }
+
+    #[test]
+    fn it_shows_a_placeholder_until_data_arrives() {
+        init_log();
+        let prom = prometheus::PrometheusTimeSeries {
+            source: String::from("http://localhost:9090/api/v1/query?query=up"),
+            color: Rgb { r: 1, g: 2, b: 3 }.into(),
+            ..prometheus::PrometheusTimeSeries::default()
+        };
+        let mut chart = TimeSeriesChart {
+            name: String::from("up"),
+            sources: vec![TimeSeriesSource::PrometheusTimeSeries(Box::new(prom))],
+            pixel_rect: PixelRect { x: 10., y: 20., width: 100., height: 25. },
+            ..TimeSeriesChart::default()
+        };
+        assert!(chart.awaiting_data());
+        let placeholder = chart.placeholder(0).unwrap();
+        assert_eq!(placeholder.rect, chart.pixel_rect);
+        assert_eq!(placeholder.color, Rgb { r: 1, g: 2, b: 3 });
+        // The pulse rises and falls across the decoration ticks.
+        let alphas: Vec<f32> =
+            (0..=PLACEHOLDER_PULSE_TICKS).map(ChartPlaceholder::pulse_alpha).collect();
+        let half = (PLACEHOLDER_PULSE_TICKS / 2) as usize;
+        assert!(alphas[..=half].windows(2).all(|pair| pair[0] < pair[1]));
+        assert!(alphas[half..].windows(2).all(|pair| pair[0] > pair[1]));
+        assert_eq!(alphas[0], alphas[PLACEHOLDER_PULSE_TICKS as usize]);
+        assert!(alphas.iter().all(|alpha| (0.05..=0.25).contains(alpha)));
+        // An internal counter is loaded from the start.
+        chart.sources.push(TimeSeriesSource::AlacrittyInput(ManualTimeSeries::default()));
+        assert!(!chart.awaiting_data());
+        assert_eq!(chart.placeholder(0), None);
+    }
+
+    #[test]
+    fn it_fits_the_decorations_space_in_the_chart() {
+        // The decorations may take up to the max fraction of the chart width.
+        assert_eq!(fit_decorations_space(100., 20., 0.5), Some(20.));
+        assert_eq!(fit_decorations_space(100., 80., 0.5), Some(50.));
+        assert_eq!(fit_decorations_space(100., 80., 0.25), Some(25.));
+        assert_eq!(fit_decorations_space(100., -5., 0.5), Some(0.));
+        // At least MIN_DRAWABLE_WIDTH is left for the series.
+        assert_eq!(fit_decorations_space(3., 4., 1.), Some(2.));
+        assert_eq!(fit_decorations_space(1., 4., 1.), Some(0.));
+        assert_eq!(fit_decorations_space(3., 4., f32::NAN), Some(1.5));
+        // The charts narrower than MIN_DRAWABLE_WIDTH are too small to draw.
+        assert_eq!(fit_decorations_space(0.5, 0., 0.5), None);
+        assert_eq!(fit_decorations_space(-3., 0., 0.5), None);
+        assert_eq!(fit_decorations_space(f32::NAN, 0., 0.5), None);
+    }
+
+    #[test]
+    fn it_draws_narrow_charts_left_to_right() {
+        let (size_test, mut chart_test) = simple_chart_setup_with_none();
+        // Two reference points of 2px on a 3px chart.
+        chart_test.dimensions = Some(ChartLength2D::pixels(3., 10.));
+        for _ in 0..2 {
+            chart_test.decorations.push(Decoration::Reference(ReferencePointDecoration {
+                padding: Value2D { x: 1., y: 0. },
+                ..ReferencePointDecoration::default()
+            }));
+        }
+        assert!(chart_test.validate_decorations_space().is_err());
+        chart_test.update_series_opengl_vecs(0, size_test);
+        assert!(chart_test.decorations_scaled);
+        assert_eq!(chart_test.drawable_width(size_test), 1.5);
+        let x_values: Vec<f32> = chart_test.opengl_vecs[0].iter().step_by(2).copied().collect();
+        assert!(!x_values.is_empty());
+        assert!(x_values.windows(2).all(|pair| pair[0] < pair[1]));
+        let (left, right) = (chart_test.ndc_rect.left, chart_test.ndc_rect.right);
+        assert!(x_values.iter().all(|x| (left..=right).contains(x)));
+        // A chart narrower than a pixel is not drawn, the placeholder is shown instead.
+        chart_test.dimensions = Some(ChartLength2D::pixels(0.5, 10.));
+        assert!(chart_test.validate_decorations_space().is_err());
+        chart_test.update_series_opengl_vecs(0, size_test);
+        assert!(chart_test.too_narrow);
+        assert!(chart_test.opengl_vecs.iter().all(Vec::is_empty));
+        assert!(chart_test.placeholder(0).is_some());
+        // The decorations fit in a wide chart.
+        chart_test.dimensions = Some(ChartLength2D::pixels(10., 10.));
+        assert_eq!(chart_test.validate_decorations_space(), Ok(()));
+        chart_test.update_series_opengl_vecs(0, size_test);
+        assert!(!chart_test.too_narrow && !chart_test.decorations_scaled);
+        assert!(!chart_test.opengl_vecs[0].is_empty());
+        assert_eq!(chart_test.placeholder(0), None);
+        // The widths in cells are not validated.
+        chart_test.dimensions = Some(ChartLength2D::cells(0.1, 1.));
+        assert_eq!(chart_test.validate_decorations_space(), Ok(()));
+    }
+
+    #[test]
+    fn it_draws_numeric_readouts() {
+        assert_eq!(Trend::between(1., 2., DEFAULT_TREND_EPSILON), Trend::Up);
+        assert_eq!(Trend::between(2., 1., DEFAULT_TREND_EPSILON), Trend::Down);
+        assert_eq!(Trend::between(1., 1.0005, DEFAULT_TREND_EPSILON), Trend::Flat);
+        assert_eq!(Trend::between(1., 1.5, 1.), Trend::Flat);
+        assert_eq!(format_readout_value(4.), "4");
+        assert_eq!(format_readout_value(1234.567), "1234.57");
+        assert_eq!(format_readout_value(-0.001), "-1.00e-3");
+        assert_eq!(format_readout_value(12345678.), "1.23e7");
+        let (size_test, mut chart_test) = simple_chart_setup_with_none();
+        assert_eq!(chart_test.numeric_readout(), None);
+        // Numeric charts do not generate vertices, only their area is calculated.
+        chart_test.chart_style = ChartStyle::Numeric;
+        chart_test.update_all_series_opengl_vecs(size_test);
+        assert!(chart_test.opengl_vecs.is_empty());
+        assert!(!chart_test.pixel_rect.is_empty());
+        let readout = chart_test.numeric_readout().unwrap();
+        assert_eq!(readout.rect, chart_test.pixel_rect);
+        assert_eq!(readout.value, "4");
+        assert_eq!(readout.trend, Trend::Up);
+        assert_eq!(readout.color, chart_test.sources[0].color());
+        assert_eq!(readout.sparkline, "▁▃▅█");
+        // The first triggered alert of the series colors the readout.
+        let alert = |target: &str, threshold: f64, color: Rgb| {
+            Decoration::Alert(ActiveAlertUnderLineDecoration {
+                target: target.to_string(),
+                threshold,
+                comparator: AlertComparator::GreaterThan,
+                color: color.into(),
+                ..ActiveAlertUnderLineDecoration::default()
+            })
+        };
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let green = Rgb { r: 0, g: 255, b: 0 };
+        chart_test.decorations.push(alert("", 10., green));
+        chart_test.decorations.push(alert("other", 3., green));
+        assert_eq!(chart_test.numeric_readout().unwrap().color, chart_test.sources[0].color());
+        chart_test.decorations.push(alert(&chart_test.sources[0].name(), 3., red));
+        assert_eq!(chart_test.numeric_readout().unwrap().color, red);
+        // The trend of differences within the epsilon is flat.
+        chart_test.trend_epsilon = Some(2.);
+        assert_eq!(chart_test.numeric_readout().unwrap().trend, Trend::Flat);
+        chart_test.update_all_series_opengl_vecs(size_test);
+        assert!(chart_test.opengl_vecs.is_empty());
+    }
+
+    #[test]
+    fn it_substitutes_the_palette_per_mode() {
+        let red = Rgb { r: 255, g: 0, b: 0 };
+        let source = |color| {
+            TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                color,
+                ..ManualTimeSeries::default()
+            })
+        };
+        let modes = [
+            PaletteMode::Default,
+            PaletteMode::HighContrast,
+            PaletteMode::Deuteranopia,
+            PaletteMode::Protanopia,
+            PaletteMode::Tritanopia,
+        ];
+        for mode in modes {
+            let chart = TimeSeriesChart {
+                sources: vec![source(Rgb::default()), source(red), source(Rgb::default())],
+                ..TimeSeriesChart::default()
+            };
+            let mut chart_config =
+                ChartsConfig { charts: vec![chart], palette_mode: mode, ..ChartsConfig::default() };
+            chart_config.setup_chart_spacing();
+            let colors = chart_config.charts[0].series_colors();
+            // The configured colors always win.
+            assert_eq!(colors[1], red);
+            match mode.palette() {
+                None => assert_eq!(colors, vec![Rgb::default(), red, Rgb::default()]),
+                Some(palette) => {
+                    assert_eq!(colors[0], palette[0]);
+                    assert_eq!(colors[2], palette[2]);
+                },
+            }
+        }
+        assert_eq!(
+            PaletteMode::Deuteranopia.palette().unwrap()[0],
+            Rgb { r: 0xe6, g: 0x9f, b: 0x00 }
+        );
+        // The palette wraps around for charts with more series than colors.
+        let palette = PaletteMode::Protanopia.palette().unwrap();
+        assert_eq!(PaletteMode::Protanopia.series_color(Rgb::default(), palette.len()), palette[0]);
+        let mode: PaletteMode = serde_yaml::from_str("Tritanopia").unwrap();
+        assert_eq!(mode, PaletteMode::Tritanopia);
+        let pattern: LinePattern = serde_yaml::from_str("dashed").unwrap();
+        assert_eq!(pattern, LinePattern::Dashed);
+    }
+
+    #[test]
+    fn it_segments_dashed_and_dotted_lines() {
+        // A line across a chart 120 pixels wide, with a vertex in the middle.
+        let strip = [-1f32, 0., 0., 0., 1., 0.];
+        let pixels_per_ndc = (60f32, 60f32);
+        assert_eq!(LinePattern::Solid.segment_vertices(&strip, pixels_per_ndc), strip.to_vec());
+        // A dash and its gap take 10 pixels, the dashes continue across the vertex.
+        let dashed = LinePattern::Dashed.segment_vertices(&strip, pixels_per_ndc);
+        assert_eq!(dashed.len() / 4, 12);
+        assert!((dashed[2] - dashed[0] - 0.1).abs() < 1e-6);
+        assert!((dashed[4] - dashed[0] - 10. / 60.).abs() < 1e-6);
+        // A dot and its gap take 6 pixels.
+        let dotted = LinePattern::Dotted.segment_vertices(&strip, pixels_per_ndc);
+        assert_eq!(dotted.len() / 4, 20);
+        // A vertical line is measured with the vertical pixels.
+        let vertical = LinePattern::Dashed.segment_vertices(&[0., -1., 0., 1.], (60., 30.));
+        assert_eq!(vertical.len() / 4, 6);
+        assert_eq!(LinePattern::Dashed.draw_mode(), draw_batch::DrawMode::Lines);
+        assert_eq!(LinePattern::Solid.draw_mode(), draw_batch::DrawMode::LineStrip);
+    }
+
+    #[test]
+    fn it_changes_the_pattern_of_alerted_series() {
+        let (size_test, mut chart_test) = simple_chart_setup_with_none();
+        chart_test.update_all_series_opengl_vecs(size_test);
+        chart_test.decorations.push(Decoration::Alert(ActiveAlertUnderLineDecoration {
+            threshold: 3.,
+            ..ActiveAlertUnderLineDecoration::default()
+        }));
+        // In Default mode the alert only changes the color.
+        assert_eq!(chart_test.series_pattern(0), LinePattern::Solid);
+        chart_test.palette_mode = PaletteMode::Deuteranopia;
+        assert_eq!(chart_test.series_pattern(0), LinePattern::Dotted);
+        assert_eq!(chart_test.series_styles()[0].pattern, LinePattern::Dotted);
+        let dotted = chart_test.get_styled_opengl_vecs(0);
+        assert!(!dotted.is_empty());
+        assert_eq!(dotted.len() % 4, 0);
+        // A series that is already dotted is dashed while alerted.
+        if let TimeSeriesSource::AlacrittyInput(source) = &mut chart_test.sources[0] {
+            source.pattern = Some(LinePattern::Dotted);
+        }
+        assert_eq!(chart_test.series_pattern(0), LinePattern::Dashed);
+        chart_test.decorations.clear();
+        assert_eq!(chart_test.series_pattern(0), LinePattern::Dotted);
+    }
+
+    #[test]
+    fn it_expands_smooth_lines() {
+        // A line 100 pixels wide with a vertex in the middle, the edges are 1.5 pixels away.
+        let strip = [0f32, 0., 0.5, 0., 1., 0.];
+        let pixels_per_ndc = (100f32, 100f32);
+        let smooth = smooth_strip_vertices(&strip, pixels_per_ndc);
+        assert_eq!(smooth.len() / 3, 4 * 3 - 1);
+        // The strip goes along the left edge and comes back along the right edge.
+        let expected = [
+            (0., 0.015, 0.),
+            (0., 0., 1.),
+            (0.5, 0.015, 0.),
+            (0.5, 0., 1.),
+            (1., 0.015, 0.),
+            (1., 0., 1.),
+            (1., -0.015, 0.),
+            (0.5, 0., 1.),
+            (0.5, -0.015, 0.),
+            (0., 0., 1.),
+            (0., -0.015, 0.),
+        ];
+        for (vertex, (x, y, fade)) in smooth.chunks_exact(3).zip(expected.iter()) {
+            assert!((vertex[0] - x).abs() < 1e-6 && (vertex[1] - y).abs() < 1e-6);
+            assert_eq!(vertex[2], *fade);
+        }
+        // The left, center and right vertices of each point fade 0, 1, 0 across the strip.
+        let fades: Vec<f32> = smooth.chunks_exact(3).map(|vertex| vertex[2]).collect();
+        assert_eq!(&fades[4..7], &[0., 1., 0.]);
+        // A corner is mitered, its edges are further from the point than half of the width.
+        let corner = smooth_strip_vertices(&[0., 0., 1., 0., 1., 1.], pixels_per_ndc);
+        assert!((corner[6] - 0.985).abs() < 1e-6 && (corner[7] - 0.015).abs() < 1e-6);
+        assert!((corner[24] - 1.015).abs() < 1e-6 && (corner[25] + 0.015).abs() < 1e-6);
+        // Nothing is drawn for a single point.
+        assert!(smooth_strip_vertices(&strip[..2], pixels_per_ndc).is_empty());
+        // The strips of the dashes are joined by two vertices.
+        let dashes = [strip[..4].to_vec(), strip[2..].to_vec()];
+        let joined =
+            join_strips(dashes.iter().map(|dash| smooth_strip_vertices(dash, pixels_per_ndc)));
+        assert_eq!(joined.len() / 3, 7 + 2 + 7);
+        assert_eq!(&joined[18..21], &joined[21..24]);
+    }
+
+    #[test]
+    fn it_downsamples_the_smooth_lines() {
+        let strip: Vec<f32> = (0..100).flat_map(|x| [x as f32, ((x * 7) % 13) as f32]).collect();
+        assert_eq!(downsample_polyline(&strip, 100), strip);
+        assert_eq!(downsample_polyline(&strip, 3), strip);
+        let downsampled = downsample_polyline(&strip, 10);
+        assert_eq!(downsampled.len() / 2, 10);
+        // The ends are kept and the vertices stay in order.
+        assert_eq!(&downsampled[..2], &strip[..2]);
+        assert_eq!(&downsampled[18..], &strip[198..]);
+        let xs: Vec<f32> = downsampled.iter().step_by(2).copied().collect();
+        assert!(xs.windows(2).all(|x| x[0] < x[1]));
+        // The peaks survive.
+        let highest = downsampled.chunks_exact(2).map(|point| point[1]).fold(0f32, f32::max);
+        assert_eq!(highest, 12.);
+        // The chart draws its series within two vertices per pixel column.
+        let (size_test, mut chart_test) = simple_chart_setup_with_none();
+        chart_test.update_all_series_opengl_vecs(size_test);
+        let points = chart_test.get_deduped_opengl_vecs(0).len() / 2;
+        assert!(points > 1 && points <= chart_test.pixel_rect.width as usize * 2);
+        chart_test.smooth = true;
+        let smooth = chart_test.get_styled_opengl_vecs(0);
+        assert_eq!(smooth.len(), (4 * points - 1) * 3);
+        let style = chart_test.series_styles()[0];
+        assert_eq!(style.draw_mode(), draw_batch::DrawMode::TriangleStrip);
+    }
+
+    #[test]
+    fn it_animates_the_updates_of_a_rotated_window() {
+        init_log();
+        let size_test = ChartSizeInfo {
+            term_size: SizeInfo { height: 200., width: 200., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let mut chart_test = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::AlacrittyInput(ManualTimeSeries::default())],
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            animate_updates: Some(AnimationConfig {
+                duration_ms: Some(2 * animation::DECORATION_TICK_MS),
+                easing: animation::Easing::Linear,
+            }),
+            ..TimeSeriesChart::default()
+        };
+        let series = chart_test.sources[0].series_mut();
+        series.metrics_capacity = 3;
+        for (epoch, value) in [(10, 1f64), (11, 2f64), (12, 3f64)] {
+            series.upsert((epoch, Some(value)));
+        }
+        chart_test.update_all_series_opengl_vecs(size_test);
+        // The first vertices are drawn at once.
+        assert_eq!(chart_test.animated_opengl_vecs(0), None);
+        let before = chart_test.opengl_vecs[0].clone();
+        // The window rotates, 10 is dropped and 13 is appended.
+        chart_test.sources[0].series_mut().upsert((13, Some(4f64)));
+        chart_test.update_all_series_opengl_vecs(size_test);
+        let after = chart_test.opengl_vecs[0][..6].to_vec();
+        // t=0: The samples 11 and 12 are drawn where they were, 13 is snapped in place.
+        let from = [before[2], before[3], before[4], before[5], after[4], after[5]];
+        assert_eq!(chart_test.animated_opengl_vecs(0), Some(from.to_vec()));
+        // t=0.5: The shared samples are halfway to their new position.
+        assert!(chart_test.advance_animations());
+        let halfway: Vec<f32> =
+            from.iter().zip(&after).map(|(from, to)| from + (to - from) / 2.).collect();
+        assert_eq!(chart_test.animated_opengl_vecs(0), Some(halfway));
+        // t=1: The transition settles on the new vertices.
+        assert!(chart_test.advance_animations());
+        assert_eq!(chart_test.animated_opengl_vecs(0), None);
+        assert!(!chart_test.advance_animations());
+        // A resize draws the new vertices at once.
+        chart_test.sources[0].series_mut().upsert((14, Some(5f64)));
+        let resized = ChartSizeInfo {
+            term_size: SizeInfo { height: 300., width: 300., ..SizeInfo::default() },
+            ..size_test
+        };
+        chart_test.update_all_series_opengl_vecs(resized);
+        assert_eq!(chart_test.animated_opengl_vecs(0), None);
+    }
+
+    /// `timeline_chart_setup` returns a 100px square chart of 10 samples in a 200px window, with
+    /// an up/down status source rendered as a timeline
+    fn timeline_chart_setup() -> (ChartSizeInfo, TimeSeriesChart) {
+        init_log();
+        let size_test = ChartSizeInfo {
+            term_size: SizeInfo { height: 200., width: 200., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let yaml = r#"
+name: status
+render_as: Timeline
+"#;
+        let status: ManualTimeSeries = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(status.render_as, RenderAs::Timeline);
+        let mut chart_test = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::AlacrittyInput(status)],
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            ..TimeSeriesChart::default()
+        };
+        let series = chart_test.sources[0].series_mut();
+        series.metrics_capacity = 10;
+        series.upsert((10, Some(1f64)));
+        series.upsert((11, Some(1f64)));
+        series.upsert((12, Some(0f64)));
+        // The epoch 13 is a gap.
+        series.upsert((14, Some(1f64)));
+        series.upsert((15, Some(1f64)));
+        (size_test, chart_test)
+    }
+
+    #[test]
+    fn it_draws_timeline_runs_as_quads() {
+        let (size_test, mut chart_test) = timeline_chart_setup();
+        assert_eq!(timeline::timeline_runs(&[Some(1.), Some(1.), None, None]), vec![0..2, 2..4]);
+        chart_test.update_all_series_opengl_vecs(size_test);
+        let vertices = chart_test.get_styled_opengl_vecs(0);
+        let quad_floats = timeline::VERTICES_PER_RUN * timeline::TIMELINE_FLOATS_PER_VERTEX;
+        assert_eq!(vertices.len(), 4 * quad_floats);
+        let timeline = TimelineConfig::default();
+        let (up, _) = timeline.state_of(Some(1.), Rgb::default());
+        let (down, _) = timeline.state_of(Some(0.), Rgb::default());
+        let expected = [
+            (-1f32, -0.8f32, up),
+            (-0.8, -0.7, down),
+            (-0.7, -0.6, timeline::DEFAULT_TIMELINE_MISSING_COLOR),
+            (-0.6, -0.4, up),
+        ];
+        for (quad, (left, right, color)) in vertices.chunks_exact(quad_floats).zip(expected) {
+            let xs: Vec<f32> =
+                quad.chunks_exact(timeline::TIMELINE_FLOATS_PER_VERTEX).map(|v| v[0]).collect();
+            let ys: Vec<f32> =
+                quad.chunks_exact(timeline::TIMELINE_FLOATS_PER_VERTEX).map(|v| v[1]).collect();
+            assert!((xs.iter().copied().fold(f32::MAX, f32::min) - left).abs() < 1e-6);
+            assert!((xs.iter().copied().fold(f32::MIN, f32::max) - right).abs() < 1e-6);
+            // The band takes the default fifth of the chart height from its bottom.
+            assert!((ys.iter().copied().fold(f32::MAX, f32::min) - -1.).abs() < 1e-6);
+            assert!((ys.iter().copied().fold(f32::MIN, f32::max) - -0.8).abs() < 1e-6);
+            assert_eq!(&quad[2..6], &color.with_alpha(1.));
+        }
+        let style = chart_test.series_styles()[0];
+        assert!(style.timeline);
+        assert_eq!(style.draw_mode(), draw_batch::DrawMode::Triangles);
+        // The readout under the mouse names the state and how long it lasted.
+        let readout = chart_test.timeline_readout(25., 190.).unwrap();
+        assert_eq!(readout.text, "status: down for 1s");
+        assert_eq!(readout.color, down);
+        assert_eq!(chart_test.timeline_readout(5., 190.).unwrap().text, "status: up for 2s");
+        assert_eq!(chart_test.timeline_readout(25., 170.), None);
+        assert_eq!(timefmt::format_duration_secs(3725), "1h2m");
+    }
+
+    #[test]
+    fn it_draws_bars_and_points() {
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let source = |render_as: RenderAs, values: &[f64]| {
+            let mut series = TimeSeries::default().with_capacity(4);
+            for (epoch, value) in values.iter().enumerate() {
+                series.upsert((10 + epoch as u64, Some(*value)));
+            }
+            TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                series,
+                render_as,
+                ..ManualTimeSeries::default()
+            })
+        };
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            sources: vec![
+                source(RenderAs::Bars, &[1., 1., 4.]),
+                source(RenderAs::Points, &[2., 2.]),
+            ],
+            decorations: vec![Decoration::Reference(ReferencePointDecoration::default())],
+            ..TimeSeriesChart::default()
+        };
+        chart.update_all_series_opengl_vecs(size);
+        // Each sample is a bar from zero, at the X of the line, after the decorations space.
+        let bars = chart.get_styled_opengl_vecs(0);
+        assert_eq!(bars.len(), 3 * 4);
+        for (bar, vertex) in bars.chunks_exact(4).zip(chart.opengl_vecs[0].chunks_exact(2)) {
+            assert_eq!(bar, [vertex[0], -1., vertex[0], vertex[1]]);
+        }
+        assert!(bars[0] > -1.);
+        // The samples with the same value are not deduped.
+        assert_eq!(chart.get_styled_opengl_vecs(1), chart.opengl_vecs[1][..4].to_vec());
+        let styles = chart.series_styles();
+        assert_eq!(styles[0].draw_mode(), draw_batch::DrawMode::Lines);
+        assert_eq!(styles[1].draw_mode(), draw_batch::DrawMode::Points);
+        // The bars of negative values hang from zero.
+        chart.sources.truncate(1);
+        chart.sources[0].series_mut().upsert((13, Some(-4.)));
+        chart.update_all_series_opengl_vecs(size);
+        let bars = chart.get_styled_opengl_vecs(0);
+        let base = size.term_size.scale_y(50.);
+        assert!(bars.chunks_exact(4).all(|bar| (bar[1] - base).abs() < 1e-5));
+        assert!(bars[bars.len() - 1] < base);
+    }
+
+    #[test]
+    fn it_scales_lines_without_the_timeline_sources() {
+        let (size_test, mut chart_test) = timeline_chart_setup();
+        // A value without a state would be the top of the chart if it was scaled with the line.
+        chart_test.sources[0].series_mut().upsert((16, Some(8f64)));
+        let mut load = ManualTimeSeries::default();
+        load.series.metrics_capacity = 10;
+        for (epoch, value) in [(14, 2f64), (15, 4f64), (16, 3f64)] {
+            load.series.upsert((epoch, Some(value)));
+        }
+        chart_test.sources.push(TimeSeriesSource::AlacrittyOutput(load));
+        chart_test.update_all_series_opengl_vecs(size_test);
+        assert_eq!((chart_test.stats.min, chart_test.stats.max), (2., 4.));
+        assert_eq!(chart_test.stats.last_epoch, 16);
+        // The line reaches the top of the 100px chart, the middle of the window.
+        let line = chart_test.get_styled_opengl_vecs(1);
+        let highest = line.iter().skip(1).step_by(2).copied().fold(f32::MIN, f32::max);
+        assert!(highest.abs() < 1e-6);
+        // The unmapped value keeps the color of the series.
+        let band = chart_test.get_styled_opengl_vecs(0);
+        let newest = &band[band.len() - timeline::TIMELINE_FLOATS_PER_VERTEX..];
+        let color = chart_test.series_color(0).unwrap();
+        assert_eq!(&newest[2..6], &color.with_alpha(1.));
+    }
+
+    #[test]
+    fn it_converts_epochs_relative_to_the_time_origin() {
+        init_log();
+        // A second cannot be told apart from the next one as an f32 at this magnitude.
+        let base = (1u64 << 31) - 150;
+        assert_eq!((base + 149) as f32, (base + 150) as f32);
+        let size_test = ChartSizeInfo {
+            term_size: SizeInfo { height: 200., width: 300., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        let mut chart_test = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::default()],
+            dimensions: Some(ChartLength2D::pixels(300., 100.)),
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(chart_test.x_to_epoch(10.), None);
+        let series = chart_test.sources[0].series_mut();
+        series.metrics_capacity = 300;
+        for epoch in base..base + 300 {
+            series.upsert((epoch, Some(1f64)));
+        }
+        chart_test.update_all_series_opengl_vecs(size_test);
+        assert_eq!(chart_test.time_origin, base);
+        // The epochs are drawn on the ticks of their samples, and the pixels map back to them.
+        let vertices = &chart_test.opengl_vecs[0];
+        let vertex_x = |idx: usize| (vertices[idx * 2] + 1.) * 150.;
+        for epoch in base..base + 300 {
+            let x = chart_test.epoch_to_x(epoch);
+            assert!((x - vertex_x((epoch - base) as usize)).abs() < 1e-3);
+            assert_eq!(chart_test.x_to_epoch(x), Some(epoch));
+        }
+        // Two adjacent seconds around 2^31 are a pixel apart.
+        let (first, second) = (1u64 << 31, (1u64 << 31) + 1);
+        assert!((chart_test.epoch_to_x(second) - chart_test.epoch_to_x(first) - 1.).abs() < 1e-6);
+        assert_eq!(chart_test.x_to_epoch(chart_test.epoch_to_x(second)), Some(second));
+        // The epochs before the time origin are left of the first tick.
+        assert_eq!(chart_test.epoch_to_x(base - 10), -10.);
+        assert_eq!(chart_test.x_to_epoch(-10.), Some(base - 10));
+    }
+
+    #[test]
+    fn it_finds_chart_links_at_pixel() {
         init_log();
-        let (size_test, mut chart_test) = simple_chart_setup_with_none();
-        chart_test.decorations.push(Decoration::Reference(ReferencePointDecoration::default()));
-        // Calling update_series_opengl_vecs also calls the decoration update opengl vecs
-        chart_test.update_series_opengl_vecs(0, size_test);
-        let deco_vecs = chart_test.decorations[0].opengl_vertices();
+        let size = SizeInfo { width: 1000., height: 800., padding_x: 5., ..SizeInfo::default() };
+        let chart_config = ChartsConfig {
+            position: Some(ChartLength2D::pixels(100., 0.)),
+            default_dimensions: Some(ChartLength2D::pixels(200., 50.)),
+            spacing: ChartLength::pixels(10.),
+            charts: vec![
+                TimeSeriesChart { name: String::from("plain"), ..TimeSeriesChart::default() },
+                TimeSeriesChart {
+                    name: String::from("linked"),
+                    link: Some(String::from("http://example.com")),
+                    ..TimeSeriesChart::default()
+                },
+            ],
+            ..ChartsConfig::default()
+        };
+        // The linked chart is placed after the first one, x from 315 to 515, y from 750 to 800.
+        let link = Some(String::from("http://example.com"));
+        assert_eq!(chart_config.link_at_pixel(400., 775., size), link);
+        assert_eq!(chart_config.link_at_pixel(315., 750., size), link);
+        // Misses outside the chart rects, or on charts without a link.
+        assert_eq!(chart_config.link_at_pixel(200., 775., size), None);
+        assert_eq!(chart_config.link_at_pixel(400., 700., size), None);
+        assert_eq!(chart_config.link_at_pixel(600., 775., size), None);
+        // The layout follows the device pixel ratio.
+        let size = SizeInfo { dpr: 2., ..size };
+        assert_eq!(chart_config.link_at_pixel(400., 775., size), None);
+        assert_eq!(chart_config.link_at_pixel(700., 750., size), link);
+    }
 
-        assert_eq!(chart_test.decorations[0].opengl_vertices().len(), 12);
-        // At this point we know 1 unit in the current drawn metrics is equals to
-        // 0.025
-        assert_eq!(
-            deco_vecs,
-            vec![
-                -1.0,     // Left-most
-                -0.97375, // 0.25 + 5% height multiplier, so 30% of the line
-                -1.0,     // Left-most
-                -0.97625, // Y value - 5% height multiplier, so 20% of the line
-                -1.0,     // Left-most
-                -0.975,   // Y value, so 25% of the line
-                -0.9,     // Right-most
-                -0.975,   // Y value is 1, so 25% of the line
-                -0.9,     // Right-most
-                -0.97625, // Y value is 1, so 25% of the line
-                -0.9,     // Right-most
-                -0.97375, // Y value is 1, so 25% of the line
-            ]
-        );
-        // Since we have added a Reference point, it needs some space to be
-        // drawn, its default width is 1px, turns out to be 0.9 between ticks
-        // Also there is an offset of 10 px so divided by 2 (for each side) becomes:
-        // 0.05
-        assert_eq!(
-            chart_test.opengl_vecs[0],
-            vec![
-                -0.99,       // 1st X value, leftmost.
-                -1.0,        // Y value is 0, so -1.0 is the bottom-most
-                -0.982,      // X plus 0.01
-                -0.975,      // Y value is 1, so 25% of the line, so 0.025
-                -0.97400004, // leftmost plus  0.01 * 2
-                -0.95,       // Y value is 2, so 50% from bottom to top
-                -0.96599996, // leftmost plus 0.01 * 0.3
-                -1.0,        // A none value means MissingValuesPolicy::Zero
-                -0.958,      // leftmost plus 0.01 * 4
-                -1.0,        // A none value means MissingValuesPolicy::Zero
-                -0.95,       // leftmost plus 0.01 * 5, rightmost
-                -0.9         // A bit below the max
-            ]
-        );
+    #[test]
+    fn it_fits_the_charts_in_the_window() {
+        init_log();
+        let chart = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        // Three charts of 100x50 are placed at x 0, 100 and 200.
+        let layout = |overflow: ChartOverflow| {
+            let mut chart_config = ChartsConfig {
+                position: Some(ChartLength2D::pixels(0., 0.)),
+                default_dimensions: Some(ChartLength2D::pixels(100., 50.)),
+                charts: vec![chart.clone(), chart.clone(), chart.clone()],
+                overflow,
+                ..ChartsConfig::default()
+            };
+            chart_config.setup_chart_spacing();
+            chart_config
+        };
+        let resize = |chart_config: &mut ChartsConfig, width: f32| -> Vec<PixelRect> {
+            let term_size = SizeInfo { width, height: 100., ..SizeInfo::default() };
+            let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+            chart_config
+                .charts
+                .iter_mut()
+                .map(|chart| {
+                    chart.update_all_series_opengl_vecs(size);
+                    chart.pixel_rect
+                })
+                .collect()
+        };
+        let rect =
+            |x: f32, width: f32, height: f32| PixelRect { x, y: 100. - height, width, height };
+        let configured = vec![rect(0., 100., 50.), rect(100., 100., 50.), rect(200., 100., 50.)];
+        // The last chart is moved inward.
+        let mut shift = layout(ChartOverflow::Shift);
+        let shifted = vec![rect(0., 100., 50.), rect(100., 100., 50.), rect(150., 100., 50.)];
+        assert_eq!(resize(&mut shift, 250.), shifted);
+        // Charts bigger than the window are also shrunk.
+        assert_eq!(resize(&mut shift, 80.), vec![rect(0., 80., 40.); 3]);
+        // The last chart is shrunk to end at the edge of the window.
+        let mut shrink = layout(ChartOverflow::Shrink);
+        let shrunk = vec![rect(0., 100., 50.), rect(100., 100., 50.), rect(200., 50., 25.)];
+        assert_eq!(resize(&mut shrink, 250.), shrunk);
+        // The last chart is not drawn.
+        let mut hide = layout(ChartOverflow::Hide);
+        let hidden = vec![rect(0., 100., 50.), rect(100., 100., 50.), PixelRect::default()];
+        assert_eq!(resize(&mut hide, 250.), hidden);
+        assert!(hide.charts[2].hidden);
+        assert!(!hide.charts[1].hidden);
+        assert!(hide.charts[2].get_deduped_opengl_vecs(0).is_empty());
+        // The configured layout is kept and restored when the window grows.
+        for chart_config in [&mut shift, &mut shrink, &mut hide] {
+            assert_eq!(chart_config.charts[2].position, Some(ChartLength2D::pixels(200., 0.)));
+            assert_eq!(chart_config.charts[2].dimensions, Some(ChartLength2D::pixels(100., 50.)));
+            assert_eq!(resize(chart_config, 400.), configured);
+            assert!(!chart_config.charts[2].hidden);
+        }
+    }
+
+    #[test]
+    fn it_splits_a_chart_into_small_multiples() {
+        init_log();
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(200., 100.)),
+            small_multiples: Some(SmallMultiplesConfig { columns: 2, ..Default::default() }),
+            ..TimeSeriesChart::default()
+        };
+        // Every series has a constant value, from 1 to 4.
+        for value in 1..=4 {
+            let mut source = TimeSeriesSource::default();
+            source.series_mut().metrics_capacity = 4;
+            for epoch in 10..14 {
+                source.series_mut().upsert((epoch, Some(value as f64)));
+            }
+            chart.sources.push(source);
+        }
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 400., height: 200., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
+        };
+        // The height of the samples of each series relative to its cell.
+        let fractions = |chart: &TimeSeriesChart| -> Vec<f32> {
+            (0..chart.sources.len())
+                .map(|idx| {
+                    let cell = chart.cells[idx].ndc_rect;
+                    let vecs = chart.get_deduped_opengl_vecs(idx);
+                    assert!(!vecs.is_empty());
+                    for vertex in vecs.chunks_exact(2) {
+                        assert!(vertex[0] >= cell.left - 1e-6 && vertex[0] <= cell.right + 1e-6);
+                        assert!(vertex[1] >= cell.bottom - 1e-6 && vertex[1] <= cell.top + 1e-6);
+                    }
+                    (vecs[1] - cell.bottom) / (cell.top - cell.bottom)
+                })
+                .collect()
+        };
+        chart.update_all_series_opengl_vecs(size);
+        // The 2x2 grid fills the chart from its top left corner.
+        let cell = |x: f32, y: f32| PixelRect { x, y, width: 100., height: 50. };
+        let rects: Vec<PixelRect> = chart.cells.iter().map(|cell| cell.rect).collect();
+        let grid = vec![cell(0., 100.), cell(100., 100.), cell(0., 150.), cell(100., 150.)];
+        assert_eq!(rects, grid);
+        assert_eq!(chart.cell_scissor_rects(), grid);
+        // Each cell uses the stats of its series, every series reaches the top of its cell.
+        for fraction in fractions(&chart) {
+            assert!((fraction - 1.).abs() < 1e-4);
+        }
+        // The shared scale uses the merged max of the chart in every cell.
+        chart.small_multiples =
+            Some(SmallMultiplesConfig { columns: 2, shared_y_scale: true, ..Default::default() });
+        chart.update_all_series_opengl_vecs(size);
+        for (idx, fraction) in fractions(&chart).into_iter().enumerate() {
+            assert!((fraction - (idx + 1) as f32 / 4.).abs() < 1e-4);
+        }
+        // Pixels map to the series of the cell under them.
+        assert_eq!(chart.series_at_pixel(150., 120.), Some(SeriesIndex(1)));
+        assert_eq!(chart.series_at_pixel(50., 180.), Some(SeriesIndex(2)));
+        assert_eq!(chart.series_at_pixel(300., 180.), None);
     }
 
     #[test]
     fn it_spaces_chart_config_dimensions_and_position() {
         init_log();
         let mut chart_config = ChartsConfig {
-            default_dimensions: Some(Value2D { x: 25., y: 100. }),
-            position: Some(Value2D { x: 200., y: 0. }),
+            default_dimensions: Some(ChartLength2D::pixels(25., 100.)),
+            position: Some(ChartLength2D::pixels(200., 0.)),
             charts: vec![],
-            spacing: 0f32,
+            spacing: ChartLength::pixels(0.),
+            resize_debounce_ms: None,
+            compact_decimals: None,
+            startup_retries: None,
+            startup_retry_delay_ms: None,
+            fetch_concurrency: None,
+            overflow: ChartOverflow::Shift,
+            memory_budget_mb: None,
+            palette_mode: PaletteMode::Default,
+            clock_jump_threshold_s: None,
+            debug_overlay_corner: debug_overlay::OverlayCorner::TopLeft,
+            window_presets: None,
+            absence_summary_s: None,
+            data_watchdog: None,
+            load_governor: None,
+            journal: None,
+            persistence: None,
+            data_quality: None,
+            timezone: timefmt::ChartTimezone::Local,
+            decoration_rates: vec![],
+            version: None,
         };
         let (_size_test, mut chart_test) = simple_chart_setup_with_none();
         chart_test.position = None;
@@ -2054,42 +9280,423 @@ mod tests {
         chart_config.charts.push(chart_test);
         chart_config.setup_chart_spacing();
         assert_eq!(chart_config.charts[0].dimensions, chart_config.default_dimensions);
-        assert_eq!(chart_config.charts[0].position, Some(Value2D { x: 200., y: 0. }));
-        assert_eq!(chart_config.charts[1].position, Some(Value2D { x: 225., y: 0. }));
-        assert_eq!(chart_config.charts[2].position, Some(Value2D { x: 250., y: 0. }));
-        assert_eq!(chart_config.charts[3].position, Some(Value2D { x: 275., y: 0. }));
-        assert_eq!(chart_config.charts[4].position, Some(Value2D { x: 300., y: 0. }));
-        assert_eq!(chart_config.charts[5].position, Some(Value2D { x: 325., y: 0. }));
+        assert_eq!(chart_config.charts[0].position, Some(ChartLength2D::pixels(200., 0.)));
+        assert_eq!(chart_config.charts[1].position, Some(ChartLength2D::pixels(225., 0.)));
+        assert_eq!(chart_config.charts[2].position, Some(ChartLength2D::pixels(250., 0.)));
+        assert_eq!(chart_config.charts[3].position, Some(ChartLength2D::pixels(275., 0.)));
+        assert_eq!(chart_config.charts[4].position, Some(ChartLength2D::pixels(300., 0.)));
+        assert_eq!(chart_config.charts[5].position, Some(ChartLength2D::pixels(325., 0.)));
         assert_eq!(chart_config.charts[5].dimensions, chart_config.default_dimensions);
     }
 
     #[test]
-    fn it_does_sanity_check() {
-        let bad = TimeSeries {
-            metrics: vec![(1, Some(0f64)), (0, Some(1f64)), (1, Some(2f64)), (0, Some(3f64))],
-            active_items: 4,
-            metrics_capacity: 4,
-            collision_policy: ValueCollisionPolicy::Overwrite,
-            missing_values_policy: MissingValuesPolicy::default(),
-            stats: TimeSeriesStats::default(),
-            first_idx: 0,
-            prev_snapshot: vec![],
-            upsert_type: UpsertType::default(),
-            prev_value: (0, None),
+    fn it_lays_out_charts_in_cells() {
+        init_log();
+        let yaml = r#"
+default_dimensions:
+  cells: { x: 10, y: 2 }
+position: { x: 100 }
+spacing: 1ch
+charts:
+- name: default
+  series: []
+- name: text
+  dimensions: 20ch x 30px
+  series: []
+- name: pixels
+  position: { x: 0, y: 0 }
+  dimensions: { x: 200, y: 30 }
+  series: []
+"#;
+        let mut chart_config: ChartsConfig = serde_yaml::from_str(yaml).unwrap();
+        chart_config.setup_chart_spacing();
+        let small = SizeInfo { cell_width: 8., cell_height: 16., ..SizeInfo::default() };
+        let large = SizeInfo { cell_width: 12., cell_height: 24., dpr: 2., ..small };
+        let layout = |size: &SizeInfo| -> Vec<(Value2D, Value2D)> {
+            let charts = chart_config.charts.iter();
+            charts
+                .map(|chart| {
+                    let position = chart.position.unwrap().resolve(size);
+                    (position, chart.dimensions.unwrap().resolve(size))
+                })
+                .collect()
         };
-        assert!(!bad.sanity_check());
-        let good = TimeSeries {
-            metrics: vec![(0, Some(0f64)), (1, Some(1f64)), (2, Some(2f64)), (3, Some(3f64))],
-            active_items: 4,
-            metrics_capacity: 4,
-            collision_policy: ValueCollisionPolicy::Overwrite,
-            missing_values_policy: MissingValuesPolicy::default(),
-            stats: TimeSeriesStats::default(),
-            first_idx: 0,
-            prev_snapshot: vec![],
-            upsert_type: UpsertType::default(),
-            prev_value: (0, None),
+        // The second chart starts after 10 columns and the 1 column of spacing.
+        assert_eq!(
+            layout(&small),
+            vec![
+                (Value2D { x: 100., y: 0. }, Value2D { x: 80., y: 32. }),
+                (Value2D { x: 188., y: 0. }, Value2D { x: 160., y: 30. }),
+                (Value2D { x: 0., y: 0. }, Value2D { x: 200., y: 30. }),
+            ]
+        );
+        // The cells are physical pixels, the layout is in logical pixels.
+        assert_eq!(
+            layout(&large),
+            vec![
+                (Value2D { x: 100., y: 0. }, Value2D { x: 60., y: 24. }),
+                (Value2D { x: 166., y: 0. }, Value2D { x: 120., y: 30. }),
+                (Value2D { x: 0., y: 0. }, Value2D { x: 200., y: 30. }),
+            ]
+        );
+        assert!(serde_yaml::from_str::<ChartLength2D>("10ch x 2").is_err());
+        assert!(serde_yaml::from_str::<ChartLength>("2ln").is_err());
+    }
+
+    #[test]
+    fn it_round_trips_chart_lengths() {
+        let lengths = [
+            ChartLength2D::pixels(200., 30.),
+            ChartLength2D::cells(10., 2.),
+            ChartLength2D { pixels: Value2D { x: 0., y: 30. }, cells: Value2D { x: 20., y: 0. } },
+            ChartLength2D::default(),
+        ];
+        for length in lengths {
+            let yaml = serde_yaml::to_string(&length).unwrap();
+            assert_eq!(serde_yaml::from_str::<ChartLength2D>(&yaml).unwrap(), length);
+        }
+        let parsed: ChartLength2D = serde_yaml::from_str("10ch x 2ln").unwrap();
+        assert_eq!(parsed, ChartLength2D::cells(10., 2.));
+        assert_eq!(serde_yaml::to_string(&parsed).unwrap(), "cells:\n  x: 10.0\n  y: 2.0\n");
+        for length in [ChartLength::pixels(10.), ChartLength::cells(2.), ChartLength::default()] {
+            let yaml = serde_yaml::to_string(&length).unwrap();
+            assert_eq!(serde_yaml::from_str::<ChartLength>(&yaml).unwrap(), length);
+        }
+        let parsed: ChartLength = serde_yaml::from_str("{ cells: 2 }").unwrap();
+        assert_eq!(parsed, ChartLength::cells(2.));
+        assert_eq!(serde_yaml::from_str::<ChartLength>("10").unwrap(), ChartLength::pixels(10.));
+    }
+
+    #[test]
+    fn it_serializes_the_values_as_runs() {
+        init_log();
+        // The circular buffer has rotated, the active items are 12 to 17 with a gap at 14, 15.
+        let mut series = TimeSeries::default().with_capacity(6);
+        for (epoch, value) in [(10, 0.), (11, 0.), (12, 1.), (13, 1.), (16, 2.), (17, 1.)] {
+            series.upsert((epoch, Some(value)));
+        }
+        assert_ne!(series.first_idx, 0);
+        let json = serde_json::to_string(&series).unwrap();
+        assert!(json.contains(r#""first_epoch":12,"values":[[1.0,2],[null,2],[2.0,1],[1.0,1]]"#));
+        // The debug fields are not written.
+        for field in ["prev_snapshot", "prev_value", "upsert_type", "first_idx", "metrics\""] {
+            assert!(!json.contains(field), "{} in {}", field, json);
+        }
+        let loaded: TimeSeries = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.as_vec(), series.as_vec());
+        assert_eq!(loaded.first_idx, 0);
+        assert_eq!(loaded.metrics_capacity(), 6);
+        assert!(loaded.sanity_check());
+        // The loaded series keeps rotating from its newest epoch.
+        let mut loaded = loaded;
+        loaded.upsert((18, Some(3.)));
+        series.upsert((18, Some(3.)));
+        assert_eq!(loaded.as_vec(), series.as_vec());
+        // A window of missing values is a single run and an empty series has no values.
+        let mut missing = TimeSeries::default().with_capacity(300);
+        missing.upsert((1000, None));
+        missing.upsert((1299, None));
+        let json = serde_json::to_string(&missing).unwrap();
+        assert!(json.contains(r#""values":[[null,300]]"#));
+        let loaded: TimeSeries = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.as_vec(), missing.as_vec());
+        let json = serde_json::to_string(&TimeSeries::default()).unwrap();
+        assert!(!json.contains("\"values\""));
+        assert_eq!(serde_json::from_str::<TimeSeries>(&json).unwrap().as_vec(), vec![]);
+        // The epochs follow the granularity.
+        let mut fast = TimeSeries { granularity_ms: 250, ..TimeSeries::default() }.with_capacity(4);
+        fast.upsert((1000, Some(1.)));
+        fast.upsert((1500, Some(1.)));
+        let json = serde_json::to_string(&fast).unwrap();
+        let loaded: TimeSeries = serde_json::from_str(&json).unwrap();
+        assert_eq!(loaded.as_vec(), vec![(1000, Some(1.)), (1250, None), (1500, Some(1.))]);
+    }
+
+    #[test]
+    fn it_dumps_compact_metrics() {
+        init_log();
+        // 20 series of 300 computed values, i.e. averages, with 17 significant digits.
+        let mut series = TimeSeries::default().with_capacity(300);
+        for epoch in 0..300u64 {
+            series.upsert((1000 + epoch, Some(epoch as f64 / 7.)));
+        }
+        series.calculate_stats();
+        let source = ManualTimeSeries { series: series.clone(), ..ManualTimeSeries::default() };
+        let chart = TimeSeriesChart {
+            sources: vec![TimeSeriesSource::AlacrittyInput(source); 20],
+            ..TimeSeriesChart::default()
+        };
+        let chart_config = ChartsConfig { charts: vec![chart], ..ChartsConfig::default() };
+        let full_dump = serde_json::to_string(&chart_config).unwrap();
+        let compact_dump = chart_config.compact_dump().unwrap();
+        // Neither dump writes the epochs of each sample, the rounded values are shorter.
+        assert!(compact_dump.len() * 4 < full_dump.len() * 3);
+        // The debug snapshot and the empty vertices are not serialized.
+        assert!(!compact_dump.contains("prev_snapshot"));
+        assert!(!compact_dump.contains("opengl_vecs"));
+        // The dump does not change the values in memory.
+        assert_eq!(chart_config.charts[0].sources[0].series(), series);
+        // A round trip through the compact form is within the configured decimals.
+        for decimals in [DEFAULT_COMPACT_DECIMALS, 2] {
+            let compact = serde_json::to_string(&series.compact(decimals)).unwrap();
+            let loaded: TimeSeries = serde_json::from_str(&compact).unwrap();
+            let tolerance = 0.5 * 10f64.powi(-(decimals as i32)) + f64::EPSILON;
+            assert_eq!(loaded.metrics.len(), series.metrics.len());
+            for (loaded, original) in loaded.metrics.iter().zip(series.metrics.iter()) {
+                assert_eq!(loaded.0, original.0);
+                assert!((loaded.1.unwrap() - original.1.unwrap()).abs() <= tolerance);
+            }
+            assert!((loaded.stats.avg - series.stats.avg).abs() <= 0.5e-6 + f64::EPSILON);
+        }
+    }
+
+    #[test]
+    fn it_derives_series_from_a_source() {
+        init_log();
+        // A counter with a gap at 102 and a reset at 104.
+        let samples = vec![
+            (100, Some(10.)),
+            (101, Some(15.)),
+            (102, None),
+            (103, Some(25.)),
+            (104, Some(5.)),
+            (105, Some(8.)),
+        ];
+        let mut series = TimeSeries::default().with_capacity(10);
+        for sample in &samples {
+            series.upsert(*sample);
+        }
+        let derived = |name: &str, operation| DerivedSeries {
+            name: name.to_string(),
+            source: String::from("requests"),
+            operation,
+            color: ChartColor::default(),
+            alpha: 1.0,
+            pattern: None,
+            base: None,
+        };
+        let mut chart = TimeSeriesChart {
+            name: String::from("counter"),
+            sources: vec![TimeSeriesSource::AlacrittyInput(ManualTimeSeries {
+                name: String::from("requests"),
+                series,
+                ..ManualTimeSeries::default()
+            })],
+            derived: vec![
+                derived("delta", DerivedOperation::Delta),
+                derived("rate", DerivedOperation::RatePerSecond),
+            ],
+            ..TimeSeriesChart::default()
+        };
+        assert_eq!(chart.series_count(), 3);
+        // The derived series have no samples until their source is resolved.
+        assert!(chart.series_at(1).is_none());
+        chart.resolve_derived_series().unwrap();
+        let values = |chart: &TimeSeriesChart, idx| -> Vec<Option<f64>> {
+            chart.series_at(idx).unwrap().as_vec().into_iter().map(|(_, value)| value).collect()
+        };
+        assert_eq!(values(&chart, 1), vec![None, Some(5.), None, Some(10.), Some(-20.), Some(3.)]);
+        assert_eq!(values(&chart, 2), vec![None, Some(5.), None, Some(5.), None, Some(3.)]);
+        assert_eq!(
+            DerivedOperation::CumulativeSum.derive(samples.iter()),
+            vec![Some(10.), Some(25.), None, Some(50.), Some(55.), Some(63.)]
+        );
+        // The derived series are drawn at the epochs of the source, which is not modified.
+        let epochs: Vec<u64> = chart.series_at(2).unwrap().as_vec().iter().map(|x| x.0).collect();
+        assert_eq!(epochs, vec![100, 101, 102, 103, 104, 105]);
+        assert_eq!(chart.sources[0].series().as_vec(), samples);
+        // The chart stats account for the derived series.
+        chart.calculate_stats();
+        assert_eq!(chart.stats.min, -20.);
+        assert_eq!(chart.series_colors().len(), 3);
+        assert_eq!(chart.series_alpha(2), Some(1.0));
+        chart.derived[0].source = String::from("missing");
+        assert!(chart.resolve_derived_series().is_err());
+    }
+
+    #[test]
+    fn it_projects_the_memory_of_the_series() {
+        let series_with_capacity = |capacity| {
+            TimeSeriesSource::AsyncLoadedItems(ManualTimeSeries {
+                series: TimeSeries::default().with_capacity(capacity),
+                ..ManualTimeSeries::default()
+            })
+        };
+        let mut chart_config = ChartsConfig {
+            charts: vec![
+                TimeSeriesChart {
+                    sources: vec![series_with_capacity(100), series_with_capacity(50)],
+                    ..TimeSeriesChart::default()
+                },
+                TimeSeriesChart {
+                    sources: vec![series_with_capacity(10)],
+                    ..TimeSeriesChart::default()
+                },
+            ],
+            ..ChartsConfig::default()
+        };
+        assert_eq!(SAMPLE_BYTES + VERTEX_BYTES, 32);
+        assert_eq!(chart_config.projected_memory_bytes(), 160 * 32);
+        // Adaptive series are projected at the capacity they may grow to.
+        chart_config.charts[1].sources[0].series_mut().adaptive_capacity = true;
+        assert_eq!(chart_config.projected_memory_bytes(), (150 + DEFAULT_MAX_CAPACITY) * 32);
+        // Within the budget, or without one, nothing changes.
+        assert!(chart_config.apply_memory_budget().is_empty());
+        chart_config.memory_budget_mb = Some(1);
+        assert!(chart_config.apply_memory_budget().is_empty());
+    }
+
+    #[test]
+    fn it_reduces_the_capacities_over_the_memory_budget() {
+        init_log();
+        let chart = |name: &str, capacities: &[usize]| TimeSeriesChart {
+            name: name.to_string(),
+            sources: capacities
+                .iter()
+                .enumerate()
+                .map(|(idx, capacity)| {
+                    TimeSeriesSource::AsyncLoadedItems(ManualTimeSeries {
+                        name: format!("series {}", idx),
+                        series: TimeSeries::default().with_capacity(*capacity),
+                        ..ManualTimeSeries::default()
+                    })
+                })
+                .collect(),
+            ..TimeSeriesChart::default()
+        };
+        // 2MB are 65536 samples of 32 bytes, the budget of 1MB halves the capacities.
+        let mut chart_config = ChartsConfig {
+            charts: vec![chart("big", &[40000, 25436]), chart("small", &[100])],
+            memory_budget_mb: Some(1),
+            ..ChartsConfig::default()
+        };
+        assert_eq!(chart_config.projected_memory_bytes(), 2 * 1024 * 1024);
+        let adjustments = chart_config.apply_memory_budget();
+        let adjustment = |chart: &str, series: &str, from, to| MemoryBudgetAdjustment {
+            chart: chart.to_string(),
+            series: series.to_string(),
+            from,
+            to,
+        };
+        assert_eq!(
+            adjustments,
+            vec![
+                adjustment("big", "series 0", 40000, 20000),
+                adjustment("big", "series 1", 25436, 12718),
+                // The small series keeps its minimum capacity.
+                adjustment("small", "series 0", 100, MIN_BUDGET_CAPACITY),
+            ]
+        );
+        assert_eq!(chart_config.charts[0].sources[0].series_ref().metrics_capacity(), 20000);
+        assert_eq!(chart_config.charts[1].sources[0].series_ref().metrics_capacity(), 60);
+        assert!(chart_config.projected_memory_bytes() <= 1024 * 1024 + MIN_BUDGET_CAPACITY * 32);
+        // Series under the minimum are kept as they are.
+        let mut tiny_config = ChartsConfig {
+            charts: vec![chart("tiny", &[10, 70000])],
+            memory_budget_mb: Some(1),
+            ..ChartsConfig::default()
+        };
+        let adjustments = tiny_config.apply_memory_budget();
+        assert_eq!(adjustments.len(), 1);
+        assert_eq!(tiny_config.charts[0].sources[0].series_ref().metrics_capacity(), 10);
+    }
+
+    #[test]
+    fn it_measures_the_memory_as_data_loads() {
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            sources: vec![TimeSeriesSource::default()],
+            ..TimeSeriesChart::default()
+        };
+        let size = ChartSizeInfo {
+            term_size: SizeInfo { width: 100., height: 100., ..SizeInfo::default() },
+            ..ChartSizeInfo::default()
         };
+        let empty = chart.memory_bytes();
+        assert_eq!(empty, chart.sources[0].series_ref().memory_bytes());
+        for epoch in 0..10 {
+            chart.sources[0].series_mut().upsert((epoch, Some(epoch as f64)));
+        }
+        chart.update_all_series_opengl_vecs(size);
+        let loaded = chart.memory_bytes();
+        assert!(loaded >= empty + 10 * VERTEX_BYTES);
+        let charts_config = ChartsConfig { charts: vec![chart], ..ChartsConfig::default() };
+        assert_eq!(charts_config.memory_bytes(), loaded);
+    }
+
+    #[test]
+    fn it_calculates_covered_rows() {
+        let mut chart_config = ChartsConfig {
+            default_dimensions: Some(ChartLength2D::pixels(25., 25.)),
+            position: Some(ChartLength2D::pixels(200., 0.)),
+            charts: vec![TimeSeriesChart::default()],
+            spacing: ChartLength::pixels(0.),
+            resize_debounce_ms: None,
+            compact_decimals: None,
+            startup_retries: None,
+            startup_retry_delay_ms: None,
+            fetch_concurrency: None,
+            overflow: ChartOverflow::Shift,
+            memory_budget_mb: None,
+            palette_mode: PaletteMode::Default,
+            clock_jump_threshold_s: None,
+            debug_overlay_corner: debug_overlay::OverlayCorner::TopLeft,
+            window_presets: None,
+            absence_summary_s: None,
+            data_watchdog: None,
+            load_governor: None,
+            journal: None,
+            persistence: None,
+            data_quality: None,
+            timezone: timefmt::ChartTimezone::Local,
+            decoration_rates: vec![],
+            version: None,
+        };
+        // 10 lines of 10px, the chart covers the bottom 25px
+        let size = SizeInfo {
+            height: 100.,
+            width: 100.,
+            cell_height: 10.,
+            cell_width: 10.,
+            screen_lines: 10,
+            columns: 10,
+            ..SizeInfo::default()
+        };
+        assert_eq!(chart_config.covered_rows(&size), vec![Line(7), Line(8), Line(9)]);
+        // The padding moves the lines down and the chart up.
+        let padded_size = SizeInfo { padding_y: 5., screen_lines: 9, ..size };
+        assert_eq!(chart_config.covered_rows(&padded_size), vec![Line(6), Line(7), Line(8)]);
+        // Bigger cells, the chart fits exactly in the last line.
+        let big_cells = SizeInfo { cell_height: 25., screen_lines: 4, ..size };
+        assert_eq!(chart_config.covered_rows(&big_cells), vec![Line(3)]);
+        // The tallest chart decides the covered lines.
+        let mut tall_chart = TimeSeriesChart::default();
+        tall_chart.dimensions = Some(ChartLength2D::pixels(25., 50.));
+        chart_config.charts.push(tall_chart);
+        assert_eq!(
+            chart_config.covered_rows(&size),
+            vec![Line(5), Line(6), Line(7), Line(8), Line(9)]
+        );
+        // No charts, nothing is covered.
+        chart_config.charts.clear();
+        assert!(chart_config.covered_rows(&size).is_empty());
+    }
+
+    #[test]
+    fn it_does_sanity_check() {
+        let bad = fixtures::corrupted_series(vec![
+            (1, Some(0f64)),
+            (0, Some(1f64)),
+            (1, Some(2f64)),
+            (0, Some(3f64)),
+        ]);
+        assert!(!bad.sanity_check());
+        let good = fixtures::wrapped_series(
+            vec![(0, Some(0f64)), (1, Some(1f64)), (2, Some(2f64)), (3, Some(3f64))],
+            0,
+            4,
+        );
         assert!(good.sanity_check());
     }
 
@@ -2124,43 +9731,38 @@ mod tests {
     #[test]
     fn sync_loss_replication() {
         init_log();
+        // Only the first 5 epochs are active, the stale epochs after them are out of order.
+        let metrics = vec![
+            (65916, None),
+            (65917, None),
+            (65918, None),
+            (65919, None),
+            (65920, None),
+            (20425, Some(9.0)),
+            (20426, Some(9.0)),
+            (20427, Some(9.0)),
+            (20428, Some(9.0)),
+            (20429, Some(9.0)),
+            (20430, Some(9.0)),
+            (20431, Some(9.0)),
+            (20432, Some(9.0)),
+            (20433, Some(9.0)),
+            (20434, Some(9.0)),
+            (20435, Some(9.0)),
+            (20436, Some(9.0)),
+            (20437, Some(9.0)),
+            (20438, Some(9.0)),
+            (20439, Some(9.0)),
+            (20440, Some(9.0)),
+            (20441, Some(9.0)),
+            (20442, Some(9.0)),
+            (20443, Some(9.0)),
+            (20444, Some(9.0)),
+        ];
         let mut corrupt = TimeSeries {
-            metrics: vec![
-                (65916, None),
-                (65917, None),
-                (65918, None),
-                (65919, None),
-                (65920, None),
-                (20425, Some(9.0)),
-                (20426, Some(9.0)),
-                (20427, Some(9.0)),
-                (20428, Some(9.0)),
-                (20429, Some(9.0)),
-                (20430, Some(9.0)),
-                (20431, Some(9.0)),
-                (20432, Some(9.0)),
-                (20433, Some(9.0)),
-                (20434, Some(9.0)),
-                (20435, Some(9.0)),
-                (20436, Some(9.0)),
-                (20437, Some(9.0)),
-                (20438, Some(9.0)),
-                (20439, Some(9.0)),
-                (20440, Some(9.0)),
-                (20441, Some(9.0)),
-                (20442, Some(9.0)),
-                (20443, Some(9.0)),
-                (20444, Some(9.0)),
-            ],
-            active_items: 5,
             metrics_capacity: 25,
             collision_policy: ValueCollisionPolicy::Overwrite,
-            missing_values_policy: MissingValuesPolicy::default(),
-            stats: TimeSeriesStats::default(),
-            first_idx: 0,
-            prev_snapshot: Vec::with_capacity(25),
-            upsert_type: UpsertType::default(),
-            prev_value: (0, None),
+            ..fixtures::wrapped_series(metrics, 0, 5)
         };
         let previous_min_epoch = corrupt.metrics[corrupt.first_idx].0;
         assert_eq!(previous_min_epoch, 65916);
@@ -2203,28 +9805,20 @@ mod tests {
                 (65915, None),
             ]
         );
-        let mut date_20201106 = TimeSeries {
-            metrics: vec![
-                (1604568598, Some(3.0)),
-                (1604568599, Some(3.0)),
-                (1604568600, None),
-                (1604568601, Some(9.0)),
-                (1604568602, Some(6.0)),
-            ],
-            metrics_capacity: 300,
-            stats: TimeSeriesStats::default(),
-            collision_policy: ValueCollisionPolicy::Increment,
-            missing_values_policy: MissingValuesPolicy::Zero,
-            first_idx: 0,
-            active_items: 5,
-            prev_snapshot: vec![],
-            prev_value: (1604568602, Some(6.0)),
-            upsert_type: UpsertType::NewEpoch,
-        };
+        let metrics = vec![
+            (1604568598, Some(3.0)),
+            (1604568599, Some(3.0)),
+            (1604568600, None),
+            (1604568601, Some(9.0)),
+            (1604568602, Some(6.0)),
+        ];
+        let mut date_20201106 =
+            TimeSeries { metrics_capacity: 300, ..fixtures::wrapped_series(metrics, 0, 5) };
         assert!(date_20201106.sanity_check());
         date_20201106.upsert((1604645848, Some(2.0)));
         assert_eq!(date_20201106.metrics[0], (1604645848, Some(2.0)));
         assert_eq!(date_20201106.first_idx, 0);
+        #[cfg(feature = "upsert-debug")]
         assert_eq!(date_20201106.upsert_type, UpsertType::VectorDiscarded);
         assert_eq!(date_20201106.active_items, 1);
         assert_eq!(date_20201106.get_last_idx(), 0);
@@ -2236,4 +9830,299 @@ mod tests {
         assert_eq!(date_20201106.metrics[2], (1604645850, None));
         assert_eq!(date_20201106.metrics[3], (1604645851, Some(1.0)));
     }
+
+    #[test]
+    fn it_keeps_the_window_within_capacity_for_random_epochs() {
+        use rand::rngs::StdRng;
+        use rand::{Rng, SeedableRng};
+        init_log();
+        for seed in 0..64 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let capacity = rng.gen_range(1..32usize);
+            let mut test0 = TimeSeries::default().with_capacity(capacity);
+            test0.granularity_ms = rng.gen_range(1..4) * 1000;
+            let step = test0.epoch_step();
+            let mut clock = 1200i64;
+            for _ in 0..256 {
+                // Mostly around the window, older epochs included, and now and then a clock jump.
+                let steps = if rng.gen_bool(0.05) {
+                    rng.gen_range(-4 * capacity as i64..=4 * capacity as i64)
+                } else {
+                    rng.gen_range(-(capacity as i64) - 2..=2)
+                };
+                clock = (clock + steps * step as i64).max(0);
+                test0.upsert((clock as u64, Some(rng.gen_range(0..10) as f64)));
+                assert!(test0.active_items <= capacity, "seed {}: {:?}", seed, test0);
+                assert!(test0.metrics.len() <= capacity, "seed {}: {:?}", seed, test0);
+                assert!(test0.sanity_check(), "seed {}: {:?}", seed, test0);
+                let epochs: Vec<u64> = test0.iter().map(|(epoch, _)| *epoch).collect();
+                assert_eq!(epochs.len(), test0.active_items, "seed {}: {:?}", seed, test0);
+                assert!(epochs.windows(2).all(|pair| pair[0] + step == pair[1]), "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn it_backfills_the_epochs_before_the_window() {
+        init_log();
+        // The vector is not full, the input and its padding are laid out before the active items.
+        let mut test0 = TimeSeries::default().with_capacity(5);
+        test0.upsert((100, Some(1.)));
+        test0.upsert((101, Some(2.)));
+        assert_eq!(test0.upsert((98, Some(3.))), 2);
+        assert_eq!(test0.as_vec(), vec![
+            (98, Some(3.)),
+            (99, None),
+            (100, Some(1.)),
+            (101, Some(2.))
+        ]);
+        assert_eq!((test0.first_idx, test0.active_items, test0.metrics.len()), (0, 4, 4));
+        assert!(test0.sanity_check());
+        // The vector is full, the input and its padding roll back from the start of the array.
+        let mut test1 = TimeSeries::default().with_capacity(5);
+        for epoch in 100..107 {
+            test1.upsert((epoch, Some(0.)));
+        }
+        test1.upsert((120, Some(1.)));
+        test1.upsert((121, Some(2.)));
+        assert_eq!((test1.first_idx, test1.active_items, test1.metrics.len()), (0, 2, 5));
+        assert_eq!(test1.upsert((118, Some(3.))), 2);
+        assert_eq!(test1.as_vec(), vec![
+            (118, Some(3.)),
+            (119, None),
+            (120, Some(1.)),
+            (121, Some(2.))
+        ]);
+        assert_eq!((test1.first_idx, test1.active_items, test1.metrics.len()), (3, 4, 5));
+        assert!(test1.sanity_check());
+        // The active epochs are spread by a coarser granularity, the padding does not fit.
+        let mut test2 = TimeSeries::default().with_capacity(5);
+        for epoch in 100..105 {
+            test2.upsert((epoch, Some(0.)));
+        }
+        test2.granularity_ms = 2000;
+        let before = test2.as_vec();
+        assert_eq!(test2.upsert((98, Some(1.))), 0);
+        assert_eq!(test2.as_vec(), before);
+    }
+
+    #[test]
+    fn it_recovers_the_series_from_clock_jumps() {
+        init_log();
+        assert_eq!(ClockJump::from_delta(3600), ClockJump::Shift(3600));
+        assert_eq!(ClockJump::from_delta(3590), ClockJump::Shift(3600));
+        assert_eq!(ClockJump::from_delta(1805), ClockJump::Shift(1800));
+        assert_eq!(ClockJump::from_delta(1000), ClockJump::Rebase);
+        assert_eq!(ClockJump::from_delta(30), ClockJump::Rebase);
+        let mut series = TimeSeries::default().with_capacity(10);
+        for epoch in 100..105 {
+            series.upsert((epoch, Some(epoch as f64)));
+        }
+        let mut shifted = series.clone();
+        shifted.shift_epochs(50);
+        let expected: Vec<(u64, Option<f64>)> =
+            (50..55).map(|epoch| (epoch, Some(epoch as f64 + 50.))).collect();
+        assert_eq!(shifted.as_vec(), expected);
+        assert_eq!(shifted.newest_epoch(), Some(54));
+        series.rebase(20);
+        assert_eq!(series.as_vec(), vec![(20, None)]);
+        assert_eq!(series.metrics_capacity(), 10);
+        // The counters are shifted while the prometheus series are fetched again.
+        let mut counter = ManualTimeSeries::default();
+        let mut prom = prometheus::PrometheusTimeSeries::default();
+        for epoch in 10_000..10_010 {
+            counter.series.upsert((epoch, Some(1.)));
+            prom.series.upsert((epoch, Some(2.)));
+        }
+        let chart = TimeSeriesChart {
+            sources: vec![
+                TimeSeriesSource::AlacrittyInput(counter),
+                TimeSeriesSource::PrometheusTimeSeries(Box::new(prom)),
+            ],
+            ..TimeSeriesChart::default()
+        };
+        let mut chart_config = ChartsConfig { charts: vec![chart], ..ChartsConfig::default() };
+        assert_eq!(chart_config.recover_clock_jump(10_000), None);
+        assert_eq!(chart_config.recover_clock_jump(10_010 - 3600), Some(ClockJump::Shift(3600)));
+        let sources = &chart_config.charts[0].sources;
+        assert_eq!(sources[0].series_ref().newest_epoch(), Some(10_009 - 3600));
+        assert_eq!(sources[0].series_ref().as_vec().len(), 10);
+        assert_eq!(sources[1].series_ref().as_vec(), vec![(10_010 - 3600, None)]);
+        assert_eq!(chart_config.newest_epoch(), Some(10_010 - 3600));
+        chart_config.clock_jump_threshold_s = Some(5000);
+        assert_eq!(chart_config.recover_clock_jump(10_010 - 7200), None);
+    }
+
+    #[test]
+    fn it_summarizes_the_series() {
+        init_log();
+        let mut series = TimeSeries::default().with_capacity(4);
+        assert_eq!(
+            series.summary("empty").to_string(),
+            "empty: 0/4 items, epochs -..-, min: 0, max: 0, avg: 0, count: 0"
+        );
+        for epoch in 10..13 {
+            series.upsert((epoch, Some(epoch as f64)));
+        }
+        series.calculate_stats();
+        assert_eq!(
+            series.summary("load").to_string(),
+            "load: 3/4 items, epochs 10..12, min: 10, max: 12, avg: 11, count: 3"
+        );
+        // A full series is described in the same bounded line.
+        let mut series = TimeSeries::default().with_capacity(DEFAULT_MAX_CAPACITY);
+        for epoch in 0..(DEFAULT_MAX_CAPACITY as u64 * 2) {
+            series.upsert((epoch, Some(1.)));
+        }
+        series.calculate_stats();
+        let summary = series.summary("input");
+        assert_eq!(summary.first_epoch, Some(DEFAULT_MAX_CAPACITY as u64));
+        assert_eq!(summary.last_epoch, Some(DEFAULT_MAX_CAPACITY as u64 * 2 - 1));
+        assert!(summary.to_string().len() < 120);
+    }
+
+    #[test]
+    fn it_summarizes_a_window_of_a_wrapped_series() {
+        init_log();
+        // The active items are 11, 12, 13, 14, 15 and 16, starting at index 3.
+        let metrics = vec![
+            (14, Some(4f64)),
+            (15, None),
+            (16, Some(2f64)),
+            (11, Some(8f64)),
+            (12, None),
+            (13, Some(1f64)),
+        ];
+        let test = fixtures::wrapped_series(metrics, 3, 6);
+        let summary = test.window_summary(12, 16).unwrap();
+        assert_eq!((summary.from_epoch, summary.to_epoch), (13, 16));
+        assert_eq!((summary.first, summary.last), (1f64, 2f64));
+        assert_eq!((summary.min, summary.max, summary.count), (1f64, 4f64, 3));
+        assert!((summary.avg - 7f64 / 3f64).abs() < f64::EPSILON);
+        assert!(!summary.truncated);
+        // A single value across the end of the buffer.
+        let summary = test.window_summary(14, 14).unwrap();
+        assert_eq!((summary.first, summary.last, summary.avg, summary.count), (4., 4., 4., 1));
+        // The ranges of only missing values, after the newest metric or inverted are empty.
+        assert_eq!(test.window_summary(15, 15), None);
+        assert_eq!(test.window_summary(17, 20), None);
+        assert_eq!(test.window_summary(16, 12), None);
+        assert_eq!(fixtures::empty_series(4).window_summary(0, 20), None);
+    }
+
+    #[test]
+    fn it_clamps_the_summary_window_to_the_retained_metrics() {
+        init_log();
+        let test = fixtures::series_with_gaps(10, &[
+            (11, Some(8f64)),
+            (13, Some(1f64)),
+            (14, Some(4f64)),
+            (16, Some(2f64)),
+        ]);
+        // The range starts before the oldest metric, the older values were dropped.
+        let summary = test.window_summary(5, 100).unwrap();
+        assert_eq!((summary.from_epoch, summary.to_epoch), (11, 16));
+        assert_eq!((summary.count, summary.avg), (4, 3.75));
+        assert!(summary.truncated);
+        // The range ends after the newest metric, nothing was dropped.
+        assert!(!test.window_summary(11, 100).unwrap().truncated);
+        // The range ends before the oldest metric.
+        assert_eq!(test.window_summary(0, 10), None);
+        // The sources of a chart are merged, the first and last values are those of the oldest
+        // and newest epochs.
+        let mut chart =
+            fixtures::chart("load", vec![TimeSeriesSource::default(), TimeSeriesSource::default()]);
+        *chart.sources[0].series_mut() =
+            fixtures::series_with_gaps(10, &[(10, Some(1f64)), (11, Some(3f64))]);
+        *chart.sources[1].series_mut() =
+            fixtures::series_with_gaps(10, &[(10, None), (11, Some(5f64)), (12, Some(0f64))]);
+        let summary = chart.summary_since(10, 20).unwrap();
+        assert_eq!(summary.to_string(), "load: from 1 to 0 over 2s, min 0, max 5, avg 2.25");
+        assert_eq!(
+            chart.summary_since(5, 20).unwrap().to_string(),
+            "load: from 1 to 0 over 2s, min 0, max 5, avg 2.25 (older values were dropped)"
+        );
+        // The charts without values in the range are not summarized.
+        let chart_config = ChartsConfig {
+            charts: vec![chart, fixtures::chart("input", vec![TimeSeriesSource::default()])],
+            ..ChartsConfig::default()
+        };
+        let summaries = chart_config.summarize_since(10, 20);
+        assert_eq!(summaries.len(), 1);
+        assert_eq!(summaries[0].name, "load");
+        assert!(chart_config.summarize_since(13, 20).is_empty());
+    }
+
+    #[test]
+    fn it_resolves_the_charts_referenced_by_bindings() {
+        let chart_config = fixtures::single_chart_config();
+        let by_name: ChartRef = serde_json::from_str("\"input\"").unwrap();
+        let by_index: ChartRef = serde_json::from_str("0").unwrap();
+        assert_eq!(chart_config.resolve_chart(&by_name), Some(ChartIndex(0)));
+        assert_eq!(chart_config.resolve_chart(&by_index), Some(ChartIndex(0)));
+        let missing = ChartRef::Name(String::from("loadavg"));
+        assert_eq!(chart_config.resolve_chart(&missing), None);
+        assert_eq!(chart_config.resolve_chart(&ChartRef::Index(1)), None);
+        assert_eq!(missing.to_string(), "'loadavg'");
+        assert_eq!(ChartRef::Index(1).to_string(), "#1");
+    }
+
+    #[test]
+    fn it_draws_the_histogram_of_the_chart_values() {
+        let term_size = SizeInfo { width: 100., height: 100., ..SizeInfo::default() };
+        let size = ChartSizeInfo { term_size, ..ChartSizeInfo::default() };
+        let histogram = HistogramDecoration {
+            buckets: Some(4),
+            width: Some(10.),
+            ..HistogramDecoration::default()
+        };
+        let mut chart = TimeSeriesChart {
+            position: Some(ChartLength2D::pixels(0., 0.)),
+            dimensions: Some(ChartLength2D::pixels(100., 100.)),
+            sources: vec![TimeSeriesSource::default()],
+            decorations: vec![Decoration::Histogram(histogram)],
+            ..TimeSeriesChart::default()
+        };
+        let fill = |chart: &mut TimeSeriesChart, values: &[f64]| {
+            let series = chart.sources[0].series_mut();
+            *series = TimeSeries::default().with_capacity(values.len());
+            for (epoch, value) in values.iter().enumerate() {
+                series.upsert((10 + epoch as u64, Some(*value)));
+            }
+        };
+        fill(&mut chart, &[0., 1., 1., 2., 3., 3., 3., 4.]);
+        chart.update_all_series_opengl_vecs(size);
+        let chart_size = ChartSizeInfo { chart_width: 100., chart_height: 100., ..size };
+        let quads = chart.decorations[0].opengl_vertices();
+        // The last bucket holds the max, it is the fullest and spans the whole strip.
+        assert_eq!(quads.len(), 4 * 12);
+        let (left, right) = (chart_size.scale_x(90.), chart_size.scale_x(100.));
+        for (bucket, count) in [1., 2., 1., 4.].iter().enumerate() {
+            let quad = &quads[bucket * 12..(bucket + 1) * 12];
+            let bottom = chart_size.value_to_ndc_y(bucket as f64, &chart.stats);
+            let top = chart_size.value_to_ndc_y(bucket as f64 + 1., &chart.stats);
+            let extent = chart_size.scale_x(90. + 10. * count / 4.);
+            assert_eq!(quad[0], left);
+            assert!((quad[1] - bottom).abs() < 1e-5);
+            assert!((quad[2] - extent).abs() < 1e-5);
+            assert!((quad[5] - top).abs() < 1e-5);
+        }
+        assert!((quads[3 * 12 + 2] - right).abs() < 1e-5);
+        // The strip is reserved, the series is drawn left of it.
+        let last_x = chart.opengl_vecs[0][chart.opengl_vecs[0].len() - 2];
+        assert!(last_x <= left);
+        // Empty buckets are not drawn.
+        fill(&mut chart, &[0., 0., 4.]);
+        chart.update_all_series_opengl_vecs(size);
+        assert_eq!(chart.decorations[0].opengl_vertices().len(), 2 * 12);
+        // All the values are equal, a single bucket spans the whole strip.
+        fill(&mut chart, &[2., 2., 2.]);
+        chart.update_all_series_opengl_vecs(size);
+        let quads = chart.decorations[0].opengl_vertices();
+        assert_eq!(quads.len(), 12);
+        assert_eq!(quads[0], left);
+        assert!((quads[2] - right).abs() < 1e-5);
+        let value_y = chart_size.value_to_ndc_y(2., &chart.stats);
+        assert!(quads[1] < value_y && quads[5] > value_y);
+    }
 }
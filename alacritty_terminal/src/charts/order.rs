@@ -0,0 +1,69 @@
+//! `order` is the ordering contract of the collections the charts are flattened into. The draw
+//! data, the series styles, the discovered series and the status listings are always ordered by
+//! the chart config index, then the source config index, then the sub-series key
+//! lexicographically.
+//!
+//! Review note: a map (i.e. a routing table or a name lookup) must not be iterated to build one
+//! of these collections, its items are collected and sorted with `stable_order_by_key` instead.
+//! Otherwise the order would depend on the hasher or on the arrival order of the data, which
+//! shuffles the draw order and the colors between runs.
+use crate::charts::{ChartIndex, SeriesIndex};
+use std::collections::HashMap;
+
+/// `OrderKey` is the position of a sub-series in the ordering contract
+pub type OrderKey = (ChartIndex, SeriesIndex, SubSeriesKey);
+
+/// `SubSeriesKey` identifies the sub-series of a source by its labels, sorted by name
+pub type SubSeriesKey = Vec<(String, String)>;
+
+/// `stable_order_by_key` sorts the items flattened from a dynamic collection by their key of the
+/// ordering contract, the items with the same key keep their relative order.
+pub fn stable_order_by_key<T, K, F>(items: &mut [T], key: F)
+where
+    K: Ord,
+    F: FnMut(&T) -> K,
+{
+    items.sort_by_key(key);
+}
+
+/// `sub_series_key` returns the key of the sub-series with `labels`, the labels are compared by
+/// name and then by value.
+pub fn sub_series_key(labels: &HashMap<String, String>) -> SubSeriesKey {
+    let mut key: SubSeriesKey =
+        labels.iter().map(|(name, value)| (name.clone(), value.clone())).collect();
+    key.sort();
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_orders_by_chart_source_and_sub_series() {
+        let labels = |pairs: &[(&str, &str)]| -> HashMap<String, String> {
+            pairs.iter().map(|(name, value)| (name.to_string(), value.to_string())).collect()
+        };
+        let boot = labels(&[("mountpoint", "/boot"), ("device", "sda1")]);
+        let root = labels(&[("device", "sda2"), ("mountpoint", "/")]);
+        assert_eq!(
+            sub_series_key(&boot),
+            vec![
+                (String::from("device"), String::from("sda1")),
+                (String::from("mountpoint"), String::from("/boot"))
+            ]
+        );
+        let mut items: Vec<OrderKey> = vec![
+            (ChartIndex(1), SeriesIndex(0), sub_series_key(&root)),
+            (ChartIndex(0), SeriesIndex(2), sub_series_key(&root)),
+            (ChartIndex(0), SeriesIndex(2), sub_series_key(&boot)),
+            (ChartIndex(0), SeriesIndex(1), sub_series_key(&root)),
+        ];
+        stable_order_by_key(&mut items, |item| item.clone());
+        let order: Vec<(usize, usize, &str)> = items
+            .iter()
+            .map(|(chart, series, key)| (chart.0, series.0, key[1].1.as_str()))
+            .collect();
+        assert_eq!(order, vec![(0, 1, "/"), (0, 2, "/boot"), (0, 2, "/"), (1, 0, "/")]);
+    }
+}
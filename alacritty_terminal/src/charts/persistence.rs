@@ -0,0 +1,556 @@
+//! `persistence` keeps the samples of the charts across sessions in a state file. The file is
+//! written on shutdown and, optionally, periodically while running. It is written to a temporary
+//! file in the same directory that is renamed over the state file, so that a terminal killed
+//! mid-write leaves the previous snapshot in place. The snapshot starts with a header that tells
+//! the format version and the chart config it was written for, a snapshot of another version or
+//! config is not loaded.
+
+use crate::charts::{TimeSeriesChart, TimeSeriesSource};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::fs::{self, File};
+use std::io::{self, Write};
+use std::path::{Path, PathBuf};
+
+/// The bytes a state file starts with
+pub const MAGIC: &[u8; 8] = b"CHRTSNAP";
+
+/// The version of the format of the snapshots, snapshots of other versions are not loaded
+pub const FORMAT_VERSION: u16 = 1u16;
+
+/// The bytes of the header: the magic bytes, the format version, the creation epoch and the
+/// fingerprint of the chart config
+pub const HEADER_BYTES: usize = MAGIC.len() + 2 + 8 + 8;
+
+/// The samples of each source of each chart, in the order of the chart config
+type SnapshotSamples = Vec<Vec<Vec<(u64, f64)>>>;
+
+/// `PersistenceConfig` sets where the samples of the charts are kept across sessions
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone)]
+pub struct PersistenceConfig {
+    /// The state file, written on shutdown and loaded on startup
+    pub path: PathBuf,
+
+    /// The seconds between the snapshots written while running, only written on shutdown when
+    /// unset
+    #[serde(default)]
+    pub snapshot_interval_s: Option<u64>,
+}
+
+/// `SnapshotHeader` is what a snapshot tells about itself before its samples
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotHeader {
+    /// The format the samples are written in
+    pub version: u16,
+
+    /// The epoch the snapshot was written at
+    pub created_epoch: u64,
+
+    /// The fingerprint of the chart config the snapshot was written for
+    pub fingerprint: u64,
+}
+
+impl SnapshotHeader {
+    /// `encode` returns the bytes of the header, the numbers are little endian
+    pub fn encode(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(HEADER_BYTES);
+        bytes.extend_from_slice(MAGIC);
+        bytes.extend_from_slice(&self.version.to_le_bytes());
+        bytes.extend_from_slice(&self.created_epoch.to_le_bytes());
+        bytes.extend_from_slice(&self.fingerprint.to_le_bytes());
+        bytes
+    }
+
+    /// `decode` reads the header at the start of a snapshot, returns it along with the rest of
+    /// the bytes
+    pub fn decode(bytes: &[u8]) -> Result<(SnapshotHeader, &[u8]), SnapshotError> {
+        if bytes.len() < HEADER_BYTES {
+            return Err(SnapshotError::Truncated);
+        }
+        let (header, body) = bytes.split_at(HEADER_BYTES);
+        let (magic, fields) = header.split_at(MAGIC.len());
+        if magic != MAGIC {
+            return Err(SnapshotError::NotASnapshot);
+        }
+        let (version, fields) = fields.split_at(2);
+        let (created_epoch, fingerprint) = fields.split_at(8);
+        let header = SnapshotHeader {
+            version: u16::from_le_bytes(version.try_into().unwrap()),
+            created_epoch: u64::from_le_bytes(created_epoch.try_into().unwrap()),
+            fingerprint: u64::from_le_bytes(fingerprint.try_into().unwrap()),
+        };
+        Ok((header, body))
+    }
+}
+
+/// `SnapshotError` is why a snapshot could not be written or loaded
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The state file could not be read or written
+    Io(io::Error),
+    /// The state file is shorter than the header
+    Truncated,
+    /// The state file does not start with the magic bytes
+    NotASnapshot,
+    /// The snapshot was written in another format version
+    Version(u16),
+    /// The snapshot was written for another chart config
+    Fingerprint { expected: u64, found: u64 },
+    /// The samples cannot be read or do not match the charts
+    Parse(String),
+}
+
+impl From<io::Error> for SnapshotError {
+    fn from(err: io::Error) -> SnapshotError {
+        SnapshotError::Io(err)
+    }
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(err) => write!(f, "{}", err),
+            SnapshotError::Truncated => write!(f, "The snapshot is truncated"),
+            SnapshotError::NotASnapshot => write!(f, "The file is not a chart snapshot"),
+            SnapshotError::Version(version) => write!(
+                f,
+                "The snapshot is in format version {}, expected {}",
+                version, FORMAT_VERSION
+            ),
+            SnapshotError::Fingerprint { expected, found } => write!(
+                f,
+                "The snapshot is for another chart config, fingerprint {:016x} instead of {:016x}",
+                found, expected
+            ),
+            SnapshotError::Parse(err) => write!(f, "The samples are invalid: {}", err),
+        }
+    }
+}
+
+/// `source_identity` returns what identifies the data of a source: its type, its name and, for
+/// the Prometheus series, what is queried. How the source is drawn is not part of it.
+fn source_identity(source: &TimeSeriesSource) -> String {
+    match source {
+        TimeSeriesSource::PrometheusTimeSeries(prom) => {
+            let mut labels: Vec<String> = prom
+                .required_labels
+                .iter()
+                .map(|(name, value)| format!("{}={}", name, value))
+                .collect();
+            labels.sort();
+            format!(
+                "prometheus:{}:{}:{}:{}",
+                prom.name,
+                prom.source,
+                prom.data_type,
+                labels.join(",")
+            )
+        },
+        TimeSeriesSource::AlacrittyInput(x) => format!("alacritty_input:{}", x.name),
+        TimeSeriesSource::AlacrittyOutput(x) => format!("alacritty_output:{}", x.name),
+        TimeSeriesSource::AsyncLoadedItems(x) => format!("async_loaded_items:{}", x.name),
+        TimeSeriesSource::AlacrittyScroll(x) => format!("alacritty_scroll:{}", x.name),
+        TimeSeriesSource::AlacrittySelection(x) => format!("alacritty_selection:{}", x.name),
+    }
+}
+
+/// `persisted_sources` returns the sources of a chart that come from the chart config. The series
+/// added by a discovery while running are not kept, the next session adds them again once their
+/// label values are discovered.
+fn persisted_sources(chart: &TimeSeriesChart) -> impl Iterator<Item = &TimeSeriesSource> {
+    chart.sources.iter().filter(|source| !is_discovered(source))
+}
+
+/// `is_discovered` returns true for the series added by the discovery of another source
+fn is_discovered(source: &TimeSeriesSource) -> bool {
+    matches!(source, TimeSeriesSource::PrometheusTimeSeries(prom) if prom.discovered.is_some())
+}
+
+/// `fingerprint` returns a hash of the chart names and the identities of their sources, in
+/// order, the discovered series are not part of it. The positions, colors and other cosmetic
+/// settings are not hashed, so that editing them keeps the snapshots. The hash is FNV-1a, it is
+/// stable across builds.
+pub fn fingerprint(charts: &[TimeSeriesChart]) -> u64 {
+    let mut hash = 0xcbf2_9ce4_8422_2325u64;
+    let mut write = |text: &str| {
+        // The separator keeps "ab" + "c" apart from "a" + "bc".
+        for byte in text.bytes().chain(std::iter::once(0u8)) {
+            hash ^= u64::from(byte);
+            hash = hash.wrapping_mul(0x0100_0000_01b3);
+        }
+    };
+    for chart in charts {
+        write(&chart.name);
+        for source in persisted_sources(chart) {
+            write(&source_identity(source));
+        }
+    }
+    hash
+}
+
+/// `encode_snapshot` returns the header and the samples of the charts, the missing samples and the
+/// discovered series are not written
+pub fn encode_snapshot(charts: &[TimeSeriesChart], now: u64) -> Result<Vec<u8>, SnapshotError> {
+    let samples: SnapshotSamples = charts
+        .iter()
+        .map(|chart| {
+            persisted_sources(chart)
+                .map(|source| {
+                    source
+                        .series_ref()
+                        .iter()
+                        .filter_map(|(epoch, value)| value.map(|value| (*epoch, value)))
+                        .collect()
+                })
+                .collect()
+        })
+        .collect();
+    let header = SnapshotHeader {
+        version: FORMAT_VERSION,
+        created_epoch: now,
+        fingerprint: fingerprint(charts),
+    };
+    let mut bytes = header.encode();
+    serde_json::to_writer(&mut bytes, &samples)
+        .map_err(|err| SnapshotError::Parse(err.to_string()))?;
+    Ok(bytes)
+}
+
+/// `decode_snapshot` upserts the samples of a snapshot into the series of the charts, returns
+/// its header and the number of samples loaded. A snapshot of another format version or chart
+/// config loads nothing.
+pub fn decode_snapshot(
+    bytes: &[u8],
+    charts: &mut [TimeSeriesChart],
+) -> Result<(SnapshotHeader, usize), SnapshotError> {
+    let (header, body) = SnapshotHeader::decode(bytes)?;
+    if header.version != FORMAT_VERSION {
+        return Err(SnapshotError::Version(header.version));
+    }
+    let expected = fingerprint(charts);
+    if header.fingerprint != expected {
+        return Err(SnapshotError::Fingerprint { expected, found: header.fingerprint });
+    }
+    let samples: SnapshotSamples =
+        serde_json::from_slice(body).map_err(|err| SnapshotError::Parse(err.to_string()))?;
+    // The fingerprint matched, a different shape is a corrupted snapshot.
+    let matches = samples.len() == charts.len()
+        && samples
+            .iter()
+            .zip(charts.iter())
+            .all(|(sources, chart)| sources.len() == persisted_sources(chart).count());
+    if !matches {
+        return Err(SnapshotError::Parse(String::from("The samples do not match the charts")));
+    }
+    let mut loaded = 0usize;
+    for (sources, chart) in samples.into_iter().zip(charts.iter_mut()) {
+        let chart_sources = chart.sources.iter_mut().filter(|source| !is_discovered(source));
+        for (source_samples, source) in sources.into_iter().zip(chart_sources) {
+            let series = source.series_mut();
+            for (epoch, value) in source_samples {
+                series.upsert((epoch, Some(value)));
+                loaded += 1;
+            }
+        }
+    }
+    Ok((header, loaded))
+}
+
+/// `write_snapshot` writes a snapshot of the charts to the state file atomically, returns the
+/// bytes written
+pub fn write_snapshot(
+    path: &Path,
+    charts: &[TimeSeriesChart],
+    now: u64,
+) -> Result<usize, SnapshotError> {
+    let bytes = encode_snapshot(charts, now)?;
+    write_atomic(path, &bytes)?;
+    Ok(bytes.len())
+}
+
+/// `load_snapshot` loads the snapshot of the state file into the charts, returns its header and
+/// the number of samples loaded
+pub fn load_snapshot(
+    path: &Path,
+    charts: &mut [TimeSeriesChart],
+) -> Result<(SnapshotHeader, usize), SnapshotError> {
+    let bytes = fs::read(path)?;
+    decode_snapshot(&bytes, charts)
+}
+
+/// `temp_path` returns the file a snapshot is written to before it is renamed over `path`, it is
+/// in the same directory so that the rename does not cross file systems
+pub fn temp_path(path: &Path) -> PathBuf {
+    let file_name = path.file_name().map(|name| name.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!(".{}.tmp", file_name))
+}
+
+/// `write_atomic` replaces the contents of `path`: they are written and synced to a temporary
+/// file that is then renamed over it. The file at `path` is either the previous contents or the
+/// new ones, the temporary file is removed when the write fails.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let tmp_path = temp_path(path);
+    let write = || -> io::Result<()> {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+        fs::rename(&tmp_path, path)
+    };
+    if let Err(err) = write() {
+        let _ = fs::remove_file(&tmp_path);
+        return Err(err);
+    }
+    // The rename is only durable once the directory is synced.
+    #[cfg(unix)]
+    if let Some(dir) = path.parent().and_then(|dir| File::open(dir).ok()) {
+        let _ = dir.sync_all();
+    }
+    Ok(())
+}
+
+/// `SnapshotSchedule` tells when the periodic snapshots are due, by the epoch of the coordinator
+/// clock
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SnapshotSchedule {
+    /// The seconds between snapshots, None when they are disabled
+    interval_s: Option<u64>,
+
+    /// The epoch of the next snapshot, None until the first check
+    next_epoch: Option<u64>,
+}
+
+impl SnapshotSchedule {
+    /// `new` returns the schedule of the snapshots every `interval_s` seconds, disabled when
+    /// unset or 0
+    pub fn new(interval_s: Option<u64>) -> Self {
+        SnapshotSchedule { interval_s: interval_s.filter(|secs| *secs > 0), next_epoch: None }
+    }
+
+    /// `is_enabled` returns true when periodic snapshots are written
+    pub fn is_enabled(&self) -> bool {
+        self.interval_s.is_some()
+    }
+
+    /// `due` returns true when a snapshot should be written at `now`, the next one is then
+    /// scheduled an interval later. The first check only starts the schedule, a clock that went
+    /// back is rescheduled from its new epoch.
+    pub fn due(&mut self, now: u64) -> bool {
+        let interval_s = match self.interval_s {
+            Some(interval_s) => interval_s,
+            None => return false,
+        };
+        match self.next_epoch {
+            Some(next_epoch) if now >= next_epoch => {
+                self.next_epoch = Some(now + interval_s);
+                true
+            },
+            Some(next_epoch) if now + interval_s >= next_epoch => false,
+            _ => {
+                self.next_epoch = Some(now + interval_s);
+                false
+            },
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::charts::fixtures::{chart, MockClock};
+    use crate::charts::prometheus::{DiscoveryConfig, PrometheusTimeSeries};
+    use crate::charts::{ChartLength2D, ManualTimeSeries};
+
+    /// `temp_dir` returns an empty directory for the state files of a test
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "chartacritty-persistence-{}-{}",
+            std::process::id(),
+            name
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    /// `charts` returns a chart with an input counter and a Prometheus series, without samples
+    fn empty_charts() -> Vec<TimeSeriesChart> {
+        let prom = PrometheusTimeSeries {
+            name: String::from("load"),
+            source: String::from("http://localhost:9090/api/v1/query?query=node_load1"),
+            data_type: String::from("scalar"),
+            ..PrometheusTimeSeries::default()
+        };
+        vec![chart("load", vec![
+            TimeSeriesSource::AlacrittyInput(ManualTimeSeries::default()),
+            TimeSeriesSource::PrometheusTimeSeries(Box::new(prom)),
+        ])]
+    }
+
+    /// `charts_with_samples` returns the charts with samples at 10, 11 and 13
+    fn charts_with_samples() -> Vec<TimeSeriesChart> {
+        let mut charts = empty_charts();
+        for (epoch, value) in [(10, 1.), (11, 2.5), (13, 4.)] {
+            for source in charts[0].sources.iter_mut() {
+                source.series_mut().upsert((epoch, Some(value)));
+            }
+        }
+        charts
+    }
+
+    #[test]
+    fn it_restores_the_samples_of_a_snapshot() {
+        let charts = charts_with_samples();
+        let bytes = encode_snapshot(&charts, 20).unwrap();
+        let mut restored = empty_charts();
+        assert!(restored[0].sources[0].series_ref().as_vec().is_empty());
+        let (header, loaded) = decode_snapshot(&bytes, &mut restored).unwrap();
+        assert_eq!(header.created_epoch, 20);
+        assert_eq!(header.version, FORMAT_VERSION);
+        // The missing sample at 12 is not written, the upserts fill it again.
+        assert_eq!(loaded, 6);
+        for (source, restored) in charts[0].sources.iter().zip(restored[0].sources.iter()) {
+            assert_eq!(source.series_ref().as_vec(), restored.series_ref().as_vec());
+        }
+    }
+
+    #[test]
+    fn it_restores_the_charts_with_discovered_series() {
+        let dir = temp_dir("discovery");
+        let path = dir.join("charts.state");
+        let discovering_charts = || {
+            let mut charts = empty_charts();
+            if let TimeSeriesSource::PrometheusTimeSeries(prom) = &mut charts[0].sources[1] {
+                prom.discover = Some(DiscoveryConfig {
+                    label: String::from("mountpoint"),
+                    matcher: String::from("node_filesystem_avail_bytes"),
+                    query_template: String::from(
+                        "http://localhost:9090/api/v1/query?query=avail{mountpoint=\"{value}\"}",
+                    ),
+                    interval: 60,
+                    grace_period: 300,
+                    max_series: 4,
+                });
+            }
+            charts
+        };
+        // The first session discovers a series that gets samples too.
+        let mut charts = discovering_charts();
+        let values = vec![String::from("/"), String::from("/boot")];
+        charts[0].apply_discovered_values(1, &values, 1000);
+        assert_eq!(charts[0].sources.len(), 4);
+        for (epoch, value) in [(10, 1.), (11, 2.5)] {
+            for source in charts[0].sources.iter_mut() {
+                source.series_mut().upsert((epoch, Some(value)));
+            }
+        }
+        write_snapshot(&path, &charts, 20).unwrap();
+        // The next session starts from the chart config, without the discovered series.
+        let mut restored = discovering_charts();
+        let (_, loaded) = load_snapshot(&path, &mut restored).unwrap();
+        assert_eq!(loaded, 4);
+        assert_eq!(restored[0].sources.len(), 2);
+        for (source, restored) in charts[0].sources.iter().zip(restored[0].sources.iter()) {
+            assert_eq!(source.series_ref().as_vec(), restored.series_ref().as_vec());
+        }
+        // The series discovered again are not confused with the ones of the chart config.
+        restored[0].apply_discovered_values(1, &values, 1100);
+        assert_eq!(fingerprint(&restored), fingerprint(&discovering_charts()));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_rejects_the_snapshots_it_cannot_load() {
+        let charts = charts_with_samples();
+        let bytes = encode_snapshot(&charts, 20).unwrap();
+        let load = |bytes: &[u8]| decode_snapshot(bytes, &mut empty_charts());
+        assert!(matches!(load(&bytes[..HEADER_BYTES - 1]), Err(SnapshotError::Truncated)));
+        let mut other_file = bytes.clone();
+        other_file[0] = b'{';
+        assert!(matches!(load(&other_file), Err(SnapshotError::NotASnapshot)));
+        let mut other_version = bytes.clone();
+        other_version[MAGIC.len()..MAGIC.len() + 2].copy_from_slice(&2u16.to_le_bytes());
+        assert!(matches!(load(&other_version), Err(SnapshotError::Version(2))));
+        // A truncated body is rejected before any sample is loaded.
+        let mut restored = empty_charts();
+        let err = decode_snapshot(&bytes[..bytes.len() - 2], &mut restored).unwrap_err();
+        assert!(matches!(err, SnapshotError::Parse(_)), "{}", err);
+        assert!(restored[0].sources[1].series_ref().as_vec().is_empty());
+        // A snapshot of another chart config loads nothing.
+        let mut renamed = empty_charts();
+        renamed[0].name = String::from("load average");
+        let err = decode_snapshot(&bytes, &mut renamed).unwrap_err();
+        assert!(matches!(err, SnapshotError::Fingerprint { .. }));
+        assert!(err.to_string().contains("another chart config"), "{}", err);
+        assert!(renamed[0].sources[0].series_ref().as_vec().is_empty());
+    }
+
+    #[test]
+    fn it_fingerprints_the_data_of_the_charts() {
+        let charts = charts_with_samples();
+        // Cosmetic edits keep the snapshots.
+        let mut cosmetic = charts.clone();
+        cosmetic[0].position = Some(ChartLength2D::pixels(200., 10.));
+        cosmetic[0].dimensions = Some(ChartLength2D::pixels(300., 80.));
+        if let TimeSeriesSource::PrometheusTimeSeries(prom) = &mut cosmetic[0].sources[1] {
+            prom.alpha = 0.5;
+            prom.pull_interval = 60;
+        }
+        assert_eq!(fingerprint(&cosmetic), fingerprint(&charts));
+        // The queries and the order of the sources are part of the data.
+        let mut query = charts.clone();
+        if let TimeSeriesSource::PrometheusTimeSeries(prom) = &mut query[0].sources[1] {
+            prom.source = String::from("http://localhost:9090/api/v1/query?query=node_load5");
+        }
+        assert_ne!(fingerprint(&query), fingerprint(&charts));
+        let mut reordered = charts.clone();
+        reordered[0].sources.reverse();
+        assert_ne!(fingerprint(&reordered), fingerprint(&charts));
+    }
+
+    #[test]
+    fn it_writes_the_state_file_atomically() {
+        let dir = temp_dir("atomic");
+        let path = dir.join("charts.state");
+        fs::write(&path, b"previous").unwrap();
+        let charts = charts_with_samples();
+        let written = write_snapshot(&path, &charts, 20).unwrap();
+        assert_eq!(fs::metadata(&path).unwrap().len() as usize, written);
+        assert!(!temp_path(&path).exists());
+        let (header, _) = load_snapshot(&path, &mut empty_charts()).unwrap();
+        assert_eq!(header.created_epoch, 20);
+        // A failed rename leaves the target as it was and removes the temporary file.
+        let target = dir.join("directory.state");
+        fs::create_dir(&target).unwrap();
+        fs::write(target.join("keep"), b"keep").unwrap();
+        assert!(write_snapshot(&target, &charts, 21).is_err());
+        assert!(!temp_path(&target).exists());
+        assert_eq!(fs::read(target.join("keep")).unwrap(), b"keep");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn it_schedules_the_periodic_snapshots() {
+        let clock = MockClock::new(1000);
+        let mut schedule = SnapshotSchedule::new(Some(60));
+        assert!(schedule.is_enabled());
+        // The first check starts the schedule.
+        assert!(!schedule.due(clock.now()));
+        assert!(!schedule.due(clock.advance(59)));
+        assert!(schedule.due(clock.advance(1)));
+        assert!(!schedule.due(clock.advance(30)));
+        // A late check is written once and the next one is an interval after it.
+        assert!(schedule.due(clock.advance(100)));
+        assert!(!schedule.due(clock.advance(59)));
+        assert!(schedule.due(clock.advance(1)));
+        // A clock that went back is rescheduled instead of waiting for the old epoch.
+        clock.set(500);
+        assert!(!schedule.due(clock.now()));
+        assert!(schedule.due(clock.advance(60)));
+        // The snapshots are only written on shutdown by default.
+        for interval_s in [None, Some(0)] {
+            let mut schedule = SnapshotSchedule::new(interval_s);
+            assert!(!schedule.is_enabled());
+            assert!(!schedule.due(clock.advance(3600)));
+        }
+    }
+}
@@ -0,0 +1,83 @@
+//! `prelude` is the supported surface for embedding the charts, importing from here keeps the
+//! callers working when the modules behind it are reorganized.
+//!
+//! Stable, the items re-exported below:
+//! - the config of the charts and their series: ChartsConfig, TimeSeriesChart, TimeSeriesSource,
+//!   MissingValuesPolicy, SeriesTransform, DecorationsConfig.
+//! - the data of a series and the values read from it: TimeSeries, NumericReadout, TimelineReadout.
+//! - the handle to the async_coordinator, the requests it accepts and their replies:
+//!   TermChartsHandle, AsyncTask, WindowId, block_on_reply.
+//! - the sink of the events raised by the charts: EventListener, ChannelEventSink.
+//! - the vertices of a frame: Dashboard, ChartDrawBatch, DrawItem, DrawMode and the decorations.
+//!
+//! Not stable, these may change or be removed in any release:
+//! - the deep paths of the items, e.g. `charts::draw_batch::ChartDrawBatch`, only their path in the
+//!   prelude is kept.
+//! - the internals of the async_coordinator, the routing of the loaded items, the fetchers and the
+//!   raw Prometheus responses, none of these are reachable from outside of the crate.
+//! - the variants of AsyncTask, TimeSeriesSource, MissingValuesPolicy and DecorationTypes, these
+//!   enums are `#[non_exhaustive]` so that a new variant is not a breaking change, a `match` on
+//!   them needs a wildcard arm.
+//!
+//! Drawing the charts of a config without a terminal:
+//!
+//! ```no_run
+//! use std::path::Path;
+//! use std::sync::mpsc;
+//!
+//! use alacritty_terminal::charts::prelude::*;
+//! use alacritty_terminal::term::SizeInfo;
+//!
+//! let charts_config: ChartsConfig = load_charts_config(Path::new("charts.yml")).unwrap();
+//! let size_info = SizeInfo::new(1280., 200., 10., 20., 0., 0., false);
+//! let (events_tx, _events_rx) = mpsc::channel();
+//! let mut dashboard = Dashboard::start(charts_config, size_info, ChannelEventSink(events_tx));
+//! let batch: &ChartDrawBatch = dashboard.frame();
+//! for item in batch.items() {
+//!     let start = item.first * FLOATS_PER_VERTEX;
+//!     let vertices = &batch.vertices()[start..start + item.count * FLOATS_PER_VERTEX];
+//!     assert_eq!(vertices.len() % FLOATS_PER_VERTEX, 0);
+//! }
+//! ```
+//!
+//! The raw Prometheus responses are internal, they are parsed into a TimeSeries by the
+//! fetchers:
+//!
+//! ```compile_fail
+//! use alacritty_terminal::charts::prometheus::HTTPResponse;
+//! ```
+//!
+//! A new AsyncTask may be added in any release, a `match` without a wildcard arm is rejected:
+//!
+//! ```compile_fail
+//! use alacritty_terminal::charts::prelude::*;
+//!
+//! fn describe(task: &AsyncTask) -> &'static str {
+//!     match task {
+//!         AsyncTask::Shutdown => "shutdown",
+//!     }
+//! }
+//! ```
+
+pub use crate::async_utils::dashboard::{
+    load_charts_config, push_chart_draw_items, ChannelEventSink, Dashboard,
+};
+pub use crate::async_utils::{
+    block_on_reply, get_debug_overlay_data, set_charts_palette, AsyncTask, ChartsStatus,
+    ChartsVertexCache, ReplyError, WindowId,
+};
+pub use crate::charts::color::RgbExt;
+pub use crate::charts::color_ref::TERMINAL_COLOR_NAMES;
+pub use crate::charts::draw_batch::{ChartDrawBatch, DrawItem, DrawMode, FLOATS_PER_VERTEX};
+pub use crate::charts::migrate::{deserialize_migrated, migrated_charts_yaml};
+pub use crate::charts::prometheus::ChartError;
+pub use crate::charts::timeline::TimelineReadout;
+pub use crate::charts::{
+    ChartIndex, ChartRef, ChartSizeInfo, ChartsConfig, MissingValuesPolicy, NumericReadout,
+    SeriesIndex, SeriesTransform, TimeSeries, TimeSeriesChart, TimeSeriesSource,
+};
+pub use crate::decorations::{
+    DecorationLines, DecorationPoints, DecorationTriangles, DecorationTypes, DecorationsConfig,
+};
+pub use crate::event::EventListener;
+pub use crate::term::TermChartsHandle;
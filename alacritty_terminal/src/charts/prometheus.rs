@@ -1,13 +1,19 @@
 //! `Prometheus HTTP API` data structures
-use super::deserialize_rgb_from_str;
+use crate::charts::color_ref::ChartColor;
+use crate::charts::expr::Expression;
+use crate::charts::order::{stable_order_by_key, sub_series_key};
+use crate::charts::timeline::{RenderAs, TimelineConfig};
+use crate::charts::LinePattern;
+use crate::charts::SeriesTransform;
 use crate::charts::TimeSeries;
 use crate::charts::ValueCollisionPolicy;
 use log::*;
-use percent_encoding::{utf8_percent_encode, CONTROLS};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS, NON_ALPHANUMERIC};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
 use std::time::{Duration, UNIX_EPOCH};
-use vte::ansi::Rgb;
 // The below data structures for parsing something like:
 //  {
 //   "data": {
@@ -31,7 +37,7 @@ use vte::ansi::Rgb;
 /// `HTTPMatrixResult` contains Range Vectors, data is stored like this
 /// [[Epoch1, Metric1], [Epoch2, Metric2], ...]
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
-pub struct HTTPMatrixResult {
+pub(crate) struct HTTPMatrixResult {
     #[serde(rename = "metric")]
     pub labels: HashMap<String, String>,
     pub values: Vec<Vec<serde_json::Value>>,
@@ -40,7 +46,7 @@ pub struct HTTPMatrixResult {
 /// `HTTPVectorResult` contains Instant Vectors, data is stored like this
 /// [Epoch1, Metric1, Epoch2, Metric2, ...]
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
-pub struct HTTPVectorResult {
+pub(crate) struct HTTPVectorResult {
     #[serde(rename = "metric")]
     pub labels: HashMap<String, String>,
     pub value: Vec<serde_json::Value>,
@@ -50,7 +56,7 @@ pub struct HTTPVectorResult {
 /// https://prometheus.io/docs/prometheus/latest/querying/api/#expression-query-result-formats
 #[derive(Serialize, Deserialize, Debug, PartialEq, Eq, Clone)]
 #[serde(tag = "resultType")]
-pub enum HTTPResponseData {
+pub(crate) enum HTTPResponseData {
     #[serde(rename = "vector")]
     Vector { result: Vec<HTTPVectorResult> },
     #[serde(rename = "matrix")]
@@ -68,17 +74,85 @@ impl Default for HTTPResponseData {
 }
 
 #[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
-pub struct HTTPResponse {
+pub(crate) struct HTTPResponse {
     pub data: HTTPResponseData,
     pub status: String,
 }
 
+impl HTTPResponseData {
+    /// `result_type` returns the resultType of the response as sent by Prometheus
+    pub fn result_type(&self) -> &'static str {
+        match self {
+            HTTPResponseData::Vector { .. } => "vector",
+            HTTPResponseData::Matrix { .. } => "matrix",
+            HTTPResponseData::Scalar { .. } => "scalar",
+            HTTPResponseData::String { .. } => "string",
+        }
+    }
+
+    /// `sample_count` returns the number of samples in the response, a vector has one sample per
+    /// result, a matrix has one per value of each result and scalars and strings have one.
+    pub fn sample_count(&self) -> usize {
+        match self {
+            HTTPResponseData::Vector { result } => result.len(),
+            HTTPResponseData::Matrix { result } => {
+                result.iter().map(|series| series.values.len()).sum()
+            },
+            HTTPResponseData::Scalar { result } | HTTPResponseData::String { result } => {
+                usize::from(!result.is_empty())
+            },
+        }
+    }
+}
+
+/// `ResponseSummary` describes a Prometheus response in a bounded line for the logs, the samples
+/// are not included.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResponseSummary {
+    /// The status of the response, i.e. success
+    pub status: String,
+
+    /// The resultType of the response
+    pub result_type: &'static str,
+
+    /// The samples in the response
+    pub samples: usize,
+
+    /// The size of the body, unknown once the body has been dropped
+    pub bytes: Option<usize>,
+}
+
+impl ResponseSummary {
+    pub(crate) fn new(response: &HTTPResponse, bytes: Option<usize>) -> Self {
+        ResponseSummary {
+            status: response.status.clone(),
+            result_type: response.data.result_type(),
+            samples: response.data.sample_count(),
+            bytes,
+        }
+    }
+}
+
+impl fmt::Display for ResponseSummary {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "status: {}, {} with {} samples", self.status, self.result_type, self.samples)?;
+        if let Some(bytes) = self.bytes {
+            write!(f, ", {} bytes", bytes)?;
+        }
+        Ok(())
+    }
+}
+
 /// Transforms an serde_json::Value into an optional u64
 /// The epoch coming from is a float (epoch with millisecond),
-/// but our internal representation is u64
-pub fn prometheus_epoch_to_u64(input: &serde_json::Value) -> Option<u64> {
+/// but our internal representation is u64, in milliseconds for a granularity under a second and
+/// in seconds otherwise
+pub fn prometheus_epoch_to_u64(input: &serde_json::Value, granularity_ms: u64) -> Option<u64> {
     if input.is_number() {
         let input = input.as_f64()?;
+        if granularity_ms < 1000 {
+            return Some((input * 1000f64).round() as u64);
+        }
         return Some(input as u64);
     }
     None
@@ -107,7 +181,7 @@ pub struct PrometheusTimeSeries {
 
     /// The TimeSeries metrics storage
     #[serde(default)]
-    pub data: HTTPResponseData,
+    pub(crate) data: HTTPResponseData,
 
     /// The URL were Prometheus metrics may be acquaired
     #[serde(default)]
@@ -129,16 +203,208 @@ pub struct PrometheusTimeSeries {
     /// The time in secondso to get the metrics from Prometheus
     /// Shouldn't be faster than the scrape interval for the Target
     #[serde(default)]
-    #[serde(rename = "refresh")]
     pub pull_interval: usize,
 
-    /// The color of the TimeSeries
-    #[serde(deserialize_with = "deserialize_rgb_from_str", default)]
-    pub color: Rgb,
+    /// The color of the TimeSeries, a literal color or the name of a terminal color
+    #[serde(default)]
+    pub color: ChartColor,
 
     /// The transparency of the TimeSeries
     #[serde(default)]
     pub alpha: f32,
+
+    /// The line pattern of the series, solid when not set
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<LinePattern>,
+
+    /// Whether the series is drawn as a line or as a timeline band
+    #[serde(default)]
+    pub render_as: RenderAs,
+
+    /// The layout and the colors of the band when rendered as a Timeline
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeline: Option<TimelineConfig>,
+
+    /// A file with query_range responses loaded at startup, before polling begins, so that
+    /// charts open pre-populated, i.e. with an incident window
+    #[serde(default)]
+    pub initial_data_file: Option<PathBuf>,
+
+    /// The largest initial_data_file that will be loaded, defaults to MAX_RESPONSE_BODY_BYTES
+    #[serde(default)]
+    pub initial_data_max_bytes: Option<usize>,
+
+    /// The largest response body read from Prometheus, a bigger body is discarded while it is
+    /// read. Defaults to MAX_RESPONSE_BODY_BYTES
+    #[serde(default)]
+    pub max_response_bytes: Option<usize>,
+
+    /// The result entries matching the labels that are loaded from a response, the rest are
+    /// dropped. Defaults to DEFAULT_MAX_RESULT_ENTRIES
+    #[serde(default)]
+    pub max_result_entries: Option<usize>,
+
+    /// The matching result entries dropped because of max_result_entries
+    #[serde(skip)]
+    pub truncated_entries: usize,
+
+    /// Adds a series to the chart for each value of a label, i.e. one per mountpoint
+    #[serde(default)]
+    pub discover: Option<DiscoveryConfig>,
+
+    /// The label value of a series added by the discovery of another source
+    #[serde(skip)]
+    pub discovered: Option<DiscoveredSeries>,
+
+    /// An arithmetic expression applied to each sample as it is loaded, i.e. `x / 1073741824`
+    /// to turn bytes into GiB
+    #[serde(default)]
+    pub expr: Option<Expression>,
+
+    /// How the values are drawn, i.e. `rate` for a counter, the loaded values are kept
+    #[serde(default, skip_serializing_if = "SeriesTransform::is_none")]
+    pub transform: SeriesTransform,
+
+    /// The number of samples the expr turned into missing values, i.e. on division by zero
+    #[serde(skip)]
+    pub expr_missing_samples: usize,
+
+    /// Set once a response has been upserted into the series, it is never cleared so that the
+    /// chart is only shown as awaiting data at startup
+    #[serde(skip)]
+    pub ever_loaded: bool,
+}
+
+/// `DiscoveryConfig` defines how the values of a label are discovered and turned into series
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq)]
+pub struct DiscoveryConfig {
+    /// The label whose values are discovered
+    pub label: String,
+
+    /// The series selector sent as match[] to the label values API
+    #[serde(rename = "match")]
+    pub matcher: String,
+
+    /// The source URL of each discovered series, {value} is replaced with the label value
+    #[serde(deserialize_with = "deserialize_query_template")]
+    pub query_template: String,
+
+    /// The time in seconds between label values requests
+    #[serde(default = "default_discovery_interval")]
+    pub interval: u64,
+
+    /// The time in seconds a label value may be missing before its series stops being polled
+    #[serde(default = "default_discovery_grace_period")]
+    pub grace_period: u64,
+
+    /// The maximum number of discovered series, including those no longer polled
+    #[serde(default = "default_discovery_max_series")]
+    pub max_series: usize,
+}
+
+/// `QUERY_VALUE` is the set of characters percent-encoded in a value written into a query
+/// parameter, the unreserved characters and '/' are kept so that mountpoints stay readable.
+const QUERY_VALUE: &AsciiSet =
+    &NON_ALPHANUMERIC.remove(b'-').remove(b'.').remove(b'_').remove(b'~').remove(b'/');
+
+/// `escape_promql_string` escapes a value to be written inside a PromQL double-quoted string
+fn escape_promql_string(value: &str) -> String {
+    let mut res = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '\\' => res.push_str("\\\\"),
+            '"' => res.push_str("\\\""),
+            '\n' => res.push_str("\\n"),
+            c => res.push(c),
+        }
+    }
+    res
+}
+
+fn default_discovery_interval() -> u64 {
+    60
+}
+
+fn default_discovery_grace_period() -> u64 {
+    300
+}
+
+fn default_discovery_max_series() -> usize {
+    16
+}
+
+/// `deserialize_query_template` rejects templates without the {value} substitution point
+fn deserialize_query_template<'de, D>(de: D) -> Result<String, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let template = String::deserialize(de)?;
+    if template.contains("{value}") {
+        Ok(template)
+    } else {
+        Err(serde::de::Error::custom(format!(
+            "query_template '{}' does not contain {{value}}",
+            template
+        )))
+    }
+}
+
+impl DiscoveryConfig {
+    /// `source_for` returns the source URL of the series for a label value, the value is escaped
+    /// for a PromQL double-quoted string and percent-encoded for the query parameter
+    pub fn source_for(&self, value: &str) -> String {
+        let value = escape_promql_string(value);
+        let value = utf8_percent_encode(&value, QUERY_VALUE).to_string();
+        self.query_template.replace("{value}", &value)
+    }
+
+    /// `label_values_url` returns the label values API URL, on the same server as the
+    /// query_template
+    pub fn label_values_url(&self) -> Result<String, String> {
+        let (base, _) = self
+            .query_template
+            .split_once("/api/v1/")
+            .ok_or_else(|| format!("Unable to find /api/v1/ in '{}'", self.query_template))?;
+        let matcher = utf8_percent_encode(&self.matcher, QUERY_VALUE);
+        let source = format!("{}/api/v1/label/{}/values?match[]={}", base, self.label, matcher);
+        PrometheusTimeSeries::prepare_url(&source, 0)
+    }
+}
+
+/// `DiscoveredSeries` tracks a series added by the discovery of another source
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DiscoveredSeries {
+    /// The index of the source whose discovery added the series
+    pub parent: usize,
+
+    /// The label value of the series
+    pub value: String,
+
+    /// The epoch in which the label value was last returned
+    pub last_seen: u64,
+
+    /// The label value has been missing for the grace period, the series is no
+    /// longer polled and its samples age out
+    pub retired: bool,
+}
+
+/// `HTTPLabelValuesResponse` is returned by the label values API, i.e.:
+/// {"status": "success", "data": ["/", "/boot"]}
+#[derive(Serialize, Deserialize, Debug, Default, PartialEq, Eq, Clone)]
+pub(crate) struct HTTPLabelValuesResponse {
+    #[serde(default)]
+    pub data: Vec<String>,
+    pub status: String,
+}
+
+/// `parse_label_values` transforms a label values API body into the label values
+pub fn parse_label_values(body: &bytes::Bytes) -> Result<Vec<String>, ChartError> {
+    let res: HTTPLabelValuesResponse =
+        serde_json::from_slice(body).map_err(|err| ChartError::Parse(err.to_string()))?;
+    if res.status != "success" {
+        return Err(ChartError::Parse(format!("Unexpected status: {}", res.status)));
+    }
+    Ok(res.data)
 }
 
 impl Default for PrometheusTimeSeries {
@@ -155,8 +421,22 @@ impl Default for PrometheusTimeSeries {
             pull_interval: 15,
             data_type: String::from("vector"),
             required_labels: HashMap::new(),
-            color: Rgb::default(),
+            color: ChartColor::default(),
             alpha: 1.0,
+            pattern: None,
+            render_as: RenderAs::default(),
+            timeline: None,
+            initial_data_file: None,
+            initial_data_max_bytes: None,
+            max_response_bytes: None,
+            max_result_entries: None,
+            truncated_entries: 0usize,
+            discover: None,
+            discovered: None,
+            expr: None,
+            transform: SeriesTransform::None,
+            expr_missing_samples: 0usize,
+            ever_loaded: false,
         }
     }
 }
@@ -184,7 +464,7 @@ impl PrometheusTimeSeries {
             required_labels,
             ..PrometheusTimeSeries::default()
         };
-        match PrometheusTimeSeries::prepare_url(&res.source, res.series.metrics_capacity as u64) {
+        match PrometheusTimeSeries::prepare_url(&res.source, res.series.metrics_capacity() as u64) {
             Ok(url) => {
                 res.url = url;
                 Ok(res)
@@ -198,6 +478,67 @@ impl PrometheusTimeSeries {
         self.series.collision_policy = ValueCollisionPolicy::Overwrite;
     }
 
+    /// `resize_capacity` resizes the series to hold `capacity` samples, the url is prepared again
+    /// so that a query_range requests the new range
+    pub fn resize_capacity(&mut self, capacity: usize) {
+        self.series.resize_capacity(capacity);
+        let metrics_capacity = self.series.metrics_capacity() as u64;
+        match PrometheusTimeSeries::prepare_url(&self.source, metrics_capacity) {
+            Ok(url) => self.url = url,
+            Err(err) => {
+                error!("resize_capacity: Unable to prepare the url of '{}': {}", self.name, err)
+            },
+        }
+    }
+
+    /// `discovered_series` returns the series for a discovered label value, the name, pull
+    /// interval, labels, color and capacity are taken from this source.
+    pub fn discovered_series(
+        &self,
+        parent: usize,
+        value: &str,
+        now: u64,
+    ) -> Result<PrometheusTimeSeries, String> {
+        let discover = self.discover.as_ref().ok_or("Discovery is not configured")?;
+        let source = discover.source_for(value);
+        let url =
+            PrometheusTimeSeries::prepare_url(&source, self.series.metrics_capacity() as u64)?;
+        let mut required_labels = self.required_labels.clone();
+        required_labels.insert(discover.label.clone(), value.to_string());
+        let series = TimeSeries {
+            collision_policy: ValueCollisionPolicy::Overwrite,
+            missing_values_policy: self.series.missing_values_policy.clone(),
+            adaptive_capacity: self.series.adaptive_capacity,
+            max_capacity: self.series.max_capacity,
+            granularity_ms: self.series.granularity_ms,
+            ..TimeSeries::default()
+        }
+        .with_capacity(self.series.metrics_capacity());
+        Ok(PrometheusTimeSeries {
+            name: format!("{} {}", self.name, value),
+            series,
+            source,
+            url,
+            data_type: self.data_type.clone(),
+            required_labels,
+            pull_interval: self.pull_interval,
+            color: self.color.clone(),
+            alpha: self.alpha,
+            pattern: self.pattern,
+            render_as: self.render_as,
+            timeline: self.timeline.clone(),
+            expr: self.expr.clone(),
+            transform: self.transform,
+            discovered: Some(DiscoveredSeries {
+                parent,
+                value: value.to_string(),
+                last_seen: now,
+                retired: false,
+            }),
+            ..PrometheusTimeSeries::default()
+        })
+    }
+
     /// `prepare_url` loads self.source into a String
     /// It also adds a epoch-start and epoch-end to the
     /// URL depending on the metrics capacity
@@ -271,27 +612,59 @@ impl PrometheusTimeSeries {
         true
     }
 
+    /// `transform` applies the expr of the source to a loaded sample, samples the expr cannot be
+    /// evaluated for are counted and become missing values
+    pub fn transform(&mut self, value: Option<f64>) -> Option<f64> {
+        match (&self.expr, value) {
+            (Some(expr), Some(value)) => {
+                let res = expr.eval(value);
+                if res.is_none() {
+                    self.expr_missing_samples += 1;
+                    debug!(
+                        "transform: '{}' yields no value for {} in '{}'",
+                        expr.source, value, self.name
+                    );
+                }
+                res
+            },
+            _ => value,
+        }
+    }
+
     /// `load_prometheus_response` loads data from PrometheusResponse into
     /// the internal `series`, returns the number of items or an error
     /// string
-    pub fn load_prometheus_response(&mut self, res: HTTPResponse) -> Result<usize, String> {
+    pub(crate) fn load_prometheus_response(&mut self, res: HTTPResponse) -> Result<usize, String> {
         let mut loaded_items = 0;
+        // Only the first max_entries results that match the labels are loaded.
+        let max_entries = self.max_result_entries.unwrap_or(DEFAULT_MAX_RESULT_ENTRIES);
+        let mut matching_entries = 0usize;
+        let granularity_ms = self.series.granularity_ms;
         if res.status != "success" {
             return Ok(0usize);
         }
         debug!("load_prometheus_response: before upsert, series is: {:?}", self.series);
         debug!("load_prometheus_response: Checking data: {:?}", res.data);
         match res.data {
-            HTTPResponseData::Vector { result: results } => {
+            HTTPResponseData::Vector { result: mut results } => {
                 // labeled metrics returned as a 2 items vector:
                 // [ {metric: {l: X}, value: [epoch1,sample1]}
                 //   {metric: {l: Y}, value: [epoch2,sample2]} ]
+                // The sub-series are upserted in the order of their labels, not of the response.
+                stable_order_by_key(&mut results, |metric_data| {
+                    sub_series_key(&metric_data.labels)
+                });
                 for metric_data in results.iter() {
                     if self.match_metric_labels(&metric_data.labels) {
+                        matching_entries += 1;
+                        if matching_entries > max_entries {
+                            continue;
+                        }
                         // The result array is  [epoch, value, epoch, value]
                         if metric_data.value.len() == 2 {
-                            let opt_epoch = prometheus_epoch_to_u64(&metric_data.value[0]);
-                            let value = serde_json_to_num(&metric_data.value[1]);
+                            let opt_epoch =
+                                prometheus_epoch_to_u64(&metric_data.value[0], granularity_ms);
+                            let value = self.transform(serde_json_to_num(&metric_data.value[1]));
                             if let Some(epoch) = opt_epoch {
                                 loaded_items += self.series.upsert((epoch, value));
                             }
@@ -299,22 +672,31 @@ impl PrometheusTimeSeries {
                     }
                 }
             },
-            HTTPResponseData::Matrix { result: results } => {
+            HTTPResponseData::Matrix { result: mut results } => {
                 // labeled metrics returned as a matrix:
                 // [ {metric: {l: X}, value: [[epoch1,sample2],[...]]}
                 //   {metric: {l: Y}, value: [[epoch3,sample4],[...]]} ]
+                stable_order_by_key(&mut results, |metric_data| {
+                    sub_series_key(&metric_data.labels)
+                });
                 for metric_data in results.iter() {
                     if self.match_metric_labels(&metric_data.labels) {
+                        matching_entries += 1;
+                        if matching_entries > max_entries {
+                            continue;
+                        }
                         // The result array is  [epoch, value, epoch, value]
+                        let mut items = Vec::with_capacity(metric_data.values.len());
                         for item_value in &metric_data.values {
                             for item in item_value.chunks_exact(2) {
-                                let opt_epoch = prometheus_epoch_to_u64(&item[0]);
-                                let value = serde_json_to_num(&item[1]);
+                                let opt_epoch = prometheus_epoch_to_u64(&item[0], granularity_ms);
+                                let value = self.transform(serde_json_to_num(&item[1]));
                                 if let Some(epoch) = opt_epoch {
-                                    loaded_items += self.series.upsert((epoch, value));
+                                    items.push((epoch, value));
                                 }
                             }
                         }
+                        loaded_items += self.series.upsert_batch(&items);
                     }
                 }
             },
@@ -323,63 +705,217 @@ impl PrometheusTimeSeries {
                 // [epoch1,sample2]
                 // XXX: no example found for String.
                 if result.len() > 1 {
-                    let opt_epoch = prometheus_epoch_to_u64(&result[0]);
-                    let value = serde_json_to_num(&result[1]);
+                    let opt_epoch = prometheus_epoch_to_u64(&result[0], granularity_ms);
+                    let value = self.transform(serde_json_to_num(&result[1]));
                     if let Some(epoch) = opt_epoch {
                         loaded_items += self.series.upsert((epoch, value));
                     }
                 }
             },
         };
+        if matching_entries > max_entries {
+            self.truncated_entries += matching_entries - max_entries;
+            warn!(
+                "load_prometheus_response: '{}' matched {} result entries, only the first {} were \
+                 loaded. {}",
+                self.name, matching_entries, max_entries, TRUNCATION_HINT
+            );
+        }
         if loaded_items > 0 {
             self.series.calculate_stats();
+            self.ever_loaded = true;
         }
         debug!("load_prometheus_response: after upsert, series is: {:?}", self.series);
         Ok(loaded_items)
     }
+
+    /// `truncation_hint` describes the result entries dropped by max_result_entries, if any
+    pub fn truncation_hint(&self) -> Option<String> {
+        if self.truncated_entries == 0 {
+            return None;
+        }
+        Some(format!("{} result entries dropped. {}", self.truncated_entries, TRUNCATION_HINT))
+    }
+
+    /// `load_from_file` loads a file containing either one HTTPResponse JSON, as returned by
+    /// query_range, or newline delimited HTTPResponses into the internal `series`, returns the
+    /// number of items loaded. Epochs outside of the capacity window are handled by upsert.
+    pub fn load_from_file(&mut self, path: &Path) -> Result<usize, ChartError> {
+        let max_bytes = self.initial_data_max_bytes.unwrap_or(MAX_RESPONSE_BODY_BYTES);
+        let file_size = std::fs::metadata(path)?.len();
+        if file_size > max_bytes as u64 {
+            return Err(ChartError::FileTooLarge(file_size));
+        }
+        let body = std::fs::read(path)?;
+        let responses = match serde_json::from_slice::<HTTPResponse>(&body) {
+            Ok(res) => vec![res],
+            Err(_) => {
+                // Try one response per line instead.
+                let mut responses = vec![];
+                for (line_idx, line) in body.split(|byte| *byte == b'\n').enumerate() {
+                    if line.iter().all(u8::is_ascii_whitespace) {
+                        continue;
+                    }
+                    match serde_json::from_slice::<HTTPResponse>(line) {
+                        Ok(res) => responses.push(res),
+                        Err(err) => {
+                            return Err(ChartError::Parse(format!(
+                                "{}:{}: {}",
+                                path.display(),
+                                line_idx + 1,
+                                err
+                            )))
+                        },
+                    }
+                }
+                responses
+            },
+        };
+        let mut loaded_items = 0usize;
+        for res in responses {
+            loaded_items += self.load_prometheus_response(res).map_err(ChartError::Parse)?;
+        }
+        info!(
+            "load_from_file: Loaded {} items for '{}' from {}",
+            loaded_items,
+            self.name,
+            path.display()
+        );
+        Ok(loaded_items)
+    }
+}
+
+/// `ChartError` contains the reasons data for a chart could not be loaded
+#[derive(Debug)]
+pub enum ChartError {
+    /// The file could not be read
+    Io(std::io::Error),
+    /// The file is bigger than the configured limit
+    FileTooLarge(u64),
+    /// The contents are not valid responses
+    Parse(String),
+}
+
+impl From<std::io::Error> for ChartError {
+    fn from(err: std::io::Error) -> ChartError {
+        ChartError::Io(err)
+    }
+}
+
+/// The largest response body that will be read from Prometheus by default, anything bigger is
+/// discarded.
+pub const MAX_RESPONSE_BODY_BYTES: usize = 16 * 1024 * 1024;
+
+/// The result entries matching the labels of a series that are loaded from a response by default
+pub const DEFAULT_MAX_RESULT_ENTRIES: usize = 1000usize;
+
+/// The response bodies bigger than this are parsed on the blocking thread pool, so that a large
+/// response does not pin a tokio worker.
+pub const BLOCKING_PARSE_BYTES: usize = 1024 * 1024;
+
+/// What to do about a response with too many result entries
+pub const TRUNCATION_HINT: &str =
+    "Add label filters to the query or to the labels of the series to select fewer entries";
+
+/// `FetchError` contains the reasons a request to Prometheus may not return a body
+#[derive(Debug)]
+pub enum FetchError {
+    /// The request plus the body read did not finish before the deadline
+    Timeout(Duration),
+    /// The response body, of the size read so far, is bigger than the limit of the series
+    BodyTooLarge(usize),
+    /// The HTTP client returned an error
+    Http(reqwest::Error),
+}
+
+impl FetchError {
+    /// `is_timeout` returns true when the request did not complete in time, either because our
+    /// own deadline elapsed or because the HTTP client timed out.
+    pub fn is_timeout(&self) -> bool {
+        match self {
+            FetchError::Timeout(_) => true,
+            FetchError::BodyTooLarge(_) => false,
+            FetchError::Http(err) => err.is_timeout(),
+        }
+    }
+}
+
+impl From<reqwest::Error> for FetchError {
+    fn from(err: reqwest::Error) -> FetchError {
+        FetchError::Http(err)
+    }
 }
 
 /// `get_from_prometheus` is an async operation that returns an Optional
-/// PrometheusResponse
+/// PrometheusResponse. The whole request, including reading the body, is bounded by
+/// `request_timeout` so that a server that accepts the connection but never replies cannot
+/// stall the caller. The body is discarded as soon as it grows over `max_body_bytes`, by default
+/// MAX_RESPONSE_BODY_BYTES.
 pub async fn get_from_prometheus(
     url: String,
-    connect_timeout: Option<Duration>,
-) -> Result<bytes::Bytes, (String, reqwest::Error)> {
+    request_timeout: Option<Duration>,
+    max_body_bytes: Option<usize>,
+) -> Result<bytes::Bytes, (String, FetchError)> {
     debug!("get_from_prometheus: Loading Prometheus URL: {}", url);
     let url_copy = url.clone();
-    // use the timeout:
-    let client = match reqwest::Client::builder()
-        .timeout(connect_timeout.unwrap_or(Duration::from_secs(10)))
-        .build()
-    {
+    let request_timeout = request_timeout.unwrap_or(Duration::from_secs(10));
+    let client = match reqwest::Client::builder().connect_timeout(request_timeout).build() {
         Ok(res) => res,
         Err(err) => {
             error!("get_from_prometheus: Error creating client: {:?}", err);
-            return Err((url_copy, err));
+            return Err((url_copy, FetchError::Http(err)));
         },
     };
-    match client.get(url).send().await {
-        // Since we don't know the end yet, we can't simply stream
-        // the chunks as they arrive as we did with the above uppercase endpoint.
-        // So here we do `.await` on the future, waiting on concatenating the full body,
-        Ok(res) => match res.bytes().await {
-            Ok(body) => Ok(body),
-            Err(err) => Err((url_copy, err)),
+    let max_body_bytes = max_body_bytes.unwrap_or(MAX_RESPONSE_BODY_BYTES);
+    match tokio::time::timeout(request_timeout, fetch_body(client, url, max_body_bytes)).await {
+        Ok(Ok(body)) => Ok(body),
+        Ok(Err(FetchError::BodyTooLarge(size))) => {
+            warn!(
+                "get_from_prometheus: Discarded the response of '{}', its body of at least {} \
+                 bytes is over the limit of {} bytes",
+                url_copy, size, max_body_bytes
+            );
+            Err((url_copy, FetchError::BodyTooLarge(size)))
         },
-        Err(err) => {
+        Ok(Err(err)) => {
             info!("get_from_prometheus: Error loading '{:?}': '{:?}'", url_copy, err);
             Err((url_copy, err))
         },
+        Err(_elapsed) => Err((url_copy, FetchError::Timeout(request_timeout))),
+    }
+}
+
+/// `fetch_body` sends the request and reads the body in chunks, stopping as soon as the body
+/// grows over `max_body_bytes`.
+async fn fetch_body(
+    client: reqwest::Client,
+    url: String,
+    max_body_bytes: usize,
+) -> Result<bytes::Bytes, FetchError> {
+    let mut res = client.get(url).send().await?;
+    if let Some(content_length) = res.content_length() {
+        if content_length as usize > max_body_bytes {
+            return Err(FetchError::BodyTooLarge(content_length as usize));
+        }
+    }
+    let mut body = bytes::BytesMut::new();
+    while let Some(chunk) = res.chunk().await? {
+        if body.len() + chunk.len() > max_body_bytes {
+            return Err(FetchError::BodyTooLarge(body.len() + chunk.len()));
+        }
+        body.extend_from_slice(&chunk);
     }
+    Ok(body.freeze())
 }
 
 /// `parse_json` transforms a hyper body chunk into a possible
 /// PrometheusResponse, mostly used for testing
-pub fn parse_json(url: &str, body: &bytes::Bytes) -> Option<HTTPResponse> {
+pub(crate) fn parse_json(url: &str, body: &bytes::Bytes) -> Option<HTTPResponse> {
     let prom_res: Result<HTTPResponse, serde_json::Error> = serde_json::from_slice(body);
     match prom_res {
         Ok(v) => {
-            debug!("parse_json for '{}': returned JSON={:?}", url, v);
+            debug!("parse_json for '{}': {}", url, ResponseSummary::new(&v, Some(body.len())));
+            trace!("parse_json for '{}': returned JSON={:?}", url, v);
             Some(v)
         },
         Err(err) => {
@@ -389,6 +925,21 @@ pub fn parse_json(url: &str, body: &bytes::Bytes) -> Option<HTTPResponse> {
     }
 }
 
+/// `parse_body` parses a response body like `parse_json`, the bodies bigger than
+/// BLOCKING_PARSE_BYTES are parsed on the blocking thread pool instead of the polling task.
+pub(crate) async fn parse_body(url: String, body: bytes::Bytes) -> Option<HTTPResponse> {
+    if body.len() <= BLOCKING_PARSE_BYTES {
+        return parse_json(&url, &body);
+    }
+    match tokio::task::spawn_blocking(move || parse_json(&url, &body)).await {
+        Ok(res) => res,
+        Err(err) => {
+            error!("parse_body: The blocking parse did not finish: {:?}", err);
+            None
+        },
+    }
+}
+
 impl PartialEq<PrometheusTimeSeries> for PrometheusTimeSeries {
     fn eq(&self, other: &PrometheusTimeSeries) -> bool {
         self.series == other.series
@@ -400,11 +951,13 @@ impl PartialEq<PrometheusTimeSeries> for PrometheusTimeSeries {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::charts::prometheus::HTTPResponseData::Vector;
-    use crate::charts::MissingValuesPolicy;
+    use crate::charts::fixtures::{
+        labels, matrix_response, matrix_result, response_body, scalar_response, vector_response,
+        vector_result, wrapped_series,
+    };
     use crate::charts::TimeSeries;
     use crate::charts::TimeSeriesStats;
-    use crate::charts::UpsertType;
+    use crate::charts::DEFAULT_GRANULARITY_MS;
     fn init_log() {
         let _ = env_logger::builder().is_test(true).try_init();
     }
@@ -447,19 +1000,7 @@ mod tests {
         );
         assert!(test0_res.is_ok());
         let mut test0 = test0_res.unwrap();
-        // A json returned by prometheus
-        let test0_json = bytes::Bytes::from(
-            r#"
-            { "status":"success",
-              "data":{
-                "resultType":"scalar",
-                "result":[1558283674.829,"1"]
-              }
-            }"#,
-        );
-        let res0_json = parse_json(&String::from("http://test"), &test0_json);
-        assert!(res0_json.is_some());
-        let res0_load = test0.load_prometheus_response(res0_json.unwrap());
+        let res0_load = test0.load_prometheus_response(scalar_response(1558283674.829, 1.));
         // 1 items should have been loaded
         assert_eq!(res0_load, Ok(1usize));
         // This json is missing the value after the epoch
@@ -479,6 +1020,31 @@ mod tests {
         assert_eq!(res1_load, Ok(0usize));
     }
 
+    #[test]
+    fn it_keeps_the_milliseconds_of_the_epochs() {
+        let epoch = serde_json::Value::from(1558283674.829);
+        assert_eq!(prometheus_epoch_to_u64(&epoch, DEFAULT_GRANULARITY_MS), Some(1558283674));
+        assert_eq!(prometheus_epoch_to_u64(&epoch, 250), Some(1558283674829));
+        let mut test0 = PrometheusTimeSeries::new(
+            String::from("http://localhost:9090/api/v1/query?query=1"),
+            15,
+            String::from("scalar"),
+            HashMap::new(),
+        )
+        .unwrap();
+        test0.series.granularity_ms = 250;
+        let loaded = test0.load_prometheus_response(scalar_response(1558283674.829, 1.));
+        assert_eq!(loaded, Ok(1usize));
+        let loaded = test0.load_prometheus_response(scalar_response(1558283675.329, 2.));
+        assert_eq!(loaded, Ok(1usize));
+        // The epochs are truncated to the granularity and the gap is filled at the same step.
+        assert_eq!(test0.series.as_vec(), vec![
+            (1558283674750, Some(1.)),
+            (1558283675000, None),
+            (1558283675250, Some(2.)),
+        ]);
+    }
+
     #[test]
     fn it_loads_prometheus_matrix() {
         init_log();
@@ -492,33 +1058,20 @@ mod tests {
         let mut test0 = test0_res.unwrap();
         // Let's create space for 15, but we will receive 11 records:
         test0.series = test0.series.with_capacity(15usize);
-        // A json returned by prometheus
-        let test0_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "matrix",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "node_load1",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "values": [
-                        [1558253469,"1.69"],[1558253470,"1.70"],[1558253471,"1.71"],
-                        [1558253472,"1.72"],[1558253473,"1.73"],[1558253474,"1.74"],
-                        [1558253475,"1.75"],[1558253476,"1.76"],[1558253477,"1.77"],
-                        [1558253478,"1.78"],[1558253479,"1.79"]]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res0_json = parse_json(&String::from("http://test"), &test0_json);
-        assert!(res0_json.is_some());
-        let res0_load = test0.load_prometheus_response(res0_json.unwrap());
+        // The load average of each epoch is 1.<last 2 digits of the epoch>
+        let node_load1 = |epochs: std::ops::RangeInclusive<u64>| {
+            let samples: Vec<(u64, f64)> =
+                epochs.map(|epoch| (epoch, (epoch - 1558253300) as f64 / 100.)).collect();
+            matrix_response(vec![matrix_result(
+                labels(&[
+                    ("__name__", "node_load1"),
+                    ("instance", "localhost:9100"),
+                    ("job", "node_exporter"),
+                ]),
+                &samples,
+            )])
+        };
+        let res0_load = test0.load_prometheus_response(node_load1(1558253469..=1558253479));
         // 11 items should have been loaded in the node_exporter
         assert_eq!(res0_load, Ok(11usize));
         debug!("it_loads_prometheus_matrix NOTVEC: {:?}", test0.series.metrics);
@@ -528,43 +1081,17 @@ mod tests {
         assert_eq!(loaded_data[1], (1558253470, Some(1.70f64)));
         assert_eq!(loaded_data[5], (1558253474, Some(1.74f64)));
         // Let's add one more item and subtract one item from the array
-        let test1_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "matrix",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "node_load1",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "values": [
-                        [1558253471,"1.71"],[1558253472,"1.72"],[1558253473,"1.73"],
-                        [1558253474,"1.74"],[1558253475,"1.75"],[1558253476,"1.76"],
-                        [1558253477,"1.77"],[1558253478,"1.78"],[1558253479,"1.79"],
-                        [1558253480,"1.80"],[1558253481,"1.81"],[1558253482,"1.82"],
-                        [1558253483,"1.83"],[1558253484,"1.84"],[1558253485,"1.85"],
-                        [1558253486,"1.86"]]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res1_json = parse_json(&String::from("http://test"), &test1_json);
-        assert!(res1_json.is_some());
+        let res1 = node_load1(1558253471..=1558253486);
         debug!("it_loads_prometheus_matrix NOTVEC: {:?}", test0.series.metrics);
         let loaded_data = test0.series.as_vec();
         debug!("it_loads_prometheus_matrix Data: {:?}", loaded_data);
-        let res1_load = test0.load_prometheus_response(res1_json.clone().unwrap());
+        let res1_load = test0.load_prometheus_response(res1.clone());
         // 7 items should have been loaded in the node_exporter, 9 already existed
         // 2 should have been rotated
         assert_eq!(res1_load, Ok(7usize));
 
         // Let's test reloading the data:
-        let res1_load = test0.load_prometheus_response(res1_json.unwrap());
+        let res1_load = test0.load_prometheus_response(res1);
         // Now 0 records should have been loaded:
         assert_eq!(res1_load, Ok(0usize));
         debug!("it_loads_prometheus_matrix NOTVEC: {:?}", test0.series.metrics);
@@ -602,6 +1129,91 @@ mod tests {
         assert_eq!(res2_load, Ok(0usize));
     }
 
+    #[test]
+    fn it_loads_prometheus_response_files() {
+        init_log();
+        let fixture = PathBuf::from(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/charts/query_range_matrix.json"
+        ));
+        let mut test = PrometheusTimeSeries::default();
+        test.series = test.series.with_capacity(15usize);
+        assert_eq!(test.load_from_file(&fixture).unwrap(), 10usize);
+        let loaded_data = test.series.as_vec();
+        assert_eq!(loaded_data.len(), 10usize);
+        assert_eq!(loaded_data[0], (1558253469, Some(1.69f64)));
+        assert_eq!(loaded_data[9], (1558253478, Some(1.78f64)));
+        // Live data merges with the loaded data, the collision policy is Overwrite.
+        test.series.upsert((1558253478, Some(3.0f64)));
+        test.series.upsert((1558253480, Some(2.0f64)));
+        let loaded_data = test.series.as_vec();
+        assert_eq!(loaded_data.len(), 12usize);
+        assert_eq!(loaded_data[9], (1558253478, Some(3.0f64)));
+        assert_eq!(loaded_data[10], (1558253479, None));
+        assert_eq!(loaded_data[11], (1558253480, Some(2.0f64)));
+        assert!(test.series.sanity_check());
+        // Newline delimited responses, only what fits in the capacity is kept.
+        let response = std::fs::read_to_string(&fixture).unwrap();
+        let response: HTTPResponse = serde_json::from_str(&response).unwrap();
+        let response = serde_json::to_string(&response).unwrap();
+        let ndjson = std::env::temp_dir()
+            .join(format!("chartacritty-prometheus-{}.ndjson", std::process::id()));
+        std::fs::write(&ndjson, format!("{}\n\n{}\n", response, response)).unwrap();
+        let mut small = PrometheusTimeSeries::default();
+        small.series = small.series.with_capacity(5usize);
+        assert_eq!(small.load_from_file(&ndjson).unwrap(), 10usize);
+        assert_eq!(small.series.as_vec()[0], (1558253474, Some(1.74f64)));
+        assert_eq!(small.series.as_vec()[4], (1558253478, Some(1.78f64)));
+        // Files over the limit are rejected.
+        small.initial_data_max_bytes = Some(10usize);
+        assert!(matches!(small.load_from_file(&ndjson), Err(ChartError::FileTooLarge(_))));
+        // Invalid lines report where they are.
+        std::fs::write(&ndjson, format!("{}\nInternal Server Error\n", response)).unwrap();
+        let mut invalid = PrometheusTimeSeries::default();
+        match invalid.load_from_file(&ndjson) {
+            Err(ChartError::Parse(err)) => assert!(err.contains(":2:")),
+            res => panic!("Expected a parse error, got: {:?}", res),
+        }
+        std::fs::remove_file(&ndjson).unwrap();
+        assert!(matches!(invalid.load_from_file(&ndjson), Err(ChartError::Io(_))));
+    }
+
+    #[test]
+    fn it_transforms_the_loaded_samples() {
+        init_log();
+        let yaml = r#"
+name: memory
+source: http://localhost:9090/api/v1/query?query=node_memory_MemAvailable_bytes
+expr: x / 1073741824
+"#;
+        let mut test: PrometheusTimeSeries = serde_yaml::from_str(yaml).unwrap();
+        test.series = test.series.with_capacity(5usize);
+        let response = matrix_response(vec![matrix_result(
+            labels(&[("__name__", "node_memory_MemAvailable_bytes")]),
+            &[(1558253469, 1073741824.), (1558253470, 2147483648.), (1558253471, 536870912.)],
+        )]);
+        assert_eq!(test.load_prometheus_response(response), Ok(3usize));
+        assert_eq!(
+            test.series.as_vec(),
+            vec![(1558253469, Some(1f64)), (1558253470, Some(2f64)), (1558253471, Some(0.5f64))]
+        );
+        assert_eq!(test.series.stats.max, 2f64);
+        assert_eq!(test.series.stats.sum, 3.5f64);
+        // Samples that cannot be transformed are missing and counted.
+        test.expr = Some(Expression::parse("1 / x").unwrap());
+        assert_eq!(test.transform(Some(0f64)), None);
+        assert_eq!(test.transform(Some(4f64)), Some(0.25f64));
+        assert_eq!(test.transform(None), None);
+        assert_eq!(test.expr_missing_samples, 1usize);
+        // Invalid expressions are rejected with the config.
+        let yaml = r#"
+name: memory
+expr: x / * 2
+"#;
+        let err = serde_yaml::from_str::<PrometheusTimeSeries>(yaml).unwrap_err();
+        assert!(err.to_string().contains("at position 4"), "{}", err);
+    }
+
     #[test]
     fn it_calculates_stats() {
         let metric_labels = HashMap::new();
@@ -613,88 +1225,31 @@ mod tests {
         );
         assert!(test0_res.is_ok());
         let mut test0 = test0_res.unwrap();
-        let test1_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "matrix",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "node_load1",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "values": [
-                      [1566918913,"4.5"],
-                      [1566918914,"4.5"],
-                      [1566918915,"4.5"],
-                      [1566918916,"4.5"],
-                      [1566918917,"4.5"],
-                      [1566918918,"4.5"],
-                      [1566918919,"4.25"],
-                      [1566918920,"4.25"],
-                      [1566918921,"4.25"],
-                      [1566918922,"4.25"],
-                      [1566918923,"4.25"],
-                      [1566918924,"4.25"],
-                      [1566918925,"4"],
-                      [1566918926,"4"],
-                      [1566918927,"4"],
-                      [1566918928,"4"],
-                      [1566918929,"4"],
-                      [1566918930,"4"],
-                      [1566918931,"4.75"],
-                      [1566918932,"4.75"],
-                      [1566918933,"4.75"],
-                      [1566918934,"4.75"],
-                      [1566918935,"4.75"],
-                      [1566918936,"4.75"]
-                    ]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res1_json = parse_json(&String::from("http://test"), &test1_json);
-        assert!(res1_json.is_some());
-        let res1_load = test0.load_prometheus_response(res1_json.unwrap());
-        // 1 items should have been loaded
+        // The load average changes every 6 seconds.
+        let samples: Vec<(u64, f64)> = (0..24u64)
+            .map(|idx| (1566918913 + idx, [4.5, 4.25, 4., 4.75][idx as usize / 6]))
+            .collect();
+        let response = matrix_response(vec![matrix_result(
+            labels(&[
+                ("__name__", "node_load1"),
+                ("instance", "localhost:9100"),
+                ("job", "node_exporter"),
+            ]),
+            &samples,
+        )]);
+        let res1_load = test0.load_prometheus_response(response);
         assert_eq!(res1_load, Ok(24usize));
         assert_eq!(
             test0.series.as_vec(),
-            vec![
-                (1566918913, Some(4.5)),
-                (1566918914, Some(4.5)),
-                (1566918915, Some(4.5)),
-                (1566918916, Some(4.5)),
-                (1566918917, Some(4.5)),
-                (1566918918, Some(4.5)),
-                (1566918919, Some(4.25)),
-                (1566918920, Some(4.25)),
-                (1566918921, Some(4.25)),
-                (1566918922, Some(4.25)),
-                (1566918923, Some(4.25)),
-                (1566918924, Some(4.25)),
-                (1566918925, Some(4.)),
-                (1566918926, Some(4.)),
-                (1566918927, Some(4.)),
-                (1566918928, Some(4.)),
-                (1566918929, Some(4.)),
-                (1566918930, Some(4.)),
-                (1566918931, Some(4.75)),
-                (1566918932, Some(4.75)),
-                (1566918933, Some(4.75)),
-                (1566918934, Some(4.75)),
-                (1566918935, Some(4.75)),
-                (1566918936, Some(4.75))
-            ]
+            samples.iter().map(|(epoch, value)| (*epoch, Some(*value))).collect::<Vec<_>>()
         );
         test0.series.calculate_stats();
         let test0_sum = 4.5 * 6. + 4.25 * 6. + 4. * 6. + 4.75 * 6.;
+        // The values are 0.125 and 0.375 away from their mean, 4.375.
+        let stats = test0.series.stats;
+        assert!((stats.stddev - 0.078125f64.sqrt()).abs() < 1e-12);
         assert_eq!(
-            test0.series.stats,
+            TimeSeriesStats { stddev: 0., ..stats },
             TimeSeriesStats {
                 first: 4.5,
                 last: 4.75,
@@ -705,6 +1260,8 @@ mod tests {
                 sum: test0_sum,
                 avg: test0_sum / 24.,
                 last_epoch: 1566918936,
+                missing_count: 0,
+                stddev: 0.,
             }
         );
     }
@@ -721,43 +1278,31 @@ mod tests {
         );
         assert!(test0_res.is_ok());
         let mut test0 = test0_res.unwrap();
-        // A json returned by prometheus
-        let test0_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "vector",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9090",
-                      "job": "prometheus"
-                    },
-                    "value": [
-                      1557571137.732,
-                      "1"
-                    ]
-                  },
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "value": [
-                      1557571138.732,
-                      "1"
-                    ]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res0_json = parse_json(&String::from("http://test"), &test0_json);
-        assert!(res0_json.is_some());
-        let res0_load = test0.load_prometheus_response(res0_json.unwrap());
+        // The up metric of the Prometheus Server at an epoch and of the Node Exporter a second
+        // later
+        let up = |epoch: f64| {
+            vector_response(vec![
+                vector_result(
+                    labels(&[
+                        ("__name__", "up"),
+                        ("instance", "localhost:9090"),
+                        ("job", "prometheus"),
+                    ]),
+                    epoch,
+                    1.,
+                ),
+                vector_result(
+                    labels(&[
+                        ("__name__", "up"),
+                        ("instance", "localhost:9100"),
+                        ("job", "node_exporter"),
+                    ]),
+                    epoch + 1.,
+                    1.,
+                ),
+            ])
+        };
+        let res0_load = test0.load_prometheus_response(up(1557571137.732));
         // 2 items should have been loaded, one for Prometheus Server and the
         // other for Prometheus Node Exporter
         assert_eq!(res0_load, Ok(2usize));
@@ -766,47 +1311,11 @@ mod tests {
             vec![(1557571137u64, Some(1.)), (1557571138u64, Some(1.))]
         );
 
-        let test1_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "vector",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9090",
-                      "job": "prometheus"
-                    },
-                    "value": [
-                      1557571139.732,
-                      "1"
-                    ]
-                  },
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "value": [
-                      1557571140.732,
-                      "1"
-                    ]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res1_json = parse_json(&String::from("http://test"), &test1_json);
-        assert!(res1_json.is_some());
-
         // Make the labels match only one instance
         metric_labels.insert(String::from("job"), String::from("prometheus"));
         metric_labels.insert(String::from("instance"), String::from("localhost:9090"));
         test0.required_labels = metric_labels.clone();
-        let res1_load = test0.load_prometheus_response(res1_json.unwrap());
+        let res1_load = test0.load_prometheus_response(up(1557571139.732));
         // Only the prometheus: localhost:9090 should have been loaded with epoch 1557571139
         assert_eq!(res1_load, Ok(1usize));
         assert_eq!(
@@ -814,45 +1323,10 @@ mod tests {
             vec![(1557571137u64, Some(1.)), (1557571138u64, Some(1.)), (1557571139u64, Some(1.))]
         );
 
-        let test2_json = bytes::Bytes::from(
-            r#"
-            {
-              "status": "success",
-              "data": {
-                "resultType": "vector",
-                "result": [
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9090",
-                      "job": "prometheus"
-                    },
-                    "value": [
-                      1557571141.732,
-                      "1"
-                    ]
-                  },
-                  {
-                    "metric": {
-                      "__name__": "up",
-                      "instance": "localhost:9100",
-                      "job": "node_exporter"
-                    },
-                    "value": [
-                      1557571142.732,
-                      "1"
-                    ]
-                  }
-                ]
-              }
-            }"#,
-        );
-        let res2_json = parse_json(&String::from("http://test"), &test2_json);
-        assert!(res2_json.is_some());
         // Make the labels not match
         metric_labels.insert(String::from("__name__"), String::from("down"));
         test0.required_labels = metric_labels;
-        let res2_load = test0.load_prometheus_response(res2_json.unwrap());
+        let res2_load = test0.load_prometheus_response(up(1557571141.732));
         assert_eq!(res2_load, Ok(0usize));
         assert_eq!(
             test0.series.as_vec(),
@@ -913,7 +1387,7 @@ mod tests {
         );
         assert!(test1_res.is_ok());
         let test1 = test1_res.unwrap();
-        let res1_get = tokio::try_join!(get_from_prometheus(test1.url.clone(), None));
+        let res1_get = tokio::try_join!(get_from_prometheus(test1.url.clone(), None, None));
         println!("get_from_prometheus: {:?}", res1_get);
         assert!(res1_get.is_ok());
         if let Some(prom_response) = parse_json(&String::from("http://test"), &res1_get.unwrap().0)
@@ -945,467 +1419,362 @@ mod tests {
     #[test]
     fn it_does_not_duplicate_epochs() {
         init_log();
-        let test_labels = HashMap::new();
+        let metrics = (1571511822..=1571511826).map(|epoch| (epoch, Some(1.8359375))).collect();
         let mut test = PrometheusTimeSeries {
             name: String::from("load average 1 min"),
             series: TimeSeries {
-                metrics: vec![
-                    (1571511822, Some(1.8359375)),
-                    (1571511823, Some(1.8359375)),
-                    (1571511824, Some(1.8359375)),
-                    (1571511825, Some(1.8359375)),
-                    (1571511826, Some(1.8359375)),
-                ],
                 metrics_capacity: 30,
-                stats: TimeSeriesStats {
-                    max: 17179869184.0,
-                    min: 17179869184.0,
-                    avg: 17179869184.0,
-                    first: 17179869184.0,
-                    last: 17179869184.0,
-                    count: 5,
-                    sum: 1202590842880.0,
-                    is_dirty: false,
-                    last_epoch: 1571511826,
-                },
                 collision_policy: ValueCollisionPolicy::Overwrite,
-                missing_values_policy: MissingValuesPolicy::Zero,
-                first_idx: 0,
-                active_items: 5,
-                prev_snapshot: vec![],
-                prev_value: (1604568602, Some(6.0)),
-                upsert_type: UpsertType::NewEpoch,
-            },
-            data: Vector {
-                result: vec![HTTPVectorResult { labels: test_labels.clone(), value: vec![] }],
+                ..wrapped_series(metrics, 0, 5)
             },
-            source: String::from(
-                "http://localhost:9090/api/v1/query_range?query=node_memory_bytes_total",
-            ),
-            url: String::from("/"),
-            data_type: String::from(""),
-            required_labels: test_labels,
-            pull_interval: 15,
-            color: Rgb { r: 207, g: 102, b: 121 },
-            alpha: 1.0,
+            ..PrometheusTimeSeries::default()
         };
         // This should result in adding 15 more items
-        let test1_json = bytes::Bytes::from(
-            r#"{
-              "status":"success",
-              "data":{
-                "resultType":"matrix",
-                "result":[{
-                  "metric":{
-                    "__name__":"node_load1",
-                    "instance":"localhost:9100",
-                    "job":"node_exporter"
-                  },
-                  "values":[
-                    [1571511822,"1.8359322"],
-                    [1571511823,"1.8359323"],
-                    [1571511824,"1.8359324"],
-                    [1571511825,"1.8359325"],
-                    [1571511826,"1.8359326"],
-                    [1571511827,"1.8359327"],
-                    [1571511828,"1.8359328"],
-                    [1571511829,"1.8359329"],
-                    [1571511830,"1.8359330"],
-                    [1571511831,"1.8359331"]
-                  ]
-                }]
-              }
-          }"#,
-        );
-        let res1_json = parse_json(&String::from("http://test"), &test1_json);
-        assert!(res1_json.is_some());
-        let res1_load = test.load_prometheus_response(res1_json.unwrap());
+        let samples: Vec<(u64, f64)> = (1571511822..=1571511831)
+            .map(|epoch| (epoch, (epoch - 1553152500) as f64 / 1e7))
+            .collect();
+        let response = matrix_response(vec![matrix_result(
+            labels(&[
+                ("__name__", "node_load1"),
+                ("instance", "localhost:9100"),
+                ("job", "node_exporter"),
+            ]),
+            &samples,
+        )]);
+        let res1_load = test.load_prometheus_response(response);
         // 5 items should have been loaded, 5 already existed.
         assert_eq!(res1_load, Ok(5usize));
         assert_eq!(test.series.active_items, 10usize);
         assert_eq!(
             test.series.as_vec(),
-            vec![
-                (1571511822, Some(1.8359322)),
-                (1571511823, Some(1.8359323)),
-                (1571511824, Some(1.8359324)),
-                (1571511825, Some(1.8359325)),
-                (1571511826, Some(1.8359326)),
-                (1571511827, Some(1.8359327)),
-                (1571511828, Some(1.8359328)),
-                (1571511829, Some(1.8359329)),
-                (1571511830, Some(1.8359330)),
-                (1571511831, Some(1.8359331))
-            ]
+            samples.iter().map(|(epoch, value)| (*epoch, Some(*value))).collect::<Vec<_>>()
         );
     }
 
     #[test]
     fn it_does_not_lose_synchrony() {
         init_log();
-        let test_labels = HashMap::new();
+        // The buffer is full and its only active item is out of order with the rest.
+        let metrics = vec![
+            (1583092654, None),
+            (1583091367, Some(5.5908203125)),
+            (1583091368, Some(5.5908203125)),
+            (1583091369, Some(5.5908203125)),
+            (1583091370, Some(5.5908203125)),
+            (1583091371, Some(5.5908203125)),
+            (1583091372, Some(5.5908203125)),
+            (1583091373, Some(5.5908203125)),
+            (1583091374, Some(5.5908203125)),
+            (1583091375, Some(5.5908203125)),
+            (1583091376, Some(5.5908203125)),
+            (1583091377, Some(5.5908203125)),
+            (1583091378, Some(5.3662109375)),
+            (1583091379, Some(5.3662109375)),
+            (1583091380, Some(5.3662109375)),
+            (1583091381, Some(5.3662109375)),
+            (1583091382, Some(5.3662109375)),
+            (1583091383, Some(5.3662109375)),
+            (1583091384, Some(5.3662109375)),
+            (1583091385, Some(5.3662109375)),
+            (1583091386, Some(5.3662109375)),
+            (1583091387, Some(5.3662109375)),
+            (1583091388, Some(5.3662109375)),
+            (1583091389, Some(5.3662109375)),
+            (1583091390, Some(5.3662109375)),
+            (1583091391, Some(5.3662109375)),
+            (1583091392, Some(5.3662109375)),
+            (1583091393, Some(5.427734375)),
+            (1583091394, Some(5.427734375)),
+            (1583091395, Some(5.427734375)),
+            (1583091396, Some(5.427734375)),
+            (1583091397, Some(5.427734375)),
+            (1583091398, Some(5.427734375)),
+            (1583091399, Some(5.427734375)),
+            (1583091400, Some(5.427734375)),
+            (1583091401, Some(5.427734375)),
+            (1583091402, Some(5.427734375)),
+            (1583091403, Some(5.427734375)),
+            (1583091404, Some(5.427734375)),
+            (1583091405, Some(5.427734375)),
+            (1583091406, Some(5.427734375)),
+            (1583091407, Some(5.427734375)),
+            (1583091408, Some(5.22607421875)),
+            (1583091409, Some(5.22607421875)),
+            (1583091410, Some(5.22607421875)),
+            (1583091411, Some(5.22607421875)),
+            (1583091412, Some(5.22607421875)),
+            (1583091413, Some(5.22607421875)),
+            (1583091414, Some(5.22607421875)),
+            (1583091415, Some(5.22607421875)),
+            (1583091416, Some(5.22607421875)),
+            (1583091417, Some(5.22607421875)),
+            (1583091418, Some(5.22607421875)),
+            (1583091419, Some(5.22607421875)),
+            (1583091420, Some(5.22607421875)),
+            (1583091421, Some(5.22607421875)),
+            (1583091422, Some(5.22607421875)),
+            (1583091423, Some(5.103515625)),
+            (1583091424, Some(5.103515625)),
+            (1583091425, Some(5.103515625)),
+            (1583091426, Some(5.103515625)),
+            (1583091427, Some(5.103515625)),
+            (1583091428, Some(5.103515625)),
+            (1583091429, Some(5.103515625)),
+            (1583091430, Some(5.103515625)),
+            (1583091431, Some(5.103515625)),
+            (1583091432, Some(5.103515625)),
+            (1583091433, Some(5.103515625)),
+            (1583091434, Some(5.103515625)),
+            (1583091435, Some(5.103515625)),
+            (1583091436, Some(5.103515625)),
+            (1583091437, Some(5.103515625)),
+            (1583091438, Some(5.08056640625)),
+            (1583091439, Some(5.08056640625)),
+            (1583091440, Some(5.08056640625)),
+            (1583091141, Some(8.09912109375)),
+            (1583091142, Some(8.09912109375)),
+            (1583091143, Some(8.09912109375)),
+            (1583091144, Some(8.09912109375)),
+            (1583091145, Some(8.09912109375)),
+            (1583091146, Some(8.09912109375)),
+            (1583091147, Some(8.09912109375)),
+            (1583091148, Some(8.09912109375)),
+            (1583091149, Some(8.09912109375)),
+            (1583091150, Some(8.09912109375)),
+            (1583091151, Some(8.09912109375)),
+            (1583091152, Some(8.09912109375)),
+            (1583091153, Some(7.78271484375)),
+            (1583091154, Some(7.78271484375)),
+            (1583091155, Some(7.78271484375)),
+            (1583091156, Some(7.78271484375)),
+            (1583091157, Some(7.78271484375)),
+            (1583091158, Some(7.78271484375)),
+            (1583091159, Some(7.78271484375)),
+            (1583091160, Some(7.78271484375)),
+            (1583091161, Some(7.78271484375)),
+            (1583091162, Some(7.78271484375)),
+            (1583091163, Some(7.78271484375)),
+            (1583091164, Some(7.78271484375)),
+            (1583091165, Some(7.78271484375)),
+            (1583091166, Some(7.78271484375)),
+            (1583091167, Some(7.78271484375)),
+            (1583091168, Some(7.49853515625)),
+            (1583091169, Some(7.49853515625)),
+            (1583091170, Some(7.49853515625)),
+            (1583091171, Some(7.49853515625)),
+            (1583091172, Some(7.49853515625)),
+            (1583091173, Some(7.49853515625)),
+            (1583091174, Some(7.49853515625)),
+            (1583091175, Some(7.49853515625)),
+            (1583091176, Some(7.49853515625)),
+            (1583091177, Some(7.49853515625)),
+            (1583091178, Some(7.49853515625)),
+            (1583091179, Some(7.49853515625)),
+            (1583091180, Some(7.49853515625)),
+            (1583091181, Some(7.49853515625)),
+            (1583091182, Some(7.49853515625)),
+            (1583091183, Some(7.16357421875)),
+            (1583091184, Some(7.16357421875)),
+            (1583091185, Some(7.16357421875)),
+            (1583091186, Some(7.16357421875)),
+            (1583091187, Some(7.16357421875)),
+            (1583091188, Some(7.16357421875)),
+            (1583091189, Some(7.16357421875)),
+            (1583091190, Some(7.16357421875)),
+            (1583091191, Some(7.16357421875)),
+            (1583091192, Some(7.16357421875)),
+            (1583091193, Some(7.16357421875)),
+            (1583091194, Some(7.16357421875)),
+            (1583091195, Some(7.16357421875)),
+            (1583091196, Some(7.16357421875)),
+            (1583091197, Some(7.16357421875)),
+            (1583091198, Some(6.9267578125)),
+            (1583091199, Some(6.9267578125)),
+            (1583091200, Some(6.9267578125)),
+            (1583091201, Some(6.9267578125)),
+            (1583091202, Some(6.9267578125)),
+            (1583091203, Some(6.9267578125)),
+            (1583091204, Some(6.9267578125)),
+            (1583091205, Some(6.9267578125)),
+            (1583091206, Some(6.9267578125)),
+            (1583091207, Some(6.9267578125)),
+            (1583091208, Some(6.9267578125)),
+            (1583091209, Some(6.9267578125)),
+            (1583091210, Some(6.9267578125)),
+            (1583091211, Some(6.9267578125)),
+            (1583091212, Some(6.9267578125)),
+            (1583091213, Some(6.701171875)),
+            (1583091214, Some(6.701171875)),
+            (1583091215, Some(6.701171875)),
+            (1583091216, Some(6.701171875)),
+            (1583091217, Some(6.701171875)),
+            (1583091218, Some(6.701171875)),
+            (1583091219, Some(6.701171875)),
+            (1583091220, Some(6.701171875)),
+            (1583091221, Some(6.701171875)),
+            (1583091222, Some(6.701171875)),
+            (1583091223, Some(6.701171875)),
+            (1583091224, Some(6.701171875)),
+            (1583091225, Some(6.701171875)),
+            (1583091226, Some(6.701171875)),
+            (1583091227, Some(6.701171875)),
+            (1583091228, Some(6.50244140625)),
+            (1583091229, Some(6.50244140625)),
+            (1583091230, Some(6.50244140625)),
+            (1583091231, Some(6.50244140625)),
+            (1583091232, Some(6.50244140625)),
+            (1583091233, Some(6.50244140625)),
+            (1583091234, Some(6.50244140625)),
+            (1583091235, Some(6.50244140625)),
+            (1583091236, Some(6.50244140625)),
+            (1583091237, Some(6.50244140625)),
+            (1583091238, Some(6.50244140625)),
+            (1583091239, Some(6.50244140625)),
+            (1583091240, Some(6.50244140625)),
+            (1583091241, Some(6.50244140625)),
+            (1583091242, Some(6.50244140625)),
+            (1583091243, Some(6.31298828125)),
+            (1583091244, Some(6.31298828125)),
+            (1583091245, Some(6.31298828125)),
+            (1583091246, Some(6.31298828125)),
+            (1583091247, Some(6.31298828125)),
+            (1583091248, Some(6.31298828125)),
+            (1583091249, Some(6.31298828125)),
+            (1583091250, Some(6.31298828125)),
+            (1583091251, Some(6.31298828125)),
+            (1583091252, Some(6.31298828125)),
+            (1583091253, Some(6.31298828125)),
+            (1583091254, Some(6.31298828125)),
+            (1583091255, Some(6.31298828125)),
+            (1583091256, Some(6.31298828125)),
+            (1583091257, Some(6.31298828125)),
+            (1583091258, Some(6.2666015625)),
+            (1583091259, Some(6.2666015625)),
+            (1583091260, Some(6.2666015625)),
+            (1583091261, Some(6.2666015625)),
+            (1583091262, Some(6.2666015625)),
+            (1583091263, Some(6.2666015625)),
+            (1583091264, Some(6.2666015625)),
+            (1583091265, Some(6.2666015625)),
+            (1583091266, Some(6.2666015625)),
+            (1583091267, Some(6.2666015625)),
+            (1583091268, Some(6.2666015625)),
+            (1583091269, Some(6.2666015625)),
+            (1583091270, Some(6.2666015625)),
+            (1583091271, Some(6.2666015625)),
+            (1583091272, Some(6.2666015625)),
+            (1583091273, Some(6.07177734375)),
+            (1583091274, Some(6.07177734375)),
+            (1583091275, Some(6.07177734375)),
+            (1583091276, Some(6.07177734375)),
+            (1583091277, Some(6.07177734375)),
+            (1583091278, Some(6.07177734375)),
+            (1583091279, Some(6.07177734375)),
+            (1583091280, Some(6.07177734375)),
+            (1583091281, Some(6.07177734375)),
+            (1583091282, Some(6.07177734375)),
+            (1583091283, Some(6.07177734375)),
+            (1583091284, Some(6.07177734375)),
+            (1583091285, Some(6.07177734375)),
+            (1583091286, Some(6.07177734375)),
+            (1583091287, Some(6.07177734375)),
+            (1583091288, Some(5.8720703125)),
+            (1583091289, Some(5.8720703125)),
+            (1583091290, Some(5.8720703125)),
+            (1583091291, Some(5.8720703125)),
+            (1583091292, Some(5.8720703125)),
+            (1583091293, Some(5.8720703125)),
+            (1583091294, Some(5.8720703125)),
+            (1583091295, Some(5.8720703125)),
+            (1583091296, Some(5.8720703125)),
+            (1583091297, Some(5.8720703125)),
+            (1583091298, Some(5.8720703125)),
+            (1583091299, Some(5.8720703125)),
+            (1583091300, Some(5.8720703125)),
+            (1583091301, Some(5.8720703125)),
+            (1583091302, Some(5.8720703125)),
+            (1583091303, Some(5.6494140625)),
+            (1583091304, Some(5.6494140625)),
+            (1583091305, Some(5.6494140625)),
+            (1583091306, Some(5.6494140625)),
+            (1583091307, Some(5.6494140625)),
+            (1583091308, Some(5.6494140625)),
+            (1583091309, Some(5.6494140625)),
+            (1583091310, Some(5.6494140625)),
+            (1583091311, Some(5.6494140625)),
+            (1583091312, Some(5.6494140625)),
+            (1583091313, Some(5.6494140625)),
+            (1583091314, Some(5.6494140625)),
+            (1583091315, Some(5.6494140625)),
+            (1583091316, Some(5.6494140625)),
+            (1583091317, Some(5.6494140625)),
+            (1583091318, Some(5.4853515625)),
+            (1583091319, Some(5.4853515625)),
+            (1583091320, Some(5.4853515625)),
+            (1583091321, Some(5.4853515625)),
+            (1583091322, Some(5.4853515625)),
+            (1583091323, Some(5.4853515625)),
+            (1583091324, Some(5.4853515625)),
+            (1583091325, Some(5.4853515625)),
+            (1583091326, Some(5.4853515625)),
+            (1583091327, Some(5.4853515625)),
+            (1583091328, Some(5.4853515625)),
+            (1583091329, Some(5.4853515625)),
+            (1583091330, Some(5.4853515625)),
+            (1583091331, Some(5.4853515625)),
+            (1583091332, Some(5.4853515625)),
+            (1583091333, Some(5.28125)),
+            (1583091334, Some(5.28125)),
+            (1583091335, Some(5.28125)),
+            (1583091336, Some(5.28125)),
+            (1583091337, Some(5.28125)),
+            (1583091338, Some(5.28125)),
+            (1583091339, Some(5.28125)),
+            (1583091340, Some(5.28125)),
+            (1583091341, Some(5.28125)),
+            (1583091342, Some(5.28125)),
+            (1583091343, Some(5.28125)),
+            (1583091344, Some(5.28125)),
+            (1583091345, Some(5.28125)),
+            (1583091346, Some(5.28125)),
+            (1583091347, Some(5.28125)),
+            (1583091348, Some(5.18505859375)),
+            (1583091349, Some(5.18505859375)),
+            (1583091350, Some(5.18505859375)),
+            (1583091351, Some(5.18505859375)),
+            (1583091352, Some(5.18505859375)),
+            (1583091353, Some(5.18505859375)),
+            (1583091354, Some(5.18505859375)),
+            (1583091355, Some(5.18505859375)),
+            (1583091356, Some(5.18505859375)),
+            (1583091357, Some(5.18505859375)),
+            (1583091358, Some(5.18505859375)),
+            (1583091359, Some(5.18505859375)),
+            (1583091360, Some(5.18505859375)),
+            (1583091361, Some(5.18505859375)),
+            (1583091362, Some(5.18505859375)),
+            (1583091363, Some(5.5908203125)),
+            (1583091364, Some(5.5908203125)),
+            (1583091365, Some(5.5908203125)),
+        ];
         let mut test = PrometheusTimeSeries {
             name: String::from("load average 5 min"),
             series: TimeSeries {
-                metrics: vec![
-                    (1583092654, None),
-                    (1583091367, Some(5.5908203125)),
-                    (1583091368, Some(5.5908203125)),
-                    (1583091369, Some(5.5908203125)),
-                    (1583091370, Some(5.5908203125)),
-                    (1583091371, Some(5.5908203125)),
-                    (1583091372, Some(5.5908203125)),
-                    (1583091373, Some(5.5908203125)),
-                    (1583091374, Some(5.5908203125)),
-                    (1583091375, Some(5.5908203125)),
-                    (1583091376, Some(5.5908203125)),
-                    (1583091377, Some(5.5908203125)),
-                    (1583091378, Some(5.3662109375)),
-                    (1583091379, Some(5.3662109375)),
-                    (1583091380, Some(5.3662109375)),
-                    (1583091381, Some(5.3662109375)),
-                    (1583091382, Some(5.3662109375)),
-                    (1583091383, Some(5.3662109375)),
-                    (1583091384, Some(5.3662109375)),
-                    (1583091385, Some(5.3662109375)),
-                    (1583091386, Some(5.3662109375)),
-                    (1583091387, Some(5.3662109375)),
-                    (1583091388, Some(5.3662109375)),
-                    (1583091389, Some(5.3662109375)),
-                    (1583091390, Some(5.3662109375)),
-                    (1583091391, Some(5.3662109375)),
-                    (1583091392, Some(5.3662109375)),
-                    (1583091393, Some(5.427734375)),
-                    (1583091394, Some(5.427734375)),
-                    (1583091395, Some(5.427734375)),
-                    (1583091396, Some(5.427734375)),
-                    (1583091397, Some(5.427734375)),
-                    (1583091398, Some(5.427734375)),
-                    (1583091399, Some(5.427734375)),
-                    (1583091400, Some(5.427734375)),
-                    (1583091401, Some(5.427734375)),
-                    (1583091402, Some(5.427734375)),
-                    (1583091403, Some(5.427734375)),
-                    (1583091404, Some(5.427734375)),
-                    (1583091405, Some(5.427734375)),
-                    (1583091406, Some(5.427734375)),
-                    (1583091407, Some(5.427734375)),
-                    (1583091408, Some(5.22607421875)),
-                    (1583091409, Some(5.22607421875)),
-                    (1583091410, Some(5.22607421875)),
-                    (1583091411, Some(5.22607421875)),
-                    (1583091412, Some(5.22607421875)),
-                    (1583091413, Some(5.22607421875)),
-                    (1583091414, Some(5.22607421875)),
-                    (1583091415, Some(5.22607421875)),
-                    (1583091416, Some(5.22607421875)),
-                    (1583091417, Some(5.22607421875)),
-                    (1583091418, Some(5.22607421875)),
-                    (1583091419, Some(5.22607421875)),
-                    (1583091420, Some(5.22607421875)),
-                    (1583091421, Some(5.22607421875)),
-                    (1583091422, Some(5.22607421875)),
-                    (1583091423, Some(5.103515625)),
-                    (1583091424, Some(5.103515625)),
-                    (1583091425, Some(5.103515625)),
-                    (1583091426, Some(5.103515625)),
-                    (1583091427, Some(5.103515625)),
-                    (1583091428, Some(5.103515625)),
-                    (1583091429, Some(5.103515625)),
-                    (1583091430, Some(5.103515625)),
-                    (1583091431, Some(5.103515625)),
-                    (1583091432, Some(5.103515625)),
-                    (1583091433, Some(5.103515625)),
-                    (1583091434, Some(5.103515625)),
-                    (1583091435, Some(5.103515625)),
-                    (1583091436, Some(5.103515625)),
-                    (1583091437, Some(5.103515625)),
-                    (1583091438, Some(5.08056640625)),
-                    (1583091439, Some(5.08056640625)),
-                    (1583091440, Some(5.08056640625)),
-                    (1583091141, Some(8.09912109375)),
-                    (1583091142, Some(8.09912109375)),
-                    (1583091143, Some(8.09912109375)),
-                    (1583091144, Some(8.09912109375)),
-                    (1583091145, Some(8.09912109375)),
-                    (1583091146, Some(8.09912109375)),
-                    (1583091147, Some(8.09912109375)),
-                    (1583091148, Some(8.09912109375)),
-                    (1583091149, Some(8.09912109375)),
-                    (1583091150, Some(8.09912109375)),
-                    (1583091151, Some(8.09912109375)),
-                    (1583091152, Some(8.09912109375)),
-                    (1583091153, Some(7.78271484375)),
-                    (1583091154, Some(7.78271484375)),
-                    (1583091155, Some(7.78271484375)),
-                    (1583091156, Some(7.78271484375)),
-                    (1583091157, Some(7.78271484375)),
-                    (1583091158, Some(7.78271484375)),
-                    (1583091159, Some(7.78271484375)),
-                    (1583091160, Some(7.78271484375)),
-                    (1583091161, Some(7.78271484375)),
-                    (1583091162, Some(7.78271484375)),
-                    (1583091163, Some(7.78271484375)),
-                    (1583091164, Some(7.78271484375)),
-                    (1583091165, Some(7.78271484375)),
-                    (1583091166, Some(7.78271484375)),
-                    (1583091167, Some(7.78271484375)),
-                    (1583091168, Some(7.49853515625)),
-                    (1583091169, Some(7.49853515625)),
-                    (1583091170, Some(7.49853515625)),
-                    (1583091171, Some(7.49853515625)),
-                    (1583091172, Some(7.49853515625)),
-                    (1583091173, Some(7.49853515625)),
-                    (1583091174, Some(7.49853515625)),
-                    (1583091175, Some(7.49853515625)),
-                    (1583091176, Some(7.49853515625)),
-                    (1583091177, Some(7.49853515625)),
-                    (1583091178, Some(7.49853515625)),
-                    (1583091179, Some(7.49853515625)),
-                    (1583091180, Some(7.49853515625)),
-                    (1583091181, Some(7.49853515625)),
-                    (1583091182, Some(7.49853515625)),
-                    (1583091183, Some(7.16357421875)),
-                    (1583091184, Some(7.16357421875)),
-                    (1583091185, Some(7.16357421875)),
-                    (1583091186, Some(7.16357421875)),
-                    (1583091187, Some(7.16357421875)),
-                    (1583091188, Some(7.16357421875)),
-                    (1583091189, Some(7.16357421875)),
-                    (1583091190, Some(7.16357421875)),
-                    (1583091191, Some(7.16357421875)),
-                    (1583091192, Some(7.16357421875)),
-                    (1583091193, Some(7.16357421875)),
-                    (1583091194, Some(7.16357421875)),
-                    (1583091195, Some(7.16357421875)),
-                    (1583091196, Some(7.16357421875)),
-                    (1583091197, Some(7.16357421875)),
-                    (1583091198, Some(6.9267578125)),
-                    (1583091199, Some(6.9267578125)),
-                    (1583091200, Some(6.9267578125)),
-                    (1583091201, Some(6.9267578125)),
-                    (1583091202, Some(6.9267578125)),
-                    (1583091203, Some(6.9267578125)),
-                    (1583091204, Some(6.9267578125)),
-                    (1583091205, Some(6.9267578125)),
-                    (1583091206, Some(6.9267578125)),
-                    (1583091207, Some(6.9267578125)),
-                    (1583091208, Some(6.9267578125)),
-                    (1583091209, Some(6.9267578125)),
-                    (1583091210, Some(6.9267578125)),
-                    (1583091211, Some(6.9267578125)),
-                    (1583091212, Some(6.9267578125)),
-                    (1583091213, Some(6.701171875)),
-                    (1583091214, Some(6.701171875)),
-                    (1583091215, Some(6.701171875)),
-                    (1583091216, Some(6.701171875)),
-                    (1583091217, Some(6.701171875)),
-                    (1583091218, Some(6.701171875)),
-                    (1583091219, Some(6.701171875)),
-                    (1583091220, Some(6.701171875)),
-                    (1583091221, Some(6.701171875)),
-                    (1583091222, Some(6.701171875)),
-                    (1583091223, Some(6.701171875)),
-                    (1583091224, Some(6.701171875)),
-                    (1583091225, Some(6.701171875)),
-                    (1583091226, Some(6.701171875)),
-                    (1583091227, Some(6.701171875)),
-                    (1583091228, Some(6.50244140625)),
-                    (1583091229, Some(6.50244140625)),
-                    (1583091230, Some(6.50244140625)),
-                    (1583091231, Some(6.50244140625)),
-                    (1583091232, Some(6.50244140625)),
-                    (1583091233, Some(6.50244140625)),
-                    (1583091234, Some(6.50244140625)),
-                    (1583091235, Some(6.50244140625)),
-                    (1583091236, Some(6.50244140625)),
-                    (1583091237, Some(6.50244140625)),
-                    (1583091238, Some(6.50244140625)),
-                    (1583091239, Some(6.50244140625)),
-                    (1583091240, Some(6.50244140625)),
-                    (1583091241, Some(6.50244140625)),
-                    (1583091242, Some(6.50244140625)),
-                    (1583091243, Some(6.31298828125)),
-                    (1583091244, Some(6.31298828125)),
-                    (1583091245, Some(6.31298828125)),
-                    (1583091246, Some(6.31298828125)),
-                    (1583091247, Some(6.31298828125)),
-                    (1583091248, Some(6.31298828125)),
-                    (1583091249, Some(6.31298828125)),
-                    (1583091250, Some(6.31298828125)),
-                    (1583091251, Some(6.31298828125)),
-                    (1583091252, Some(6.31298828125)),
-                    (1583091253, Some(6.31298828125)),
-                    (1583091254, Some(6.31298828125)),
-                    (1583091255, Some(6.31298828125)),
-                    (1583091256, Some(6.31298828125)),
-                    (1583091257, Some(6.31298828125)),
-                    (1583091258, Some(6.2666015625)),
-                    (1583091259, Some(6.2666015625)),
-                    (1583091260, Some(6.2666015625)),
-                    (1583091261, Some(6.2666015625)),
-                    (1583091262, Some(6.2666015625)),
-                    (1583091263, Some(6.2666015625)),
-                    (1583091264, Some(6.2666015625)),
-                    (1583091265, Some(6.2666015625)),
-                    (1583091266, Some(6.2666015625)),
-                    (1583091267, Some(6.2666015625)),
-                    (1583091268, Some(6.2666015625)),
-                    (1583091269, Some(6.2666015625)),
-                    (1583091270, Some(6.2666015625)),
-                    (1583091271, Some(6.2666015625)),
-                    (1583091272, Some(6.2666015625)),
-                    (1583091273, Some(6.07177734375)),
-                    (1583091274, Some(6.07177734375)),
-                    (1583091275, Some(6.07177734375)),
-                    (1583091276, Some(6.07177734375)),
-                    (1583091277, Some(6.07177734375)),
-                    (1583091278, Some(6.07177734375)),
-                    (1583091279, Some(6.07177734375)),
-                    (1583091280, Some(6.07177734375)),
-                    (1583091281, Some(6.07177734375)),
-                    (1583091282, Some(6.07177734375)),
-                    (1583091283, Some(6.07177734375)),
-                    (1583091284, Some(6.07177734375)),
-                    (1583091285, Some(6.07177734375)),
-                    (1583091286, Some(6.07177734375)),
-                    (1583091287, Some(6.07177734375)),
-                    (1583091288, Some(5.8720703125)),
-                    (1583091289, Some(5.8720703125)),
-                    (1583091290, Some(5.8720703125)),
-                    (1583091291, Some(5.8720703125)),
-                    (1583091292, Some(5.8720703125)),
-                    (1583091293, Some(5.8720703125)),
-                    (1583091294, Some(5.8720703125)),
-                    (1583091295, Some(5.8720703125)),
-                    (1583091296, Some(5.8720703125)),
-                    (1583091297, Some(5.8720703125)),
-                    (1583091298, Some(5.8720703125)),
-                    (1583091299, Some(5.8720703125)),
-                    (1583091300, Some(5.8720703125)),
-                    (1583091301, Some(5.8720703125)),
-                    (1583091302, Some(5.8720703125)),
-                    (1583091303, Some(5.6494140625)),
-                    (1583091304, Some(5.6494140625)),
-                    (1583091305, Some(5.6494140625)),
-                    (1583091306, Some(5.6494140625)),
-                    (1583091307, Some(5.6494140625)),
-                    (1583091308, Some(5.6494140625)),
-                    (1583091309, Some(5.6494140625)),
-                    (1583091310, Some(5.6494140625)),
-                    (1583091311, Some(5.6494140625)),
-                    (1583091312, Some(5.6494140625)),
-                    (1583091313, Some(5.6494140625)),
-                    (1583091314, Some(5.6494140625)),
-                    (1583091315, Some(5.6494140625)),
-                    (1583091316, Some(5.6494140625)),
-                    (1583091317, Some(5.6494140625)),
-                    (1583091318, Some(5.4853515625)),
-                    (1583091319, Some(5.4853515625)),
-                    (1583091320, Some(5.4853515625)),
-                    (1583091321, Some(5.4853515625)),
-                    (1583091322, Some(5.4853515625)),
-                    (1583091323, Some(5.4853515625)),
-                    (1583091324, Some(5.4853515625)),
-                    (1583091325, Some(5.4853515625)),
-                    (1583091326, Some(5.4853515625)),
-                    (1583091327, Some(5.4853515625)),
-                    (1583091328, Some(5.4853515625)),
-                    (1583091329, Some(5.4853515625)),
-                    (1583091330, Some(5.4853515625)),
-                    (1583091331, Some(5.4853515625)),
-                    (1583091332, Some(5.4853515625)),
-                    (1583091333, Some(5.28125)),
-                    (1583091334, Some(5.28125)),
-                    (1583091335, Some(5.28125)),
-                    (1583091336, Some(5.28125)),
-                    (1583091337, Some(5.28125)),
-                    (1583091338, Some(5.28125)),
-                    (1583091339, Some(5.28125)),
-                    (1583091340, Some(5.28125)),
-                    (1583091341, Some(5.28125)),
-                    (1583091342, Some(5.28125)),
-                    (1583091343, Some(5.28125)),
-                    (1583091344, Some(5.28125)),
-                    (1583091345, Some(5.28125)),
-                    (1583091346, Some(5.28125)),
-                    (1583091347, Some(5.28125)),
-                    (1583091348, Some(5.18505859375)),
-                    (1583091349, Some(5.18505859375)),
-                    (1583091350, Some(5.18505859375)),
-                    (1583091351, Some(5.18505859375)),
-                    (1583091352, Some(5.18505859375)),
-                    (1583091353, Some(5.18505859375)),
-                    (1583091354, Some(5.18505859375)),
-                    (1583091355, Some(5.18505859375)),
-                    (1583091356, Some(5.18505859375)),
-                    (1583091357, Some(5.18505859375)),
-                    (1583091358, Some(5.18505859375)),
-                    (1583091359, Some(5.18505859375)),
-                    (1583091360, Some(5.18505859375)),
-                    (1583091361, Some(5.18505859375)),
-                    (1583091362, Some(5.18505859375)),
-                    (1583091363, Some(5.5908203125)),
-                    (1583091364, Some(5.5908203125)),
-                    (1583091365, Some(5.5908203125)),
-                ],
-                metrics_capacity: 300,
-                stats: TimeSeriesStats {
-                    max: 8.09912109375,
-                    min: 5.08056640625,
-                    avg: 6.147174479166667,
-                    first: 8.09912109375,
-                    last: 5.08056640625,
-                    count: 300,
-                    sum: 1844.15234375,
-                    last_epoch: 1583091439,
-                    is_dirty: false,
-                },
                 collision_policy: ValueCollisionPolicy::Overwrite,
-                missing_values_policy: MissingValuesPolicy::Zero,
-                first_idx: 0,
-                active_items: 1,
-                prev_snapshot: vec![],
-                prev_value: (1604568602, Some(6.0)),
-                upsert_type: UpsertType::NewEpoch,
-            },
-            data: Vector {
-                result: vec![HTTPVectorResult { labels: test_labels.clone(), value: vec![] }],
+                ..wrapped_series(metrics, 0, 1)
             },
-            source: String::from(
-                "http://localhost:9090/api/v1/query_range?query=node_memory_bytes_total",
-            ),
-            url: String::from("/"),
-            data_type: String::from(""),
-            required_labels: test_labels,
-            pull_interval: 15,
-            color: Rgb { r: 207, g: 102, b: 121 },
-            alpha: 1.0,
+            ..PrometheusTimeSeries::default()
         };
         assert_eq!(test.series.metrics.len(), 300usize);
-        let test1_json = bytes::Bytes::from(
-            r#"{
-              "status":"success",
-              "data":{
-                "resultType":"matrix",
-                "result":[{
-                  "metric":{
-                    "__name__":"node_load5",
-                    "instance":"localhost:9100",
-                    "job":"node_exporter"
-                  },
-                  "values":[
-                    [1583092652, "5.0283203125"],
-                    [1583092653, "5.0283203125"],
-                    [1583092654, "5.0283203125"]
-                ]
-              }]
-            }
-          }"#,
-        );
-        let res1_json = parse_json(&String::from("http://test"), &test1_json);
-        assert!(res1_json.is_some());
-        let res1_load = test.load_prometheus_response(res1_json.unwrap());
+        let response = matrix_response(vec![matrix_result(
+            labels(&[
+                ("__name__", "node_load5"),
+                ("instance", "localhost:9100"),
+                ("job", "node_exporter"),
+            ]),
+            &[(1583092652, 5.0283203125), (1583092653, 5.0283203125), (1583092654, 5.0283203125)],
+        )]);
+        let res1_load = test.load_prometheus_response(response);
         assert_eq!(res1_load, Ok(2usize));
         assert_eq!(test.series.active_items, 3usize);
         assert_eq!(test.series.metrics[0], (1583092654, Some(5.0283203125)));
@@ -1422,4 +1791,226 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn it_parses_discovery_config() {
+        init_log();
+        let yaml = r#"
+label: mountpoint
+match: node_filesystem_avail_bytes{fstype!="tmpfs"}
+query_template: http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes{mountpoint="{value}"}
+"#;
+        let discover: DiscoveryConfig = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(discover.interval, 60);
+        assert_eq!(discover.grace_period, 300);
+        assert_eq!(discover.max_series, 16);
+        assert_eq!(
+            discover.source_for("/boot"),
+            "http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes{mountpoint=\"/boot\"}"
+        );
+        let url = discover.label_values_url().unwrap();
+        assert!(url.starts_with("http://localhost:9090/api/v1/label/mountpoint/values?match[]="));
+        // The template must contain the substitution point.
+        let yaml = r#"
+label: mountpoint
+match: node_filesystem_avail_bytes
+query_template: http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes
+"#;
+        assert!(serde_yaml::from_str::<DiscoveryConfig>(yaml).is_err());
+    }
+
+    #[test]
+    fn it_escapes_the_discovered_label_values() {
+        init_log();
+        let yaml = r#"
+label: mountpoint
+match: node_filesystem_avail_bytes{fstype!="tmpfs",mountpoint=~"/mnt/.+"}
+query_template: http://localhost:9090/api/v1/query?query=node_filesystem_avail_bytes{mountpoint="{value}"}
+"#;
+        let discover: DiscoveryConfig = serde_yaml::from_str(yaml).unwrap();
+        let query = |url: &str| -> Vec<(String, String)> {
+            let url = url.parse::<reqwest::Url>().unwrap();
+            url.query_pairs().map(|(key, value)| (key.into_owned(), value.into_owned())).collect()
+        };
+        let value = r#"/mnt/a "b"\c&d#e+f"#;
+        let url = PrometheusTimeSeries::prepare_url(&discover.source_for(value), 0).unwrap();
+        assert_eq!(query(&url), vec![(
+            String::from("query"),
+            String::from(r#"node_filesystem_avail_bytes{mountpoint="/mnt/a \"b\"\\c&d#e+f"}"#)
+        )]);
+        let url = discover.label_values_url().unwrap();
+        assert_eq!(query(&url), vec![(
+            String::from("match[]"),
+            String::from(r#"node_filesystem_avail_bytes{fstype!="tmpfs",mountpoint=~"/mnt/.+"}"#)
+        )]);
+    }
+
+    #[test]
+    fn it_parses_label_values() {
+        init_log();
+        let body = bytes::Bytes::from(r#"{"status":"success","data":["/","/boot"]}"#);
+        assert_eq!(
+            parse_label_values(&body).unwrap(),
+            vec![String::from("/"), String::from("/boot")]
+        );
+        let body = bytes::Bytes::from(r#"{"status":"error","errorType":"bad_data"}"#);
+        assert!(parse_label_values(&body).is_err());
+        assert!(parse_label_values(&bytes::Bytes::from("Internal Server Error")).is_err());
+    }
+
+    /// `numbered_response` returns a matrix response of `entries` results, each with a sample at
+    /// its own epoch, the even ones are of the job "node"
+    fn numbered_response(entries: usize) -> HTTPResponse {
+        matrix_response(
+            (0..entries)
+                .map(|idx| {
+                    let instance = format!("host-{:06}", idx);
+                    let job = if idx % 2 == 0 { "node" } else { "other" };
+                    matrix_result(
+                        labels(&[("instance", instance.as_str()), ("job", job)]),
+                        &[(1_000_000 + idx as u64, 1.)],
+                    )
+                })
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn it_truncates_the_result_entries() {
+        init_log();
+        let load = |test: &mut PrometheusTimeSeries, entries: usize| {
+            test.load_prometheus_response(numbered_response(entries)).unwrap();
+        };
+        // A normal response is loaded whole.
+        let mut test = PrometheusTimeSeries::default();
+        load(&mut test, 20);
+        assert_eq!(test.series.active_items, 20);
+        assert_eq!(test.truncated_entries, 0);
+        assert_eq!(test.truncation_hint(), None);
+        // Only the first entries of a huge response are loaded, the rest are counted.
+        let mut test = PrometheusTimeSeries {
+            max_result_entries: Some(10),
+            ..PrometheusTimeSeries::default()
+        };
+        load(&mut test, 5000);
+        assert_eq!(test.series.active_items, 10);
+        assert_eq!(test.series.newest_epoch(), Some(1_000_009));
+        assert_eq!(test.truncated_entries, 4990);
+        load(&mut test, 5000);
+        assert_eq!(test.truncated_entries, 9980);
+        assert_eq!(
+            test.truncation_hint(),
+            Some(format!("9980 result entries dropped. {}", TRUNCATION_HINT))
+        );
+        // The limit applies to the entries that match the labels.
+        let mut labels = HashMap::new();
+        labels.insert(String::from("job"), String::from("node"));
+        let mut test = PrometheusTimeSeries {
+            required_labels: labels,
+            max_result_entries: Some(10),
+            ..PrometheusTimeSeries::default()
+        };
+        load(&mut test, 30);
+        assert_eq!(test.series.active_items, 10);
+        assert_eq!(test.truncated_entries, 5);
+    }
+
+    #[tokio::test]
+    async fn it_discards_the_bodies_over_the_limit() {
+        init_log();
+        let body = response_body(&numbered_response(2000));
+        assert!(body.len() > 64 * 1024);
+        // A fixture server that replies the body with its length on /length and streams it
+        // until the connection is closed otherwise.
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let served = body.clone();
+        tokio::spawn(async move {
+            use tokio::io::{AsyncReadExt, AsyncWriteExt};
+            while let Ok((mut socket, _)) = listener.accept().await {
+                let body = served.clone();
+                tokio::spawn(async move {
+                    let mut request = [0u8; 1024];
+                    let read = socket.read(&mut request).await.unwrap_or_default();
+                    let length = if request[..read].starts_with(b"GET /length") {
+                        format!("Content-Length: {}\r\n", body.len())
+                    } else {
+                        String::from("Connection: close\r\n")
+                    };
+                    let head = format!("HTTP/1.1 200 OK\r\n{}\r\n", length);
+                    let _ = socket.write_all(head.as_bytes()).await;
+                    let _ = socket.write_all(&body).await;
+                    let _ = socket.shutdown().await;
+                });
+            }
+        });
+        let timeout = Some(Duration::from_secs(5));
+        for path in ["length", "stream"] {
+            let url = format!("http://{}/{}", addr, path);
+            match get_from_prometheus(url.clone(), timeout, Some(64 * 1024)).await {
+                Err((_, FetchError::BodyTooLarge(size))) => assert!(size > 64 * 1024),
+                other => {
+                    panic!("{}: expected BodyTooLarge, got {:?}", path, other.map(|b| b.len()))
+                },
+            }
+            // Within the limit the body is read whole and a large one is parsed off the task.
+            let fetched = get_from_prometheus(url, timeout, None).await.unwrap();
+            assert_eq!(fetched, body);
+        }
+        let large = response_body(&numbered_response(20000));
+        assert!(large.len() > BLOCKING_PARSE_BYTES);
+        let parsed = parse_body(String::from("http://test"), large.clone()).await;
+        assert_eq!(parsed, parse_json("http://test", &large));
+        assert_eq!(parsed.unwrap().data.sample_count(), 20000);
+    }
+
+    #[test]
+    fn it_summarizes_the_responses() {
+        init_log();
+        let scalar = bytes::Bytes::from(
+            r#"{"status":"success","data":{"resultType":"scalar","result":[1558283674.829,"1"]}}"#,
+        );
+        let response = parse_json("http://test", &scalar).unwrap();
+        assert_eq!(
+            ResponseSummary::new(&response, Some(scalar.len())).to_string(),
+            format!("status: success, scalar with 1 samples, {} bytes", scalar.len())
+        );
+        assert_eq!(
+            ResponseSummary::new(&response, None).to_string(),
+            "status: success, scalar with 1 samples"
+        );
+        // A large matrix is described by its counts, the summary does not grow with the samples.
+        let samples: Vec<(u64, f64)> = (0..3600u64).map(|epoch| (epoch, 1.)).collect();
+        let response = matrix_response(vec![matrix_result(HashMap::new(), &samples); 10]);
+        let summary = ResponseSummary::new(&response, Some(1_000_000));
+        assert_eq!(summary.samples, 36000usize);
+        assert_eq!(
+            summary.to_string(),
+            "status: success, matrix with 36000 samples, 1000000 bytes"
+        );
+        assert!(summary.to_string().len() < 80);
+    }
+
+    #[test]
+    fn it_prepares_the_url_after_a_resize() {
+        let mut test0 = PrometheusTimeSeries::new(
+            String::from("http://localhost:9090/api/v1/query_range?query=node_load1"),
+            15,
+            String::from("matrix"),
+            HashMap::new(),
+        )
+        .unwrap();
+        let range = |test: &PrometheusTimeSeries| {
+            let url = test.url.parse::<reqwest::Url>().unwrap();
+            let param = |name: &str| -> u64 {
+                url.query_pairs().find(|(key, _)| key == name).unwrap().1.parse().unwrap()
+            };
+            param("end") - param("start")
+        };
+        test0.resize_capacity(60);
+        assert_eq!(test0.series.metrics_capacity(), 60);
+        assert_eq!(range(&test0), 60);
+        test0.resize_capacity(30);
+        assert_eq!(range(&test0), 30);
+    }
 }
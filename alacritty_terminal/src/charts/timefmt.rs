@@ -0,0 +1,258 @@
+//! `timefmt` formats the times shown to the user: the absolute times, i.e. the clock of a sample
+//! or of a journal entry, are shown in the configured zone, while the relative ones, i.e. how long
+//! ago a chart was updated, do not depend on it. The zones are fixed offsets from UTC or the zone
+//! of the OS, there is no tz database.
+
+use chrono::{DateTime, FixedOffset, Local, Offset, TimeZone, Utc};
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::fmt;
+use std::str::FromStr;
+
+/// The seconds in a day, the clock of an epoch wraps around midnight
+const SECS_PER_DAY: i64 = 86_400i64;
+
+/// `ChartTimezone` is the zone the absolute times are shown in
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ChartTimezone {
+    /// The zone of the OS, its offset may change with DST
+    #[default]
+    Local,
+    Utc,
+    /// The seconds east of UTC
+    Fixed(i32),
+}
+
+impl ChartTimezone {
+    /// `utc_offset_secs` returns the seconds east of UTC of the zone at an epoch
+    pub fn utc_offset_secs(&self, epoch: u64) -> i32 {
+        self.utc_offset_secs_with(epoch, local_utc_offset_secs)
+    }
+
+    /// `utc_offset_secs_with` returns the seconds east of UTC of the zone at an epoch, the offset
+    /// of the Local zone is given by `local`
+    pub fn utc_offset_secs_with(&self, epoch: u64, local: impl Fn(u64) -> i32) -> i32 {
+        match self {
+            ChartTimezone::Local => local(epoch),
+            ChartTimezone::Utc => 0,
+            ChartTimezone::Fixed(offset) => *offset,
+        }
+    }
+
+    /// `now` returns the current time in the zone
+    pub fn now(&self) -> DateTime<FixedOffset> {
+        let now = Utc::now();
+        let offset_secs = self.utc_offset_secs(now.timestamp().max(0) as u64);
+        let offset = FixedOffset::east_opt(offset_secs).unwrap_or_else(|| Utc.fix());
+        now.with_timezone(&offset)
+    }
+
+    /// `clock` returns the HH:MM of an epoch in the zone
+    pub fn clock(&self, epoch: u64) -> String {
+        clock(epoch, self.utc_offset_secs(epoch))
+    }
+
+    /// `clock_secs` returns the HH:MM:SS of an epoch in the zone
+    pub fn clock_secs(&self, epoch: u64) -> String {
+        clock_secs(epoch, self.utc_offset_secs(epoch))
+    }
+}
+
+impl FromStr for ChartTimezone {
+    type Err = String;
+
+    /// The zones are Local, Utc or an offset as +HH:MM or -HH:MM
+    fn from_str(s: &str) -> Result<ChartTimezone, String> {
+        let invalid = || format!("Invalid timezone {:?}, expected Local, Utc or +HH:MM", s);
+        match s {
+            "Local" | "local" => return Ok(ChartTimezone::Local),
+            "Utc" | "UTC" | "utc" => return Ok(ChartTimezone::Utc),
+            _ => {},
+        }
+        let (sign, offset) = if let Some(offset) = s.strip_prefix('+') {
+            (1, offset)
+        } else if let Some(offset) = s.strip_prefix('-') {
+            (-1, offset)
+        } else {
+            return Err(invalid());
+        };
+        let (hours, minutes) = offset.split_once(':').ok_or_else(invalid)?;
+        let two_digits = |part: &str| part.len() == 2 && part.bytes().all(|b| b.is_ascii_digit());
+        if !two_digits(hours) || !two_digits(minutes) {
+            return Err(invalid());
+        }
+        let hours: i32 = hours.parse().map_err(|_| invalid())?;
+        let minutes: i32 = minutes.parse().map_err(|_| invalid())?;
+        if hours > 23 || minutes > 59 {
+            return Err(invalid());
+        }
+        Ok(ChartTimezone::Fixed(sign * (hours * 3600 + minutes * 60)))
+    }
+}
+
+impl fmt::Display for ChartTimezone {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ChartTimezone::Local => write!(f, "Local"),
+            ChartTimezone::Utc => write!(f, "Utc"),
+            ChartTimezone::Fixed(offset) => {
+                let sign = if *offset < 0 { '-' } else { '+' };
+                let offset = offset.unsigned_abs();
+                write!(f, "{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+            },
+        }
+    }
+}
+
+impl Serialize for ChartTimezone {
+    fn serialize<S>(&self, ser: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        ser.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ChartTimezone {
+    fn deserialize<D>(de: D) -> Result<ChartTimezone, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        String::deserialize(de)?.parse().map_err(D::Error::custom)
+    }
+}
+
+/// `local_utc_offset_secs` returns the seconds east of UTC of the OS zone at an epoch
+pub fn local_utc_offset_secs(epoch: u64) -> i32 {
+    match Local.timestamp_opt(epoch as i64, 0).earliest() {
+        Some(time) => time.offset().local_minus_utc(),
+        None => 0,
+    }
+}
+
+/// `secs_of_day` returns the seconds since midnight of an epoch at an offset
+fn secs_of_day(epoch: u64, utc_offset_secs: i32) -> i64 {
+    (epoch as i64 + utc_offset_secs as i64).rem_euclid(SECS_PER_DAY)
+}
+
+/// `clock` returns the HH:MM of an epoch at an offset, i.e. for the axis ticks
+pub fn clock(epoch: u64, utc_offset_secs: i32) -> String {
+    let secs = secs_of_day(epoch, utc_offset_secs);
+    format!("{:02}:{:02}", secs / 3600, secs % 3600 / 60)
+}
+
+/// `clock_secs` returns the HH:MM:SS of an epoch at an offset, i.e. for the journal entries
+pub fn clock_secs(epoch: u64, utc_offset_secs: i32) -> String {
+    let secs = secs_of_day(epoch, utc_offset_secs);
+    format!("{:02}:{:02}:{:02}", secs / 3600, secs % 3600 / 60, secs % 60)
+}
+
+/// `format_duration_secs` formats seconds as the two most significant units, i.e. 1h5m or 30s
+pub fn format_duration_secs(secs: u64) -> String {
+    let (hours, minutes, seconds) = (secs / 3600, secs % 3600 / 60, secs % 60);
+    if hours > 0 {
+        format!("{}h{}m", hours, minutes)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+/// `ago` returns how long ago something happened `secs` ago, i.e. 1m5s ago
+pub fn ago(secs: u64) -> String {
+    format!("{} ago", format_duration_secs(secs))
+}
+
+/// `ago_millis` returns how long ago something happened `millis` ago, for the sub-second times
+pub fn ago_millis(millis: u128) -> String {
+    format!("{}ms ago", millis)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn it_parses_the_timezones() {
+        let cases = [
+            ("Local", Ok(ChartTimezone::Local)),
+            ("Utc", Ok(ChartTimezone::Utc)),
+            ("UTC", Ok(ChartTimezone::Utc)),
+            ("+05:30", Ok(ChartTimezone::Fixed(19_800))),
+            ("-03:00", Ok(ChartTimezone::Fixed(-10_800))),
+            ("-00:30", Ok(ChartTimezone::Fixed(-1_800))),
+            ("+00:00", Ok(ChartTimezone::Fixed(0))),
+            ("+23:59", Ok(ChartTimezone::Fixed(86_340))),
+            ("05:30", Err(())),
+            ("+5:30", Err(())),
+            ("+0530", Err(())),
+            ("+24:00", Err(())),
+            ("+05:60", Err(())),
+            ("+-5:30", Err(())),
+            ("CET", Err(())),
+            ("", Err(())),
+        ];
+        for (input, expected) in cases {
+            let parsed = input.parse::<ChartTimezone>();
+            assert_eq!(parsed.clone().map_err(|_| ()), expected, "parsing {:?}", input);
+            // The valid zones are written back as they are configured.
+            if let Ok(timezone) = parsed {
+                assert_eq!(timezone.to_string().parse(), Ok(timezone));
+            }
+        }
+        assert_eq!(ChartTimezone::Fixed(-10_800).to_string(), "-03:00");
+        let config: ChartTimezone = serde_yaml::from_str("\"+05:30\"").unwrap();
+        assert_eq!(config, ChartTimezone::Fixed(19_800));
+        assert!(serde_yaml::from_str::<ChartTimezone>("CET").is_err());
+    }
+
+    #[test]
+    fn it_formats_the_clock_at_fixed_offsets() {
+        // 1970-01-02 12:34:56 UTC
+        let noon = 86_400 + 12 * 3600 + 34 * 60 + 56;
+        let cases = [
+            (noon, "Utc", "12:34", "12:34:56"),
+            (noon, "+05:30", "18:04", "18:04:56"),
+            (noon, "-03:00", "09:34", "09:34:56"),
+            // Midnight wraps to the next day ahead of UTC and to the previous one behind it.
+            (86_400 + 23 * 3600 + 45 * 60, "+05:30", "05:15", "05:15:00"),
+            (86_400 + 30 * 60, "-01:00", "23:30", "23:30:00"),
+            (0, "-00:30", "23:30", "23:30:00"),
+            (86_400, "Utc", "00:00", "00:00:00"),
+        ];
+        for (epoch, zone, expected_clock, expected_clock_secs) in cases {
+            let timezone: ChartTimezone = zone.parse().unwrap();
+            assert_eq!(timezone.clock(epoch), expected_clock, "clock of {} at {}", epoch, zone);
+            assert_eq!(timezone.clock_secs(epoch), expected_clock_secs);
+        }
+    }
+
+    #[test]
+    fn it_formats_the_clock_at_the_local_offset() {
+        let noon = 86_400 + 12 * 3600;
+        let cet = |_epoch| 3600;
+        let offset = ChartTimezone::Local.utc_offset_secs_with(noon, cet);
+        assert_eq!(offset, 3600);
+        assert_eq!(clock(noon, offset), "13:00");
+        // The Local offset does not apply to the other zones.
+        assert_eq!(ChartTimezone::Utc.utc_offset_secs_with(noon, cet), 0);
+        assert_eq!(ChartTimezone::Fixed(-1_800).utc_offset_secs_with(noon, cet), -1_800);
+        // The Local offset may change with DST between epochs.
+        let dst = |epoch| if epoch < noon { 3600 } else { 7200 };
+        assert_eq!(
+            clock(noon - 1, ChartTimezone::Local.utc_offset_secs_with(noon - 1, dst)),
+            "12:59"
+        );
+        assert_eq!(clock(noon, ChartTimezone::Local.utc_offset_secs_with(noon, dst)), "14:00");
+    }
+
+    #[test]
+    fn it_formats_the_relative_times() {
+        let cases = [(0, "0s ago"), (5, "5s ago"), (65, "1m5s ago"), (3725, "1h2m ago")];
+        for (secs, expected) in cases {
+            assert_eq!(ago(secs), expected);
+        }
+        assert_eq!(ago_millis(250), "250ms ago");
+    }
+}
@@ -0,0 +1,229 @@
+//! `timeline` draws a source as a horizontal band colored by its state, i.e. the 0/1 of an `up`
+//! or `probe_success` metric, instead of a line. Each run of consecutive equal values is a quad,
+//! the bands of the timeline sources of a chart are stacked in slots from the bottom of the
+//! chart and they do not take part in the Y scale of the lines.
+
+use crate::charts::color::RgbExt;
+use crate::charts::color_ref::{ChartColor, TerminalPalette};
+use crate::charts::timefmt::format_duration_secs;
+use crate::charts::{same_value, PixelRect, SeriesIndex};
+use crate::vte::ansi::Rgb;
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// The fraction of the chart height taken by a timeline band
+pub const DEFAULT_TIMELINE_HEIGHT: f32 = 0.2f32;
+
+/// The color of the samples that are missing
+pub const DEFAULT_TIMELINE_MISSING_COLOR: Rgb = Rgb { r: 128, g: 128, b: 128 };
+
+/// The floats of each vertex of a band: x, y, r, g, b, a
+pub const TIMELINE_FLOATS_PER_VERTEX: usize = 6usize;
+
+/// The vertices of the two triangles of each run
+pub const VERTICES_PER_RUN: usize = 6usize;
+
+/// `RenderAs` is how the samples of a source are drawn
+#[derive(Serialize, Deserialize, Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum RenderAs {
+    /// A line scaled to the values of the chart
+    #[default]
+    Line,
+    /// A band colored by the state of each sample
+    Timeline,
+    /// A vertical bar from zero to each sample, scaled like a line
+    Bars,
+    /// A point at each sample, scaled like a line
+    Points,
+}
+
+/// `TimelineState` is the color and the name shown for a value of a timeline source
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+pub struct TimelineState {
+    /// The value of the samples in this state
+    pub value: f64,
+
+    /// The color of the runs in this state, a literal color or the name of a terminal color
+    pub color: ChartColor,
+
+    /// The name of the state in the hover readout, the value is shown when not set
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// `TimelineConfig` is the layout and the colors of a timeline source
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+pub struct TimelineConfig {
+    /// The fraction of the chart height of the band, defaults to DEFAULT_TIMELINE_HEIGHT
+    #[serde(default)]
+    pub height: Option<f32>,
+
+    /// The position of the band from the bottom of the chart, in band heights. Defaults to the
+    /// position of the source among the timeline sources of the chart.
+    #[serde(default)]
+    pub slot: Option<usize>,
+
+    /// The color of each value, defaults to 1 as green "up" and 0 as red "down"
+    #[serde(default)]
+    pub states: Option<Vec<TimelineState>>,
+
+    /// The color of the missing samples, defaults to DEFAULT_TIMELINE_MISSING_COLOR
+    #[serde(default)]
+    pub missing_color: Option<ChartColor>,
+}
+
+impl TimelineConfig {
+    /// `height` returns the fraction of the chart height of the band
+    pub fn height(&self) -> f32 {
+        self.height.unwrap_or(DEFAULT_TIMELINE_HEIGHT).clamp(0f32, 1f32)
+    }
+
+    /// `states` returns the configured states or the default up and down states
+    pub fn states(&self) -> Vec<TimelineState> {
+        self.states.clone().unwrap_or_else(|| {
+            vec![
+                TimelineState {
+                    value: 1f64,
+                    color: Rgb::from_u32(0x4caf50).into(),
+                    label: Some(String::from("up")),
+                },
+                TimelineState {
+                    value: 0f64,
+                    color: Rgb::from_u32(0xe53935).into(),
+                    label: Some(String::from("down")),
+                },
+            ]
+        })
+    }
+
+    /// `state_of` returns the color and the name of a sample, the values without a state use
+    /// `fallback` as their color.
+    pub fn state_of(&self, value: Option<f64>, fallback: Rgb) -> (Rgb, String) {
+        let value = match value {
+            Some(value) => value,
+            None => {
+                let color = self
+                    .missing_color
+                    .as_ref()
+                    .map_or(DEFAULT_TIMELINE_MISSING_COLOR, ChartColor::rgb);
+                return (color, String::from("missing"));
+            },
+        };
+        match self.states().into_iter().find(|state| same_value(state.value, value)) {
+            Some(state) => (state.color.rgb(), state.label.unwrap_or_else(|| value.to_string())),
+            None => (fallback, value.to_string()),
+        }
+    }
+
+    /// `resolve_colors` resolves the named colors of the states against the palette, the
+    /// unknown names are added to `unknown`
+    pub fn resolve_colors(&mut self, palette: &TerminalPalette, unknown: &mut Vec<String>) {
+        let states = self.states.iter_mut().flatten().map(|state| &mut state.color);
+        for color in states.chain(self.missing_color.iter_mut()) {
+            if let Err(name) = color.resolve(palette) {
+                unknown.push(name);
+            }
+        }
+    }
+}
+
+/// `timeline_runs` splits the samples into the ranges of consecutive equal values, the missing
+/// samples are a run of their own.
+pub fn timeline_runs(values: &[Option<f64>]) -> Vec<Range<usize>> {
+    let mut runs: Vec<Range<usize>> = vec![];
+    for (idx, value) in values.iter().enumerate() {
+        match runs.last_mut() {
+            Some(run)
+                if match (values[run.start], value) {
+                    (Some(current), Some(value)) => same_value(current, *value),
+                    (None, None) => true,
+                    _ => false,
+                } =>
+            {
+                run.end = idx + 1;
+            },
+            _ => runs.push(idx..idx + 1),
+        }
+    }
+    runs
+}
+
+/// `push_run_quad` appends the two triangles of a run from x0,y0 (bottom left) to x1,y1
+/// (top right) as x,y,r,g,b,a vertices.
+pub fn push_run_quad(vertices: &mut Vec<f32>, x0: f32, y0: f32, x1: f32, y1: f32, color: Rgb) {
+    let [r, g, b, a] = color.with_alpha(1f32);
+    vertices.reserve(VERTICES_PER_RUN * TIMELINE_FLOATS_PER_VERTEX);
+    for (x, y) in [(x0, y0), (x1, y0), (x1, y1), (x0, y0), (x1, y1), (x0, y1)] {
+        vertices.extend_from_slice(&[x, y, r, g, b, a]);
+    }
+}
+
+/// `TimelineRun` is a run of a band as drawn in window pixels
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineRun {
+    /// The window pixel where the run starts
+    pub start_x: f32,
+
+    /// The window pixel where the run ends
+    pub end_x: f32,
+
+    /// The name of the state of the run
+    pub label: String,
+
+    /// The color of the run
+    pub color: Rgb,
+
+    /// The seconds from the first sample of the run to the first sample of the next run, or to
+    /// the last sample for the newest run
+    pub duration_secs: u64,
+}
+
+/// `TimelineBand` is the area of a timeline source in window pixels and its runs, used to find
+/// the state under the mouse
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct TimelineBand {
+    /// The source drawn in the band
+    pub series: SeriesIndex,
+
+    /// The name of the source
+    pub name: String,
+
+    /// The area of the band in window pixels
+    pub rect: PixelRect,
+
+    /// The runs of the band from the oldest
+    pub runs: Vec<TimelineRun>,
+}
+
+impl TimelineBand {
+    /// `readout_at` returns the state under the x,y window pixel, None outside of the band
+    pub fn readout_at(&self, x: f32, y: f32) -> Option<TimelineReadout> {
+        if !self.rect.contains(x, y) {
+            return None;
+        }
+        let run = self.runs.iter().find(|run| x >= run.start_x && x <= run.end_x)?;
+        Some(TimelineReadout {
+            rect: self.rect,
+            text: format!(
+                "{}: {} for {}",
+                self.name,
+                run.label,
+                format_duration_secs(run.duration_secs)
+            ),
+            color: run.color,
+        })
+    }
+}
+
+/// `TimelineReadout` is the state of a band under the mouse, ready to be drawn as text
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimelineReadout {
+    /// The area of the band in window pixels
+    pub rect: PixelRect,
+
+    /// The name of the source, its state and how long it has been in it
+    pub text: String,
+
+    /// The color of the state
+    pub color: Rgb,
+}
@@ -1,5 +1,5 @@
 //! Hexagon Line Background Decorations
-use super::Decoration;
+use super::{Decoration, HexGridCache};
 use crate::term::SizeInfo;
 use serde::{Deserialize, Serialize};
 use vte::ansi::Rgb;
@@ -15,6 +15,16 @@ pub struct HexagonLineBackground {
     radius: f32,
     #[serde(default)]
     pub vecs: Vec<f32>,
+    /// The toggles that show the decoration, Always when unset
+    #[serde(default)]
+    pub visibility_group: Option<super::VisibilityGroup>,
+    /// The updates per second, DEFAULT_UPDATE_HZ when unset, 0 never updates once the vertices
+    /// are generated
+    #[serde(default)]
+    pub update_hz: Option<f32>,
+    /// The positions of the hexagons, kept across resizes
+    #[serde(skip)]
+    pub grid: HexGridCache,
 }
 
 impl HexagonLineBackground {
@@ -27,21 +37,16 @@ impl HexagonLineBackground {
             size_info,
             radius,
             vecs: vec![],
+            visibility_group: None,
+            update_hz: None,
+            grid: HexGridCache::default(),
         }
     }
 
     pub fn update_opengl_vecs(&mut self) {
-        let mut hexagons = vec![];
-        let coords = super::gen_hex_grid_positions(self.size_info, self.radius);
-        for coord in coords {
-            hexagons.append(&mut super::gen_2d_hexagon_vertices(
-                self.size_info,
-                coord.x,
-                coord.y,
-                self.radius,
-            ));
-        }
-        self.vecs = hexagons;
+        self.grid.update(self.size_info, self.radius);
+        self.vecs =
+            super::hexagon_line_vertices(self.size_info, self.grid.positions(), self.radius);
     }
 }
 
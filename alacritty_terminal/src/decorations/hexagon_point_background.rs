@@ -1,5 +1,6 @@
 //! Hexagon Point Background decoration
 //! TODO: Use lyon
+use super::HexGridCache;
 use crate::term::SizeInfo;
 use log::*;
 use rand::Rng;
@@ -50,6 +51,19 @@ pub struct HexagonPointBackground {
     /// The OpenGL representation of the dots for a buffer array object
     #[serde(default)]
     pub vecs: Vec<f32>,
+
+    /// The toggles that show the decoration, Always when unset
+    #[serde(default)]
+    pub visibility_group: Option<super::VisibilityGroup>,
+
+    /// The updates per second, DEFAULT_UPDATE_HZ when unset, 0 never updates once the vertices
+    /// are generated
+    #[serde(default)]
+    pub update_hz: Option<f32>,
+
+    /// The positions of the hexagons, kept across resizes
+    #[serde(skip)]
+    pub grid: HexGridCache,
 }
 
 impl Default for HexagonPointBackground {
@@ -69,6 +83,9 @@ impl Default for HexagonPointBackground {
             animation_offset: 0.0f32,
             next_update_epoch: start_animation_ms + animation_duration_ms,
             vecs: vec![],
+            visibility_group: None,
+            update_hz: None,
+            grid: HexGridCache::default(),
             animated: true,
         };
         res.update_opengl_vecs();
@@ -89,6 +106,9 @@ impl HexagonPointBackground {
             size_info,
             radius,
             vecs: vec![],
+            visibility_group: None,
+            update_hz: None,
+            grid: HexGridCache::default(),
             chosen_vertices: vec![],
             update_interval_s: 0i32,
             start_animation_ms: 0.0f32,
@@ -149,17 +169,9 @@ impl HexagonPointBackground {
     }
 
     pub fn update_opengl_vecs(&mut self) {
-        let mut hexagons = vec![];
-        let coords = super::gen_hex_grid_positions(self.size_info, self.radius);
-        for coord in coords {
-            hexagons.append(&mut super::gen_2d_hexagon_vertices(
-                self.size_info,
-                coord.x,
-                coord.y,
-                self.radius,
-            ));
-        }
-        self.vecs = hexagons;
+        self.grid.update(self.size_info, self.radius);
+        self.vecs =
+            super::hexagon_line_vertices(self.size_info, self.grid.positions(), self.radius);
         let hexagon_top_left_x = self.vecs[4];
         let hexagon_top_right_x = self.vecs[2];
         self.animation_offset = (hexagon_top_right_x - hexagon_top_left_x).abs();
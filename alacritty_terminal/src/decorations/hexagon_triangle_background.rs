@@ -1,7 +1,10 @@
 //! Hexagon Triangle Background decoration
 
-use crate::charts::deserialize_rgb_from_str;
+use super::HexGridCache;
+use crate::charts::color::RgbExt;
+use crate::charts::{deserialize_optional_rgb_from_str, deserialize_rgb_from_str};
 use crate::term::SizeInfo;
+use lyon::math::Point;
 use noise::{NoiseFn, Perlin};
 use serde::{Deserialize, Serialize};
 use vte::ansi::Rgb;
@@ -12,24 +15,50 @@ pub struct HexagonTriangleBackground {
     pub vertex_color: Rgb,
     #[serde(deserialize_with = "deserialize_rgb_from_str", default)]
     pub center_color: Rgb,
+    /// When set, the hexagons fade from the center_color at the center of the window to this
+    /// color at the borders, otherwise they all use the vertex_color
+    #[serde(deserialize_with = "deserialize_optional_rgb_from_str", default)]
+    pub edge_color: Option<Rgb>,
+    /// The exponent applied to the normalized distance to the center, values over 1.0 keep the
+    /// center_color for longer
+    #[serde(default = "default_falloff")]
+    pub falloff: f32,
     pub alpha: f32,
     #[serde(default)]
     pub size_info: SizeInfo,
     radius: f32,
     #[serde(default)]
     pub vecs: Vec<f32>,
+    /// The toggles that show the decoration, Always when unset
+    #[serde(default)]
+    pub visibility_group: Option<super::VisibilityGroup>,
+    /// The updates per second, DEFAULT_UPDATE_HZ when unset, 0 never updates once the vertices
+    /// are generated
+    #[serde(default)]
+    pub update_hz: Option<f32>,
     #[serde(skip)]
     pub noise: Perlin,
+    /// The positions of the hexagons, kept across resizes
+    #[serde(skip)]
+    pub grid: HexGridCache,
+}
+
+fn default_falloff() -> f32 {
+    1.0
 }
 
 impl PartialEq for HexagonTriangleBackground {
     fn eq(&self, rhs: &Self) -> bool {
         self.vertex_color == rhs.vertex_color
             && self.center_color == rhs.center_color
+            && self.edge_color == rhs.edge_color
+            && self.falloff == rhs.falloff
             && self.alpha == rhs.alpha
             && self.size_info == rhs.size_info
             && self.radius == rhs.radius
             && self.vecs == rhs.vecs
+            && self.visibility_group == rhs.visibility_group
+            && self.update_hz == rhs.update_hz
     }
 }
 
@@ -45,25 +74,59 @@ impl HexagonTriangleBackground {
         HexagonTriangleBackground {
             vertex_color,
             center_color,
+            edge_color: None,
+            falloff: default_falloff(),
             alpha,
             size_info,
             radius,
             vecs: vec![],
+            visibility_group: None,
+            update_hz: None,
             noise,
+            grid: HexGridCache::default(),
         }
     }
 
+    /// `with_edge_color` builder enables the gradient from the center_color to the edge_color
+    pub fn with_edge_color(mut self, edge_color: Rgb, falloff: f32) -> Self {
+        self.edge_color = Some(edge_color);
+        self.falloff = falloff;
+        self
+    }
+
+    /// `hexagon_color` returns the r,g,b of a hexagon at the normalized `distance` from the center
+    /// hexagon
+    pub fn hexagon_color(&self, distance: f32) -> [f32; 3] {
+        let edge_color = match self.edge_color {
+            Some(edge_color) => edge_color.to_normalized(),
+            None => return self.vertex_color.to_normalized(),
+        };
+        let center_color = self.center_color.to_normalized();
+        let factor = distance.clamp(0., 1.).powf(self.falloff);
+        [0, 1, 2].map(|idx| center_color[idx] + (edge_color[idx] - center_color[idx]) * factor)
+    }
+
     pub fn set_size_info(&mut self, size_info: SizeInfo) {
         self.size_info = size_info;
         self.update_opengl_vecs();
     }
 
     pub fn update_opengl_vecs(&mut self) {
-        let mut res = vec![];
         // To avoid colliding with the HexagonLines, the inner triangles ocupy a radius a bit
         // smaller
         let inner_hexagon_radius_percent = 0.92f32; // XXX: Maybe this can be a field?
-        let coords = super::gen_hex_grid_positions(self.size_info, self.radius);
+        self.grid.update(self.size_info, self.radius);
+        let coords = self.grid.positions();
+        // 6 triangles of 3 vertices with x,y,z,r,g,b,a per hexagon
+        let mut res = Vec::with_capacity(coords.len() * 18 * 7);
+        // The distances change with the window size, without an edge_color they are not needed.
+        let distances = if self.edge_color.is_some() {
+            let center_idx =
+                super::find_hexagon_grid_center_idx(coords, self.size_info, self.radius);
+            normalized_distances(coords, coords[center_idx])
+        } else {
+            vec![0f32; coords.len()]
+        };
         // TODO: The alpha should be calculated inside the shaders
         //          N
         //      3-------2
@@ -74,34 +137,33 @@ impl HexagonTriangleBackground {
         //     \         /
         //      5-------6
         //          S
-        let mut center = vec![
-            0.0f32, // x
-            0.0f32, // y
-            0.0f32, // z
-            <f32 as From<_>>::from(self.vertex_color.r) / 255.,
-            <f32 as From<_>>::from(self.vertex_color.g) / 255.,
-            <f32 as From<_>>::from(self.vertex_color.b) / 255.,
-            0.0f32, // a
-        ];
-        let sides = vec![
-            0f32, // x
-            0f32, // y
-            0f32, // z
-            <f32 as From<_>>::from(self.vertex_color.r) / 255.,
-            <f32 as From<_>>::from(self.vertex_color.g) / 255.,
-            <f32 as From<_>>::from(self.vertex_color.b) / 255.,
-            self.alpha,
-        ];
+        let [r, g, b] = self.vertex_color.to_normalized();
+        // x, y, z, r, g, b, a
+        let mut center = vec![0f32, 0f32, 0f32, r, g, b, 0f32];
+        let sides = vec![0f32, 0f32, 0f32, r, g, b, self.alpha];
         let mut east = sides.clone();
         let mut northeast = sides.clone();
         let mut northwest = sides.clone();
         let mut west = sides.clone();
         let mut southwest = sides.clone();
         let mut southeast = sides;
-        for coord in coords.iter() {
+        for (coord, distance) in coords.iter().zip(distances) {
             // The first pair of coordinates are the center of the hexagon
             center[0] = self.size_info.scale_x(coord.x);
             center[1] = self.size_info.scale_y(coord.y);
+            // The center vertex keeps its own alpha, only the color changes with the distance.
+            let color = self.hexagon_color(distance);
+            for vertex in [
+                &mut center,
+                &mut east,
+                &mut northeast,
+                &mut northwest,
+                &mut west,
+                &mut southwest,
+                &mut southeast,
+            ] {
+                vertex[3..6].copy_from_slice(&color);
+            }
             let hexagon_vertices = super::gen_2d_hexagon_vertices(
                 self.size_info,
                 coord.x,
@@ -122,29 +184,29 @@ impl HexagonTriangleBackground {
             southeast[0] = hexagon_vertices[10];
             southeast[1] = hexagon_vertices[11];
             // 0, 1, 2, // North-East triangle
-            res.append(&mut center.clone());
-            res.append(&mut east.clone());
-            res.append(&mut northeast.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&east);
+            res.extend_from_slice(&northeast);
             // 0, 2, 3, North triangle
-            res.append(&mut center.clone());
-            res.append(&mut northeast.clone());
-            res.append(&mut northwest.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&northeast);
+            res.extend_from_slice(&northwest);
             // 0, 3, 4, North-West triangle
-            res.append(&mut center.clone());
-            res.append(&mut northwest.clone());
-            res.append(&mut west.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&northwest);
+            res.extend_from_slice(&west);
             // 0, 4, 5, South-West triangle
-            res.append(&mut center.clone());
-            res.append(&mut west.clone());
-            res.append(&mut southwest.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&west);
+            res.extend_from_slice(&southwest);
             // 0, 5, 6, South triangle
-            res.append(&mut center.clone());
-            res.append(&mut southwest.clone());
-            res.append(&mut southeast.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&southwest);
+            res.extend_from_slice(&southeast);
             // 0, 6, 1, South-East triangle
-            res.append(&mut center.clone());
-            res.append(&mut southeast.clone());
-            res.append(&mut east.clone());
+            res.extend_from_slice(&center);
+            res.extend_from_slice(&southeast);
+            res.extend_from_slice(&east);
         }
         self.vecs = res;
     }
@@ -160,3 +222,102 @@ impl HexagonTriangleBackground {
         }
     }
 }
+
+/// `normalized_distances` returns the distance of each hexagon to the center hexagon, divided by
+/// the distance of the farthest hexagon so that the corners are at 1.0
+fn normalized_distances(coords: &[Point], center: Point) -> Vec<f32> {
+    let distances: Vec<f32> = coords.iter().map(|coord| coord.distance_to(center)).collect();
+    let max_distance = distances.iter().cloned().fold(0f32, f32::max);
+    if max_distance == 0. {
+        return vec![0f32; coords.len()];
+    }
+    distances.into_iter().map(|distance| distance / max_distance).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::decorations::{find_hexagon_grid_center_idx, gen_hex_grid_positions};
+
+    const CENTER_COLOR: Rgb = Rgb { r: 0, g: 16, b: 32 };
+    const EDGE_COLOR: Rgb = Rgb { r: 255, g: 128, b: 0 };
+
+    /// Each hexagon has 6 triangles of 3 xyzrgba vertices
+    fn hexagon_rgb(vecs: &[f32], hexagon_idx: usize) -> Vec<[f32; 3]> {
+        vecs[hexagon_idx * 18 * 7..(hexagon_idx + 1) * 18 * 7]
+            .chunks_exact(7)
+            .map(|vertex| [vertex[3], vertex[4], vertex[5]])
+            .collect()
+    }
+
+    fn gradient(size: SizeInfo, falloff: f32) -> HexagonTriangleBackground {
+        let mut hex = HexagonTriangleBackground::new(EDGE_COLOR, CENTER_COLOR, 0.05, size, 10.)
+            .with_edge_color(EDGE_COLOR, falloff);
+        hex.update_opengl_vecs();
+        hex
+    }
+
+    #[test]
+    fn it_fades_from_the_center_to_the_edge_color() {
+        for size in [
+            SizeInfo { width: 100., height: 100., ..Default::default() },
+            // Resizing recomputes the distances.
+            SizeInfo { width: 300., height: 120., ..Default::default() },
+        ] {
+            let mut hex = gradient(SizeInfo { width: 50., height: 50., ..Default::default() }, 1.0);
+            hex.set_size_info(size);
+            let coords = gen_hex_grid_positions(size, 10.);
+            let center_idx = find_hexagon_grid_center_idx(&coords, size, 10.);
+            let center = coords[center_idx];
+            let corner_idx = (0..coords.len())
+                .max_by(|a, b| {
+                    let a = coords[*a].distance_to(center);
+                    a.partial_cmp(&coords[*b].distance_to(center)).unwrap()
+                })
+                .unwrap();
+            for rgb in hexagon_rgb(&hex.vecs, center_idx) {
+                assert_eq!(rgb, CENTER_COLOR.to_normalized());
+            }
+            for rgb in hexagon_rgb(&hex.vecs, corner_idx) {
+                for (channel, expected) in rgb.iter().zip(EDGE_COLOR.to_normalized()) {
+                    assert!((channel - expected).abs() < 1. / 255., "{:?}", rgb);
+                }
+            }
+        }
+        // Without an edge_color every hexagon keeps the vertex_color.
+        let size = SizeInfo { width: 100., height: 100., ..Default::default() };
+        let mut hex = HexagonTriangleBackground::new(EDGE_COLOR, CENTER_COLOR, 0.05, size, 10.);
+        hex.update_opengl_vecs();
+        for hexagon_idx in 0..hex.vecs.len() / (18 * 7) {
+            for rgb in hexagon_rgb(&hex.vecs, hexagon_idx) {
+                assert_eq!(rgb, EDGE_COLOR.to_normalized());
+            }
+        }
+    }
+
+    #[test]
+    fn it_interpolates_monotonically_along_a_row() {
+        let size = SizeInfo { width: 300., height: 100., ..Default::default() };
+        let coords = gen_hex_grid_positions(size, 10.);
+        let center = coords[find_hexagon_grid_center_idx(&coords, size, 10.)];
+        // The hexagons to the right of the center, on the same row.
+        let mut row: Vec<usize> = (0..coords.len())
+            .filter(|idx| (coords[*idx].y - center.y).abs() < 0.01 && coords[*idx].x >= center.x)
+            .collect();
+        row.sort_by(|a, b| coords[*a].x.partial_cmp(&coords[*b].x).unwrap());
+        assert!(row.len() > 3);
+        let reds = |falloff: f32| -> Vec<f32> {
+            let hex = gradient(size, falloff);
+            row.iter().map(|idx| hexagon_rgb(&hex.vecs, *idx)[0][0]).collect()
+        };
+        let linear = reds(1.0);
+        let quadratic = reds(2.0);
+        for reds in [&linear, &quadratic] {
+            assert!(reds.windows(2).all(|pair| pair[0] < pair[1]), "{:?}", reds);
+        }
+        // A higher falloff keeps the center color for longer.
+        for (linear, quadratic) in linear.iter().zip(&quadratic).skip(1) {
+            assert!(quadratic < linear);
+        }
+    }
+}
@@ -2,6 +2,7 @@
 
 use super::moon_phase::MoonPhaseState;
 use super::PolarClockState;
+use crate::charts::timefmt::ChartTimezone;
 use crate::term::SizeInfo;
 use chrono::prelude::*;
 use lyon::tessellation as tess;
@@ -27,7 +28,7 @@ pub struct LyonDecoration {
     #[serde(default)]
     pub vertices: Vec<Vec<f32>>,
     #[serde(default = "local_now")]
-    pub now: DateTime<Local>,
+    pub now: DateTime<FixedOffset>,
     #[serde(default)]
     pub x: f32,
     #[serde(default)]
@@ -35,6 +36,16 @@ pub struct LyonDecoration {
     /// The last time the decoration was drawn.
     #[serde(default)]
     pub last_drawn_msecs: f32,
+    /// The toggles that show the decoration, Always when unset
+    #[serde(default)]
+    pub visibility_group: Option<super::VisibilityGroup>,
+    /// The updates per second, DEFAULT_UPDATE_HZ when unset, 0 never updates once the vertices
+    /// are generated
+    #[serde(default)]
+    pub update_hz: Option<f32>,
+    /// The zone of the polar clock, set from the timezone of the charts
+    #[serde(skip)]
+    pub timezone: ChartTimezone,
 }
 
 fn from_str_serde<'de, D>(deserializer: D) -> Result<Rgb, D::Error>
@@ -48,8 +59,8 @@ where
     Ok(Rgb::new(color.red as f32 / 255f32, color.green as f32 / 255f32, color.blue as f32 / 255f32))
 }
 
-fn local_now() -> DateTime<Local> {
-    Local::now()
+fn local_now() -> DateTime<FixedOffset> {
+    ChartTimezone::Local.now()
 }
 
 impl LyonDecoration {
@@ -59,7 +70,8 @@ impl LyonDecoration {
         let coord = coords[center_idx];
         // self.alpha = 0.7f32;
         // Store the center hexagon position for re-use later.
-        let now = Local::now();
+        let timezone = ChartTimezone::default();
+        let now = timezone.now();
         // TODO: Read the config and if props are provided give them below as param
         let polar_clock = PolarClockState::new(None);
         Self {
@@ -72,6 +84,9 @@ impl LyonDecoration {
             vertices: Default::default(),
             now,
             last_drawn_msecs: 0f32,
+            visibility_group: None,
+            update_hz: None,
+            timezone,
             x: coord.x,
             y: coord.y,
         }
@@ -84,7 +99,7 @@ impl LyonDecoration {
         self.x = coord.x;
         self.y = coord.y;
         self.size_info = size_info;
-        let now = Local::now();
+        let now = self.timezone.now();
         self.polar_clock.mark_as_dirty();
         self.polar_clock.tick(&now, self.x, self.y, self.radius, size_info, self.alpha);
         self.moon_state.mark_as_dirty();
@@ -92,9 +107,15 @@ impl LyonDecoration {
         self.update_opengl_vecs();
     }
 
+    /// `set_timezone` changes the zone of the polar clock, it is drawn in it on the next tick
+    pub fn set_timezone(&mut self, timezone: ChartTimezone) {
+        self.timezone = timezone;
+        self.polar_clock.mark_as_dirty();
+    }
+
     /// This is called regularly to potentially update the decoration vertices.
     pub fn tick(&mut self, time: f32) {
-        self.now = Local::now();
+        self.now = self.timezone.now();
         self.polar_clock.tick(&self.now, self.x, self.y, self.radius, self.size_info, self.alpha);
         self.moon_state.tick(self.x, self.y, self.radius, self.size_info);
         self.last_drawn_msecs = time;
@@ -1,5 +1,7 @@
 //! Decorations for the Alacritty Terminal.
 //!
+use crate::charts::animation::{TickSchedule, DEFAULT_UPDATE_HZ};
+use crate::charts::timefmt::ChartTimezone;
 use crate::term::SizeInfo;
 pub use hexagon_line_background::HexagonLineBackground;
 pub use hexagon_point_background::HexagonPointBackground;
@@ -8,8 +10,8 @@ use log::*;
 use lyon::math::point;
 pub use lyon_decor::LyonDecoration;
 pub use polar_clock::PolarClockState;
-use serde::{Deserialize, Serialize};
-use std::time::Instant;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::time::{Duration, Instant};
 
 pub mod hexagon_line_background;
 pub mod hexagon_point_background;
@@ -36,11 +38,82 @@ pub trait Decoration {
 #[derive(Default, Debug, Serialize, Deserialize, PartialEq, Clone)]
 pub struct DecorationsConfig {
     /// An array of active decorators
+    #[serde(deserialize_with = "deserialize_decorators")]
     pub decorators: Vec<DecorationTypes>,
 
-    /// The time at which config was initialized
+    /// The animation clock of each decorator, it only runs while the decorator is visible
     #[serde(skip)]
-    init_start: Option<Instant>,
+    clocks: Vec<AnimationClock>,
+
+    /// The update cadence of each decorator, the ticks are skipped until its period elapsed
+    #[serde(skip)]
+    schedules: Vec<TickSchedule>,
+
+    /// The instant of the last tick, the schedules advance by the time since then
+    #[serde(skip)]
+    last_tick: Option<Instant>,
+}
+
+/// `VisibilityGroup` is the toggle a decoration is shown with
+#[derive(Debug, Serialize, Deserialize, PartialEq, Eq, Clone, Copy)]
+pub enum VisibilityGroup {
+    /// The decoration is shown regardless of the charts and decorations toggles
+    Always,
+    /// The decoration is shown while the charts are shown
+    WithCharts,
+    /// The decoration is shown while the decorations are enabled
+    WithDecorToggle,
+}
+
+/// The visibility group of the background decorations that do not set one
+pub const DEFAULT_BACKGROUND_VISIBILITY: VisibilityGroup = VisibilityGroup::Always;
+
+/// The visibility group of the chart decorations that do not set one
+pub const DEFAULT_CHART_DECORATION_VISIBILITY: VisibilityGroup = VisibilityGroup::WithCharts;
+
+impl VisibilityGroup {
+    /// `is_visible` returns true when the decorations of the group are shown with the current
+    /// state of the charts and decorations toggles
+    pub fn is_visible(self, charts_enabled: bool, decorations_enabled: bool) -> bool {
+        match self {
+            VisibilityGroup::Always => true,
+            VisibilityGroup::WithCharts => charts_enabled,
+            VisibilityGroup::WithDecorToggle => decorations_enabled,
+        }
+    }
+}
+
+/// `AnimationClock` is the time a decoration has been animated, the time it spends suspended is
+/// not accumulated so that it resumes where it was paused.
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct AnimationClock {
+    /// The animation time accumulated up to `running_since`
+    elapsed: Duration,
+
+    /// The time of the last tick while visible, None while suspended
+    running_since: Option<Instant>,
+}
+
+impl AnimationClock {
+    /// `advance` accumulates the time since the previous visible tick, the clock keeps running
+    /// while `visible`. Returns the animation time in seconds.
+    pub fn advance(&mut self, now: Instant, visible: bool) -> f32 {
+        if let Some(since) = self.running_since {
+            self.elapsed += now.saturating_duration_since(since);
+        }
+        self.running_since = if visible { Some(now) } else { None };
+        self.elapsed_secs()
+    }
+
+    /// `elapsed_secs` returns the animation time in seconds
+    pub fn elapsed_secs(&self) -> f32 {
+        self.elapsed.as_secs_f32()
+    }
+
+    /// `is_running` returns true when the decoration was visible on its last tick
+    pub fn is_running(&self) -> bool {
+        self.running_since.is_some()
+    }
 }
 
 impl DecorationsConfig {
@@ -54,6 +127,17 @@ impl DecorationsConfig {
         }
     }
 
+    /// `set_timezone` sets the zone the decorations that show the time are drawn in
+    pub fn set_timezone(&mut self, timezone: ChartTimezone) {
+        for decor in self.decorators.iter_mut() {
+            if let DecorationTypes::Triangles(tris) = decor {
+                if let DecorationTriangles::Lyon(lyon_decor) = &mut **tris {
+                    lyon_decor.set_timezone(timezone);
+                }
+            }
+        }
+    }
+
     /// `from_optional_decor_config` transforms an optional DecorationsConfig into an
     /// DecorationsConfig with resized vector items
     pub fn optional_decor_to_sized(config_decorations: Option<Self>, size_info: SizeInfo) -> Self {
@@ -69,44 +153,106 @@ impl DecorationsConfig {
         }
     }
 
-    pub fn get_elapsed_secs_with_millis(&self) -> f32 {
-        let mut res = 0.0f32;
-        if let Some(val) = self.init_start {
-            let elapsed = val.elapsed();
-            res = elapsed.as_secs_f32();
-        }
-        res
+    /// `tick` calls the underlying decorators to update decorations that depend on time
+    /// such as animations, the decorators hidden by their visibility group are suspended.
+    /// Returns the number of decorators that were updated.
+    pub fn tick(&mut self, charts_enabled: bool, decorations_enabled: bool) -> usize {
+        self.tick_at(Instant::now(), charts_enabled, decorations_enabled)
     }
 
-    /// `tick` calls the underlying decorators to update decorations that depend on time
-    /// such as animations
-    pub fn tick(&mut self) {
-        let elapsed_secs_with_millis = self.get_elapsed_secs_with_millis();
-        for decor in self.decorators.iter_mut() {
-            decor.tick(elapsed_secs_with_millis);
+    /// `tick_at` ticks the visible decorators at the `now` instant, each decorator is only
+    /// updated once the period of its update rate elapsed. Returns the number of decorators that
+    /// were updated.
+    pub fn tick_at(
+        &mut self,
+        now: Instant,
+        charts_enabled: bool,
+        decorations_enabled: bool,
+    ) -> usize {
+        self.clocks.resize_with(self.decorators.len(), AnimationClock::default);
+        if self.schedules.len() != self.decorators.len() {
+            self.schedules = self.new_schedules();
+        }
+        let elapsed = match self.last_tick {
+            Some(last_tick) => now.saturating_duration_since(last_tick),
+            None => Duration::ZERO,
+        };
+        self.last_tick = Some(now);
+        let mut updated = 0;
+        let decorators = self.decorators.iter_mut().zip(self.clocks.iter_mut());
+        for ((decor, clock), schedule) in decorators.zip(self.schedules.iter_mut()) {
+            let visible = decor.visibility_group().is_visible(charts_enabled, decorations_enabled);
+            let elapsed_secs = clock.advance(now, visible);
+            if schedule.advance(elapsed) && visible {
+                decor.tick(elapsed_secs);
+                updated += 1;
+            }
         }
+        updated
+    }
+
+    /// `update_rates` returns the updates per second of each decorator, the decoration ticks of
+    /// the coordinator follow the fastest one
+    pub fn update_rates(&self) -> Vec<f32> {
+        self.decorators.iter().map(|decor| decor.update_hz()).collect()
+    }
+
+    /// `new_schedules` returns the update cadence of each decorator
+    fn new_schedules(&self) -> Vec<TickSchedule> {
+        self.decorators.iter().map(|decor| TickSchedule::new(decor.update_hz())).collect()
+    }
+
+    /// `visible_decorators` returns the decorators visible on the last tick along with their
+    /// animation time in seconds
+    pub fn visible_decorators(&self) -> impl Iterator<Item = (&DecorationTypes, f32)> {
+        self.decorators
+            .iter()
+            .zip(self.clocks.iter())
+            .filter(|(_, clock)| clock.is_running())
+            .map(|(decor, clock)| (decor, clock.elapsed_secs()))
     }
 
     /// `init_timers` will initialize times/epochs in the animation to some chosen defaults
     pub fn init_timers(&mut self) {
         let curr_time = Instant::now();
-        self.init_start = Some(curr_time);
+        self.clocks = vec![AnimationClock::default(); self.decorators.len()];
+        self.schedules = self.new_schedules();
+        self.last_tick = None;
         for decor in self.decorators.iter_mut() {
             decor.init_timers(curr_time);
         }
     }
 }
 
+/// `deserialize_decorators` loads the decorators array, empty entries (i.e. `- ~`) are dropped
+/// with a warning instead of reaching the renderer. An unknown `type` is still an error that lists
+/// the valid decoration types.
+fn deserialize_decorators<'de, D>(de: D) -> Result<Vec<DecorationTypes>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let decorators: Vec<Option<DecorationTypes>> = Vec::deserialize(de)?;
+    Ok(decorators
+        .into_iter()
+        .enumerate()
+        .filter_map(|(idx, decor)| {
+            if decor.is_none() {
+                warn!("Ignoring empty decoration at decorators[{}]", idx);
+            }
+            decor
+        })
+        .collect())
+}
+
 // TODO: Maybe we can change the <Type>(Decor<Type>) to simply Decor<Type>
 /// DecorationTypes Groups available decorations
-#[derive(Debug, Default, Serialize, Deserialize, PartialEq, Clone)]
+#[derive(Debug, Serialize, Deserialize, PartialEq, Clone)]
 #[serde(tag = "type", content = "props")]
+#[non_exhaustive]
 pub enum DecorationTypes {
     Lines(DecorationLines),
     Triangles(Box<DecorationTriangles>),
     Points(DecorationPoints),
-    #[default]
-    None,
 }
 
 impl DecorationTypes {
@@ -122,9 +268,6 @@ impl DecorationTypes {
             DecorationTypes::Lines(ref mut hexagon_lines) => {
                 hexagon_lines.set_size_info(size_info);
             },
-            DecorationTypes::None => {
-                unreachable!("Attempting to update decorations on None variant");
-            },
         }
     }
 
@@ -133,10 +276,40 @@ impl DecorationTypes {
         match self {
             DecorationTypes::Points(ref mut hexagon_points) => hexagon_points.tick(time),
             DecorationTypes::Triangles(ref mut tris) => tris.tick(time),
-            _ => {},
+            DecorationTypes::Lines(_) => {},
         }
     }
 
+    /// `visibility_group` returns the toggle the decoration is shown with
+    pub fn visibility_group(&self) -> VisibilityGroup {
+        let group = match self {
+            DecorationTypes::Lines(DecorationLines::Hexagon(hex_lines)) => {
+                hex_lines.visibility_group
+            },
+            DecorationTypes::Points(DecorationPoints::Hexagon(hex_points)) => {
+                hex_points.visibility_group
+            },
+            DecorationTypes::Triangles(tris) => match &**tris {
+                DecorationTriangles::Hexagon(hex_tris) => hex_tris.visibility_group,
+                DecorationTriangles::Lyon(lyon_decor) => lyon_decor.visibility_group,
+            },
+        };
+        group.unwrap_or(DEFAULT_BACKGROUND_VISIBILITY)
+    }
+
+    /// `update_hz` returns the updates per second of the decoration
+    pub fn update_hz(&self) -> f32 {
+        let update_hz = match self {
+            DecorationTypes::Lines(DecorationLines::Hexagon(hex_lines)) => hex_lines.update_hz,
+            DecorationTypes::Points(DecorationPoints::Hexagon(hex_points)) => hex_points.update_hz,
+            DecorationTypes::Triangles(tris) => match &**tris {
+                DecorationTriangles::Hexagon(hex_tris) => hex_tris.update_hz,
+                DecorationTriangles::Lyon(lyon_decor) => lyon_decor.update_hz,
+            },
+        };
+        update_hz.unwrap_or(DEFAULT_UPDATE_HZ)
+    }
+
     /// `init_timers` will initialize times/epochs in the animation to some chosen defaults
     pub fn init_timers(&mut self, time: Instant) {
         if let DecorationTypes::Points(ref mut hexagon_points) = self {
@@ -232,9 +405,73 @@ impl DecorationTriangles {
     }
 }
 
+/// `HexGridCache` keeps the positions of the hexagons of a background grid, they only change
+/// when the number of rows or columns that fit in the window changes, otherwise a resize only
+/// projects the same positions to the new size.
+#[derive(Debug, Default, Clone)]
+pub struct HexGridCache {
+    /// The (rows, columns) and radius of the cached positions
+    key: Option<(usize, usize, f32)>,
+
+    /// The center of each hexagon in pixels
+    positions: Vec<lyon::math::Point>,
+
+    /// The number of times the positions have been generated
+    pub generations: usize,
+}
+
+impl HexGridCache {
+    /// `update` generates the positions again when the grid for the size has a different number
+    /// of rows or columns, returns true when they were generated.
+    pub fn update(&mut self, size: SizeInfo, radius: f32) -> bool {
+        let (rows, columns) = hex_grid_dimensions(size, radius);
+        if self.key == Some((rows, columns, radius)) {
+            return false;
+        }
+        self.key = Some((rows, columns, radius));
+        self.positions = hex_grid_positions(rows, columns, radius);
+        self.generations += 1;
+        true
+    }
+
+    /// `positions` returns the center of each hexagon of the latest update
+    pub fn positions(&self) -> &[lyon::math::Point] {
+        &self.positions
+    }
+}
+
+impl PartialEq for HexGridCache {
+    /// The cache does not change what is drawn
+    fn eq(&self, _rhs: &Self) -> bool {
+        true
+    }
+}
+
+/// `hexagon_line_vertices` returns the vertices of the hexagons centered at `positions`, the
+/// output is allocated once for all the hexagons.
+pub fn hexagon_line_vertices(
+    size_info: SizeInfo,
+    positions: &[lyon::math::Point],
+    radius: f32,
+) -> Vec<f32> {
+    let mut res = Vec::with_capacity(positions.len() * 12);
+    for position in positions {
+        push_2d_hexagon_vertices(&mut res, size_info, position.x, position.y, radius);
+    }
+    res
+}
+
 /// `gen_2d_hexagon_vertices` Returns the vertices for an hexagon created at center x,y with a
 /// specific radius
 pub fn gen_2d_hexagon_vertices(size_info: SizeInfo, x: f32, y: f32, radius: f32) -> Vec<f32> {
+    let mut res = Vec::with_capacity(12);
+    push_2d_hexagon_vertices(&mut res, size_info, x, y, radius);
+    res
+}
+
+/// `push_2d_hexagon_vertices` appends the vertices for an hexagon created at center x,y with a
+/// specific radius
+fn push_2d_hexagon_vertices(res: &mut Vec<f32>, size_info: SizeInfo, x: f32, y: f32, radius: f32) {
     let x_60_degrees_offset = COS_60 * radius;
     let y_60_degrees_offset = SIN_60 * radius;
     // Order of vertices:
@@ -245,7 +482,7 @@ pub fn gen_2d_hexagon_vertices(size_info: SizeInfo, x: f32, y: f32, radius: f32)
     //  \           /
     //   \         /
     //    5-------6
-    vec![
+    res.extend_from_slice(&[
         // Mid right:
         size_info.scale_x(x + radius),
         size_info.scale_y(y),
@@ -264,11 +501,37 @@ pub fn gen_2d_hexagon_vertices(size_info: SizeInfo, x: f32, y: f32, radius: f32)
         // Bottom Right
         size_info.scale_x(x + x_60_degrees_offset),
         size_info.scale_y(y - y_60_degrees_offset),
-    ]
+    ]);
 }
 
 /// Creates a vector with x,y,z coordinates in which new hexagons can be drawn
 fn gen_hex_grid_positions(size: SizeInfo, radius: f32) -> Vec<lyon::math::Point> {
+    let (rows, columns) = hex_grid_dimensions(size, radius);
+    hex_grid_positions(rows, columns, radius)
+}
+
+/// `hex_grid_dimensions` returns the number of (rows, columns) of hexagons needed to cover the
+/// window, the positions are accumulated the same way as in `hex_grid_positions`.
+fn hex_grid_dimensions(size: SizeInfo, radius: f32) -> (usize, usize) {
+    let x_offset = COS_60 * radius;
+    let y_offset = SIN_60 * radius;
+    let mut columns = 0usize;
+    let mut current_x_position = 0f32;
+    while current_x_position < (size.width + x_offset) {
+        columns += 1;
+        current_x_position += x_offset * 3f32;
+    }
+    let mut rows = 0usize;
+    let mut temp_y = 0f32;
+    while temp_y <= (size.height + y_offset) {
+        rows += 1;
+        temp_y += y_offset * 2f32;
+    }
+    (rows, columns)
+}
+
+/// `hex_grid_positions` returns the center of the hexagons of a grid, column by column
+fn hex_grid_positions(rows: usize, columns: usize, radius: f32) -> Vec<lyon::math::Point> {
     // We only care for the 60 degrees X,Y,Z, the rest we can calculate from this distance.
     // For the degrees at 0, X is the radius, and Y is 0.
     // let angle = 60.0f32; // Hexagon degrees
@@ -281,11 +544,11 @@ fn gen_hex_grid_positions(size: SizeInfo, radius: f32) -> Vec<lyon::math::Point>
     let mut current_x_position = 0f32;
     // When true, we will add half radius to Y to make sure the hexagons do not overlap
     let mut half_offset = true;
-    let mut res = vec![];
-    while current_x_position < (size.width + x_offset) {
+    let mut res = Vec::with_capacity(rows * columns);
+    for _ in 0..columns {
         let current_y_position = 0f32;
         let mut temp_y = current_y_position;
-        while temp_y <= (size.height + y_offset) {
+        for _ in 0..rows {
             res.push(point(
                 current_x_position,
                 // shift the y position in alternate fashion that the positions look like:
@@ -360,4 +623,247 @@ mod tests {
         assert_eq!(x_hex_n, 8);
         assert_eq!(hex_coords.len(), 56);
     }
+
+    #[test]
+    fn it_reuses_the_hexagon_grid_on_resize() {
+        let size = SizeInfo { width: 100., height: 100., ..Default::default() };
+        let color = vte::ansi::Rgb { r: 25, g: 88, b: 167 };
+        let mut lines = HexagonLineBackground::new(color, 0.4, size, 10.);
+        let mut triangles = HexagonTriangleBackground::new(color, color, 0.05, size, 10.);
+        let resizes = [
+            // The same rows and columns fit the window, only the projection changes.
+            (SizeInfo { width: 102., height: 101., ..size }, 1),
+            (SizeInfo { width: 103., height: 100., ..size }, 1),
+            // An extra column fits the window.
+            (SizeInfo { width: 200., height: 100., ..size }, 2),
+            (size, 3),
+        ];
+        for (resized, generations) in resizes {
+            lines.size_info = resized;
+            lines.update_opengl_vecs();
+            triangles.set_size_info(resized);
+            assert_eq!(lines.grid.generations, generations);
+            assert_eq!(triangles.grid.generations, generations);
+            let mut naive = vec![];
+            for coord in gen_hex_grid_positions(resized, 10.) {
+                naive.append(&mut gen_2d_hexagon_vertices(resized, coord.x, coord.y, 10.));
+            }
+            assert_eq!(lines.vecs, naive);
+            assert_eq!(lines.vecs.capacity(), naive.len());
+            assert_eq!(triangles.vecs.len(), naive.len() / 12 * 18 * 7);
+        }
+    }
+
+    const MIXED_DECORATIONS: &str = r#"
+decorators:
+  - type: Lines
+    props:
+      type: Hexagon
+      props:
+        color: { r: 25, g: 88, b: 167 }
+        alpha: 0.4
+        radius: 100
+  - type: Triangles
+    props:
+      type: Hexagon
+      props:
+        vertex_color: "0x1958a7"
+        center_color: "0x000000"
+        alpha: 0.05
+        radius: 100
+  - type: Points
+    props:
+      type: Hexagon
+      props:
+        color: { r: 25, g: 88, b: 167 }
+        alpha: 0.7
+        radius: 100
+  - type: Triangles
+    props:
+      type: Lyon
+      props:
+        color: "0x1958a7"
+        alpha: 0.2
+        radius: 100
+"#;
+
+    #[test]
+    fn it_rejects_unknown_decoration_types() {
+        let config = r#"
+decorators:
+  - type: Lnes
+    props:
+      type: Hexagon
+      props:
+        color: { r: 25, g: 88, b: 167 }
+        alpha: 0.4
+        radius: 100
+"#;
+        let err = serde_yaml::from_str::<DecorationsConfig>(config).unwrap_err().to_string();
+        assert!(err.contains("Lnes"), "{}", err);
+        assert!(err.contains("Lines"), "{}", err);
+        assert!(err.contains("Triangles"), "{}", err);
+        assert!(err.contains("Points"), "{}", err);
+    }
+
+    #[test]
+    fn it_loads_empty_decorators() {
+        let config: DecorationsConfig = serde_yaml::from_str("decorators: []").unwrap();
+        assert!(config.decorators.is_empty());
+        let config: DecorationsConfig = serde_yaml::from_str("decorators:\n  - ~\n").unwrap();
+        assert!(config.decorators.is_empty());
+    }
+
+    #[test]
+    fn it_loads_mixed_decorators() {
+        let config: DecorationsConfig = serde_yaml::from_str(MIXED_DECORATIONS).unwrap();
+        assert_eq!(config.decorators.len(), 4);
+        assert!(matches!(config.decorators[0], DecorationTypes::Lines(_)));
+        assert!(matches!(config.decorators[1], DecorationTypes::Triangles(_)));
+        assert!(matches!(config.decorators[2], DecorationTypes::Points(_)));
+        assert!(matches!(config.decorators[3], DecorationTypes::Triangles(_)));
+    }
+
+    #[test]
+    fn it_shows_the_decorations_by_visibility_group() {
+        let size = SizeInfo { width: 100., height: 100., ..Default::default() };
+        let color = vte::ansi::Rgb { r: 25, g: 88, b: 167 };
+        let groups = [
+            None,
+            Some(VisibilityGroup::Always),
+            Some(VisibilityGroup::WithCharts),
+            Some(VisibilityGroup::WithDecorToggle),
+        ];
+        let mut config = DecorationsConfig::default();
+        for group in groups {
+            let mut lines = HexagonLineBackground::new(color, 0.4, size, 10.);
+            lines.visibility_group = group;
+            config.decorators.push(DecorationTypes::Lines(DecorationLines::Hexagon(lines)));
+        }
+        config.set_size_info(size);
+        config.init_timers();
+        // The decorators with vertices for each (charts_enabled, decorations_enabled).
+        let matrix = [
+            ((true, true), vec![0, 1, 2, 3]),
+            ((true, false), vec![0, 1, 2]),
+            ((false, true), vec![0, 1, 3]),
+            ((false, false), vec![0, 1]),
+        ];
+        for ((charts_enabled, decorations_enabled), expected) in matrix {
+            config.tick(charts_enabled, decorations_enabled);
+            let visible: Vec<usize> = config
+                .visible_decorators()
+                .map(|(decor, _)| {
+                    let idx = config.decorators.iter().position(|d| std::ptr::eq(d, decor));
+                    match decor {
+                        DecorationTypes::Lines(DecorationLines::Hexagon(lines)) => {
+                            assert!(!lines.vecs.is_empty());
+                        },
+                        decor => panic!("Unexpected decoration: {:?}", decor),
+                    }
+                    idx.unwrap()
+                })
+                .collect();
+            assert_eq!(
+                visible, expected,
+                "charts {} decorations {}",
+                charts_enabled, decorations_enabled
+            );
+        }
+    }
+
+    #[test]
+    fn it_suspends_the_animation_of_hidden_decorations() {
+        let start = Instant::now();
+        let secs = |secs: u64| start + Duration::from_secs(secs);
+        let mut clock = AnimationClock::default();
+        assert_eq!(clock.advance(secs(0), true), 0.);
+        assert_eq!(clock.advance(secs(2), true), 2.);
+        // The time while hidden is not accumulated.
+        assert_eq!(clock.advance(secs(3), false), 3.);
+        assert!(!clock.is_running());
+        assert_eq!(clock.advance(secs(10), false), 3.);
+        assert_eq!(clock.advance(secs(20), true), 3.);
+        assert_eq!(clock.advance(secs(21), true), 4.);
+        let config = MIXED_DECORATIONS
+            .replace("radius: 100", "radius: 100\n        visibility_group: WithCharts");
+        let mut config: DecorationsConfig = serde_yaml::from_str(&config).unwrap();
+        config.init_timers();
+        config.tick_at(secs(0), false, true);
+        assert_eq!(config.visible_decorators().count(), 0);
+        config.tick_at(secs(5), true, true);
+        config.tick_at(secs(6), true, true);
+        let elapsed: Vec<f32> = config.visible_decorators().map(|(_, elapsed)| elapsed).collect();
+        assert_eq!(elapsed, vec![1.; 4]);
+    }
+
+    #[test]
+    fn it_updates_each_decoration_at_its_own_rate() {
+        let point = |update_hz: &str| {
+            format!(
+                r#"
+  - type: Points
+    props:
+      type: Hexagon
+      props:
+        color: {{ r: 25, g: 88, b: 167 }}
+        alpha: 0.7
+        radius: 100
+        update_hz: {}"#,
+                update_hz
+            )
+        };
+        let config = format!("decorators:{}{}{}\n", point("1"), point("10"), point("0"));
+        let mut config: DecorationsConfig = serde_yaml::from_str(&config).unwrap();
+        assert_eq!(config.update_rates(), vec![1., 10., 0.]);
+        config.set_size_info(SizeInfo { width: 100., height: 100., ..Default::default() });
+        config.init_timers();
+        let start = Instant::now();
+        let tick = Duration::from_millis(crate::charts::animation::DECORATION_TICK_MS);
+        // 2 seconds of decoration ticks, the first one starts the schedules.
+        let mut redraws = 0;
+        for step in 0..=20 {
+            if config.tick_at(start + tick * step, true, true) > 0 {
+                redraws += 1;
+            }
+        }
+        let updates: Vec<u64> =
+            config.schedules.iter().map(|schedule| schedule.updates()).collect();
+        assert_eq!(updates, vec![2, 20, 0]);
+        assert_eq!(redraws, 20);
+        // The decoration at 0 Hz kept the vertices of its initial generation.
+        match &config.decorators[2] {
+            DecorationTypes::Points(DecorationPoints::Hexagon(points)) => {
+                assert!(!points.vecs.is_empty())
+            },
+            decor => panic!("Unexpected decoration: {:?}", decor),
+        }
+    }
+
+    #[test]
+    fn it_sizes_every_decoration_type() {
+        let size = SizeInfo { width: 100., height: 100., ..Default::default() };
+        let mut config: DecorationsConfig = serde_yaml::from_str(MIXED_DECORATIONS).unwrap();
+        config.init_timers();
+        for decor in config.decorators.iter_mut() {
+            decor.set_size_info(size);
+            decor.tick(1.);
+        }
+        let color = vte::ansi::Rgb { r: 25, g: 88, b: 167 };
+        let mut decors = vec![
+            DecorationTypes::Lines(DecorationLines::Hexagon(HexagonLineBackground::new(
+                color, 0.4, size, 10.,
+            ))),
+            DecorationTypes::Points(DecorationPoints::Hexagon(HexagonPointBackground::new(
+                color, 0.7, size, 10.,
+            ))),
+            DecorationTypes::Triangles(Box::new(DecorationTriangles::Hexagon(Box::new(
+                HexagonTriangleBackground::new(color, color, 0.05, size, 10.),
+            )))),
+        ];
+        for decor in decors.iter_mut() {
+            decor.set_size_info(size);
+            decor.tick(1.);
+        }
+    }
 }
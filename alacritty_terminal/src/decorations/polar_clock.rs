@@ -199,7 +199,7 @@ impl PolarClockUnit {
 
     /// Returns the whiskers that are useful to understand clearly the time progression across
     /// its domain, i.e. for the month, one tick per month, for hour, one tick every 3 hours, etc.
-    pub fn get_unit_whiskers(&self, _input_time: &DateTime<Local>) -> Vec<u32> {
+    pub fn get_unit_whiskers(&self, _input_time: &DateTime<FixedOffset>) -> Vec<u32> {
         // TODO: use input_time to return special days of like weekends
         match self {
             Self::DayOfYear => vec![0],
@@ -212,7 +212,7 @@ impl PolarClockUnit {
     }
 
     /// Gets the current time unit value
-    pub fn get_time_unit_value(&self, input_time: &DateTime<Local>) -> u32 {
+    pub fn get_time_unit_value(&self, input_time: &DateTime<FixedOffset>) -> u32 {
         match self {
             Self::DayOfYear => input_time.ordinal(),
             Self::MonthOfYear => input_time.month(),
@@ -228,7 +228,7 @@ impl PolarClockUnit {
 
     /// Returns the max value for the current time unit in a time frame, for example, for a year it
     /// would return the number of days in the current year, or month, or etc.
-    pub fn get_time_unit_max_value(&self, input_time: &DateTime<Local>) -> u32 {
+    pub fn get_time_unit_max_value(&self, input_time: &DateTime<FixedOffset>) -> u32 {
         match self {
             Self::DayOfYear => Self::day_of_year_max_value(input_time),
             Self::MonthOfYear => 12,
@@ -241,7 +241,7 @@ impl PolarClockUnit {
 
     /// Find the number of days in the current year by getting the first day of the current year
     /// and the first day of the next year and substracting them
-    pub fn day_of_year_max_value(input_time: &DateTime<Local>) -> u32 {
+    pub fn day_of_year_max_value(input_time: &DateTime<FixedOffset>) -> u32 {
         let first_day_of_year = NaiveDate::from_ymd_opt(input_time.year(), 1, 1).unwrap();
         let first_day_of_next_year = NaiveDate::from_ymd_opt(input_time.year() + 1, 1, 1).unwrap();
         first_day_of_next_year.signed_duration_since(first_day_of_year).num_days() as u32
@@ -249,7 +249,7 @@ impl PolarClockUnit {
 
     /// Find the number of days in the current month by getting the first day of the current month
     /// and the first day of the next month and substracting them
-    pub fn day_of_month_max_value(input_time: &DateTime<Local>) -> u32 {
+    pub fn day_of_month_max_value(input_time: &DateTime<FixedOffset>) -> u32 {
         let first_day_of_next_year = NaiveDate::from_ymd_opt(input_time.year() + 1, 1, 1).unwrap();
         let first_day_of_next_month = if input_time.month() == 12 {
             first_day_of_next_year
@@ -310,7 +310,7 @@ impl PolarClockUnitState {
     /// Updates the vertices of the arc if needed.
     pub fn tick(
         &mut self,
-        tick_time: &DateTime<Local>,
+        tick_time: &DateTime<FixedOffset>,
         x: f32,
         y: f32,
         radius: f32,
@@ -337,7 +337,7 @@ impl PolarClockUnitState {
     /// Creates vertices for the Polar Clock Arc
     fn gen_vertices(
         &self,
-        tick_time: &DateTime<Local>,
+        tick_time: &DateTime<FixedOffset>,
         x: f32,
         y: f32,
         radius: f32,
@@ -430,7 +430,7 @@ impl PolarClockState {
     /// Calculates the vertices of the polar clock if needed.
     pub fn tick(
         &mut self,
-        tick_time: &DateTime<Local>,
+        tick_time: &DateTime<FixedOffset>,
         x: f32,
         y: f32,
         radius: f32,
@@ -459,7 +459,8 @@ mod tests {
             .unwrap()
             .and_hms_milli_opt(12, 34, 56, 789)
             .unwrap();
-        let dt = DateTime::<Local>::from_naive_utc_and_offset(naivedatetime_west, timezone_west);
+        let dt =
+            DateTime::<FixedOffset>::from_naive_utc_and_offset(naivedatetime_west, timezone_west);
         assert_eq!(seconds_with_millis.get_time_unit_value(&dt), 56_789);
         let day_of_year = PolarClockUnit::DayOfYear;
         assert_eq!(day_of_year.get_time_unit_value(&dt), 11);
@@ -61,6 +61,9 @@ pub enum Event {
 
     /// Chart data has been updated and charts should be redrawn
     ChartEvent,
+
+    /// The charts need the attention of the user, i.e. their data stopped loading
+    ChartAlert(String),
 }
 
 impl Debug for Event {
@@ -81,6 +84,7 @@ impl Debug for Event {
             Event::ChildExit(code) => write!(f, "ChildExit({code})"),
             Event::DecorEvent => write!(f, "DecorEvent"),
             Event::ChartEvent => write!(f, "ChartEvent"),
+            Event::ChartAlert(text) => write!(f, "ChartAlert({text})"),
         }
     }
 }
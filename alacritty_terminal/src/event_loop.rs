@@ -17,10 +17,12 @@ use polling::{Event as PollingEvent, Events, PollMode};
 use crate::event::{self, Event, EventListener, WindowSize};
 use crate::sync::FairMutex;
 use crate::term::Term;
+#[cfg(feature = "charts")]
 use crate::term::TermChartsHandle;
 use crate::vte::ansi;
 use crate::{thread, tty};
 
+#[cfg(feature = "charts")]
 use crate::async_utils::AsyncTask;
 
 /// Max bytes to read from the PTY before forced terminal synchronization.
@@ -55,6 +57,7 @@ pub struct EventLoop<T: tty::EventedPty, U: EventListener> {
     event_proxy: U,
     hold: bool,
     ref_test: bool,
+    #[cfg(feature = "charts")]
     tokio_setup: Option<TermChartsHandle>,
 }
 
@@ -82,6 +85,7 @@ where
             event_proxy,
             hold,
             ref_test,
+            #[cfg(feature = "charts")]
             tokio_setup: None,
         })
     }
@@ -90,6 +94,7 @@ where
         EventLoopSender { sender: self.tx.clone(), poller: self.poll.clone() }
     }
 
+    #[cfg(feature = "charts")]
     pub fn set_tokio_setup(&mut self, tokio_setup: TermChartsHandle) {
         self.tokio_setup = Some(tokio_setup)
     }
@@ -103,14 +108,17 @@ where
                 Msg::Input(input) => state.write_list.push_back(input),
                 Msg::Resize(window_size) => self.pty.on_resize(window_size),
                 Msg::Shutdown => {
+                    #[cfg(feature = "charts")]
                     if let Some(tokio_setup) = &self.tokio_setup {
                         let tokio_handle = tokio_setup.tokio_handle.clone();
                         let charts_tx = tokio_setup.charts_tx.clone();
                         tokio_handle.spawn(async move {
-                            charts_tx
-                                .send(AsyncTask::Shutdown)
-                                .await
-                                .expect("Unable to send shutdown signal to tokio runtime");
+                            if let Err(err) = charts_tx.send(AsyncTask::Shutdown).await {
+                                error!(
+                                    "Unable to send shutdown signal to tokio runtime: {:?}",
+                                    err
+                                );
+                            }
                         });
                     }
                     return false;
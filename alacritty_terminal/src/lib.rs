@@ -1,4 +1,6 @@
 //! Alacritty - The GPU Enhanced Terminal.
+//!
+//! The charts are embedded through `charts::prelude`, its docs list which items are stable.
 
 #![warn(rust_2018_idioms, future_incompatible)]
 #![deny(clippy::all, clippy::if_not_else, clippy::enum_glob_use)]
@@ -15,8 +17,11 @@ pub mod thread;
 pub mod tty;
 pub mod vi_mode;
 
+#[cfg(feature = "charts")]
 pub mod async_utils;
+#[cfg(feature = "charts")]
 pub mod charts;
+#[cfg(feature = "charts")]
 pub mod decorations;
 
 pub use crate::grid::Grid;
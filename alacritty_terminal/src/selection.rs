@@ -47,6 +47,16 @@ impl SelectionRange {
 }
 
 impl SelectionRange {
+    /// Number of selected cells in a grid with `columns` columns.
+    pub fn cell_count(&self, columns: usize) -> usize {
+        let lines = (self.end.line.0 - self.start.line.0) as usize + 1;
+        if self.is_block {
+            lines * (self.end.column.0.abs_diff(self.start.column.0) + 1)
+        } else {
+            ((lines - 1) * columns + self.end.column.0 + 1).saturating_sub(self.start.column.0)
+        }
+    }
+
     /// Check if a point lies within the selection.
     pub fn contains(&self, point: Point) -> bool {
         self.start.line <= point.line
@@ -665,4 +675,16 @@ mod tests {
         assert!(!selection.intersects_range(..=Line(2)));
         assert!(!selection.intersects_range(Line(7)..=Line(8)));
     }
+
+    #[test]
+    fn range_cell_count() {
+        let start = Point::new(Line(2), Column(3));
+        let end = Point::new(Line(4), Column(1));
+        assert_eq!(SelectionRange::new(start, start, false).cell_count(10), 1);
+        // Rest of the first line, one full line and the start of the last one.
+        assert_eq!(SelectionRange::new(start, end, false).cell_count(10), 7 + 10 + 2);
+
+        let end = Point::new(Line(4), Column(5));
+        assert_eq!(SelectionRange::new(start, end, true).cell_count(10), 3 * 3);
+    }
 }
@@ -2,7 +2,6 @@
 
 use std::ops::{Index, IndexMut, Range};
 use std::sync::Arc;
-use std::time::UNIX_EPOCH;
 use std::{cmp, mem, ptr, slice, str};
 
 use serde::{Deserialize, Serialize};
@@ -10,10 +9,12 @@ use serde::{Deserialize, Serialize};
 use base64::engine::general_purpose::STANDARD as Base64;
 use base64::Engine;
 use bitflags::bitflags;
-use log::{debug, error, trace};
+#[cfg(feature = "charts")]
+use log::error;
+use log::{debug, trace};
 use unicode_width::UnicodeWidthChar;
 
-use crate::event::{Event, EventListener};
+use crate::event::{Event, EventListener, WindowSize};
 use crate::grid::{Dimensions, Grid, GridIterator, Scroll};
 use crate::index::{self, Boundary, Column, Direction, Line, Point, Side};
 use crate::selection::{Selection, SelectionRange, SelectionType};
@@ -26,7 +27,9 @@ use crate::vte::ansi::{
     StandardCharset,
 };
 
-use crate::charts::ChartsConfig;
+#[cfg(feature = "charts")]
+use crate::charts::{ChartIndex, ChartsConfig};
+#[cfg(feature = "charts")]
 use tokio::sync::mpsc as tokio_mpsc;
 
 pub mod cell;
@@ -124,6 +127,7 @@ impl Default for TermMode {
 
 /// `TermChartsHandle` allows connecting to the tokio background thread
 /// that is constantly fetching information and calculating OpenGL vecs.
+#[cfg(feature = "charts")]
 #[derive(Clone)]
 pub struct TermChartsHandle {
     /// A handle to the tokio current thread runtime
@@ -135,8 +139,31 @@ pub struct TermChartsHandle {
     /// Wether or not the charts are enabled
     // TODO: Rename to charts_enabled.
     pub enabled: bool,
+
+    /// Whether the display is able to draw the charts, when false they cannot be enabled.
+    pub rendering_available: bool,
+
+    /// Whether the coordinator is still running, it turns disabled after it panics.
+    pub status: tokio::sync::watch::Receiver<crate::async_utils::ChartsStatus>,
+
+    /// Whether the debug overlay describing the charts is drawn over the terminal.
+    pub debug_overlay: bool,
+
+    /// The time windows in seconds the charts cycle through.
+    pub window_presets: Vec<u64>,
+
+    /// The index of the current window in the window_presets, None until the first cycle.
+    pub window_preset: Option<usize>,
+
+    /// The seconds the terminal must be unfocused before the charts are summarized when it is
+    /// focused again, disabled when None.
+    pub absence_summary_s: Option<u64>,
+
+    /// The epoch the terminal lost the focus at, None while it is focused.
+    pub focus_lost_epoch: Option<u64>,
 }
 
+#[cfg(feature = "charts")]
 impl TermChartsHandle {
     pub fn new_with_thread<T>(
         charts_config: &ChartsConfig,
@@ -151,6 +178,9 @@ impl TermChartsHandle {
         let (charts_tx, charts_rx) = tokio::sync::mpsc::channel(4_096usize);
         // Create a channel to receive a handle from Tokio
         let (handle_tx, handle_rx) = std::sync::mpsc::channel();
+        // Create a channel the coordinator uses to report that the charts have been disabled.
+        let (status_tx, status) =
+            tokio::sync::watch::channel(crate::async_utils::ChartsStatus::Running);
         // Start the Async I/O runtime, this needs to run in a background thread because in OSX,
         // only the main thread can write to the graphics card.
         let tokio_thread = crate::async_utils::spawn_async_tasks(
@@ -160,11 +190,72 @@ impl TermChartsHandle {
             handle_tx,
             size_info,
             event_proxy,
+            status_tx,
         );
         let tokio_handle =
             handle_rx.recv().expect("Unable to get the tokio handle in a background thread");
+        let window_presets = charts_config
+            .window_presets
+            .clone()
+            .unwrap_or_else(|| crate::charts::DEFAULT_WINDOW_PRESETS.to_vec())
+            .into_iter()
+            .filter(|seconds| *seconds > 0)
+            .collect();
+
+        (
+            Self {
+                tokio_handle,
+                charts_tx,
+                enabled: true,
+                rendering_available: true,
+                status,
+                debug_overlay: false,
+                window_presets,
+                window_preset: None,
+                absence_summary_s: charts_config.absence_summary_s,
+                focus_lost_epoch: None,
+            },
+            tokio_thread,
+        )
+    }
+
+    /// `next_window_preset` advances to the next of the window_presets and returns its seconds,
+    /// None when there are no presets.
+    pub fn next_window_preset(&mut self) -> Option<u64> {
+        if self.window_presets.is_empty() {
+            return None;
+        }
+        let next = self.window_preset.map_or(0, |idx| (idx + 1) % self.window_presets.len());
+        self.window_preset = Some(next);
+        Some(self.window_presets[next])
+    }
+
+    /// `focus_changed` remembers the epoch the terminal lost the focus at, when it is focused
+    /// again after at least absence_summary_s it returns that epoch to summarize the charts since.
+    pub fn focus_changed(&mut self, is_focused: bool, now: u64) -> Option<u64> {
+        if !is_focused {
+            self.focus_lost_epoch = Some(now);
+            return None;
+        }
+        let absence_s = self.absence_summary_s?;
+        let lost_epoch = self.focus_lost_epoch.take()?;
+        (now.saturating_sub(lost_epoch) >= absence_s).then_some(lost_epoch)
+    }
 
-        (Self { tokio_handle, charts_tx, enabled: true }, tokio_thread)
+    /// `coordinator_running` returns false once the coordinator has disabled the charts after a
+    /// panic, the terminal keeps working without them.
+    pub fn coordinator_running(&self) -> bool {
+        *self.status.borrow() != crate::async_utils::ChartsStatus::Disabled
+    }
+
+    /// `disable_rendering` is used when the display cannot draw the charts, they are hidden, the
+    /// rows reserved for them are released and the coordinator stops generating vertices.
+    pub fn disable_rendering(&mut self) {
+        self.enabled = false;
+        self.rendering_available = false;
+        if let Err(err) = self.charts_tx.try_send(crate::async_utils::AsyncTask::DisableRendering) {
+            error!("disable_rendering: Unable to notify the coordinator: {:?}", err);
+        }
     }
 }
 
@@ -194,9 +285,108 @@ pub struct SizeInfo {
 
     /// Number of columns in the viewport.
     pub columns: usize,
+
+    /// Device pixel ratio of the window, an unset ratio is treated as 1.0.
+    pub dpr: f64,
 }
 
 impl SizeInfo {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        width: f32,
+        height: f32,
+        cell_width: f32,
+        cell_height: f32,
+        mut padding_x: f32,
+        mut padding_y: f32,
+        dynamic_padding: bool,
+    ) -> SizeInfo {
+        if dynamic_padding {
+            padding_x = Self::dynamic_padding(padding_x.floor(), width, cell_width);
+            padding_y = Self::dynamic_padding(padding_y.floor(), height, cell_height);
+        }
+
+        let lines = (height - 2. * padding_y) / cell_height;
+        let screen_lines = cmp::max(lines as usize, MIN_SCREEN_LINES);
+
+        let columns = (width - 2. * padding_x) / cell_width;
+        let columns = cmp::max(columns as usize, MIN_COLUMNS);
+
+        SizeInfo {
+            width,
+            height,
+            cell_width,
+            cell_height,
+            padding_x: padding_x.floor(),
+            padding_y: padding_y.floor(),
+            screen_lines,
+            columns,
+            dpr: 1.,
+        }
+    }
+
+    #[inline]
+    pub fn width(&self) -> f32 {
+        self.width
+    }
+
+    #[inline]
+    pub fn height(&self) -> f32 {
+        self.height
+    }
+
+    #[inline]
+    pub fn cell_width(&self) -> f32 {
+        self.cell_width
+    }
+
+    #[inline]
+    pub fn cell_height(&self) -> f32 {
+        self.cell_height
+    }
+
+    #[inline]
+    pub fn padding_x(&self) -> f32 {
+        self.padding_x
+    }
+
+    #[inline]
+    pub fn padding_y(&self) -> f32 {
+        self.padding_y
+    }
+
+    #[inline]
+    pub fn reserve_lines(&mut self, count: usize) {
+        self.screen_lines = cmp::max(self.screen_lines.saturating_sub(count), MIN_SCREEN_LINES);
+    }
+
+    /// Check if coordinates are inside the terminal grid.
+    ///
+    /// The padding, message bar or search are not counted as part of the grid.
+    #[inline]
+    pub fn contains_point(&self, x: usize, y: usize) -> bool {
+        x <= (self.padding_x + self.columns as f32 * self.cell_width) as usize
+            && x > self.padding_x as usize
+            && y <= (self.padding_y + self.screen_lines as f32 * self.cell_height) as usize
+            && y > self.padding_y as usize
+    }
+
+    /// Calculate padding to spread it evenly around the terminal content.
+    #[inline]
+    fn dynamic_padding(padding: f32, dimension: f32, cell_dimension: f32) -> f32 {
+        padding + ((dimension - 2. * padding) % cell_dimension) / 2.
+    }
+
+    /// `scale_factor` returns the device pixel ratio used to turn logical pixels into physical
+    /// pixels.
+    pub fn scale_factor(&self) -> f32 {
+        if self.dpr > 0. {
+            self.dpr as f32
+        } else {
+            1.
+        }
+    }
+
     /// `scale_x` Scales the value from the current display boundary to
     /// a cartesian plane from [-1.0, 1.0], where -1.0 is 0px (left-most) and
     /// 1.0 is the `display_width` parameter (right-most), i.e. 1024px.
@@ -207,12 +397,41 @@ impl SizeInfo {
     }
 
     /// `scale_y` Scales the value from the current display boundary to
-    /// a cartesian plane from [-1.0, 1.0], where 1.0 is 0px (top) and -1.0 is
-    /// the `display_height` parameter (bottom), i.e. 768px.
+    /// a cartesian plane from [-1.0, 1.0], where -1.0 is 0px (bottom-most) and
+    /// 1.0 is the `display_height` parameter (top-most), i.e. 768px.
+    /// The input value starts at the bottom of the padded area, like `scale_x`.
     pub fn scale_y(&self, input_value: f32) -> f32 {
         let center_y = self.height / 2.;
-        let y = self.height - input_value;
-        -(y - center_y) / center_y
+        let y = self.padding_y + input_value;
+        (y - center_y) / center_y
+    }
+}
+
+impl Dimensions for SizeInfo {
+    #[inline]
+    fn columns(&self) -> usize {
+        self.columns
+    }
+
+    #[inline]
+    fn screen_lines(&self) -> usize {
+        self.screen_lines
+    }
+
+    #[inline]
+    fn total_lines(&self) -> usize {
+        self.screen_lines()
+    }
+}
+
+impl From<SizeInfo> for WindowSize {
+    fn from(size_info: SizeInfo) -> Self {
+        Self {
+            num_cols: size_info.columns as u16,
+            num_lines: size_info.screen_lines as u16,
+            cell_width: size_info.cell_width as u16,
+            cell_height: size_info.cell_height as u16,
+        }
     }
 }
 
@@ -407,6 +626,7 @@ pub struct Term<T> {
     event_proxy: T,
 
     /// The handle to the background utilities (Should this be Option?)
+    #[cfg(feature = "charts")]
     pub tokio_setup: Option<TermChartsHandle>,
 
     /// Current title of the window.
@@ -419,6 +639,9 @@ pub struct Term<T> {
     /// Terminal decorations enabled
     pub decorations_enabled: bool,
 
+    /// Viewport lines covered by the charts.
+    charts_covered_lines: Vec<Line>,
+
     /// The stack for the keyboard modes.
     keyboard_mode_stack: Vec<KeyboardModes>,
 
@@ -545,10 +768,13 @@ impl<T> Term<T> {
             title: Default::default(),
             mode: Default::default(),
             decorations_enabled: true,
+            #[cfg(feature = "charts")]
             tokio_setup: None,
+            charts_covered_lines: Vec::new(),
         }
     }
 
+    #[cfg(feature = "charts")]
     pub fn set_tokio_setup(&mut self, tokio_setup: TermChartsHandle) {
         self.tokio_setup = Some(tokio_setup);
     }
@@ -813,22 +1039,23 @@ impl<T> Term<T> {
         self.damage.resize(num_cols, num_lines);
     }
 
+    #[cfg(feature = "charts")]
     pub fn increment_counter(&mut self, counter_type: &'static str, increment: f64) {
-        let now = std::time::SystemTime::now().duration_since(UNIX_EPOCH).unwrap().as_secs();
         if let Some(tokio_setup) = &self.tokio_setup {
             let charts_tx = tokio_setup.charts_tx.clone();
             tokio_setup.tokio_handle.spawn(async move {
-                let send_increment_input_counter = charts_tx.send(if counter_type == "input" {
-                    crate::async_utils::AsyncTask::IncrementInputCounter(now, increment)
-                } else {
-                    crate::async_utils::AsyncTask::IncrementOutputCounter(now, increment)
-                });
-                match send_increment_input_counter.await {
-                    Err(err) => error!("Sending IncrementInputCounter Task: err={:?}", err),
-                    Ok(_) => debug!(
-                        "Sent IncrementInputCounter Task for instant {} with value: {}",
-                        now, increment
-                    ),
+                use crate::async_utils::AsyncTask;
+                let task = match counter_type {
+                    "input" => AsyncTask::IncrementInputCounter(increment),
+                    "scroll" => AsyncTask::IncrementScrollCounter(increment),
+                    "selection" => AsyncTask::IncrementSelectionCounter(increment),
+                    _ => AsyncTask::IncrementOutputCounter(increment),
+                };
+                match charts_tx.send(task).await {
+                    Err(err) => error!("Sending {} counter Task: err={:?}", counter_type, err),
+                    Ok(_) => {
+                        debug!("Sent {} counter Task with value: {}", counter_type, increment)
+                    },
                 }
             });
         }
@@ -845,13 +1072,106 @@ impl<T> Term<T> {
         self.increment_counter("input", increment);
     }
 
+    #[inline]
+    pub fn increment_chart_scroll_counter(&mut self, increment: f64) {
+        self.increment_counter("scroll", increment);
+    }
+
+    #[inline]
+    pub fn increment_chart_selection_counter(&mut self, increment: f64) {
+        self.increment_counter("selection", increment);
+    }
+
+    #[cfg(feature = "charts")]
     #[inline]
     pub fn toggle_chart_show(&mut self) {
         if let Some(ref mut tokio_setup) = self.tokio_setup {
-            tokio_setup.enabled = !tokio_setup.enabled;
+            tokio_setup.enabled = !tokio_setup.enabled && tokio_setup.rendering_available;
+            let task = crate::async_utils::AsyncTask::SetChartsVisible(tokio_setup.enabled);
+            if let Err(err) = tokio_setup.charts_tx.try_send(task) {
+                error!("toggle_chart_show: Unable to notify the coordinator: {:?}", err);
+            }
+        }
+    }
+
+    /// `toggle_decorations` enables or disables the decorations of the WithDecorToggle group, the
+    /// coordinator is told so that the chart decorations follow.
+    #[cfg(feature = "charts")]
+    #[inline]
+    pub fn toggle_decorations(&mut self) {
+        self.decorations_enabled = !self.decorations_enabled;
+        if let Some(ref tokio_setup) = self.tokio_setup {
+            let task =
+                crate::async_utils::AsyncTask::SetDecorationsEnabled(self.decorations_enabled);
+            if let Err(err) = tokio_setup.charts_tx.try_send(task) {
+                error!("toggle_decorations: Unable to notify the coordinator: {:?}", err);
+            }
+        }
+    }
+
+    #[cfg(feature = "charts")]
+    #[inline]
+    pub fn toggle_chart_debug_overlay(&mut self) {
+        if let Some(ref mut tokio_setup) = self.tokio_setup {
+            tokio_setup.debug_overlay = !tokio_setup.debug_overlay;
         }
     }
 
+    /// `cycle_chart_window` changes the time window of a chart, or of all the charts when
+    /// `chart_index` is None, to the next of the window presets, the samples within the new window
+    /// are kept. The charts share the position in the presets. NotFound is returned when the chart
+    /// does not exist.
+    #[cfg(feature = "charts")]
+    pub fn cycle_chart_window(
+        &mut self,
+        chart_index: Option<ChartIndex>,
+    ) -> crate::async_utils::ChartTaskResult<()> {
+        if let Some(ref mut tokio_setup) = self.tokio_setup {
+            let previous_preset = tokio_setup.window_preset;
+            let seconds = match tokio_setup.next_window_preset() {
+                Some(seconds) => seconds,
+                None => return Ok(()),
+            };
+            let changed = crate::async_utils::set_charts_window(
+                tokio_setup.charts_tx.clone(),
+                chart_index,
+                seconds,
+                tokio_setup.tokio_handle.clone(),
+            )
+            .map_err(|err| {
+                // A missing chart does not move the other charts along the presets.
+                tokio_setup.window_preset = previous_preset;
+                err
+            })?;
+            debug!("cycle_chart_window: {}s window, changed: {}", seconds, changed);
+        }
+        Ok(())
+    }
+
+    /// `charts_focus_changed` returns the summaries of the charts since the terminal lost the
+    /// focus, when it was unfocused for longer than the configured absence.
+    #[cfg(feature = "charts")]
+    pub fn charts_focus_changed(&mut self, is_focused: bool) -> Vec<String> {
+        let tokio_setup = match self.tokio_setup {
+            Some(ref mut tokio_setup) if tokio_setup.coordinator_running() => tokio_setup,
+            _ => return vec![],
+        };
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .map_or(0, |elapsed| elapsed.as_secs());
+        let epoch = match tokio_setup.focus_changed(is_focused, now) {
+            Some(epoch) => epoch,
+            None => return vec![],
+        };
+        let summaries = crate::async_utils::summarize_charts_since(
+            tokio_setup.charts_tx.clone(),
+            epoch,
+            tokio_setup.tokio_handle.clone(),
+        );
+        debug!("charts_focus_changed: {} summaries since {}", summaries.len(), epoch);
+        summaries.iter().map(ToString::to_string).collect()
+    }
+
     /// Active terminal modes.
     #[inline]
     pub fn mode(&self) -> &TermMode {
@@ -950,10 +1270,54 @@ impl<T> Term<T> {
         self.mark_fully_damaged();
     }
 
+    /// Set the viewport lines covered by the charts.
+    pub fn set_charts_covered_lines(&mut self, lines: Vec<Line>) {
+        self.charts_covered_lines = lines;
+    }
+
+    /// Viewport lines covered by the charts.
+    #[inline]
+    pub fn charts_covered_lines(&self) -> &[Line] {
+        &self.charts_covered_lines
+    }
+
+    /// Check if a point is inside the lines covered by the charts.
+    pub fn is_point_covered_by_charts(&self, point: Point) -> bool {
+        let viewport_line = point.line + self.grid.display_offset();
+        self.charts_covered_lines.contains(&viewport_line)
+    }
+
+    /// Clamp a point to the end of the last line that is not covered by the charts.
+    pub fn clamp_to_charts_uncovered(&self, mut point: Point) -> Point {
+        let first_covered = match self.charts_covered_lines.iter().min() {
+            Some(line) => *line,
+            None => return point,
+        };
+        let display_offset = self.grid.display_offset();
+        if point.line + display_offset >= first_covered {
+            point.line = cmp::max(first_covered - display_offset - 1, self.topmost_line());
+            point.column = self.last_column();
+        }
+        point
+    }
+
+    #[cfg(feature = "charts")]
     #[inline]
     pub fn charts_enabled(&self) -> bool {
         if let Some(tokio_setup) = &self.tokio_setup {
-            tokio_setup.enabled
+            tokio_setup.enabled && tokio_setup.coordinator_running()
+        } else {
+            false
+        }
+    }
+
+    /// `chart_debug_overlay_enabled` returns true when the debug overlay is toggled on, it is
+    /// drawn also while the charts are hidden.
+    #[cfg(feature = "charts")]
+    #[inline]
+    pub fn chart_debug_overlay_enabled(&self) -> bool {
+        if let Some(tokio_setup) = &self.tokio_setup {
+            tokio_setup.debug_overlay && tokio_setup.coordinator_running()
         } else {
             false
         }
@@ -1196,6 +1560,40 @@ impl<T> Term<T> {
     }
 }
 
+/// Without the charts feature there is no background thread, the chart counters and toggles are
+/// kept so that the terminal and its key bindings do not need to know about it.
+#[cfg(not(feature = "charts"))]
+impl<T> Term<T> {
+    #[inline]
+    pub fn increment_counter(&mut self, _counter_type: &'static str, _increment: f64) {}
+
+    #[inline]
+    pub fn toggle_chart_show(&mut self) {}
+
+    #[inline]
+    pub fn toggle_decorations(&mut self) {
+        self.decorations_enabled = !self.decorations_enabled;
+    }
+
+    #[inline]
+    pub fn toggle_chart_debug_overlay(&mut self) {}
+
+    #[inline]
+    pub fn charts_focus_changed(&mut self, _is_focused: bool) -> Vec<String> {
+        vec![]
+    }
+
+    #[inline]
+    pub fn charts_enabled(&self) -> bool {
+        false
+    }
+
+    #[inline]
+    pub fn chart_debug_overlay_enabled(&self) -> bool {
+        false
+    }
+}
+
 impl<T> Dimensions for Term<T> {
     #[inline]
     fn columns(&self) -> usize {
@@ -2652,6 +3050,35 @@ mod tests {
     use crate::term::test::TermSize;
     use crate::vte::ansi::{self, CharsetIndex, Handler, StandardCharset};
 
+    #[test]
+    fn clamp_selection_to_charts_uncovered_lines() {
+        let size = TermSize::new(5, 10);
+        let mut term = Term::new(Config::default(), &size, VoidListener);
+        let point = Point::new(Line(8), Column(2));
+
+        // Nothing is covered, the point is unchanged.
+        assert_eq!(term.clamp_to_charts_uncovered(point), point);
+
+        // A selection drag ending inside the charts clamps to the last uncovered line.
+        term.set_charts_covered_lines(vec![Line(7), Line(8), Line(9)]);
+        assert!(term.is_point_covered_by_charts(point));
+        assert_eq!(term.clamp_to_charts_uncovered(point), Point::new(Line(6), Column(4)));
+        let uncovered = Point::new(Line(3), Column(2));
+        assert!(!term.is_point_covered_by_charts(uncovered));
+        assert_eq!(term.clamp_to_charts_uncovered(uncovered), uncovered);
+
+        // The covered lines are relative to the viewport.
+        for _ in 0..20 {
+            term.newline();
+        }
+        term.scroll_display(Scroll::Delta(2));
+        assert!(term.is_point_covered_by_charts(Point::new(Line(5), Column(0))));
+        assert_eq!(
+            term.clamp_to_charts_uncovered(Point::new(Line(5), Column(0))),
+            Point::new(Line(4), Column(4))
+        );
+    }
+
     #[test]
     fn scroll_display_page_up() {
         let size = TermSize::new(5, 10);
@@ -0,0 +1,24 @@
+#!/usr/bin/env bash
+
+# Check that without the `charts` feature neither the async runtime nor the HTTP client of the
+# charts end up in the dependency tree.
+
+set -eu
+
+forbidden="tokio hyper reqwest"
+
+check() {
+    local tree
+    tree=$(cargo tree --target all -e normal --prefix none "$@")
+    for crate in $forbidden; do
+        if grep -q "^$crate v" <<< "$tree"; then
+            echo "$crate is a dependency of \`cargo tree $*\`" >&2
+            status=1
+        fi
+    done
+}
+
+status=0
+check -p alacritty_terminal --no-default-features --features serde
+check -p chartacritty --no-default-features --features wayland,x11
+exit $status